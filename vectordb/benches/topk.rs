@@ -0,0 +1,177 @@
+// benches/topk.rs
+//
+// Top-k selection: bounded heap vs sorting every score.
+//
+//   cargo bench --bench topk
+//
+// `select/*` isolates the selection step on pre-scored candidates;
+// `brute_force/*` is a whole exact search over a synthetic collection,
+// where the heap also skips copying the IDs of candidates that lose to
+// the threshold. The sort variant is what `search::rank` did before
+// `TopK`: collect every result, sort, truncate. `parallel` splits the same
+// scan across threads that share one early-termination threshold.
+// `batch/*` scores 64 queries at once, one at a time vs as a blocked
+// matrix product (engine/gemm.rs).
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use vectordb::engine::gemm;
+use vectordb::engine::search::{self, TopK};
+use vectordb::models::{DistanceMetric, SearchResult};
+
+const DIMENSION: usize = 64;
+
+/// xorshift64, so runs are repeatable without a rand dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+fn sort_truncate(
+    mut results: Vec<SearchResult>,
+    metric: DistanceMetric,
+    k: usize,
+) -> Vec<SearchResult> {
+    results.sort_by(|a, b| search::compare_scores(metric, a.score, b.score));
+    results.truncate(k);
+    results
+}
+
+fn scored(n: usize, rng: &mut Rng) -> Vec<SearchResult> {
+    (0..n)
+        .map(|i| SearchResult::new(format!("v{}", i), rng.next_f32()))
+        .collect()
+}
+
+fn bench_select(c: &mut Criterion) {
+    let mut group = c.benchmark_group("select");
+    let mut rng = Rng::new(7);
+    for n in [10_000, 100_000, 1_000_000] {
+        let results = scored(n, &mut rng);
+        for k in [10, 100] {
+            let id = format!("n={}/k={}", n, k);
+            group.bench_with_input(BenchmarkId::new("sort", &id), &results, |b, r| {
+                b.iter_batched(
+                    || r.clone(),
+                    |r| sort_truncate(r, DistanceMetric::Cosine, k),
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_with_input(BenchmarkId::new("heap", &id), &results, |b, r| {
+                b.iter_batched(
+                    || r.clone(),
+                    |r| search::rank(r, DistanceMetric::Cosine, k),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_brute_force(c: &mut Criterion) {
+    let mut group = c.benchmark_group("brute_force");
+    group.sample_size(20);
+    let mut rng = Rng::new(11);
+    let n = 100_000;
+    let ids: Vec<String> = (0..n).map(|i| format!("v{}", i)).collect();
+    let data: Vec<Vec<f32>> = (0..n)
+        .map(|_| (0..DIMENSION).map(|_| rng.next_f32()).collect())
+        .collect();
+    let query: Vec<f32> = (0..DIMENSION).map(|_| rng.next_f32()).collect();
+    let candidates = || {
+        ids.iter()
+            .map(String::as_str)
+            .zip(data.iter().map(Vec::as_slice))
+    };
+    let metric = DistanceMetric::Euclidean;
+
+    for k in [10, 100] {
+        group.bench_function(BenchmarkId::new("sort", k), |b| {
+            b.iter(|| {
+                let all = candidates()
+                    .map(|(id, v)| SearchResult::new(id.to_string(), metric.calculate(&query, v)))
+                    .collect();
+                sort_truncate(all, metric, k)
+            })
+        });
+        group.bench_function(BenchmarkId::new("heap", k), |b| {
+            b.iter(|| search::brute_force(black_box(&query), metric, k, candidates()))
+        });
+        let all: Vec<(&str, &[f32])> = candidates().collect();
+        let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+        group.bench_function(BenchmarkId::new("parallel", k), |b| {
+            b.iter(|| search::parallel_brute_force(black_box(&query), metric, k, &all, workers))
+        });
+    }
+    group.finish();
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.sample_size(10);
+    let mut rng = Rng::new(17);
+    let n = 20_000;
+    let ids: Vec<String> = (0..n).map(|i| format!("v{}", i)).collect();
+    let data: Vec<Vec<f32>> = (0..n)
+        .map(|_| (0..DIMENSION).map(|_| rng.next_f32()).collect())
+        .collect();
+    let candidates: Vec<(&str, &[f32])> = ids
+        .iter()
+        .map(String::as_str)
+        .zip(data.iter().map(Vec::as_slice))
+        .collect();
+    let queries: Vec<Vec<f32>> = (0..64)
+        .map(|_| (0..DIMENSION).map(|_| rng.next_f32()).collect())
+        .collect();
+    let query_refs: Vec<&[f32]> = queries.iter().map(Vec::as_slice).collect();
+
+    for metric in [DistanceMetric::Cosine, DistanceMetric::Euclidean] {
+        let name = format!("{:?}", metric).to_lowercase();
+        group.bench_function(BenchmarkId::new("per_query", &name), |b| {
+            b.iter(|| {
+                query_refs
+                    .iter()
+                    .map(|q| search::brute_force(q, metric, 10, candidates.iter().copied()))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_function(BenchmarkId::new("gemm", &name), |b| {
+            b.iter(|| gemm::batch_brute_force(black_box(&query_refs), metric, 10, &candidates))
+        });
+    }
+    group.finish();
+}
+
+fn bench_threshold(c: &mut Criterion) {
+    // Raw collector throughput with cheap items
+    let mut rng = Rng::new(13);
+    let scores: Vec<f32> = (0..1_000_000).map(|_| rng.next_f32()).collect();
+    c.bench_function("topk_push/n=1000000/k=10", |b| {
+        b.iter(|| {
+            let mut top = TopK::new(10, DistanceMetric::Dot);
+            for (i, &score) in scores.iter().enumerate() {
+                top.push(score, i);
+            }
+            top.into_sorted_vec()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_select,
+    bench_brute_force,
+    bench_batch,
+    bench_threshold
+);
+criterion_main!(benches);
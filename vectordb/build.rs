@@ -0,0 +1,18 @@
+// build.rs
+//
+// Generates the gRPC service from proto/vectordb.proto when the `grpc`
+// feature is on; otherwise there's nothing to build.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        let protoc =
+            protoc_bin_vendored::protoc_bin_path().expect("No bundled protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::configure()
+            .build_client(true)
+            .compile_protos(&["proto/vectordb.proto"], &["proto"])
+            .expect("Failed to compile proto/vectordb.proto");
+    }
+}
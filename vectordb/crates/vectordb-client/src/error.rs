@@ -0,0 +1,123 @@
+// crates/vectordb-client/src/error.rs
+//
+// What a client call can fail with.
+//
+// The server answers every failed request with a status code and an
+// `ErrorResponse` body; the status picks the variant (the same mapping as
+// `ApiError` in the server's main.rs, in reverse), so callers can match on
+// `Error::NotFound` instead of comparing numbers. Failures below HTTP —
+// connection refused, reset, timed out — are `Transport`.
+
+use std::fmt;
+use vectordb_types::FieldError;
+
+/// Result type of every client call
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// 400: the request was invalid (wrong dimension, bad parameter, schema
+    /// violation — `fields` lists each bad metadata field)
+    BadRequest {
+        message: String,
+        fields: Vec<FieldError>,
+    },
+
+    /// 401: missing or unknown API key
+    Unauthorized(String),
+
+    /// 403: the key isn't allowed to do this (e.g. admin-only endpoints,
+    /// deleting a protected collection)
+    Forbidden(String),
+
+    /// 404: no such collection or point
+    NotFound(String),
+
+    /// 409: the collection already exists, or the write conflicts with
+    /// what's stored
+    Conflict(String),
+
+    /// 412: a conditional write's `if` predicate didn't hold
+    PreconditionFailed(String),
+
+    /// 423: the collection is frozen against writes
+    ReadOnly(String),
+
+    /// Any other error status (429, 5xx, ...)
+    Status { status: u16, message: String },
+
+    /// No response: connecting, sending, or reading the body failed
+    Transport(reqwest::Error),
+
+    /// The client couldn't be built from its configuration
+    Config(String),
+
+    /// A successful response whose body isn't what the endpoint returns
+    /// (usually a client/server version mismatch)
+    Decode(String),
+}
+
+impl Error {
+    /// Error for a non-2xx response with the server's `message`
+    pub(crate) fn from_status(status: u16, message: String, fields: Vec<FieldError>) -> Self {
+        match status {
+            400 => Error::BadRequest { message, fields },
+            401 => Error::Unauthorized(message),
+            403 => Error::Forbidden(message),
+            404 => Error::NotFound(message),
+            409 => Error::Conflict(message),
+            412 => Error::PreconditionFailed(message),
+            423 => Error::ReadOnly(message),
+            _ => Error::Status { status, message },
+        }
+    }
+
+    /// HTTP status of the response, if there was one
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::BadRequest { .. } => Some(400),
+            Error::Unauthorized(_) => Some(401),
+            Error::Forbidden(_) => Some(403),
+            Error::NotFound(_) => Some(404),
+            Error::Conflict(_) => Some(409),
+            Error::PreconditionFailed(_) => Some(412),
+            Error::ReadOnly(_) => Some(423),
+            Error::Status { status, .. } => Some(*status),
+            Error::Transport(e) => e.status().map(|s| s.as_u16()),
+            Error::Config(_) | Error::Decode(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BadRequest { message, .. }
+            | Error::Unauthorized(message)
+            | Error::Forbidden(message)
+            | Error::NotFound(message)
+            | Error::Conflict(message)
+            | Error::PreconditionFailed(message)
+            | Error::ReadOnly(message) => write!(f, "{}", message),
+            Error::Status { status, message } => write!(f, "HTTP {}: {}", status, message),
+            Error::Transport(e) => write!(f, "Request failed: {}", e),
+            Error::Config(msg) => write!(f, "Invalid client configuration: {}", msg),
+            Error::Decode(msg) => write!(f, "Unexpected response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Transport(err)
+    }
+}
@@ -0,0 +1,330 @@
+// crates/vectordb-client/src/lib.rs
+//
+// Async Rust client for the VectorDB HTTP API.
+//
+//   let client = Client::new("http://localhost:3000")?;
+//   client.create_collection(&CreateCollectionRequest::new("docs", 3)).await?;
+//   client.insert("docs", vec![PointInput::new("a", vec![0.1, 0.2, 0.3])]).await?;
+//   let hits = client.search("docs", &SearchRequest::new(vec![0.1, 0.2, 0.3], 5)).await?;
+//
+// Requests and responses are the server's own types (crates/vectordb-types,
+// re-exported here), so a field added to the API shows up in both at once.
+// Failed calls come back as a typed `Error` keyed on the response status,
+// and transient failures are retried with exponential backoff according to
+// the client's `RetryPolicy` (see retry.rs for what counts as transient).
+
+pub mod error;
+pub mod retry;
+
+pub use error::{Error, Result};
+pub use retry::RetryPolicy;
+pub use vectordb_types::*;
+
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::Method;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+use std::time::Duration;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONFIGURATION
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Settings for a `Client`
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Sent as `X-API-Key` on every request
+    pub api_key: Option<String>,
+    /// Limit on each attempt, connecting through reading the body
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            timeout: Duration::from_secs(30),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CLIENT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A connection pool to one server. Cheap to clone; clones share the pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    /// Client for the server at `base_url` (e.g. "http://localhost:3000")
+    /// with the default configuration
+    pub fn new(base_url: impl Into<String>) -> Result<Self> {
+        Self::with_config(base_url, ClientConfig::default())
+    }
+
+    pub fn with_config(base_url: impl Into<String>, config: ClientConfig) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = &config.api_key {
+            let value = key
+                .parse()
+                .map_err(|_| Error::Config("API key isn't a valid header value".into()))?;
+            headers.insert("x-api-key", value);
+        }
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            retry: config.retry,
+        })
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // Collections
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// Create a collection. Not retried after a possibly-delivered attempt:
+    /// a repeat would fail with `Conflict` even though the first succeeded.
+    pub async fn create_collection(&self, req: &CreateCollectionRequest) -> Result<CollectionInfo> {
+        self.send(Method::POST, "/api/collections", Some(req), false)
+            .await
+    }
+
+    pub async fn get_collection(&self, name: &str) -> Result<CollectionInfo> {
+        let path = format!("/api/collections/{}", name);
+        self.send(Method::GET, &path, None::<&()>, true).await
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<CollectionInfo>> {
+        self.send(Method::GET, "/api/collections", None::<&()>, true)
+            .await
+    }
+
+    /// Delete a collection (moved to the trash, if the server keeps one)
+    pub async fn delete_collection(&self, name: &str) -> Result<()> {
+        let path = format!("/api/collections/{}", name);
+        let IgnoredAny = self.send(Method::DELETE, &path, None::<&()>, true).await?;
+        Ok(())
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // Points
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// Insert or overwrite points. Points that fail individually are
+    /// reported in the response (`failed`, and each `PointResult`), not as
+    /// an error.
+    pub async fn insert(
+        &self,
+        collection: &str,
+        points: Vec<PointInput>,
+    ) -> Result<UpsertResponse> {
+        self.upsert(
+            collection,
+            &UpsertRequest {
+                points,
+                on_conflict: OnConflict::default(),
+                atomic: false,
+            },
+        )
+        .await
+    }
+
+    /// Write points with an explicit conflict policy and atomicity. An
+    /// atomic batch with a failing point comes back as `BadRequest`.
+    pub async fn upsert(&self, collection: &str, req: &UpsertRequest) -> Result<UpsertResponse> {
+        let path = format!("/api/collections/{}/points", collection);
+        // Writing the same points twice only differs under on_conflict=error
+        let idempotent = req.on_conflict != OnConflict::Error;
+        self.send(Method::POST, &path, Some(req), idempotent).await
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // Search
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// Nearest neighbours of `req.vector` in a collection (or alias)
+    pub async fn search(&self, collection: &str, req: &SearchRequest) -> Result<Vec<SearchResult>> {
+        let path = format!("/api/collections/{}/search", collection);
+        self.send(Method::POST, &path, Some(req), true).await
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // Transport
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// Send a request, retrying per the policy, and decode the response
+    async fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+        idempotent: bool,
+    ) -> Result<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let body = body
+            .map(serde_json::to_vec)
+            .transpose()
+            .map_err(|e| Error::Decode(e.to_string()))?;
+
+        let mut retry = 0;
+        loop {
+            let (err, retry_after) = match self.attempt(&method, &url, body.as_deref()).await {
+                Ok(bytes) => {
+                    return serde_json::from_slice(&bytes)
+                        .map_err(|e| Error::Decode(format!("{} {}: {}", method, path, e)));
+                }
+                Err(failure) => failure,
+            };
+            if retry >= self.retry.max_retries || !retry::should_retry(&err, idempotent) {
+                return Err(err);
+            }
+            tokio::time::sleep(self.retry.backoff(retry, retry_after)).await;
+            retry += 1;
+        }
+    }
+
+    /// One attempt: the body of a 2xx response, or the error and any
+    /// Retry-After the server sent with it
+    async fn attempt(
+        &self,
+        method: &Method,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> std::result::Result<Vec<u8>, (Error, Option<Duration>)> {
+        let mut request = self.http.request(method.clone(), url);
+        if let Some(body) = body {
+            request = request
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_vec());
+        }
+        let response = request.send().await.map_err(|e| (e.into(), None))?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_secs);
+        let bytes = response.bytes().await.map_err(|e| (e.into(), None))?;
+        if status.is_success() {
+            return Ok(bytes.to_vec());
+        }
+
+        // Not every error comes from our handlers (proxies, axum's own
+        // rejections), so fall back to the raw body
+        let (message, fields) = match serde_json::from_slice::<ErrorResponse>(&bytes) {
+            Ok(body) => (body.message, body.fields),
+            Err(_) => (
+                String::from_utf8_lossy(&bytes).trim().to_string(),
+                Vec::new(),
+            ),
+        };
+        Err((
+            Error::from_status(status.as_u16(), message, fields),
+            retry_after,
+        ))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A server that answers the first `failures` requests with `status`
+    /// and the rest with an empty search result, one connection each;
+    /// returns its URL and a count of requests seen
+    async fn flaky_server(failures: usize, status: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let _ = socket.read(&mut buf).await;
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = if n < failures {
+                    (status, r#"{"error":true,"message":"busy"}"#)
+                } else {
+                    ("200 OK", "[]")
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\nretry-after: 0\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, seen)
+    }
+
+    fn config(max_retries: u32) -> ClientConfig {
+        ClientConfig {
+            retry: RetryPolicy {
+                max_retries,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(5),
+            },
+            ..ClientConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        let (url, seen) = flaky_server(2, "503 Service Unavailable").await;
+        let client = Client::with_config(url, config(3)).unwrap();
+        let hits = client
+            .search("docs", &SearchRequest::new(vec![1.0], 1))
+            .await
+            .unwrap();
+        assert!(hits.is_empty());
+        assert_eq!(seen.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_and_reports_the_status() {
+        let (url, seen) = flaky_server(10, "503 Service Unavailable").await;
+        let client = Client::with_config(url, config(2)).unwrap();
+        let err = client.list_collections().await.unwrap_err();
+        assert!(
+            matches!(&err, Error::Status { status: 503, message } if message == "busy"),
+            "{:?}",
+            err
+        );
+        assert_eq!(seen.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_non_idempotent_calls_after_gateway_errors() {
+        let (url, seen) = flaky_server(1, "504 Gateway Timeout").await;
+        let client = Client::with_config(url, config(3)).unwrap();
+        let err = client
+            .create_collection(&CreateCollectionRequest::new("docs", 3))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(504));
+        assert_eq!(seen.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,116 @@
+// crates/vectordb-client/src/retry.rs
+//
+// When a failed call is tried again, and how long to wait first.
+//
+// Whether a retry is safe depends on whether the server may have acted on
+// the first attempt:
+//
+//   connection refused, 429, 503   never reached (or was turned away by)
+//                                  the handler: always retried
+//   timeouts, resets, 502, 504     may have been applied: retried only for
+//                                  idempotent calls (searches, reads,
+//                                  overwriting upserts, deletes)
+//   anything else                  not retried; the same request would fail
+//                                  the same way
+//
+// Waits double from `initial_backoff` up to `max_backoff`. A Retry-After
+// header (seconds) on a 429/503 replaces the computed wait, still capped.
+
+use crate::error::Error;
+use std::time::Duration;
+
+/// How many times to retry a failed call, and how long to wait in between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 = never retry)
+    pub max_retries: u32,
+    /// Wait before the first retry
+    pub initial_backoff: Duration,
+    /// Longest wait between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before retry number `retry` (0-based), unless the server said
+    /// otherwise
+    pub fn backoff(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let computed = self
+            .initial_backoff
+            .checked_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .unwrap_or(self.max_backoff);
+        retry_after.unwrap_or(computed).min(self.max_backoff)
+    }
+}
+
+/// Is `err` worth another attempt of a call that is (or isn't) idempotent?
+pub(crate) fn should_retry(err: &Error, idempotent: bool) -> bool {
+    match err {
+        Error::Status { status, .. } => match status {
+            429 | 503 => true,
+            502 | 504 => idempotent,
+            _ => false,
+        },
+        Error::Transport(e) if e.is_connect() => true,
+        Error::Transport(e) => idempotent && (e.is_timeout() || e.is_request()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        let waits: Vec<u128> = (0..6)
+            .map(|i| policy.backoff(i, None).as_millis())
+            .collect();
+        assert_eq!(waits, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(40, None), Duration::from_secs(1));
+
+        // Retry-After wins, but is still capped
+        let after = Some(Duration::from_millis(300));
+        assert_eq!(policy.backoff(0, after), Duration::from_millis(300));
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_secs(60))),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_which_errors_are_retried() {
+        let status = |status| Error::Status {
+            status,
+            message: String::new(),
+        };
+        assert!(should_retry(&status(503), false));
+        assert!(should_retry(&status(429), false));
+        assert!(should_retry(&status(504), true));
+        assert!(!should_retry(&status(504), false));
+        assert!(!should_retry(&status(500), true));
+        assert!(!should_retry(&Error::NotFound("x".into()), true));
+    }
+}
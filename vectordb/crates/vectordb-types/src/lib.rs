@@ -0,0 +1,1075 @@
+// crates/vectordb-types/src/lib.rs
+//
+// The JSON shapes of the HTTP API, shared by the server and the Rust client
+// (crates/vectordb-client).
+//
+// Everything here is plain data: vectors, distance metrics, and the request
+// and response bodies of the collection, point and search endpoints, with
+// the serde attributes that define their wire format. The server's
+// `vectordb::models` re-exports all of it, so server code keeps importing
+// from there; anything that needs the engine (errors, storage, write
+// outcomes) stays in the server crate.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CORE DATA TYPES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A vector embedding with metadata.
+///
+/// This is the fundamental unit stored in our database.
+/// Each vector has embedding data and optional key-value metadata.
+///
+/// # Example
+/// ```
+/// use vectordb_types::Vector;
+/// let v = Vector::new(vec![0.1, 0.2, 0.3]);
+/// assert_eq!(v.dimension(), 3);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vector {
+    /// The raw embedding data (e.g., 768 floats for BERT)
+    pub data: Vec<f32>,
+
+    /// Key-value metadata: {"title": "Document Name", "category": "tech"}
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+
+    /// Sparse embedding stored next to the dense one (BM25, SPLADE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<SparseVector>,
+}
+
+impl Vector {
+    /// Create a new vector with just data (no metadata)
+    pub fn new(data: Vec<f32>) -> Self {
+        Self::with_metadata(data, HashMap::new())
+    }
+
+    /// Create a vector with metadata
+    pub fn with_metadata(data: Vec<f32>, metadata: HashMap<String, String>) -> Self {
+        Self {
+            data,
+            metadata,
+            sparse: None,
+        }
+    }
+
+    /// Attach a sparse embedding
+    pub fn with_sparse(mut self, sparse: SparseVector) -> Self {
+        self.sparse = Some(sparse);
+        self
+    }
+
+    /// Get the dimensionality of this vector
+    pub fn dimension(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Calculate the L2 norm (magnitude)
+    pub fn magnitude(&self) -> f32 {
+        self.data.iter().map(|x| x * x).sum::<f32>().sqrt()
+    }
+
+    /// Normalize the vector in-place (make magnitude = 1.0)
+    pub fn normalize(&mut self) {
+        let mag = self.magnitude();
+        if mag > 0.0 {
+            for x in &mut self.data {
+                *x /= mag;
+            }
+        }
+    }
+
+    /// Get a normalized copy (original unchanged)
+    pub fn normalized(&self) -> Self {
+        let mut copy = self.clone();
+        copy.normalize();
+        copy
+    }
+}
+
+/// A sparse embedding: only the nonzero components, by index.
+///
+/// Vocabulary-sized vectors from BM25 or SPLADE have a few hundred
+/// nonzeros out of tens of thousands of dimensions, so they're kept as
+/// (index, value) pairs and scored by dot product over shared indices.
+///
+/// # Example
+/// ```
+/// use vectordb_types::SparseVector;
+/// let a = SparseVector::new(vec![3, 17, 40], vec![0.5, 1.0, 2.0]);
+/// let b = SparseVector::new(vec![17, 40, 99], vec![2.0, 0.5, 1.0]);
+/// assert_eq!(a.dot(&b), 3.0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    /// Component indices, strictly increasing once sorted
+    pub indices: Vec<u32>,
+    /// Value of each listed component
+    pub values: Vec<f32>,
+}
+
+impl SparseVector {
+    pub fn new(indices: Vec<u32>, values: Vec<f32>) -> Self {
+        Self { indices, values }
+    }
+
+    /// Number of stored (nonzero) components
+    pub fn nnz(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Put the components in index order, as `check` and `dot` expect
+    pub fn sort(&mut self) {
+        if self.indices.windows(2).all(|w| w[0] < w[1]) {
+            return;
+        }
+        let mut pairs: Vec<(u32, f32)> = self
+            .indices
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+            .collect();
+        pairs.sort_by_key(|&(i, _)| i);
+        (self.indices, self.values) = pairs.into_iter().unzip();
+    }
+
+    /// Why this isn't a valid sorted sparse vector, if it isn't
+    pub fn check(&self) -> Result<(), String> {
+        if self.indices.len() != self.values.len() {
+            return Err(format!(
+                "sparse vector has {} indices but {} values",
+                self.indices.len(),
+                self.values.len()
+            ));
+        }
+        if let Some(w) = self.indices.windows(2).find(|w| w[0] >= w[1]) {
+            return Err(format!("sparse vector repeats index {}", w[1]));
+        }
+        match self.values.iter().position(|x| !x.is_finite()) {
+            Some(i) => Err(format!(
+                "sparse vector value at index {} is {}",
+                self.indices[i], self.values[i]
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Dot product with `other`: a merge over the two sorted index lists
+    pub fn dot(&self, other: &SparseVector) -> f32 {
+        let (mut i, mut j) = (0, 0);
+        let mut sum = 0.0;
+        while i < self.indices.len() && j < other.indices.len() {
+            match self.indices[i].cmp(&other.indices[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    sum += self.values[i] * other.values[j];
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DISTANCE METRICS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Supported distance/similarity metrics.
+///
+/// Different use cases require different metrics:
+/// - Cosine: Good for text embeddings (direction matters, not magnitude)
+/// - Euclidean: Good for spatial data
+/// - Dot: Fast, works well with normalized vectors
+/// - Hamming: Binary embeddings (one bit per component)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Cosine similarity: 1 = identical, 0 = orthogonal, -1 = opposite
+    #[default]
+    Cosine,
+
+    /// Euclidean distance: 0 = identical, larger = more different
+    Euclidean,
+
+    /// Dot product: higher = more similar (assumes normalized vectors)
+    Dot,
+
+    /// Hamming distance: how many bits differ, reading each component as
+    /// a bit (1 if > 0). 0 = identical, larger = more different
+    Hamming,
+}
+
+impl DistanceMetric {
+    /// Calculate distance/similarity between two vectors.
+    ///
+    /// # Panics
+    /// Silently truncates if vectors have different lengths (zip behavior).
+    /// Use `validate_dimensions` before calling this in production.
+    pub fn calculate(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            DistanceMetric::Cosine => {
+                let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+                let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+                if norm_a == 0.0 || norm_b == 0.0 {
+                    0.0
+                } else {
+                    dot / (norm_a * norm_b)
+                }
+            }
+            DistanceMetric::Euclidean => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f32>()
+                .sqrt(),
+            DistanceMetric::Dot => a.iter().zip(b).map(|(x, y)| x * y).sum(),
+            DistanceMetric::Hamming => a
+                .iter()
+                .zip(b)
+                .filter(|(x, y)| (**x > 0.0) != (**y > 0.0))
+                .count() as f32,
+        }
+    }
+
+    /// Does a larger score mean "more similar"?
+    ///
+    /// True for the similarity metrics (Cosine, Dot), false for Euclidean
+    /// and Hamming where the score is a distance.
+    pub fn higher_is_better(&self) -> bool {
+        !matches!(self, DistanceMetric::Euclidean | DistanceMetric::Hamming)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SEARCH TYPES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A single search result with ID and similarity score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// The ID of the matching vector
+    pub id: String,
+
+    /// Similarity/distance score
+    pub score: f32,
+
+    /// Calibrated relevance probability (collections with a calibration
+    /// fitted for the search metric only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f32>,
+
+    /// The matching vector's data (searches with `include_vector` only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+
+    /// The matching vector's metadata (searches with `include_metadata`
+    /// only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl SearchResult {
+    /// A hit with just an ID and score; searches fill in the rest on request
+    pub fn new(id: impl Into<String>, score: f32) -> Self {
+        Self {
+            id: id.into(),
+            score,
+            probability: None,
+            vector: None,
+            metadata: None,
+        }
+    }
+}
+
+/// What a search returns with each hit besides its ID and score. Every
+/// search body takes these two flags, flattened in; both default to off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncludeFields {
+    /// Return each hit's vector data with it, saving a GET per hit
+    #[serde(default)]
+    pub include_vector: bool,
+
+    /// Return each hit's metadata with it
+    #[serde(default)]
+    pub include_metadata: bool,
+}
+
+impl IncludeFields {
+    /// Does the search want anything beyond IDs and scores?
+    pub fn any(&self) -> bool {
+        self.include_vector || self.include_metadata
+    }
+}
+
+/// Parameters for a search query (received from clients).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchRequest {
+    /// The query vector
+    pub vector: Vec<f32>,
+
+    /// Number of results to return (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Distance metric to use (default: the collection's metric, else the
+    /// deployment-wide default — see `engine::metric::resolve`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<DistanceMetric>,
+
+    /// Scan exactly instead of searching the collection's index, and
+    /// instead of failing if the index can't rank by `metric`
+    #[serde(default)]
+    pub exact: bool,
+
+    /// Uploaded WASM UDF deciding which candidates to keep (servers built
+    /// with `--features wasm`; see `engine::udf`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_udf: Option<String>,
+
+    /// Uploaded WASM UDF adjusting each candidate's score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_udf: Option<String>,
+
+    /// Return every hit within this score instead of the top_k best: a
+    /// distance ceiling for Euclidean, a similarity floor for cosine and
+    /// dot. Hits come back best first, top_k (up to a server cap) per page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+
+    /// Where a radius search continues: the `x-next-cursor` header of the
+    /// previous page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+
+    /// Search only once the write with this sequence number (the `seq` of
+    /// a write response) has been applied, waiting a bounded time for it:
+    /// read-your-writes across connections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_seq: Option<u64>,
+
+    /// Skip this many of the best hits and return the top_k after them.
+    /// Any offset (0 too) makes the response carry an `x-next-page-token`
+    /// header while more hits follow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+
+    /// Where a paged search continues: the `x-next-page-token` header of
+    /// the previous page (instead of an offset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_token: Option<String>,
+
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
+}
+
+fn default_top_k() -> usize {
+    10
+}
+
+impl SearchRequest {
+    /// Create a simple search request
+    pub fn new(vector: Vec<f32>, top_k: usize) -> Self {
+        Self {
+            vector,
+            top_k,
+            metric: None,
+            exact: false,
+            filter_udf: None,
+            score_udf: None,
+            radius: None,
+            cursor: None,
+            min_seq: None,
+            offset: None,
+            page_token: None,
+            include: IncludeFields::default(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// POINT TYPES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Wrapper for upsert payloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertRequest {
+    pub points: Vec<PointInput>,
+
+    /// What to do when a point's ID already exists
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+
+    /// All-or-nothing: if any point fails, write none of them
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// Outcome of one point in a batch upsert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointStatus {
+    Inserted,
+    Overwritten,
+    Merged,
+    Skipped,
+    Failed,
+}
+
+/// Per-point entry in a batch upsert response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointResult {
+    pub id: String,
+    pub status: PointStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Schema violations, when that's why the point failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+impl PointResult {
+    pub fn ok(id: String, status: PointStatus) -> Self {
+        Self {
+            id,
+            status,
+            error: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn failed(id: String, error: String, fields: Vec<FieldError>) -> Self {
+        Self {
+            id,
+            status: PointStatus::Failed,
+            error: Some(error),
+            fields,
+        }
+    }
+}
+
+/// Response to a batch upsert (POST /api/collections/:name/points)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertResponse {
+    /// "upserted", or "partial" if some points failed
+    pub status: String,
+    pub collection: String,
+    /// Points written to the collection
+    pub count: usize,
+    /// Points mirrored into its shadow
+    pub mirrored: usize,
+    pub inserted: usize,
+    pub overwritten: usize,
+    pub merged: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub results: Vec<PointResult>,
+    /// WAL sequence number of the write; pass it as a search's `min_seq`
+    /// to read it back (absent when the server runs without a WAL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+/// Policy for writing a point whose ID already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Reject the write
+    Error,
+    /// Keep the existing point untouched
+    Skip,
+    /// Replace the existing point (the default)
+    #[default]
+    Overwrite,
+    /// Replace the vector, keep existing metadata keys the write doesn't set
+    MergeMetadata,
+}
+
+/// How a collection assigns IDs to points written without one.
+///
+/// Serialized as `"uuid"`, `"ulid"`, `"auto_increment"`, or
+/// `{ "snowflake": { "node": 3 } }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// Clients must supply every ID (the default)
+    #[default]
+    Client,
+    /// Random UUIDv4 — no ordering
+    Uuid,
+    /// 26-char ULID — sorts by creation time as a string
+    Ulid,
+    /// 64-bit Twitter-style ID (time, node, sequence) — sorts by creation
+    /// time as a number; `node` keeps IDs from several writers distinct
+    Snowflake { node: u16 },
+    /// 1, 2, 3, ... per collection
+    AutoIncrement,
+}
+
+impl IdStrategy {
+    /// Does the server generate IDs under this strategy?
+    pub fn generates(&self) -> bool {
+        *self != IdStrategy::Client
+    }
+}
+
+/// A single point to insert/update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointInput {
+    /// Omit to have the collection generate one (see `IdStrategy`)
+    #[serde(default)]
+    pub id: String,
+    pub vector: Vec<f32>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Embedding from the new model, mirrored into the shadow collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_vector: Option<Vec<f32>>,
+    /// Sparse embedding stored next to `vector`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<SparseVector>,
+}
+
+impl PointInput {
+    /// A point with just an ID and vector data
+    pub fn new(id: impl Into<String>, vector: Vec<f32>) -> Self {
+        Self::with_metadata(id, vector, HashMap::new())
+    }
+
+    /// A point with metadata
+    pub fn with_metadata(
+        id: impl Into<String>,
+        vector: Vec<f32>,
+        metadata: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            metadata,
+            shadow_vector: None,
+            sparse: None,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COLLECTION TYPES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Request to create a new collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    pub dimension: usize,
+    /// Metric for searches (default: the deployment-wide default metric)
+    #[serde(default)]
+    pub distance: Option<DistanceMetric>,
+    /// Optional metadata schema enforced on every insert
+    #[serde(default)]
+    pub schema: Option<CollectionSchema>,
+    /// Metadata values filled in when an insert doesn't provide them
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+    /// Fields computed by the server at insert time
+    #[serde(default)]
+    pub computed: Vec<ComputedField>,
+    /// How many previous versions to keep per vector (0 = no history)
+    #[serde(default)]
+    pub max_versions: usize,
+    /// How IDs are generated for points written without one
+    #[serde(default)]
+    pub id_strategy: IdStrategy,
+    /// Refuse deletes and destructive rewrites unless forced with the
+    /// admin key
+    #[serde(default)]
+    pub protected: bool,
+    /// Search through an HNSW graph instead of an exact scan
+    #[serde(default)]
+    pub hnsw: Option<HnswConfig>,
+    /// Scan exactly while small, build an HNSW graph once large
+    #[serde(default)]
+    pub auto_index: Option<AutoIndex>,
+    /// Scale every inserted vector to unit length, so cosine scores are
+    /// plain dot products (cosine and dot collections only)
+    #[serde(default)]
+    pub normalized: bool,
+    /// How components are stored: f32, or one bit each (binary
+    /// collections take only 0s and 1s and search by Hamming distance)
+    #[serde(default)]
+    pub vector_type: VectorType,
+}
+
+impl CreateCollectionRequest {
+    /// A collection with every option at its default
+    pub fn new(name: impl Into<String>, dimension: usize) -> Self {
+        Self {
+            name: name.into(),
+            dimension,
+            ..Self::default()
+        }
+    }
+}
+
+/// Information about a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    pub name: String,
+    pub dimension: usize,
+    pub distance: DistanceMetric,
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<CollectionSchema>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub defaults: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub computed: Vec<ComputedField>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_versions: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<String>,
+    #[serde(default)]
+    pub id_strategy: IdStrategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hnsw: Option<HnswConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_index: Option<AutoIndex>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub normalized: bool,
+    #[serde(default, skip_serializing_if = "VectorType::is_float32")]
+    pub vector_type: VectorType,
+}
+
+/// How a collection stores vector components
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorType {
+    /// 4 bytes per component
+    #[default]
+    Float32,
+    /// 1 bit per component, packed 64 to a word: 32x smaller than f32,
+    /// for embeddings from binary quantized models
+    Binary,
+}
+
+impl VectorType {
+    pub fn is_float32(&self) -> bool {
+        *self == VectorType::Float32
+    }
+}
+
+/// Build an HNSW graph for a collection once it reaches `threshold`
+/// points; until then searches scan every vector exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoIndex {
+    pub threshold: usize,
+    /// Parameters of the graph to build
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+}
+
+/// Parameters of an HNSW (hierarchical navigable small world) graph index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Links per node on the upper layers (twice this on the bottom one)
+    #[serde(default = "default_hnsw_m")]
+    pub m: usize,
+    /// Candidates considered when linking a new node
+    #[serde(default = "default_ef_construction")]
+    pub ef_construction: usize,
+    /// Candidates kept while searching (at least top_k)
+    #[serde(default = "default_ef_search")]
+    pub ef_search: usize,
+    /// Prefetch the vectors and neighbour lists a search is about to read
+    /// (only takes effect once the graph outgrows the CPU caches)
+    #[serde(default = "default_prefetch")]
+    pub prefetch: bool,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: default_hnsw_m(),
+            ef_construction: default_ef_construction(),
+            ef_search: default_ef_search(),
+            prefetch: default_prefetch(),
+        }
+    }
+}
+
+fn default_hnsw_m() -> usize {
+    16
+}
+
+fn default_ef_construction() -> usize {
+    200
+}
+
+fn default_ef_search() -> usize {
+    64
+}
+
+fn default_prefetch() -> bool {
+    true
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// Platt-scaling parameters that turn a raw score into a relevance
+/// probability: P(relevant | s) = 1 / (1 + exp(a·s + b)).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Metric the scores were computed with; other metrics aren't calibrated
+    pub metric: DistanceMetric,
+    pub a: f64,
+    pub b: f64,
+    /// Labeled pairs the fit used, and how many of them were relevant
+    pub pairs: usize,
+    pub relevant: usize,
+    /// Unix timestamp (seconds) of the fit
+    pub fitted_at: u64,
+}
+
+impl Calibration {
+    /// Relevance probability for a raw score
+    pub fn probability(&self, score: f32) -> f32 {
+        (1.0 / (1.0 + (self.a * score as f64 + self.b).exp())) as f32
+    }
+}
+
+/// Metadata the server derives for every inserted vector.
+///
+/// The value is stored under the field's own name (e.g. `inserted_at`), so
+/// it can be filtered on like any client-supplied field.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComputedField {
+    /// Unix timestamp (seconds) of the insert
+    InsertedAt,
+
+    /// L2 norm of the vector
+    Norm,
+}
+
+impl ComputedField {
+    /// Metadata key the value is stored under
+    pub fn key(&self) -> &'static str {
+        match self {
+            ComputedField::InsertedAt => "inserted_at",
+            ComputedField::Norm => "norm",
+        }
+    }
+
+    /// Type of the stored value, for schema checks
+    pub fn field_type(&self) -> FieldType {
+        match self {
+            ComputedField::InsertedAt => FieldType::Integer,
+            ComputedField::Norm => FieldType::Float,
+        }
+    }
+
+    /// Compute the value for `vector` inserted at `unix_secs`
+    pub fn compute(&self, vector: &Vector, unix_secs: u64) -> String {
+        match self {
+            ComputedField::InsertedAt => unix_secs.to_string(),
+            ComputedField::Norm => vector.magnitude().to_string(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// METADATA SCHEMAS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Type a metadata value must parse as.
+///
+/// Metadata is stored as strings, so "integer" means "a string that parses
+/// as an i64" — which is exactly what range filters need to rely on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Integer,
+    Float,
+    Bool,
+}
+
+impl FieldType {
+    /// Does `value` parse as this type?
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            FieldType::String => true,
+            FieldType::Integer => value.parse::<i64>().is_ok(),
+            FieldType::Float => value.parse::<f64>().is_ok(),
+            FieldType::Bool => matches!(value, "true" | "false"),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FieldType::String => "string",
+            FieldType::Integer => "integer",
+            FieldType::Float => "float",
+            FieldType::Bool => "bool",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One declared metadata field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    /// Inserts without this field are rejected (default: true)
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// Declared metadata fields for a collection.
+///
+/// Fields not listed here are still accepted, untyped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionSchema {
+    pub fields: Vec<FieldSchema>,
+}
+
+impl CollectionSchema {
+    /// Check metadata against the schema, returning one error per bad field.
+    pub fn validate(&self, metadata: &HashMap<String, String>) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        for field in &self.fields {
+            match metadata.get(&field.name) {
+                None if field.required => errors.push(FieldError::new(
+                    &field.name,
+                    "missing required field".to_string(),
+                )),
+                None => {}
+                Some(value) if !field.field_type.accepts(value) => errors.push(FieldError::new(
+                    &field.name,
+                    format!("expected {}, got '{}'", field.field_type, value),
+                )),
+                Some(_) => {}
+            }
+        }
+        errors
+    }
+}
+
+/// A single metadata field that failed schema validation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// ID of the offending vector, when validating a batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: String) -> Self {
+        Self {
+            id: None,
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ERROR RESPONSES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Body of every non-2xx response:
+/// `{ "error": true, "message": "...", "fields": [...] }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    #[serde(default)]
+    pub error: bool,
+    pub message: String,
+    /// Per-field validation errors (schema violations)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_creation() {
+        let v = Vector::new(vec![0.1, 0.2, 0.3]);
+        assert_eq!(v.dimension(), 3);
+        assert!(v.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_vector_with_metadata() {
+        let mut meta = HashMap::new();
+        meta.insert("title".to_string(), "Test Doc".to_string());
+        let v = Vector::with_metadata(vec![1.0, 2.0], meta);
+        assert_eq!(v.metadata["title"], "Test Doc");
+    }
+
+    #[test]
+    fn test_vector_magnitude() {
+        let v = Vector::new(vec![3.0, 4.0]);
+        assert!((v.magnitude() - 5.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_vector_normalize() {
+        let mut v = Vector::new(vec![3.0, 4.0]);
+        v.normalize();
+        assert!((v.magnitude() - 1.0).abs() < 0.0001);
+        assert!((v.data[0] - 0.6).abs() < 0.0001);
+        assert!((v.data[1] - 0.8).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        let a = vec![1.0, 0.0];
+        let b = vec![0.0, 1.0];
+        // Orthogonal vectors → cosine = 0
+        let score = DistanceMetric::Cosine.calculate(&a, &b);
+        assert!((score - 0.0).abs() < 0.0001);
+
+        // Identical vectors → cosine = 1
+        let score = DistanceMetric::Cosine.calculate(&a, &a);
+        assert!((score - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_euclidean_distance() {
+        let a = vec![0.0, 0.0];
+        let b = vec![3.0, 4.0];
+        let dist = DistanceMetric::Euclidean.calculate(&a, &b);
+        assert!((dist - 5.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_dot_product() {
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![4.0, 5.0, 6.0];
+        let dot = DistanceMetric::Dot.calculate(&a, &b);
+        assert!((dot - 32.0).abs() < 0.0001); // 1*4 + 2*5 + 3*6 = 32
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        let a = vec![1.0, 0.0, 1.0, 1.0];
+        let b = vec![1.0, 1.0, 0.0, 1.0];
+        assert_eq!(DistanceMetric::Hamming.calculate(&a, &b), 2.0);
+        assert_eq!(DistanceMetric::Hamming.calculate(&a, &a), 0.0);
+        assert!(!DistanceMetric::Hamming.higher_is_better());
+    }
+
+    #[test]
+    fn test_sparse_vectors_sort_check_and_dot() {
+        let mut a = SparseVector::new(vec![40, 3, 17], vec![2.0, 0.5, 1.0]);
+        a.sort();
+        assert_eq!(a.indices, [3, 17, 40]);
+        assert_eq!(a.values, [0.5, 1.0, 2.0]);
+        assert!(a.check().is_ok());
+
+        let b = SparseVector::new(vec![0, 17, 40], vec![9.0, 2.0, 0.5]);
+        assert_eq!(a.dot(&b), 3.0);
+        assert_eq!(a.dot(&SparseVector::default()), 0.0);
+
+        let repeated = SparseVector::new(vec![1, 1], vec![1.0, 1.0]);
+        assert!(repeated.check().unwrap_err().contains("repeats index 1"));
+        assert!(SparseVector::new(vec![1], vec![]).check().is_err());
+        let nan = SparseVector::new(vec![5], vec![f32::NAN]);
+        assert!(nan.check().is_err());
+    }
+
+    #[test]
+    fn test_schema_validation() {
+        let schema = CollectionSchema {
+            fields: vec![
+                FieldSchema {
+                    name: "lang".into(),
+                    field_type: FieldType::String,
+                    required: true,
+                },
+                FieldSchema {
+                    name: "year".into(),
+                    field_type: FieldType::Integer,
+                    required: false,
+                },
+            ],
+        };
+
+        let mut meta = HashMap::new();
+        meta.insert("year".to_string(), "20x4".to_string());
+        let errors = schema.validate(&meta);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "lang");
+        assert!(errors[1].message.contains("expected integer"));
+
+        meta.insert("lang".to_string(), "de".to_string());
+        meta.insert("year".to_string(), "2024".to_string());
+        assert!(schema.validate(&meta).is_empty());
+    }
+
+    #[test]
+    fn test_request_defaults_on_the_wire() {
+        // Omitted fields take their documented defaults
+        let req: SearchRequest = serde_json::from_str(r#"{"vector":[1.0]}"#).unwrap();
+        assert_eq!((req.top_k, req.metric, req.exact), (10, None, false));
+        assert_eq!(req.include, IncludeFields::default());
+        let req: SearchRequest =
+            serde_json::from_str(r#"{"vector":[1.0],"include_metadata":true}"#).unwrap();
+        assert!(req.include.include_metadata && !req.include.include_vector);
+        let req: CreateCollectionRequest =
+            serde_json::from_str(r#"{"name":"docs","dimension":3}"#).unwrap();
+        assert_eq!(req.id_strategy, IdStrategy::Client);
+
+        // Optional fields the server doesn't need aren't sent
+        let json = serde_json::to_value(PointInput::new("a", vec![1.0])).unwrap();
+        assert!(json.get("shadow_vector").is_none());
+        let json =
+            serde_json::to_value(PointResult::ok("a".into(), PointStatus::Inserted)).unwrap();
+        assert_eq!(json, serde_json::json!({ "id": "a", "status": "inserted" }));
+
+        // The include flags sit at the top level of the body, not nested
+        let json = serde_json::to_value(SearchRequest::new(vec![1.0], 1)).unwrap();
+        assert_eq!(json["include_vector"], false);
+        assert!(json.get("include").is_none());
+    }
+}
@@ -0,0 +1,469 @@
+// src/auth.rs
+//
+// Scoped API keys: which collections a key may touch, and how.
+//
+// By default the HTTP API is open (only the admin endpoints can require a
+// key, see admin_key in config.rs). Naming a key file in `api_keys`
+// closes it: every request then has to present one of the file's keys, as
+// `X-API-Key` or `Authorization: Bearer`, and the key has to allow what
+// the request does:
+//
+//   {
+//     "keys": [
+//       { "key": "s3cr3t-search", "collections": ["docs-*"], "verbs": ["search"] },
+//       { "key": "s3cr3t-ingest", "collections": ["docs-*", "logs"],
+//         "verbs": ["read", "write"] },
+//       { "key": "s3cr3t-ops" }
+//     ]
+//   }
+//
+// A collection pattern is a name in which `*` matches any run of
+// characters; a key without `collections` may use every collection, and
+// one without `verbs` every verb. The admin key may do anything.
+//
+// Each request is checked by the `authorize` middleware against what its
+// method and path say it does (`Access::of`):
+//
+//   read     GET and HEAD: points, stats, exports, listings, jobs
+//   search   searches, including shadow comparisons, search streams and
+//            vector arithmetic
+//   write    inserts, updates and deletes of points, imports, uploads
+//   manage   creating, renaming and deleting collections, index builds,
+//            calibration, shadows, aliases, templates, trash, admin
+//
+// Requests under /api/collections/:name act on that collection, its name
+// percent-decoded as the handler will see it. The few that reach a second
+// collection check it too, with the `Principal` the middleware leaves in
+// the request's extensions: a rename needs manage on the new name, a
+// shadow needs manage on the shadow (and a comparison search on it), and a
+// search through an alias needs search on the collection it routes to.
+// Anything else names its collections in the body or not at all (the
+// legacy /vectors API, multi and batch searches, templates, uploads by
+// id, ...), so it's only allowed to keys whose patterns include "*". The
+// public endpoints (/, /health, /metrics) need no key.
+//
+// The gRPC API authenticates the same credentials in an interceptor, and
+// each call checks the collection its message names.
+//
+// Access tokens from an OIDC provider can stand in for keys, their claims
+// mapped to the same collections and verbs (see src/oidc.rs).
+
+use crate::models::{Result, VectorDbError};
+use crate::oidc::{self, Grant, Verifier};
+use axum::http::Method;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Endpoints anyone may call, key or not
+const PUBLIC_PATHS: [&str; 3] = ["/", "/health", "/metrics"];
+
+/// What a request does to the collections it touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verb {
+    Read,
+    Search,
+    Write,
+    Manage,
+}
+
+impl Verb {
+    pub const ALL: [Verb; 4] = [Verb::Read, Verb::Search, Verb::Write, Verb::Manage];
+
+    /// The verb named `name` ("read", "search", ...)
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.to_string() == name)
+    }
+}
+
+impl fmt::Display for Verb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verb::Read => "read",
+            Verb::Search => "search",
+            Verb::Write => "write",
+            Verb::Manage => "manage",
+        })
+    }
+}
+
+/// What one request needs a key to allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    pub verb: Verb,
+    /// The collection named in the path; None when the request may touch
+    /// any of them
+    pub collection: Option<String>,
+}
+
+impl Access {
+    /// `verb` on collection `name`
+    pub fn on(verb: Verb, name: &str) -> Self {
+        Self {
+            verb,
+            collection: Some(name.to_string()),
+        }
+    }
+
+    /// The access a request needs, from its method and path; None for the
+    /// public endpoints
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        if PUBLIC_PATHS.contains(&path) {
+            return None;
+        }
+        let reads = *method == Method::GET || *method == Method::HEAD;
+        if let Some(rest) = path.strip_prefix("/api/collections/") {
+            let (name, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy();
+            let verb = match rest {
+                "search" | "search/sparse" | "search/hybrid" | "shadow/compare" => Verb::Search,
+                _ if reads => Verb::Read,
+                "" | "rename" | "index" | "index/relayout" | "calibration" | "shadow" => {
+                    Verb::Manage
+                }
+                _ => Verb::Write,
+            };
+            return Some(Self::on(verb, &name));
+        }
+
+        let verb = if path.starts_with("/api/admin/") || path.starts_with("/debug/") {
+            Verb::Manage
+        } else if path == "/search" || path == "/api/compute/arith" {
+            Verb::Search
+        } else if path.starts_with("/api/search/") {
+            match path.strip_prefix("/api/search/template/") {
+                Some(_) if *method == Method::POST => Verb::Search,
+                Some(_) if !reads => Verb::Manage,
+                _ if reads && path != "/api/search/stream" => Verb::Read,
+                _ => Verb::Search,
+            }
+        } else if reads || matches!(path, "/api/vectors/get" | "/api/vectors/exists") {
+            Verb::Read
+        } else if path == "/vectors"
+            || path.starts_with("/vectors/")
+            || path.starts_with("/api/vectors/")
+            || path.starts_with("/api/uploads/")
+        {
+            Verb::Write
+        } else {
+            Verb::Manage
+        };
+        Some(Self {
+            verb,
+            collection: None,
+        })
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.collection {
+            Some(name) => write!(f, "{} collection '{}'", self.verb, name),
+            None => write!(f, "{} every collection", self.verb),
+        }
+    }
+}
+
+/// One key and what it allows
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopedKey {
+    pub key: String,
+    /// Collection name patterns (`*` matches anything)
+    #[serde(default = "all_collections")]
+    pub collections: Vec<String>,
+    #[serde(default = "all_verbs")]
+    pub verbs: Vec<Verb>,
+}
+
+fn all_collections() -> Vec<String> {
+    vec!["*".into()]
+}
+
+fn all_verbs() -> Vec<Verb> {
+    Verb::ALL.to_vec()
+}
+
+impl ScopedKey {
+    /// Does this key allow `access`?
+    pub fn allows(&self, access: &Access) -> bool {
+        scope_allows(&self.collections, &self.verbs, access)
+    }
+}
+
+/// Do collection `patterns` and `verbs` together allow `access`?
+pub fn scope_allows(patterns: &[String], verbs: &[Verb], access: &Access) -> bool {
+    verbs.contains(&access.verb)
+        && match &access.collection {
+            Some(name) => patterns.iter().any(|p| matches(p, name)),
+            None => patterns.iter().any(|p| p == "*"),
+        }
+}
+
+/// Who a request's credential turned out to be
+#[derive(Debug, Clone)]
+pub enum Principal {
+    /// The admin key: allowed everything
+    Admin,
+    Key(ScopedKey),
+    Token(Grant),
+}
+
+impl Principal {
+    /// Does this credential allow `access`?
+    pub fn allows(&self, access: &Access) -> bool {
+        match self {
+            Principal::Admin => true,
+            Principal::Key(key) => key.allows(access),
+            Principal::Token(grant) => grant.allows(access),
+        }
+    }
+
+    /// What kind of credential this is, for messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Principal::Admin => "admin key",
+            Principal::Key(_) => "API key",
+            Principal::Token(_) => "access token",
+        }
+    }
+}
+
+/// What credentials are checked against: the scoped keys, the OIDC
+/// provider and the admin key, whichever are configured
+#[derive(Debug, Clone, Default)]
+pub struct KeyCheck {
+    pub keys: Option<Arc<ApiKeys>>,
+    pub oidc: Option<Arc<Verifier>>,
+    /// Allowed everything, like a key without scopes
+    pub admin_key: Option<Arc<String>>,
+}
+
+impl KeyCheck {
+    /// Is anything configured to check against?
+    pub fn is_enabled(&self) -> bool {
+        self.keys.is_some() || self.oidc.is_some()
+    }
+
+    /// Who `credential` belongs to, at unix time `now` (for token expiry);
+    /// Err with the reason if it's nobody
+    pub fn authenticate(
+        &self,
+        credential: &str,
+        now: u64,
+    ) -> std::result::Result<Principal, String> {
+        let is_admin = self
+            .admin_key
+            .as_ref()
+            .is_some_and(|admin| constant_time_eq(credential.as_bytes(), admin.as_bytes()));
+        if is_admin {
+            return Ok(Principal::Admin);
+        }
+        if let Some(scoped) = self.keys.as_ref().and_then(|keys| keys.find(credential)) {
+            return Ok(Principal::Key(scoped.clone()));
+        }
+        match &self.oidc {
+            Some(verifier) if oidc::is_token(credential) => verifier
+                .verify(credential, now)
+                .map(Principal::Token)
+                .map_err(|e| format!("Invalid access token: {}", e)),
+            _ => Err("Unknown API key".into()),
+        }
+    }
+}
+
+/// The keys of an api_keys file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeys {
+    keys: Vec<ScopedKey>,
+}
+
+impl ApiKeys {
+    /// Load and check a key file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            VectorDbError::InvalidParameter(format!("can't read {}: {}", path.display(), e))
+        })?;
+        Self::parse(&text)
+            .map_err(|e| VectorDbError::InvalidParameter(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parse a key file's JSON
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let keys: Self = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        for (i, key) in keys.keys.iter().enumerate() {
+            if key.key.trim().is_empty() {
+                return Err(format!("key {} is empty", i));
+            }
+            if keys.keys[..i].iter().any(|k| k.key == key.key) {
+                return Err(format!("key {} repeats an earlier key", i));
+            }
+            if key.collections.is_empty() || key.verbs.is_empty() {
+                return Err(format!("key {} allows nothing", i));
+            }
+        }
+        Ok(keys)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The entry for `key`, compared in constant time against each
+    pub fn find(&self, key: &str) -> Option<&ScopedKey> {
+        self.keys
+            .iter()
+            .find(|k| constant_time_eq(k.key.as_bytes(), key.as_bytes()))
+    }
+}
+
+/// Does `name` match `pattern`, where `*` matches any run of characters?
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the whole name has to match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Compare secrets in time independent of where they first differ
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(method: Method, path: &str) -> Option<(Verb, Option<String>)> {
+        Access::of(&method, path).map(|a| (a.verb, a.collection))
+    }
+
+    fn on(name: &str) -> Option<String> {
+        Some(name.to_string())
+    }
+
+    #[test]
+    fn test_patterns_match_with_wildcards() {
+        assert!(matches("*", "anything"));
+        assert!(matches("docs", "docs"));
+        assert!(!matches("docs", "docs-2"));
+        assert!(matches("docs-*", "docs-2"));
+        assert!(matches("docs-*", "docs-"));
+        assert!(!matches("docs-*", "logs-2"));
+        assert!(matches("*-v2", "docs-v2"));
+        assert!(matches("a*b*c", "a-b-b-c"));
+        assert!(!matches("a*b*c", "a-c"));
+        assert!(!matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_requests_map_to_verbs_and_collections() {
+        use Verb::*;
+        assert_eq!(access(Method::GET, "/health"), None);
+        assert_eq!(
+            access(Method::POST, "/api/collections/docs/search"),
+            Some((Search, on("docs")))
+        );
+        assert_eq!(
+            access(Method::GET, "/api/collections/docs/points/7"),
+            Some((Read, on("docs")))
+        );
+        assert_eq!(
+            access(Method::PUT, "/api/collections/docs/points/7"),
+            Some((Write, on("docs")))
+        );
+        assert_eq!(
+            access(Method::DELETE, "/api/collections/docs"),
+            Some((Manage, on("docs")))
+        );
+        assert_eq!(
+            access(Method::POST, "/api/collections/docs/index"),
+            Some((Manage, on("docs")))
+        );
+        assert_eq!(
+            access(Method::POST, "/api/collections"),
+            Some((Manage, None))
+        );
+        assert_eq!(
+            access(Method::POST, "/api/search/batch"),
+            Some((Search, None))
+        );
+        assert_eq!(
+            access(Method::GET, "/api/search/stream"),
+            Some((Search, None))
+        );
+        assert_eq!(
+            access(Method::GET, "/api/search/templates"),
+            Some((Read, None))
+        );
+        assert_eq!(
+            access(Method::PUT, "/api/search/template/t"),
+            Some((Manage, None))
+        );
+        assert_eq!(access(Method::POST, "/api/vectors/get"), Some((Read, None)));
+        assert_eq!(access(Method::DELETE, "/vectors/3"), Some((Write, None)));
+        assert_eq!(
+            access(Method::GET, "/api/admin/usage"),
+            Some((Manage, None))
+        );
+
+        // Names are checked decoded, as axum's Path hands them over
+        assert_eq!(
+            access(Method::POST, "/api/collections/hr%2Dprivate/search"),
+            Some((Search, on("hr-private")))
+        );
+    }
+
+    #[test]
+    fn test_keys_allow_only_their_scopes() {
+        let keys = ApiKeys::parse(
+            r#"{ "keys": [
+                { "key": "k1", "collections": ["docs-*"], "verbs": ["search"] },
+                { "key": "k2" }
+            ] }"#,
+        )
+        .unwrap();
+        let search = |name: Option<&str>| Access {
+            verb: Verb::Search,
+            collection: name.map(str::to_string),
+        };
+        let k1 = keys.find("k1").unwrap();
+        assert!(k1.allows(&search(Some("docs-en"))));
+        assert!(!k1.allows(&search(Some("logs"))));
+        assert!(!k1.allows(&search(None)));
+        assert!(!k1.allows(&Access {
+            verb: Verb::Write,
+            collection: Some("docs-en".into()),
+        }));
+        assert!(keys.find("k2").unwrap().allows(&search(None)));
+        assert!(keys.find("k3").is_none());
+
+        assert!(ApiKeys::parse(r#"{ "keys": [{ "key": "a" }, { "key": "a" }] }"#).is_err());
+        assert!(ApiKeys::parse(r#"{ "keys": [{ "key": "a", "verbs": [] }] }"#).is_err());
+        assert!(ApiKeys::parse(r#"{ "keys": [{ "key": "a", "verbs": ["delete"] }] }"#).is_err());
+    }
+}
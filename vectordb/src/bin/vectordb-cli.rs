@@ -0,0 +1,1154 @@
+// src/bin/vectordb-cli.rs
+//
+// Command-line tools for operating a VectorDB server.
+//
+// Commands:
+//   replay <LOG> --target <URL> [--speed <X>] [--output <FILE>]
+//       Re-issue requests from a query log (see src/querylog.rs) against
+//       another instance. --speed 1 keeps the original spacing, 10 runs ten
+//       times faster, 0 sends everything at once. --output writes every
+//       response as JSON Lines, so two runs can be diffed for regressions.
+//
+//   inspect <FILE> [--decode] [--offset <N>] [--length <N>] [--vector <I>] [--samples <N>]
+//       Hex dump a segment file page by page, or with --decode walk its
+//       structure (header fields, vector data, trailing bytes) and print
+//       sample vectors with their offsets.
+//
+//   merge <INPUT>... --output <FILE> [--drop <INPUT>:<INDEX>]... [--drop-id <ID>]...
+//       Merge segment files into one (see src/storage/compaction.rs),
+//       leaving out each vector named by --drop (the input's position on
+//       the command line, from 0, and the vector's index within it) or by
+//       --drop-id (its point ID, for segments with ID tables).
+//
+//   defrag <SEGMENT> [--drop <INDEX>]... [--drop-id <ID>]...
+//       Rewrite one segment in place without the vectors named by --drop
+//       (their index) or --drop-id, keeping its encoding. Cheaper than a
+//       merge when only one large segment has accumulated deletes.
+//
+//   scan <SEGMENT> --query <X,Y,...> [--top-k <N>] [--metric cosine|euclidean|dot]
+//       Exact top-k of one segment, skipping blocks whose stored norm
+//       range (see src/storage/norms.rs) rules them out, and report how
+//       many blocks were skipped. Useful for checking what the ranges buy
+//       on a real dataset before relying on them.
+//
+//   convert <INPUT> --output <FILE> [--limit <N>] [--array <NAME>]
+//       Convert between segment files and the .fvecs/.bvecs/.ivecs formats
+//       the SIFT/GIST benchmark datasets ship in (see src/storage/vecs.rs),
+//       or NumPy float32 matrices in .npy/.npz files (src/storage/npy.rs).
+//       The direction and format come from the file extensions: a dataset
+//       input becomes a segment with IDs "0", "1", ... (matching the
+//       neighbour indices in ground-truth files); a dataset output gets the
+//       segment's vectors. --limit keeps only the first N vectors; --array
+//       picks the array in an .npz archive holding more than one.
+//
+//   bench [--target <URL>] [--dim <N>] [--count <N>] [--distribution gaussian|clustered]
+//         [--clusters <N>] [--spread <X>] [--seed <N>] [--collection <NAME>]
+//         [--queries <N>] [--concurrency <N>] [--recall-queries <N>]
+//       Generate a synthetic dataset (see src/synthetic.rs), load it into a
+//       new collection, and run the standard query mix against it: 70%
+//       top_k=10, 20% top_k=100, 10% top_k=1. Reports load throughput, QPS,
+//       latency per query type, and recall against exact neighbours
+//       computed locally for the first --recall-queries queries.
+//
+//   relayout <COLLECTION> [--target <URL>]
+//       Renumber a collection's HNSW graph on a running server so searches
+//       traverse nearby memory (see src/engine/hnsw.rs), and report the
+//       mean link span before and after. Graphs are laid out when built;
+//       this is for ones that have since grown by inserts.
+//
+//   schema
+//       Print a JSON description of the on-disk formats (segment headers
+//       for every version, sections, the WAL record framing and tags,
+//       snapshot file names), built from the constants the reader and
+//       writer use (see src/storage/schema.rs).
+//
+// Run with: cargo run --bin vectordb-cli -- replay queries.jsonl --target http://localhost:3000
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use vectordb::engine::{gemm, metric, search};
+use vectordb::models::{DistanceMetric, SearchResult};
+use vectordb::querylog::{self, LoggedQuery};
+use vectordb::storage::compaction::{self, Position};
+use vectordb::storage::inspect::{self, InspectOptions};
+use vectordb::storage::norms;
+use vectordb::storage::npy;
+use vectordb::storage::schema;
+use vectordb::storage::vecs::{self, VecsFormat};
+use vectordb::synthetic::{DatasetSpec, Distribution, Generator};
+
+const USAGE: &str = "\
+Usage: vectordb-cli <command> [options]
+
+Commands:
+  replay <LOG> --target <URL> [--speed <X>] [--output <FILE>]
+      Replay a query log against a server (speed 0 = as fast as possible)
+  inspect <FILE> [--decode] [--offset <N>] [--length <N>] [--vector <I>] [--samples <N>]
+      Hex dump a segment file, or decode its structure with --decode
+  merge <INPUT>... --output <FILE> [--drop <INPUT>:<INDEX>]... [--drop-id <ID>]...
+      Merge segment files into one, leaving out dropped vectors
+  defrag <SEGMENT> [--drop <INDEX>]... [--drop-id <ID>]...
+      Rewrite a segment in place without dropped vectors
+  scan <SEGMENT> --query <X,Y,...> [--top-k <N>] [--metric cosine|euclidean|dot]
+      Exact top-k of a segment, skipping blocks by their norm ranges
+  convert <INPUT> --output <FILE> [--limit <N>] [--array <NAME>]
+      Convert between segments and .fvecs/.bvecs/.ivecs/.npy/.npz files (by extension)
+  bench [--target <URL>] [--dim <N>] [--count <N>] [--distribution gaussian|clustered]
+        [--clusters <N>] [--spread <X>] [--seed <N>] [--collection <NAME>]
+        [--queries <N>] [--concurrency <N>] [--recall-queries <N>]
+      Load a synthetic dataset and measure load rate, query latency, and recall
+  relayout <COLLECTION> [--target <URL>]
+      Renumber a collection's HNSW graph for memory locality
+  schema
+      Print a JSON description of the on-disk formats";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("replay") => match ReplayArgs::parse(&args[1..]) {
+            Ok(replay_args) => replay(replay_args).await,
+            Err(e) => Err(e),
+        },
+        Some("inspect") => InspectArgs::parse(&args[1..]).and_then(run_inspect),
+        Some("merge") => MergeArgs::parse(&args[1..]).and_then(run_merge),
+        Some("defrag") => DefragArgs::parse(&args[1..]).and_then(run_defrag),
+        Some("scan") => ScanArgs::parse(&args[1..]).and_then(run_scan),
+        Some("convert") => ConvertArgs::parse(&args[1..]).and_then(run_convert),
+        Some("bench") => match BenchArgs::parse(&args[1..]) {
+            Ok(bench_args) => bench(bench_args).await,
+            Err(e) => Err(e),
+        },
+        Some("relayout") => match RelayoutArgs::parse(&args[1..]) {
+            Ok(relayout_args) => relayout(relayout_args).await,
+            Err(e) => Err(e),
+        },
+        Some("schema") => run_schema(&args[1..]),
+        Some("-h") | Some("--help") | None => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Some(other) => Err(format!("unknown command '{}'", other)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// INSPECT
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct InspectArgs {
+    file: PathBuf,
+    options: InspectOptions,
+}
+
+impl InspectArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut file = None;
+        let mut options = InspectOptions::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut number = |name: &str| -> Result<u64, String> {
+                let s = iter
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", name))?;
+                s.parse::<u64>()
+                    .map_err(|_| format!("{} must be a non-negative integer, got '{}'", name, s))
+            };
+            match arg.as_str() {
+                "--decode" => options.decode = true,
+                "--offset" => options.offset = number("--offset")?,
+                "--length" => options.length = Some(number("--length")?),
+                "--vector" => options.first_vector = number("--vector")?,
+                "--samples" => options.samples = number("--samples")?,
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if file.is_none() => file = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            file: file.ok_or("missing segment file path")?,
+            options,
+        })
+    }
+}
+
+fn run_inspect(args: InspectArgs) -> Result<(), String> {
+    let mut file =
+        std::fs::File::open(&args.file).map_err(|e| format!("{}: {}", args.file.display(), e))?;
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    match inspect::inspect(&mut file, &mut out, &args.options).and_then(|_| out.flush()) {
+        // Piping into `head` closes stdout early; that's not a failure
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.map_err(|e| e.to_string()),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCHEMA
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn run_schema(args: &[String]) -> Result<(), String> {
+    if let Some(extra) = args.first() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+    let json = serde_json::to_string_pretty(&schema::describe()).map_err(|e| e.to_string())?;
+    match writeln!(std::io::stdout().lock(), "{}", json) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.map_err(|e| e.to_string()),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RELAYOUT
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct RelayoutArgs {
+    collection: String,
+    target: String,
+}
+
+impl RelayoutArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut collection = None;
+        let mut target = "http://localhost:3000".to_string();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--target" => {
+                    target = iter.next().cloned().ok_or("--target needs a value")?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                name if collection.is_none() => collection = Some(name.to_string()),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            collection: collection.ok_or("missing collection name")?,
+            target: target.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+async fn relayout(args: RelayoutArgs) -> Result<(), String> {
+    let url = format!(
+        "{}/api/collections/{}/index/relayout",
+        args.target, args.collection
+    );
+    let start = Instant::now();
+    let resp = reqwest::Client::new()
+        .post(&url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = resp.status().as_u16();
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !(200..300).contains(&status) {
+        let message = body["message"].as_str().unwrap_or("no error message");
+        return Err(format!("relayout failed ({}): {}", status, message));
+    }
+    println!(
+        "Relaid out '{}': {} nodes in {:.2?}",
+        args.collection,
+        body["nodes"],
+        start.elapsed()
+    );
+    println!(
+        "Mean link span: {:.1} -> {:.1}",
+        body["edge_span_before"].as_f64().unwrap_or(0.0),
+        body["edge_span_after"].as_f64().unwrap_or(0.0)
+    );
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// MERGE
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct MergeArgs {
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    drop: HashSet<Position>,
+    drop_ids: HashSet<String>,
+}
+
+impl MergeArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut inputs = Vec::new();
+        let mut output = None;
+        let mut drop = HashSet::new();
+        let mut drop_ids = HashSet::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match arg.as_str() {
+                "--output" => output = Some(PathBuf::from(value("--output")?)),
+                "--drop" => {
+                    let spec = value("--drop")?;
+                    let position = spec
+                        .split_once(':')
+                        .and_then(|(input, index)| {
+                            Some(Position {
+                                input: input.parse().ok()?,
+                                index: index.parse().ok()?,
+                            })
+                        })
+                        .ok_or_else(|| format!("--drop expects <INPUT>:<INDEX>, got '{}'", spec))?;
+                    drop.insert(position);
+                }
+                "--drop-id" => {
+                    drop_ids.insert(value("--drop-id")?);
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path => inputs.push(PathBuf::from(path)),
+            }
+        }
+
+        if inputs.is_empty() {
+            return Err("missing input segment paths".into());
+        }
+        if let Some(p) = drop.iter().find(|p| p.input >= inputs.len()) {
+            return Err(format!(
+                "--drop names input {}, but there are only {}",
+                p.input,
+                inputs.len()
+            ));
+        }
+        Ok(Self {
+            inputs,
+            output: output.ok_or("missing --output")?,
+            drop,
+            drop_ids,
+        })
+    }
+}
+
+fn run_merge(args: MergeArgs) -> Result<(), String> {
+    let report = compaction::merge_segments(&args.inputs, &args.output, |p, id| {
+        args.drop.contains(&p) || id.is_some_and(|id| args.drop_ids.contains(id))
+    })
+    .map_err(|e| e.to_string())?;
+    println!(
+        "Merged {} segments into {}: {} vectors written, {} dropped, {} bytes",
+        report.inputs,
+        args.output.display(),
+        report.written,
+        report.dropped,
+        report.bytes
+    );
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DEFRAG
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct DefragArgs {
+    segment: PathBuf,
+    drop: HashSet<u64>,
+    drop_ids: HashSet<String>,
+}
+
+impl DefragArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut segment = None;
+        let mut drop = HashSet::new();
+        let mut drop_ids = HashSet::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match arg.as_str() {
+                "--drop" => {
+                    let index = value("--drop")?;
+                    drop.insert(
+                        index
+                            .parse()
+                            .map_err(|_| format!("--drop expects an index, got '{}'", index))?,
+                    );
+                }
+                "--drop-id" => {
+                    drop_ids.insert(value("--drop-id")?);
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if segment.is_none() => segment = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            segment: segment.ok_or("missing segment path")?,
+            drop,
+            drop_ids,
+        })
+    }
+}
+
+fn run_defrag(args: DefragArgs) -> Result<(), String> {
+    let report = compaction::defragment_segment(&args.segment, |index, id| {
+        args.drop.contains(&index) || id.is_some_and(|id| args.drop_ids.contains(id))
+    })
+    .map_err(|e| e.to_string())?;
+    if report.dropped == 0 {
+        println!("Nothing to drop in {}", args.segment.display());
+        return Ok(());
+    }
+    println!(
+        "Defragmented {}: {} of {} vectors dropped, {} -> {} bytes",
+        args.segment.display(),
+        report.dropped,
+        report.read,
+        report.bytes_before,
+        report.bytes
+    );
+    if !report.dropped_sections.is_empty() {
+        println!("Dropped sections: {}", report.dropped_sections.join(", "));
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCAN
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct ScanArgs {
+    segment: PathBuf,
+    query: Vec<f32>,
+    top_k: usize,
+    metric: DistanceMetric,
+}
+
+impl ScanArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut segment = None;
+        let mut query = None;
+        let mut top_k = 10;
+        let mut metric = DistanceMetric::Cosine;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match arg.as_str() {
+                "--query" => {
+                    let list = value("--query")?;
+                    let parsed: Result<Vec<f32>, _> =
+                        list.split(',').map(|x| x.trim().parse::<f32>()).collect();
+                    query = Some(parsed.map_err(|_| {
+                        format!("--query expects comma-separated numbers, got '{}'", list)
+                    })?);
+                }
+                "--top-k" => {
+                    let k = value("--top-k")?;
+                    top_k = k.parse().ok().filter(|&k| k > 0).ok_or_else(|| {
+                        format!("--top-k must be a positive integer, got '{}'", k)
+                    })?;
+                }
+                "--metric" => {
+                    metric = metric::parse(&value("--metric")?).map_err(|e| e.to_string())?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if segment.is_none() => segment = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            segment: segment.ok_or("missing segment path")?,
+            query: query.ok_or("--query is required")?,
+            top_k,
+            metric,
+        })
+    }
+}
+
+fn run_scan(args: ScanArgs) -> Result<(), String> {
+    let scan = norms::scan_segment(&args.segment, &args.query, args.metric, args.top_k)
+        .map_err(|e| format!("{}: {}", args.segment.display(), e))?;
+    for (rank, hit) in scan.hits.iter().enumerate() {
+        println!("{:>4}  {:<24} {:.6}", rank + 1, hit.id, hit.score);
+    }
+    println!(
+        "Scanned {} of {} blocks, {} skipped by norm bounds",
+        scan.blocks - scan.skipped,
+        scan.blocks,
+        scan.skipped
+    );
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONVERT
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct ConvertArgs {
+    input: PathBuf,
+    output: PathBuf,
+    limit: Option<u64>,
+    array: Option<String>,
+}
+
+impl ConvertArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut input = None;
+        let mut output = None;
+        let mut limit = None;
+        let mut array = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match arg.as_str() {
+                "--output" => output = Some(PathBuf::from(value("--output")?)),
+                "--array" => array = Some(value("--array")?),
+                "--limit" => {
+                    let n = value("--limit")?;
+                    limit = Some(
+                        n.parse()
+                            .map_err(|_| format!("--limit expects a count, got '{}'", n))?,
+                    );
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if input.is_none() => input = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            input: input.ok_or("missing input path")?,
+            output: output.ok_or("missing --output")?,
+            limit,
+            array,
+        })
+    }
+}
+
+/// A non-segment file `convert` understands, by extension
+#[derive(Debug, Clone, Copy)]
+enum Dataset {
+    Vecs(VecsFormat),
+    Npy,
+    Npz,
+}
+
+impl Dataset {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "npy" => Some(Self::Npy),
+            "npz" => Some(Self::Npz),
+            _ => VecsFormat::from_path(path).map(Self::Vecs),
+        }
+    }
+}
+
+fn run_convert(args: ConvertArgs) -> Result<(), String> {
+    if args.array.is_some() && !matches!(Dataset::from_path(&args.input), Some(Dataset::Npz)) {
+        return Err("--array only applies to an .npz input".into());
+    }
+    let report = match (
+        Dataset::from_path(&args.input),
+        Dataset::from_path(&args.output),
+    ) {
+        (Some(Dataset::Vecs(format)), None) => {
+            vecs::vecs_to_segment(&args.input, format, &args.output, args.limit)
+        }
+        (Some(Dataset::Npy), None) => npy::npy_to_segment(&args.input, &args.output, args.limit),
+        (Some(Dataset::Npz), None) => {
+            npy::npz_to_segment(&args.input, args.array.as_deref(), &args.output, args.limit)
+        }
+        (None, Some(Dataset::Vecs(format))) => {
+            vecs::segment_to_vecs(&args.input, &args.output, format, args.limit)
+        }
+        (None, Some(Dataset::Npy)) => npy::segment_to_npy(&args.input, &args.output, args.limit),
+        (None, Some(Dataset::Npz)) => {
+            return Err("can't write .npz; export to .npy and np.savez it if needed".into())
+        }
+        (Some(_), Some(_)) => {
+            return Err("one side of a conversion must be a segment, not both datasets".into())
+        }
+        (None, None) => {
+            return Err(
+                "one side of a conversion must end in .fvecs, .bvecs, .ivecs, .npy or .npz".into(),
+            )
+        }
+    }
+    .map_err(|e| e.to_string())?;
+    println!(
+        "Converted {} to {}: {} vectors of dimension {}, {} bytes",
+        args.input.display(),
+        args.output.display(),
+        report.vectors,
+        report.dimension,
+        report.bytes
+    );
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// REPLAY
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct ReplayArgs {
+    log: PathBuf,
+    target: String,
+    speed: f64,
+    output: Option<PathBuf>,
+}
+
+impl ReplayArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut log = None;
+        let mut target = None;
+        let mut speed = 1.0;
+        let mut output = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            match arg.as_str() {
+                "--target" => target = Some(value("--target")?),
+                "--speed" => {
+                    let s = value("--speed")?;
+                    speed = s
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|x| *x >= 0.0)
+                        .ok_or_else(|| format!("--speed must be a number >= 0, got '{}'", s))?;
+                }
+                "--output" => output = Some(PathBuf::from(value("--output")?)),
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                path if log.is_none() => log = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument '{}'", extra)),
+            }
+        }
+
+        Ok(Self {
+            log: log.ok_or("missing query log path")?,
+            target: target
+                .ok_or("missing --target")?
+                .trim_end_matches('/')
+                .to_string(),
+            speed,
+            output,
+        })
+    }
+}
+
+/// Outcome of one replayed request
+struct Replayed {
+    path: String,
+    status: Option<u16>,
+    latency: Duration,
+    response: serde_json::Value,
+}
+
+async fn replay(args: ReplayArgs) -> Result<(), String> {
+    let entries = querylog::read_log(&args.log).map_err(|e| e.to_string())?;
+    let Some(first) = entries.first().map(|e| e.offset_ms) else {
+        println!("Query log is empty, nothing to replay");
+        return Ok(());
+    };
+    println!(
+        "Replaying {} requests against {} at {}x",
+        entries.len(),
+        args.target,
+        if args.speed == 0.0 {
+            "max".to_string()
+        } else {
+            args.speed.to_string()
+        }
+    );
+
+    // Each request runs on its own task so a slow response doesn't delay
+    // the ones scheduled after it
+    let client = reqwest::Client::new();
+    let start = Instant::now();
+    let mut handles = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let due = querylog::replay_delay(&entry, first, args.speed);
+        tokio::time::sleep_until((start + due).into()).await;
+        let client = client.clone();
+        let url = format!("{}{}", args.target, entry.path);
+        handles.push(tokio::spawn(send(client, url, entry)));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.map_err(|e| e.to_string())?);
+    }
+    let wall_time = start.elapsed();
+
+    if let Some(path) = &args.output {
+        write_results(path, &results).map_err(|e| e.to_string())?;
+        println!("Responses written to {}", path.display());
+    }
+    print_summary(&results, wall_time);
+    Ok(())
+}
+
+async fn send(client: reqwest::Client, url: String, entry: LoggedQuery) -> Replayed {
+    let sent = Instant::now();
+    let outcome = client.post(&url).json(&entry.body).send().await;
+    let (status, response) = match outcome {
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let body = resp.json().await.unwrap_or(serde_json::Value::Null);
+            (Some(status), body)
+        }
+        Err(e) => (None, serde_json::json!({ "error": e.to_string() })),
+    };
+
+    Replayed {
+        path: entry.path,
+        status,
+        latency: sent.elapsed(),
+        response,
+    }
+}
+
+fn write_results(path: &PathBuf, results: &[Replayed]) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    for r in results {
+        let line = serde_json::json!({
+            "path": r.path,
+            "status": r.status,
+            "latency_ms": r.latency.as_secs_f64() * 1000.0,
+            "response": r.response,
+        });
+        writeln!(file, "{}", line)?;
+    }
+    file.flush()
+}
+
+fn print_summary(results: &[Replayed], wall_time: Duration) {
+    let ok = results
+        .iter()
+        .filter(|r| matches!(r.status, Some(200..=299)))
+        .count();
+    let failed_to_send = results.iter().filter(|r| r.status.is_none()).count();
+
+    let mut latencies: Vec<Duration> = results.iter().map(|r| r.latency).collect();
+    latencies.sort();
+    let percentile = |p: f64| {
+        let i = ((latencies.len() as f64 * p).ceil() as usize).saturating_sub(1);
+        latencies[i.min(latencies.len() - 1)]
+    };
+
+    println!();
+    println!("Requests:   {}", results.len());
+    println!("Succeeded:  {}", ok);
+    println!("HTTP error: {}", results.len() - ok - failed_to_send);
+    println!("No reply:   {}", failed_to_send);
+    println!("Wall time:  {:.2?}", wall_time);
+    println!(
+        "Latency:    p50 {:.2?}  p95 {:.2?}  p99 {:.2?}  max {:.2?}",
+        percentile(0.50),
+        percentile(0.95),
+        percentile(0.99),
+        percentile(1.0)
+    );
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// BENCH
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The standard query mix: (top_k, share out of 10)
+const QUERY_MIX: [(usize, u64); 3] = [(10, 7), (100, 2), (1, 1)];
+
+/// Largest upsert body to send; the server rejects bodies over 2 MiB
+const MAX_BATCH_BYTES: usize = 1_500_000;
+
+#[derive(Debug)]
+struct BenchArgs {
+    target: String,
+    spec: DatasetSpec,
+    collection: String,
+    queries: u64,
+    concurrency: usize,
+    recall_queries: u64,
+}
+
+impl BenchArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut bench = Self {
+            target: "http://localhost:3000".to_string(),
+            spec: DatasetSpec::default(),
+            collection: "bench".to_string(),
+            queries: 1000,
+            concurrency: 8,
+            recall_queries: 100,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            fn parse<T: std::str::FromStr>(name: &str, s: String) -> Result<T, String> {
+                s.parse::<T>()
+                    .map_err(|_| format!("{} got an invalid value '{}'", name, s))
+            }
+            match arg.as_str() {
+                "--target" => bench.target = value("--target")?,
+                "--dim" => bench.spec.dimension = parse("--dim", value("--dim")?)?,
+                "--count" => bench.spec.count = parse("--count", value("--count")?)?,
+                "--distribution" => bench.spec.distribution = value("--distribution")?.parse()?,
+                "--clusters" => bench.spec.clusters = parse("--clusters", value("--clusters")?)?,
+                "--spread" => bench.spec.spread = parse("--spread", value("--spread")?)?,
+                "--seed" => bench.spec.seed = parse("--seed", value("--seed")?)?,
+                "--collection" => bench.collection = value("--collection")?,
+                "--queries" => bench.queries = parse("--queries", value("--queries")?)?,
+                "--concurrency" => {
+                    bench.concurrency = parse("--concurrency", value("--concurrency")?)?
+                }
+                "--recall-queries" => {
+                    bench.recall_queries = parse("--recall-queries", value("--recall-queries")?)?
+                }
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+
+        if bench.spec.count == 0 {
+            return Err("--count must be at least 1".into());
+        }
+        if bench.concurrency == 0 {
+            return Err("--concurrency must be at least 1".into());
+        }
+        bench.target = bench.target.trim_end_matches('/').to_string();
+        bench.recall_queries = bench.recall_queries.min(bench.queries);
+        Ok(bench)
+    }
+}
+
+/// Outcome of one benchmark query
+struct Timed {
+    query: u64,
+    top_k: usize,
+    latency: Duration,
+    /// Returned IDs, None if the request failed
+    ids: Option<Vec<String>>,
+}
+
+async fn bench(args: BenchArgs) -> Result<(), String> {
+    let generator =
+        std::sync::Arc::new(Generator::new(args.spec.clone()).map_err(|e| e.to_string())?);
+    let spec = &args.spec;
+    match spec.distribution {
+        Distribution::Gaussian => println!(
+            "Dataset:   {} x {}, gaussian, seed {}",
+            spec.count, spec.dimension, spec.seed
+        ),
+        Distribution::Clustered => println!(
+            "Dataset:   {} x {}, clustered ({} clusters, spread {}), seed {}",
+            spec.count, spec.dimension, spec.clusters, spec.spread, spec.seed
+        ),
+    }
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("{}/api/collections", args.target))
+        .json(&serde_json::json!({
+            "name": args.collection,
+            "dimension": spec.dimension,
+            "distance": "cosine",
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    match resp.status().as_u16() {
+        200..=299 => {}
+        409 => {
+            return Err(format!(
+                "collection '{}' already exists; pick another with --collection",
+                args.collection
+            ))
+        }
+        status => {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!(
+                "creating the collection failed ({}): {}",
+                status, body
+            ));
+        }
+    }
+
+    // Load
+    let batch = (MAX_BATCH_BYTES / (spec.dimension * 13)).clamp(1, 1000) as u64;
+    let url = format!("{}/api/collections/{}/points", args.target, args.collection);
+    println!(
+        "Loading into '{}' at {} ({} per request, {} at a time)",
+        args.collection, args.target, batch, args.concurrency
+    );
+    let start = Instant::now();
+    let mut tasks = JoinSet::new();
+    let mut loaded = 0u64;
+    for first in (0..spec.count).step_by(batch as usize) {
+        if tasks.len() >= args.concurrency {
+            loaded += join_load(&mut tasks).await?;
+        }
+        let last = (first + batch).min(spec.count);
+        let (client, url, generator) = (client.clone(), url.clone(), generator.clone());
+        tasks.spawn(async move {
+            let points: Vec<serde_json::Value> = (first..last)
+                .map(|i| serde_json::json!({ "id": point_id(i), "vector": generator.vector(i) }))
+                .collect();
+            let resp = client
+                .post(&url)
+                .json(&serde_json::json!({ "points": points }))
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !resp.status().is_success() {
+                let status = resp.status().as_u16();
+                let body = resp.text().await.unwrap_or_default();
+                return Err(format!("upsert failed ({}): {}", status, body));
+            }
+            Ok(last - first)
+        });
+    }
+    while !tasks.is_empty() {
+        loaded += join_load(&mut tasks).await?;
+    }
+    let load_time = start.elapsed();
+    println!(
+        "Loaded:    {} vectors in {:.2?} ({:.0} vectors/s)",
+        loaded,
+        load_time,
+        loaded as f64 / load_time.as_secs_f64()
+    );
+
+    // Query mix
+    let url = format!("{}/api/collections/{}/search", args.target, args.collection);
+    let start = Instant::now();
+    let mut tasks = JoinSet::new();
+    let mut results = Vec::with_capacity(args.queries as usize);
+    for query in 0..args.queries {
+        if tasks.len() >= args.concurrency {
+            results.push(join_query(&mut tasks).await?);
+        }
+        let top_k = top_k_for(query);
+        let (client, url, vector) = (client.clone(), url.clone(), generator.query(query));
+        tasks.spawn(async move {
+            let sent = Instant::now();
+            let body = serde_json::json!({ "vector": vector, "top_k": top_k });
+            let ids = match client.post(&url).json(&body).send().await {
+                Ok(resp) if resp.status().is_success() => resp
+                    .json::<Vec<SearchResult>>()
+                    .await
+                    .ok()
+                    .map(|hits| hits.into_iter().map(|h| h.id).collect()),
+                _ => None,
+            };
+            Timed {
+                query,
+                top_k,
+                latency: sent.elapsed(),
+                ids,
+            }
+        });
+    }
+    while !tasks.is_empty() {
+        results.push(join_query(&mut tasks).await?);
+    }
+    let query_time = start.elapsed();
+    results.sort_by_key(|r| r.query);
+
+    let failed = results.iter().filter(|r| r.ids.is_none()).count();
+    println!(
+        "Queries:   {} at concurrency {} in {:.2?} ({:.1} QPS, {} failed)",
+        results.len(),
+        args.concurrency,
+        query_time,
+        results.len() as f64 / query_time.as_secs_f64(),
+        failed
+    );
+    for (top_k, _) in QUERY_MIX {
+        let mut latencies: Vec<Duration> = results
+            .iter()
+            .filter(|r| r.top_k == top_k)
+            .map(|r| r.latency)
+            .collect();
+        if latencies.is_empty() {
+            continue;
+        }
+        latencies.sort();
+        let percentile = |p: f64| {
+            let i = ((latencies.len() as f64 * p).ceil() as usize).saturating_sub(1);
+            latencies[i.min(latencies.len() - 1)]
+        };
+        println!(
+            "  top_k={:<4} {:>6} queries  p50 {:.2?}  p95 {:.2?}  p99 {:.2?}  max {:.2?}",
+            top_k,
+            latencies.len(),
+            percentile(0.50),
+            percentile(0.95),
+            percentile(0.99),
+            percentile(1.0)
+        );
+    }
+
+    // Recall against exact neighbours
+    if args.recall_queries > 0 {
+        println!(
+            "Computing exact neighbours for {} queries...",
+            args.recall_queries
+        );
+        let queries: Vec<(Vec<f32>, usize)> = (0..args.recall_queries)
+            .map(|q| (generator.query(q), top_k_for(q)))
+            .collect();
+        let truth = tokio::task::spawn_blocking(move || exact_neighbours(&generator, &queries))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for (top_k, _) in QUERY_MIX {
+            let recalls: Vec<f64> = results
+                .iter()
+                .zip(&truth)
+                .filter(|(r, _)| r.top_k == top_k)
+                .filter_map(|(r, exact)| {
+                    let ids = r.ids.as_ref()?;
+                    let found = exact.iter().filter(|id| ids.contains(id)).count();
+                    Some(found as f64 / exact.len().max(1) as f64)
+                })
+                .collect();
+            if !recalls.is_empty() {
+                println!(
+                    "  recall@{:<4} {:.4} over {} queries",
+                    top_k,
+                    recalls.iter().sum::<f64>() / recalls.len() as f64,
+                    recalls.len()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn point_id(i: u64) -> String {
+    format!("v{}", i)
+}
+
+/// Query type of the i-th query, spread evenly through the run
+fn top_k_for(query: u64) -> usize {
+    let mut slot = query % 10;
+    for (top_k, share) in QUERY_MIX {
+        if slot < share {
+            return top_k;
+        }
+        slot -= share;
+    }
+    unreachable!("QUERY_MIX shares add up to 10")
+}
+
+async fn join_load(tasks: &mut JoinSet<Result<u64, String>>) -> Result<u64, String> {
+    match tasks.join_next().await {
+        Some(joined) => joined.map_err(|e| e.to_string())?,
+        None => Ok(0),
+    }
+}
+
+async fn join_query(tasks: &mut JoinSet<Timed>) -> Result<Timed, String> {
+    tasks
+        .join_next()
+        .await
+        .expect("only called with queries in flight")
+        .map_err(|e| e.to_string())
+}
+
+/// Exact top-k IDs for each (query, k), regenerating the dataset in chunks
+/// on every core rather than holding it in memory
+fn exact_neighbours(generator: &Generator, queries: &[(Vec<f32>, usize)]) -> Vec<Vec<String>> {
+    const CHUNK: u64 = 4096;
+    let metric = DistanceMetric::Cosine;
+    let count = generator.spec().count;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let per_thread = ((count + threads - 1) / threads).max(1);
+    // Scored together as one matrix product per chunk (see engine/gemm.rs)
+    let query_refs: Vec<&[f32]> = queries.iter().map(|(q, _)| q.as_slice()).collect();
+    let query_refs = &query_refs;
+    let max_k = queries.iter().map(|(_, k)| *k).max().unwrap_or(0);
+
+    let partials: Vec<Vec<Vec<SearchResult>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..count)
+            .step_by(per_thread as usize)
+            .map(|begin| {
+                let end = (begin + per_thread).min(count);
+                scope.spawn(move || {
+                    let mut best = vec![Vec::new(); queries.len()];
+                    for first in (begin..end).step_by(CHUNK as usize) {
+                        let chunk: Vec<(String, Vec<f32>)> = (first..(first + CHUNK).min(end))
+                            .map(|i| (point_id(i), generator.vector(i)))
+                            .collect();
+                        let candidates: Vec<(&str, &[f32])> = chunk
+                            .iter()
+                            .map(|(id, v)| (id.as_str(), v.as_slice()))
+                            .collect();
+                        let hits = gemm::batch_brute_force(query_refs, metric, max_k, &candidates);
+                        for (((_, k), best), hits) in queries.iter().zip(best.iter_mut()).zip(hits)
+                        {
+                            let merged = std::mem::take(best);
+                            *best = search::rank([merged, hits].concat(), metric, *k);
+                        }
+                    }
+                    best
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    (0..queries.len())
+        .map(|q| {
+            let all: Vec<SearchResult> = partials.iter().flat_map(|p| p[q].clone()).collect();
+            search::rank(all, metric, queries[q].1)
+                .into_iter()
+                .map(|r| r.id)
+                .collect()
+        })
+        .collect()
+}
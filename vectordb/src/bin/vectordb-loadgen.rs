@@ -0,0 +1,549 @@
+// src/bin/vectordb-loadgen.rs
+//
+// Closed-loop load generator for capacity testing.
+//
+// `--concurrency` workers each send one request, wait for the reply, and
+// send the next, until `--duration` is up. Operations are interleaved so
+// that exactly `--write-ratio` of them are writes (upserts of `--batch`
+// new points) and the rest are reads (top-k searches). Vectors come from
+// the synthetic generator (src/synthetic.rs), so reads land near the
+// written data.
+//
+// Throughput is printed every `--report-interval` seconds; at the end
+// every operation type gets percentiles and a latency histogram. Latencies
+// are kept in log-linear buckets (16 per power of two), so percentiles are
+// within ~6% however long the run is.
+//
+// Run with: cargo run --release --bin vectordb-loadgen -- --target http://staging:3000 \
+//               --concurrency 64 --write-ratio 0.1 --dim 768 --duration 300
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use vectordb::synthetic::{DatasetSpec, Generator};
+
+const USAGE: &str = "\
+Usage: vectordb-loadgen [options]
+
+Options:
+  --target <URL>           Server to load (default http://localhost:3000)
+  --collection <NAME>      Collection to use, created if missing (default loadgen)
+  --dim <N>                Vector dimension (default 128)
+  --concurrency <N>        Requests in flight (default 16)
+  --duration <SECS>        How long to run (default 30)
+  --write-ratio <X>        Fraction of operations that are writes, 0..=1 (default 0.2)
+  --batch <N>              Points per write (default 1)
+  --top-k <N>              Results per search (default 10)
+  --preload <N>            Points written before measuring (default 10000)
+  --report-interval <SECS> Progress line interval, 0 = none (default 5)
+  --seed <N>               Dataset seed (default 42)";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let result = match Args::parse(&args) {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ARGUMENTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct Args {
+    target: String,
+    collection: String,
+    dimension: usize,
+    concurrency: usize,
+    duration: Duration,
+    write_ratio: f64,
+    batch: u64,
+    top_k: usize,
+    preload: u64,
+    report_interval: Duration,
+    seed: u64,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Self {
+            target: "http://localhost:3000".to_string(),
+            collection: "loadgen".to_string(),
+            dimension: 128,
+            concurrency: 16,
+            duration: Duration::from_secs(30),
+            write_ratio: 0.2,
+            batch: 1,
+            top_k: 10,
+            preload: 10_000,
+            report_interval: Duration::from_secs(5),
+            seed: 42,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |name: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", name))
+            };
+            fn parse<T: std::str::FromStr>(name: &str, s: String) -> Result<T, String> {
+                s.parse::<T>()
+                    .map_err(|_| format!("{} got an invalid value '{}'", name, s))
+            }
+            match arg.as_str() {
+                "--target" => parsed.target = value("--target")?,
+                "--collection" => parsed.collection = value("--collection")?,
+                "--dim" => parsed.dimension = parse("--dim", value("--dim")?)?,
+                "--concurrency" => {
+                    parsed.concurrency = parse("--concurrency", value("--concurrency")?)?
+                }
+                "--duration" => {
+                    parsed.duration =
+                        Duration::from_secs(parse("--duration", value("--duration")?)?)
+                }
+                "--write-ratio" => {
+                    parsed.write_ratio = parse("--write-ratio", value("--write-ratio")?)?
+                }
+                "--batch" => parsed.batch = parse("--batch", value("--batch")?)?,
+                "--top-k" => parsed.top_k = parse("--top-k", value("--top-k")?)?,
+                "--preload" => parsed.preload = parse("--preload", value("--preload")?)?,
+                "--report-interval" => {
+                    parsed.report_interval = Duration::from_secs(parse(
+                        "--report-interval",
+                        value("--report-interval")?,
+                    )?)
+                }
+                "--seed" => parsed.seed = parse("--seed", value("--seed")?)?,
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+
+        if parsed.concurrency == 0 {
+            return Err("--concurrency must be at least 1".into());
+        }
+        if parsed.duration.is_zero() {
+            return Err("--duration must be at least 1 second".into());
+        }
+        if !(0.0..=1.0).contains(&parsed.write_ratio) {
+            return Err(format!(
+                "--write-ratio must be between 0 and 1, got {}",
+                parsed.write_ratio
+            ));
+        }
+        if parsed.batch == 0 || parsed.top_k == 0 {
+            return Err("--batch and --top-k must be at least 1".into());
+        }
+        parsed.target = parsed.target.trim_end_matches('/').to_string();
+        Ok(parsed)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// LATENCY HISTOGRAM
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Sub-buckets per power of two (log2)
+const SUB_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BITS;
+
+/// Upper bounds (ms) of the rows in the printed histogram
+const DISPLAY_BOUNDS_MS: [f64; 14] = [
+    0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0,
+];
+
+/// Log-linear histogram of latencies in microseconds
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+    errors: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn bucket(us: u64) -> usize {
+        if us < SUB_BUCKETS {
+            return us as usize;
+        }
+        let exp = 63 - us.leading_zeros();
+        let sub = (us >> (exp - SUB_BITS)) & (SUB_BUCKETS - 1);
+        ((u64::from(exp - SUB_BITS + 1) << SUB_BITS) + sub) as usize
+    }
+
+    /// Largest value that lands in `bucket`
+    fn upper_bound(bucket: usize) -> u64 {
+        let bucket = bucket as u64;
+        if bucket < SUB_BUCKETS {
+            return bucket;
+        }
+        let exp = (bucket >> SUB_BITS) + u64::from(SUB_BITS) - 1;
+        let sub = bucket & (SUB_BUCKETS - 1);
+        ((SUB_BUCKETS + sub + 1) << (exp - u64::from(SUB_BITS))) - 1
+    }
+
+    fn record(&mut self, latency: Duration) {
+        let us = latency.as_micros().min(u128::from(u64::MAX)) as u64;
+        let bucket = Self::bucket(us);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+        self.total += 1;
+        self.max_us = self.max_us.max(us);
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        if self.counts.len() < other.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            *mine += theirs;
+        }
+        self.total += other.total;
+        self.errors += other.errors;
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        let rank = ((self.total as f64 * p).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(Self::upper_bound(bucket).min(self.max_us));
+            }
+        }
+        Duration::from_micros(self.max_us)
+    }
+
+    fn print(&self, name: &str) {
+        println!("\n{} latency:", name);
+        let mut rows = vec![0u64; DISPLAY_BOUNDS_MS.len() + 1];
+        for (bucket, count) in self.counts.iter().enumerate() {
+            let ms = Self::upper_bound(bucket) as f64 / 1000.0;
+            let row = DISPLAY_BOUNDS_MS
+                .iter()
+                .position(|bound| ms <= *bound)
+                .unwrap_or(DISPLAY_BOUNDS_MS.len());
+            rows[row] += count;
+        }
+        let widest = rows.iter().copied().max().unwrap_or(0).max(1);
+        let last = rows.iter().rposition(|c| *c > 0).unwrap_or(0);
+        let first = rows.iter().position(|c| *c > 0).unwrap_or(0);
+        for (row, count) in rows.iter().enumerate().take(last + 1).skip(first) {
+            let label = match DISPLAY_BOUNDS_MS.get(row) {
+                Some(bound) => format!("<= {}ms", bound),
+                None => format!("> {}ms", DISPLAY_BOUNDS_MS[row - 1]),
+            };
+            let bar = "#".repeat((count * 50 / widest) as usize);
+            println!("  {:>10} {:>9} {}", label, count, bar);
+        }
+    }
+}
+
+/// Latencies per operation type, shared by the workers
+#[derive(Debug, Default)]
+struct Stats {
+    reads: Histogram,
+    writes: Histogram,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RUN
+// ═══════════════════════════════════════════════════════════════════════════
+
+async fn run(args: Args) -> Result<(), String> {
+    let args = Arc::new(args);
+    let generator = Arc::new(
+        Generator::new(DatasetSpec {
+            dimension: args.dimension,
+            count: args.preload,
+            seed: args.seed,
+            ..DatasetSpec::default()
+        })
+        .map_err(|e| e.to_string())?,
+    );
+    let client = reqwest::Client::new();
+    ensure_collection(&client, &args).await?;
+
+    // Point IDs are allocated from one counter, preload first
+    let next_point = Arc::new(AtomicU64::new(0));
+    if args.preload > 0 {
+        println!("Preloading {} points...", args.preload);
+        let batch = (1_500_000 / (args.dimension * 13)).clamp(1, 1000) as u64;
+        let mut first = 0;
+        while first < args.preload {
+            let count = batch.min(args.preload - first);
+            write(&client, &args, &generator, first, count).await?;
+            first += count;
+        }
+        next_point.store(args.preload, Ordering::Relaxed);
+    }
+
+    println!(
+        "Running {:?} against {} ('{}', {} dims): concurrency {}, {:.0}% writes of {} point(s), top_k {}",
+        args.duration,
+        args.target,
+        args.collection,
+        args.dimension,
+        args.concurrency,
+        args.write_ratio * 100.0,
+        args.batch,
+        args.top_k
+    );
+
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    let next_op = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let start = Instant::now();
+    let mut workers = JoinSet::new();
+    for _ in 0..args.concurrency {
+        let (client, args, generator) = (client.clone(), args.clone(), generator.clone());
+        let (stats, next_op, next_point, stop) = (
+            stats.clone(),
+            next_op.clone(),
+            next_point.clone(),
+            stop.clone(),
+        );
+        workers.spawn(async move {
+            while !stop.load(Ordering::Relaxed) {
+                let op = next_op.fetch_add(1, Ordering::Relaxed);
+                // Op n is a write when floor(n * ratio) steps up: exact mix,
+                // evenly spread
+                let is_write = ((op + 1) as f64 * args.write_ratio).floor()
+                    > (op as f64 * args.write_ratio).floor();
+                let sent = Instant::now();
+                let outcome = if is_write {
+                    let first = next_point.fetch_add(args.batch, Ordering::Relaxed);
+                    write(&client, &args, &generator, first, args.batch).await
+                } else {
+                    read(&client, &args, &generator, op).await
+                };
+                let latency = sent.elapsed();
+
+                let mut stats = stats.lock().unwrap();
+                let histogram = if is_write {
+                    &mut stats.writes
+                } else {
+                    &mut stats.reads
+                };
+                match outcome {
+                    Ok(()) => histogram.record(latency),
+                    Err(_) => histogram.errors += 1,
+                }
+            }
+        });
+    }
+
+    // Progress lines until the deadline
+    let deadline = start + args.duration;
+    let mut last = (Instant::now(), 0u64, 0u64);
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        let wait = if args.report_interval.is_zero() {
+            deadline - now
+        } else {
+            args.report_interval.min(deadline - now)
+        };
+        tokio::time::sleep(wait).await;
+        if args.report_interval.is_zero() {
+            continue;
+        }
+        let (reads, writes, errors) = {
+            let stats = stats.lock().unwrap();
+            (
+                stats.reads.total,
+                stats.writes.total,
+                stats.reads.errors + stats.writes.errors,
+            )
+        };
+        let elapsed = last.0.elapsed().as_secs_f64();
+        println!(
+            "[{:>5.0}s] {:>8.1} ops/s  (reads {:.1}/s, writes {:.1}/s)  errors {}",
+            start.elapsed().as_secs_f64(),
+            (reads + writes - last.1 - last.2) as f64 / elapsed,
+            (reads - last.1) as f64 / elapsed,
+            (writes - last.2) as f64 / elapsed,
+            errors
+        );
+        last = (Instant::now(), reads, writes);
+    }
+    stop.store(true, Ordering::Relaxed);
+    while workers.join_next().await.is_some() {}
+    let wall_time = start.elapsed();
+
+    let stats = stats.lock().unwrap();
+    let mut all = Histogram::default();
+    all.merge(&stats.reads);
+    all.merge(&stats.writes);
+    println!("\nSummary over {:.2?}:", wall_time);
+    for (name, histogram) in [
+        ("reads", &stats.reads),
+        ("writes", &stats.writes),
+        ("all", &all),
+    ] {
+        if histogram.total + histogram.errors == 0 {
+            continue;
+        }
+        println!(
+            "  {:<6} {:>9} ok {:>6} errors {:>9.1} ops/s  p50 {:.2?}  p90 {:.2?}  p99 {:.2?}  p99.9 {:.2?}  max {:.2?}",
+            name,
+            histogram.total,
+            histogram.errors,
+            histogram.total as f64 / wall_time.as_secs_f64(),
+            histogram.percentile(0.50),
+            histogram.percentile(0.90),
+            histogram.percentile(0.99),
+            histogram.percentile(0.999),
+            Duration::from_micros(histogram.max_us)
+        );
+    }
+    for (name, histogram) in [("Read", &stats.reads), ("Write", &stats.writes)] {
+        if histogram.total > 0 {
+            histogram.print(name);
+        }
+    }
+    Ok(())
+}
+
+/// Use the collection if it exists with the right dimension, else create it
+async fn ensure_collection(client: &reqwest::Client, args: &Args) -> Result<(), String> {
+    let url = format!("{}/api/collections/{}", args.target, args.collection);
+    let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    if resp.status().is_success() {
+        let info: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+        return match info["dimension"].as_u64() {
+            Some(d) if d as usize == args.dimension => Ok(()),
+            other => Err(format!(
+                "collection '{}' exists with dimension {:?}, not {}",
+                args.collection, other, args.dimension
+            )),
+        };
+    }
+
+    let resp = client
+        .post(format!("{}/api/collections", args.target))
+        .json(&serde_json::json!({ "name": args.collection, "dimension": args.dimension }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        let status = resp.status().as_u16();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!(
+            "creating the collection failed ({}): {}",
+            status, body
+        ));
+    }
+    Ok(())
+}
+
+/// Upsert points `first..first + count`
+async fn write(
+    client: &reqwest::Client,
+    args: &Args,
+    generator: &Generator,
+    first: u64,
+    count: u64,
+) -> Result<(), String> {
+    let points: Vec<serde_json::Value> = (first..first + count)
+        .map(|i| serde_json::json!({ "id": format!("lg{}", i), "vector": generator.vector(i) }))
+        .collect();
+    let url = format!("{}/api/collections/{}/points", args.target, args.collection);
+    check(
+        client
+            .post(url)
+            .json(&serde_json::json!({ "points": points }))
+            .send()
+            .await,
+    )
+}
+
+/// Search with query `i`
+async fn read(
+    client: &reqwest::Client,
+    args: &Args,
+    generator: &Generator,
+    i: u64,
+) -> Result<(), String> {
+    let url = format!("{}/api/collections/{}/search", args.target, args.collection);
+    check(
+        client
+            .post(url)
+            .json(&serde_json::json!({ "vector": generator.query(i), "top_k": args.top_k }))
+            .send()
+            .await,
+    )
+}
+
+fn check(outcome: reqwest::Result<reqwest::Response>) -> Result<(), String> {
+    let resp = outcome.map_err(|e| e.to_string())?;
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", resp.status().as_u16()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_bound_their_values() {
+        for us in [
+            0,
+            1,
+            15,
+            16,
+            17,
+            31,
+            32,
+            100,
+            1_000,
+            65_535,
+            1_000_000,
+            123_456_789,
+        ] {
+            let bucket = Histogram::bucket(us);
+            assert!(Histogram::upper_bound(bucket) >= us, "{}", us);
+            if bucket > 0 {
+                assert!(Histogram::upper_bound(bucket - 1) < us, "{}", us);
+            }
+            // Relative error stays within one sub-bucket
+            assert!(Histogram::upper_bound(bucket) as f64 <= us as f64 * 1.0625 + 1.0);
+        }
+    }
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut h = Histogram::default();
+        for ms in 1..=100 {
+            h.record(Duration::from_millis(ms));
+        }
+        let p50 = h.percentile(0.5).as_secs_f64() * 1000.0;
+        let p99 = h.percentile(0.99).as_secs_f64() * 1000.0;
+        assert!((50.0..=53.2).contains(&p50), "{}", p50);
+        assert!((99.0..=100.0).contains(&p99), "{}", p99);
+        assert_eq!(h.percentile(1.0), Duration::from_millis(100));
+    }
+}
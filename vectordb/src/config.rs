@@ -0,0 +1,1176 @@
+// src/config.rs
+//
+// Server settings: where to listen, where to keep data, which metric to
+// default to.
+//
+// Each setting is taken from the most specific place that names it:
+//
+//   1. a command-line flag          (--port 8080)
+//   2. an environment variable      (VECTORDB_PORT=8080)
+//   3. the config file              (port = 8080)
+//   4. the built-in default         (3000)
+//
+// The config file is TOML or YAML, chosen by its extension, and is only
+// read if named with --config or VECTORDB_CONFIG:
+//
+//   # vectordb.toml
+//   host = "0.0.0.0"
+//   port = 8080
+//   data_dir = "/var/lib/vectordb"
+//   default_metric = "dot"
+//   numa = true
+//   huge_pages = "transparent"
+//   drain_timeout_secs = 25
+//   admin_port = 9090               # admin endpoints and /metrics, apart
+//   wal_sync = "every:100"          # always, never, every:<n>, interval:<ms>
+//   max_fds = 4096                  # below the raised RLIMIT_NOFILE
+//   trash_retention_hours = 168
+//   lock_warn_ms = 500              # log state lock holders past this
+//   memtable_max_bytes = 67108864   # flush a memtable at this size...
+//   memtable_max_age_secs = 300     # ...or this age
+//   cold_after_days = 30            # demote vectors unread this long...
+//   max_hot_vectors = 1000000       # ...or the least-read past this many
+//   tiering_sweep_secs = 3600
+//   grpc_addr = "0.0.0.0:50051"     # in `grpc` builds
+//   max_dimension = 4096
+//   max_radius_results = 1000       # hits per page of a radius search
+//   result_cache_size = 1024        # cache repeated searches (0: off)...
+//   result_cache_ttl_secs = 60      # ...for this long
+//   query_log = "/var/log/vectordb/queries.jsonl"   # see src/querylog.rs
+//   query_log_sample = 0.01
+//   query_log_redact = ["filter"]
+//   api_keys = "/etc/vectordb/keys.json"            # see src/auth.rs
+//   admin_key = "..."               # required by the admin endpoints
+//   udf_fuel = 100000               # in `wasm` builds, see engine/udf.rs
+//
+//   [admin_tls]                     # mutual TLS on admin_port, see src/tls.rs
+//   cert = "/etc/vectordb/admin.pem"
+//   key = "/etc/vectordb/admin.key"
+//   client_ca = "/etc/vectordb/ops-ca.pem"
+//
+//   [oidc]                          # accept access tokens, see src/oidc.rs
+//   issuer = "https://id.example.com"  # keys found by discovery
+//
+//   [cold_store]                    # cold tier in a bucket, see
+//   endpoint = "https://s3.eu-west-1.amazonaws.com"   # storage/object.rs
+//   bucket = "vectordb-cold"
+//   region = "eu-west-1"
+//
+// VECTORDB_ADDR (host:port in one variable) still works and sits with the
+// other environment variables, below VECTORDB_HOST and VECTORDB_PORT.
+// With an admin port, the admin endpoints and /metrics are served only
+// there, on loopback unless admin_host says otherwise, so they stay off
+// whatever fronts the public port.
+// Everything is checked before the server touches the disk, so a typo
+// fails at startup with the setting and its source in the message rather
+// than as a panic later on. Unknown keys in the file are errors too, and
+// the API key file is read and checked here along with everything else.
+
+use crate::auth::ApiKeys;
+use crate::engine::cache::{self, CacheConfig};
+use crate::engine::metric;
+use crate::engine::trash::TrashPolicy;
+use crate::fds;
+use crate::hugepages::HugePages;
+use crate::limits;
+use crate::models::{DistanceMetric, Result, VectorDbError};
+use crate::oidc::OidcConfig;
+use crate::querylog::QueryLogConfig;
+use crate::storage::memtable::FlushPolicy;
+use crate::storage::object::{Credentials, S3Config};
+use crate::storage::tiering::TieringPolicy;
+use crate::storage::wal::SyncPolicy;
+use crate::tls::MtlsConfig;
+use clap::Parser;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Listen host if nothing names one
+pub const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Listen port if nothing names one
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// How long shutdown waits for in-flight requests, if nothing says. Under
+/// Kubernetes' default 30 s grace period, so the flush that follows still
+/// runs before SIGKILL.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// gRPC listen address if nothing names one
+pub const DEFAULT_GRPC_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 50051);
+
+/// Fraction of searches the query log records if nothing says
+pub const DEFAULT_QUERY_LOG_SAMPLE: f64 = 0.01;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SOURCES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Command-line flags, each falling back to its environment variable.
+#[derive(Debug, Clone, Default, Parser)]
+#[command(name = "vectordb", version, about = "VectorDB server")]
+pub struct Args {
+    /// Config file (.toml, .yaml or .yml)
+    #[arg(long, short, env = "VECTORDB_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on
+    #[arg(long, env = "VECTORDB_HOST")]
+    pub host: Option<IpAddr>,
+
+    /// Port to listen on (0 picks a free port)
+    #[arg(long, short, env = "VECTORDB_PORT")]
+    pub port: Option<u16>,
+
+    /// Directory for the snapshot and write-ahead log
+    #[arg(long, env = "VECTORDB_DATA_DIR", value_name = "PATH")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Directory for in-progress resumable uploads
+    #[arg(long, env = "VECTORDB_UPLOAD_DIR", value_name = "PATH")]
+    pub upload_dir: Option<PathBuf>,
+
+    /// Metric for collections and searches that don't name one
+    #[arg(long, env = "VECTORDB_DEFAULT_METRIC", value_name = "METRIC", value_parser = parse_metric)]
+    pub default_metric: Option<DistanceMetric>,
+
+    /// Keep scans on the NUMA node holding their vectors (see numa.rs)
+    #[arg(long, env = "VECTORDB_NUMA", num_args = 0..=1, default_missing_value = "true")]
+    pub numa: Option<bool>,
+
+    /// Back the vector arena with huge pages: off, transparent or explicit
+    /// (see hugepages.rs)
+    #[arg(long, env = "VECTORDB_HUGE_PAGES", value_name = "MODE")]
+    pub huge_pages: Option<HugePages>,
+
+    /// Seconds to wait for in-flight requests on SIGTERM / SIGINT before
+    /// flushing and exiting anyway
+    #[arg(long, env = "VECTORDB_DRAIN_TIMEOUT_SECS", value_name = "SECS")]
+    pub drain_timeout_secs: Option<u64>,
+
+    /// OIDC issuer whose access tokens are accepted
+    #[arg(long, env = "VECTORDB_OIDC_ISSUER", value_name = "URL")]
+    pub oidc_issuer: Option<String>,
+
+    /// The issuer's signing keys, an https:// JWKS URL or a file (default:
+    /// discovered from the issuer)
+    #[arg(long, env = "VECTORDB_OIDC_JWKS", value_name = "URL|PATH")]
+    pub oidc_jwks: Option<String>,
+
+    /// Audience access tokens must be issued for
+    #[arg(long, env = "VECTORDB_OIDC_AUDIENCE")]
+    pub oidc_audience: Option<String>,
+
+    /// Address for the admin listener (with --admin-port; default
+    /// 127.0.0.1)
+    #[arg(long, env = "VECTORDB_ADMIN_HOST")]
+    pub admin_host: Option<IpAddr>,
+
+    /// Serve the admin endpoints and /metrics on this port instead of the
+    /// public one (0 picks a free port)
+    #[arg(long, env = "VECTORDB_ADMIN_PORT")]
+    pub admin_port: Option<u16>,
+
+    /// Serve the admin listener over TLS with this certificate chain (with
+    /// --admin-tls-key and --admin-client-ca)
+    #[arg(long, env = "VECTORDB_ADMIN_TLS_CERT", value_name = "PATH")]
+    pub admin_tls_cert: Option<PathBuf>,
+
+    /// Private key for --admin-tls-cert
+    #[arg(long, env = "VECTORDB_ADMIN_TLS_KEY", value_name = "PATH")]
+    pub admin_tls_key: Option<PathBuf>,
+
+    /// CA bundle admin clients' certificates must chain to
+    #[arg(long, env = "VECTORDB_ADMIN_CLIENT_CA", value_name = "PATH")]
+    pub admin_client_ca: Option<PathBuf>,
+
+    /// When the write-ahead log is fsynced: always, never,
+    /// every:<records> or interval:<ms> (default: always)
+    #[arg(long, env = "VECTORDB_WAL_SYNC", value_name = "POLICY", value_parser = SyncPolicy::parse)]
+    pub wal_sync: Option<SyncPolicy>,
+
+    /// Open file descriptors to budget for, if below the raised
+    /// RLIMIT_NOFILE
+    #[arg(long, env = "VECTORDB_MAX_FDS", value_name = "N")]
+    pub max_fds: Option<u64>,
+
+    /// Hours a deleted collection stays restorable (default: 168; 0 purges
+    /// at the next pass)
+    #[arg(long, env = "VECTORDB_TRASH_RETENTION_HOURS", value_name = "HOURS")]
+    pub trash_retention_hours: Option<u64>,
+
+    /// Log whoever holds the state lock longer than this
+    #[arg(long, env = "VECTORDB_LOCK_WARN_MS", value_name = "MS")]
+    pub lock_warn_ms: Option<u64>,
+
+    /// Flush a collection's memtable once its writes reach this size
+    #[arg(long, env = "VECTORDB_MEMTABLE_MAX_BYTES", value_name = "BYTES")]
+    pub memtable_max_bytes: Option<u64>,
+
+    /// Flush a collection's memtable once its oldest write is this old
+    #[arg(long, env = "VECTORDB_MEMTABLE_MAX_AGE_SECS", value_name = "SECS")]
+    pub memtable_max_age_secs: Option<u64>,
+
+    /// Demote vectors to the cold tier once unread for this many days
+    /// (default: 30)
+    #[arg(long, env = "VECTORDB_COLD_AFTER_DAYS", value_name = "DAYS")]
+    pub cold_after_days: Option<u64>,
+
+    /// Keep at most this many vectors hot, demoting the least-read
+    #[arg(long, env = "VECTORDB_MAX_HOT_VECTORS", value_name = "N")]
+    pub max_hot_vectors: Option<usize>,
+
+    /// Seconds between tiering sweeps (default: 3600)
+    #[arg(long, env = "VECTORDB_TIERING_SWEEP_SECS", value_name = "SECS")]
+    pub tiering_sweep_secs: Option<u64>,
+
+    /// Keep the cold tier in the S3-compatible service at this URL (with
+    /// --cold-store-bucket) instead of the data directory
+    #[arg(long, env = "VECTORDB_COLD_STORE_ENDPOINT", value_name = "URL")]
+    pub cold_store_endpoint: Option<String>,
+
+    /// Bucket for the cold tier
+    #[arg(long, env = "VECTORDB_COLD_STORE_BUCKET")]
+    pub cold_store_bucket: Option<String>,
+
+    /// Region requests are signed for (default: us-east-1)
+    #[arg(long, env = "VECTORDB_COLD_STORE_REGION")]
+    pub cold_store_region: Option<String>,
+
+    /// Prefix for the cold tier's object keys
+    #[arg(long, env = "VECTORDB_COLD_STORE_PREFIX")]
+    pub cold_store_prefix: Option<String>,
+
+    /// Address for the gRPC API, in `grpc` builds (default:
+    /// 127.0.0.1:50051; port 0 picks a free port)
+    #[arg(long, env = "VECTORDB_GRPC_ADDR", value_name = "HOST:PORT")]
+    pub grpc_addr: Option<SocketAddr>,
+
+    /// Largest vector dimension accepted (default: 65536)
+    #[arg(long, env = "VECTORDB_MAX_DIMENSION", value_name = "N")]
+    pub max_dimension: Option<usize>,
+
+    /// Most hits one page of a radius search returns (default: 1000)
+    #[arg(long, env = "VECTORDB_MAX_RADIUS_RESULTS", value_name = "N")]
+    pub max_radius_results: Option<usize>,
+
+    /// Cache up to this many search results (default: 0, off)
+    #[arg(long, env = "VECTORDB_RESULT_CACHE_SIZE", value_name = "N")]
+    pub result_cache_size: Option<usize>,
+
+    /// Seconds a cached search result stays valid (default: 60)
+    #[arg(long, env = "VECTORDB_RESULT_CACHE_TTL_SECS", value_name = "SECS")]
+    pub result_cache_ttl_secs: Option<u64>,
+
+    /// Append a sample of search requests to this file, for replay
+    #[arg(long, env = "VECTORDB_QUERY_LOG", value_name = "PATH")]
+    pub query_log: Option<PathBuf>,
+
+    /// Fraction of searches the query log records (default: 0.01)
+    #[arg(long, env = "VECTORDB_QUERY_LOG_SAMPLE", value_name = "RATE")]
+    pub query_log_sample: Option<f64>,
+
+    /// Request body fields the query log leaves out, comma-separated
+    #[arg(
+        long,
+        env = "VECTORDB_QUERY_LOG_REDACT",
+        value_name = "FIELDS",
+        value_delimiter = ','
+    )]
+    pub query_log_redact: Option<Vec<String>>,
+
+    /// Require one of this file's scoped keys on every request (see
+    /// auth.rs)
+    #[arg(long, env = "VECTORDB_API_KEYS", value_name = "PATH")]
+    pub api_keys: Option<PathBuf>,
+
+    /// Key the admin endpoints require (unset: diagnostics are open and
+    /// profiling is off)
+    #[arg(long, env = "VECTORDB_ADMIN_KEY", hide_env_values = true)]
+    pub admin_key: Option<String>,
+
+    /// Fuel for one UDF call, about that many WASM instructions, in
+    /// `wasm` builds (default: 100000)
+    #[arg(long, env = "VECTORDB_UDF_FUEL", value_name = "N")]
+    pub udf_fuel: Option<u64>,
+
+    /// Listen address as host:port (older form of --host and --port)
+    #[arg(skip = std::env::var("VECTORDB_ADDR").ok())]
+    pub addr: Option<String>,
+
+    /// Keys for the cold store, from the AWS_* variables only
+    #[arg(skip = Credentials::from_env())]
+    pub cold_store_credentials: Option<Credentials>,
+}
+
+fn parse_metric(s: &str) -> std::result::Result<DistanceMetric, String> {
+    metric::parse(s).map_err(|e| match e {
+        VectorDbError::InvalidParameter(message) => message,
+        other => other.to_string(),
+    })
+}
+
+/// The settings a config file may contain.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub host: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub data_dir: Option<PathBuf>,
+    pub upload_dir: Option<PathBuf>,
+    pub default_metric: Option<DistanceMetric>,
+    pub numa: Option<bool>,
+    pub huge_pages: Option<HugePages>,
+    pub drain_timeout_secs: Option<u64>,
+    pub admin_host: Option<IpAddr>,
+    pub admin_port: Option<u16>,
+    pub admin_tls: Option<MtlsConfig>,
+    pub oidc: Option<OidcConfig>,
+    pub wal_sync: Option<SyncPolicy>,
+    pub max_fds: Option<u64>,
+    pub trash_retention_hours: Option<u64>,
+    pub lock_warn_ms: Option<u64>,
+    pub memtable_max_bytes: Option<u64>,
+    pub memtable_max_age_secs: Option<u64>,
+    pub cold_after_days: Option<u64>,
+    pub max_hot_vectors: Option<usize>,
+    pub tiering_sweep_secs: Option<u64>,
+    pub cold_store: Option<S3Config>,
+    pub grpc_addr: Option<SocketAddr>,
+    pub max_dimension: Option<usize>,
+    pub max_radius_results: Option<usize>,
+    pub result_cache_size: Option<usize>,
+    pub result_cache_ttl_secs: Option<u64>,
+    pub query_log: Option<PathBuf>,
+    pub query_log_sample: Option<f64>,
+    pub query_log_redact: Option<Vec<String>>,
+    pub api_keys: Option<PathBuf>,
+    pub admin_key: Option<String>,
+    pub udf_fuel: Option<u64>,
+}
+
+impl FileConfig {
+    /// Parse a config file, picking TOML or YAML by extension
+    pub fn parse(path: &Path, text: &str) -> Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let parsed = match extension {
+            "toml" => toml::from_str(text).map_err(|e| e.message().to_string()),
+            "yaml" | "yml" => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+            _ => Err(format!(
+                "unsupported config format '.{}' (expected .toml, .yaml or .yml)",
+                extension
+            )),
+        };
+        parsed.map_err(|e| {
+            VectorDbError::InvalidParameter(format!("config file {}: {}", path.display(), e))
+        })
+    }
+
+    /// Read and parse a config file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            VectorDbError::InvalidParameter(format!(
+                "can't read config file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::parse(path, &text)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RESOLVED CONFIG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The settings the server runs with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub addr: SocketAddr,
+    pub data_dir: PathBuf,
+    pub upload_dir: PathBuf,
+    pub default_metric: DistanceMetric,
+    pub numa: bool,
+    pub huge_pages: HugePages,
+    pub drain_timeout: Duration,
+    /// Where the admin endpoints and /metrics listen, if apart from `addr`
+    pub admin_addr: Option<SocketAddr>,
+    /// Client certificates the admin listener requires, if set
+    pub admin_tls: Option<MtlsConfig>,
+    /// Access tokens accepted in place of API keys, if set
+    pub oidc: Option<OidcConfig>,
+    pub wal_sync: SyncPolicy,
+    /// Descriptor limit to budget for, if lower than RLIMIT_NOFILE
+    pub max_fds: Option<u64>,
+    pub trash: TrashPolicy,
+    /// Hold time past which state lock holders are logged, if set
+    pub lock_warn: Option<Duration>,
+    pub memtable: FlushPolicy,
+    pub tiering: TieringPolicy,
+    /// Bucket holding the cold tier, if not the data directory
+    pub cold_store: Option<S3Config>,
+    /// Where the gRPC API listens, in `grpc` builds
+    pub grpc_addr: SocketAddr,
+    pub max_dimension: usize,
+    pub max_radius_results: usize,
+    /// Search result cache, if on
+    pub result_cache: Option<CacheConfig>,
+    /// Sampled query log, if on
+    pub query_log: Option<QueryLogConfig>,
+    /// Keys every request needs one of, if set
+    pub api_keys: Option<ApiKeys>,
+    /// Key the admin endpoints require, if set
+    pub admin_key: Option<String>,
+    /// Fuel for one UDF call, if not the default
+    pub udf_fuel: Option<u64>,
+}
+
+impl Config {
+    /// Parse the process's flags and environment, read the config file
+    /// they name (if any), and validate the result
+    pub fn load() -> Result<Self> {
+        let args = Args::parse();
+        let file = match &args.config {
+            Some(path) => FileConfig::load(path)?,
+            None => FileConfig::default(),
+        };
+        Self::resolve(&args, file)
+    }
+
+    /// Layer `args` over `file` over the defaults, then validate
+    pub fn resolve(args: &Args, file: FileConfig) -> Result<Self> {
+        let legacy = match &args.addr {
+            Some(s) => Some(s.parse::<SocketAddr>().map_err(|_| {
+                VectorDbError::InvalidParameter(format!(
+                    "VECTORDB_ADDR must be host:port, got '{}'",
+                    s
+                ))
+            })?),
+            None => None,
+        };
+
+        let host = args
+            .host
+            .or(legacy.map(|a| a.ip()))
+            .or(file.host)
+            .unwrap_or(DEFAULT_HOST);
+        let port = args
+            .port
+            .or(legacy.map(|a| a.port()))
+            .or(file.port)
+            .unwrap_or(DEFAULT_PORT);
+        let admin_host = args.admin_host.or(file.admin_host);
+        let admin_addr = match args.admin_port.or(file.admin_port) {
+            Some(port) => Some(SocketAddr::new(admin_host.unwrap_or(DEFAULT_HOST), port)),
+            None if admin_host.is_some() => {
+                return Err(VectorDbError::InvalidParameter(
+                    "admin_host needs an admin_port".into(),
+                ))
+            }
+            None => None,
+        };
+
+        let memtable_max_bytes = args.memtable_max_bytes.or(file.memtable_max_bytes);
+        let memtable_max_age_secs = args.memtable_max_age_secs.or(file.memtable_max_age_secs);
+        for (name, value) in [
+            ("memtable_max_bytes", memtable_max_bytes),
+            ("memtable_max_age_secs", memtable_max_age_secs),
+        ] {
+            if value == Some(0) {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "{} must be positive",
+                    name
+                )));
+            }
+        }
+        let memtable = FlushPolicy {
+            max_bytes: memtable_max_bytes.map_or(FlushPolicy::default().max_bytes, |n| {
+                usize::try_from(n).unwrap_or(usize::MAX)
+            }),
+            max_age: memtable_max_age_secs
+                .map_or(FlushPolicy::default().max_age, Duration::from_secs),
+        };
+
+        if args.tiering_sweep_secs.or(file.tiering_sweep_secs) == Some(0) {
+            return Err(VectorDbError::InvalidParameter(
+                "tiering_sweep_secs must be positive".into(),
+            ));
+        }
+        let mut tiering = args
+            .cold_after_days
+            .or(file.cold_after_days)
+            .map_or_else(TieringPolicy::default, TieringPolicy::after_days);
+        tiering.max_hot_vectors = args.max_hot_vectors.or(file.max_hot_vectors);
+        if let Some(secs) = args.tiering_sweep_secs.or(file.tiering_sweep_secs) {
+            tiering.sweep_interval = Duration::from_secs(secs);
+        }
+
+        let result_cache = args
+            .result_cache_size
+            .or(file.result_cache_size)
+            .filter(|&capacity| capacity > 0)
+            .map(|capacity| CacheConfig {
+                capacity,
+                ttl: args
+                    .result_cache_ttl_secs
+                    .or(file.result_cache_ttl_secs)
+                    .map_or(cache::DEFAULT_TTL, Duration::from_secs),
+            });
+        let query_log = args
+            .query_log
+            .clone()
+            .or(file.query_log)
+            .map(|path| QueryLogConfig {
+                path,
+                sample_rate: args
+                    .query_log_sample
+                    .or(file.query_log_sample)
+                    .unwrap_or(DEFAULT_QUERY_LOG_SAMPLE),
+                redact: args
+                    .query_log_redact
+                    .clone()
+                    .or(file.query_log_redact)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty())
+                    .collect(),
+            });
+        let api_keys = match args.api_keys.as_ref().or(file.api_keys.as_ref()) {
+            Some(path) => Some(ApiKeys::load(path)?),
+            None => None,
+        };
+
+        let config = Self {
+            addr: SocketAddr::new(host, port),
+            data_dir: args
+                .data_dir
+                .clone()
+                .or(file.data_dir)
+                .unwrap_or_else(|| "data".into()),
+            upload_dir: args
+                .upload_dir
+                .clone()
+                .or(file.upload_dir)
+                .unwrap_or_else(|| "uploads".into()),
+            default_metric: args
+                .default_metric
+                .or(file.default_metric)
+                .unwrap_or_default(),
+            numa: args.numa.or(file.numa).unwrap_or(false),
+            huge_pages: args.huge_pages.or(file.huge_pages).unwrap_or_default(),
+            drain_timeout: args
+                .drain_timeout_secs
+                .or(file.drain_timeout_secs)
+                .map_or(DEFAULT_DRAIN_TIMEOUT, Duration::from_secs),
+            admin_addr,
+            admin_tls: resolve_admin_tls(args, file.admin_tls)?,
+            oidc: resolve_oidc(args, file.oidc),
+            wal_sync: args.wal_sync.or(file.wal_sync).unwrap_or_default(),
+            max_fds: args.max_fds.or(file.max_fds),
+            trash: args
+                .trash_retention_hours
+                .or(file.trash_retention_hours)
+                .map_or_else(TrashPolicy::default, TrashPolicy::hours),
+            lock_warn: args
+                .lock_warn_ms
+                .or(file.lock_warn_ms)
+                .map(Duration::from_millis),
+            memtable,
+            tiering,
+            cold_store: resolve_cold_store(args, file.cold_store)?,
+            grpc_addr: args
+                .grpc_addr
+                .or(file.grpc_addr)
+                .unwrap_or(DEFAULT_GRPC_ADDR),
+            max_dimension: args
+                .max_dimension
+                .or(file.max_dimension)
+                .unwrap_or(limits::DEFAULT_MAX_DIMENSION),
+            max_radius_results: args
+                .max_radius_results
+                .or(file.max_radius_results)
+                .unwrap_or(limits::DEFAULT_MAX_RADIUS_RESULTS),
+            result_cache,
+            query_log,
+            api_keys,
+            admin_key: args
+                .admin_key
+                .clone()
+                .or(file.admin_key)
+                .filter(|key| !key.is_empty()),
+            udf_fuel: args.udf_fuel.or(file.udf_fuel),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject settings the server can't start with
+    pub fn validate(&self) -> Result<()> {
+        for (name, dir) in [
+            ("data_dir", &self.data_dir),
+            ("upload_dir", &self.upload_dir),
+        ] {
+            if dir.as_os_str().is_empty() {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "{} must not be empty",
+                    name
+                )));
+            }
+            if dir.exists() && !dir.is_dir() {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "{} {} exists but is not a directory",
+                    name,
+                    dir.display()
+                )));
+            }
+        }
+        if let Some(admin) = self.admin_addr {
+            if admin.port() != 0 && admin.port() == self.addr.port() {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "admin_port must differ from port (both are {})",
+                    admin.port()
+                )));
+            }
+        }
+        if self.admin_tls.is_some() && self.admin_addr.is_none() {
+            return Err(VectorDbError::InvalidParameter(
+                "admin_tls needs an admin_port (the public listener is plain HTTP)".into(),
+            ));
+        }
+        if let Some(oidc) = &self.oidc {
+            oidc.validate()?;
+        }
+        if let Some(cold_store) = &self.cold_store {
+            cold_store
+                .validate()
+                .map_err(VectorDbError::InvalidParameter)?;
+        }
+        if let Some(max) = self.max_fds {
+            if max <= fds::RESERVED as u64 {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "max_fds must be above {}, got {}",
+                    fds::RESERVED,
+                    max
+                )));
+            }
+        }
+        if !(1..=limits::MAX_CONFIGURABLE_DIMENSION).contains(&self.max_dimension) {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "max_dimension must be in 1..={}, got {}",
+                limits::MAX_CONFIGURABLE_DIMENSION,
+                self.max_dimension
+            )));
+        }
+        if self.max_radius_results == 0 {
+            return Err(VectorDbError::InvalidParameter(
+                "max_radius_results must be positive".into(),
+            ));
+        }
+        if let Some(log) = &self.query_log {
+            if !(0.0..=1.0).contains(&log.sample_rate) {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "query_log_sample must be between 0 and 1, got {}",
+                    log.sample_rate
+                )));
+            }
+        }
+        if self.udf_fuel == Some(0) {
+            return Err(VectorDbError::InvalidParameter(
+                "udf_fuel must be positive".into(),
+            ));
+        }
+        if self.data_dir == self.upload_dir {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "data_dir and upload_dir must differ (both are {})",
+                self.data_dir.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The file's [admin_tls] table with the flags laid over it; the flags
+/// alone must name all three files
+fn resolve_admin_tls(args: &Args, file: Option<MtlsConfig>) -> Result<Option<MtlsConfig>> {
+    let flags = [
+        &args.admin_tls_cert,
+        &args.admin_tls_key,
+        &args.admin_client_ca,
+    ];
+    let Some(mut tls) = file else {
+        return match flags {
+            [Some(cert), Some(key), Some(client_ca)] => Ok(Some(MtlsConfig {
+                cert: cert.clone(),
+                key: key.clone(),
+                client_ca: client_ca.clone(),
+            })),
+            [None, None, None] => Ok(None),
+            _ => Err(VectorDbError::InvalidParameter(
+                "admin TLS needs --admin-tls-cert, --admin-tls-key and --admin-client-ca".into(),
+            )),
+        };
+    };
+    if let Some(cert) = &args.admin_tls_cert {
+        tls.cert = cert.clone();
+    }
+    if let Some(key) = &args.admin_tls_key {
+        tls.key = key.clone();
+    }
+    if let Some(client_ca) = &args.admin_client_ca {
+        tls.client_ca = client_ca.clone();
+    }
+    Ok(Some(tls))
+}
+
+/// The file's [oidc] table with the flags laid over it; the flags alone
+/// are enough if they name both the issuer and the JWKS
+fn resolve_oidc(args: &Args, file: Option<OidcConfig>) -> Option<OidcConfig> {
+    let mut oidc = file;
+    if args.oidc_issuer.is_some() || args.oidc_jwks.is_some() {
+        let oidc = oidc.get_or_insert_with(|| OidcConfig::new(""));
+        if let Some(issuer) = &args.oidc_issuer {
+            oidc.issuer = issuer.clone();
+        }
+        if let Some(jwks) = &args.oidc_jwks {
+            oidc.jwks = Some(jwks.clone());
+        }
+    }
+    if let (Some(oidc), Some(audience)) = (&mut oidc, &args.oidc_audience) {
+        oidc.audience = Some(audience.clone());
+    }
+    oidc
+}
+
+/// The file's [cold_store] table with the flags laid over it; the flags
+/// alone must name the endpoint and the bucket. Credentials always come
+/// from the environment.
+fn resolve_cold_store(args: &Args, file: Option<S3Config>) -> Result<Option<S3Config>> {
+    let flags = [
+        &args.cold_store_endpoint,
+        &args.cold_store_bucket,
+        &args.cold_store_region,
+        &args.cold_store_prefix,
+    ];
+    let mut store = match file {
+        Some(store) => store,
+        None if flags.iter().all(|flag| flag.is_none()) => return Ok(None),
+        None => match (&args.cold_store_endpoint, &args.cold_store_bucket) {
+            (Some(endpoint), Some(bucket)) => S3Config::new(endpoint, bucket),
+            _ => {
+                return Err(VectorDbError::InvalidParameter(
+                    "cold store needs --cold-store-endpoint and --cold-store-bucket".into(),
+                ))
+            }
+        },
+    };
+    if let Some(endpoint) = &args.cold_store_endpoint {
+        store.endpoint = endpoint.clone();
+    }
+    if let Some(bucket) = &args.cold_store_bucket {
+        store.bucket = bucket.clone();
+    }
+    if let Some(region) = &args.cold_store_region {
+        store.region = region.clone();
+    }
+    if let Some(prefix) = &args.cold_store_prefix {
+        store.prefix = prefix.clone();
+    }
+    store.credentials = args.cold_store_credentials.clone().unwrap_or_default();
+    Ok(Some(store))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_beat_file_beat_defaults() {
+        let file = FileConfig::parse(
+            Path::new("vectordb.toml"),
+            "host = \"0.0.0.0\"\nport = 8080\ndefault_metric = \"dot\"\n",
+        )
+        .unwrap();
+        let args = Args {
+            port: Some(9090),
+            data_dir: Some("/srv/vectors".into()),
+            ..Args::default()
+        };
+        let config = Config::resolve(&args, file).unwrap();
+        assert_eq!(config.addr, "0.0.0.0:9090".parse().unwrap());
+        assert_eq!(config.data_dir, PathBuf::from("/srv/vectors"));
+        assert_eq!(config.upload_dir, PathBuf::from("uploads"));
+        assert_eq!(config.default_metric, DistanceMetric::Dot);
+
+        // VECTORDB_ADDR fills in under the other flags
+        let args = Args {
+            addr: Some("10.0.0.1:4000".into()),
+            port: Some(5000),
+            ..Args::default()
+        };
+        let config = Config::resolve(&args, FileConfig::default()).unwrap();
+        assert_eq!(config.addr, "10.0.0.1:5000".parse().unwrap());
+    }
+
+    #[test]
+    fn test_yaml_matches_toml() {
+        let toml = FileConfig::parse(
+            Path::new("a.toml"),
+            "port = 8080\ndata_dir = \"/d\"\ndefault_metric = \"euclidean\"\n",
+        )
+        .unwrap();
+        let yaml = FileConfig::parse(
+            Path::new("a.yaml"),
+            "port: 8080\ndata_dir: /d\ndefault_metric: euclidean\n",
+        )
+        .unwrap();
+        assert_eq!(toml, yaml);
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let err = |r: Result<FileConfig>| r.unwrap_err().to_string();
+        assert!(err(FileConfig::parse(Path::new("c.toml"), "prot = 1")).contains("prot"));
+        assert!(err(FileConfig::parse(Path::new("c.yml"), "port: lots")).contains("c.yml"));
+        assert!(err(FileConfig::parse(Path::new("c.ini"), "")).contains("unsupported"));
+        assert!(FileConfig::parse(Path::new("c.toml"), "default_metric = \"l2\"").is_err());
+
+        let args = Args {
+            addr: Some("localhost".into()),
+            ..Args::default()
+        };
+        let e = Config::resolve(&args, FileConfig::default()).unwrap_err();
+        assert!(e.to_string().contains("VECTORDB_ADDR"), "{}", e);
+
+        let args = Args {
+            data_dir: Some("same".into()),
+            upload_dir: Some("same".into()),
+            ..Args::default()
+        };
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+
+        let flags = Args::try_parse_from(["vectordb", "--default-metric", "cosin"]);
+        assert!(flags.unwrap_err().to_string().contains("unknown metric"));
+    }
+
+    #[test]
+    fn test_numa_flag() {
+        let file = FileConfig::parse(Path::new("n.toml"), "numa = true").unwrap();
+        assert!(
+            Config::resolve(&Args::default(), file.clone())
+                .unwrap()
+                .numa
+        );
+        assert!(
+            !Config::resolve(&Args::default(), FileConfig::default())
+                .unwrap()
+                .numa
+        );
+
+        // A bare --numa turns it on; --numa=false overrides the file
+        let args = Args::try_parse_from(["vectordb", "--numa"]).unwrap();
+        assert_eq!(args.numa, Some(true));
+        let args = Args::try_parse_from(["vectordb", "--numa=false"]).unwrap();
+        assert!(!Config::resolve(&args, file).unwrap().numa);
+    }
+
+    #[test]
+    fn test_huge_pages_setting() {
+        let file = FileConfig::parse(Path::new("h.yaml"), "huge_pages: explicit").unwrap();
+        let config = Config::resolve(&Args::default(), file).unwrap();
+        assert_eq!(config.huge_pages, HugePages::Explicit);
+
+        let args = Args::try_parse_from(["vectordb", "--huge-pages", "transparent"]).unwrap();
+        assert_eq!(args.huge_pages, Some(HugePages::Transparent));
+        let flags = Args::try_parse_from(["vectordb", "--huge-pages", "always"]);
+        assert!(flags
+            .unwrap_err()
+            .to_string()
+            .contains("unknown huge page mode"));
+    }
+
+    #[test]
+    fn test_drain_timeout() {
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.drain_timeout, DEFAULT_DRAIN_TIMEOUT);
+
+        let file = FileConfig::parse(Path::new("d.toml"), "drain_timeout_secs = 5").unwrap();
+        let args = Args::try_parse_from(["vectordb", "--drain-timeout-secs", "0"]).unwrap();
+        assert_eq!(
+            Config::resolve(&Args::default(), file.clone())
+                .unwrap()
+                .drain_timeout,
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            Config::resolve(&args, file).unwrap().drain_timeout,
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_oidc_flags_fill_in_the_file_table() {
+        let file = FileConfig::parse(
+            Path::new("o.toml"),
+            "[oidc]\nissuer = \"https://id\"\njwks = \"keys.json\"\ntenant_claim = \"org\"\n",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["vectordb", "--oidc-jwks", "https://id/jwks"]).unwrap();
+        let oidc = Config::resolve(&args, file.clone()).unwrap().oidc.unwrap();
+        assert_eq!(oidc.issuer, "https://id");
+        assert_eq!(oidc.jwks.as_deref(), Some("https://id/jwks"));
+        assert_eq!(oidc.tenant_claim.as_deref(), Some("org"));
+        assert_eq!(oidc.scope_prefix, "vectordb:");
+
+        // The JWKS alone names no issuer, and plain http is refused
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+        let args = Args::try_parse_from(["vectordb", "--oidc-jwks", "http://id/jwks"]).unwrap();
+        assert!(Config::resolve(&args, file).is_err());
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.oidc, None);
+    }
+
+    #[test]
+    fn test_admin_listener_defaults_to_loopback() {
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.admin_addr, None);
+
+        let file = FileConfig::parse(
+            Path::new("a.toml"),
+            "host = \"0.0.0.0\"\nadmin_port = 9090\n",
+        )
+        .unwrap();
+        let config = Config::resolve(&Args::default(), file.clone()).unwrap();
+        assert_eq!(config.admin_addr, Some("127.0.0.1:9090".parse().unwrap()));
+
+        let args = Args::try_parse_from(["vectordb", "--admin-host", "10.0.0.2"]).unwrap();
+        let config = Config::resolve(&args, file).unwrap();
+        assert_eq!(config.admin_addr, Some("10.0.0.2:9090".parse().unwrap()));
+
+        // A host alone, or the public port, is a mistake
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+        let args = Args::try_parse_from(["vectordb", "--port", "8080", "--admin-port", "8080"]);
+        assert!(Config::resolve(&args.unwrap(), FileConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_admin_tls_needs_all_three_files_and_an_admin_port() {
+        let file = FileConfig::parse(
+            Path::new("t.toml"),
+            "admin_port = 9090\n[admin_tls]\ncert = \"a.pem\"\nkey = \"a.key\"\nclient_ca = \"ca.pem\"\n",
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["vectordb", "--admin-client-ca", "ops.pem"]).unwrap();
+        let tls = Config::resolve(&args, file).unwrap().admin_tls.unwrap();
+        assert_eq!(tls.cert, PathBuf::from("a.pem"));
+        assert_eq!(tls.client_ca, PathBuf::from("ops.pem"));
+
+        // One flag alone, or TLS without an admin listener, won't start
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+        let args = Args::try_parse_from([
+            "vectordb",
+            "--admin-tls-cert",
+            "a.pem",
+            "--admin-tls-key",
+            "a.key",
+            "--admin-client-ca",
+            "ca.pem",
+        ])
+        .unwrap();
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_storage_settings() {
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.wal_sync, SyncPolicy::Always);
+        assert_eq!(config.trash, TrashPolicy::default());
+        assert_eq!(config.memtable, FlushPolicy::default());
+        assert_eq!((config.max_fds, config.lock_warn), (None, None));
+
+        let file = FileConfig::parse(
+            Path::new("s.toml"),
+            "wal_sync = \"every:100\"\nmax_fds = 4096\ntrash_retention_hours = 2\n\
+             lock_warn_ms = 250\nmemtable_max_bytes = 1024\nmemtable_max_age_secs = 30\n",
+        )
+        .unwrap();
+        let config = Config::resolve(&Args::default(), file.clone()).unwrap();
+        assert_eq!(config.wal_sync, SyncPolicy::EveryN(100));
+        assert_eq!(config.max_fds, Some(4096));
+        assert_eq!(config.trash.retention, Duration::from_secs(2 * 60 * 60));
+        assert_eq!(config.lock_warn, Some(Duration::from_millis(250)));
+        assert_eq!(config.memtable.max_bytes, 1024);
+        assert_eq!(config.memtable.max_age, Duration::from_secs(30));
+
+        let args = Args::try_parse_from(["vectordb", "--wal-sync", "never"]).unwrap();
+        assert_eq!(
+            Config::resolve(&args, file).unwrap().wal_sync,
+            SyncPolicy::Never
+        );
+    }
+
+    #[test]
+    fn test_bad_storage_settings_are_errors() {
+        let flags = Args::try_parse_from(["vectordb", "--wal-sync", "sometimes"]);
+        assert!(flags
+            .unwrap_err()
+            .to_string()
+            .contains("unknown WAL sync policy"));
+        let file = FileConfig::parse(Path::new("s.toml"), "wal_sync = \"every:0\"");
+        assert!(file.unwrap_err().to_string().contains("positive"));
+        assert!(Args::try_parse_from(["vectordb", "--max-fds", "lots"]).is_err());
+
+        for (text, problem) in [
+            ("max_fds = 4", "max_fds"),
+            ("memtable_max_bytes = 0", "memtable_max_bytes"),
+            ("memtable_max_age_secs = 0", "memtable_max_age_secs"),
+        ] {
+            let file = FileConfig::parse(Path::new("s.toml"), text).unwrap();
+            let e = Config::resolve(&Args::default(), file).unwrap_err();
+            assert!(e.to_string().contains(problem), "{}", e);
+        }
+    }
+
+    #[test]
+    fn test_tiering_settings() {
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.tiering, TieringPolicy::default());
+
+        let file = FileConfig::parse(
+            Path::new("t.yaml"),
+            "cold_after_days: 7\nmax_hot_vectors: 1000\ntiering_sweep_secs: 60\n",
+        )
+        .unwrap();
+        let tiering = Config::resolve(&Args::default(), file).unwrap().tiering;
+        assert_eq!(tiering.cold_after, TieringPolicy::after_days(7).cold_after);
+        assert_eq!(tiering.max_hot_vectors, Some(1000));
+        assert_eq!(tiering.sweep_interval, Duration::from_secs(60));
+
+        let args = Args::try_parse_from(["vectordb", "--tiering-sweep-secs", "0"]).unwrap();
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_cold_store_flags_fill_in_the_file_table() {
+        let credentials = Credentials {
+            access_key: "id".into(),
+            secret_key: "secret".into(),
+            session_token: None,
+        };
+        let file = FileConfig::parse(
+            Path::new("c.toml"),
+            "[cold_store]\nendpoint = \"https://s3.example.com\"\nbucket = \"cold\"\n",
+        )
+        .unwrap();
+        let mut args =
+            Args::try_parse_from(["vectordb", "--cold-store-region", "eu-west-1"]).unwrap();
+        args.cold_store_credentials = Some(credentials.clone());
+        let store = Config::resolve(&args, file.clone())
+            .unwrap()
+            .cold_store
+            .unwrap();
+        assert_eq!(store.bucket, "cold");
+        assert_eq!(store.region, "eu-west-1");
+        assert_eq!(store.credentials, credentials);
+
+        // A region alone names no bucket, and keys are required
+        assert!(Config::resolve(&args, FileConfig::default()).is_err());
+        args.cold_store_credentials = None;
+        let e = Config::resolve(&args, file).unwrap_err();
+        assert!(e.to_string().contains("AWS_SECRET_ACCESS_KEY"), "{}", e);
+        assert!(FileConfig::parse(Path::new("c.toml"), "[cold_store]\nbucket = \"cold\"").is_err());
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.cold_store, None);
+    }
+
+    #[test]
+    fn test_service_settings() {
+        let config = Config::resolve(&Args::default(), FileConfig::default()).unwrap();
+        assert_eq!(config.grpc_addr, DEFAULT_GRPC_ADDR);
+        assert_eq!(config.max_dimension, limits::DEFAULT_MAX_DIMENSION);
+        assert_eq!(
+            config.max_radius_results,
+            limits::DEFAULT_MAX_RADIUS_RESULTS
+        );
+        assert_eq!((config.result_cache, &config.query_log), (None, &None));
+        assert_eq!((&config.api_keys, &config.admin_key), (&None, &None));
+
+        let file = FileConfig::parse(
+            Path::new("s.toml"),
+            "grpc_addr = \"0.0.0.0:50051\"\nmax_dimension = 4096\nresult_cache_size = 64\n\
+             query_log = \"q.jsonl\"\nquery_log_redact = [\"filter\"]\nadmin_key = \"k\"\n",
+        )
+        .unwrap();
+        let config = Config::resolve(&Args::default(), file.clone()).unwrap();
+        assert_eq!(config.grpc_addr, "0.0.0.0:50051".parse().unwrap());
+        assert_eq!(config.max_dimension, 4096);
+        let cache = config.result_cache.unwrap();
+        assert_eq!((cache.capacity, cache.ttl), (64, cache::DEFAULT_TTL));
+        let log = config.query_log.unwrap();
+        assert_eq!(log.sample_rate, DEFAULT_QUERY_LOG_SAMPLE);
+        assert_eq!(log.redact, vec!["filter".to_string()]);
+        assert_eq!(config.admin_key.as_deref(), Some("k"));
+
+        let args = Args::try_parse_from([
+            "vectordb",
+            "--grpc-addr",
+            "127.0.0.1:0",
+            "--result-cache-size",
+            "0",
+            "--query-log-redact",
+            "filter, vector",
+            "--admin-key",
+            "",
+        ])
+        .unwrap();
+        let config = Config::resolve(&args, file).unwrap();
+        assert_eq!(config.grpc_addr, "127.0.0.1:0".parse().unwrap());
+        assert_eq!(config.result_cache, None);
+        let redact = config.query_log.unwrap().redact;
+        assert_eq!(redact, vec!["filter".to_string(), "vector".to_string()]);
+        assert_eq!(config.admin_key, None);
+    }
+
+    #[test]
+    fn test_bad_service_settings_are_errors() {
+        assert!(Args::try_parse_from(["vectordb", "--grpc-addr", "localhost"]).is_err());
+        assert!(Args::try_parse_from(["vectordb", "--udf-fuel", "lots"]).is_err());
+
+        for (text, problem) in [
+            ("max_dimension = 0", "max_dimension"),
+            ("max_dimension = 99999999", "max_dimension"),
+            ("max_radius_results = 0", "max_radius_results"),
+            (
+                "query_log = \"q\"\nquery_log_sample = 1.5",
+                "query_log_sample",
+            ),
+            ("udf_fuel = 0", "udf_fuel"),
+            ("api_keys = \"no/such/keys.json\"", "no/such/keys.json"),
+        ] {
+            let file = FileConfig::parse(Path::new("s.toml"), text).unwrap();
+            let e = Config::resolve(&Args::default(), file).unwrap_err();
+            assert!(e.to_string().contains(problem), "{}", e);
+        }
+
+        let path = std::env::temp_dir().join(format!("vectordb_keys_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{ "keys": [{ "key": "a", "verbs": [] }] }"#).unwrap();
+        let args = Args {
+            api_keys: Some(path.clone()),
+            ..Args::default()
+        };
+        let e = Config::resolve(&args, FileConfig::default()).unwrap_err();
+        assert!(e.to_string().contains("allows nothing"), "{}", e);
+        std::fs::write(&path, r#"{ "keys": [{ "key": "a" }] }"#).unwrap();
+        let keys = Config::resolve(&args, FileConfig::default())
+            .unwrap()
+            .api_keys;
+        assert_eq!(keys.map(|k| k.len()), Some(1));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
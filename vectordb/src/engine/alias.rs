@@ -0,0 +1,255 @@
+// src/engine/alias.rs
+//
+// Collection aliases with optional A/B routing.
+//
+// Clients search an alias ("docs") instead of a concrete collection
+// ("docs_2024_06"), so the target can be swapped without a deploy. An alias
+// may also carry an experiment that sends a share of searches to a second
+// collection:
+//
+//   POST /api/collections/docs/search
+//          │
+//          ├── 90% ──▶ docs_2024_06   (control)
+//          └── 10% ──▶ docs_2024_07   (treatment)
+//
+// Routing hashes the caller's routing key when one is given, so the same
+// user always sees the same variant; otherwise it hashes a request counter,
+// which spreads traffic evenly. Per-variant counters are atomics so routing
+// only needs the read lock.
+
+use crate::engine::collection::validate_name;
+use crate::models::{CreateAliasRequest, ExperimentConfig, Result, VectorDbError};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Which side of an experiment a search was routed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    Control,
+    Treatment,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::Control => "control",
+            Variant::Treatment => "treatment",
+        }
+    }
+}
+
+/// Search counters for one variant
+#[derive(Debug, Default)]
+pub struct VariantStats {
+    searches: AtomicU64,
+    total_latency_us: AtomicU64,
+}
+
+impl VariantStats {
+    /// Record one search and how long it took
+    pub fn record(&self, latency: Duration) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.total_latency_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// JSON summary: searches and mean latency
+    pub fn snapshot(&self) -> serde_json::Value {
+        let searches = self.searches.load(Ordering::Relaxed);
+        let total = self.total_latency_us.load(Ordering::Relaxed);
+        serde_json::json!({
+            "searches": searches,
+            "mean_latency_us": total.checked_div(searches).unwrap_or(0),
+        })
+    }
+}
+
+/// A share of an alias's traffic sent to another collection
+#[derive(Debug)]
+pub struct Experiment {
+    /// Collection that receives the treatment traffic
+    pub collection: String,
+
+    /// Share of searches routed to `collection`, 0.0..=100.0
+    pub percent: f32,
+}
+
+/// A named pointer to a collection, optionally splitting traffic.
+#[derive(Debug)]
+pub struct Alias {
+    pub name: String,
+
+    /// Collection that receives control traffic
+    pub collection: String,
+
+    pub experiment: Option<Experiment>,
+
+    control: VariantStats,
+    treatment: VariantStats,
+    requests: AtomicU64,
+}
+
+impl Alias {
+    /// Build an alias from an API request (collections are checked by the
+    /// caller, which owns them)
+    pub fn from_request(req: &CreateAliasRequest) -> Result<Self> {
+        validate_name(&req.name)?;
+        let experiment = match &req.experiment {
+            Some(exp) => {
+                if !(0.0..=100.0).contains(&exp.percent) {
+                    return Err(VectorDbError::InvalidParameter(format!(
+                        "experiment percent must be between 0 and 100, got {}",
+                        exp.percent
+                    )));
+                }
+                Some(Experiment {
+                    collection: exp.collection.clone(),
+                    percent: exp.percent,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            name: req.name.clone(),
+            collection: req.collection.clone(),
+            experiment,
+            control: VariantStats::default(),
+            treatment: VariantStats::default(),
+            requests: AtomicU64::new(0),
+        })
+    }
+
+    /// Pick the variant and target collection for one search
+    pub fn route(&self, routing_key: Option<&str>) -> (Variant, &str) {
+        let Some(exp) = &self.experiment else {
+            return (Variant::Control, &self.collection);
+        };
+
+        let mut hasher = DefaultHasher::new();
+        match routing_key {
+            Some(key) => key.hash(&mut hasher),
+            None => self
+                .requests
+                .fetch_add(1, Ordering::Relaxed)
+                .hash(&mut hasher),
+        }
+        // Basis points, so fractional percentages like 0.5% work
+        let bucket = hasher.finish() % 10_000;
+        if (bucket as f32) < exp.percent * 100.0 {
+            (Variant::Treatment, &exp.collection)
+        } else {
+            (Variant::Control, &self.collection)
+        }
+    }
+
+    /// Counters for a variant
+    pub fn stats(&self, variant: Variant) -> &VariantStats {
+        match variant {
+            Variant::Control => &self.control,
+            Variant::Treatment => &self.treatment,
+        }
+    }
+
+    /// The configuration this alias was built from, as persisted
+    pub fn config(&self) -> CreateAliasRequest {
+        CreateAliasRequest {
+            name: self.name.clone(),
+            collection: self.collection.clone(),
+            experiment: self.experiment.as_ref().map(|e| ExperimentConfig {
+                collection: e.collection.clone(),
+                percent: e.percent,
+            }),
+        }
+    }
+
+    /// Collections this alias can route to
+    pub fn targets(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.collection.as_str())
+            .chain(self.experiment.iter().map(|e| e.collection.as_str()))
+    }
+
+    /// Summary returned by the aliases API
+    pub fn info(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "collection": self.collection,
+            "experiment": self.experiment.as_ref().map(|e| serde_json::json!({
+                "collection": e.collection,
+                "percent": e.percent,
+            })),
+            "variants": {
+                "control": self.control.snapshot(),
+                "treatment": self.treatment.snapshot(),
+            },
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(percent: f32) -> Alias {
+        Alias::from_request(&CreateAliasRequest {
+            name: "docs".into(),
+            collection: "docs_a".into(),
+            experiment: Some(ExperimentConfig {
+                collection: "docs_b".into(),
+                percent,
+            }),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_route_splits_traffic() {
+        let a = alias(25.0);
+        let treated = (0..4000)
+            .filter(|_| a.route(None).0 == Variant::Treatment)
+            .count();
+        assert!((800..1200).contains(&treated), "treated = {}", treated);
+
+        assert_eq!(alias(0.0).route(None), (Variant::Control, "docs_a"));
+        assert_eq!(alias(100.0).route(None), (Variant::Treatment, "docs_b"));
+    }
+
+    #[test]
+    fn test_routing_key_is_sticky() {
+        let a = alias(50.0);
+        let first = a.route(Some("user-42"));
+        for _ in 0..10 {
+            assert_eq!(a.route(Some("user-42")), first);
+        }
+    }
+
+    #[test]
+    fn test_config_rebuilds_the_same_alias() {
+        let a = alias(25.0);
+        let b = Alias::from_request(&a.config()).unwrap();
+        assert_eq!(b.name, "docs");
+        assert_eq!(b.targets().collect::<Vec<_>>(), ["docs_a", "docs_b"]);
+        assert_eq!(b.experiment.unwrap().percent, 25.0);
+    }
+
+    #[test]
+    fn test_percent_is_validated() {
+        let req = CreateAliasRequest {
+            name: "docs".into(),
+            collection: "docs_a".into(),
+            experiment: Some(ExperimentConfig {
+                collection: "docs_b".into(),
+                percent: 120.0,
+            }),
+        };
+        assert!(Alias::from_request(&req).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// src/engine/arith.rs
+//
+// Server-side vector arithmetic over stored IDs.
+//
+// Analogy-style exploration ("king − man + woman ≈ queen") needs a few
+// stored vectors combined into a new query. Doing it here saves the
+// client downloading every input vector just to add them up:
+//
+//   result = Σ vec(add) − Σ vec(subtract)          (mode: sum)
+//   result = (Σ vec(add) − Σ vec(subtract)) / n    (mode: average)
+//
+// where n counts every term, so averaging a list of `add` IDs gives their
+// centroid. The same ID may appear more than once; each occurrence counts.
+
+use crate::engine::normalize;
+use crate::models::{ArithMode, Result, VectorDbError};
+
+/// Most IDs one expression may reference
+pub const MAX_TERMS: usize = 1000;
+
+/// Combine the vectors for `add` and `subtract`, looking each ID up with
+/// `lookup`. Every missing ID is reported at once.
+pub fn evaluate(
+    add: &[String],
+    subtract: &[String],
+    mode: ArithMode,
+    normalize: bool,
+    lookup: impl Fn(&str) -> Option<Vec<f32>>,
+) -> Result<Vec<f32>> {
+    let terms = add.len() + subtract.len();
+    if terms == 0 {
+        return Err(VectorDbError::InvalidParameter(
+            "Nothing to compute: provide 'add' and/or 'subtract'".into(),
+        ));
+    }
+    if terms > MAX_TERMS {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "An expression can reference at most {} IDs (got {})",
+            MAX_TERMS, terms
+        )));
+    }
+
+    let signed = add
+        .iter()
+        .map(|id| (id, 1.0))
+        .chain(subtract.iter().map(|id| (id, -1.0)));
+    let mut result: Option<Vec<f32>> = None;
+    let mut missing = Vec::new();
+    for (id, sign) in signed {
+        let Some(data) = lookup(id) else {
+            missing.push(id.as_str());
+            continue;
+        };
+        match &mut result {
+            None => result = Some(data.iter().map(|x| x * sign).collect()),
+            Some(sum) if sum.len() != data.len() => {
+                return Err(VectorDbError::DimensionMismatch {
+                    expected: sum.len(),
+                    got: data.len(),
+                })
+            }
+            Some(sum) => {
+                for (acc, x) in sum.iter_mut().zip(&data) {
+                    *acc += x * sign;
+                }
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Err(VectorDbError::NotFound(format!(
+            "vectors {}",
+            missing
+                .iter()
+                .map(|id| format!("'{}'", id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    let mut result = result.unwrap_or_default();
+    if mode == ArithMode::Average {
+        for x in &mut result {
+            *x /= terms as f32;
+        }
+    }
+    if normalize {
+        let n = normalize::norm(&result);
+        if n == 0.0 {
+            return Err(VectorDbError::InvalidParameter(
+                "The result is the zero vector and can't be normalized".into(),
+            ));
+        }
+        for x in &mut result {
+            *x /= n;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let words: HashMap<&str, Vec<f32>> = [
+            ("king", vec![1.0, 1.0]),
+            ("man", vec![1.0, 0.0]),
+            ("woman", vec![0.0, 3.0]),
+            ("short", vec![1.0]),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |id: &str| words.get(id).cloned();
+
+        let analogy = evaluate(
+            &ids(&["king", "woman"]),
+            &ids(&["man"]),
+            ArithMode::Sum,
+            false,
+            lookup,
+        );
+        assert_eq!(analogy.unwrap(), vec![0.0, 4.0]);
+
+        let centroid = evaluate(
+            &ids(&["man", "woman"]),
+            &[],
+            ArithMode::Average,
+            false,
+            lookup,
+        );
+        assert_eq!(centroid.unwrap(), vec![0.5, 1.5]);
+
+        let unit = evaluate(&ids(&["woman"]), &[], ArithMode::Sum, true, lookup);
+        assert_eq!(unit.unwrap(), vec![0.0, 1.0]);
+
+        // Every missing ID is named; mixed dimensions and zero results fail
+        let err = evaluate(
+            &ids(&["a", "king", "b"]),
+            &[],
+            ArithMode::Sum,
+            false,
+            lookup,
+        );
+        assert!(err.unwrap_err().to_string().contains("'a', 'b'"));
+        assert!(evaluate(&ids(&["king", "short"]), &[], ArithMode::Sum, false, lookup).is_err());
+        assert!(evaluate(&ids(&["man"]), &ids(&["man"]), ArithMode::Sum, true, lookup).is_err());
+        assert!(evaluate(&[], &[], ArithMode::Sum, false, lookup).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+// src/engine/cache.rs
+//
+// Search result cache for repeated queries.
+//
+// Dashboards re-run the same handful of "canned" searches every few
+// seconds. With the cache on, a search whose fingerprint was seen recently
+// is answered from memory instead of re-scanning the collection:
+//
+//   fingerprint = (collection, write epoch, quantized query, parameters)
+//
+// The write epoch changes on every insert, update, delete or settings
+// change, calibration included (see `Collection::epoch`), and epochs are
+// unique across the whole process, so a write — or dropping and
+// recreating the collection — makes every older entry unreachable;
+// they're purged as soon as a newer epoch is seen. Queries are quantized
+// to 1/65536 per component so a vector that went through a JSON round
+// trip (or a float formatter) still hits. Entries also expire after a
+// TTL, and the oldest are evicted past the capacity.
+//
+// Off unless result_cache_size (max entries) is set; result_cache_ttl_secs
+// sets the TTL (default 60). See config.rs.
+//
+// A search that read the epoch before a write can finish after the first
+// search past it; its stale `put` must not bring back what the newer one
+// purged. tests/loom.rs checks that race under every interleaving.
+
+use crate::models::SearchResult;
+use crate::sync::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Default time an entry stays valid
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Steps per unit when quantizing query components
+const QUANTIZATION: f32 = 65536.0;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONFIG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Size and lifetime of the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Most entries kept
+    pub capacity: usize,
+    /// Time an entry stays valid
+    pub ttl: Duration,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FINGERPRINT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Everything a search's results depend on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    collection: String,
+    epoch: u64,
+    query: Vec<i32>,
+    /// Every other parameter that changes the results (top_k, metric,
+    /// filters, ...), in a fixed rendering chosen by the caller
+    params: String,
+}
+
+impl Fingerprint {
+    pub fn new(collection: &str, epoch: u64, query: &[f32], params: String) -> Self {
+        Self {
+            collection: collection.to_string(),
+            epoch,
+            query: query
+                .iter()
+                .map(|x| (x * QUANTIZATION).round() as i32)
+                .collect(),
+            params,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CACHE
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct Entry {
+    results: Vec<SearchResult>,
+    stored_at: Instant,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Fingerprint, Entry>,
+    /// Insertion order, for eviction (may hold keys already removed)
+    order: VecDeque<Fingerprint>,
+    /// Newest epoch seen per collection
+    epochs: HashMap<String, u64>,
+}
+
+/// Recent search results by fingerprint. Shared by concurrent searches,
+/// so it locks internally.
+pub struct ResultCache {
+    config: CacheConfig,
+    inner: Mutex<Inner>,
+}
+
+impl std::fmt::Debug for ResultCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResultCache")
+            .field("config", &self.config)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl ResultCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Cached results for `key`, if still valid at `now`
+    pub fn get(&self, key: &Fingerprint, now: Instant) -> Option<Vec<SearchResult>> {
+        let mut inner = self.inner.lock().unwrap();
+        let fresh = inner
+            .entries
+            .get(key)
+            .map(|e| now.saturating_duration_since(e.stored_at) < self.config.ttl)?;
+        if !fresh {
+            inner.entries.remove(key);
+            return None;
+        }
+        inner.entries.get(key).map(|e| e.results.clone())
+    }
+
+    /// Remember `results` for `key`. A newer epoch for the collection drops
+    /// all of its older entries; a stale one isn't stored.
+    pub fn put(&self, key: Fingerprint, results: Vec<SearchResult>, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        let newest = inner.epochs.get(&key.collection).copied().unwrap_or(0);
+        if key.epoch < newest {
+            return;
+        }
+        if key.epoch > newest {
+            inner.entries.retain(|k, _| k.collection != key.collection);
+            inner.epochs.insert(key.collection.clone(), key.epoch);
+        }
+
+        while inner.entries.len() >= self.config.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+        inner.order.push_back(key.clone());
+        inner.entries.insert(
+            key,
+            Entry {
+                results,
+                stored_at: now,
+            },
+        );
+        // Keys of invalidated entries pile up in `order`; trim them once
+        // they outnumber the live ones
+        if inner.order.len() > 2 * self.config.capacity {
+            let Inner { entries, order, .. } = &mut *inner;
+            order.retain(|k| entries.contains_key(k));
+        }
+    }
+
+    /// Entries currently held
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(id: &str) -> Vec<SearchResult> {
+        vec![SearchResult::new(id, 1.0)]
+    }
+
+    fn cache(capacity: usize) -> ResultCache {
+        ResultCache::new(CacheConfig {
+            capacity,
+            ttl: Duration::from_secs(10),
+        })
+    }
+
+    #[test]
+    fn test_hit_within_ttl_and_quantization() {
+        let cache = cache(10);
+        let now = Instant::now();
+        let key = Fingerprint::new("docs", 1, &[0.1, 0.2], "k=10".into());
+        cache.put(key, hits("a"), now);
+
+        let nearly = Fingerprint::new("docs", 1, &[0.1 + 1e-7, 0.2], "k=10".into());
+        assert_eq!(cache.get(&nearly, now).unwrap()[0].id, "a");
+        let other_params = Fingerprint::new("docs", 1, &[0.1, 0.2], "k=5".into());
+        assert!(cache.get(&other_params, now).is_none());
+        assert!(cache.get(&nearly, now + Duration::from_secs(10)).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_new_epoch_invalidates_collection() {
+        let cache = cache(10);
+        let now = Instant::now();
+        cache.put(
+            Fingerprint::new("docs", 1, &[1.0], "".into()),
+            hits("a"),
+            now,
+        );
+        cache.put(
+            Fingerprint::new("news", 2, &[1.0], "".into()),
+            hits("n"),
+            now,
+        );
+        cache.put(
+            Fingerprint::new("docs", 3, &[0.0], "".into()),
+            hits("b"),
+            now,
+        );
+
+        assert!(cache
+            .get(&Fingerprint::new("docs", 1, &[1.0], "".into()), now)
+            .is_none());
+        assert_eq!(cache.len(), 2);
+        // A search that started before the write doesn't repopulate
+        cache.put(
+            Fingerprint::new("docs", 1, &[1.0], "".into()),
+            hits("a"),
+            now,
+        );
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_evicts_oldest_past_capacity() {
+        let cache = cache(2);
+        let now = Instant::now();
+        for (i, id) in ["a", "b", "c"].iter().enumerate() {
+            cache.put(
+                Fingerprint::new("docs", 1, &[i as f32], "".into()),
+                hits(id),
+                now,
+            );
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get(&Fingerprint::new("docs", 1, &[0.0], "".into()), now)
+            .is_none());
+    }
+}
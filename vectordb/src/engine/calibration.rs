@@ -0,0 +1,224 @@
+// src/engine/calibration.rs
+//
+// Score calibration: raw similarity → relevance probability.
+//
+// A cosine of 0.78 means "very relevant" under one embedding model and
+// "barely related" under another, so a threshold tuned on raw scores
+// breaks every time the model changes. Platt scaling fits a sigmoid
+//
+//   P(relevant | s) = 1 / (1 + exp(a·s + b))
+//
+// to labeled (score, relevant?) pairs, and the fitted probability is what
+// thresholds should be set on. Refit with new labels after switching
+// models and the thresholds keep their meaning.
+//
+// The fit is the Newton method with backtracking line search from Lin,
+// Lin & Weng, "A note on Platt's probabilistic outputs for support vector
+// machines" (2007), including Platt's smoothed targets, which keep a and b
+// finite even when the labels are perfectly separable.
+
+use crate::engine::collection::Collection;
+use crate::models::{Calibration, DistanceMetric, LabeledPair, Result, VectorDbError};
+
+/// Fewest relevant and fewest irrelevant pairs a fit needs
+pub const MIN_PER_CLASS: usize = 3;
+
+/// Most labeled pairs in one request
+pub const MAX_PAIRS: usize = 100_000;
+
+const MAX_ITERATIONS: usize = 100;
+const MIN_STEP: f64 = 1e-10;
+const SIGMA: f64 = 1e-12;
+const EPSILON: f64 = 1e-5;
+
+/// Turn labeled pairs into (score, relevant) samples, scoring query/id
+/// pairs against `collection` with `metric`.
+pub fn samples(
+    collection: &Collection,
+    metric: DistanceMetric,
+    pairs: &[LabeledPair],
+) -> Result<Vec<(f32, bool)>> {
+    if pairs.len() > MAX_PAIRS {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "at most {} labeled pairs per calibration (got {})",
+            MAX_PAIRS,
+            pairs.len()
+        )));
+    }
+    pairs
+        .iter()
+        .enumerate()
+        .map(|(i, pair)| {
+            let score = match (pair.score, &pair.query, &pair.id) {
+                (Some(score), None, None) => score,
+                (None, Some(query), Some(id)) => {
+                    if query.len() != collection.dimension {
+                        return Err(VectorDbError::DimensionMismatch {
+                            expected: collection.dimension,
+                            got: query.len(),
+                        });
+                    }
+                    let point = collection.get(id).ok_or_else(|| {
+                        VectorDbError::NotFound(format!("pair {}: point '{}'", i, id))
+                    })?;
+                    metric.calculate(query, &point.data)
+                }
+                _ => {
+                    return Err(VectorDbError::InvalidParameter(format!(
+                        "pair {} needs either 'score', or 'query' and 'id'",
+                        i
+                    )))
+                }
+            };
+            if !score.is_finite() {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "pair {} has a non-finite score",
+                    i
+                )));
+            }
+            Ok((score, pair.relevant))
+        })
+        .collect()
+}
+
+/// Fit Platt scaling parameters to `samples`.
+pub fn fit(metric: DistanceMetric, samples: &[(f32, bool)], now: u64) -> Result<Calibration> {
+    let relevant = samples.iter().filter(|(_, r)| *r).count();
+    let irrelevant = samples.len() - relevant;
+    if relevant < MIN_PER_CLASS || irrelevant < MIN_PER_CLASS {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "calibration needs at least {} relevant and {} irrelevant pairs (got {} and {})",
+            MIN_PER_CLASS, MIN_PER_CLASS, relevant, irrelevant
+        )));
+    }
+
+    // Smoothed targets instead of 0/1
+    let (pos, neg) = (relevant as f64, irrelevant as f64);
+    let hi = (pos + 1.0) / (pos + 2.0);
+    let lo = 1.0 / (neg + 2.0);
+    let data: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|&(s, r)| (s as f64, if r { hi } else { lo }))
+        .collect();
+
+    let objective = |a: f64, b: f64| -> f64 {
+        data.iter()
+            .map(|&(s, t)| {
+                let f = s * a + b;
+                if f >= 0.0 {
+                    t * f + (1.0 + (-f).exp()).ln()
+                } else {
+                    (t - 1.0) * f + (1.0 + f.exp()).ln()
+                }
+            })
+            .sum()
+    };
+
+    let mut a = 0.0;
+    let mut b = ((neg + 1.0) / (pos + 1.0)).ln();
+    let mut value = objective(a, b);
+    for _ in 0..MAX_ITERATIONS {
+        // Gradient and Hessian of the negative log-likelihood
+        let (mut h11, mut h22, mut h21, mut g1, mut g2) = (SIGMA, SIGMA, 0.0, 0.0, 0.0);
+        for &(s, t) in &data {
+            let f = s * a + b;
+            let (p, q) = if f >= 0.0 {
+                let e = (-f).exp();
+                (e / (1.0 + e), 1.0 / (1.0 + e))
+            } else {
+                let e = f.exp();
+                (1.0 / (1.0 + e), e / (1.0 + e))
+            };
+            let d2 = p * q;
+            h11 += s * s * d2;
+            h22 += d2;
+            h21 += s * d2;
+            let d1 = t - p;
+            g1 += s * d1;
+            g2 += d1;
+        }
+        if g1.abs() < EPSILON && g2.abs() < EPSILON {
+            break;
+        }
+
+        let det = h11 * h22 - h21 * h21;
+        let da = -(h22 * g1 - h21 * g2) / det;
+        let db = -(-h21 * g1 + h11 * g2) / det;
+        let gd = g1 * da + g2 * db;
+
+        let mut step = 1.0;
+        while step >= MIN_STEP {
+            let (na, nb) = (a + step * da, b + step * db);
+            let next = objective(na, nb);
+            if next < value + 1e-4 * step * gd {
+                (a, b, value) = (na, nb, next);
+                break;
+            }
+            step /= 2.0;
+        }
+        if step < MIN_STEP {
+            break; // line search made no progress; keep the best so far
+        }
+    }
+
+    Ok(Calibration {
+        metric,
+        a,
+        b,
+        pairs: samples.len(),
+        relevant,
+        fitted_at: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_orders_probabilities() {
+        let samples = [
+            (0.91, true),
+            (0.85, true),
+            (0.80, true),
+            (0.62, false),
+            (0.83, true),
+            (0.55, false),
+            (0.71, false),
+            (0.79, false),
+            (0.40, false),
+        ];
+        let c = fit(DistanceMetric::Cosine, &samples, 0).unwrap();
+        assert!(
+            c.a < 0.0,
+            "higher cosine should mean more relevant: {:?}",
+            c
+        );
+
+        let (high, mid, low) = (c.probability(0.9), c.probability(0.75), c.probability(0.4));
+        assert!(high > 0.7 && low < 0.1, "{} {}", high, low);
+        assert!(high > mid && mid > low);
+
+        // Euclidean distances: smaller is better, the fit learns the sign
+        let distances: Vec<(f32, bool)> = samples.iter().map(|&(s, r)| (1.0 - s, r)).collect();
+        let c = fit(DistanceMetric::Euclidean, &distances, 0).unwrap();
+        assert!(c.probability(0.1) > c.probability(0.6));
+    }
+
+    #[test]
+    fn test_separable_labels_stay_finite() {
+        let samples = [
+            (0.9, true),
+            (0.8, true),
+            (0.7, true),
+            (0.3, false),
+            (0.2, false),
+            (0.1, false),
+        ];
+        let c = fit(DistanceMetric::Cosine, &samples, 0).unwrap();
+        assert!(c.a.is_finite() && c.b.is_finite());
+        assert!(c.probability(0.9) < 1.0);
+
+        assert!(fit(DistanceMetric::Cosine, &samples[..4], 0).is_err());
+    }
+}
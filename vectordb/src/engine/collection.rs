@@ -0,0 +1,2637 @@
+// src/engine/collection.rs
+//
+// A named set of vectors sharing one dimension and one distance metric.
+//
+// Collections are the unit clients create, insert into, and search. Every
+// vector in a collection has the collection's dimension, which is checked
+// on insert so search never has to deal with mismatched lengths.
+
+use crate::engine::filter::Filter;
+use crate::engine::gemm;
+use crate::engine::history::{VectorVersion, VersionHistory};
+use crate::engine::hnsw::{self, FilterStrategy, Hnsw, Layout};
+use crate::engine::hooks::Hooks;
+use crate::engine::ids::{self, IdGenerator};
+use crate::engine::index::{self, IndexInfo, IndexState, IndexType, MemoryFootprint, MemtableInfo};
+use crate::engine::metric;
+use crate::engine::normalize;
+use crate::engine::search::{self, Cursor, TopK};
+use crate::engine::stats::FieldStats;
+use crate::limits;
+use crate::models::{
+    AutoIndex, Calibration, CollectionInfo, CollectionSchema, ComputedField,
+    CreateCollectionRequest, DistanceMetric, FieldError, FieldType, Fusion, HnswConfig, IdStrategy,
+    OnConflict, Result, SearchResult, SparseVector, Vector, VectorDbError, VectorType,
+    WriteOutcome,
+};
+use crate::numa::{self, NodeArena, Topology};
+use crate::quantization::binary;
+use crate::storage::memtable::{self, Flush, Memtable};
+use crate::storage::norms::{self, NormIndex};
+use crate::storage::segment::VectorEncoding;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest collection name we accept
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Most points kept out of the graph before they're all inserted at once:
+/// each is scored exactly by every search until then
+pub const MAX_UNINDEXED: usize = 4096;
+
+/// Points per norm block (see `norms`)
+const NORM_BLOCK_SIZE: u32 = norms::DEFAULT_BLOCK_SIZE;
+
+/// Per-candidate callback for `Collection::search_adjusted`: (id, vector,
+/// score) → the score to rank by, or None to drop the candidate
+pub type Adjust<'a> = dyn FnMut(&str, &Vector, f32) -> Result<Option<f32>> + 'a;
+
+/// An in-memory collection of vectors.
+#[derive(Debug, Clone)]
+pub struct Collection {
+    /// Unique collection name
+    pub name: String,
+
+    /// Dimension every vector must have
+    pub dimension: usize,
+
+    /// Metric used when searching this collection
+    pub distance: DistanceMetric,
+
+    /// Vectors are scaled to unit length on insert (see normalize.rs)
+    pub normalized: bool,
+
+    /// Components are f32, or bits stored packed (see quantization/binary.rs)
+    pub vector_type: VectorType,
+
+    /// Metadata fields every vector must carry (if declared)
+    pub schema: Option<CollectionSchema>,
+
+    /// Metadata values applied when an insert leaves them out
+    pub defaults: HashMap<String, String>,
+
+    /// Fields the server computes on every insert
+    pub computed: Vec<ComputedField>,
+
+    /// Collection that mirrors inserts and deletes during an embedding
+    /// model upgrade
+    pub shadow: Option<String>,
+
+    /// Maps raw scores to relevance probabilities (see calibration.rs)
+    pub calibration: Option<Calibration>,
+
+    /// Deletes and destructive rewrites need force plus the admin key
+    pub protected: bool,
+
+    /// Writes are refused, searches still run (migrations, legal holds)
+    pub read_only: bool,
+
+    /// Callbacks run around inserts and searches (see hooks.rs)
+    pub hooks: Hooks,
+
+    /// Stored vectors: id → vector. A binary collection keeps their
+    /// components packed in `codes` instead, and one with an `arena` keeps
+    /// them there, leaving `data` empty here.
+    vectors: HashMap<String, Vector>,
+
+    /// A binary collection's points as packed bits: id → words (see
+    /// quantization/binary.rs)
+    codes: HashMap<String, Vec<u64>>,
+
+    /// Version numbers and (optionally) previous versions per ID
+    history: VersionHistory,
+
+    /// Generates IDs for points written without one
+    ids: IdGenerator,
+
+    /// Unix timestamp (seconds) of the last insert, update, or delete
+    last_write_at: Option<u64>,
+
+    /// Changes on every write or settings change (see `epoch`)
+    epoch: u64,
+
+    /// Cardinality sketches for the schema's fields (see stats.rs)
+    field_stats: FieldStats,
+
+    /// Graph index answering searches, if the collection was created with
+    /// one. Deletes apply to it at once; upserts wait in `unindexed`.
+    index: Option<Hnsw>,
+
+    /// Points upserted since the memtable was last sealed that aren't in
+    /// the graph yet (or are there with an older vector). Searches score
+    /// them exactly and merge them with the graph's results; they go into
+    /// the graph when the memtable is sealed, or MAX_UNINDEXED pile up.
+    unindexed: HashSet<String>,
+
+    /// Build a graph once the collection is this large (see `wants_index`)
+    auto_index: Option<AutoIndex>,
+
+    /// While a graph is built in the background from a copy of the
+    /// points: the IDs written since the copy, replayed onto the new graph
+    /// when it's swapped in (see `begin_index_build`)
+    rebuild: Option<HashSet<String>>,
+
+    /// The components of every point of the collection's dimension, split
+    /// across NUMA nodes, when NUMA placement or huge pages are on (see
+    /// numa.rs). Binary collections keep `codes` instead.
+    arena: Option<NodeArena>,
+
+    /// Points grouped into blocks by norm, so exact dot and Euclidean
+    /// searches can skip the blocks that can't beat the top k found so far
+    /// (see storage/norms.rs). None for collections too small for it to
+    /// pay, and for binary or normalized ones, whose norms bound nothing.
+    norms: Option<NormIndex>,
+
+    /// Points upserted since `norms` was built. Searches score them in
+    /// full and skip their entries in the blocks, which may be stale; the
+    /// blocks are rebuilt once enough pile up (see `norms_pending`).
+    unblocked: HashSet<String>,
+
+    /// Writes since the last flush to a segment (see storage/memtable.rs)
+    memtable: Memtable,
+}
+
+/// Source of write epochs, shared by every collection in the process
+static NEXT_EPOCH: AtomicU64 = AtomicU64::new(1);
+
+fn next_epoch() -> u64 {
+    NEXT_EPOCH.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Collection {
+    /// Create an empty collection, validating its name and dimension.
+    pub fn new(name: &str, dimension: usize, distance: DistanceMetric) -> Result<Self> {
+        validate_name(name)?;
+        limits::check_dimension(dimension)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            dimension,
+            distance,
+            normalized: false,
+            vector_type: VectorType::Float32,
+            schema: None,
+            defaults: HashMap::new(),
+            computed: Vec::new(),
+            shadow: None,
+            protected: false,
+            read_only: false,
+            hooks: Hooks::default(),
+            calibration: None,
+            vectors: HashMap::new(),
+            codes: HashMap::new(),
+            history: VersionHistory::default(),
+            ids: IdGenerator::default(),
+            last_write_at: None,
+            epoch: next_epoch(),
+            field_stats: FieldStats::default(),
+            index: None,
+            unindexed: HashSet::new(),
+            auto_index: None,
+            rebuild: None,
+            arena: numa::arena_storage().then(|| NodeArena::new(Topology::get(), dimension)),
+            norms: None,
+            unblocked: HashSet::new(),
+            memtable: Memtable::default(),
+        })
+    }
+
+    /// Create a collection from an API request
+    pub fn from_request(req: &CreateCollectionRequest) -> Result<Self> {
+        let distance = match req.vector_type {
+            VectorType::Float32 => req.distance.unwrap_or_else(metric::default_metric),
+            VectorType::Binary => req.distance.unwrap_or(DistanceMetric::Hamming),
+        };
+        if req.vector_type == VectorType::Binary && distance != DistanceMetric::Hamming {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "binary collections are searched by hamming distance, not {}",
+                metric::name(distance)
+            )));
+        }
+        let mut collection = Self::new(&req.name, req.dimension, distance)?;
+        collection.vector_type = req.vector_type;
+        if req.vector_type == VectorType::Binary {
+            collection.arena = None;
+        }
+        if let Some(schema) = &req.schema {
+            let mut seen = HashSet::new();
+            for field in &schema.fields {
+                if field.name.is_empty() || !seen.insert(field.name.as_str()) {
+                    return Err(VectorDbError::InvalidParameter(format!(
+                        "schema field names must be unique and non-empty: '{}'",
+                        field.name
+                    )));
+                }
+            }
+
+            // Defaults and computed fields must satisfy the schema too,
+            // otherwise every insert relying on them would be rejected.
+            for field in &schema.fields {
+                if let Some(value) = req.defaults.get(&field.name) {
+                    if !field.field_type.accepts(value) {
+                        return Err(VectorDbError::InvalidParameter(format!(
+                            "default for '{}' must be {}, got '{}'",
+                            field.name, field.field_type, value
+                        )));
+                    }
+                }
+                if let Some(c) = req.computed.iter().find(|c| c.key() == field.name) {
+                    let sample = match c.field_type() {
+                        FieldType::Float => "0.5",
+                        _ => "0",
+                    };
+                    if !field.field_type.accepts(sample) {
+                        return Err(VectorDbError::InvalidParameter(format!(
+                            "computed field '{}' is {}, schema declares {}",
+                            field.name,
+                            c.field_type(),
+                            field.field_type
+                        )));
+                    }
+                }
+            }
+            collection.field_stats.track(schema);
+            collection.schema = Some(schema.clone());
+        }
+        collection.defaults = req.defaults.clone();
+        collection.computed = req.computed.clone();
+        collection.history = VersionHistory::new(req.max_versions);
+        ids::validate_strategy(req.id_strategy).map_err(VectorDbError::InvalidParameter)?;
+        collection.ids = IdGenerator::new(req.id_strategy);
+        collection.protected = req.protected;
+        if req.normalized {
+            normalize::check_metric(distance)?;
+            collection.normalized = true;
+        }
+        if let Some(config) = req.hnsw {
+            hnsw::validate(&config).map_err(VectorDbError::InvalidParameter)?;
+            collection.index = Some(collection.empty_index(config));
+        }
+        if let Some(auto) = req.auto_index {
+            if auto.threshold == 0 {
+                return Err(VectorDbError::InvalidParameter(
+                    "auto_index threshold must be at least 1 (create the collection with \
+                     'hnsw' to index it from the start)"
+                        .into(),
+                ));
+            }
+            hnsw::validate(&auto.hnsw).map_err(VectorDbError::InvalidParameter)?;
+            collection.auto_index = Some(auto);
+        }
+        Ok(collection)
+    }
+
+    /// Rebuild a collection from its saved configuration and points.
+    ///
+    /// Points are stored as they were saved: defaults and computed fields
+    /// were applied when they were first written, so they aren't re-run.
+    pub fn restore(info: &CollectionInfo, points: Vec<(String, Vector)>) -> Result<Self> {
+        let req = CreateCollectionRequest {
+            name: info.name.clone(),
+            dimension: info.dimension,
+            distance: Some(info.distance),
+            schema: info.schema.clone(),
+            defaults: info.defaults.clone(),
+            computed: info.computed.clone(),
+            max_versions: info.max_versions,
+            id_strategy: info.id_strategy,
+            protected: info.protected,
+            hnsw: info.hnsw,
+            auto_index: info.auto_index,
+            normalized: info.normalized,
+            vector_type: info.vector_type,
+        };
+        let mut collection = Self::from_request(&req)?;
+        collection.reconfigure(info);
+        for (id, vector) in points {
+            collection.check_dimension(vector.dimension())?;
+            collection.insert_prepared(id, vector);
+        }
+        collection.index_pending();
+        collection.relayout_index();
+        collection.build_norms();
+        // Restored points are already on disk
+        collection.memtable.take();
+        Ok(collection)
+    }
+
+    /// Apply the settings that can change after creation (shadow,
+    /// calibration, protection, read-only) from `info`.
+    pub fn reconfigure(&mut self, info: &CollectionInfo) {
+        self.shadow = info.shadow.clone();
+        self.calibration = info.calibration.clone();
+        self.protected = info.protected;
+        self.read_only = info.read_only;
+        if info.hnsw != self.index.as_ref().map(Hnsw::config) {
+            // An index built (or dropped) since the info was saved
+            self.index = info.hnsw.map(|config| {
+                let mut index = self.empty_index(config);
+                for (id, vector) in &self.vectors {
+                    index.insert(id, &self.components(id, vector));
+                }
+                index.relayout();
+                index
+            });
+            self.unindexed.clear();
+        }
+        self.epoch = next_epoch();
+    }
+
+    /// An empty graph for this collection's vectors
+    pub fn empty_index(&self, config: HnswConfig) -> Hnsw {
+        let mut index = Hnsw::new(config, self.distance, self.dimension);
+        index.assume_unit_vectors(self.normalized);
+        index
+    }
+
+    /// Copies of the stored points, for building an index without holding
+    /// the collection
+    pub fn index_points(&self) -> Vec<(String, Vec<f32>)> {
+        self.vectors
+            .iter()
+            .map(|(id, v)| (id.clone(), self.components(id, v).into_owned()))
+            .collect()
+    }
+
+    /// Start a background index build: the points to build from, copied
+    /// now. Searches keep using the current graph (or scan) meanwhile, and
+    /// the IDs written from here on are recorded for `install_index`.
+    /// Fails if a build is already running.
+    pub fn begin_index_build(&mut self) -> Result<Vec<(String, Vec<f32>)>> {
+        if self.rebuild.is_some() {
+            return Err(VectorDbError::Conflict(format!(
+                "an index build on '{}' is already running",
+                self.name
+            )));
+        }
+        self.rebuild = Some(HashSet::new());
+        Ok(self.index_points())
+    }
+
+    /// Give up on a background build without installing anything
+    pub fn abandon_index_build(&mut self) {
+        self.rebuild = None;
+    }
+
+    /// Make `index` answer this collection's searches, replacing any graph
+    /// it had, in one step. The index was built from an earlier copy of the
+    /// points, so the writes made since are applied to it first; returns
+    /// how many. With a build begun by `begin_index_build` that's its
+    /// recorded delta; otherwise every point is compared with the graph.
+    pub fn install_index(&mut self, mut index: Hnsw) -> Result<usize> {
+        if index.metric() != self.distance {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "index was built for {} but the collection uses {}",
+                metric::name(index.metric()),
+                metric::name(self.distance)
+            )));
+        }
+        index.assume_unit_vectors(self.normalized);
+        let caught_up = match self.rebuild.take() {
+            Some(delta) => {
+                for id in &delta {
+                    match self.get(id) {
+                        Some(v) if v.dimension() == self.dimension => index.insert(id, &v.data),
+                        _ => {
+                            index.remove(id);
+                        }
+                    }
+                }
+                delta.len()
+            }
+            None => self.catch_up(&mut index),
+        };
+        // The copy it was built from had them, or the delta did
+        self.unindexed.clear();
+        self.index = Some(index);
+        self.epoch = next_epoch();
+        Ok(caught_up)
+    }
+
+    /// Bring `index` in line with the stored points by comparing them all;
+    /// returns how many differed
+    fn catch_up(&self, index: &mut Hnsw) -> usize {
+        let stale: Vec<String> = index
+            .ids()
+            .filter(|id| !self.vectors.contains_key(*id))
+            .map(str::to_string)
+            .collect();
+        let mut caught_up = stale.len();
+        for id in stale {
+            index.remove(&id);
+        }
+        for (id, vector) in &self.vectors {
+            let components = self.components(id, vector);
+            if components.len() != self.dimension {
+                continue;
+            }
+            if index.vector_of(id) != Some(components.as_ref()) {
+                index.insert(id, &components);
+                caught_up += 1;
+            }
+        }
+        caught_up
+    }
+
+    /// The graph to build now, if the collection has reached its
+    /// auto_index threshold without a graph or a build under way
+    pub fn wants_index(&self) -> Option<HnswConfig> {
+        let auto = self.auto_index?;
+        let due = self.index.is_none()
+            && self.rebuild.is_none()
+            && !self.read_only
+            && self.vectors.len() >= auto.threshold;
+        due.then_some(auto.hnsw)
+    }
+
+    /// When a graph gets built automatically, if at all
+    pub fn auto_index(&self) -> Option<AutoIndex> {
+        self.auto_index
+    }
+
+    /// Where the collection stands between exact scans and a graph
+    pub fn index_state(&self) -> IndexState {
+        match (&self.index, self.auto_index) {
+            _ if self.rebuild.is_some() => IndexState::Building,
+            (Some(_), _) => IndexState::Indexed,
+            (None, Some(_)) => IndexState::BelowThreshold,
+            (None, None) => IndexState::Flat,
+        }
+    }
+
+    /// Renumber the graph's nodes so traversals read nearby memory (see
+    /// engine/hnsw.rs); None if the collection has no graph
+    pub fn relayout_index(&mut self) -> Option<Layout> {
+        self.index.as_mut().map(Hnsw::relayout)
+    }
+
+    /// Write epoch: a process-wide unique number that changes whenever the
+    /// collection's contents or search settings do, so search results
+    /// tagged with it can be cached (see cache.rs)
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Fail with ReadOnly if writes to this collection are frozen
+    pub fn check_writable(&self) -> Result<()> {
+        match self.read_only {
+            true => Err(VectorDbError::ReadOnly(format!(
+                "collection '{}' is read-only",
+                self.name
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    /// Apply defaults, computed fields, and before_insert hooks, then
+    /// validate (and scale to unit length, in a normalized collection).
+    ///
+    /// Client-supplied metadata wins over defaults; computed fields always
+    /// overwrite whatever the client sent. Hooks see (and may change) the
+    /// result of both.
+    pub fn prepare(&self, id: &str, mut vector: Vector) -> Result<Vector> {
+        for (key, value) in &self.defaults {
+            vector
+                .metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+
+        if !self.computed.is_empty() {
+            let now = unix_now();
+            for field in &self.computed {
+                let value = field.compute(&vector, now);
+                vector.metadata.insert(field.key().to_string(), value);
+            }
+        }
+
+        self.hooks.before_insert(&self.name, id, &mut vector)?;
+        if let Some(sparse) = &mut vector.sparse {
+            sparse.sort();
+        }
+        if vector.sparse.as_ref().is_some_and(SparseVector::is_empty) {
+            vector.sparse = None;
+        }
+        self.validate(id, &vector)?;
+        if self.normalized {
+            normalize::to_unit(&mut vector.data);
+        }
+        Ok(vector)
+    }
+
+    /// Decide how a write of `vector` under `id` plays out under `policy`,
+    /// returning the prepared vector to store (None when skipped).
+    ///
+    /// With MergeMetadata, existing metadata keys the write doesn't set are
+    /// carried over before defaults, computed fields, and the schema apply.
+    pub fn resolve(
+        &self,
+        id: &str,
+        vector: Vector,
+        policy: OnConflict,
+    ) -> Result<(WriteOutcome, Option<Vector>)> {
+        self.resolve_over(id, vector, policy, self.vectors.get(id))
+    }
+
+    /// `resolve` against `existing` rather than the stored point: for a
+    /// batch that repeats an ID, the version an earlier item staged.
+    pub fn resolve_over(
+        &self,
+        id: &str,
+        mut vector: Vector,
+        policy: OnConflict,
+        existing: Option<&Vector>,
+    ) -> Result<(WriteOutcome, Option<Vector>)> {
+        let Some(existing) = existing else {
+            return Ok((WriteOutcome::Inserted, Some(self.prepare(id, vector)?)));
+        };
+        match policy {
+            OnConflict::Error => Err(VectorDbError::Conflict(format!(
+                "point '{}' already exists",
+                id
+            ))),
+            OnConflict::Skip => Ok((WriteOutcome::Skipped, None)),
+            OnConflict::Overwrite => {
+                Ok((WriteOutcome::Overwritten, Some(self.prepare(id, vector)?)))
+            }
+            OnConflict::MergeMetadata => {
+                for (key, value) in &existing.metadata {
+                    vector
+                        .metadata
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+                Ok((WriteOutcome::Merged, Some(self.prepare(id, vector)?)))
+            }
+        }
+    }
+
+    /// Check that `vector` could be inserted under `id` without inserting it
+    pub fn validate(&self, id: &str, vector: &Vector) -> Result<()> {
+        if id.is_empty() {
+            return Err(VectorDbError::InvalidParameter(
+                "vector ID cannot be empty".into(),
+            ));
+        }
+        self.check_dimension(vector.dimension())?;
+        if self.vector_type == VectorType::Binary {
+            binary::check_bits(&vector.data)?;
+        }
+        if let Some(sparse) = &vector.sparse {
+            sparse.check().map_err(VectorDbError::InvalidParameter)?;
+        }
+
+        if let Some(schema) = &self.schema {
+            let mut errors = schema.validate(&vector.metadata);
+            if !errors.is_empty() {
+                for e in &mut errors {
+                    e.id = Some(id.to_string());
+                }
+                return Err(VectorDbError::SchemaViolation(errors));
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert or replace a vector. Returns `true` if the ID already existed.
+    pub fn insert(&mut self, id: String, vector: Vector) -> Result<bool> {
+        let vector = self.prepare(&id, vector)?;
+        Ok(self.insert_prepared(id, vector))
+    }
+
+    /// Store a vector that already went through `prepare`.
+    pub fn insert_prepared(&mut self, id: String, mut vector: Vector) -> bool {
+        self.ids.observe(&id);
+        self.field_stats.observe(&vector.metadata);
+        let now = unix_now();
+        if self.index.is_some() {
+            self.unindexed.insert(id.clone());
+        }
+        self.unblocked.insert(id.clone());
+        let (stowed, replaced) = self.stow(&id, &mut vector);
+        self.memtable
+            .put(&id, index::vector_bytes(&vector) + stowed);
+        if let Some(delta) = &mut self.rebuild {
+            delta.insert(id.clone());
+        }
+        let mut previous = self.vectors.insert(id.clone(), vector);
+        // History keeps whole vectors, for rollback to restore
+        if let (Some(old), Some(components)) = (previous.as_mut(), replaced) {
+            old.data = components;
+        }
+        let existed = previous.is_some();
+        self.history.record_write(&id, previous, now);
+        self.last_write_at = Some(now);
+        self.epoch = next_epoch();
+        if !self.hooks.is_empty() {
+            let stored = self.decoded(&id, &self.vectors[&id]);
+            self.hooks.after_insert(&self.name, &id, &stored);
+        }
+        if self.unindexed.len() >= MAX_UNINDEXED {
+            self.index_pending();
+        }
+        if self.norms_pending() {
+            self.build_norms();
+        }
+        existed
+    }
+
+    /// Have enough points been written since the norm blocks were built
+    /// that they're worth rebuilding? A fraction of the collection, so
+    /// rebuilds cost a constant amount per write however large it grows.
+    fn norms_pending(&self) -> bool {
+        self.unblocked.len() >= MAX_UNINDEXED.max(self.vectors.len() / 8)
+    }
+
+    /// Group every point into norm blocks afresh (see `norms`)
+    fn build_norms(&mut self) {
+        self.unblocked.clear();
+        let useful = self.vector_type == VectorType::Float32
+            && !self.normalized
+            && self.vectors.len() >= 2 * NORM_BLOCK_SIZE as usize;
+        self.norms = useful.then(|| {
+            let rows = self
+                .vectors
+                .keys()
+                .filter_map(|id| Some((id.as_str(), self.row(id)?)));
+            NormIndex::build(rows, NORM_BLOCK_SIZE)
+        });
+    }
+
+    /// Move `vector`'s components into `codes` or the arena, if the
+    /// collection keeps them there. Returns the bytes they take there (0
+    /// if they stay in `vector`) and the components they replaced under
+    /// `id`, if those were kept there too.
+    fn stow(&mut self, id: &str, vector: &mut Vector) -> (usize, Option<Vec<f32>>) {
+        if self.vector_type == VectorType::Binary {
+            let words = binary::pack(&std::mem::take(&mut vector.data));
+            let bytes = words.len() * std::mem::size_of::<u64>();
+            let replaced = self.codes.insert(id.to_string(), words);
+            return (
+                bytes,
+                replaced.map(|old| binary::unpack(&old, self.dimension)),
+            );
+        }
+        match &mut self.arena {
+            Some(arena) if vector.data.len() == self.dimension => {
+                let replaced = arena.upsert(id, &vector.data);
+                let components = std::mem::take(&mut vector.data);
+                (components.len() * std::mem::size_of::<f32>(), replaced)
+            }
+            // A point of another dimension doesn't fit a row, so it keeps
+            // its components
+            Some(arena) => (0, arena.remove(id)),
+            None => (0, None),
+        }
+    }
+
+    /// Insert the points waiting in `unindexed` into the graph. Returns
+    /// how many went in.
+    pub fn index_pending(&mut self) -> usize {
+        let Some(mut index) = self.index.take() else {
+            self.unindexed.clear();
+            return 0;
+        };
+        let pending = std::mem::take(&mut self.unindexed);
+        for id in &pending {
+            if let Some(vector) = self.vectors.get(id) {
+                index.insert(id, &self.components(id, vector));
+            }
+        }
+        self.index = Some(index);
+        pending.len()
+    }
+
+    /// Remove a vector and its history. Returns `true` if it existed.
+    pub fn delete(&mut self, id: &str) -> bool {
+        let existed = self.vectors.remove(id).is_some();
+        self.codes.remove(id);
+        if let Some(arena) = &mut self.arena {
+            arena.remove(id);
+        }
+        self.history.remove(id);
+        self.unindexed.remove(id);
+        self.unblocked.remove(id);
+        if let Some(index) = &mut self.index {
+            index.remove(id);
+        }
+        if existed {
+            self.memtable.delete(id);
+            if let Some(delta) = &mut self.rebuild {
+                delta.insert(id.to_string());
+            }
+            self.last_write_at = Some(unix_now());
+            self.epoch = next_epoch();
+        }
+        existed
+    }
+
+    /// Writes since the last flush
+    pub fn memtable(&self) -> &Memtable {
+        &self.memtable
+    }
+
+    /// Empty the memtable into a flush holding the current value of every
+    /// point it upserted, as of WAL sequence `seq`. None if there was
+    /// nothing to flush. Points whose dimension doesn't match the
+    /// collection's stay out (a segment has one dimension) and the flush is
+    /// marked incomplete, so the WAL keeps covering them.
+    pub fn seal_memtable(&mut self, seq: u64) -> Option<Flush> {
+        if self.memtable.is_empty() {
+            return None;
+        }
+        let sealed = self.memtable.take();
+        self.index_pending();
+        let upserted: Vec<(String, Vector)> = sealed
+            .upserts()
+            .filter_map(|id| self.get(id).map(|v| (id.to_string(), v.into_owned())))
+            .collect();
+        let count = upserted.len();
+        let mut points: Vec<(String, Vector)> = upserted
+            .into_iter()
+            .filter(|(_, v)| v.dimension() == self.dimension)
+            .collect();
+        let complete = points.len() == count;
+        points.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut deleted: Vec<String> = sealed.deletes().map(str::to_string).collect();
+        deleted.sort();
+        Some(Flush {
+            generation: memtable::next_generation(),
+            seq,
+            collection: self.name.clone(),
+            points,
+            deleted,
+            encoding: VectorEncoding::for_vector_type(self.vector_type),
+            complete,
+        })
+    }
+
+    /// Apply a flushed segment found on disk at startup. Returns how many
+    /// of its writes applied (points of another dimension are skipped).
+    pub fn apply_flush(&mut self, flush: Flush) -> usize {
+        let mut applied = 0;
+        for (id, vector) in flush.points {
+            if vector.dimension() == self.dimension {
+                self.insert_prepared(id, vector);
+                applied += 1;
+            }
+        }
+        for id in &flush.deleted {
+            self.delete(id);
+            applied += 1;
+        }
+        // Already on disk, in the segment just read
+        self.memtable.take();
+        applied
+    }
+
+    /// Check a conditional write's predicate against the stored point.
+    ///
+    /// A point that doesn't exist never satisfies a condition, so `if` can't
+    /// be used to create points — only to move existing ones between states.
+    pub fn check_condition(&self, id: &str, condition: &Filter, source: &str) -> Result<()> {
+        match self.vectors.get(id) {
+            Some(vector) if condition.matches(&vector.metadata) => Ok(()),
+            Some(_) => Err(VectorDbError::PreconditionFailed(format!(
+                "point '{}' does not match {}",
+                id, source
+            ))),
+            None => Err(VectorDbError::PreconditionFailed(format!(
+                "point '{}' does not exist",
+                id
+            ))),
+        }
+    }
+
+    /// How this collection generates IDs
+    pub fn id_strategy(&self) -> IdStrategy {
+        self.ids.strategy()
+    }
+
+    /// Draw a fresh ID, or None if clients must supply their own
+    pub fn generate_id(&mut self) -> Option<String> {
+        self.ids.next_id()
+    }
+
+    /// The next auto-increment ID, for collections that draw them
+    pub fn id_counter(&self) -> Option<u64> {
+        self.ids.counter()
+    }
+
+    /// Carry on drawing auto-increment IDs from a persisted counter
+    pub fn resume_ids(&mut self, next: u64) {
+        self.ids.resume(next);
+    }
+
+    /// Look up a vector by ID. Where the components are kept apart (a
+    /// binary collection's bits, or the arena) it's a copy put together
+    /// from both; otherwise it's borrowed.
+    pub fn get(&self, id: &str) -> Option<Cow<'_, Vector>> {
+        self.vectors.get(id).map(|v| self.decoded(id, v))
+    }
+
+    /// The components of an f32 point, wherever they're kept (None for a
+    /// binary collection's, which are packed)
+    fn row(&self, id: &str) -> Option<&[f32]> {
+        match self.arena.as_ref().and_then(|arena| arena.get(id)) {
+            Some(row) => Some(row),
+            None if self.codes.contains_key(id) => None,
+            None => self.vectors.get(id).map(|v| v.data.as_slice()),
+        }
+    }
+
+    /// Is a point stored under `id`?
+    pub fn contains(&self, id: &str) -> bool {
+        self.vectors.contains_key(id)
+    }
+
+    /// The point stored as `vector` under `id`, with its components
+    fn decoded<'a>(&'a self, id: &str, vector: &'a Vector) -> Cow<'a, Vector> {
+        match self.components(id, vector) {
+            // Kept in the vector itself
+            Cow::Borrowed(data) if std::ptr::eq(data, vector.data.as_slice()) => {
+                Cow::Borrowed(vector)
+            }
+            data => Cow::Owned(Vector {
+                data: data.into_owned(),
+                ..vector.clone()
+            }),
+        }
+    }
+
+    /// The components of the point stored as `vector` under `id`
+    fn components<'a>(&'a self, id: &str, vector: &'a Vector) -> Cow<'a, [f32]> {
+        if let Some(words) = self.codes.get(id) {
+            return Cow::Owned(binary::unpack(words, self.dimension));
+        }
+        match self.arena.as_ref().and_then(|arena| arena.get(id)) {
+            Some(row) => Cow::Borrowed(row),
+            None => Cow::Borrowed(&vector.data),
+        }
+    }
+
+    /// Current version number of `id` (0 if it doesn't exist)
+    pub fn version(&self, id: &str) -> u64 {
+        if self.vectors.contains_key(id) {
+            self.history.current_version(id)
+        } else {
+            0
+        }
+    }
+
+    /// Retained previous versions of `id`, oldest first
+    pub fn history(&self, id: &str) -> Vec<VectorVersion> {
+        self.history.versions(id)
+    }
+
+    /// Restore `id` to a previous version.
+    ///
+    /// The restored vector is written as a new version, so the one being
+    /// replaced stays in history. Returns the new version number.
+    pub fn rollback(&mut self, id: &str, version: u64) -> Result<u64> {
+        if !self.vectors.contains_key(id) {
+            return Err(VectorDbError::NotFound(format!("vector '{}'", id)));
+        }
+        if self.history.max_versions == 0 {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "collection '{}' does not keep version history",
+                self.name
+            )));
+        }
+        let vector = self
+            .history
+            .find(id, version)
+            .map(|v| v.vector.clone())
+            .ok_or_else(|| {
+                VectorDbError::NotFound(format!("version {} of vector '{}'", version, id))
+            })?;
+
+        self.insert_prepared(id.to_string(), vector);
+        Ok(self.history.current_version(id))
+    }
+
+    /// The kind of index that answers this collection's searches
+    pub fn index_type(&self) -> IndexType {
+        match self.index {
+            Some(_) => IndexType::Hnsw,
+            None => IndexType::Flat,
+        }
+    }
+
+    /// Describe the index that answers this collection's searches
+    pub fn index_info(&self) -> IndexInfo {
+        let packed: usize = self.codes.values().map(|words| words.len() * 8).sum();
+        let arena = self.arena.as_ref().map_or(0, NodeArena::memory_bytes);
+        let memory = MemoryFootprint::measure(&self.vectors, self.history.retained_bytes())
+            .with_stored_vectors(packed + arena);
+        let mut info = IndexInfo {
+            collection: self.name.clone(),
+            index_type: self.index_type(),
+            state: self.index_state(),
+            auto_index_threshold: self.auto_index().map(|auto| auto.threshold),
+            exact: true,
+            metric: self.distance,
+            dimension: self.dimension,
+            parameters: serde_json::Map::new(),
+            vectors: self.vectors.len(),
+            nodes: None,
+            edges: None,
+            centroids: None,
+            memory,
+            built_at: None,
+            last_write_at: self.last_write_at,
+            memtable: MemtableInfo {
+                points: self.memtable.len(),
+                unindexed: self.unindexed.len(),
+                bytes: self.memtable.bytes(),
+                age_secs: self.memtable.age(Instant::now()).as_secs(),
+            },
+        };
+        if let Some(index) = &self.index {
+            let config = index.config();
+            info.exact = false;
+            info.parameters.insert("m".into(), config.m.into());
+            info.parameters
+                .insert("ef_construction".into(), config.ef_construction.into());
+            info.parameters
+                .insert("ef_search".into(), config.ef_search.into());
+            info.parameters
+                .insert("prefetch".into(), config.prefetch.into());
+            info.nodes = Some(index.nodes());
+            info.edges = Some(index.edges());
+            info.memory = info.memory.with_index(index.memory_bytes());
+            info.built_at = Some(index.built_at());
+        }
+        info
+    }
+
+    /// `id` rewritten as a unit vector with its original norm recorded
+    /// (see `normalize`), checked against the schema like any write but not
+    /// stored. None if it's missing or needs no rewriting.
+    pub fn normalized_point(&self, id: &str) -> Result<Option<Vector>> {
+        let Some(vector) = self.get(id).and_then(|v| normalize::normalized(&v)) else {
+            return Ok(None);
+        };
+        self.validate(id, &vector)?;
+        Ok(Some(vector))
+    }
+
+    /// Rewrite `id` as a unit vector (see `normalized_point`). Returns
+    /// `true` if it was rewritten.
+    pub fn normalize_point(&mut self, id: &str) -> Result<bool> {
+        match self.normalized_point(id)? {
+            Some(vector) => {
+                self.insert_prepared(id.to_string(), vector);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Every stored point, in no particular order (decoded like `get`'s)
+    pub fn points(&self) -> impl Iterator<Item = (&String, Cow<'_, Vector>)> {
+        self.vectors.iter().map(|(id, v)| (id, self.decoded(id, v)))
+    }
+
+    /// Snapshot of every stored ID
+    pub fn ids(&self) -> Vec<String> {
+        self.vectors.keys().cloned().collect()
+    }
+
+    /// Check that setting `set` and removing `remove` keeps metadata valid
+    /// under the schema, before touching any vector.
+    pub fn validate_metadata_update(
+        &self,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Result<()> {
+        let Some(schema) = &self.schema else {
+            return Ok(());
+        };
+
+        let mut errors = Vec::new();
+        for field in &schema.fields {
+            if let Some(value) = set.get(&field.name) {
+                if !field.field_type.accepts(value) {
+                    errors.push(FieldError::new(
+                        &field.name,
+                        format!("expected {}, got '{}'", field.field_type, value),
+                    ));
+                }
+            } else if field.required && remove.contains(&field.name) {
+                errors.push(FieldError::new(
+                    &field.name,
+                    "cannot remove a required field".to_string(),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(VectorDbError::SchemaViolation(errors))
+        }
+    }
+
+    /// `id` with a metadata edit applied, if it (still) matches `filter`,
+    /// without storing it (the edit is checked by
+    /// `validate_metadata_update`).
+    pub fn metadata_updated(
+        &self,
+        id: &str,
+        filter: &Filter,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> Option<Vector> {
+        let vector = self.get(id).filter(|v| filter.matches(&v.metadata))?;
+        let mut vector = vector.into_owned();
+        for key in remove {
+            vector.metadata.remove(key);
+        }
+        for (key, value) in set {
+            vector.metadata.insert(key.clone(), value.clone());
+        }
+        Some(vector)
+    }
+
+    /// Apply a metadata edit to `id` if it (still) matches `filter`.
+    ///
+    /// Returns `true` if the vector was updated.
+    pub fn update_metadata(
+        &mut self,
+        id: &str,
+        filter: &Filter,
+        set: &HashMap<String, String>,
+        remove: &[String],
+    ) -> bool {
+        match self.metadata_updated(id, filter, set, remove) {
+            Some(vector) => {
+                self.insert_prepared(id.to_string(), vector);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Top-k search using the collection's metric
+    pub fn search(&self, query: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.search_with(query, top_k, self.distance)
+    }
+
+    /// Top-k search ranked by `metric` (see `metric::resolve`): through the
+    /// graph index if there is one built for `metric`, else exact
+    pub fn search_with(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+    ) -> Result<Vec<SearchResult>> {
+        self.run_search(query, top_k, metric, false)
+    }
+
+    /// Exact top-k search ranked by `metric`, bypassing any index
+    pub fn search_exact(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+    ) -> Result<Vec<SearchResult>> {
+        self.run_search(query, top_k, metric, true)
+    }
+
+    /// The graph index, if it can rank by `metric`
+    fn index_for(&self, metric: DistanceMetric) -> Option<&Hnsw> {
+        self.index.as_ref().filter(|index| index.metric() == metric)
+    }
+
+    /// The query and metric to score a search ranked by `metric` with.
+    /// Every stored vector of a normalized collection is unit length, so
+    /// cosine is the dot product with the unit query: same scores, without
+    /// the two norms per comparison.
+    fn scoring<'q>(
+        &self,
+        query: &'q [f32],
+        metric: DistanceMetric,
+    ) -> (Cow<'q, [f32]>, DistanceMetric) {
+        match metric {
+            DistanceMetric::Cosine if self.normalized => {
+                let mut unit = query.to_vec();
+                normalize::to_unit(&mut unit);
+                (Cow::Owned(unit), DistanceMetric::Dot)
+            }
+            _ => (Cow::Borrowed(query), metric),
+        }
+    }
+
+    /// `query` packed into bits, when a search scored by `kernel` compares
+    /// bits: a binary collection's ranked by hamming (see `score`)
+    fn packed(&self, query: &[f32], kernel: DistanceMetric) -> Option<Vec<u64>> {
+        let popcount = self.vector_type == VectorType::Binary && kernel == DistanceMetric::Hamming;
+        popcount.then(|| binary::pack(query))
+    }
+
+    /// Score the point stored as `vector` under `id` against a query from
+    /// `scoring`. Packed points are XORed with `bits`, the packed query (if
+    /// the search compares bits), and the differences counted by popcount;
+    /// under another metric they're unpacked first.
+    fn score(
+        &self,
+        id: &str,
+        vector: &Vector,
+        query: &[f32],
+        kernel: DistanceMetric,
+        bits: Option<&[u64]>,
+    ) -> f32 {
+        match (self.codes.get(id), bits) {
+            (Some(words), Some(bits)) => binary::hamming(words, bits) as f32,
+            _ => kernel.calculate(query, &self.components(id, vector)),
+        }
+    }
+
+    /// Exact top-k of every point, one at a time through `score`: how a
+    /// binary collection is scanned, its points staying packed
+    fn scan(
+        &self,
+        query: &[f32],
+        kernel: DistanceMetric,
+        metric: DistanceMetric,
+        top_k: usize,
+    ) -> Vec<SearchResult> {
+        let bits = self.packed(query, kernel);
+        let mut top = TopK::new(top_k, metric);
+        for (id, vector) in &self.vectors {
+            let score = self.score(id, vector, query, kernel, bits.as_deref());
+            top.push_with(score, || SearchResult::new(id.clone(), score));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Top-k through the graph, merged with exact scores for the points
+    /// not in it yet, so a point is searchable as soon as it's written.
+    /// `query` and `kernel` come from `scoring`.
+    fn graph_search(
+        &self,
+        index: &Hnsw,
+        query: &[f32],
+        kernel: DistanceMetric,
+        metric: DistanceMetric,
+        top_k: usize,
+        filter: Option<&Filter>,
+    ) -> Vec<SearchResult> {
+        let matches = |id: &str| {
+            filter.map_or(true, |f| {
+                self.vectors.get(id).is_some_and(|v| f.matches(&v.metadata))
+            })
+        };
+        if self.unindexed.is_empty() {
+            return match filter {
+                Some(_) => index.search(query, top_k, &matches),
+                None => index.search(query, top_k, &|_| true),
+            };
+        }
+
+        // The graph may hold an older vector for an unindexed ID
+        let from_graph = index.search(query, top_k, &|id| {
+            !self.unindexed.contains(id) && matches(id)
+        });
+        let mut top = TopK::new(top_k, metric);
+        for hit in from_graph {
+            top.push_result(hit);
+        }
+        let bits = self.packed(query, kernel);
+        for id in &self.unindexed {
+            let Some(vector) = self.vectors.get(id) else {
+                continue;
+            };
+            if filter.is_some_and(|f| !f.matches(&vector.metadata)) {
+                continue;
+            }
+            let score = self.score(id, vector, query, kernel, bits.as_deref());
+            top.push_with(score, || SearchResult::new(id.clone(), score));
+        }
+        top.into_sorted_vec()
+    }
+
+    fn run_search(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+        exact: bool,
+    ) -> Result<Vec<SearchResult>> {
+        if query.is_empty() {
+            return Err(VectorDbError::EmptyVector);
+        }
+        self.check_dimension(query.len())?;
+        self.hooks.before_search(&self.name, query, top_k)?;
+
+        let (scored, kernel) = self.scoring(query, metric);
+        if let Some(index) = self.index_for(metric).filter(|_| !exact) {
+            let mut results = self.graph_search(index, &scored, kernel, metric, top_k, None);
+            self.calibrate(metric, &mut results);
+            self.hooks.after_search(&self.name, query, &mut results);
+            return Ok(results);
+        }
+        if self.vector_type == VectorType::Binary {
+            let mut results = self.scan(&scored, kernel, metric, top_k);
+            self.calibrate(metric, &mut results);
+            self.hooks.after_search(&self.name, query, &mut results);
+            return Ok(results);
+        }
+        let workers = search::scan_workers(self.vectors.len());
+        let mut results = match (&self.norms, &self.arena) {
+            (Some(norms), _) if norms::prunes(kernel) => {
+                self.scan_by_norm(norms, &scored, kernel, top_k, workers)
+            }
+            (_, Some(arena)) if workers > 1 => arena.search(&scored, kernel, top_k, workers),
+            (_, Some(arena)) => search::brute_force(&scored, kernel, top_k, arena.rows()),
+            (_, None) => {
+                let candidates = self
+                    .vectors
+                    .iter()
+                    .map(|(id, v)| (id.as_str(), v.data.as_slice()));
+                if workers > 1 {
+                    let candidates: Vec<_> = candidates.collect();
+                    search::parallel_brute_force(&scored, kernel, top_k, &candidates, workers)
+                } else {
+                    search::brute_force(&scored, kernel, top_k, candidates)
+                }
+            }
+        };
+        self.calibrate(metric, &mut results);
+        self.hooks.after_search(&self.name, query, &mut results);
+        Ok(results)
+    }
+
+    /// Exact top-k through the norm blocks, skipping those that can't beat
+    /// the threshold. Points written since the blocks were built are
+    /// scored first, so the threshold starts where they leave it.
+    fn scan_by_norm(
+        &self,
+        norms: &NormIndex,
+        query: &[f32],
+        kernel: DistanceMetric,
+        top_k: usize,
+        workers: usize,
+    ) -> Vec<SearchResult> {
+        let fresh = self
+            .unblocked
+            .iter()
+            .filter_map(|id| Some((id.as_str(), self.row(id)?)));
+        let scored = search::brute_force(query, kernel, top_k, fresh);
+        let query_norm = norms::norm(query);
+        let blocks = norms
+            .blocks()
+            .map(|((min, max), ids)| (norms::best_score(kernel, query_norm, min, max), ids))
+            .collect();
+        // A point in `unblocked` may have a new norm since its block was
+        // built, and was scored above anyway
+        let row = |id: &str| match self.unblocked.contains(id) {
+            true => None,
+            false => self.row(id),
+        };
+        search::bounded_brute_force(query, kernel, top_k, blocks, &row, scored, workers)
+    }
+
+    /// Top-k search for every query in `queries`, results in query order.
+    ///
+    /// Through the graph index as `search_with` would, unless `exact`;
+    /// otherwise one blocked pass over the points scores all the queries,
+    /// so each stored vector is read once per batch rather than per query.
+    pub fn search_batch(
+        &self,
+        queries: &[&[f32]],
+        top_k: usize,
+        metric: DistanceMetric,
+        exact: bool,
+    ) -> Result<Vec<Vec<SearchResult>>> {
+        for query in queries {
+            if query.is_empty() {
+                return Err(VectorDbError::EmptyVector);
+            }
+            self.check_dimension(query.len())?;
+            self.hooks.before_search(&self.name, query, top_k)?;
+        }
+
+        let scored: Vec<_> = queries.iter().map(|q| self.scoring(q, metric)).collect();
+        let kernel = scored.first().map_or(metric, |(_, kernel)| *kernel);
+        let scored: Vec<&[f32]> = scored.iter().map(|(q, _)| q.as_ref()).collect();
+        let mut batch = match self.index_for(metric).filter(|_| !exact) {
+            Some(index) => scored
+                .iter()
+                .map(|query| self.graph_search(index, query, kernel, metric, top_k, None))
+                .collect(),
+            None if self.vector_type == VectorType::Binary => scored
+                .iter()
+                .map(|query| self.scan(query, kernel, metric, top_k))
+                .collect(),
+            None => {
+                let candidates: Vec<_> = match &self.arena {
+                    Some(arena) => arena.rows().collect(),
+                    None => self
+                        .vectors
+                        .iter()
+                        .map(|(id, v)| (id.as_str(), v.data.as_slice()))
+                        .collect(),
+                };
+                gemm::batch_brute_force(&scored, kernel, top_k, &candidates)
+            }
+        };
+        for (query, results) in queries.iter().zip(&mut batch) {
+            self.calibrate(metric, results);
+            self.hooks.after_search(&self.name, query, results);
+        }
+        Ok(batch)
+    }
+
+    /// Attach relevance probabilities to `results`, scored by `metric`
+    fn calibrate(&self, metric: DistanceMetric, results: &mut [SearchResult]) {
+        // A calibration only holds for the metric its scores came from
+        if let Some(calibration) = self.calibration.as_ref().filter(|c| c.metric == metric) {
+            for hit in results {
+                hit.probability = Some(calibration.probability(hit.score));
+            }
+        }
+    }
+
+    /// Exact top-k search where `adjust` sees every candidate first and
+    /// returns its score (possibly changed), or None to drop it.
+    ///
+    /// Calibrated probabilities are only attached if no score was changed.
+    pub fn search_adjusted(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+        adjust: &mut Adjust<'_>,
+    ) -> Result<Vec<SearchResult>> {
+        if query.is_empty() {
+            return Err(VectorDbError::EmptyVector);
+        }
+        self.check_dimension(query.len())?;
+        self.hooks.before_search(&self.name, query, top_k)?;
+
+        let (scored, kernel) = self.scoring(query, metric);
+        let bits = self.packed(&scored, kernel);
+        let mut rescored = false;
+        let mut top = TopK::new(top_k, metric);
+        for (id, vector) in &self.vectors {
+            let score = self.score(id, vector, &scored, kernel, bits.as_deref());
+            if let Some(adjusted) = adjust(id, &self.decoded(id, vector), score)? {
+                rescored |= adjusted != score;
+                top.push_with(adjusted, || SearchResult::new(id.clone(), adjusted));
+            }
+        }
+        let mut results = top.into_sorted_vec();
+        if !rescored {
+            self.calibrate(metric, &mut results);
+        }
+        self.hooks.after_search(&self.name, query, &mut results);
+        Ok(results)
+    }
+
+    /// Top-k search over the vectors whose metadata matches `filter`, run
+    /// the way `filter_strategy` picks
+    pub fn search_filtered(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+        filter: &Filter,
+    ) -> Result<Vec<SearchResult>> {
+        let strategy = self.filter_strategy(filter, metric);
+        self.search_filtered_by(query, top_k, metric, filter, strategy)
+    }
+
+    /// How a search filtered by `filter` would run: through the graph when
+    /// the filter is expected to let enough points through, else as an
+    /// exact scan of the matching points (always, without a graph for
+    /// `metric`)
+    pub fn filter_strategy(&self, filter: &Filter, metric: DistanceMetric) -> FilterStrategy {
+        match self.index_for(metric) {
+            Some(index) => {
+                let selectivity = self.field_stats.selectivity(filter, self.vectors.len());
+                hnsw::plan(&index.config(), selectivity, self.vectors.len())
+            }
+            None => FilterStrategy::PreFilter,
+        }
+    }
+
+    /// Filtered top-k search with an explicit strategy. `InGraph` falls
+    /// back to a scan without a graph for `metric`.
+    pub fn search_filtered_by(
+        &self,
+        query: &[f32],
+        top_k: usize,
+        metric: DistanceMetric,
+        filter: &Filter,
+        strategy: FilterStrategy,
+    ) -> Result<Vec<SearchResult>> {
+        match self.index_for(metric) {
+            Some(index) if strategy == FilterStrategy::InGraph => {
+                if query.is_empty() {
+                    return Err(VectorDbError::EmptyVector);
+                }
+                self.check_dimension(query.len())?;
+                self.hooks.before_search(&self.name, query, top_k)?;
+                let (scored, kernel) = self.scoring(query, metric);
+                let mut results =
+                    self.graph_search(index, &scored, kernel, metric, top_k, Some(filter));
+                self.calibrate(metric, &mut results);
+                self.hooks.after_search(&self.name, query, &mut results);
+                Ok(results)
+            }
+            _ => self.search_adjusted(query, top_k, metric, &mut |_, vector, score| {
+                Ok(filter.matches(&vector.metadata).then_some(score))
+            }),
+        }
+    }
+
+    /// One page of an exact radius search ranked by `metric`: up to
+    /// `page_size` hits within `radius` (and matching `filter`, if given)
+    /// that follow `after`, plus the cursor for the next page if more remain
+    pub fn search_radius(
+        &self,
+        query: &[f32],
+        radius: f32,
+        metric: DistanceMetric,
+        page_size: usize,
+        after: Option<&Cursor>,
+        filter: Option<&Filter>,
+    ) -> Result<(Vec<SearchResult>, Option<Cursor>)> {
+        if query.is_empty() {
+            return Err(VectorDbError::EmptyVector);
+        }
+        if !radius.is_finite() {
+            return Err(VectorDbError::InvalidParameter(
+                "radius must be a finite number".into(),
+            ));
+        }
+        if page_size == 0 {
+            return Err(VectorDbError::InvalidParameter(
+                "top_k must be at least 1".into(),
+            ));
+        }
+        self.check_dimension(query.len())?;
+        self.hooks.before_search(&self.name, query, page_size)?;
+
+        // Only hits within the radius get their ID copied
+        let (scored, kernel) = self.scoring(query, metric);
+        let bits = self.packed(&scored, kernel);
+        let hits = self
+            .vectors
+            .iter()
+            .filter(|(_, v)| filter.map_or(true, |f| f.matches(&v.metadata)))
+            .filter_map(|(id, v)| {
+                let score = self.score(id, v, &scored, kernel, bits.as_deref());
+                search::within(metric, score, radius).then(|| SearchResult::new(id.clone(), score))
+            });
+        let (mut results, next) = search::radius_page(hits, metric, radius, page_size, after);
+        self.calibrate(metric, &mut results);
+        self.hooks.after_search(&self.name, query, &mut results);
+        Ok((results, next))
+    }
+
+    /// Top-k of the points with a sparse embedding that shares a nonzero
+    /// component with `query`, ranked by dot product (higher is better),
+    /// keeping only those matching `filter` if given. An exact scan: the
+    /// graph index and the collection's metric are for the dense vectors.
+    pub fn search_sparse(
+        &self,
+        query: &SparseVector,
+        top_k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<SearchResult>> {
+        let mut query = query.clone();
+        query.sort();
+        query.check().map_err(VectorDbError::InvalidParameter)?;
+        if query.is_empty() {
+            return Err(VectorDbError::EmptyVector);
+        }
+
+        let mut top = TopK::new(top_k, DistanceMetric::Dot);
+        for (id, vector) in &self.vectors {
+            let Some(sparse) = &vector.sparse else {
+                continue;
+            };
+            if filter.is_some_and(|f| !f.matches(&vector.metadata)) {
+                continue;
+            }
+            let score = query.dot(sparse);
+            if score == 0.0 {
+                continue;
+            }
+            top.push_with(score, || SearchResult::new(id.clone(), score));
+        }
+        Ok(top.into_sorted_vec())
+    }
+
+    /// Top-k points by `fusion` of two rankings: by dense vector under the
+    /// collection's metric, and by sparse embedding. Each ranking
+    /// contributes its `candidates` best hits (at least `top_k`), both
+    /// restricted to `filter` if given.
+    pub fn search_hybrid(
+        &self,
+        dense: &[f32],
+        sparse: &SparseVector,
+        top_k: usize,
+        candidates: usize,
+        fusion: Fusion,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<SearchResult>> {
+        let valid = match fusion {
+            Fusion::Rrf { k } => k.is_finite() && k >= 0.0,
+            Fusion::Weighted {
+                dense_weight,
+                sparse_weight,
+            } => [dense_weight, sparse_weight]
+                .iter()
+                .all(|w| w.is_finite() && *w >= 0.0),
+        };
+        if !valid {
+            return Err(VectorDbError::InvalidParameter(
+                "fusion k and weights must be finite and non-negative".into(),
+            ));
+        }
+        let candidates = candidates.max(top_k);
+        let dense_hits = match filter {
+            Some(filter) => self.search_filtered(dense, candidates, self.distance, filter)?,
+            None => self.search(dense, candidates)?,
+        };
+        let sparse_hits = self.search_sparse(sparse, candidates, filter)?;
+        Ok(search::fuse(
+            &dense_hits,
+            self.distance,
+            &sparse_hits,
+            fusion,
+            top_k,
+        ))
+    }
+
+    /// Per-field cardinality estimates for the schema's fields
+    pub fn field_stats(&self) -> &FieldStats {
+        &self.field_stats
+    }
+
+    /// Number of vectors stored
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Is the collection empty?
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Summary returned by the collections API
+    pub fn info(&self) -> CollectionInfo {
+        CollectionInfo {
+            name: self.name.clone(),
+            dimension: self.dimension,
+            distance: self.distance,
+            count: self.vectors.len(),
+            schema: self.schema.clone(),
+            defaults: self.defaults.clone(),
+            computed: self.computed.clone(),
+            max_versions: self.history.max_versions,
+            shadow: self.shadow.clone(),
+            id_strategy: self.ids.strategy(),
+            calibration: self.calibration.clone(),
+            protected: self.protected,
+            read_only: self.read_only,
+            hnsw: self.index.as_ref().map(Hnsw::config),
+            auto_index: self.auto_index,
+            normalized: self.normalized,
+            vector_type: self.vector_type,
+        }
+    }
+
+    fn check_dimension(&self, got: usize) -> Result<()> {
+        if got != self.dimension {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: self.dimension,
+                got,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Seconds since the Unix epoch (0 if the clock is before 1970)
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Collection names are used in URLs and (later) file names, so keep them
+/// to ASCII letters, digits, `_` and `-`.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "collection name must be 1-{} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "collection name '{}' may only contain letters, digits, '_' and '-'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HnswConfig;
+
+    #[test]
+    fn test_exact_search_through_norm_blocks_sees_every_write() {
+        let mut c = Collection::new("docs", 4, DistanceMetric::Dot).unwrap();
+        let point = |i: usize| {
+            let scale = 1.0 + (i % 50) as f32;
+            let angle = i as f32 * 0.37;
+            Vector::new(vec![angle.cos() * scale, angle.sin() * scale, 0.5, -0.25])
+        };
+        let n = 2 * NORM_BLOCK_SIZE as usize + 100;
+        for i in 0..n {
+            c.insert(format!("p{}", i), point(i)).unwrap();
+        }
+        c.build_norms();
+        assert!(c.norms.is_some());
+
+        // Written since the blocks were built: a new winner, a former
+        // winner shrunk, and a deleted one
+        c.insert("new".into(), Vector::new(vec![200.0, 0.0, 0.0, 0.0]))
+            .unwrap();
+        c.insert("p49".into(), Vector::new(vec![0.0, 0.0, 0.1, 0.0]))
+            .unwrap();
+        c.delete("p99");
+        assert_eq!(c.unblocked.len(), 2);
+
+        let query = [1.0, 0.2, 0.1, 0.0];
+        for metric in [DistanceMetric::Dot, DistanceMetric::Euclidean] {
+            let expected = search::brute_force(
+                &query,
+                metric,
+                10,
+                c.vectors
+                    .keys()
+                    .filter_map(|id| Some((id.as_str(), c.row(id)?))),
+            );
+            let hits = c.search_exact(&query, 10, metric).unwrap();
+            let ids = |r: &[SearchResult]| r.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+            assert_eq!(ids(&hits), ids(&expected), "{:?}", metric);
+        }
+        let hits = c.search_exact(&query, 3, DistanceMetric::Dot).unwrap();
+        assert_eq!(hits[0].id, "new");
+        assert!(hits.iter().all(|h| h.id != "p49" && h.id != "p99"));
+    }
+
+    #[test]
+    fn test_insert_checks_dimension() {
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        assert!(!c.insert("a".into(), Vector::new(vec![1.0, 0.0])).unwrap());
+        assert!(c.insert("a".into(), Vector::new(vec![0.0, 1.0])).unwrap());
+
+        let err = c.insert("b".into(), Vector::new(vec![1.0])).unwrap_err();
+        assert!(matches!(
+            err,
+            VectorDbError::DimensionMismatch {
+                expected: 2,
+                got: 1
+            }
+        ));
+        assert_eq!(c.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_conflict_policies() {
+        let mut c = Collection::new("docs", 1, DistanceMetric::Cosine).unwrap();
+        let mut existing = Vector::new(vec![1.0]);
+        existing.metadata.insert("lang".into(), "en".into());
+        existing.metadata.insert("title".into(), "old".into());
+        c.insert("a".into(), existing).unwrap();
+
+        let mut update = Vector::new(vec![2.0]);
+        update.metadata.insert("title".into(), "new".into());
+
+        let (outcome, _) = c.resolve("b", update.clone(), OnConflict::Error).unwrap();
+        assert_eq!(outcome, WriteOutcome::Inserted);
+        assert!(matches!(
+            c.resolve("a", update.clone(), OnConflict::Error),
+            Err(VectorDbError::Conflict(_))
+        ));
+        assert!(matches!(
+            c.resolve("a", update.clone(), OnConflict::Skip).unwrap(),
+            (WriteOutcome::Skipped, None)
+        ));
+
+        let (outcome, v) = c
+            .resolve("a", update.clone(), OnConflict::Overwrite)
+            .unwrap();
+        assert_eq!(outcome, WriteOutcome::Overwritten);
+        assert!(!v.unwrap().metadata.contains_key("lang"));
+
+        let (outcome, v) = c.resolve("a", update, OnConflict::MergeMetadata).unwrap();
+        let v = v.unwrap();
+        assert_eq!(outcome, WriteOutcome::Merged);
+        assert_eq!(v.data, vec![2.0]);
+        assert_eq!(
+            (v.metadata["lang"].as_str(), v.metadata["title"].as_str()),
+            ("en", "new")
+        );
+
+        // A version staged earlier in the batch counts as existing
+        let (outcome, staged) = c.resolve("b", v.clone(), OnConflict::Overwrite).unwrap();
+        assert_eq!(outcome, WriteOutcome::Inserted);
+        assert!(matches!(
+            c.resolve_over("b", v, OnConflict::Error, staged.as_ref()),
+            Err(VectorDbError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_dimension_limits() {
+        assert!(Collection::new("zero", 0, DistanceMetric::Cosine).is_err());
+        let err = Collection::new("huge", 1_000_000, DistanceMetric::Cosine).unwrap_err();
+        assert!(err.to_string().contains("supported dimensions"), "{}", err);
+    }
+
+    #[test]
+    fn test_search_uses_collection_metric() {
+        let mut c = Collection::new("geo", 2, DistanceMetric::Euclidean).unwrap();
+        c.insert("near".into(), Vector::new(vec![1.0, 1.0]))
+            .unwrap();
+        c.insert("far".into(), Vector::new(vec![9.0, 9.0])).unwrap();
+
+        let results = c.search(&[0.0, 0.0], 1).unwrap();
+        assert_eq!(results[0].id, "near");
+        assert!(c.search(&[0.0], 1).is_err());
+    }
+
+    #[test]
+    fn test_radius_search_pages_with_filter() {
+        let mut c = Collection::new("line", 1, DistanceMetric::Euclidean).unwrap();
+        for i in 0..10 {
+            let mut meta = HashMap::new();
+            meta.insert("parity".to_string(), (i % 2).to_string());
+            c.insert(
+                format!("p{}", i),
+                Vector::with_metadata(vec![i as f32], meta),
+            )
+            .unwrap();
+        }
+        let even = Filter::parse(r#"parity == "0""#).unwrap();
+
+        let (page, next) = c
+            .search_radius(&[0.0], 6.0, DistanceMetric::Euclidean, 2, None, Some(&even))
+            .unwrap();
+        let ids: Vec<_> = page.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["p0", "p2"]);
+
+        let (page, next) = c
+            .search_radius(
+                &[0.0],
+                6.0,
+                DistanceMetric::Euclidean,
+                2,
+                next.as_ref(),
+                Some(&even),
+            )
+            .unwrap();
+        let ids: Vec<_> = page.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["p4", "p6"]);
+        assert!(next.is_none());
+
+        assert!(c
+            .search_radius(&[0.0], f32::NAN, DistanceMetric::Euclidean, 2, None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_batch_search_matches_single_queries() {
+        let mut c = Collection::new("grid", 2, DistanceMetric::Euclidean).unwrap();
+        for i in 0..40 {
+            let point = vec![(i % 8) as f32, (i / 8) as f32];
+            c.insert(format!("p{}", i), Vector::new(point)).unwrap();
+        }
+        let queries: Vec<Vec<f32>> = (0..6).map(|i| vec![i as f32 * 1.3, 2.1]).collect();
+        let refs: Vec<&[f32]> = queries.iter().map(|q| q.as_slice()).collect();
+
+        let batch = c
+            .search_batch(&refs, 3, DistanceMetric::Euclidean, false)
+            .unwrap();
+        assert_eq!(batch.len(), queries.len());
+        for (query, results) in queries.iter().zip(&batch) {
+            let single = c.search(query, 3).unwrap();
+            let pairs = |r: &[SearchResult]| -> Vec<(String, f32)> {
+                r.iter().map(|h| (h.id.clone(), h.score)).collect()
+            };
+            assert_eq!(pairs(results), pairs(&single));
+        }
+
+        let bad: Vec<&[f32]> = vec![&[1.0, 2.0], &[1.0]];
+        assert!(c
+            .search_batch(&bad, 3, DistanceMetric::Euclidean, false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("docs_en-2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(&"x".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_schema_enforced_on_insert() {
+        use crate::models::FieldSchema;
+
+        let req = CreateCollectionRequest {
+            name: "typed".into(),
+            dimension: 1,
+            distance: Some(DistanceMetric::Cosine),
+            schema: Some(CollectionSchema {
+                fields: vec![FieldSchema {
+                    name: "lang".into(),
+                    field_type: FieldType::String,
+                    required: true,
+                }],
+            }),
+            defaults: HashMap::new(),
+            computed: Vec::new(),
+            max_versions: 0,
+            id_strategy: IdStrategy::Client,
+            protected: false,
+            hnsw: None,
+            auto_index: None,
+            normalized: false,
+            vector_type: VectorType::Float32,
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+
+        let err = c.insert("a".into(), Vector::new(vec![1.0])).unwrap_err();
+        match err {
+            VectorDbError::SchemaViolation(errors) => {
+                assert_eq!(errors[0].id.as_deref(), Some("a"));
+                assert_eq!(errors[0].field, "lang");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        let mut meta = HashMap::new();
+        meta.insert("lang".to_string(), "en".to_string());
+        assert!(c
+            .insert("a".into(), Vector::with_metadata(vec![1.0], meta))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_defaults_and_computed_fields() {
+        use crate::models::FieldSchema;
+
+        let mut defaults = HashMap::new();
+        defaults.insert("lang".to_string(), "en".to_string());
+        let req = CreateCollectionRequest {
+            name: "enriched".into(),
+            dimension: 2,
+            distance: Some(DistanceMetric::Cosine),
+            schema: Some(CollectionSchema {
+                fields: vec![FieldSchema {
+                    name: "lang".into(),
+                    field_type: FieldType::String,
+                    required: true,
+                }],
+            }),
+            defaults,
+            computed: vec![ComputedField::InsertedAt, ComputedField::Norm],
+            max_versions: 0,
+            id_strategy: IdStrategy::Client,
+            protected: false,
+            hnsw: None,
+            auto_index: None,
+            normalized: false,
+            vector_type: VectorType::Float32,
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+
+        // The default satisfies the required field
+        c.insert("a".into(), Vector::new(vec![3.0, 4.0])).unwrap();
+        let stored = c.get("a").unwrap();
+        assert_eq!(stored.metadata["lang"], "en");
+        assert_eq!(stored.metadata["norm"], "5");
+        assert!(stored.metadata["inserted_at"].parse::<u64>().unwrap() > 0);
+
+        // Client values win over defaults
+        let mut meta = HashMap::new();
+        meta.insert("lang".to_string(), "de".to_string());
+        c.insert("b".into(), Vector::with_metadata(vec![1.0, 0.0], meta))
+            .unwrap();
+        assert_eq!(c.get("b").unwrap().metadata["lang"], "de");
+    }
+
+    #[test]
+    fn test_default_must_match_schema_type() {
+        use crate::models::FieldSchema;
+
+        let mut defaults = HashMap::new();
+        defaults.insert("year".to_string(), "soon".to_string());
+        let req = CreateCollectionRequest {
+            name: "bad".into(),
+            dimension: 2,
+            distance: Some(DistanceMetric::Cosine),
+            schema: Some(CollectionSchema {
+                fields: vec![FieldSchema {
+                    name: "year".into(),
+                    field_type: FieldType::Integer,
+                    required: true,
+                }],
+            }),
+            defaults,
+            computed: Vec::new(),
+            max_versions: 0,
+            id_strategy: IdStrategy::Client,
+            protected: false,
+            hnsw: None,
+            auto_index: None,
+            normalized: false,
+            vector_type: VectorType::Float32,
+        };
+        assert!(Collection::from_request(&req).is_err());
+    }
+
+    #[test]
+    fn test_update_metadata_by_filter() {
+        let mut c = Collection::new("docs", 1, DistanceMetric::Cosine).unwrap();
+        for (id, category) in [("a", "old"), ("b", "old"), ("c", "keep")] {
+            let mut meta = HashMap::new();
+            meta.insert("category".to_string(), category.to_string());
+            meta.insert("tmp".to_string(), "1".to_string());
+            c.insert(id.into(), Vector::with_metadata(vec![1.0], meta))
+                .unwrap();
+        }
+
+        let filter = Filter::parse(r#"category == "old""#).unwrap();
+        let mut set = HashMap::new();
+        set.insert("category".to_string(), "new".to_string());
+        let remove = vec!["tmp".to_string()];
+
+        // Staging an edit leaves the stored point alone
+        let staged = c.metadata_updated("a", &filter, &set, &remove).unwrap();
+        assert_eq!(staged.metadata["category"], "new");
+        assert_eq!(c.get("a").unwrap().metadata["category"], "old");
+
+        let mut updated = 0;
+        for id in c.ids() {
+            if c.update_metadata(&id, &filter, &set, &remove) {
+                updated += 1;
+            }
+        }
+        assert_eq!(updated, 2);
+
+        // Already relabeled, so it no longer matches
+        assert!(!c.update_metadata("a", &filter, &set, &remove));
+        assert_eq!(c.get("a").unwrap().metadata["category"], "new");
+        assert!(!c.get("a").unwrap().metadata.contains_key("tmp"));
+        assert_eq!(c.get("c").unwrap().metadata["category"], "keep");
+    }
+
+    #[test]
+    fn test_history_and_rollback() {
+        let req = CreateCollectionRequest {
+            name: "versioned".into(),
+            dimension: 1,
+            distance: Some(DistanceMetric::Cosine),
+            schema: None,
+            defaults: HashMap::new(),
+            computed: Vec::new(),
+            max_versions: 2,
+            id_strategy: IdStrategy::Client,
+            protected: false,
+            hnsw: None,
+            auto_index: None,
+            normalized: false,
+            vector_type: VectorType::Float32,
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        c.insert("a".into(), Vector::new(vec![1.0])).unwrap();
+        c.insert("a".into(), Vector::new(vec![2.0])).unwrap();
+        c.insert("a".into(), Vector::new(vec![-1.0])).unwrap(); // bad re-embed
+        assert_eq!(c.version("a"), 3);
+        assert_eq!(c.history("a").len(), 2);
+
+        assert_eq!(c.rollback("a", 2).unwrap(), 4);
+        assert_eq!(c.get("a").unwrap().data, vec![2.0]);
+        // The bad version is still retained; v1 fell off the end
+        let kept: Vec<_> = c.history("a").iter().map(|v| v.version).collect();
+        assert_eq!(kept, vec![2, 3]);
+        assert!(matches!(
+            c.rollback("a", 1),
+            Err(VectorDbError::NotFound(_))
+        ));
+
+        let mut plain = Collection::new("plain", 1, DistanceMetric::Cosine).unwrap();
+        plain.insert("a".into(), Vector::new(vec![1.0])).unwrap();
+        assert!(plain.rollback("a", 1).is_err());
+    }
+
+    #[test]
+    fn test_generated_ids_skip_client_ids() {
+        let req = CreateCollectionRequest {
+            name: "tickets".into(),
+            dimension: 1,
+            distance: Some(DistanceMetric::Cosine),
+            schema: None,
+            defaults: HashMap::new(),
+            computed: Vec::new(),
+            max_versions: 0,
+            id_strategy: IdStrategy::AutoIncrement,
+            protected: false,
+            hnsw: None,
+            auto_index: None,
+            normalized: false,
+            vector_type: VectorType::Float32,
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        assert_eq!(c.info().id_strategy, IdStrategy::AutoIncrement);
+
+        let first = c.generate_id().unwrap();
+        c.insert(first.clone(), Vector::new(vec![1.0])).unwrap();
+        c.insert("10".into(), Vector::new(vec![1.0])).unwrap(); // imported
+        assert_eq!(
+            (first.as_str(), c.generate_id().unwrap().as_str()),
+            ("1", "11")
+        );
+
+        assert_eq!(
+            Collection::new("plain", 1, DistanceMetric::Cosine)
+                .unwrap()
+                .generate_id(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_normalize_point() {
+        let mut c = Collection::new("legacy", 2, DistanceMetric::Cosine).unwrap();
+        c.insert("long".into(), Vector::new(vec![3.0, 4.0]))
+            .unwrap();
+        c.insert("unit".into(), Vector::new(vec![1.0, 0.0]))
+            .unwrap();
+
+        assert!(c.normalize_point("long").unwrap());
+        assert!(!c.normalize_point("unit").unwrap());
+        assert!(!c.normalize_point("missing").unwrap());
+        let v = c.get("long").unwrap();
+        assert_eq!(v.data, vec![0.6, 0.8]);
+        assert_eq!(v.metadata[normalize::ORIGINAL_NORM_KEY], "5");
+        assert_eq!(c.version("long"), 2);
+
+        // The recorded norm goes through the schema like any metadata
+        let mut req = CreateCollectionRequest::new("typed", 2);
+        req.distance = Some(DistanceMetric::Cosine);
+        req.schema = Some(CollectionSchema {
+            fields: vec![crate::models::FieldSchema {
+                name: normalize::ORIGINAL_NORM_KEY.into(),
+                field_type: FieldType::Integer,
+                required: false,
+            }],
+        });
+        let mut c = Collection::from_request(&req).unwrap();
+        c.insert("long".into(), Vector::new(vec![0.3, 0.4]))
+            .unwrap();
+        assert!(matches!(
+            c.normalized_point("long"),
+            Err(VectorDbError::SchemaViolation(_))
+        ));
+        assert_eq!(c.get("long").unwrap().data, vec![0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_check_condition() {
+        let mut c = Collection::new("posts", 1, DistanceMetric::Cosine).unwrap();
+        let mut draft = Vector::new(vec![1.0]);
+        draft.metadata.insert("status".into(), "draft".into());
+        c.insert("p1".into(), draft).unwrap();
+
+        let source = r#"metadata.status=="draft""#;
+        let is_draft = Filter::parse(source).unwrap();
+        assert!(c.check_condition("p1", &is_draft, source).is_ok());
+        assert!(matches!(
+            c.check_condition("missing", &is_draft, source),
+            Err(VectorDbError::PreconditionFailed(_))
+        ));
+
+        let publish = HashMap::from([("status".to_string(), "published".to_string())]);
+        assert!(c.update_metadata("p1", &is_draft, &publish, &[]));
+        let err = c.check_condition("p1", &is_draft, source).unwrap_err();
+        assert!(err.to_string().contains(source), "{}", err);
+    }
+
+    #[test]
+    fn test_calibrated_search() {
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        c.insert("a".into(), Vector::new(vec![1.0, 0.0])).unwrap();
+        c.calibration = Some(Calibration {
+            metric: DistanceMetric::Cosine,
+            a: -10.0,
+            b: 5.0,
+            pairs: 6,
+            relevant: 3,
+            fitted_at: 0,
+        });
+
+        let hit = &c.search(&[1.0, 0.0], 1).unwrap()[0];
+        let p = hit.probability.unwrap();
+        assert!((p - 1.0 / (1.0 + (-5.0f32).exp())).abs() < 1e-6);
+
+        // Other metrics' scores aren't calibrated
+        let hit = &c.search_with(&[1.0, 0.0], 1, DistanceMetric::Dot).unwrap()[0];
+        assert!(hit.probability.is_none());
+
+        // The calibration is part of the saved configuration
+        let restored = Collection::restore(&c.info(), Vec::new()).unwrap();
+        assert_eq!(restored.calibration.unwrap().a, -10.0);
+    }
+
+    #[test]
+    fn test_sealed_memtable_replays_onto_another_copy() {
+        let mut c = Collection::new("docs", 2, DistanceMetric::Euclidean).unwrap();
+        let mut copy = c.clone();
+        c.insert("a".into(), Vector::new(vec![1.0, 0.0])).unwrap();
+        c.insert("b".into(), Vector::new(vec![0.0, 1.0])).unwrap();
+        c.insert("b".into(), Vector::new(vec![0.0, 2.0])).unwrap();
+        c.delete("a");
+        assert_eq!(c.memtable().len(), 2);
+
+        let flush = c.seal_memtable(0).unwrap();
+        assert!(c.memtable().is_empty());
+        assert!(c.seal_memtable(0).is_none());
+        assert_eq!(flush.collection, "docs");
+        assert_eq!(flush.deleted, ["a"]);
+        assert_eq!(flush.points.len(), 1);
+        assert_eq!(flush.points[0].1.data, [0.0, 2.0]);
+
+        copy.insert("a".into(), Vector::new(vec![1.0, 0.0]))
+            .unwrap();
+        assert_eq!(copy.apply_flush(flush), 2);
+        assert!(copy.get("a").is_none());
+        assert_eq!(copy.get("b").unwrap().data, [0.0, 2.0]);
+        assert!(copy.memtable().is_empty());
+    }
+
+    #[test]
+    fn test_fresh_writes_are_searchable_before_they_reach_the_graph() {
+        let req = CreateCollectionRequest {
+            distance: Some(DistanceMetric::Euclidean),
+            hnsw: Some(HnswConfig::default()),
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        for i in 0..30 {
+            let mut v = Vector::new(vec![i as f32, 0.0]);
+            v.metadata.insert("even".into(), (i % 2 == 0).to_string());
+            c.insert(format!("p{}", i), v).unwrap();
+        }
+        assert!(c.seal_memtable(0).is_some());
+        assert_eq!(c.index_info().nodes, Some(30));
+        let ids = |hits: Vec<SearchResult>| hits.into_iter().map(|h| h.id).collect::<Vec<_>>();
+
+        // Freshly inserted: only in the memtable, found anyway
+        c.insert("fresh".into(), Vector::new(vec![10.1, 0.0]))
+            .unwrap();
+        assert_eq!(c.index_info().nodes, Some(30));
+        assert_eq!(c.index_info().memtable.unindexed, 1);
+        assert_eq!(ids(c.search(&[10.0, 0.0], 2).unwrap()), ["p10", "fresh"]);
+
+        // Moved: the graph's older vector doesn't count any more
+        c.insert("p12".into(), Vector::new(vec![-5.0, 0.0]))
+            .unwrap();
+        assert_eq!(ids(c.search(&[11.9, 0.0], 1).unwrap()), ["p11"]);
+        assert_eq!(ids(c.search(&[-4.0, 0.0], 1).unwrap()), ["p12"]);
+        let batch = c
+            .search_batch(&[&[-4.0, 0.0]], 1, DistanceMetric::Euclidean, false)
+            .unwrap();
+        assert_eq!(ids(batch.into_iter().next().unwrap()), ["p12"]);
+
+        let mut tagged = Vector::new(vec![20.2, 0.0]);
+        tagged.metadata.insert("even".into(), "true".into());
+        c.insert("tagged".into(), tagged).unwrap();
+        let even = Filter::parse(r#"metadata.even=="true""#).unwrap();
+        let hits = c
+            .search_filtered_by(
+                &[21.3, 0.0],
+                2,
+                DistanceMetric::Euclidean,
+                &even,
+                FilterStrategy::InGraph,
+            )
+            .unwrap();
+        assert_eq!(ids(hits), ["p22", "tagged"]);
+
+        // Deleted before it ever reached the graph
+        c.delete("fresh");
+        assert_eq!(ids(c.search(&[10.1, 0.0], 1).unwrap()), ["p10"]);
+
+        // Sealing the memtable puts the rest into the graph
+        c.seal_memtable(0);
+        assert_eq!(c.index_info().memtable.unindexed, 0);
+        // 31 live, plus the tombstone of p12's old vector
+        assert_eq!(c.index_info().nodes, Some(32));
+        assert_eq!(ids(c.search(&[-4.0, 0.0], 1).unwrap()), ["p12"]);
+    }
+
+    #[test]
+    fn test_install_index_catches_up_with_writes() {
+        let mut c = Collection::new("late", 2, DistanceMetric::Euclidean).unwrap();
+        for i in 0..20 {
+            c.insert(format!("p{}", i), Vector::new(vec![i as f32, 0.0]))
+                .unwrap();
+        }
+        let config = HnswConfig {
+            m: 8,
+            ..HnswConfig::default()
+        };
+        let mut index = Hnsw::new(config, DistanceMetric::Euclidean, 2);
+        for (id, data) in c.begin_index_build().unwrap() {
+            index.insert(&id, &data);
+        }
+        assert!(matches!(
+            c.begin_index_build(),
+            Err(VectorDbError::Conflict(_))
+        ));
+
+        // Written while the graph was being built
+        c.delete("p3");
+        c.insert("p4".into(), Vector::new(vec![50.0, 0.0])).unwrap();
+        c.insert("new".into(), Vector::new(vec![3.1, 0.0])).unwrap();
+        c.insert("new".into(), Vector::new(vec![3.1, 0.0])).unwrap();
+        c.delete("missing");
+
+        // Only the delta is replayed, and the old graph's gone in one step
+        assert_eq!(c.install_index(index).unwrap(), 3);
+        assert_eq!(c.index_state(), IndexState::Indexed);
+        assert_eq!(c.index_type(), IndexType::Hnsw);
+        assert_eq!(c.info().hnsw, Some(config));
+        let ids: Vec<_> = c
+            .search(&[3.0, 0.0], 2)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(ids, vec!["new", "p2"]);
+
+        let wrong = Hnsw::new(config, DistanceMetric::Cosine, 2);
+        assert!(c.install_index(wrong).is_err());
+
+        // Replaying the saved info onto a copy without the graph rebuilds it
+        let mut replayed = Collection::new("late", 2, DistanceMetric::Euclidean).unwrap();
+        for (id, data) in c.index_points() {
+            replayed.insert(id, Vector::new(data)).unwrap();
+        }
+        replayed.reconfigure(&c.info());
+        assert_eq!(replayed.index_type(), IndexType::Hnsw);
+    }
+
+    #[test]
+    fn test_normalized_collection_scores_cosine_as_dot() {
+        let euclidean = CreateCollectionRequest {
+            distance: Some(DistanceMetric::Euclidean),
+            normalized: true,
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        assert!(Collection::from_request(&euclidean).is_err());
+
+        let plain = CreateCollectionRequest {
+            distance: Some(DistanceMetric::Cosine),
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        let normalized = CreateCollectionRequest {
+            normalized: true,
+            ..plain.clone()
+        };
+        let graph = CreateCollectionRequest {
+            hnsw: Some(HnswConfig::default()),
+            ..normalized.clone()
+        };
+        let mut collections: Vec<_> = [&plain, &normalized, &graph]
+            .into_iter()
+            .map(|req| Collection::from_request(req).unwrap())
+            .collect();
+        for c in &mut collections {
+            for i in 0..20 {
+                let v = Vector::new(vec![1.0 + i as f32, 20.0 - i as f32]);
+                c.insert(format!("p{}", i), v).unwrap();
+            }
+        }
+        assert_eq!(collections[0].get("p0").unwrap().data, [1.0, 20.0]);
+        let stored = &collections[1].get("p0").unwrap().data;
+        assert!((normalize::norm(stored) - 1.0).abs() < 1e-6, "{:?}", stored);
+        assert_eq!(collections[2].index_type(), IndexType::Hnsw);
+
+        let query = [30.0, 10.0];
+        let expected = collections[0].search(&query, 5).unwrap();
+        for c in &collections[1..] {
+            let got = c.search(&query, 5).unwrap();
+            for (e, g) in expected.iter().zip(&got) {
+                assert_eq!(e.id, g.id);
+                assert!(
+                    (e.score - g.score).abs() < 1e-5,
+                    "{} vs {}",
+                    e.score,
+                    g.score
+                );
+            }
+            let batch = c.search_batch(&[&query], 5, DistanceMetric::Cosine, true);
+            assert_eq!(batch.unwrap()[0][0].id, expected[0].id);
+        }
+
+        let restored = Collection::restore(&collections[1].info(), Vec::new()).unwrap();
+        assert!(restored.normalized);
+    }
+
+    #[test]
+    fn test_binary_collection_takes_bits_and_ranks_by_hamming() {
+        let binary = CreateCollectionRequest {
+            vector_type: VectorType::Binary,
+            ..CreateCollectionRequest::new("codes", 8)
+        };
+        let cosine = CreateCollectionRequest {
+            distance: Some(DistanceMetric::Cosine),
+            ..binary.clone()
+        };
+        assert!(Collection::from_request(&cosine).is_err());
+
+        let mut c = Collection::from_request(&binary).unwrap();
+        assert_eq!(c.distance, DistanceMetric::Hamming);
+        let bits = |s: &str| Vector::new(s.bytes().map(|b| (b - b'0') as f32).collect());
+        c.insert("a".into(), bits("11110000")).unwrap();
+        c.insert("b".into(), bits("11111100")).unwrap();
+        c.insert("c".into(), bits("00001111")).unwrap();
+        let err = c.insert("d".into(), Vector::new(vec![0.5; 8])).unwrap_err();
+        assert!(matches!(err, VectorDbError::InvalidParameter(_)), "{}", err);
+
+        let hits = c.search(&bits("11110001").data, 3).unwrap();
+        let ranked: Vec<_> = hits.iter().map(|h| (h.id.as_str(), h.score)).collect();
+        assert_eq!(ranked, [("a", 1.0), ("b", 3.0), ("c", 7.0)]);
+
+        let flush = c.seal_memtable(0).unwrap();
+        assert_eq!(flush.encoding, VectorEncoding::Binary);
+        let restored = Collection::restore(&c.info(), flush.points).unwrap();
+        assert_eq!(restored.vector_type, VectorType::Binary);
+        assert_eq!(restored.get("c").unwrap().data, bits("00001111").data);
+    }
+
+    #[test]
+    fn test_arena_holds_the_only_copy_of_the_components() {
+        let mut plain = Collection::new("plain", 3, DistanceMetric::Euclidean).unwrap();
+        let mut c = plain.clone();
+        c.arena = Some(NodeArena::new(&Topology::single(vec![0]), 3));
+        for target in [&mut plain, &mut c] {
+            for i in 0..100 {
+                let v = Vector::new(vec![i as f32, (i % 7) as f32, 1.0]);
+                target.insert(format!("p{}", i), v).unwrap();
+            }
+            target
+                .insert("p5".into(), Vector::new(vec![50.0, 0.0, 1.0]))
+                .unwrap();
+            for i in (0..100).step_by(3) {
+                target.delete(&format!("p{}", i));
+            }
+        }
+
+        let arena = c.arena.as_ref().unwrap();
+        assert_eq!(arena.len(), c.len());
+        assert!(c.vectors.values().all(|v| v.data.is_empty()));
+        assert_eq!(c.get("p5").unwrap().data, [50.0, 0.0, 1.0]);
+        assert!(c.index_info().memory.vectors >= arena.len() * 3 * 4);
+
+        let query = [40.2, 2.1, 1.0];
+        let ids =
+            |hits: Vec<SearchResult>| -> Vec<String> { hits.into_iter().map(|h| h.id).collect() };
+        assert_eq!(
+            ids(c.search(&query, 5).unwrap()),
+            ids(plain.search(&query, 5).unwrap())
+        );
+        let batch = c.search_batch(&[&query], 5, DistanceMetric::Euclidean, true);
+        assert_eq!(
+            ids(batch.unwrap().remove(0)),
+            ids(plain.search(&query, 5).unwrap())
+        );
+        let (hits, _) = c
+            .search_radius(&query, 3.0, DistanceMetric::Euclidean, 10, None, None)
+            .unwrap();
+        assert_eq!(ids(hits), ["p38", "p40"]);
+
+        // Overwritten components go to history and come back on rollback
+        let mut versioned = Collection::from_request(&CreateCollectionRequest {
+            max_versions: 2,
+            ..CreateCollectionRequest::new("versioned", 3)
+        })
+        .unwrap();
+        versioned.arena = Some(NodeArena::new(&Topology::single(vec![0]), 3));
+        versioned
+            .insert("a".into(), Vector::new(vec![1.0, 2.0, 3.0]))
+            .unwrap();
+        versioned
+            .insert("a".into(), Vector::new(vec![4.0, 5.0, 6.0]))
+            .unwrap();
+        versioned.rollback("a", 1).unwrap();
+        assert_eq!(versioned.get("a").unwrap().data, [1.0, 2.0, 3.0]);
+        assert_eq!(versioned.clone().get("a").unwrap().data, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_binary_collection_keeps_points_packed() {
+        let req = CreateCollectionRequest {
+            vector_type: VectorType::Binary,
+            max_versions: 2,
+            ..CreateCollectionRequest::new("codes", 70)
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        let bits =
+            |ones: usize| Vector::new((0..70).map(|d| if d < ones { 1.0 } else { 0.0 }).collect());
+        c.insert("a".into(), bits(3)).unwrap();
+        c.insert("b".into(), bits(66)).unwrap();
+
+        // Two words a point, not 70 floats
+        assert_eq!(c.index_info().memory.vectors, 2 * 2 * 8);
+        assert_eq!(c.get("b").unwrap().data, bits(66).data);
+        let points: HashMap<_, _> = c
+            .points()
+            .map(|(id, v)| (id.clone(), v.data.clone()))
+            .collect();
+        assert_eq!(points["a"], bits(3).data);
+
+        // Every search path scores by popcount over the packed words
+        let query = bits(4).data;
+        let ranked = |hits: Vec<SearchResult>| -> Vec<(String, f32)> {
+            hits.into_iter().map(|h| (h.id, h.score)).collect()
+        };
+        let want = vec![("a".to_string(), 1.0), ("b".to_string(), 62.0)];
+        assert_eq!(ranked(c.search(&query, 2).unwrap()), want);
+        let batch = c.search_batch(&[&query], 2, DistanceMetric::Hamming, true);
+        assert_eq!(ranked(batch.unwrap().remove(0)), want);
+        let adjusted = c.search_adjusted(&query, 2, DistanceMetric::Hamming, &mut |_, v, s| {
+            assert_eq!(v.dimension(), 70);
+            Ok(Some(s))
+        });
+        assert_eq!(ranked(adjusted.unwrap()), want);
+        let (hits, _) = c
+            .search_radius(&query, 1.0, DistanceMetric::Hamming, 10, None, None)
+            .unwrap();
+        assert_eq!(ranked(hits), want[..1]);
+
+        // Updates and rollback work on whole vectors
+        let unset = Filter::parse(r#"NOT k == "v""#).unwrap();
+        let set = HashMap::from([("k".to_string(), "v".to_string())]);
+        assert!(c.update_metadata("a", &unset, &set, &[]));
+        assert_eq!(c.get("a").unwrap().data, bits(3).data);
+        c.insert("a".into(), bits(5)).unwrap();
+        c.rollback("a", 1).unwrap();
+        assert_eq!(c.get("a").unwrap().data, bits(3).data);
+    }
+
+    #[test]
+    fn test_sparse_search_ranks_by_shared_terms() {
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        let doc = |terms: &[(u32, f32)], lang: &str| {
+            let (indices, values) = terms.iter().copied().unzip();
+            let mut v = Vector::new(vec![1.0, 0.0]).with_sparse(SparseVector::new(indices, values));
+            v.metadata.insert("lang".into(), lang.into());
+            v
+        };
+        // Unsorted on the way in, sorted once stored
+        c.insert("a".into(), doc(&[(30, 1.0), (7, 2.0)], "en"))
+            .unwrap();
+        c.insert("b".into(), doc(&[(7, 0.5), (12, 3.0)], "de"))
+            .unwrap();
+        c.insert("dense_only".into(), Vector::new(vec![0.0, 1.0]))
+            .unwrap();
+        c.insert("unrelated".into(), doc(&[(99, 5.0)], "en"))
+            .unwrap();
+        assert_eq!(
+            c.get("a").unwrap().sparse.as_ref().unwrap().indices,
+            [7, 30]
+        );
+        let err = c
+            .insert("bad".into(), doc(&[(7, 1.0), (7, 2.0)], "en"))
+            .unwrap_err();
+        assert!(matches!(err, VectorDbError::InvalidParameter(_)), "{}", err);
+
+        let query = SparseVector::new(vec![12, 7], vec![1.0, 1.0]);
+        let hits = c.search_sparse(&query, 10, None).unwrap();
+        let ranked: Vec<_> = hits.iter().map(|h| (h.id.as_str(), h.score)).collect();
+        assert_eq!(ranked, [("b", 3.5), ("a", 2.0)]);
+
+        let english = Filter::parse("lang == \"en\"").unwrap();
+        let hits = c.search_sparse(&query, 10, Some(&english)).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, "a");
+        assert!(c.search_sparse(&SparseVector::default(), 10, None).is_err());
+
+        // Sealed and restored, the embeddings come back
+        let flush = c.seal_memtable(0).unwrap();
+        let restored = Collection::restore(&c.info(), flush.points).unwrap();
+        assert_eq!(restored.search_sparse(&query, 1, None).unwrap()[0].id, "b");
+    }
+
+    #[test]
+    fn test_auto_index_waits_for_the_threshold() {
+        let auto = AutoIndex {
+            threshold: 3,
+            hnsw: HnswConfig::default(),
+        };
+        let zero = CreateCollectionRequest {
+            auto_index: Some(AutoIndex {
+                threshold: 0,
+                ..auto
+            }),
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        assert!(Collection::from_request(&zero).is_err());
+
+        let req = CreateCollectionRequest {
+            auto_index: Some(auto),
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        for i in 0..2 {
+            c.insert(format!("p{}", i), Vector::new(vec![i as f32, 1.0]))
+                .unwrap();
+        }
+        assert_eq!(c.index_state(), IndexState::BelowThreshold);
+        assert_eq!(c.wants_index(), None);
+
+        c.insert("p2".into(), Vector::new(vec![2.0, 1.0])).unwrap();
+        assert_eq!(c.wants_index(), Some(HnswConfig::default()));
+        let points = c.begin_index_build().unwrap();
+        assert_eq!(c.index_state(), IndexState::Building);
+        assert_eq!(c.wants_index(), None, "one build at a time");
+
+        let mut index = Hnsw::new(HnswConfig::default(), c.distance, 2);
+        for (id, data) in points {
+            index.insert(&id, &data);
+        }
+        c.install_index(index).unwrap();
+        assert_eq!(c.index_state(), IndexState::Indexed);
+        assert_eq!(c.wants_index(), None);
+
+        let restored = Collection::restore(&c.info(), Vec::new()).unwrap();
+        assert_eq!(restored.auto_index(), Some(auto));
+        assert_eq!(restored.index_state(), IndexState::Indexed);
+    }
+
+    #[test]
+    fn test_hnsw_filtered_search_strategies() {
+        let req = CreateCollectionRequest {
+            distance: Some(DistanceMetric::Euclidean),
+            hnsw: Some(HnswConfig::default()),
+            ..CreateCollectionRequest::new("docs", 2)
+        };
+        let mut c = Collection::from_request(&req).unwrap();
+        for i in 0..40 {
+            let mut v = Vector::new(vec![i as f32, 0.0]);
+            let lang = if i % 4 == 0 { "de" } else { "en" };
+            v.metadata.insert("lang".into(), lang.into());
+            c.insert(format!("p{}", i), v).unwrap();
+        }
+        assert_eq!(c.index_type(), IndexType::Hnsw);
+
+        let de = Filter::parse(r#"metadata.lang=="de""#).unwrap();
+        // Forty points: scanning them is cheaper than any graph search
+        assert_eq!(
+            c.filter_strategy(&de, DistanceMetric::Euclidean),
+            FilterStrategy::PreFilter
+        );
+        for strategy in [FilterStrategy::PreFilter, FilterStrategy::InGraph] {
+            let hits = c
+                .search_filtered_by(&[9.0, 0.0], 2, DistanceMetric::Euclidean, &de, strategy)
+                .unwrap();
+            let ids: Vec<_> = hits.iter().map(|h| h.id.as_str()).collect();
+            assert_eq!(ids, ["p8", "p12"], "{:?}", strategy);
+        }
+
+        // Deleted points leave the graph too
+        c.delete("p8");
+        assert_eq!(c.search(&[7.9, 0.0], 1).unwrap()[0].id, "p7");
+
+        let restored = Collection::restore(&c.info(), Vec::new()).unwrap();
+        assert_eq!(restored.index_type(), IndexType::Hnsw);
+        assert_eq!(restored.info().hnsw, Some(HnswConfig::default()));
+    }
+}
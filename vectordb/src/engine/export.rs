@@ -0,0 +1,253 @@
+// src/engine/export.rs
+//
+// Row formatting for collection exports.
+//
+// An export is a header (CSV only) followed by one row per vector, with
+// only the requested fields:
+//
+//   fields=id,metadata.title         → {"id":"a","metadata":{"title":"x"}}
+//   fields=id,vector                 → {"id":"a","vector":[0.1,0.2]}
+//   (default)                        → id, vector, and all metadata
+//
+// Formatting is per row so the HTTP layer can stream an export of any size
+// in batches without building it in memory.
+
+use crate::models::{Result, Vector, VectorDbError};
+use serde_json::{json, Map, Value};
+
+/// Output encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// RFC 4180 CSV with a header row
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            other => Err(VectorDbError::InvalidParameter(format!(
+                "unknown export format '{}' (expected jsonl, csv or parquet)",
+                other
+            ))),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/x-ndjson",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// One selectable field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportField {
+    Id,
+    Vector,
+    /// All metadata as one object
+    Metadata,
+    /// A single metadata key
+    MetadataKey(String),
+}
+
+impl ExportField {
+    /// Column name, as in a CSV header
+    pub fn name(&self) -> String {
+        match self {
+            Self::Id => "id".into(),
+            Self::Vector => "vector".into(),
+            Self::Metadata => "metadata".into(),
+            Self::MetadataKey(key) => format!("metadata.{}", key),
+        }
+    }
+}
+
+/// Which fields to export, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    fields: Vec<ExportField>,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self {
+            fields: vec![ExportField::Id, ExportField::Vector, ExportField::Metadata],
+        }
+    }
+}
+
+impl Projection {
+    /// Parse a comma-separated field list like "id,metadata.title"
+    pub fn parse(fields: &str) -> Result<Self> {
+        let mut parsed = Vec::new();
+        for name in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let field = match name {
+                "id" => ExportField::Id,
+                "vector" => ExportField::Vector,
+                "metadata" => ExportField::Metadata,
+                other => match other.strip_prefix("metadata.") {
+                    Some(key) if !key.is_empty() => ExportField::MetadataKey(key.to_string()),
+                    _ => {
+                        return Err(VectorDbError::InvalidParameter(format!(
+                            "unknown export field '{}' (expected id, vector, metadata, or metadata.<key>)",
+                            other
+                        )))
+                    }
+                },
+            };
+            if !parsed.contains(&field) {
+                parsed.push(field);
+            }
+        }
+        if parsed.is_empty() {
+            return Err(VectorDbError::InvalidParameter(
+                "fields must name at least one field".into(),
+            ));
+        }
+        Ok(Self { fields: parsed })
+    }
+
+    /// The selected fields, in order
+    pub fn fields(&self) -> &[ExportField] {
+        &self.fields
+    }
+
+    /// Header line (CSV only), including the trailing newline
+    pub fn header(&self, format: ExportFormat) -> Option<String> {
+        match format {
+            ExportFormat::Jsonl => None,
+            ExportFormat::Csv => {
+                let names: Vec<String> = self.fields.iter().map(|f| csv_cell(&f.name())).collect();
+                Some(format!("{}\r\n", names.join(",")))
+            }
+        }
+    }
+
+    /// One row, including the trailing newline
+    pub fn row(&self, format: ExportFormat, id: &str, vector: &Vector) -> String {
+        match format {
+            ExportFormat::Jsonl => format!("{}\n", self.json_row(id, vector)),
+            ExportFormat::Csv => {
+                let cells: Vec<String> = self
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let text = match field {
+                            ExportField::Id => id.to_string(),
+                            ExportField::Vector => json!(vector.data).to_string(),
+                            ExportField::Metadata => json!(vector.metadata).to_string(),
+                            ExportField::MetadataKey(key) => {
+                                vector.metadata.get(key).cloned().unwrap_or_default()
+                            }
+                        };
+                        csv_cell(&text)
+                    })
+                    .collect();
+                format!("{}\r\n", cells.join(","))
+            }
+        }
+    }
+
+    fn json_row(&self, id: &str, vector: &Vector) -> Value {
+        let mut row = Map::new();
+        let mut metadata = Map::new();
+        for field in &self.fields {
+            match field {
+                ExportField::Id => {
+                    row.insert("id".into(), json!(id));
+                }
+                ExportField::Vector => {
+                    row.insert("vector".into(), json!(vector.data));
+                }
+                ExportField::Metadata => {
+                    for (k, v) in &vector.metadata {
+                        metadata.insert(k.clone(), json!(v));
+                    }
+                }
+                ExportField::MetadataKey(key) => {
+                    if let Some(v) = vector.metadata.get(key) {
+                        metadata.insert(key.clone(), json!(v));
+                    }
+                }
+            }
+        }
+        let wants_metadata = self
+            .fields
+            .iter()
+            .any(|f| matches!(f, ExportField::Metadata | ExportField::MetadataKey(_)));
+        if wants_metadata {
+            row.insert("metadata".into(), Value::Object(metadata));
+        }
+        Value::Object(row)
+    }
+}
+
+/// Quote a CSV cell if it contains a delimiter, quote, or line break
+fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Vector {
+        let mut v = Vector::new(vec![0.5, -1.0]);
+        v.metadata.insert("title".into(), "Hello, \"world\"".into());
+        v.metadata.insert("lang".into(), "en".into());
+        v
+    }
+
+    #[test]
+    fn test_jsonl_projection() {
+        let p = Projection::parse("id,metadata.title").unwrap();
+        let row: Value = serde_json::from_str(&p.row(ExportFormat::Jsonl, "a", &doc())).unwrap();
+        assert_eq!(
+            row,
+            json!({ "id": "a", "metadata": { "title": "Hello, \"world\"" } })
+        );
+
+        let row = Projection::default().row(ExportFormat::Jsonl, "a", &doc());
+        assert!(row.ends_with('\n'));
+        let row: Value = serde_json::from_str(&row).unwrap();
+        assert_eq!(row["vector"], json!([0.5, -1.0]));
+        assert_eq!(row["metadata"]["lang"], "en");
+    }
+
+    #[test]
+    fn test_csv_quotes_cells() {
+        let p = Projection::parse("id, metadata.title, metadata.missing, vector").unwrap();
+        assert_eq!(
+            p.header(ExportFormat::Csv).unwrap(),
+            "id,metadata.title,metadata.missing,vector\r\n"
+        );
+        assert_eq!(
+            p.row(ExportFormat::Csv, "a", &doc()),
+            "a,\"Hello, \"\"world\"\"\",,\"[0.5,-1.0]\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_fields() {
+        assert!(Projection::parse("id,score").is_err());
+        assert!(Projection::parse("metadata.").is_err());
+        assert!(Projection::parse(" , ").is_err());
+        assert!(ExportFormat::parse("xml").is_err());
+    }
+}
@@ -0,0 +1,429 @@
+// src/engine/filter.rs
+//
+// Metadata filter expressions.
+//
+// A tiny expression language over the string metadata attached to vectors:
+//
+//   category == "news" AND (year >= 2020 OR NOT featured == true)
+//   metadata.status != "draft" && score > 0.5
+//
+// Grammar (lowest to highest precedence):
+//
+//   expr       := and ( ("OR" | "||") and )*
+//   and        := unary ( ("AND" | "&&") unary )*
+//   unary      := ("NOT" | "!") unary | "(" expr ")" | comparison
+//   comparison := field op value
+//   op         := == | != | < | <= | > | >=
+//   value      := "string" | 'string' | number | true | false
+//
+// Metadata values are strings, so comparisons are numeric when both sides
+// parse as numbers and lexicographic otherwise. A field that is missing
+// from a vector's metadata never matches (including `!=`).
+
+use crate::models::{Result, VectorDbError};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// AST
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Comparison operator in a filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A parsed filter expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// `field op value`
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: String,
+    },
+    /// All sub-filters must match
+    And(Vec<Filter>),
+    /// Any sub-filter must match
+    Or(Vec<Filter>),
+    /// Sub-filter must not match
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Parse a filter expression
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.parse_or()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(filter_error(format!(
+                "unexpected token {} at position {}",
+                parser.tokens[parser.pos], parser.pos
+            )));
+        }
+        Ok(filter)
+    }
+
+    /// Does `metadata` satisfy this filter?
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        match self {
+            Filter::Compare { field, op, value } => match metadata.get(field) {
+                Some(actual) => compare(actual, value, *op),
+                None => false,
+            },
+            Filter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            Filter::Not(filter) => !filter.matches(metadata),
+        }
+    }
+
+    /// Every metadata field this filter reads
+    pub fn fields(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_fields(&mut out);
+        out
+    }
+
+    fn collect_fields<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Filter::Compare { field, .. } => {
+                if !out.contains(&field.as_str()) {
+                    out.push(field);
+                }
+            }
+            Filter::And(filters) | Filter::Or(filters) => {
+                for f in filters {
+                    f.collect_fields(out);
+                }
+            }
+            Filter::Not(f) => f.collect_fields(out),
+        }
+    }
+}
+
+/// Compare a stored metadata value against a filter literal
+fn compare(actual: &str, expected: &str, op: CompareOp) -> bool {
+    let ordering = match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(actual.cmp(expected)),
+    };
+
+    match ordering {
+        Some(ord) => match op {
+            CompareOp::Eq => ord == Ordering::Equal,
+            CompareOp::Ne => ord != Ordering::Equal,
+            CompareOp::Lt => ord == Ordering::Less,
+            CompareOp::Le => ord != Ordering::Greater,
+            CompareOp::Gt => ord == Ordering::Greater,
+            CompareOp::Ge => ord != Ordering::Less,
+        },
+        // NaN on either side
+        None => op == CompareOp::Ne,
+    }
+}
+
+fn filter_error(msg: String) -> VectorDbError {
+    VectorDbError::InvalidParameter(format!("filter: {}", msg))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TOKENIZER
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{}'", s),
+            Token::Literal(s) => write!(f, "\"{}\"", s),
+            Token::Op(op) => write!(f, "{:?}", op),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '=' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Eq));
+                i += 2;
+            }
+            '!' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Ne));
+                i += 2;
+            }
+            '!' => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            '<' | '>' => {
+                let op = match (c, next == Some('=')) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    _ => CompareOp::Ge,
+                };
+                tokens.push(Token::Op(op));
+                i += if next == Some('=') { 2 } else { 1 };
+            }
+            '&' if next == Some('&') => {
+                tokens.push(Token::And);
+                i += 2;
+            }
+            '|' if next == Some('|') => {
+                tokens.push(Token::Or);
+                i += 2;
+            }
+            '"' | '\'' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(filter_error("unterminated string".into())),
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            value.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&ch) if ch == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&ch) => {
+                            value.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Literal(value));
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || ".eE+-".contains(chars[i])) {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                if number.parse::<f64>().is_err() {
+                    return Err(filter_error(format!("invalid number '{}'", number)));
+                }
+                tokens.push(Token::Literal(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "TRUE" | "FALSE" => Token::Literal(word.to_ascii_lowercase()),
+                    _ => Token::Ident(word),
+                });
+            }
+            other => {
+                return Err(filter_error(format!(
+                    "unexpected character '{}' at position {}",
+                    other, i
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// PARSER (recursive descent)
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Filter> {
+        let mut terms = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            terms.push(self.parse_and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Filter::Or(terms)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter> {
+        let mut terms = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            terms.push(self.parse_unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Filter::And(terms)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter> {
+        match self.next() {
+            Some(Token::Not) => Ok(Filter::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(filter_error("missing ')'".into())),
+                }
+            }
+            Some(Token::Ident(field)) => {
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    other => {
+                        return Err(filter_error(format!(
+                            "expected comparison operator after '{}', got {}",
+                            field,
+                            other.map_or("end of input".to_string(), |t| t.to_string())
+                        )))
+                    }
+                };
+                let value = match self.next() {
+                    Some(Token::Literal(v)) => v,
+                    other => {
+                        return Err(filter_error(format!(
+                            "expected a value after '{}', got {}",
+                            field,
+                            other.map_or("end of input".to_string(), |t| t.to_string())
+                        )))
+                    }
+                };
+                // `metadata.title` and `title` refer to the same field
+                let field = field
+                    .strip_prefix("metadata.")
+                    .map(str::to_string)
+                    .unwrap_or(field);
+                Ok(Filter::Compare { field, op, value })
+            }
+            Some(other) => Err(filter_error(format!("unexpected token {}", other))),
+            None => Err(filter_error("unexpected end of input".into())),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_simple_equality() {
+        let f = Filter::parse(r#"category == "news""#).unwrap();
+        assert!(f.matches(&meta(&[("category", "news")])));
+        assert!(!f.matches(&meta(&[("category", "sports")])));
+        assert!(!f.matches(&meta(&[])));
+    }
+
+    #[test]
+    fn test_numeric_comparison() {
+        let f = Filter::parse("year >= 2020").unwrap();
+        assert!(f.matches(&meta(&[("year", "2021")])));
+        // "900" < "2020" lexicographically would be wrong; numeric wins
+        assert!(!f.matches(&meta(&[("year", "900")])));
+    }
+
+    #[test]
+    fn test_boolean_operators_and_precedence() {
+        let f = Filter::parse(r#"a == 1 OR b == 2 AND NOT (c == "x")"#).unwrap();
+        assert!(f.matches(&meta(&[("a", "1")])));
+        assert!(f.matches(&meta(&[("b", "2"), ("c", "y")])));
+        assert!(!f.matches(&meta(&[("b", "2"), ("c", "x")])));
+
+        let f = Filter::parse("a == 1 && !(b != 2) || c == true").unwrap();
+        assert!(f.matches(&meta(&[("a", "1"), ("b", "2")])));
+        assert!(f.matches(&meta(&[("c", "true")])));
+    }
+
+    #[test]
+    fn test_metadata_prefix_is_stripped() {
+        let f = Filter::parse(r#"metadata.status=="draft""#).unwrap();
+        assert!(f.matches(&meta(&[("status", "draft")])));
+        assert_eq!(f.fields(), vec!["status"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Filter::parse("").is_err());
+        assert!(Filter::parse("a ==").is_err());
+        assert!(Filter::parse("a == 'open").is_err());
+        assert!(Filter::parse("(a == 1").is_err());
+        assert!(Filter::parse("a == 1 b == 2").is_err());
+        assert!(Filter::parse("a ~ 1").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+// src/engine/gemm.rs
+//
+// Exact search for many queries at once, scored as a blocked matrix
+// multiply.
+//
+// Scoring queries one at a time streams every stored vector through the
+// cache once per query, and each multiply-add loads two floats. With a
+// batch of queries, every score is a dot product from Q × Cᵀ (Q the
+// queries, C the candidates), and the usual GEMM tricks apply:
+//
+//   1. Pack a panel of PANEL candidates component-major, so component d
+//      of all of them sits in one contiguous run (one SIMD register):
+//
+//        panel[d] = [c0[d], c1[d], ..., c7[d]]
+//
+//   2. Run a microkernel over QUERY_BLOCK queries × that panel: for each d,
+//      broadcast q[d] and multiply-add it into a row of PANEL
+//      accumulators. The 4 × 8 accumulators stay in registers for the
+//      whole dimension, and every loaded panel value is used by all four
+//      queries.
+//
+//   3. Packing costs one pass over the panel and is shared by every query
+//      in the batch.
+//
+// Cosine and Euclidean come from the dot products and precomputed norms
+// (|q − c|² = |q|² + |c|² − 2 q·c). That sums in a different order than
+// `DistanceMetric::calculate`, so the kept hits are rescored with it at
+// the end: returned scores match a single-query search exactly, though a
+// candidate tied with the k-th hit to within rounding may be swapped.
+//
+// Hamming distance isn't a function of dot products (it counts sign
+// disagreements), so Hamming batches are scanned one query at a time.
+
+use crate::engine::search::{self, TopK};
+use crate::models::{DistanceMetric, SearchResult};
+
+/// Queries per microkernel call
+const QUERY_BLOCK: usize = 4;
+
+/// Candidates per packed panel: eight f32 lanes, one AVX register
+const PANEL: usize = 8;
+
+/// Exact top-k of every query against `candidates`, in query order.
+///
+/// Queries and candidates of a different dimension than the first query
+/// are handled like `search::brute_force`: mismatched candidates are
+/// skipped; a mismatched query is scored on its own.
+pub fn batch_brute_force(
+    queries: &[&[f32]],
+    metric: DistanceMetric,
+    k: usize,
+    candidates: &[(&str, &[f32])],
+) -> Vec<Vec<SearchResult>> {
+    let Some(dimension) = queries.first().map(|q| q.len()) else {
+        return Vec::new();
+    };
+    let (batched, single): (Vec<usize>, Vec<usize>) = (0..queries.len())
+        .partition(|&q| queries[q].len() == dimension && metric != DistanceMetric::Hamming);
+    let usable: Vec<usize> = (0..candidates.len())
+        .filter(|&c| candidates[c].1.len() == dimension)
+        .collect();
+
+    let query_norms: Vec<f32> = batched.iter().map(|&q| norm_sq(queries[q])).collect();
+    let mut tops: Vec<TopK<usize>> = batched.iter().map(|_| TopK::new(k, metric)).collect();
+    let mut panel = vec![0.0f32; dimension * PANEL];
+    let mut dots = [[0.0f32; PANEL]; QUERY_BLOCK];
+
+    for members in usable.chunks(PANEL) {
+        pack(
+            &mut panel,
+            members.iter().map(|&c| candidates[c].1),
+            dimension,
+        );
+        let norms: Vec<f32> = members.iter().map(|&c| norm_sq(candidates[c].1)).collect();
+
+        for (block, rows) in batched.chunks(QUERY_BLOCK).enumerate() {
+            // A short last block repeats its first query; the extra rows
+            // are computed and ignored
+            let block_queries: [&[f32]; QUERY_BLOCK] =
+                std::array::from_fn(|i| queries[rows[i.min(rows.len() - 1)]]);
+            microkernel(&block_queries, &panel, &mut dots);
+
+            for (i, row) in dots.iter().enumerate().take(rows.len()) {
+                let q = block * QUERY_BLOCK + i;
+                for (j, &c) in members.iter().enumerate() {
+                    let score = combine(metric, row[j], query_norms[q], norms[j]);
+                    tops[q].push(score, c);
+                }
+            }
+        }
+    }
+
+    let mut results = vec![Vec::new(); queries.len()];
+    for (top, &q) in tops.into_iter().zip(&batched) {
+        let rescored = top.into_sorted_vec().into_iter().map(|c| {
+            let (id, data) = candidates[c];
+            SearchResult::new(id.to_string(), metric.calculate(queries[q], data))
+        });
+        results[q] = search::rank(rescored, metric, k);
+    }
+    for q in single {
+        results[q] = search::brute_force(queries[q], metric, k, candidates.iter().copied());
+    }
+    results
+}
+
+/// Lay out up to PANEL vectors component-major; missing lanes are zero
+fn pack<'a>(panel: &mut [f32], vectors: impl Iterator<Item = &'a [f32]>, dimension: usize) {
+    panel.fill(0.0);
+    for (lane, vector) in vectors.enumerate() {
+        for (d, &x) in vector.iter().enumerate().take(dimension) {
+            panel[d * PANEL + lane] = x;
+        }
+    }
+}
+
+/// Dot products of QUERY_BLOCK queries with one packed panel
+#[inline(always)]
+fn microkernel(
+    queries: &[&[f32]; QUERY_BLOCK],
+    panel: &[f32],
+    out: &mut [[f32; PANEL]; QUERY_BLOCK],
+) {
+    let mut acc = [[0.0f32; PANEL]; QUERY_BLOCK];
+    for (d, column) in panel.chunks_exact(PANEL).enumerate() {
+        for (row, query) in acc.iter_mut().zip(queries) {
+            let x = query[d];
+            for (a, &c) in row.iter_mut().zip(column) {
+                *a += x * c;
+            }
+        }
+    }
+    *out = acc;
+}
+
+/// A metric's score from a dot product and the two squared norms
+fn combine(metric: DistanceMetric, dot: f32, query_sq: f32, candidate_sq: f32) -> f32 {
+    match metric {
+        DistanceMetric::Dot => dot,
+        DistanceMetric::Cosine => {
+            let norms = (query_sq * candidate_sq).sqrt();
+            if norms == 0.0 {
+                0.0
+            } else {
+                dot / norms
+            }
+        }
+        DistanceMetric::Euclidean => (query_sq + candidate_sq - 2.0 * dot).max(0.0).sqrt(),
+        DistanceMetric::Hamming => unreachable!("hamming batches are scored per query"),
+    }
+}
+
+fn norm_sq(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::Rng;
+
+    fn random(n: usize, dim: usize, stream: u64) -> Vec<Vec<f32>> {
+        (0..n as u64)
+            .map(|i| {
+                let mut rng = Rng::stream(11, stream, i);
+                (0..dim).map(|_| rng.normal()).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_matches_single_query_search() {
+        // Sizes that leave partial panels and a partial query block
+        let data = random(203, 19, 0);
+        let ids: Vec<String> = (0..data.len()).map(|i| i.to_string()).collect();
+        let candidates: Vec<(&str, &[f32])> = ids
+            .iter()
+            .zip(&data)
+            .map(|(id, v)| (id.as_str(), v.as_slice()))
+            .collect();
+        let queries = random(6, 19, 1);
+        let query_refs: Vec<&[f32]> = queries.iter().map(Vec::as_slice).collect();
+
+        for metric in [
+            DistanceMetric::Cosine,
+            DistanceMetric::Euclidean,
+            DistanceMetric::Dot,
+        ] {
+            let batch = batch_brute_force(&query_refs, metric, 7, &candidates);
+            assert_eq!(batch.len(), queries.len());
+            for (query, hits) in queries.iter().zip(&batch) {
+                let single = search::brute_force(query, metric, 7, candidates.iter().copied());
+                let pairs = |r: &[SearchResult]| -> Vec<(String, f32)> {
+                    r.iter().map(|h| (h.id.clone(), h.score)).collect()
+                };
+                assert_eq!(pairs(hits), pairs(&single), "{:?}", metric);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mismatched_dimensions() {
+        let a = [1.0, 0.0];
+        let b = [0.0, 1.0, 0.0];
+        let candidates = [("a", &a[..]), ("b", &b[..])];
+        let queries: [&[f32]; 2] = [&[1.0, 0.0], &[0.0, 1.0, 0.0]];
+        let results = batch_brute_force(&queries, DistanceMetric::Dot, 5, &candidates);
+        assert_eq!(results[0].len(), 1);
+        assert_eq!(results[0][0].id, "a");
+        assert_eq!(results[1][0].id, "b");
+        assert!(batch_brute_force(&[], DistanceMetric::Dot, 5, &candidates).is_empty());
+    }
+}
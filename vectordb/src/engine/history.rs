@@ -0,0 +1,146 @@
+// src/engine/history.rs
+//
+// Bounded per-vector version history.
+//
+// When enabled on a collection (`max_versions > 0`), every overwrite pushes
+// the previous vector onto a small ring buffer for that ID:
+//
+//   v1 ──overwrite──▶ v2 ──overwrite──▶ v3 (current)
+//                      history: [v1, v2]
+//
+// Rolling back to v1 doesn't rewrite history — it inserts a copy of v1 as
+// v4, so the bad v3 is still there if the rollback itself was a mistake.
+
+use crate::engine::index;
+use crate::models::Vector;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+/// A previous version of a vector
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorVersion {
+    /// Version number (1 = first insert)
+    pub version: u64,
+
+    /// Unix timestamp (seconds) when this version was replaced
+    pub replaced_at: u64,
+
+    /// The vector as it was
+    pub vector: Vector,
+}
+
+/// Version counters and retained old versions for one collection.
+#[derive(Debug, Clone, Default)]
+pub struct VersionHistory {
+    /// How many old versions to keep per ID (0 = history disabled)
+    pub max_versions: usize,
+
+    /// Current version number per ID
+    current: HashMap<String, u64>,
+
+    /// Old versions per ID, oldest first
+    past: HashMap<String, VecDeque<VectorVersion>>,
+}
+
+impl VersionHistory {
+    /// Create a history that keeps up to `max_versions` old versions per ID
+    pub fn new(max_versions: usize) -> Self {
+        Self {
+            max_versions,
+            ..Self::default()
+        }
+    }
+
+    /// Record a write to `id`. `previous` is the vector being replaced, if
+    /// any. Returns the new version number.
+    pub fn record_write(&mut self, id: &str, previous: Option<Vector>, now: u64) -> u64 {
+        let old_version = self.current.get(id).copied().unwrap_or(0);
+        let new_version = old_version + 1;
+        self.current.insert(id.to_string(), new_version);
+
+        if let (Some(vector), true) = (previous, self.max_versions > 0) {
+            let versions = self.past.entry(id.to_string()).or_default();
+            versions.push_back(VectorVersion {
+                version: old_version,
+                replaced_at: now,
+                vector,
+            });
+            while versions.len() > self.max_versions {
+                versions.pop_front();
+            }
+        }
+
+        new_version
+    }
+
+    /// Forget everything about `id`
+    pub fn remove(&mut self, id: &str) {
+        self.current.remove(id);
+        self.past.remove(id);
+    }
+
+    /// Current version number of `id` (0 if never written)
+    pub fn current_version(&self, id: &str) -> u64 {
+        self.current.get(id).copied().unwrap_or(0)
+    }
+
+    /// Retained old versions of `id`, oldest first
+    pub fn versions(&self, id: &str) -> Vec<VectorVersion> {
+        self.past
+            .get(id)
+            .map(|v| v.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Heap bytes held by retained old versions
+    pub fn retained_bytes(&self) -> usize {
+        self.past
+            .values()
+            .flatten()
+            .map(|v| index::vector_bytes(&v.vector))
+            .sum()
+    }
+
+    /// Find a retained old version of `id`
+    pub fn find(&self, id: &str, version: u64) -> Option<&VectorVersion> {
+        self.past.get(id)?.iter().find(|v| v.version == version)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut h = VersionHistory::new(2);
+        assert_eq!(h.record_write("a", None, 0), 1);
+        for i in 0..4 {
+            h.record_write("a", Some(Vector::new(vec![i as f32])), 10 + i);
+        }
+
+        assert_eq!(h.current_version("a"), 5);
+        let versions = h.versions("a");
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version, 3);
+        assert_eq!(versions[1].version, 4);
+        assert!(h.find("a", 1).is_none());
+        assert_eq!(h.find("a", 4).unwrap().vector.data, vec![3.0]);
+    }
+
+    #[test]
+    fn test_disabled_history_still_counts_versions() {
+        let mut h = VersionHistory::new(0);
+        h.record_write("a", None, 0);
+        h.record_write("a", Some(Vector::new(vec![1.0])), 0);
+        assert_eq!(h.current_version("a"), 2);
+        assert!(h.versions("a").is_empty());
+
+        h.remove("a");
+        assert_eq!(h.current_version("a"), 0);
+    }
+}
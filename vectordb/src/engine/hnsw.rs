@@ -0,0 +1,876 @@
+// src/engine/hnsw.rs
+//
+// HNSW: approximate nearest-neighbour search over a layered proximity graph
+// (Malkov & Yashunin, 2016).
+//
+// Every node lives on layer 0; each layer above holds a random sample of
+// roughly 1/M of the one below. A search descends greedily from the entry
+// point on the top layer to the closest node it can find, then runs a
+// best-first search keeping `ef` candidates on layer 0:
+//
+//   layer 2   E ──────────────────── x
+//   layer 1   E ────── x ───── x ─── x
+//   layer 0   E─x─x─x─x─x─x─x─x─x─x─x─x   ← ef-wide beam here
+//
+// Internally every score is a distance (lower is better): similarity
+// metrics are negated, so one comparison serves all three.
+//
+// Filtered search
+//
+// Post-filtering graph results (search for k, drop what doesn't match)
+// returns almost nothing once a filter is selective: the k nearest nodes
+// mostly fail it. `search` instead checks the predicate during expansion.
+// Every node is still traversed, so the search can cross regions where
+// nothing matches, but only matching nodes enter the result beam, and the
+// search keeps going until it holds ef of them or runs out of closer
+// candidates. With a very selective filter that visits most of the graph,
+// which is where `plan` sends the search to an exact scan of the matching
+// points instead (`FilterStrategy::PreFilter`).
+//
+// Deletes leave a tombstone: the node keeps routing searches but never
+// appears in results. Once tombstones outnumber live nodes, the graph is
+// rebuilt from the live ones.
+//
+// Prefetching
+//
+// Each hop of a traversal jumps to a node whose vector and neighbour list
+// sit at unrelated addresses, so on a graph bigger than the caches nearly
+// every hop waits on a load from memory. The hardware prefetcher can't
+// guess graph edges, but the search knows them a step ahead: while it
+// scores one neighbour it prefetches the next one's vector, and before
+// expanding a node it prefetches the neighbour list of the candidate it
+// will expand after. On graphs that fit in cache the loads are already
+// fast and the prefetches are pure overhead, so they're only issued past
+// PREFETCH_MIN_BYTES (and never with `prefetch: false`).
+//
+// Layout
+//
+// Nodes are numbered in insertion order, so a node's neighbours sit
+// anywhere in `data` and every hop lands on a different page. `relayout`
+// renumbers them breadth-first over layer 0 from the entry point, each
+// node's neighbours nearest first, so a node's neighbours mostly end up a
+// few slots from it and a traversal keeps reading nearby memory. (Upper
+// layers hold a small fraction of the nodes, and searches cross them on
+// every query, so they stay cached wherever they are.) Nodes the walk
+// can't reach follow, highest layer and degree first.
+//
+// A graph built in one go (restore, background build, tombstone rebuild)
+// is laid out before use. One that has grown by inserts since can be laid
+// out again with POST /api/collections/:name/index/relayout
+// (`vectordb-cli relayout`).
+
+use crate::models::{DistanceMetric, HnswConfig, SearchResult};
+use crate::synthetic::Rng;
+use serde::Serialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Highest layer a node can be drawn for
+const MAX_LEVEL: usize = 16;
+
+/// Largest accepted `m`; neighbour lists are scanned linearly
+pub const MAX_M: usize = 128;
+
+/// Seed for layer assignment, so the same inserts build the same graph
+const LEVEL_SEED: u64 = 0x484e_5357;
+
+/// Graph vectors beyond which a traversal prefetches (about an L3 cache)
+const PREFETCH_MIN_BYTES: usize = 8 * 1024 * 1024;
+
+/// Cache lines prefetched per vector. The lines after them are sequential,
+/// which the hardware prefetcher picks up by itself.
+const PREFETCH_LINES: usize = 4;
+
+/// f32s per 64-byte cache line
+const LINE_FLOATS: usize = 16;
+
+/// Check that a config describes a usable graph
+pub fn validate(config: &HnswConfig) -> Result<(), String> {
+    if config.m < 2 || config.m > MAX_M {
+        return Err(format!("hnsw m must be between 2 and {}", MAX_M));
+    }
+    if config.ef_construction == 0 || config.ef_search == 0 {
+        return Err("hnsw ef_construction and ef_search must be positive".into());
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FILTER PLANNING
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// How a filtered search runs on a collection with a graph index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterStrategy {
+    /// Exact scan of the points that match the filter
+    PreFilter,
+    /// Graph search that only admits matching nodes to its results
+    InGraph,
+}
+
+/// Choose how to run a search whose filter matches `selectivity` (0..=1)
+/// of `total` points.
+///
+/// Compares distance computations: a pre-filter scores every matching
+/// point; the graph finds its ef matches about every 1/selectivity nodes
+/// it expands, scoring up to 2M neighbours per expansion, and never more
+/// than every point.
+pub fn plan(config: &HnswConfig, selectivity: f64, total: usize) -> FilterStrategy {
+    let total = total as f64;
+    let matching = selectivity * total;
+    let graph = (config.ef_search * 2 * config.m) as f64 / selectivity.max(f64::EPSILON);
+    if matching <= graph.min(total) {
+        FilterStrategy::PreFilter
+    } else {
+        FilterStrategy::InGraph
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// GRAPH
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A node and its distance from the query, ordered nearest first
+#[derive(Debug, Clone, Copy)]
+struct Scored {
+    distance: f32,
+    node: u32,
+}
+
+impl Ord for Scored {
+    /// NaN distances sort after everything
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+/// What `relayout` did
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Layout {
+    /// Nodes renumbered, tombstones included
+    pub nodes: usize,
+    /// Mean gap between the numbers of two nodes joined by a layer-0 link,
+    /// before and after: the smaller, the closer a hop stays in memory
+    pub edge_span_before: f64,
+    pub edge_span_after: f64,
+}
+
+/// An HNSW graph over one collection's vectors
+#[derive(Debug, Clone)]
+pub struct Hnsw {
+    config: HnswConfig,
+    metric: DistanceMetric,
+    /// What distances are computed with: `metric`, or dot when every
+    /// vector (and query) is unit length and `metric` is cosine
+    kernel: DistanceMetric,
+    dimension: usize,
+    /// Node i's vector is data[i * dimension..(i + 1) * dimension]
+    data: Vec<f32>,
+    /// Point ID of each node
+    ids: Vec<String>,
+    /// Each node's neighbour lists, one per layer it's on
+    links: Vec<Vec<Vec<u32>>>,
+    /// Deleted nodes, kept for routing until the next rebuild
+    deleted: Vec<bool>,
+    tombstones: usize,
+    /// The live node of each point ID
+    nodes: HashMap<String, u32>,
+    /// Start of every search: a node on the top layer
+    entry: Option<u32>,
+    /// Unix timestamp (seconds) of the last (re)build
+    built_at: u64,
+}
+
+impl Hnsw {
+    /// An empty graph for vectors of `dimension` ranked by `metric`
+    pub fn new(config: HnswConfig, metric: DistanceMetric, dimension: usize) -> Self {
+        Self {
+            config,
+            metric,
+            kernel: metric,
+            dimension,
+            data: Vec::new(),
+            ids: Vec::new(),
+            links: Vec::new(),
+            deleted: Vec::new(),
+            tombstones: 0,
+            nodes: HashMap::new(),
+            entry: None,
+            built_at: unix_now(),
+        }
+    }
+
+    pub fn config(&self) -> HnswConfig {
+        self.config
+    }
+
+    /// The metric the graph's neighbourhoods were built under
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
+    /// Promise that every vector inserted and every query is unit length,
+    /// so cosine can be computed as a dot product
+    pub fn assume_unit_vectors(&mut self, unit: bool) {
+        self.kernel = match self.metric {
+            DistanceMetric::Cosine if unit => DistanceMetric::Dot,
+            metric => metric,
+        };
+    }
+
+    /// Live points in the graph
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// IDs of the live points
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.nodes.keys().map(String::as_str)
+    }
+
+    /// The vector the graph holds for `id`
+    pub fn vector_of(&self, id: &str) -> Option<&[f32]> {
+        self.nodes.get(id).map(|&node| self.vector(node))
+    }
+
+    /// Nodes, tombstones included
+    pub fn nodes(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Directed links over every layer
+    pub fn edges(&self) -> usize {
+        self.links.iter().flatten().map(Vec::len).sum()
+    }
+
+    /// Unix timestamp (seconds) of the last (re)build
+    pub fn built_at(&self) -> u64 {
+        self.built_at
+    }
+
+    /// Heap bytes of the graph: its copy of the vectors, links, and IDs
+    pub fn memory_bytes(&self) -> usize {
+        let ids: usize = self.ids.iter().map(String::len).sum();
+        self.data.len() * std::mem::size_of::<f32>()
+            + self.edges() * std::mem::size_of::<u32>()
+            + ids
+            + self.deleted.len()
+    }
+
+    /// Add a point, replacing any earlier node for the same ID
+    pub fn insert(&mut self, id: &str, vector: &[f32]) {
+        debug_assert_eq!(vector.len(), self.dimension);
+        if let Some(old) = self.nodes.remove(id) {
+            self.bury(old);
+        }
+        let node = self.ids.len() as u32;
+        let level = self.random_level(node);
+        self.data.extend_from_slice(vector);
+        self.ids.push(id.to_string());
+        self.links.push(vec![Vec::new(); level + 1]);
+        self.deleted.push(false);
+        self.nodes.insert(id.to_string(), node);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+        let top = self.links[entry as usize].len() - 1;
+        let mut nearest = vec![self.scored(vector, entry)];
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(vector, &nearest, 1, layer, &|_| true);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(
+                vector,
+                &nearest,
+                self.config.ef_construction,
+                layer,
+                &|_| true,
+            );
+            let neighbours = self.select(&found, self.max_links(layer));
+            for &neighbour in &neighbours {
+                let list = &mut self.links[neighbour as usize][layer];
+                list.push(node);
+                if list.len() > self.max_links(layer) {
+                    self.shrink(neighbour, layer);
+                }
+            }
+            self.links[node as usize][layer] = neighbours;
+            nearest = found;
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    /// Drop a point from results. Returns whether it was in the graph.
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.nodes.remove(id) {
+            Some(node) => {
+                self.bury(node);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The `k` nearest live points to `query` that `accept` lets through,
+    /// nearest first
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        accept: &dyn Fn(&str) -> bool,
+    ) -> Vec<SearchResult> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if k == 0 || query.len() != self.dimension {
+            return Vec::new();
+        }
+        let mut nearest = vec![self.scored(query, entry)];
+        for layer in (1..self.links[entry as usize].len()).rev() {
+            nearest = self.search_layer(query, &nearest, 1, layer, &|_| true);
+        }
+        let ef = self.config.ef_search.max(k);
+        let admit = |node: u32| !self.deleted[node as usize] && accept(&self.ids[node as usize]);
+        self.search_layer(query, &nearest, ef, 0, &admit)
+            .into_iter()
+            .take(k)
+            .map(|s| SearchResult::new(self.ids[s.node as usize].clone(), self.score(s.distance)))
+            .collect()
+    }
+
+    /// Best-first search of one layer from `entry`, keeping the `ef`
+    /// nearest nodes that `admit` lets into the results (nearest first).
+    /// Nodes it rejects are still expanded.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry: &[Scored],
+        ef: usize,
+        layer: usize,
+        admit: &dyn Fn(u32) -> bool,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = entry.iter().map(|s| s.node).collect();
+        let mut candidates: BinaryHeap<Reverse<Scored>> =
+            entry.iter().copied().map(Reverse).collect();
+        let mut results: BinaryHeap<Scored> =
+            entry.iter().copied().filter(|s| admit(s.node)).collect();
+        while results.len() > ef {
+            results.pop();
+        }
+
+        let prefetch = self.prefetching();
+        while let Some(Reverse(current)) = candidates.pop() {
+            let worst = results.peek().map(|s| s.distance);
+            if results.len() >= ef && worst.is_some_and(|w| current.distance > w) {
+                break;
+            }
+            let Some(neighbours) = self.links[current.node as usize].get(layer) else {
+                continue;
+            };
+            if prefetch {
+                if let Some(Reverse(next)) = candidates.peek() {
+                    self.prefetch_links(next.node);
+                }
+                if let Some(&first) = neighbours.first() {
+                    self.prefetch_vector(first);
+                }
+            }
+            for (i, &neighbour) in neighbours.iter().enumerate() {
+                if prefetch {
+                    if let Some(&ahead) = neighbours.get(i + 1) {
+                        self.prefetch_vector(ahead);
+                    }
+                }
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let next = self.scored(query, neighbour);
+                let worst = results.peek().map(|s| s.distance);
+                if results.len() < ef || worst.is_some_and(|w| next.distance < w) {
+                    candidates.push(Reverse(next));
+                    if admit(neighbour) {
+                        results.push(next);
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// Pick up to `max` neighbours from `candidates` (nearest first),
+    /// preferring ones that aren't already covered by a closer pick: a
+    /// candidate nearer to a selected neighbour than to the new node is
+    /// reachable through it. Skipped candidates fill any remaining slots.
+    fn select(&self, candidates: &[Scored], max: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max);
+        let mut skipped = Vec::new();
+        for candidate in candidates {
+            if selected.len() == max {
+                break;
+            }
+            let covered = selected
+                .iter()
+                .any(|&s| self.node_distance(candidate.node, s) < candidate.distance);
+            if covered {
+                skipped.push(candidate.node);
+            } else {
+                selected.push(candidate.node);
+            }
+        }
+        let room = max - selected.len();
+        selected.extend(skipped.into_iter().take(room));
+        selected
+    }
+
+    /// Cut `node`'s neighbour list on `layer` back to its limit
+    fn shrink(&mut self, node: u32, layer: usize) {
+        let base = self.vector(node).to_vec();
+        let mut candidates: Vec<Scored> = self.links[node as usize][layer]
+            .iter()
+            .map(|&n| self.scored(&base, n))
+            .collect();
+        candidates.sort();
+        self.links[node as usize][layer] = self.select(&candidates, self.max_links(layer));
+    }
+
+    fn bury(&mut self, node: u32) {
+        self.deleted[node as usize] = true;
+        self.tombstones += 1;
+        if self.tombstones > self.nodes.len() {
+            self.rebuild();
+        }
+    }
+
+    /// Rebuild the graph from its live points, dropping tombstones
+    fn rebuild(&mut self) {
+        let mut live: Vec<u32> = self.nodes.values().copied().collect();
+        live.sort_unstable();
+        let mut fresh = Self::new(self.config, self.metric, self.dimension);
+        fresh.kernel = self.kernel;
+        for node in live {
+            fresh.insert(&self.ids[node as usize], self.vector(node));
+        }
+        fresh.relayout();
+        *self = fresh;
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        match layer {
+            0 => self.config.m * 2,
+            _ => self.config.m,
+        }
+    }
+
+    /// Layer for a new node: floor(-ln(u) / ln(m)), so each layer keeps
+    /// about 1/m of the one below
+    fn random_level(&self, node: u32) -> usize {
+        let u = 1.0 - Rng::stream(LEVEL_SEED, 0, node as u64).unit();
+        let level = -u.ln() / (self.config.m as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+
+    /// Renumber the nodes in traversal order so neighbours sit near each
+    /// other in memory (see "Layout" above). Searches return the same
+    /// results before and after.
+    pub fn relayout(&mut self) -> Layout {
+        let edge_span_before = self.edge_span();
+        let order = self.traversal_order();
+        let mut rank = vec![0u32; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            rank[old as usize] = new as u32;
+        }
+
+        let mut data = Vec::with_capacity(self.data.len());
+        for &old in &order {
+            data.extend_from_slice(self.vector(old));
+        }
+        let mut links = std::mem::take(&mut self.links);
+        let mut ids = std::mem::take(&mut self.ids);
+        self.links = order
+            .iter()
+            .map(|&old| {
+                let mut layers = std::mem::take(&mut links[old as usize]);
+                for node in layers.iter_mut().flatten() {
+                    *node = rank[*node as usize];
+                }
+                layers
+            })
+            .collect();
+        self.ids = order
+            .iter()
+            .map(|&old| std::mem::take(&mut ids[old as usize]))
+            .collect();
+        self.deleted = order
+            .iter()
+            .map(|&old| self.deleted[old as usize])
+            .collect();
+        self.data = data;
+        for node in self.nodes.values_mut() {
+            *node = rank[*node as usize];
+        }
+        self.entry = self.entry.map(|entry| rank[entry as usize]);
+
+        Layout {
+            nodes: order.len(),
+            edge_span_before,
+            edge_span_after: self.edge_span(),
+        }
+    }
+
+    /// Mean distance between the numbers of linked nodes on layer 0
+    pub fn edge_span(&self) -> f64 {
+        let (mut total, mut count) = (0u64, 0u64);
+        for (node, layers) in self.links.iter().enumerate() {
+            for &neighbour in layers.first().into_iter().flatten() {
+                total += (neighbour as i64 - node as i64).unsigned_abs();
+                count += 1;
+            }
+        }
+        match count {
+            0 => 0.0,
+            _ => total as f64 / count as f64,
+        }
+    }
+
+    /// Every node once, breadth-first over layer 0 from the entry point;
+    /// then whatever that missed, highest layer and degree first
+    fn traversal_order(&self) -> Vec<u32> {
+        let mut rest: Vec<u32> = (0..self.ids.len() as u32).collect();
+        rest.sort_by_key(|&node| {
+            let layers = &self.links[node as usize];
+            (Reverse(layers.len()), Reverse(layers[0].len()), node)
+        });
+
+        let mut order = Vec::with_capacity(self.ids.len());
+        let mut seen = vec![false; self.ids.len()];
+        let mut queue = VecDeque::new();
+        for start in self.entry.into_iter().chain(rest) {
+            if std::mem::replace(&mut seen[start as usize], true) {
+                continue;
+            }
+            queue.push_back(start);
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+                for &next in &self.links[node as usize][0] {
+                    if !std::mem::replace(&mut seen[next as usize], true) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    /// Is the graph big enough for prefetching to pay?
+    fn prefetching(&self) -> bool {
+        worth_prefetching(
+            self.config.prefetch,
+            self.data.len() * std::mem::size_of::<f32>(),
+        )
+    }
+
+    fn prefetch_vector(&self, node: u32) {
+        let vector = self.vector(node);
+        for line in (0..vector.len()).step_by(LINE_FLOATS).take(PREFETCH_LINES) {
+            prefetch(vector[line..].as_ptr());
+        }
+    }
+
+    /// Prefetch `node`'s per-layer list headers; reading a list's own
+    /// address from them would already be the load this tries to hide
+    fn prefetch_links(&self, node: u32) {
+        prefetch(self.links[node as usize].as_ptr());
+    }
+
+    fn vector(&self, node: u32) -> &[f32] {
+        let start = node as usize * self.dimension;
+        &self.data[start..start + self.dimension]
+    }
+
+    fn scored(&self, query: &[f32], node: u32) -> Scored {
+        Scored {
+            distance: self.distance(self.kernel.calculate(query, self.vector(node))),
+            node,
+        }
+    }
+
+    fn node_distance(&self, a: u32, b: u32) -> f32 {
+        self.distance(self.kernel.calculate(self.vector(a), self.vector(b)))
+    }
+
+    /// Metric score → distance (lower is better)
+    fn distance(&self, score: f32) -> f32 {
+        match self.metric.higher_is_better() {
+            true => -score,
+            false => score,
+        }
+    }
+
+    /// Distance → metric score
+    fn score(&self, distance: f32) -> f32 {
+        self.distance(distance)
+    }
+}
+
+fn worth_prefetching(enabled: bool, graph_bytes: usize) -> bool {
+    enabled && graph_bytes >= PREFETCH_MIN_BYTES
+}
+
+/// Hint that the cache line holding `ptr` will be read soon
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    // SAFETY: a prefetch is only a hint; it never faults, whatever the
+    // address
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast::<i8>());
+    }
+    // SAFETY: as above; PRFM doesn't fault either
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        std::arch::asm!(
+            "prfm pldl1keep, [{}]",
+            in(reg) ptr,
+            options(nostack, readonly, preserves_flags)
+        );
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = ptr;
+}
+
+/// Seconds since the Unix epoch (0 if the clock is before 1970)
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::search;
+
+    fn random_points(n: usize, dim: usize, seed: u64) -> Vec<(String, Vec<f32>)> {
+        (0..n as u64)
+            .map(|i| {
+                let mut rng = Rng::stream(seed, 1, i);
+                (i.to_string(), (0..dim).map(|_| rng.normal()).collect())
+            })
+            .collect()
+    }
+
+    /// A graph small enough to build quickly in debug builds
+    fn build(points: &[(String, Vec<f32>)], metric: DistanceMetric) -> Hnsw {
+        let config = HnswConfig {
+            m: 8,
+            ef_construction: 48,
+            ef_search: 48,
+            prefetch: true,
+        };
+        let mut graph = Hnsw::new(config, metric, points[0].1.len());
+        for (id, vector) in points {
+            graph.insert(id, vector);
+        }
+        graph
+    }
+
+    /// Fraction of the exact top-k (among points `accept` passes) found
+    fn recall(
+        graph: &Hnsw,
+        points: &[(String, Vec<f32>)],
+        metric: DistanceMetric,
+        accept: &dyn Fn(&str) -> bool,
+    ) -> f64 {
+        let k = 10;
+        let mut found = 0;
+        for (_, query) in random_points(20, points[0].1.len(), 99) {
+            let exact = search::brute_force(
+                &query,
+                metric,
+                k,
+                points
+                    .iter()
+                    .filter(|(id, _)| accept(id))
+                    .map(|(id, v)| (id.as_str(), v.as_slice())),
+            );
+            let approx: HashSet<String> = graph
+                .search(&query, k, accept)
+                .into_iter()
+                .map(|r| r.id)
+                .collect();
+            found += exact.iter().filter(|r| approx.contains(&r.id)).count();
+        }
+        found as f64 / (20 * k) as f64
+    }
+
+    #[test]
+    fn test_recall_against_exact_search() {
+        let points = random_points(1000, 12, 3);
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::Cosine] {
+            let graph = build(&points, metric);
+            let r = recall(&graph, &points, metric, &|_| true);
+            assert!(r >= 0.9, "{:?} recall {}", metric, r);
+        }
+    }
+
+    #[test]
+    fn test_filtered_search_only_returns_matches() {
+        let points = random_points(1000, 12, 4);
+        let graph = build(&points, DistanceMetric::Euclidean);
+        // One point in ten, spread through the graph
+        let accept = |id: &str| id.parse::<u32>().unwrap() % 10 == 0;
+        let hits = graph.search(&points[5].1, 10, &accept);
+        assert_eq!(hits.len(), 10);
+        assert!(hits.iter().all(|h| accept(&h.id)));
+        let r = recall(&graph, &points, DistanceMetric::Euclidean, &accept);
+        assert!(r >= 0.9, "filtered recall {}", r);
+    }
+
+    #[test]
+    fn test_removed_points_leave_results_and_rebuild() {
+        let points = random_points(200, 8, 5);
+        let mut graph = build(&points, DistanceMetric::Euclidean);
+        assert!(graph.remove("0"));
+        assert!(!graph.remove("0"));
+        let hits = graph.search(&points[0].1, 5, &|_| true);
+        assert!(hits.iter().all(|h| h.id != "0"));
+
+        // Re-inserting moves the ID to a new node
+        graph.insert("1", &points[0].1);
+        assert_eq!(graph.search(&points[0].1, 1, &|_| true)[0].id, "1");
+        assert_eq!(graph.len(), 199);
+
+        // Deleting most points triggers a rebuild without the tombstones
+        for (id, _) in &points[2..150] {
+            graph.remove(id);
+        }
+        assert_eq!(graph.len(), 51);
+        assert!(graph.nodes() < 200);
+        let hits = graph.search(&points[160].1, 1, &|_| true);
+        assert_eq!(hits[0].id, "160");
+    }
+
+    #[test]
+    fn test_scores_use_the_metric_sign() {
+        let points = random_points(50, 4, 6);
+        let graph = build(&points, DistanceMetric::Dot);
+        let hits = graph.search(&points[7].1, 3, &|_| true);
+        let exact = DistanceMetric::Dot.calculate(
+            &points[7].1,
+            &points[hits[0].id.parse::<usize>().unwrap()].1,
+        );
+        assert_eq!(hits[0].score, exact);
+        assert!(hits[0].score >= hits[1].score);
+    }
+
+    #[test]
+    fn test_plan_prefers_a_scan_for_selective_filters() {
+        let config = HnswConfig::default();
+        assert_eq!(plan(&config, 0.01, 100_000), FilterStrategy::PreFilter);
+        assert_eq!(plan(&config, 0.5, 100_000), FilterStrategy::InGraph);
+        // Small collections: scanning everything is already cheap
+        assert_eq!(plan(&config, 0.9, 1_000), FilterStrategy::PreFilter);
+        assert_eq!(plan(&config, 0.0, 100_000), FilterStrategy::PreFilter);
+    }
+
+    #[test]
+    fn test_prefetch_only_past_the_cache() {
+        assert!(!worth_prefetching(true, 1024));
+        assert!(worth_prefetching(true, PREFETCH_MIN_BYTES));
+        assert!(!worth_prefetching(false, PREFETCH_MIN_BYTES));
+
+        // The hints themselves don't change what a search reads
+        let points = random_points(50, 12, 3);
+        let graph = build(&points, DistanceMetric::Euclidean);
+        for node in 0..graph.nodes() as u32 {
+            graph.prefetch_vector(node);
+            graph.prefetch_links(node);
+        }
+        assert_eq!(graph.search(&points[7].1, 1, &|_| true)[0].id, points[7].0);
+    }
+
+    #[test]
+    fn test_relayout_keeps_results_and_shortens_hops() {
+        let points = random_points(600, 2, 6);
+        let mut graph = build(&points, DistanceMetric::Euclidean);
+        graph.remove("3");
+        let before = graph.clone();
+
+        let layout = graph.relayout();
+        assert_eq!(layout.nodes, 600);
+        assert!(
+            layout.edge_span_after < layout.edge_span_before / 2.0,
+            "{:?}",
+            layout
+        );
+        assert_eq!(graph.edges(), before.edges());
+        assert_eq!(graph.vector_of("42"), before.vector_of("42"));
+        // The entry point is node 0 now
+        assert_eq!(graph.entry, Some(0));
+
+        let ranked = |g: &Hnsw, q: &[f32]| -> Vec<(String, f32)> {
+            g.search(q, 10, &|_| true)
+                .into_iter()
+                .map(|r| (r.id, r.score))
+                .collect()
+        };
+        for (_, query) in random_points(10, 2, 7) {
+            assert_eq!(ranked(&graph, &query), ranked(&before, &query));
+        }
+
+        // Still a working graph: writes after the relayout land normally
+        graph.insert("new", &points[3].1);
+        assert!(!graph.remove("3"));
+        assert_eq!(graph.search(&points[3].1, 1, &|_| true)[0].id, "new");
+    }
+
+    #[test]
+    fn test_validate_rejects_degenerate_configs() {
+        assert!(validate(&HnswConfig::default()).is_ok());
+        let bad = HnswConfig {
+            m: 1,
+            ..HnswConfig::default()
+        };
+        assert!(validate(&bad).is_err());
+        let bad = HnswConfig {
+            ef_search: 0,
+            ..HnswConfig::default()
+        };
+        assert!(validate(&bad).is_err());
+    }
+}
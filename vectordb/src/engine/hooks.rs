@@ -0,0 +1,246 @@
+// src/engine/hooks.rs
+//
+// Callbacks that library users attach to a collection, run around inserts
+// and searches.
+//
+// Embedding VectorDB as a library shouldn't mean forking the engine to add
+// a check or a log line. A hook sees every write or query on the
+// collection it's registered with:
+//
+//   insert:  defaults → computed fields → before_insert hooks → schema check
+//            → store → after_insert hooks
+//   search:  before_search hooks → rank → calibrate → after_search hooks
+//
+// `before_*` hooks can change what goes in (enrichment) or veto it by
+// returning an error, which the caller gets as-is (validation). `after_*`
+// hooks see the outcome: after_insert can't fail (the point is already
+// stored), while after_search may rewrite the results. Hooks run in the
+// order they were added. The server doesn't serve cached results for a
+// collection with search hooks, so they see every search.
+//
+//   struct RequireSource;
+//   impl InsertHook for RequireSource {
+//       fn before_insert(&self, _: &str, id: &str, v: &mut Vector) -> Result<()> {
+//           match v.metadata.contains_key("source") {
+//               true => Ok(()),
+//               false => Err(VectorDbError::InvalidParameter(format!("'{}' has no source", id))),
+//           }
+//       }
+//   }
+//   collection.hooks.add_insert(Arc::new(RequireSource));
+//
+// Hooks run under whatever lock protects the collection, so keep them
+// quick; send slow side-channel work (shipping logs) elsewhere. They live
+// in memory only: a collection restored from disk starts with none.
+
+use crate::models::{Result, SearchResult, Vector};
+use std::fmt;
+use std::sync::Arc;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HOOK TRAITS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Runs around every point written to a collection.
+pub trait InsertHook: Send + Sync {
+    /// Inspect or change `vector` before it's validated and stored; an
+    /// error rejects the write
+    fn before_insert(&self, collection: &str, id: &str, vector: &mut Vector) -> Result<()> {
+        let _ = (collection, id, vector);
+        Ok(())
+    }
+
+    /// Observe a point that was just stored
+    fn after_insert(&self, collection: &str, id: &str, vector: &Vector) {
+        let _ = (collection, id, vector);
+    }
+}
+
+/// Runs around every search of a collection.
+pub trait SearchHook: Send + Sync {
+    /// Inspect a query before it runs; an error rejects it
+    fn before_search(&self, collection: &str, query: &[f32], top_k: usize) -> Result<()> {
+        let _ = (collection, query, top_k);
+        Ok(())
+    }
+
+    /// Observe or rewrite the results before they're returned
+    fn after_search(&self, collection: &str, query: &[f32], results: &mut Vec<SearchResult>) {
+        let _ = (collection, query, results);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// REGISTRY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The hooks registered on one collection.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    insert: Vec<Arc<dyn InsertHook>>,
+    search: Vec<Arc<dyn SearchHook>>,
+}
+
+impl Hooks {
+    /// Register a hook run around inserts
+    pub fn add_insert(&mut self, hook: Arc<dyn InsertHook>) {
+        self.insert.push(hook);
+    }
+
+    /// Register a hook run around searches
+    pub fn add_search(&mut self, hook: Arc<dyn SearchHook>) {
+        self.search.push(hook);
+    }
+
+    /// Remove every hook
+    pub fn clear(&mut self) {
+        self.insert.clear();
+        self.search.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.insert.is_empty() && self.search.is_empty()
+    }
+
+    /// Are there hooks run around searches?
+    pub fn has_search(&self) -> bool {
+        !self.search.is_empty()
+    }
+
+    /// Run the before_insert hooks, stopping at the first error
+    pub fn before_insert(&self, collection: &str, id: &str, vector: &mut Vector) -> Result<()> {
+        self.insert
+            .iter()
+            .try_for_each(|hook| hook.before_insert(collection, id, vector))
+    }
+
+    /// Run the after_insert hooks
+    pub fn after_insert(&self, collection: &str, id: &str, vector: &Vector) {
+        for hook in &self.insert {
+            hook.after_insert(collection, id, vector);
+        }
+    }
+
+    /// Run the before_search hooks, stopping at the first error
+    pub fn before_search(&self, collection: &str, query: &[f32], top_k: usize) -> Result<()> {
+        self.search
+            .iter()
+            .try_for_each(|hook| hook.before_search(collection, query, top_k))
+    }
+
+    /// Run the after_search hooks
+    pub fn after_search(&self, collection: &str, query: &[f32], results: &mut Vec<SearchResult>) {
+        for hook in &self.search {
+            hook.after_search(collection, query, results);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("insert", &self.insert.len())
+            .field("search", &self.search.len())
+            .finish()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::collection::Collection;
+    use crate::models::{DistanceMetric, VectorDbError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Rejects points without a "source", stamps the rest
+    struct Enrich;
+
+    impl InsertHook for Enrich {
+        fn before_insert(&self, collection: &str, id: &str, vector: &mut Vector) -> Result<()> {
+            if !vector.metadata.contains_key("source") {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "'{}' has no source",
+                    id
+                )));
+            }
+            vector
+                .metadata
+                .insert("collection".into(), collection.into());
+            Ok(())
+        }
+    }
+
+    /// Records what was stored and searched, and keeps only the top hit
+    #[derive(Default)]
+    struct Audit {
+        stored: Mutex<Vec<String>>,
+        searches: AtomicUsize,
+    }
+
+    impl InsertHook for Audit {
+        fn after_insert(&self, _: &str, id: &str, _: &Vector) {
+            self.stored.lock().unwrap().push(id.to_string());
+        }
+    }
+
+    impl SearchHook for Audit {
+        fn before_search(&self, _: &str, _: &[f32], top_k: usize) -> Result<()> {
+            match top_k {
+                0 => Err(VectorDbError::InvalidParameter("top_k is 0".into())),
+                _ => Ok(()),
+            }
+        }
+
+        fn after_search(&self, _: &str, _: &[f32], results: &mut Vec<SearchResult>) {
+            self.searches.fetch_add(1, Ordering::Relaxed);
+            results.truncate(1);
+        }
+    }
+
+    fn sourced(data: Vec<f32>) -> Vector {
+        let mut v = Vector::new(data);
+        v.metadata.insert("source".into(), "test".into());
+        v
+    }
+
+    #[test]
+    fn test_insert_hooks_validate_and_enrich() {
+        let audit = Arc::new(Audit::default());
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        c.hooks.add_insert(Arc::new(Enrich));
+        c.hooks.add_insert(audit.clone());
+
+        let err = c
+            .insert("a".into(), Vector::new(vec![1.0, 0.0]))
+            .unwrap_err();
+        assert!(err.to_string().contains("no source"), "{}", err);
+        assert!(c.is_empty());
+
+        c.insert("b".into(), sourced(vec![1.0, 0.0])).unwrap();
+        assert_eq!(c.get("b").unwrap().metadata["collection"], "docs");
+        assert_eq!(*audit.stored.lock().unwrap(), vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn test_search_hooks_veto_and_rewrite() {
+        let audit = Arc::new(Audit::default());
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        c.insert("a".into(), Vector::new(vec![1.0, 0.0])).unwrap();
+        c.insert("b".into(), Vector::new(vec![0.0, 1.0])).unwrap();
+        assert!(!c.hooks.has_search());
+        c.hooks.add_search(audit.clone());
+        assert!(c.hooks.has_search(), "searches must skip the result cache");
+
+        assert_eq!(c.search(&[1.0, 0.1], 2).unwrap().len(), 1);
+        assert!(c.search(&[1.0, 0.1], 0).is_err());
+        assert_eq!(audit.searches.load(Ordering::Relaxed), 1);
+
+        c.hooks.clear();
+        assert_eq!(c.search(&[1.0, 0.1], 2).unwrap().len(), 2);
+    }
+}
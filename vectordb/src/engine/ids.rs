@@ -0,0 +1,255 @@
+// src/engine/ids.rs
+//
+// Server-side ID generation for points written without an ID.
+//
+// Each collection picks one strategy when it's created (see `IdStrategy`),
+// and it's reported in the collection's info so importers and replicas can
+// tell what ordering the IDs carry:
+//
+//   uuid            9f1c2e4a-07b3-4d8e-a1f0-5c6d7e8f9a0b   no order
+//   ulid            01HZX3K5V8QW2N4R6T8Y0B2D4F             string order = time order
+//   snowflake       289651207345987584                      numeric order = time order
+//   auto_increment  1, 2, 3, ...                            numeric order = write order
+//
+// Time-ordered strategies stay monotonic within one generator even if the
+// clock steps backwards or many IDs are drawn in the same millisecond.
+//
+// The auto-increment counter is persisted (logged with the writes that
+// draw from it, and kept in snapshots): the surviving IDs alone would hand
+// out a deleted highest ID again after a restart.
+
+use crate::models::IdStrategy;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Snowflake timestamps count milliseconds from 2020-01-01T00:00:00Z
+pub const SNOWFLAKE_EPOCH_MS: u64 = 1_577_836_800_000;
+
+/// Largest snowflake node ID (10 bits)
+pub const MAX_SNOWFLAKE_NODE: u16 = 1023;
+
+/// Sequence numbers per millisecond per snowflake node (12 bits)
+const SNOWFLAKE_SEQUENCE: u64 = 1 << 12;
+
+/// Crockford base32, as used by ULIDs
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Per-collection ID generator state
+#[derive(Debug, Clone, Default)]
+pub struct IdGenerator {
+    strategy: IdStrategy,
+    /// Next auto-increment value
+    next: u64,
+    /// Millisecond of the last time-ordered ID
+    last_ms: u64,
+    /// Snowflake sequence within `last_ms`
+    sequence: u64,
+    /// Random part of the last ULID, incremented within one millisecond
+    last_random: u128,
+}
+
+impl IdGenerator {
+    pub fn new(strategy: IdStrategy) -> Self {
+        Self {
+            strategy,
+            next: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn strategy(&self) -> IdStrategy {
+        self.strategy
+    }
+
+    /// Generate the next ID, or None for `IdStrategy::Client`
+    pub fn next_id(&mut self) -> Option<String> {
+        self.next_id_at(unix_millis())
+    }
+
+    /// `next_id` with an explicit clock, for tests
+    pub fn next_id_at(&mut self, now_ms: u64) -> Option<String> {
+        match self.strategy {
+            IdStrategy::Client => None,
+            IdStrategy::Uuid => Some(uuid_v4(random_u128())),
+            IdStrategy::Ulid => {
+                if now_ms > self.last_ms {
+                    self.last_ms = now_ms;
+                    self.last_random = random_u128() >> 48;
+                } else {
+                    // Same (or earlier) millisecond: bump the random part
+                    // so the new ID still sorts after the last one
+                    self.last_random = (self.last_random + 1) & ((1 << 80) - 1);
+                    if self.last_random == 0 {
+                        self.last_ms += 1;
+                    }
+                }
+                Some(ulid(self.last_ms, self.last_random))
+            }
+            IdStrategy::Snowflake { node } => {
+                if now_ms > self.last_ms {
+                    self.last_ms = now_ms;
+                    self.sequence = 0;
+                } else {
+                    self.sequence += 1;
+                    if self.sequence == SNOWFLAKE_SEQUENCE {
+                        // Sequence exhausted: borrow the next millisecond
+                        self.last_ms += 1;
+                        self.sequence = 0;
+                    }
+                }
+                let elapsed = self.last_ms.saturating_sub(SNOWFLAKE_EPOCH_MS);
+                let id = (elapsed << 22) | (u64::from(node) << 12) | self.sequence;
+                Some(id.to_string())
+            }
+            IdStrategy::AutoIncrement => {
+                let id = self.next;
+                self.next += 1;
+                Some(id.to_string())
+            }
+        }
+    }
+
+    /// The next auto-increment value, or None for other strategies
+    pub fn counter(&self) -> Option<u64> {
+        (self.strategy == IdStrategy::AutoIncrement).then_some(self.next)
+    }
+
+    /// Carry on from a persisted `counter`; it never moves backwards
+    pub fn resume(&mut self, next: u64) {
+        self.next = self.next.max(next);
+    }
+
+    /// Account for an ID written by a client (or an import), so generated
+    /// auto-increment IDs never collide with it.
+    pub fn observe(&mut self, id: &str) {
+        if self.strategy == IdStrategy::AutoIncrement {
+            if let Ok(n) = id.parse::<u64>() {
+                self.next = self.next.max(n.saturating_add(1));
+            }
+        }
+    }
+}
+
+/// Check a strategy's parameters
+pub fn validate_strategy(strategy: IdStrategy) -> Result<(), String> {
+    match strategy {
+        IdStrategy::Snowflake { node } if node > MAX_SNOWFLAKE_NODE => Err(format!(
+            "snowflake node must be 0..={}, got {}",
+            MAX_SNOWFLAKE_NODE, node
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Format 128 random bits as a version 4, RFC 4122 variant UUID
+fn uuid_v4(bits: u128) -> String {
+    let bits = (bits & !(0xF << 76) | (0x4 << 76)) & !(0x3 << 62) | (0x2 << 62);
+    let hex = format!("{:032x}", bits);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Encode a 48-bit timestamp and 80 random bits as 26 Crockford base32 chars
+fn ulid(ms: u64, random: u128) -> String {
+    let value = (u128::from(ms & ((1 << 48) - 1)) << 80) | random;
+    (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1F) as usize] as char)
+        .collect()
+}
+
+fn random_u128() -> u128 {
+    // A process-wide counter keeps two draws in the same nanosecond distinct
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let half = || {
+        let mut h = RandomState::new().build_hasher();
+        h.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        h.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        h.finish()
+    };
+    (u128::from(half()) << 64) | u128::from(half())
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_time_ordered_ids_are_monotonic() {
+        let t = SNOWFLAKE_EPOCH_MS + 1_000;
+        for strategy in [IdStrategy::Ulid, IdStrategy::Snowflake { node: 7 }] {
+            let mut ids = IdGenerator::new(strategy);
+            // Same millisecond, then a clock step backwards, then forwards
+            let drawn: Vec<String> = [t, t, t, t - 500, t + 1]
+                .iter()
+                .map(|&ms| ids.next_id_at(ms).unwrap())
+                .collect();
+            for pair in drawn.windows(2) {
+                match strategy {
+                    IdStrategy::Ulid => assert!(pair[0] < pair[1], "{:?}", pair),
+                    _ => {
+                        let (a, b): (u64, u64) =
+                            (pair[0].parse().unwrap(), pair[1].parse().unwrap());
+                        assert!(a < b, "{:?}", pair);
+                    }
+                }
+            }
+        }
+
+        let mut snowflake = IdGenerator::new(IdStrategy::Snowflake { node: 7 });
+        let id: u64 = snowflake.next_id_at(t).unwrap().parse().unwrap();
+        assert_eq!(id >> 22, 1_000);
+        assert_eq!((id >> 12) & 0x3FF, 7);
+
+        let ulid = IdGenerator::new(IdStrategy::Ulid).next_id_at(t).unwrap();
+        assert_eq!(ulid.len(), 26);
+        assert!(ulid.starts_with("01DX"), "{}", ulid); // early 2020
+    }
+
+    #[test]
+    fn test_uuid_and_auto_increment() {
+        let mut uuids = IdGenerator::new(IdStrategy::Uuid);
+        let drawn: HashSet<String> = (0..100).map(|_| uuids.next_id().unwrap()).collect();
+        assert_eq!(drawn.len(), 100);
+        let id = drawn.iter().next().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!("89ab".contains(&id[19..20]), "{}", id);
+
+        let mut seq = IdGenerator::new(IdStrategy::AutoIncrement);
+        assert_eq!(seq.next_id().unwrap(), "1");
+        seq.observe("41");
+        seq.observe("not-a-number");
+        seq.observe("7");
+        assert_eq!(seq.next_id().unwrap(), "42");
+        assert_eq!(seq.counter(), Some(43));
+        seq.resume(50);
+        seq.resume(10);
+        assert_eq!(seq.next_id().unwrap(), "50");
+        assert_eq!(IdGenerator::new(IdStrategy::Uuid).counter(), None);
+
+        assert_eq!(IdGenerator::new(IdStrategy::Client).next_id(), None);
+        assert!(validate_strategy(IdStrategy::Snowflake { node: 1024 }).is_err());
+    }
+}
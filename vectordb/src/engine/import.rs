@@ -0,0 +1,417 @@
+// src/engine/import.rs
+//
+// Row-by-row validation for bulk imports.
+//
+// The import format is the one `export` produces: JSON Lines with one
+//
+//   {"id": "doc_1", "vector": [0.1, 0.2], "metadata": {"title": "..."}}
+//
+// per line. Every row is checked the same way an upsert would be — JSON
+// shape, metadata types, dimension, schema, defaults and computed fields —
+// plus one check an upsert can't do: an ID repeated within the file.
+// IDs that already exist in the collection are handled by the import's
+// `on_conflict` policy, exactly as in a batch upsert.
+//
+// Errors are collected per row rather than stopping at the first one, so
+// `validate_only=true` can hand a data team the full list of problems
+// before they commit to a long load.
+
+use crate::engine::collection::Collection;
+use crate::models::{OnConflict, Result, Vector, VectorDbError, WriteCounts};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Shape problems found while decoding a row, as (field, message)
+pub type RowErrors = Vec<(Option<String>, String)>;
+
+/// Most row errors included in a report (the counts are always exact)
+pub const MAX_REPORTED_ERRORS: usize = 1000;
+
+/// Longest single input line accepted
+pub const MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
+/// One problem with one input row
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RowError {
+    /// 1-based line number in the input (row number for Parquet)
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// Summary of an import or a validation pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// Non-blank rows read
+    pub rows: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    /// Rows written (0 for validate_only or a rejected import)
+    pub written: usize,
+    /// Per-outcome counts: what was written, or what would be for
+    /// validate_only
+    #[serde(flatten)]
+    pub counts: WriteCounts,
+    pub errors: Vec<RowError>,
+    /// True if more than MAX_REPORTED_ERRORS errors were found
+    pub errors_truncated: bool,
+}
+
+impl ImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.invalid == 0
+    }
+}
+
+/// Validates rows as they stream in, optionally keeping the prepared
+/// vectors for the write that follows
+#[derive(Debug)]
+pub struct ImportValidator {
+    report: ImportReport,
+    seen: HashSet<String>,
+    policy: OnConflict,
+    keep_rows: bool,
+    rows: Vec<(String, Vector)>,
+    /// Bytes after the last newline, waiting for the rest of their line
+    pending: Vec<u8>,
+    line_no: usize,
+}
+
+impl ImportValidator {
+    /// `keep_rows = false` for validate_only, so memory stays flat
+    pub fn new(policy: OnConflict, keep_rows: bool) -> Self {
+        Self {
+            report: ImportReport::default(),
+            seen: HashSet::new(),
+            policy,
+            keep_rows,
+            rows: Vec::new(),
+            pending: Vec::new(),
+            line_no: 0,
+        }
+    }
+
+    /// Append raw input and check every line it completes. Chunks may
+    /// split lines anywhere.
+    pub fn feed(&mut self, collection: &Collection, bytes: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(bytes);
+        let Some(last_newline) = self.pending.iter().rposition(|b| *b == b'\n') else {
+            if self.pending.len() > MAX_LINE_BYTES {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "line {} exceeds {} bytes",
+                    self.line_no + 1,
+                    MAX_LINE_BYTES
+                )));
+            }
+            return Ok(());
+        };
+
+        let complete: Vec<u8> = self.pending.drain(..=last_newline).collect();
+        for line in complete[..last_newline].split(|b| *b == b'\n') {
+            self.line_no += 1;
+            self.check_line(collection, self.line_no, &String::from_utf8_lossy(line));
+        }
+        Ok(())
+    }
+
+    /// Check the final line if the input didn't end with a newline
+    pub fn end_input(&mut self, collection: &Collection) {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.line_no += 1;
+            self.check_line(collection, self.line_no, &String::from_utf8_lossy(&line));
+        }
+    }
+
+    /// Validate one input line against `collection`
+    pub fn check_line(&mut self, collection: &Collection, line_no: usize, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        let mut errors = Vec::new();
+        let parsed = parse_row(line, &mut errors);
+        self.check_row(collection, line_no, parsed, errors);
+    }
+
+    /// Validate one row decoded by the caller (from JSON or another input
+    /// format). `errors` are shape problems already found, as (field,
+    /// message); `parsed` is None if the row couldn't be decoded at all.
+    pub fn check_row(
+        &mut self,
+        collection: &Collection,
+        line_no: usize,
+        parsed: Option<(String, Vector)>,
+        mut errors: RowErrors,
+    ) {
+        self.report.rows += 1;
+        let id = parsed.as_ref().map(|(id, _)| id.clone());
+
+        if let Some((id, vector)) = parsed {
+            // Semantic checks only make sense once the row's shape is right
+            let shape_ok = errors.is_empty();
+            if !self.seen.insert(id.clone()) {
+                errors.push((Some("id".into()), "duplicate ID in input".into()));
+            }
+            if shape_ok {
+                match collection.resolve(&id, vector, self.policy) {
+                    Ok((outcome, vector)) if errors.is_empty() => {
+                        self.report.counts.record(outcome);
+                        if let (true, Some(vector)) = (self.keep_rows, vector) {
+                            self.rows.push((id, vector));
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e @ VectorDbError::Conflict(_)) => {
+                        errors.push((Some("id".into()), e.to_string()));
+                    }
+                    Err(VectorDbError::SchemaViolation(fields)) => {
+                        for f in fields {
+                            errors.push((Some(format!("metadata.{}", f.field)), f.message));
+                        }
+                    }
+                    Err(e @ VectorDbError::DimensionMismatch { .. }) => {
+                        errors.push((Some("vector".into()), e.to_string()));
+                    }
+                    Err(e) => errors.push((None, e.to_string())),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            self.report.valid += 1;
+            return;
+        }
+        self.report.invalid += 1;
+        for (field, message) in errors {
+            if self.report.errors.len() == MAX_REPORTED_ERRORS {
+                self.report.errors_truncated = true;
+                break;
+            }
+            self.report.errors.push(RowError {
+                line: line_no,
+                id: id.clone(),
+                field,
+                message,
+            });
+        }
+    }
+
+    /// The report so far, plus the resolved rows to write if they were
+    /// kept. Skipped rows aren't included; the rest should be resolved
+    /// again at write time in case the collection changed meanwhile.
+    pub fn finish(self) -> (ImportReport, Vec<(String, Vector)>) {
+        (self.report, self.rows)
+    }
+}
+
+/// Parse one row's JSON into (id, vector), recording shape errors
+fn parse_row(line: &str, errors: &mut RowErrors) -> Option<(String, Vector)> {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => {
+            errors.push((None, format!("invalid JSON: {}", e)));
+            return None;
+        }
+    };
+    let Value::Object(row) = value else {
+        errors.push((None, "row must be a JSON object".into()));
+        return None;
+    };
+
+    let id = match row.get("id") {
+        Some(Value::String(id)) if !id.is_empty() => Some(id.clone()),
+        Some(Value::String(_)) => {
+            errors.push((Some("id".into()), "ID cannot be empty".into()));
+            None
+        }
+        Some(other) => {
+            errors.push((
+                Some("id".into()),
+                format!("expected string, got {}", type_name(other)),
+            ));
+            None
+        }
+        None => {
+            errors.push((Some("id".into()), "missing".into()));
+            None
+        }
+    };
+
+    let data = match row.get("vector") {
+        Some(Value::Array(items)) => {
+            let mut data = Vec::with_capacity(items.len());
+            for (i, item) in items.iter().enumerate() {
+                match item.as_f64() {
+                    Some(x) => data.push(x as f32),
+                    None => errors.push((
+                        Some(format!("vector[{}]", i)),
+                        format!("expected number, got {}", type_name(item)),
+                    )),
+                }
+            }
+            Some(data)
+        }
+        Some(other) => {
+            errors.push((
+                Some("vector".into()),
+                format!("expected array, got {}", type_name(other)),
+            ));
+            None
+        }
+        None => {
+            errors.push((Some("vector".into()), "missing".into()));
+            None
+        }
+    };
+
+    let mut metadata = HashMap::new();
+    match row.get("metadata") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(fields)) => {
+            for (key, value) in fields {
+                match value {
+                    Value::String(s) => {
+                        metadata.insert(key.clone(), s.clone());
+                    }
+                    other => errors.push((
+                        Some(format!("metadata.{}", key)),
+                        format!(
+                            "invalid metadata type: expected string, got {}",
+                            type_name(other)
+                        ),
+                    )),
+                }
+            }
+        }
+        Some(other) => errors.push((
+            Some("metadata".into()),
+            format!("expected object, got {}", type_name(other)),
+        )),
+    }
+
+    Some((id?, Vector::with_metadata(data?, metadata)))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DistanceMetric;
+
+    fn validate(lines: &[&str]) -> (ImportReport, Vec<(String, Vector)>) {
+        let collection = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        let mut validator = ImportValidator::new(OnConflict::Overwrite, true);
+        for (i, line) in lines.iter().enumerate() {
+            validator.check_line(&collection, i + 1, line);
+        }
+        validator.finish()
+    }
+
+    #[test]
+    fn test_valid_rows_are_kept() {
+        let (report, rows) = validate(&[
+            r#"{"id":"a","vector":[1,0],"metadata":{"title":"x"}}"#,
+            "",
+            r#"{"id":"b","vector":[0.5,0.5]}"#,
+        ]);
+        assert!(report.is_valid());
+        assert_eq!((report.rows, report.valid), (2, 2));
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].1.metadata["title"], "x");
+    }
+
+    #[test]
+    fn test_feed_reassembles_split_lines() {
+        let collection = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        let mut validator = ImportValidator::new(OnConflict::Overwrite, true);
+        let input = b"{\"id\":\"a\",\"vector\":[1,0]}\n{\"id\":\"b\",\"vec";
+        validator.feed(&collection, &input[..10]).unwrap();
+        validator.feed(&collection, &input[10..]).unwrap();
+        validator.feed(&collection, b"tor\":[0,1,2]}").unwrap();
+        validator.end_input(&collection);
+
+        let (report, rows) = validator.finish();
+        assert_eq!((report.rows, report.valid), (2, 1));
+        assert_eq!(report.errors[0].line, 2);
+        assert_eq!(rows[0].0, "a");
+    }
+
+    #[test]
+    fn test_on_conflict_policy_applies_per_row() {
+        let mut collection = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        collection
+            .insert("a".into(), Vector::new(vec![1.0, 0.0]))
+            .unwrap();
+        let lines = [
+            r#"{"id":"a","vector":[0,1]}"#,
+            r#"{"id":"b","vector":[0,1]}"#,
+        ];
+
+        let run = |policy| {
+            let mut validator = ImportValidator::new(policy, true);
+            for (i, line) in lines.iter().enumerate() {
+                validator.check_line(&collection, i + 1, line);
+            }
+            validator.finish()
+        };
+
+        let (report, rows) = run(OnConflict::Skip);
+        assert_eq!((report.counts.inserted, report.counts.skipped), (1, 1));
+        assert_eq!(rows.len(), 1);
+
+        let (report, _) = run(OnConflict::Error);
+        assert_eq!(report.invalid, 1);
+        assert_eq!(report.errors[0].field.as_deref(), Some("id"));
+
+        let (report, rows) = run(OnConflict::Overwrite);
+        assert_eq!((report.counts.inserted, report.counts.overwritten), (1, 1));
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn test_every_row_error_is_reported() {
+        let (report, rows) = validate(&[
+            r#"{"id":"a","vector":[1,0]}"#,
+            r#"{"id":"a","vector":[0,1,0]}"#,
+            r#"{"id":"b","vector":[1,0,0]}"#,
+            r#"{"id":"c","vector":[1,"x"],"metadata":{"n":3}}"#,
+            "not json",
+        ]);
+        assert_eq!((report.rows, report.valid, report.invalid), (5, 1, 4));
+        assert_eq!(rows.len(), 1);
+
+        let found: Vec<(usize, Option<&str>)> = report
+            .errors
+            .iter()
+            .map(|e| (e.line, e.field.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, Some("id")),
+                (2, Some("vector")),
+                (3, Some("vector")),
+                (4, Some("vector[1]")),
+                (4, Some("metadata.n")),
+                (5, None),
+            ]
+        );
+        assert!(report.errors[4]
+            .message
+            .contains("expected string, got number"));
+    }
+}
@@ -0,0 +1,250 @@
+// src/engine/index.rs
+//
+// Introspection for a collection's search index.
+//
+// Recall regressions after a large ingest are hard to debug without
+// seeing what the index looks like. `IndexInfo` reports the same shape for
+// every index type, so snapshots from before and after can be diffed:
+//
+//   type        which structure answers searches
+//   parameters  its build/search parameters
+//   nodes/edges graph size (HNSW), centroids (IVF) — omitted when N/A
+//   memory      heap bytes by component
+//   built_at    when the structure was last built (none for flat)
+//
+// Collections search with a flat index (an exact scan over every stored
+// vector) unless they were created with an HNSW graph (see hnsw.rs). The
+// flat index has no build step and no parameters, but its memory footprint
+// and last write time are still worth knowing.
+//
+// A collection created with `auto_index` starts flat and gets a graph
+// once it reaches the threshold: for a few thousand vectors a scan is as
+// fast as a graph search and always exact. `state` shows where it is.
+
+use crate::engine::metric;
+use crate::models::{DistanceMetric, Vector};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Which structure answers searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexType {
+    /// Exact scan over every vector
+    Flat,
+    /// Hierarchical navigable small world graph
+    Hnsw,
+}
+
+/// Where a collection stands on its way from exact scans to a graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexState {
+    /// Exact scans, with no graph planned
+    Flat,
+    /// Exact scans until the collection reaches its auto_index threshold
+    BelowThreshold,
+    /// A graph is being built in the background; searches keep using
+    /// whatever answered them before until it's installed
+    Building,
+    /// A graph answers searches and takes each write as it happens
+    Indexed,
+}
+
+/// How an index can serve a query ranked by some metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricSupport {
+    /// Correct rankings, computed exactly
+    Exact,
+    /// Correct up to the index's recall (the metric it was built for)
+    Approximate,
+    /// The index's structure doesn't reflect this metric; its rankings
+    /// would be silently wrong
+    Unsupported,
+}
+
+impl IndexType {
+    /// How this index, built for `built_for`, serves `requested`.
+    ///
+    /// A flat scan computes every score from scratch, so it's exact for any
+    /// metric. Graph and cluster indexes encode neighbourhoods under one
+    /// metric and can only be trusted for that one.
+    pub fn support(self, built_for: DistanceMetric, requested: DistanceMetric) -> MetricSupport {
+        match (self, built_for, requested) {
+            (IndexType::Flat, _, _) => MetricSupport::Exact,
+            (IndexType::Hnsw, built, requested) if built == requested => MetricSupport::Approximate,
+            (IndexType::Hnsw, _, _) => MetricSupport::Unsupported,
+        }
+    }
+
+    /// Support for every metric, e.g. "cosine: exact, euclidean: exact, dot: exact"
+    pub fn compatibility_matrix(self, built_for: DistanceMetric) -> String {
+        metric::ALL
+            .iter()
+            .map(|&m| {
+                let support = match self.support(built_for, m) {
+                    MetricSupport::Exact => "exact",
+                    MetricSupport::Approximate => "approximate",
+                    MetricSupport::Unsupported => "unsupported",
+                };
+                format!("{}: {}", metric::name(m), support)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Approximate heap usage, in bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryFootprint {
+    /// Vector data (4 bytes per dimension)
+    pub vectors: usize,
+    /// Metadata keys and values
+    pub metadata: usize,
+    /// Point IDs
+    pub ids: usize,
+    /// Retained previous versions
+    pub history: usize,
+    /// The search index's own structures (graph links, its copy of the
+    /// vectors); 0 for flat
+    pub index: usize,
+    pub total: usize,
+}
+
+impl MemoryFootprint {
+    /// Measure stored points plus `history` bytes of retained versions
+    pub fn measure(points: &HashMap<String, Vector>, history: usize) -> Self {
+        let mut footprint = Self {
+            history,
+            ..Self::default()
+        };
+        for (id, vector) in points {
+            footprint.ids += id.len();
+            footprint.vectors += vector.data.len() * std::mem::size_of::<f32>();
+            footprint.metadata += metadata_bytes(vector);
+        }
+        footprint.total =
+            footprint.vectors + footprint.metadata + footprint.ids + footprint.history;
+        footprint
+    }
+
+    /// Add `bytes` of vector components kept apart from `measure`'s points
+    /// (a binary collection's bits, or the NUMA arena)
+    pub fn with_stored_vectors(mut self, bytes: usize) -> Self {
+        self.vectors += bytes;
+        self.total += bytes;
+        self
+    }
+
+    /// Add `bytes` held by the search index
+    pub fn with_index(mut self, bytes: usize) -> Self {
+        self.index += bytes;
+        self.total += bytes;
+        self
+    }
+}
+
+/// Heap bytes of one vector's data, metadata and sparse embedding
+pub fn vector_bytes(vector: &Vector) -> usize {
+    vector.data.len() * std::mem::size_of::<f32>() + metadata_bytes(vector) + sparse_bytes(vector)
+}
+
+fn sparse_bytes(vector: &Vector) -> usize {
+    // A u32 index and an f32 value per nonzero
+    vector.sparse.as_ref().map_or(0, |s| s.nnz() * 8)
+}
+
+fn metadata_bytes(vector: &Vector) -> usize {
+    vector.metadata.iter().map(|(k, v)| k.len() + v.len()).sum()
+}
+
+/// Snapshot of a collection's index, returned by GET /api/collections/:name/index
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexInfo {
+    pub collection: String,
+    #[serde(rename = "type")]
+    pub index_type: IndexType,
+    pub state: IndexState,
+    /// Size at which a graph is built automatically, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_index_threshold: Option<usize>,
+    /// Does search return exact results?
+    pub exact: bool,
+    pub metric: DistanceMetric,
+    pub dimension: usize,
+    /// Build and search parameters (empty for flat)
+    pub parameters: serde_json::Map<String, serde_json::Value>,
+    /// Vectors the index covers
+    pub vectors: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edges: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub centroids: Option<usize>,
+    pub memory: MemoryFootprint,
+    /// Unix timestamp (seconds) of the last build, if the index has one
+    pub built_at: Option<u64>,
+    /// Unix timestamp (seconds) of the last insert, update, or delete
+    pub last_write_at: Option<u64>,
+    /// Writes not yet flushed to a segment (see storage/memtable.rs)
+    pub memtable: MemtableInfo,
+}
+
+/// A collection's unflushed writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct MemtableInfo {
+    /// IDs written since the last flush
+    pub points: usize,
+    /// Of those, upserts not in the graph yet (scored exactly by searches)
+    pub unindexed: usize,
+    pub bytes: usize,
+    /// Age of the oldest unflushed write
+    pub age_secs: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_footprint() {
+        let mut points = HashMap::new();
+        let mut v = Vector::new(vec![0.0; 4]);
+        v.metadata.insert("lang".into(), "en".into());
+        points.insert("doc1".to_string(), v);
+        points.insert("b".to_string(), Vector::new(vec![0.0; 4]));
+
+        let m = MemoryFootprint::measure(&points, 10);
+        assert_eq!(
+            m,
+            MemoryFootprint {
+                vectors: 32,
+                metadata: 6,
+                ids: 5,
+                history: 10,
+                index: 0,
+                total: 53,
+            }
+        );
+        assert_eq!(vector_bytes(&points["doc1"]), 22);
+    }
+
+    #[test]
+    fn test_flat_index_is_exact_for_every_metric() {
+        assert_eq!(
+            IndexType::Flat.compatibility_matrix(DistanceMetric::Cosine),
+            "cosine: exact, euclidean: exact, dot: exact, hamming: exact"
+        );
+    }
+
+    #[test]
+    fn test_hnsw_only_serves_its_own_metric() {
+        assert_eq!(
+            IndexType::Hnsw.compatibility_matrix(DistanceMetric::Euclidean),
+            "cosine: unsupported, euclidean: approximate, dot: unsupported, \
+             hamming: unsupported"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// src/engine/metric.rs
+//
+// Which distance metric a search uses.
+//
+// Three places can name a metric, and the most specific one wins:
+//
+//   1. the search request ("metric": "euclidean")
+//   2. the collection it targets (chosen at creation)
+//   3. the deployment-wide default (`default_metric` in src/config.rs,
+//      else cosine)
+//
+// The global default also applies to collections created without a
+// `distance`, and to searches on the flat /vectors store, which has no
+// collection. A request may only override a collection's metric if the
+// collection's index can rank by it (see `IndexType::support`). Otherwise
+// the search fails with the index's compatibility matrix, rather than
+// returning silently wrong rankings — unless the request sets
+// `"exact": true`, which falls back to an exact scan.
+
+use crate::engine::collection::Collection;
+use crate::engine::index::{IndexType, MetricSupport};
+use crate::models::{DistanceMetric, Result, VectorDbError};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static DEFAULT_METRIC: AtomicU8 = AtomicU8::new(0); // index into ALL
+
+/// Every metric, in the order they're listed in messages
+pub const ALL: [DistanceMetric; 4] = [
+    DistanceMetric::Cosine,
+    DistanceMetric::Euclidean,
+    DistanceMetric::Dot,
+    DistanceMetric::Hamming,
+];
+
+/// The deployment-wide default metric
+pub fn default_metric() -> DistanceMetric {
+    ALL[DEFAULT_METRIC.load(Ordering::Relaxed) as usize]
+}
+
+/// Change the deployment-wide default metric
+pub fn set_default_metric(metric: DistanceMetric) {
+    let index = ALL.iter().position(|m| *m == metric).unwrap_or(0);
+    DEFAULT_METRIC.store(index as u8, Ordering::Relaxed);
+}
+
+/// A metric's name as used in JSON
+pub fn name(metric: DistanceMetric) -> &'static str {
+    match metric {
+        DistanceMetric::Cosine => "cosine",
+        DistanceMetric::Euclidean => "euclidean",
+        DistanceMetric::Dot => "dot",
+        DistanceMetric::Hamming => "hamming",
+    }
+}
+
+/// Parse a metric name as used in JSON ("cosine", "euclidean", "dot",
+/// "hamming")
+pub fn parse(name: &str) -> Result<DistanceMetric> {
+    serde_json::from_value(serde_json::Value::String(name.to_ascii_lowercase())).map_err(|_| {
+        VectorDbError::InvalidParameter(format!(
+            "unknown metric '{}' (expected cosine, euclidean, dot, or hamming)",
+            name
+        ))
+    })
+}
+
+/// Resolve the metric for a search: request > collection > global default.
+///
+/// Errors if the request names a metric the collection's index can't rank
+/// by, unless `exact` allows falling back to an exact scan.
+pub fn resolve(
+    requested: Option<DistanceMetric>,
+    collection: Option<&Collection>,
+    exact: bool,
+) -> Result<DistanceMetric> {
+    let Some(collection) = collection else {
+        return Ok(requested.unwrap_or_else(default_metric));
+    };
+    let Some(metric) = requested else {
+        return Ok(collection.distance);
+    };
+    let index = collection.index_type();
+    if index.support(collection.distance, metric) == MetricSupport::Unsupported && !exact {
+        return Err(incompatible(
+            &collection.name,
+            index,
+            collection.distance,
+            metric,
+        ));
+    }
+    Ok(metric)
+}
+
+/// Error for a metric an index can't serve, with the compatibility matrix
+/// and what to do instead
+fn incompatible(
+    collection: &str,
+    index: IndexType,
+    built_for: DistanceMetric,
+    requested: DistanceMetric,
+) -> VectorDbError {
+    VectorDbError::InvalidParameter(format!(
+        "metric '{}' can't be used on collection '{}': its {:?} index was built for '{}' \
+         (supported: {}). Omit \"metric\" to search by '{}', or set \"exact\": true \
+         to fall back to an exact scan",
+        name(requested),
+        collection,
+        index,
+        name(built_for),
+        index.compatibility_matrix(built_for),
+        name(built_for)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        let euclidean = Collection::new("geo", 2, DistanceMetric::Euclidean).unwrap();
+
+        // The global default is process-wide; this is the only test that
+        // changes it, and it restores it before returning
+        set_default_metric(DistanceMetric::Dot);
+        assert_eq!(resolve(None, None, false).unwrap(), DistanceMetric::Dot);
+        assert_eq!(
+            resolve(None, Some(&euclidean), false).unwrap(),
+            DistanceMetric::Euclidean
+        );
+        assert_eq!(
+            resolve(Some(DistanceMetric::Cosine), Some(&euclidean), false).unwrap(),
+            DistanceMetric::Cosine // flat index: exact for any metric
+        );
+        set_default_metric(DistanceMetric::Cosine);
+        assert_eq!(resolve(None, None, false).unwrap(), DistanceMetric::Cosine);
+    }
+
+    #[test]
+    fn test_incompatible_metric_message() {
+        let err = incompatible(
+            "docs",
+            IndexType::Flat,
+            DistanceMetric::Cosine,
+            DistanceMetric::Euclidean,
+        )
+        .to_string();
+        assert!(err.contains("built for 'cosine'"), "{}", err);
+        assert!(err
+            .contains("(supported: cosine: exact, euclidean: exact, dot: exact, hamming: exact)"));
+        assert!(err.contains("\"exact\": true"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("Euclidean").unwrap(), DistanceMetric::Euclidean);
+        assert!(parse("manhattan").is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// src/engine/mod.rs
+//
+// Search engine: scoring and ranking candidates.
+
+pub mod alias;
+pub mod arith;
+pub mod cache;
+pub mod calibration;
+pub mod collection;
+pub mod export;
+pub mod filter;
+pub mod gemm;
+pub mod history;
+pub mod hnsw;
+pub mod hooks;
+pub mod ids;
+pub mod import;
+pub mod index;
+pub mod metric;
+pub mod normalize;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod search;
+pub mod shadow;
+pub mod stats;
+pub mod template;
+pub mod transaction;
+pub mod trash;
+#[cfg(feature = "wasm")]
+pub mod udf;
@@ -0,0 +1,102 @@
+// src/engine/normalize.rs
+//
+// Migration for collections holding unnormalized vectors.
+//
+// Cosine and dot collections are fastest and most predictable with unit
+// vectors: cosine becomes a plain dot product, and dot stops favouring
+// long vectors. Data loaded before clients normalized their embeddings
+// can mix lengths, so the normalize job rewrites each such vector as
+//
+//   data = data / |data|,  metadata.original_norm = |data|
+//
+// The original length is kept so it can be recovered (data × norm).
+// Vectors already within NORM_TOLERANCE of unit length, and all-zero
+// vectors (no direction to keep), are left alone.
+//
+// Collections created with `normalized: true` don't need the job: every
+// insert is scaled to unit length before it's stored (`to_unit`, no norm
+// kept), and cosine searches on them skip the norms entirely and rank by
+// the dot product with the normalized query.
+
+use crate::engine::metric;
+use crate::models::{DistanceMetric, Result, Vector, VectorDbError};
+
+/// Metadata key holding a normalized vector's original L2 norm
+pub const ORIGINAL_NORM_KEY: &str = "original_norm";
+
+/// How far from 1.0 a norm may be before the vector is rewritten
+pub const NORM_TOLERANCE: f32 = 1e-3;
+
+/// Only metrics where direction is what matters can be normalized safely
+pub fn check_metric(metric: DistanceMetric) -> Result<()> {
+    match metric {
+        DistanceMetric::Cosine | DistanceMetric::Dot => Ok(()),
+        DistanceMetric::Euclidean | DistanceMetric::Hamming => {
+            Err(VectorDbError::InvalidParameter(format!(
+                "normalizing would change {} distances; only cosine and dot collections \
+                 can be normalized",
+                metric::name(metric)
+            )))
+        }
+    }
+}
+
+/// L2 norm of `data`
+pub fn norm(data: &[f32]) -> f32 {
+    data.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Scale `data` to unit length in place (all-zero data stays as it is)
+pub fn to_unit(data: &mut [f32]) {
+    let n = norm(data);
+    if n > 0.0 && n != 1.0 {
+        for x in data {
+            *x /= n;
+        }
+    }
+}
+
+/// Does `vector` need rewriting?
+pub fn needs_normalizing(vector: &Vector) -> bool {
+    let n = norm(&vector.data);
+    n > 0.0 && (n - 1.0).abs() > NORM_TOLERANCE
+}
+
+/// The normalized copy of `vector` with its original norm recorded, or
+/// None if it's already unit length (or zero)
+pub fn normalized(vector: &Vector) -> Option<Vector> {
+    if !needs_normalizing(vector) {
+        return None;
+    }
+    let n = norm(&vector.data);
+    let mut out = vector.clone();
+    for x in &mut out.data {
+        *x /= n;
+    }
+    out.metadata
+        .insert(ORIGINAL_NORM_KEY.to_string(), n.to_string());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized() {
+        let v = normalized(&Vector::new(vec![3.0, 4.0])).unwrap();
+        assert_eq!(v.data, vec![0.6, 0.8]);
+        assert_eq!(v.metadata[ORIGINAL_NORM_KEY], "5");
+
+        assert!(normalized(&Vector::new(vec![0.6, 0.8])).is_none());
+        assert!(normalized(&Vector::new(vec![0.0, 0.0])).is_none());
+        assert!(check_metric(DistanceMetric::Euclidean).is_err());
+
+        let mut data = vec![0.0, 3.0, 4.0];
+        to_unit(&mut data);
+        assert_eq!(data, vec![0.0, 0.6, 0.8]);
+        let mut zero = vec![0.0; 2];
+        to_unit(&mut zero);
+        assert_eq!(zero, vec![0.0; 2]);
+    }
+}
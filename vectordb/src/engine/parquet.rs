@@ -0,0 +1,451 @@
+// src/engine/parquet.rs
+//
+// Parquet encoding for collection exports and imports (`--features parquet`).
+//
+// An export has one column per selected field, named like the CSV header:
+//
+//   id               string
+//   vector           fixed_size_list<float32>[dimension]
+//   metadata.<key>   string (null where the point doesn't have the key)
+//
+// `fields=metadata` (the default projection) expands to one column per
+// metadata key found in the collection, sorted. Rows are written in row
+// groups of up to ROW_GROUP_ROWS, zstd-compressed, and the encoded bytes
+// are handed back after every batch so the HTTP layer can stream them.
+//
+// An import needs an `id` and a `vector` column; every other column is
+// metadata, keyed by its name with any `metadata.` prefix dropped, so a
+// file written by an export comes back unchanged and a DataFrame with a
+// plain `title` column works too. IDs and metadata of any scalar type are
+// taken as their display text (an int64 ID 42 becomes "42"), nulls are
+// left out of the metadata, and the vector column may be any list of
+// integers or floats. Decoded rows go through the same ImportValidator as
+// JSON Lines, so the checks and the report are identical; the report's
+// `line` is the 1-based row number.
+
+use crate::engine::export::{ExportField, Projection};
+use crate::engine::import::RowErrors;
+use crate::models::{Result, Vector, VectorDbError};
+use arrow_array::builder::{FixedSizeListBuilder, Float32Builder, StringBuilder};
+use arrow_array::cast::AsArray;
+use arrow_array::types::Float32Type;
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// MIME type for Parquet files
+pub const CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+/// Rows per row group in an export
+pub const ROW_GROUP_ROWS: usize = 65_536;
+
+/// Rows decoded per batch in an import
+pub const IMPORT_BATCH_ROWS: usize = 1024;
+
+fn invalid(message: impl std::fmt::Display) -> VectorDbError {
+    VectorDbError::InvalidParameter(format!("invalid Parquet input: {}", message))
+}
+
+fn encoding(message: impl std::fmt::Display) -> VectorDbError {
+    VectorDbError::SerializationError(format!("Parquet encoding failed: {}", message))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Column {
+    Id,
+    Vector,
+    Metadata(String),
+}
+
+/// Every metadata key used by `vectors`, sorted: the columns for
+/// `fields=metadata`
+pub fn metadata_keys<'a>(vectors: impl IntoIterator<Item = &'a Vector>) -> Vec<String> {
+    let keys: BTreeSet<&String> = vectors
+        .into_iter()
+        .flat_map(|v| v.metadata.keys())
+        .collect();
+    keys.into_iter().cloned().collect()
+}
+
+/// An export in progress: feed it batches of rows and forward the bytes
+/// each call returns.
+pub struct ParquetExport {
+    columns: Vec<Column>,
+    dimension: i32,
+    schema: SchemaRef,
+    writer: ArrowWriter<Vec<u8>>,
+}
+
+impl std::fmt::Debug for ParquetExport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetExport")
+            .field("columns", &self.columns)
+            .field("dimension", &self.dimension)
+            .finish()
+    }
+}
+
+impl ParquetExport {
+    /// Start an export of `projection` for vectors of `dimension`;
+    /// `metadata_keys` are the columns `fields=metadata` expands to
+    pub fn new(
+        projection: &Projection,
+        dimension: usize,
+        metadata_keys: &[String],
+    ) -> Result<Self> {
+        let mut columns = Vec::new();
+        for field in projection.fields() {
+            let expanded = match field {
+                ExportField::Id => vec![Column::Id],
+                ExportField::Vector => vec![Column::Vector],
+                ExportField::Metadata => metadata_keys
+                    .iter()
+                    .map(|k| Column::Metadata(k.clone()))
+                    .collect(),
+                ExportField::MetadataKey(key) => vec![Column::Metadata(key.clone())],
+            };
+            for column in expanded {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+        }
+
+        let dimension = i32::try_from(dimension)
+            .map_err(|_| encoding(format!("dimension {} is too large", dimension)))?;
+        let fields: Vec<Field> = columns
+            .iter()
+            .map(|column| match column {
+                Column::Id => Field::new("id", DataType::Utf8, false),
+                Column::Vector => Field::new(
+                    "vector",
+                    DataType::FixedSizeList(
+                        Arc::new(Field::new("item", DataType::Float32, false)),
+                        dimension,
+                    ),
+                    false,
+                ),
+                Column::Metadata(key) => {
+                    Field::new(format!("metadata.{}", key), DataType::Utf8, true)
+                }
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .build();
+        let writer =
+            ArrowWriter::try_new(Vec::new(), schema.clone(), Some(props)).map_err(encoding)?;
+        Ok(Self {
+            columns,
+            dimension,
+            schema,
+            writer,
+        })
+    }
+
+    /// Encode `rows`, returning the bytes ready to send (often none until a
+    /// row group fills)
+    pub fn write(&mut self, rows: &[(&str, &Vector)]) -> Result<Vec<u8>> {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let arrays = self
+            .columns
+            .iter()
+            .map(|column| -> ArrayRef {
+                match column {
+                    Column::Id => {
+                        let mut b = StringBuilder::new();
+                        for (id, _) in rows {
+                            b.append_value(id);
+                        }
+                        Arc::new(b.finish())
+                    }
+                    Column::Vector => {
+                        let values =
+                            Float32Builder::with_capacity(rows.len() * self.dimension as usize);
+                        let mut b = FixedSizeListBuilder::new(values, self.dimension)
+                            .with_field(Arc::new(Field::new("item", DataType::Float32, false)));
+                        for (_, vector) in rows {
+                            b.values().append_slice(&vector.data);
+                            b.append(true);
+                        }
+                        Arc::new(b.finish())
+                    }
+                    Column::Metadata(key) => {
+                        let mut b = StringBuilder::new();
+                        for (_, vector) in rows {
+                            b.append_option(vector.metadata.get(key));
+                        }
+                        Arc::new(b.finish())
+                    }
+                }
+            })
+            .collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays).map_err(encoding)?;
+        self.writer.write(&batch).map_err(encoding)?;
+        Ok(std::mem::take(self.writer.inner_mut()))
+    }
+
+    /// Write the last row group and the footer, returning the remaining bytes
+    pub fn finish(self) -> Result<Vec<u8>> {
+        self.writer.into_inner().map_err(encoding)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One decoded input row, ready for `ImportValidator::check_row`
+#[derive(Debug)]
+pub struct DecodedRow {
+    /// 1-based row number
+    pub row: usize,
+    /// None if the ID or vector is missing
+    pub parsed: Option<(String, Vector)>,
+    pub errors: RowErrors,
+}
+
+/// Rows of a Parquet file, decoded a batch at a time
+pub struct ParquetRows {
+    reader: ParquetRecordBatchReader,
+    rows: usize,
+}
+
+impl std::fmt::Debug for ParquetRows {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetRows")
+            .field("rows", &self.rows)
+            .finish()
+    }
+}
+
+impl ParquetRows {
+    /// Open a whole Parquet file. Fails if it isn't one or lacks an `id` or
+    /// `vector` column.
+    pub fn open(data: Bytes) -> Result<Self> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(data).map_err(invalid)?;
+        let schema = builder.schema();
+        for required in ["id", "vector"] {
+            if schema.field_with_name(required).is_err() {
+                return Err(invalid(format!("no '{}' column", required)));
+            }
+        }
+        let reader = builder
+            .with_batch_size(IMPORT_BATCH_ROWS)
+            .build()
+            .map_err(invalid)?;
+        Ok(Self { reader, rows: 0 })
+    }
+
+    /// Decode the next batch of rows (None at the end of the file)
+    pub fn next_batch(&mut self) -> Option<Result<Vec<DecodedRow>>> {
+        let batch = match self.reader.next()? {
+            Ok(batch) => batch,
+            Err(e) => return Some(Err(invalid(e))),
+        };
+        let first = self.rows + 1;
+        self.rows += batch.num_rows();
+        Some(decode(&batch, first))
+    }
+}
+
+fn decode(batch: &RecordBatch, first: usize) -> Result<Vec<DecodedRow>> {
+    let options = FormatOptions::default();
+    let schema = batch.schema();
+    let column = |name: &str| -> Result<&ArrayRef> {
+        let (index, _) = schema
+            .column_with_name(name)
+            .ok_or_else(|| invalid(format!("no '{}' column", name)))?;
+        Ok(batch.column(index))
+    };
+
+    let ids = column("id")?;
+    let id_text = ArrayFormatter::try_new(ids.as_ref(), &options).map_err(invalid)?;
+
+    // Any list of numbers becomes list<float32>
+    let vectors = column("vector")?;
+    let item = Arc::new(Field::new("item", DataType::Float32, true));
+    let vectors = arrow_cast::cast(vectors, &DataType::List(item)).map_err(|_| {
+        invalid(format!(
+            "column 'vector' has type {}, expected a list of numbers",
+            vectors.data_type()
+        ))
+    })?;
+    let vectors = vectors.as_list::<i32>();
+
+    let mut metadata = Vec::new();
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        if field.name() == "id" || field.name() == "vector" {
+            continue;
+        }
+        let key = field
+            .name()
+            .strip_prefix("metadata.")
+            .unwrap_or(field.name());
+        let text = ArrayFormatter::try_new(array.as_ref(), &options).map_err(invalid)?;
+        metadata.push((key.to_string(), array, text));
+    }
+
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    for i in 0..batch.num_rows() {
+        let mut errors = RowErrors::new();
+        let id = match ids.is_null(i) {
+            true => {
+                errors.push((Some("id".into()), "missing".into()));
+                None
+            }
+            false => Some(id_text.value(i).to_string()),
+        };
+        let data = match vectors.is_null(i) {
+            true => {
+                errors.push((Some("vector".into()), "missing".into()));
+                None
+            }
+            false => {
+                let values = vectors.value(i);
+                let values = values.as_primitive::<Float32Type>();
+                if values.null_count() > 0 {
+                    for j in (0..values.len()).filter(|&j| values.is_null(j)) {
+                        errors.push((
+                            Some(format!("vector[{}]", j)),
+                            "expected number, got null".into(),
+                        ));
+                    }
+                }
+                Some(values.values().to_vec())
+            }
+        };
+        let fields: HashMap<String, String> = metadata
+            .iter()
+            .filter(|(_, array, _)| !array.is_null(i))
+            .map(|(key, _, text)| (key.clone(), text.value(i).to_string()))
+            .collect();
+
+        let parsed = match (id, data) {
+            (Some(id), Some(data)) => Some((id, Vector::with_metadata(data, fields))),
+            _ => None,
+        };
+        rows.push(DecodedRow {
+            row: first + i,
+            parsed,
+            errors,
+        });
+    }
+    Ok(rows)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::builder::{Float64Builder, Int64Builder, ListBuilder};
+
+    fn doc(x: f32, title: Option<&str>) -> Vector {
+        let mut v = Vector::new(vec![x, -x]);
+        if let Some(title) = title {
+            v.metadata.insert("title".into(), title.into());
+        }
+        v
+    }
+
+    fn decode_all(data: Vec<u8>) -> Vec<DecodedRow> {
+        let mut rows = ParquetRows::open(Bytes::from(data)).unwrap();
+        let mut out = Vec::new();
+        while let Some(batch) = rows.next_batch() {
+            out.extend(batch.unwrap());
+        }
+        out
+    }
+
+    #[test]
+    fn test_export_round_trips_through_import() {
+        let a = doc(0.5, Some("first"));
+        let b = doc(1.5, None);
+        let keys = metadata_keys([&a, &b]);
+        assert_eq!(keys, ["title"]);
+
+        let mut export = ParquetExport::new(&Projection::default(), 2, &keys).unwrap();
+        let mut data = export.write(&[("a", &a)]).unwrap();
+        data.extend(export.write(&[("b", &b)]).unwrap());
+        data.extend(export.finish().unwrap());
+        assert_eq!(&data[..4], b"PAR1");
+
+        let rows = decode_all(data);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].row, 2);
+        for (row, (id, expected)) in rows.iter().zip([("a", &a), ("b", &b)]) {
+            let (got_id, got) = row.parsed.as_ref().unwrap();
+            assert_eq!(got_id, id);
+            assert_eq!(got.data, expected.data);
+            assert_eq!(got.metadata, expected.metadata);
+        }
+    }
+
+    #[test]
+    fn test_import_converts_ids_vectors_and_metadata() {
+        // What a DataFrame would write: int ids, float64 lists, plain names
+        let mut ids = Int64Builder::new();
+        let mut vectors = ListBuilder::new(Float64Builder::new());
+        let mut years = Int64Builder::new();
+        for (id, v, year) in [(7, Some([0.25, 1.0]), Some(2020)), (8, None, None)] {
+            ids.append_value(id);
+            match v {
+                Some(v) => {
+                    vectors.values().append_slice(&v);
+                    vectors.append(true);
+                }
+                None => vectors.append(false),
+            }
+            years.append_option(year);
+        }
+        let batch = RecordBatch::try_from_iter([
+            ("id", Arc::new(ids.finish()) as ArrayRef),
+            ("vector", Arc::new(vectors.finish()) as ArrayRef),
+            ("year", Arc::new(years.finish()) as ArrayRef),
+        ])
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        let rows = decode_all(writer.into_inner().unwrap());
+
+        let (id, vector) = rows[0].parsed.clone().unwrap();
+        assert_eq!(id, "7");
+        assert_eq!(vector.data, vec![0.25, 1.0]);
+        assert_eq!(vector.metadata["year"], "2020");
+        assert!(rows[1].parsed.is_none());
+        assert_eq!(rows[1].errors, [(Some("vector".into()), "missing".into())]);
+    }
+
+    #[test]
+    fn test_import_rejects_missing_columns_and_non_parquet() {
+        let batch = RecordBatch::try_from_iter([(
+            "id",
+            Arc::new(arrow_array::StringArray::from(vec!["a"])) as ArrayRef,
+        )])
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        let err = ParquetRows::open(Bytes::from(writer.into_inner().unwrap())).unwrap_err();
+        assert!(err.to_string().contains("no 'vector' column"), "{}", err);
+
+        assert!(ParquetRows::open(Bytes::from_static(b"{\"id\":\"a\"}\n")).is_err());
+    }
+}
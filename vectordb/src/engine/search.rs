@@ -0,0 +1,1121 @@
+// src/engine/search.rs
+//
+// Brute-force (exact) k-NN search.
+//
+// Score every candidate and keep the best k. Sorting all n scores costs
+// O(n log n) and holds every candidate in memory; `TopK` instead keeps a
+// bounded heap of the k best seen so far:
+//
+//   heap top = the worst of the current top k (the threshold)
+//   candidate not better than the threshold → rejected, nothing allocated
+//   candidate better → replaces the top, O(log k)
+//
+// That's O(n log k) with k entries of memory, and since most candidates in
+// a large collection lose to the threshold, they never even get their ID
+// copied. Every search path ranks through it (see benches/topk.rs).
+//
+// The threshold also cuts the scoring itself for Euclidean: a distance is a
+// sum of non-negative terms, so once the running sum passes the threshold
+// the rest of the components can't bring it back and the candidate is
+// dropped mid-vector. Large scans split the candidates across threads
+// (`parallel_brute_force`); each worker keeps its own TopK but publishes
+// its k-th best score to a `SharedThreshold`, so every worker prunes
+// against the best bound found anywhere, not just in its own slice:
+//
+//   worker A's k-th best = 3.2 ─┐
+//   worker B's k-th best = 1.7 ─┼─► shared 1.7 ─► A drops a candidate as
+//                                │                 soon as its partial sum
+//                                │                 passes 1.7²
+//
+// Any worker's k-th best bounds the global k-th best, so pruning against
+// the shared value never drops a true neighbour. Cosine and dot scores
+// aren't monotone in the components, so those metrics scan in parallel
+// without pruning.
+//
+// Radius searches return every hit within a threshold rather than the best
+// k, one page at a time. Pages are stateless: a `Cursor` holds the last
+// hit's (score, id), and the next page is every hit after it in the total
+// (score, then id) order, so writes between pages never repeat or skip a
+// hit that was there all along.
+//
+// Top-k searches page the same way when asked to (`offset` or a page
+// token): each page is ranked afresh, deep enough to cover it, and starts
+// after the previous page's last hit rather than at a position, so a write
+// that lands between pages doesn't repeat a hit at the boundary.
+//
+// Hybrid searches rank the same points twice, by dense vector and by
+// sparse embedding, and fuse the two rankings into one (`fuse`): by
+// Reciprocal Rank Fusion, which only looks at positions, or by a weighted
+// sum of min-max normalized scores.
+
+use crate::models::{DistanceMetric, Fusion, ScoreNormalization, SearchResult};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{self, AtomicU32};
+
+/// Order two scores so that the better one comes first under `metric`.
+///
+/// NaN scores always sort last.
+pub fn compare_scores(metric: DistanceMetric, a: f32, b: f32) -> Ordering {
+    let ord = match (a.is_nan(), b.is_nan()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    };
+
+    if metric.higher_is_better() {
+        ord.reverse()
+    } else {
+        ord
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TOP-K COLLECTOR
+// ═══════════════════════════════════════════════════════════════════════════
+
+struct Entry<T> {
+    score: f32,
+    /// Arrival order: among equal scores the earlier one ranks first, as a
+    /// stable sort would have it
+    seq: u64,
+    metric: DistanceMetric,
+    item: T,
+}
+
+impl<T> Ord for Entry<T> {
+    /// Worse entries are greater, so the heap's top is the one to evict
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_scores(self.metric, self.score, other.score).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+/// The `k` best-scoring items offered so far under a metric.
+pub struct TopK<T> {
+    k: usize,
+    metric: DistanceMetric,
+    heap: BinaryHeap<Entry<T>>,
+    seq: u64,
+}
+
+impl<T> TopK<T> {
+    pub fn new(k: usize, metric: DistanceMetric) -> Self {
+        Self {
+            k,
+            metric,
+            heap: BinaryHeap::with_capacity(k.min(1024) + 1),
+            seq: 0,
+        }
+    }
+
+    /// Would an item with `score` make it in? Check this before building
+    /// an expensive item.
+    pub fn accepts(&self, score: f32) -> bool {
+        if self.heap.len() < self.k {
+            return true;
+        }
+        match self.heap.peek() {
+            Some(worst) => compare_scores(self.metric, score, worst.score) == Ordering::Less,
+            None => false,
+        }
+    }
+
+    /// The score a new item has to beat, once k items are held
+    pub fn threshold(&self) -> Option<f32> {
+        match self.heap.len() >= self.k {
+            true => self.heap.peek().map(|worst| worst.score),
+            false => None,
+        }
+    }
+
+    /// Offer an item; returns whether it was kept
+    pub fn push(&mut self, score: f32, item: T) -> bool {
+        if !self.accepts(score) {
+            return false;
+        }
+        if self.heap.len() == self.k {
+            self.heap.pop();
+        }
+        self.heap.push(Entry {
+            score,
+            seq: self.seq,
+            metric: self.metric,
+            item,
+        });
+        self.seq += 1;
+        true
+    }
+
+    /// Offer the item `make` builds, only calling it if `score` is kept
+    pub fn push_with(&mut self, score: f32, make: impl FnOnce() -> T) -> bool {
+        if !self.accepts(score) {
+            return false;
+        }
+        self.push(score, make())
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// The kept items, best first
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.item)
+            .collect()
+    }
+}
+
+impl TopK<SearchResult> {
+    /// Offer a scored result
+    pub fn push_result(&mut self, result: SearchResult) -> bool {
+        self.push(result.score, result)
+    }
+}
+
+/// Rank scored candidates best-first and keep the top `k`.
+pub fn rank(
+    results: impl IntoIterator<Item = SearchResult>,
+    metric: DistanceMetric,
+    k: usize,
+) -> Vec<SearchResult> {
+    let mut top = TopK::new(k, metric);
+    for result in results {
+        top.push_result(result);
+    }
+    top.into_sorted_vec()
+}
+
+/// Score a set of `(id, data)` candidates against `query` and rank them.
+///
+/// Candidates whose dimension differs from the query are skipped rather
+/// than silently truncated by `DistanceMetric::calculate`.
+pub fn brute_force<'a>(
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+    candidates: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+) -> Vec<SearchResult> {
+    scan(query, metric, k, candidates, None)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// PARALLEL SCAN
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Fewest candidates worth giving a thread of its own
+pub const MIN_CANDIDATES_PER_WORKER: usize = 8 * 1024;
+
+/// Components summed between checks of a partial distance
+const CHECK_EVERY: usize = 16;
+
+/// How many threads a scan of `candidates` vectors should use
+pub fn scan_workers(candidates: usize) -> usize {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    threads.min(candidates / MIN_CANDIDATES_PER_WORKER).max(1)
+}
+
+/// The best k-th score any worker of a scan has seen so far.
+///
+/// Stored as f32 bits; NaN means no worker has k results yet.
+pub struct SharedThreshold {
+    metric: DistanceMetric,
+    bits: AtomicU32,
+}
+
+impl SharedThreshold {
+    pub fn new(metric: DistanceMetric) -> Self {
+        Self {
+            metric,
+            bits: AtomicU32::new(f32::NAN.to_bits()),
+        }
+    }
+
+    pub fn get(&self) -> Option<f32> {
+        let value = f32::from_bits(self.bits.load(atomic::Ordering::Relaxed));
+        (!value.is_nan()).then_some(value)
+    }
+
+    /// Publish a worker's k-th score, if it's better than the current one
+    pub fn offer(&self, score: f32) {
+        if score.is_nan() {
+            return;
+        }
+        let _ = self.bits.fetch_update(
+            atomic::Ordering::Relaxed,
+            atomic::Ordering::Relaxed,
+            |current| {
+                let current = f32::from_bits(current);
+                let better = current.is_nan()
+                    || compare_scores(self.metric, score, current) == Ordering::Less;
+                better.then_some(score.to_bits())
+            },
+        );
+    }
+}
+
+/// Euclidean distance between `a` and `b`, or None as soon as the running
+/// sum shows it's greater than `limit`.
+///
+/// Sums in the same order as `DistanceMetric::calculate`, so a distance
+/// that completes is exactly the one it would return.
+pub fn euclidean_within(a: &[f32], b: &[f32], limit: f32) -> Option<f32> {
+    let limit_sq = limit * limit;
+    let mut sum = 0.0f32;
+    for (xs, ys) in a.chunks(CHECK_EVERY).zip(b.chunks(CHECK_EVERY)) {
+        for (x, y) in xs.iter().zip(ys) {
+            sum += (x - y).powi(2);
+        }
+        // limit_sq may round down; only the sqrt decides a tie
+        if sum > limit_sq && sum.sqrt() > limit {
+            return None;
+        }
+    }
+    Some(sum.sqrt())
+}
+
+/// `brute_force` split across `workers` threads sharing one threshold.
+///
+/// Returns the same hits in the same order as `brute_force` over
+/// `candidates`: ties still go to the earlier candidate, since each
+/// worker takes a contiguous slice and their hits are merged in order.
+pub fn parallel_brute_force(
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+    candidates: &[(&str, &[f32])],
+    workers: usize,
+) -> Vec<SearchResult> {
+    let workers = workers.clamp(1, candidates.len().max(1));
+    if workers == 1 || k == 0 {
+        return brute_force(query, metric, k, candidates.iter().copied());
+    }
+    let per_worker = (candidates.len() + workers - 1) / workers;
+    let shared = SharedThreshold::new(metric);
+    let partials: Vec<Vec<SearchResult>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(per_worker)
+            .map(|slice| {
+                let shared = &shared;
+                scope.spawn(move || scan(query, metric, k, slice.iter().copied(), Some(shared)))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("scan worker panicked"))
+            .collect()
+    });
+    rank(partials.into_iter().flatten(), metric, k)
+}
+
+/// One worker's share of a scan whose threads are managed elsewhere (see
+/// numa.rs): its local top k, pruned against `shared` too
+pub fn scan_with_threshold<'a>(
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+    candidates: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+    shared: &SharedThreshold,
+) -> Vec<SearchResult> {
+    scan(query, metric, k, candidates, Some(shared))
+}
+
+/// `brute_force` over candidates grouped into blocks, each paired with
+/// the best score any candidate in it could have (see storage/norms.rs),
+/// split across `workers` threads sharing one threshold.
+///
+/// Blocks are visited best bound first, so the threshold rises as fast as
+/// it can, and a worker stops at the first block whose bound can't beat
+/// it: no later block's bound is any better. `row` gives a candidate's
+/// components, or None to leave it out. `scored` are hits ranked
+/// beforehand (best first); their k-th score is where the threshold
+/// starts, and they're merged into the result.
+pub fn bounded_brute_force<'a>(
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+    mut blocks: Vec<(f32, &'a [String])>,
+    row: &(dyn Fn(&str) -> Option<&'a [f32]> + Sync),
+    scored: Vec<SearchResult>,
+    workers: usize,
+) -> Vec<SearchResult> {
+    if k == 0 {
+        return Vec::new();
+    }
+    blocks.sort_by(|a, b| compare_scores(metric, a.0, b.0));
+    let shared = SharedThreshold::new(metric);
+    if let Some(kth) = scored.get(k - 1) {
+        shared.offer(kth.score);
+    }
+
+    let workers = workers.clamp(1, blocks.len().max(1));
+    let worker = |w: usize| {
+        let mut top = TopK::new(k, metric);
+        for &(bound, ids) in blocks.iter().skip(w).step_by(workers) {
+            let beaten = shared
+                .get()
+                .is_some_and(|t| compare_scores(metric, bound, t) == Ordering::Greater);
+            if beaten || !top.accepts(bound) {
+                break;
+            }
+            let candidates = ids.iter().filter_map(|id| Some((id.as_str(), row(id)?)));
+            scan_into(&mut top, query, metric, candidates, Some(&shared));
+        }
+        top.into_sorted_vec()
+    };
+    let partials: Vec<Vec<SearchResult>> = if workers == 1 {
+        vec![worker(0)]
+    } else {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|w| {
+                    let worker = &worker;
+                    scope.spawn(move || worker(w))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("scan worker panicked"))
+                .collect()
+        })
+    };
+    rank(
+        scored.into_iter().chain(partials.into_iter().flatten()),
+        metric,
+        k,
+    )
+}
+
+/// One worker's scan: the local top k, pruned against the better of its
+/// own threshold and the shared one
+fn scan<'a>(
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+    candidates: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+    shared: Option<&SharedThreshold>,
+) -> Vec<SearchResult> {
+    let mut top = TopK::new(k, metric);
+    scan_into(&mut top, query, metric, candidates, shared);
+    top.into_sorted_vec()
+}
+
+/// Offer every candidate to `top`, as `scan` does
+fn scan_into<'a>(
+    top: &mut TopK<SearchResult>,
+    query: &[f32],
+    metric: DistanceMetric,
+    candidates: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+    shared: Option<&SharedThreshold>,
+) {
+    for (id, data) in candidates {
+        if data.len() != query.len() {
+            continue;
+        }
+        let score = match metric {
+            DistanceMetric::Euclidean => {
+                let local = top.threshold().filter(|t| !t.is_nan());
+                let limit = match (local, shared.and_then(SharedThreshold::get)) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                match limit {
+                    Some(limit) => match euclidean_within(query, data, limit) {
+                        Some(distance) => distance,
+                        None => continue,
+                    },
+                    None => metric.calculate(query, data),
+                }
+            }
+            _ => metric.calculate(query, data),
+        };
+        let kept = top.push_with(score, || SearchResult::new(id.to_string(), score));
+        if let (true, Some(shared), Some(threshold)) = (kept, shared, top.threshold()) {
+            shared.offer(threshold);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RADIUS SEARCH
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Where a radius search left off: the last hit of the previous page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub score: f32,
+    pub id: String,
+}
+
+impl Cursor {
+    /// Opaque token form, as sent in the `x-next-cursor` header. The ID is
+    /// hex-encoded so any ID makes a valid header value.
+    pub fn encode(&self) -> String {
+        let mut token = format!("{:08x}.", self.score.to_bits());
+        for byte in self.id.as_bytes() {
+            token.push_str(&format!("{:02x}", byte));
+        }
+        token
+    }
+
+    /// Inverse of `encode`; None for anything it didn't produce
+    pub fn parse(token: &str) -> Option<Self> {
+        let (bits, id) = token.split_once('.')?;
+        if bits.len() != 8 || id.len() % 2 != 0 || !token.is_ascii() {
+            return None;
+        }
+        let score = f32::from_bits(u32::from_str_radix(bits, 16).ok()?);
+        let id = (0..id.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&id[i..i + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(Self {
+            score,
+            id: String::from_utf8(id).ok()?,
+        })
+    }
+
+    fn of(hit: &SearchResult) -> Self {
+        Self {
+            score: hit.score,
+            id: hit.id.clone(),
+        }
+    }
+}
+
+/// Where a paged top-k search left off: how many hits came before, and
+/// the last of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageToken {
+    pub offset: usize,
+    pub after: Cursor,
+}
+
+/// Deepest a paged search may reach (offset plus page size), since every
+/// page ranks all the hits before it again
+pub const MAX_PAGE_DEPTH: usize = 10_000;
+
+impl PageToken {
+    /// Opaque token form, as sent in the `x-next-page-token` header
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.offset, self.after.encode())
+    }
+
+    /// Inverse of `encode`; None for anything it didn't produce
+    pub fn parse(token: &str) -> Option<Self> {
+        let (offset, after) = token.split_once('.')?;
+        Some(Self {
+            offset: offset.parse().ok()?,
+            after: Cursor::parse(after)?,
+        })
+    }
+}
+
+/// One page of a top-k search: of `hits` (the best offset + page_size + 1
+/// or more), the `page_size` after `token`'s last hit, or after the first
+/// `offset` without a token, and the token for the next page if any hits
+/// remain.
+pub fn top_k_page(
+    mut hits: Vec<SearchResult>,
+    metric: DistanceMetric,
+    offset: usize,
+    page_size: usize,
+    token: Option<&PageToken>,
+) -> (Vec<SearchResult>, Option<PageToken>) {
+    hits.sort_by(|a, b| compare_hits(metric, (a.score, &a.id), (b.score, &b.id)));
+    let start = match token {
+        Some(token) => hits.partition_point(|hit| {
+            compare_hits(
+                metric,
+                (hit.score, &hit.id),
+                (token.after.score, &token.after.id),
+            ) != Ordering::Greater
+        }),
+        None => offset.min(hits.len()),
+    };
+    let offset = token.map_or(offset, |t| t.offset);
+    let mut page: Vec<SearchResult> = hits.drain(start..).collect();
+    let more = page.len() > page_size;
+    page.truncate(page_size);
+    let next = match page.last() {
+        Some(last) if more => Some(PageToken {
+            offset: offset + page.len(),
+            after: Cursor::of(last),
+        }),
+        _ => None,
+    };
+    (page, next)
+}
+
+/// Is `score` within `radius`: a distance ceiling when lower is better, a
+/// similarity floor when higher is. NaN is never within.
+pub fn within(metric: DistanceMetric, score: f32, radius: f32) -> bool {
+    if metric.higher_is_better() {
+        score >= radius
+    } else {
+        score <= radius
+    }
+}
+
+/// Radius result order: best score first, ties by id
+fn compare_hits(metric: DistanceMetric, a: (f32, &str), b: (f32, &str)) -> Ordering {
+    compare_scores(metric, a.0, b.0).then_with(|| a.1.cmp(b.1))
+}
+
+/// One page of a radius search over scored hits: those within `radius`
+/// that come after `after`, best first, at most `page_size` of them, and
+/// the cursor for the next page if any hits remain.
+pub fn radius_page(
+    hits: impl IntoIterator<Item = SearchResult>,
+    metric: DistanceMetric,
+    radius: f32,
+    page_size: usize,
+    after: Option<&Cursor>,
+) -> (Vec<SearchResult>, Option<Cursor>) {
+    let mut page: Vec<SearchResult> = hits
+        .into_iter()
+        .filter(|hit| within(metric, hit.score, radius))
+        .filter(|hit| {
+            after.map_or(true, |c| {
+                compare_hits(metric, (hit.score, &hit.id), (c.score, &c.id)) == Ordering::Greater
+            })
+        })
+        .collect();
+    let by_rank = |a: &SearchResult, b: &SearchResult| {
+        compare_hits(metric, (a.score, &a.id), (b.score, &b.id))
+    };
+    if page.len() > page_size {
+        // Only the page itself needs sorting
+        page.select_nth_unstable_by(page_size, by_rank);
+        page.truncate(page_size + 1);
+    }
+    page.sort_unstable_by(by_rank);
+
+    let next = (page.len() > page_size).then(|| {
+        page.truncate(page_size);
+        page.last().map(Cursor::of)
+    });
+    (page, next.flatten())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCORE NORMALIZATION
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Map one collection's ranked scores onto a common "higher is better"
+/// scale so hits from different collections can be merged.
+///
+/// Cosine scores from a German collection and an English one aren't directly
+/// comparable (different score distributions), and Euclidean distances
+/// aren't even pointing the same way. Normalizing per collection fixes both.
+pub fn normalize_scores(
+    results: &[SearchResult],
+    metric: DistanceMetric,
+    method: ScoreNormalization,
+) -> Vec<f32> {
+    // Flip distances so that larger always means better
+    let oriented: Vec<f32> = results
+        .iter()
+        .map(|r| {
+            if metric.higher_is_better() {
+                r.score
+            } else {
+                -r.score
+            }
+        })
+        .collect();
+
+    match method {
+        ScoreNormalization::None => results.iter().map(|r| r.score).collect(),
+        ScoreNormalization::MinMax => {
+            let min = oriented.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = oriented.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let range = max - min;
+            oriented
+                .iter()
+                .map(|&s| if range > 0.0 { (s - min) / range } else { 1.0 })
+                .collect()
+        }
+        ScoreNormalization::ZScore => {
+            let n = oriented.len() as f32;
+            let mean = oriented.iter().sum::<f32>() / n;
+            let variance = oriented.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n;
+            let stddev = variance.sqrt();
+            oriented
+                .iter()
+                .map(|&s| {
+                    if stddev > 0.0 {
+                        (s - mean) / stddev
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCORE FUSION
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Hits a hybrid search takes from each ranking per result it returns,
+/// unless the request says otherwise
+pub const DEFAULT_CANDIDATES_PER_RESULT: usize = 4;
+
+/// Fuse a dense and a sparse ranking of the same collection into the
+/// `top_k` best by `fusion` (higher fused score first, ties by ID).
+///
+/// `dense` is ranked by `metric`; `sparse` by dot product.
+pub fn fuse(
+    dense: &[SearchResult],
+    metric: DistanceMetric,
+    sparse: &[SearchResult],
+    fusion: Fusion,
+    top_k: usize,
+) -> Vec<SearchResult> {
+    let mut fused: HashMap<&str, f32> = HashMap::new();
+    match fusion {
+        Fusion::Rrf { k } => {
+            for ranking in [dense, sparse] {
+                for (rank, hit) in ranking.iter().enumerate() {
+                    *fused.entry(&hit.id).or_default() += 1.0 / (k + rank as f32 + 1.0);
+                }
+            }
+        }
+        Fusion::Weighted {
+            dense_weight,
+            sparse_weight,
+        } => {
+            let rankings = [
+                (dense, metric, dense_weight),
+                (sparse, DistanceMetric::Dot, sparse_weight),
+            ];
+            for (ranking, metric, weight) in rankings {
+                let scores = normalize_scores(ranking, metric, ScoreNormalization::MinMax);
+                for (hit, score) in ranking.iter().zip(scores) {
+                    *fused.entry(&hit.id).or_default() += weight * score;
+                }
+            }
+        }
+    }
+
+    let mut results: Vec<SearchResult> = fused
+        .into_iter()
+        .map(|(id, score)| SearchResult::new(id.to_string(), score))
+        .collect();
+    results.sort_by(|a, b| {
+        compare_scores(DistanceMetric::Dot, a.score, b.score).then_with(|| a.id.cmp(&b.id))
+    });
+    results.truncate(top_k);
+    results
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::Rng;
+
+    #[test]
+    fn test_radius_pages_cover_every_hit_once() {
+        let hits: Vec<_> = [
+            ("a", 0.5),
+            ("b", 0.1),
+            ("c", 0.5),
+            ("d", 2.0),
+            ("e", 0.9),
+            ("f", f32::NAN),
+            ("g", 1.0),
+        ]
+        .iter()
+        .map(|&(id, score)| SearchResult::new(id, score))
+        .collect();
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = radius_page(
+                hits.clone(),
+                DistanceMetric::Euclidean,
+                1.0,
+                2,
+                cursor.as_ref(),
+            );
+            assert!(page.len() <= 2);
+            seen.extend(page.into_iter().map(|h| h.id));
+            match next {
+                Some(next) => cursor = Some(Cursor::parse(&next.encode()).unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["b", "a", "c", "e", "g"]);
+    }
+
+    #[test]
+    fn test_radius_is_a_floor_for_similarities() {
+        let hits = vec![
+            SearchResult::new("x", 0.95),
+            SearchResult::new("y", 0.4),
+            SearchResult::new("z", 0.8),
+        ];
+        let (page, next) = radius_page(hits, DistanceMetric::Cosine, 0.8, 10, None);
+        let ids: Vec<_> = page.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, vec!["x", "z"]);
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            score: -0.25,
+            id: "doc.7 ünïcode".to_string(),
+        };
+        assert_eq!(Cursor::parse(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::parse("nonsense"), None);
+        assert_eq!(Cursor::parse("zzzzzzzz.a"), None);
+    }
+
+    #[test]
+    fn test_brute_force_cosine_orders_descending() {
+        let a = vec![1.0, 0.0];
+        let b = vec![0.7, 0.7];
+        let c = vec![0.0, 1.0];
+        let candidates = vec![
+            ("c", c.as_slice()),
+            ("a", a.as_slice()),
+            ("b", b.as_slice()),
+        ];
+
+        let results = brute_force(&[1.0, 0.0], DistanceMetric::Cosine, 2, candidates);
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_brute_force_euclidean_orders_ascending() {
+        let near = vec![1.0, 1.0];
+        let far = vec![5.0, 5.0];
+        let candidates = vec![("far", far.as_slice()), ("near", near.as_slice())];
+
+        let results = brute_force(&[0.0, 0.0], DistanceMetric::Euclidean, 10, candidates);
+        assert_eq!(results[0].id, "near");
+        assert_eq!(results[1].id, "far");
+    }
+
+    #[test]
+    fn test_brute_force_skips_dimension_mismatch() {
+        let short = vec![1.0];
+        let ok = vec![1.0, 0.0];
+        let candidates = vec![("short", short.as_slice()), ("ok", ok.as_slice())];
+
+        let results = brute_force(&[1.0, 0.0], DistanceMetric::Dot, 10, candidates);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "ok");
+    }
+
+    /// `n` vectors of dimension `dim`, and their IDs
+    fn random_vectors(n: usize, dim: usize, seed: u64) -> (Vec<String>, Vec<Vec<f32>>) {
+        let vectors = (0..n as u64)
+            .map(|i| {
+                let mut rng = Rng::stream(seed, 0, i);
+                (0..dim).map(|_| rng.normal()).collect()
+            })
+            .collect();
+        ((0..n).map(|i| i.to_string()).collect(), vectors)
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial() {
+        let (ids, vectors) = random_vectors(2000, 40, 7);
+        // Exact duplicates, so ties have to land the same way too
+        let mut vectors = vectors;
+        vectors[1500] = vectors[10].clone();
+        let candidates: Vec<(&str, &[f32])> = ids
+            .iter()
+            .zip(&vectors)
+            .map(|(id, v)| (id.as_str(), v.as_slice()))
+            .collect();
+        let query = vectors[10].clone();
+
+        for metric in [
+            DistanceMetric::Euclidean,
+            DistanceMetric::Cosine,
+            DistanceMetric::Dot,
+        ] {
+            let serial = brute_force(&query, metric, 25, candidates.iter().copied());
+            for workers in [2, 3, 8] {
+                let parallel = parallel_brute_force(&query, metric, 25, &candidates, workers);
+                let pairs = |r: &[SearchResult]| -> Vec<(String, u32)> {
+                    r.iter()
+                        .map(|h| (h.id.clone(), h.score.to_bits()))
+                        .collect()
+                };
+                assert_eq!(
+                    pairs(&parallel),
+                    pairs(&serial),
+                    "{:?} x{}",
+                    metric,
+                    workers
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounded_scan_matches_brute_force_and_skips_blocks() {
+        use crate::storage::norms::{self, NormIndex};
+        use std::sync::atomic::AtomicUsize;
+
+        // Norms spread over two orders of magnitude
+        let (ids, mut vectors) = random_vectors(2000, 4, 11);
+        for (i, v) in vectors.iter_mut().enumerate() {
+            let scale = 1.0 + (i % 100) as f32;
+            v.iter_mut().for_each(|x| *x *= scale);
+        }
+        let candidates: Vec<(&str, &[f32])> = ids
+            .iter()
+            .zip(&vectors)
+            .map(|(id, v)| (id.as_str(), v.as_slice()))
+            .collect();
+        let rows: HashMap<&str, &[f32]> = candidates.iter().copied().collect();
+        let index = NormIndex::build(candidates.iter().copied(), 100);
+        let query = vectors[1234].clone();
+        let query_norm = norms::norm(&query);
+
+        for metric in [DistanceMetric::Dot, DistanceMetric::Euclidean] {
+            let serial = brute_force(&query, metric, 10, candidates.iter().copied());
+            let blocks: Vec<(f32, &[String])> = index
+                .blocks()
+                .map(|((min, max), ids)| (norms::best_score(metric, query_norm, min, max), ids))
+                .collect();
+            for workers in [1, 3] {
+                let read = AtomicUsize::new(0);
+                let row = |id: &str| {
+                    read.fetch_add(1, atomic::Ordering::Relaxed);
+                    rows.get(id).copied()
+                };
+                let bounded = bounded_brute_force(
+                    &query,
+                    metric,
+                    10,
+                    blocks.clone(),
+                    &row,
+                    Vec::new(),
+                    workers,
+                );
+                let ids = |r: &[SearchResult]| r.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+                assert_eq!(ids(&bounded), ids(&serial), "{:?} x{}", metric, workers);
+                let read = read.into_inner();
+                assert!(
+                    read < candidates.len() / 2,
+                    "{:?} x{} read {}",
+                    metric,
+                    workers,
+                    read
+                );
+            }
+        }
+
+        // Hits scored beforehand are merged in, and left out of the blocks
+        let scored = vec![SearchResult::new("x", f32::MAX)];
+        let blocks = index.blocks().map(|(_, ids)| (f32::MAX, ids)).collect();
+        let row = |id: &str| rows.get(id).copied();
+        let hits = bounded_brute_force(&query, DistanceMetric::Dot, 3, blocks, &row, scored, 2);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].id, "x");
+    }
+
+    #[test]
+    fn test_euclidean_within_stops_past_the_limit() {
+        let a = vec![0.0; 40];
+        let mut b = vec![0.0; 40];
+        b[0] = 3.0;
+        b[39] = 4.0;
+        let exact = DistanceMetric::Euclidean.calculate(&a, &b);
+        assert_eq!(euclidean_within(&a, &b, 10.0), Some(exact));
+        // Exactly at the limit still completes: ties are TopK's call
+        assert_eq!(euclidean_within(&a, &b, 5.0), Some(5.0));
+        // Already past 2.0 after the first block of components
+        assert_eq!(euclidean_within(&a, &b, 2.0), None);
+    }
+
+    #[test]
+    fn test_shared_threshold_keeps_the_best_bound() {
+        let shared = SharedThreshold::new(DistanceMetric::Euclidean);
+        assert_eq!(shared.get(), None);
+        shared.offer(3.0);
+        shared.offer(5.0);
+        shared.offer(f32::NAN);
+        assert_eq!(shared.get(), Some(3.0));
+
+        let shared = SharedThreshold::new(DistanceMetric::Cosine);
+        shared.offer(0.2);
+        shared.offer(0.9);
+        assert_eq!(shared.get(), Some(0.9));
+    }
+
+    #[test]
+    fn test_nan_sorts_last() {
+        let results = vec![
+            SearchResult::new("nan", f32::NAN),
+            SearchResult::new("good", 0.5),
+        ];
+        let ranked = rank(results, DistanceMetric::Cosine, 10);
+        assert_eq!(ranked[0].id, "good");
+    }
+
+    #[test]
+    fn test_top_k_matches_full_sort() {
+        // Plenty of ties, to check they keep arrival order like a stable sort
+        let scores: Vec<f32> = (0..500).map(|i| ((i * 37) % 101) as f32 / 7.0).collect();
+        for metric in [DistanceMetric::Cosine, DistanceMetric::Euclidean] {
+            for k in [0, 1, 10, 500, 600] {
+                let mut expected = results(&scores);
+                expected.sort_by(|a, b| compare_scores(metric, a.score, b.score));
+                expected.truncate(k);
+                let ranked = rank(results(&scores), metric, k);
+                let ids = |r: &[SearchResult]| r.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+                assert_eq!(ids(&ranked), ids(&expected), "{:?} k={}", metric, k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_k_threshold_prunes() {
+        let mut top = TopK::new(2, DistanceMetric::Euclidean);
+        assert_eq!(top.threshold(), None);
+        assert!(top.push(3.0, "c"));
+        assert!(top.push(1.0, "a"));
+        assert_eq!(top.threshold(), Some(3.0));
+        // Not better than the worst kept: the item is never built
+        assert!(!top.push_with(3.0, || unreachable!()));
+        assert!(top.push(2.0, "b"));
+        assert_eq!(top.threshold(), Some(2.0));
+        assert_eq!(top.into_sorted_vec(), vec!["a", "b"]);
+    }
+
+    fn results(scores: &[f32]) -> Vec<SearchResult> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| SearchResult::new(format!("v{}", i), score))
+            .collect()
+    }
+
+    #[test]
+    fn test_min_max_normalization() {
+        let r = results(&[0.9, 0.7, 0.5]);
+        let n = normalize_scores(&r, DistanceMetric::Cosine, ScoreNormalization::MinMax);
+        assert_eq!(n, vec![1.0, 0.5, 0.0]);
+
+        // Distances are flipped: the smallest distance becomes 1.0
+        let r = results(&[1.0, 3.0]);
+        let n = normalize_scores(&r, DistanceMetric::Euclidean, ScoreNormalization::MinMax);
+        assert_eq!(n, vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_z_score_normalization() {
+        let r = results(&[3.0, 1.0]);
+        let n = normalize_scores(&r, DistanceMetric::Dot, ScoreNormalization::ZScore);
+        assert!((n[0] - 1.0).abs() < 1e-6);
+        assert!((n[1] + 1.0).abs() < 1e-6);
+
+        let single = normalize_scores(
+            &results(&[0.4]),
+            DistanceMetric::Dot,
+            ScoreNormalization::ZScore,
+        );
+        assert_eq!(single, vec![0.0]);
+    }
+
+    #[test]
+    fn test_fusion_rewards_hits_in_both_rankings() {
+        // "b" is second in both; "a" and "c" top one ranking each
+        let dense = vec![
+            SearchResult::new("a", 0.1),
+            SearchResult::new("b", 0.2),
+            SearchResult::new("d", 0.9),
+        ];
+        let sparse = vec![SearchResult::new("c", 5.0), SearchResult::new("b", 4.0)];
+        let metric = DistanceMetric::Euclidean;
+
+        let rrf = fuse(&dense, metric, &sparse, Fusion::Rrf { k: 60.0 }, 3);
+        let ids: Vec<_> = rrf.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert!((rrf[0].score - 2.0 / 62.0).abs() < 1e-6);
+
+        // Weighted: distances are flipped before normalizing, and the
+        // sparse ranking counts three times as much
+        let weighted = Fusion::Weighted {
+            dense_weight: 0.25,
+            sparse_weight: 0.75,
+        };
+        let hits = fuse(&dense, metric, &sparse, weighted, 10);
+        let ids: Vec<_> = hits.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b", "d"]);
+        assert_eq!(hits[0].score, 0.75);
+        assert_eq!(hits[3].score, 0.0);
+    }
+
+    #[test]
+    fn test_top_k_pages_continue_after_the_last_hit() {
+        let hits = |scores: &[(&str, f32)]| -> Vec<SearchResult> {
+            scores
+                .iter()
+                .map(|&(id, score)| SearchResult::new(id, score))
+                .collect()
+        };
+        let ids =
+            |page: &[SearchResult]| -> Vec<String> { page.iter().map(|h| h.id.clone()).collect() };
+        let ranked = hits(&[("a", 0.9), ("b", 0.8), ("c", 0.7), ("d", 0.6), ("e", 0.5)]);
+        let metric = DistanceMetric::Cosine;
+
+        let (page, next) = top_k_page(ranked.clone(), metric, 0, 2, None);
+        assert_eq!(ids(&page), ids(&ranked[..2]));
+        let next = next.unwrap();
+        assert_eq!(PageToken::parse(&next.encode()), Some(next.clone()));
+        assert_eq!(next.offset, 2);
+
+        // A better hit written between pages doesn't push "b" onto page two
+        let mut rewritten = hits(&[("new", 0.95)]);
+        rewritten.extend(ranked.clone());
+        let (page, next) = top_k_page(rewritten, metric, 0, 2, Some(&next));
+        assert_eq!(ids(&page), ids(&ranked[2..4]));
+        assert_eq!(next.as_ref().unwrap().offset, 4);
+
+        let (page, next) = top_k_page(ranked.clone(), metric, 0, 2, next.as_ref());
+        assert_eq!(ids(&page), ids(&ranked[4..]));
+        assert_eq!(next, None);
+
+        let (page, next) = top_k_page(ranked.clone(), metric, 3, 10, None);
+        assert_eq!(ids(&page), ids(&ranked[3..]));
+        assert_eq!(next, None);
+        assert_eq!(PageToken::parse("x.3f800000.61"), None);
+    }
+}
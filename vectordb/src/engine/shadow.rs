@@ -0,0 +1,226 @@
+// src/engine/shadow.rs
+//
+// Shadow collections for embedding model upgrades.
+//
+// Switching embedding models changes every vector, so "is the new model
+// better?" can't be answered by looking at one query. While a shadow is
+// attached, inserts into the primary collection are mirrored into it with
+// the new model's embedding, and the comparison endpoint measures how much
+// the rankings move:
+//
+//   docs (old model) ──mirror──▶ docs_v2 (new model)
+//
+//   for each sampled point p present in both:
+//     old = search(docs,    old_embedding(p))
+//     new = search(docs_v2, new_embedding(p))
+//     compare(old, new)
+//
+// Sampled queries are stored points themselves (query-by-example), since
+// they're the only inputs we have embedded under both models.
+//
+// The server doesn't run models itself, so clients supply the new-model
+// embedding alongside the old one (`shadow_vector` on each point).
+
+use crate::models::SearchResult;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// How far apart two rankings for the same query are
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RankingDivergence {
+    /// Fraction of the primary's top-k also in the shadow's top-k
+    pub overlap: f32,
+
+    /// Do both rankings agree on the best hit?
+    pub top1_match: bool,
+
+    /// Mean absolute rank difference over IDs present in both lists
+    pub mean_rank_shift: f32,
+}
+
+/// Compare the primary and shadow rankings for one query.
+pub fn compare_rankings(primary: &[SearchResult], shadow: &[SearchResult]) -> RankingDivergence {
+    let shadow_ranks: HashMap<&str, usize> = shadow
+        .iter()
+        .enumerate()
+        .map(|(rank, r)| (r.id.as_str(), rank))
+        .collect();
+
+    let mut common = 0usize;
+    let mut shift = 0usize;
+    for (rank, r) in primary.iter().enumerate() {
+        if let Some(&other) = shadow_ranks.get(r.id.as_str()) {
+            common += 1;
+            shift += rank.abs_diff(other);
+        }
+    }
+
+    let k = primary.len().max(shadow.len());
+    RankingDivergence {
+        overlap: if k == 0 {
+            1.0
+        } else {
+            common as f32 / k as f32
+        },
+        top1_match: primary.first().map(|r| &r.id) == shadow.first().map(|r| &r.id),
+        mean_rank_shift: if common == 0 {
+            0.0
+        } else {
+            shift as f32 / common as f32
+        },
+    }
+}
+
+/// One sampled query whose rankings diverged
+#[derive(Debug, Clone, Serialize)]
+pub struct DivergentQuery {
+    /// ID of the point used as the query
+    pub id: String,
+
+    #[serde(flatten)]
+    pub divergence: RankingDivergence,
+}
+
+/// Aggregate comparison returned by the shadow compare endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowReport {
+    pub primary: String,
+    pub shadow: String,
+    pub top_k: usize,
+
+    /// Number of sampled queries compared
+    pub queries: usize,
+
+    /// Mean top-k overlap (1.0 = identical result sets)
+    pub mean_overlap: f32,
+
+    /// Fraction of queries whose best hit is unchanged
+    pub top1_agreement: f32,
+
+    /// Mean rank shift of results present in both lists
+    pub mean_rank_shift: f32,
+
+    /// Queries with the lowest overlap, worst first
+    pub most_divergent: Vec<DivergentQuery>,
+}
+
+/// How many divergent queries a report lists
+pub const REPORT_WORST: usize = 10;
+
+impl ShadowReport {
+    /// Aggregate per-query comparisons into a report
+    pub fn new(
+        primary: &str,
+        shadow: &str,
+        top_k: usize,
+        mut per_query: Vec<DivergentQuery>,
+    ) -> Self {
+        let n = per_query.len();
+        let mean = |f: &dyn Fn(&RankingDivergence) -> f32| {
+            if n == 0 {
+                0.0
+            } else {
+                per_query.iter().map(|q| f(&q.divergence)).sum::<f32>() / n as f32
+            }
+        };
+        let mean_overlap = mean(&|d| d.overlap);
+        let top1_agreement = mean(&|d| if d.top1_match { 1.0 } else { 0.0 });
+        let mean_rank_shift = mean(&|d| d.mean_rank_shift);
+
+        per_query.sort_by(|a, b| {
+            a.divergence
+                .overlap
+                .total_cmp(&b.divergence.overlap)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        per_query.truncate(REPORT_WORST);
+
+        Self {
+            primary: primary.to_string(),
+            shadow: shadow.to_string(),
+            top_k,
+            queries: n,
+            mean_overlap,
+            top1_agreement,
+            mean_rank_shift,
+            most_divergent: per_query,
+        }
+    }
+}
+
+/// Pick up to `n` IDs pseudo-randomly but reproducibly for a given `seed`.
+pub fn sample_ids(mut ids: Vec<String>, n: usize, seed: u64) -> Vec<String> {
+    let key = |id: &String| {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        id.hash(&mut hasher);
+        hasher.finish()
+    };
+    ids.sort_by_cached_key(key);
+    ids.truncate(n);
+    ids
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(ids: &[&str]) -> Vec<SearchResult> {
+        ids.iter()
+            .map(|id| SearchResult::new(id.to_string(), 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_rankings() {
+        let same = compare_rankings(&ranking(&["a", "b", "c"]), &ranking(&["a", "b", "c"]));
+        assert_eq!(same.overlap, 1.0);
+        assert!(same.top1_match);
+        assert_eq!(same.mean_rank_shift, 0.0);
+
+        let moved = compare_rankings(
+            &ranking(&["a", "b", "c", "d"]),
+            &ranking(&["b", "a", "x", "y"]),
+        );
+        assert_eq!(moved.overlap, 0.5);
+        assert!(!moved.top1_match);
+        assert_eq!(moved.mean_rank_shift, 1.0);
+    }
+
+    #[test]
+    fn test_report_lists_worst_first() {
+        let q = |id: &str, overlap: f32| DivergentQuery {
+            id: id.into(),
+            divergence: RankingDivergence {
+                overlap,
+                top1_match: overlap == 1.0,
+                mean_rank_shift: 0.0,
+            },
+        };
+        let report = ShadowReport::new(
+            "old",
+            "new",
+            10,
+            vec![q("a", 1.0), q("b", 0.2), q("c", 0.6)],
+        );
+        assert_eq!(report.queries, 3);
+        assert_eq!(report.most_divergent[0].id, "b");
+        assert!((report.mean_overlap - 0.6).abs() < 1e-6);
+        assert!((report.top1_agreement - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sample_ids_is_reproducible() {
+        let ids: Vec<String> = (0..100).map(|i| format!("id{}", i)).collect();
+        let a = sample_ids(ids.clone(), 10, 7);
+        assert_eq!(a.len(), 10);
+        assert_eq!(a, sample_ids(ids.clone(), 10, 7));
+        assert_ne!(a, sample_ids(ids, 10, 8));
+    }
+}
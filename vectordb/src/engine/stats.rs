@@ -0,0 +1,512 @@
+// src/engine/stats.rs
+//
+// Per-field statistics for a collection's declared metadata fields.
+//
+// Knowing how many distinct values a field holds answers "how selective
+// is `category == "news"`?" without scanning anything: with d distinct
+// values, an equality filter keeps roughly 1/d of the points. That's the
+// number a planner needs to choose between filtering first (few matches)
+// and ranking first (most points match).
+//
+// Counting distinct values exactly takes memory proportional to the
+// values, so each field keeps a HyperLogLog sketch instead:
+//
+//   2^12 one-byte registers per field (4 KiB), ~1.6% standard error
+//   insert:   hash the value; the top 12 bits pick a register, which keeps
+//             the longest run of leading zeros seen in the remaining bits
+//   estimate: harmonic mean of 2^register across registers, with the
+//             small-range (linear counting) correction
+//
+// Integer and float fields also get an equi-depth histogram, for range
+// filters (`price < 20`) and percentiles. Each bucket holds about the same
+// number of values, so skewed data gets narrow buckets where it's dense:
+//
+//   reservoir: a uniform sample of up to 1024 values (algorithm R), so
+//              memory is fixed however many points are written
+//   histogram: 32 buckets cut at the sample's quantiles, rebuilt lazily
+//              the first time it's read after the sample changed
+//   range:     whole buckets inside the range count fully, the buckets at
+//              either end by linear interpolation
+//
+// `selectivity` combines both for a whole filter expression, treating
+// clauses as independent (AND multiplies, OR adds minus the overlap);
+// fields without stats fall back to fixed guesses.
+//
+// Only fields declared in the collection's schema are tracked. Sketches
+// only grow: deleting the last point with a value doesn't lower the
+// estimate until the collection is next loaded from disk, when the
+// sketches are rebuilt from the stored points.
+
+use crate::engine::filter::{CompareOp, Filter};
+use crate::models::{CollectionSchema, FieldType};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Register index bits
+const PRECISION: u32 = 12;
+
+/// Number of registers (2^PRECISION)
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Values kept per numeric field
+const RESERVOIR_SIZE: usize = 1024;
+
+/// Buckets per histogram
+const BUCKETS: usize = 32;
+
+/// Guessed selectivity of `field == value` on an untracked field
+const DEFAULT_EQ_SELECTIVITY: f64 = 0.1;
+
+/// Guessed selectivity of a range on a field without a histogram
+const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HYPERLOGLOG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Approximate count of distinct strings.
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// Add a value to the set
+    pub fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Leading zeros of the remaining bits, plus one; the sentinel bit
+        // caps the run if they're all zero
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Estimated number of distinct values inserted
+    pub fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+
+    /// Fold another sketch's values into this one
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HISTOGRAM
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Equi-depth histogram: `bounds[i]..=bounds[i + 1]` holds 1/buckets of
+/// the values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub bounds: Vec<f64>,
+}
+
+impl Histogram {
+    /// Cut `values` into (at most) `buckets` equal-count buckets; None if
+    /// there are no values
+    pub fn from_values(mut values: Vec<f64>, buckets: usize) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        let buckets = buckets.clamp(1, values.len());
+        let last = (values.len() - 1) as f64;
+        let bounds = (0..=buckets)
+            .map(|i| quantile(&values, last * i as f64 / buckets as f64))
+            .collect();
+        Some(Self { bounds })
+    }
+
+    fn buckets(&self) -> usize {
+        self.bounds.len() - 1
+    }
+
+    /// Estimated fraction of values <= `x`
+    pub fn cdf(&self, x: f64) -> f64 {
+        let (first, last) = (self.bounds[0], self.bounds[self.buckets()]);
+        if x < first {
+            return 0.0;
+        }
+        if x >= last {
+            return 1.0;
+        }
+        // The bucket containing x; equal bounds (a repeated value) are
+        // skipped so all of that value counts as <= x
+        let i = self.bounds.partition_point(|&b| b <= x) - 1;
+        let (lo, hi) = (self.bounds[i], self.bounds[i + 1]);
+        let within = if hi > lo { (x - lo) / (hi - lo) } else { 1.0 };
+        (i as f64 + within) / self.buckets() as f64
+    }
+
+    /// Estimated value below which fraction `p` (0..=1) of values fall
+    pub fn percentile(&self, p: f64) -> f64 {
+        let position = p.clamp(0.0, 1.0) * self.buckets() as f64;
+        quantile(&self.bounds, position)
+    }
+
+    /// Estimated fraction of values that `op value` keeps
+    pub fn selectivity(&self, op: CompareOp, value: f64) -> f64 {
+        let below = self.cdf(value);
+        // Values equal to `value`: what the cdf gains across it
+        let at = below - self.cdf(value - value.abs().max(1.0) * 1e-9);
+        match op {
+            CompareOp::Le => below,
+            CompareOp::Lt => below - at,
+            CompareOp::Gt => 1.0 - below,
+            CompareOp::Ge => 1.0 - below + at,
+            CompareOp::Eq => at,
+            CompareOp::Ne => 1.0 - at,
+        }
+    }
+}
+
+/// Linear interpolation into sorted `values` at fractional `position`
+fn quantile(values: &[f64], position: f64) -> f64 {
+    let i = position.floor() as usize;
+    match values.get(i + 1) {
+        Some(&next) => values[i] + (next - values[i]) * (position - i as f64),
+        None => values[values.len() - 1],
+    }
+}
+
+/// A uniform sample of a numeric field's values.
+#[derive(Debug, Clone)]
+struct Reservoir {
+    values: Vec<f64>,
+    /// Values offered so far
+    seen: u64,
+    /// xorshift state for choosing replacements
+    rng: u64,
+    /// Built on first read after the sample changed
+    histogram: OnceLock<Option<Histogram>>,
+}
+
+impl Default for Reservoir {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            seen: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+            histogram: OnceLock::new(),
+        }
+    }
+}
+
+impl Reservoir {
+    fn offer(&mut self, value: f64) {
+        self.seen += 1;
+        if self.values.len() < RESERVOIR_SIZE {
+            self.values.push(value);
+        } else {
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            match (self.rng % self.seen) as usize {
+                slot if slot < RESERVOIR_SIZE => self.values[slot] = value,
+                _ => return,
+            }
+        }
+        self.histogram = OnceLock::new();
+    }
+
+    fn histogram(&self) -> Option<&Histogram> {
+        self.histogram
+            .get_or_init(|| Histogram::from_values(self.values.clone(), BUCKETS))
+            .as_ref()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FIELD STATS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What the stats API reports for one field
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldSummary {
+    /// Points that carried the field when written
+    pub count: u64,
+    /// Approximate number of distinct values
+    pub distinct: u64,
+    /// Estimated fraction of points an equality filter on the field keeps
+    pub eq_selectivity: f64,
+    /// Numeric fields only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Histogram>,
+}
+
+/// Sketches for one tracked field
+#[derive(Debug, Clone, Default)]
+struct FieldSketch {
+    count: u64,
+    distinct: HyperLogLog,
+    numeric: Option<Reservoir>,
+}
+
+/// Sketches for each tracked field.
+#[derive(Debug, Clone, Default)]
+pub struct FieldStats {
+    fields: HashMap<String, FieldSketch>,
+}
+
+impl FieldStats {
+    /// Track exactly the schema's fields from now on (sketches for others
+    /// are dropped); integer and float fields get histograms
+    pub fn track(&mut self, schema: &CollectionSchema) {
+        let mut tracked = HashMap::new();
+        for field in &schema.fields {
+            let mut sketch = self.fields.remove(&field.name).unwrap_or_default();
+            let numeric = matches!(field.field_type, FieldType::Integer | FieldType::Float);
+            if numeric != sketch.numeric.is_some() {
+                sketch.numeric = numeric.then(Reservoir::default);
+            }
+            tracked.insert(field.name.clone(), sketch);
+        }
+        self.fields = tracked;
+    }
+
+    /// Record a written point's metadata
+    pub fn observe(&mut self, metadata: &HashMap<String, String>) {
+        for (field, sketch) in &mut self.fields {
+            let Some(value) = metadata.get(field) else {
+                continue;
+            };
+            sketch.count += 1;
+            sketch.distinct.insert(value);
+            if let (Some(reservoir), Ok(x)) = (&mut sketch.numeric, value.parse::<f64>()) {
+                reservoir.offer(x);
+            }
+        }
+    }
+
+    /// Estimated fraction of `total` points that `field == value` keeps
+    /// (None if the field isn't tracked)
+    pub fn eq_selectivity(&self, field: &str, total: usize) -> Option<f64> {
+        let sketch = self.fields.get(field)?;
+        let distinct = sketch.distinct.estimate().max(1) as f64;
+        Some(presence(sketch.count, total) / distinct)
+    }
+
+    /// The histogram of a numeric field, if it has seen any values
+    pub fn histogram(&self, field: &str) -> Option<&Histogram> {
+        self.fields.get(field)?.numeric.as_ref()?.histogram()
+    }
+
+    /// Estimated fraction of `total` points that `filter` keeps
+    pub fn selectivity(&self, filter: &Filter, total: usize) -> f64 {
+        let estimate = match filter {
+            Filter::Compare { field, op, value } => {
+                self.compare_selectivity(field, *op, value, total)
+            }
+            Filter::And(filters) => filters.iter().map(|f| self.selectivity(f, total)).product(),
+            Filter::Or(filters) => {
+                1.0 - filters
+                    .iter()
+                    .map(|f| 1.0 - self.selectivity(f, total))
+                    .product::<f64>()
+            }
+            Filter::Not(filter) => 1.0 - self.selectivity(filter, total),
+        };
+        estimate.clamp(0.0, 1.0)
+    }
+
+    fn compare_selectivity(&self, field: &str, op: CompareOp, value: &str, total: usize) -> f64 {
+        let Some(sketch) = self.fields.get(field) else {
+            return match op {
+                CompareOp::Eq => DEFAULT_EQ_SELECTIVITY,
+                CompareOp::Ne => 1.0 - DEFAULT_EQ_SELECTIVITY,
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            };
+        };
+        // A missing field never matches, so everything scales by presence
+        let present = presence(sketch.count, total);
+        let histogram = sketch.numeric.as_ref().and_then(|r| r.histogram());
+        let within = match (histogram, value.parse::<f64>()) {
+            (Some(h), Ok(x)) => h.selectivity(op, x),
+            _ => {
+                let eq = 1.0 / sketch.distinct.estimate().max(1) as f64;
+                match op {
+                    CompareOp::Eq => eq,
+                    CompareOp::Ne => 1.0 - eq,
+                    _ => DEFAULT_RANGE_SELECTIVITY,
+                }
+            }
+        };
+        present * within
+    }
+
+    /// Summary of every tracked field, for the stats API
+    pub fn summary(&self, total: usize) -> BTreeMap<String, FieldSummary> {
+        self.fields
+            .iter()
+            .map(|(field, sketch)| {
+                let summary = FieldSummary {
+                    count: sketch.count,
+                    distinct: sketch.distinct.estimate(),
+                    eq_selectivity: self.eq_selectivity(field, total).unwrap_or(0.0),
+                    histogram: self.histogram(field).cloned(),
+                };
+                (field.clone(), summary)
+            })
+            .collect()
+    }
+}
+
+/// Fraction of `total` points carrying a field seen `count` times
+fn presence(count: u64, total: usize) -> f64 {
+    match total {
+        0 => 0.0,
+        _ => (count as f64 / total as f64).min(1.0),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FieldSchema;
+
+    fn schema(fields: &[(&str, FieldType)]) -> CollectionSchema {
+        CollectionSchema {
+            fields: fields
+                .iter()
+                .map(|(name, field_type)| FieldSchema {
+                    name: name.to_string(),
+                    field_type: *field_type,
+                    required: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_within_error() {
+        for n in [10usize, 1_000, 50_000] {
+            let mut hll = HyperLogLog::default();
+            for i in 0..n {
+                hll.insert(&format!("value-{}", i));
+                // Repeats don't count
+                hll.insert(&format!("value-{}", i / 2));
+            }
+            let error = (hll.estimate() as f64 - n as f64).abs() / n as f64;
+            assert!(error < 0.05, "n={} estimate={}", n, hll.estimate());
+        }
+    }
+
+    #[test]
+    fn test_merge_is_union() {
+        let (mut a, mut b) = (HyperLogLog::default(), HyperLogLog::default());
+        for i in 0..2_000 {
+            a.insert(&i.to_string());
+            b.insert(&(i + 1_000).to_string());
+        }
+        a.merge(&b);
+        let error = (a.estimate() as f64 - 3_000.0).abs() / 3_000.0;
+        assert!(error < 0.05, "{}", a.estimate());
+    }
+
+    #[test]
+    fn test_field_selectivity() {
+        let mut stats = FieldStats::default();
+        stats.track(&schema(&[
+            ("category", FieldType::String),
+            ("lang", FieldType::String),
+        ]));
+        for i in 0..100 {
+            let mut metadata = HashMap::new();
+            metadata.insert("category".to_string(), format!("c{}", i % 4));
+            if i % 2 == 0 {
+                metadata.insert("lang".to_string(), "en".to_string());
+            }
+            stats.observe(&metadata);
+        }
+        let summary = stats.summary(100);
+        assert_eq!(summary["category"].distinct, 4);
+        assert_eq!(summary["category"].eq_selectivity, 0.25);
+        // Half the points carry lang, all with one value
+        assert_eq!(stats.eq_selectivity("lang", 100), Some(0.5));
+        assert_eq!(stats.eq_selectivity("title", 100), None);
+    }
+
+    #[test]
+    fn test_histogram_percentiles_and_ranges() {
+        let h = Histogram::from_values((1..=1000).map(f64::from).collect(), 10).unwrap();
+        assert_eq!(h.bounds.len(), 11);
+        assert!((h.percentile(0.5) - 500.5).abs() < 1.0, "{:?}", h);
+        assert!((h.selectivity(CompareOp::Lt, 250.0) - 0.25).abs() < 0.01);
+        assert!((h.selectivity(CompareOp::Ge, 900.0) - 0.1).abs() < 0.01);
+        assert_eq!(h.selectivity(CompareOp::Gt, 5000.0), 0.0);
+
+        // Skewed data: buckets crowd where the values are
+        let mut skewed = vec![1.0; 900];
+        skewed.extend((0..100).map(|i| 100.0 + i as f64));
+        let h = Histogram::from_values(skewed, 10).unwrap();
+        assert_eq!(h.percentile(0.8), 1.0);
+        // Estimates are good to about one bucket (1/10)
+        let at_most_one = h.selectivity(CompareOp::Le, 1.0);
+        assert!((0.8..=0.9).contains(&at_most_one), "{}", at_most_one);
+        assert!(h.selectivity(CompareOp::Eq, 1.0) >= 0.8);
+        assert!(h.selectivity(CompareOp::Gt, 1.0) <= 0.2);
+    }
+
+    #[test]
+    fn test_filter_selectivity() {
+        let mut stats = FieldStats::default();
+        stats.track(&schema(&[
+            ("price", FieldType::Float),
+            ("category", FieldType::String),
+        ]));
+        // More values than the reservoir holds
+        for i in 0..10_000 {
+            let mut metadata = HashMap::new();
+            metadata.insert("price".to_string(), (i % 100).to_string());
+            metadata.insert("category".to_string(), format!("c{}", i % 5));
+            stats.observe(&metadata);
+        }
+        let estimate = |source: &str| stats.selectivity(&Filter::parse(source).unwrap(), 10_000);
+
+        assert!((estimate("price < 25") - 0.25).abs() < 0.05);
+        assert!((estimate("price >= 25 AND price < 75") - 0.5).abs() < 0.05);
+        assert!((estimate(r#"category == "c1""#) - 0.2).abs() < 0.01);
+        assert!((estimate(r#"price < 50 AND category == "c1""#) - 0.1).abs() < 0.02);
+        assert!((estimate("NOT price < 50") - 0.5).abs() < 0.05);
+        assert_eq!(estimate("title == 'x'"), DEFAULT_EQ_SELECTIVITY);
+
+        let h = stats.histogram("price").unwrap();
+        assert!((h.percentile(0.9) - 90.0).abs() < 4.0, "{:?}", h.bounds);
+        assert!(stats.histogram("category").is_none());
+    }
+}
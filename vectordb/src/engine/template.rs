@@ -0,0 +1,164 @@
+// src/engine/template.rs
+//
+// Named query templates.
+//
+// The busiest searches usually come in a handful of fixed shapes: same
+// collection, same filter, same top_k, only the vector changes. A template
+// registers that shape once:
+//
+//   PUT  /api/search/template/news   { "collection": "docs", "top_k": 5,
+//                                      "filter": "category == \"news\"" }
+//   POST /api/search/template/news   { "vector": [0.1, 0.2, ...] }
+//
+// The filter is parsed, the metric resolved and the target checked when
+// the template is registered, so an invocation only has to deserialize a
+// vector. A template that no longer fits its collection (the collection
+// was dropped, or its index can't rank by the template's metric any more)
+// fails at call time with the same errors a plain search would give.
+//
+// Templates are persisted like aliases: registering or deleting one is
+// logged to the WAL, and snapshots keep them in the catalog. A restored
+// template is compiled again from its configuration.
+
+use crate::engine::collection::validate_name;
+use crate::engine::filter::Filter;
+use crate::engine::metric;
+use crate::models::{CreateTemplateRequest, DistanceMetric, Result, SearchRequest, VectorDbError};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A registered, pre-compiled search shape.
+#[derive(Debug)]
+pub struct QueryTemplate {
+    pub name: String,
+
+    /// Collection (or alias) searched
+    pub collection: String,
+
+    /// Parsed filter, and its source for listing
+    pub filter: Option<(Filter, String)>,
+
+    pub top_k: usize,
+
+    /// Resolved when registered (None: the collection's metric)
+    pub metric: Option<DistanceMetric>,
+
+    pub exact: bool,
+
+    invocations: AtomicU64,
+}
+
+impl QueryTemplate {
+    /// Compile a template from an API request (the target collection is
+    /// checked by the caller, which owns it)
+    pub fn from_request(name: &str, req: &CreateTemplateRequest) -> Result<Self> {
+        validate_name(name)?;
+        if req.top_k == 0 {
+            return Err(VectorDbError::InvalidParameter(
+                "template top_k must be at least 1".into(),
+            ));
+        }
+        let filter = match &req.filter {
+            Some(source) => Some((Filter::parse(source)?, source.clone())),
+            None => None,
+        };
+        Ok(Self {
+            name: name.to_string(),
+            collection: req.collection.clone(),
+            filter,
+            top_k: req.top_k,
+            metric: req.metric,
+            exact: req.exact,
+            invocations: AtomicU64::new(0),
+        })
+    }
+
+    /// The search to run for one invocation
+    pub fn request(&self, vector: Vec<f32>) -> SearchRequest {
+        let mut req = SearchRequest::new(vector, self.top_k);
+        req.metric = self.metric;
+        req.exact = self.exact;
+        req
+    }
+
+    /// The parsed filter, if the template has one
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref().map(|(filter, _)| filter)
+    }
+
+    /// The configuration this template was compiled from, as persisted
+    pub fn config(&self) -> CreateTemplateRequest {
+        CreateTemplateRequest {
+            collection: self.collection.clone(),
+            filter: self.filter.as_ref().map(|(_, source)| source.clone()),
+            top_k: self.top_k,
+            metric: self.metric,
+            exact: self.exact,
+        }
+    }
+
+    /// Count one invocation
+    pub fn record(&self) {
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// JSON summary for the template API
+    pub fn info(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "collection": self.collection,
+            "filter": self.filter.as_ref().map(|(_, source)| source),
+            "top_k": self.top_k,
+            "metric": self.metric.map(metric::name),
+            "exact": self.exact,
+            "invocations": self.invocations.load(Ordering::Relaxed),
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(filter: Option<&str>, top_k: usize) -> CreateTemplateRequest {
+        CreateTemplateRequest {
+            collection: "docs".into(),
+            filter: filter.map(String::from),
+            top_k,
+            metric: Some(DistanceMetric::Dot),
+            exact: false,
+        }
+    }
+
+    #[test]
+    fn test_compiles_once() {
+        let template =
+            QueryTemplate::from_request("news", &request(Some(r#"category == "news""#), 5))
+                .unwrap();
+        assert!(matches!(template.filter(), Some(Filter::Compare { .. })));
+
+        let req = template.request(vec![1.0, 0.0]);
+        assert_eq!(req.top_k, 5);
+        assert_eq!(req.metric, Some(DistanceMetric::Dot));
+
+        template.record();
+        let info = template.info();
+        assert_eq!(info["filter"], r#"category == "news""#);
+        assert_eq!(info["metric"], "dot");
+        assert_eq!(info["invocations"], 1);
+
+        let again = QueryTemplate::from_request("news", &template.config()).unwrap();
+        assert_eq!(again.info()["filter"], info["filter"]);
+        assert_eq!(again.info()["invocations"], 0);
+    }
+
+    #[test]
+    fn test_rejects_bad_templates() {
+        assert!(QueryTemplate::from_request("news", &request(Some("category =="), 5)).is_err());
+        assert!(QueryTemplate::from_request("news", &request(None, 0)).is_err());
+        assert!(QueryTemplate::from_request("bad name!", &request(None, 5)).is_err());
+    }
+}
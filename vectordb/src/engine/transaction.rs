@@ -0,0 +1,198 @@
+// src/engine/transaction.rs
+//
+// Multi-operation transactions on one collection.
+//
+// A document split into ten chunk vectors should appear (or disappear) as
+// a unit — a search must never see chunks 1-6 of the new version next to
+// chunks 7-10 of the old one. A transaction is a list of upserts, deletes,
+// and metadata updates that either all apply or none do:
+//
+//   [{ "op": "delete", "id": "doc1#3" },
+//    { "op": "upsert", "id": "doc1#0", "vector": [...] },
+//    { "op": "update_metadata", "id": "doc1#1", "set": { "rev": "7" } }]
+//
+// Commit happens in two phases. `plan` replays the operations against a
+// staged overlay of the collection, so later operations see the effects
+// of earlier ones (upsert then update_metadata on the same ID works), and
+// validates everything. Only if every operation succeeds does `apply`
+// write the final state of each touched ID, under the same write lock.
+
+use crate::engine::collection::Collection;
+use crate::models::{Result, TransactionOp, Vector, VectorDbError};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Largest number of operations in one transaction
+pub const MAX_TRANSACTION_OPS: usize = 10_000;
+
+/// Why one operation failed
+#[derive(Debug, Clone, Serialize)]
+pub struct OpError {
+    /// Position in the request's operation list
+    pub index: usize,
+    pub op: &'static str,
+    pub id: String,
+    pub message: String,
+}
+
+/// A validated transaction, ready to apply
+#[derive(Debug, Default)]
+pub struct TransactionPlan {
+    /// Final state per touched ID, in first-touched order (None = deleted)
+    writes: Vec<(String, Option<Vector>)>,
+    pub upserted: usize,
+    pub deleted: usize,
+    pub updated: usize,
+}
+
+/// Validate `ops` against `collection` without changing it.
+///
+/// Every operation is checked, so the error list names all the problems
+/// rather than just the first.
+pub fn plan(
+    collection: &Collection,
+    ops: Vec<TransactionOp>,
+) -> std::result::Result<TransactionPlan, Vec<OpError>> {
+    let mut plan = TransactionPlan::default();
+    let mut staged: HashMap<String, usize> = HashMap::new(); // id → index in writes
+    let mut errors = Vec::new();
+
+    for (index, op) in ops.into_iter().enumerate() {
+        let (name, id) = (op.name(), op.id().to_string());
+        let current = match staged.get(&id) {
+            Some(&i) => plan.writes[i].1.as_ref().map(Cow::Borrowed),
+            None => collection.get(&id),
+        };
+
+        let next: Result<Option<Vector>> = match op {
+            TransactionOp::Upsert {
+                vector, metadata, ..
+            } => collection
+                .prepare(&id, Vector::with_metadata(vector, metadata))
+                .map(Some),
+            TransactionOp::Delete { .. } => match current {
+                Some(_) => Ok(None),
+                None => Err(VectorDbError::NotFound(format!("point '{}'", id))),
+            },
+            TransactionOp::UpdateMetadata { set, remove, .. } => match current {
+                Some(vector) => {
+                    let mut vector = vector.into_owned();
+                    for key in &remove {
+                        vector.metadata.remove(key);
+                    }
+                    vector.metadata.extend(set);
+                    collection.validate(&id, &vector).map(|_| Some(vector))
+                }
+                None => Err(VectorDbError::NotFound(format!("point '{}'", id))),
+            },
+        };
+
+        match next {
+            Ok(state) => {
+                match &state {
+                    None => plan.deleted += 1,
+                    Some(_) if name == "upsert" => plan.upserted += 1,
+                    Some(_) => plan.updated += 1,
+                }
+                match staged.get(&id) {
+                    Some(&i) => plan.writes[i].1 = state,
+                    None => {
+                        staged.insert(id.clone(), plan.writes.len());
+                        plan.writes.push((id, state));
+                    }
+                }
+            }
+            Err(e) => errors.push(OpError {
+                index,
+                op: name,
+                id,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(plan)
+    } else {
+        Err(errors)
+    }
+}
+
+impl TransactionPlan {
+    /// Write every staged state. Can't fail: all checks happened in `plan`.
+    pub fn apply(self, collection: &mut Collection) {
+        for (id, state) in self.writes {
+            match state {
+                Some(vector) => {
+                    collection.insert_prepared(id, vector);
+                }
+                None => {
+                    collection.delete(&id);
+                }
+            }
+        }
+    }
+
+    /// Final state per touched ID (None = deleted), for the write-ahead log
+    pub fn writes(&self) -> &[(String, Option<Vector>)] {
+        &self.writes
+    }
+
+    /// Distinct IDs the transaction touches
+    pub fn touched(&self) -> usize {
+        self.writes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DistanceMetric;
+
+    fn ops(json: serde_json::Value) -> Vec<TransactionOp> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_later_ops_see_earlier_ones() {
+        let mut c = Collection::new("docs", 1, DistanceMetric::Cosine).unwrap();
+        c.insert("old".into(), Vector::new(vec![1.0])).unwrap();
+
+        let plan = plan(
+            &c,
+            ops(serde_json::json!([
+                { "op": "upsert", "id": "new", "vector": [2.0] },
+                { "op": "update_metadata", "id": "new", "set": { "rev": "2" } },
+                { "op": "delete", "id": "old" },
+            ])),
+        )
+        .unwrap();
+        assert_eq!((plan.upserted, plan.updated, plan.deleted), (1, 1, 1));
+        assert_eq!(plan.touched(), 2);
+
+        plan.apply(&mut c);
+        assert!(c.get("old").is_none());
+        assert_eq!(c.get("new").unwrap().metadata["rev"], "2");
+        assert_eq!(c.version("new"), 1); // one write, not two
+    }
+
+    #[test]
+    fn test_any_failure_aborts_everything() {
+        let c = Collection::new("docs", 1, DistanceMetric::Cosine).unwrap();
+        let errors = plan(
+            &c,
+            ops(serde_json::json!([
+                { "op": "upsert", "id": "a", "vector": [1.0] },
+                { "op": "delete", "id": "a" },
+                { "op": "delete", "id": "a" },
+                { "op": "upsert", "id": "b", "vector": [1.0, 2.0] },
+            ])),
+        )
+        .unwrap_err();
+
+        let failed: Vec<usize> = errors.iter().map(|e| e.index).collect();
+        assert_eq!(failed, vec![2, 3]);
+        assert_eq!(errors[0].op, "delete");
+    }
+}
@@ -0,0 +1,211 @@
+// src/engine/trash.rs
+//
+// Soft-deleted collections, kept for a retention window before they're
+// purged for good.
+//
+// `DELETE /api/collections/prod` is one typo away from
+// `DELETE /api/collections/prod-staging`, so deleting a collection only
+// moves it here:
+//
+//   DELETE /api/collections/docs      → docs moves to the trash
+//   POST   /api/trash/docs/restore    → docs is back, points and all
+//   DELETE /api/trash/docs            → gone now, no waiting
+//   (retention elapses)               → gone at the next purge pass
+//
+// The trash holds at most one copy per name: deleting a second "docs"
+// replaces the first. Expiry is a pure function of the deletion time, so
+// purges driven by the clock aren't logged; a trashed collection replayed
+// from the log after its window is simply purged again on startup.
+
+use crate::engine::collection::Collection;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Default time a deleted collection stays restorable
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How often the server looks for expired entries
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// POLICY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// How long deleted collections are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrashPolicy {
+    /// Time between deletion and purge
+    pub retention: Duration,
+}
+
+impl Default for TrashPolicy {
+    fn default() -> Self {
+        Self {
+            retention: DEFAULT_RETENTION,
+        }
+    }
+}
+
+impl TrashPolicy {
+    /// A retention of whole hours (0 purges at the next pass; the
+    /// `trash_retention_hours` setting, see config.rs)
+    pub fn hours(hours: u64) -> Self {
+        Self {
+            retention: Duration::from_secs(hours.saturating_mul(60 * 60)),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TRASH
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A deleted collection waiting out its retention window
+#[derive(Debug, Clone)]
+pub struct Trashed {
+    pub collection: Collection,
+    /// Unix timestamp (seconds) of the delete
+    pub deleted_at: u64,
+}
+
+/// Deleted collections by name.
+#[derive(Debug, Default)]
+pub struct Trash {
+    pub policy: TrashPolicy,
+    entries: HashMap<String, Trashed>,
+}
+
+impl Trash {
+    /// Create an empty trash with the given policy
+    pub fn new(policy: TrashPolicy) -> Self {
+        Self {
+            policy,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Move `collection` into the trash, returning the older copy it
+    /// replaces (if any)
+    pub fn put(&mut self, collection: Collection, deleted_at: u64) -> Option<Trashed> {
+        self.entries.insert(
+            collection.name.clone(),
+            Trashed {
+                collection,
+                deleted_at,
+            },
+        )
+    }
+
+    /// Remove `name` from the trash, to restore or purge it
+    pub fn take(&mut self, name: &str) -> Option<Trashed> {
+        self.entries.remove(name)
+    }
+
+    /// Look up a trashed collection
+    pub fn get(&self, name: &str) -> Option<&Trashed> {
+        self.entries.get(name)
+    }
+
+    /// Unix timestamp after which `entry` is purged
+    pub fn purge_at(&self, entry: &Trashed) -> u64 {
+        entry
+            .deleted_at
+            .saturating_add(self.policy.retention.as_secs())
+    }
+
+    /// Purge every entry whose retention ended by `now_secs`, returning
+    /// the names purged
+    pub fn purge_expired(&mut self, now_secs: u64) -> Vec<String> {
+        let expired: Vec<String> = self
+            .entries
+            .values()
+            .filter(|e| self.purge_at(e) <= now_secs)
+            .map(|e| e.collection.name.clone())
+            .collect();
+        for name in &expired {
+            self.entries.remove(name);
+        }
+        expired
+    }
+
+    /// Every trashed collection
+    pub fn iter(&self) -> impl Iterator<Item = &Trashed> {
+        self.entries.values()
+    }
+
+    /// Summary returned by the trash API, oldest delete first
+    pub fn list(&self) -> Vec<serde_json::Value> {
+        let mut entries: Vec<&Trashed> = self.entries.values().collect();
+        entries.sort_by(|a, b| {
+            (a.deleted_at, &a.collection.name).cmp(&(b.deleted_at, &b.collection.name))
+        });
+        entries
+            .into_iter()
+            .map(|e| {
+                serde_json::json!({
+                    "name": e.collection.name,
+                    "count": e.collection.len(),
+                    "deleted_at": e.deleted_at,
+                    "purge_at": self.purge_at(e),
+                })
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DistanceMetric;
+
+    fn collection(name: &str) -> Collection {
+        Collection::new(name, 2, DistanceMetric::Cosine).unwrap()
+    }
+
+    #[test]
+    fn test_purge_after_retention() {
+        let mut trash = Trash::new(TrashPolicy::hours(1));
+        trash.put(collection("a"), 1_000);
+        trash.put(collection("b"), 2_000);
+
+        assert!(trash.purge_expired(1_000 + 3_599).is_empty());
+        assert_eq!(trash.purge_expired(1_000 + 3_600), vec!["a".to_string()]);
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash.list()[0]["purge_at"], 2_000 + 3_600);
+    }
+
+    #[test]
+    fn test_one_copy_per_name() {
+        let mut trash = Trash::default();
+        assert!(trash.put(collection("docs"), 10).is_none());
+        let older = trash.put(collection("docs"), 20).unwrap();
+        assert_eq!(older.deleted_at, 10);
+        assert_eq!(trash.take("docs").unwrap().deleted_at, 20);
+        assert!(trash.is_empty());
+    }
+
+    #[test]
+    fn test_policy_hours() {
+        assert_eq!(TrashPolicy::hours(0).retention, Duration::ZERO);
+        assert_eq!(
+            TrashPolicy::hours(48).retention,
+            Duration::from_secs(48 * 3600)
+        );
+        assert_eq!(
+            TrashPolicy::hours(u64::MAX).retention,
+            Duration::from_secs(u64::MAX)
+        );
+    }
+}
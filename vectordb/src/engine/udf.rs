@@ -0,0 +1,475 @@
+// src/engine/udf.rs
+//
+// User-defined filter and score functions, uploaded as WebAssembly.
+//
+// The filter language covers comparisons on metadata; anything past that
+// (parsing a field, a business rule, a custom boost) can be shipped as a
+// small WASM module and named in a search:
+//
+//   PUT  /api/admin/udfs/fresh            body: the .wasm (or .wat) module
+//   POST /api/collections/docs/search     { "vector": [...], "filter_udf": "fresh" }
+//
+// A module runs in a wasmtime sandbox with no imports at all: it can't
+// touch files, the network or the clock, only its own memory. It must
+// export:
+//
+//   memory                                  its linear memory
+//   alloc(len: i32) -> i32                  a buffer of `len` bytes the host
+//                                           may keep writing into
+//   filter(ptr: i32, len: i32) -> i32       nonzero keeps the candidate, and/or
+//   score(score: f32, ptr: i32, len: i32) -> f32
+//                                           the candidate's new score
+//
+// Each candidate is passed as JSON, `{"id":"...","metadata":{...}}`, at
+// ptr..ptr+len. The host calls `alloc` once per search and again only if
+// a candidate needs a bigger buffer, so a bump allocator is enough.
+//
+// Every call gets a fresh fuel budget (roughly one unit per instruction,
+// udf_fuel in config.rs) and the module's memory is capped; a call that runs
+// out, traps, or returns nonsense fails the whole search rather than
+// silently dropping candidates. Modules are compiled on upload and kept
+// as <data_dir>/udfs/<name>.wasm, so they survive restarts.
+//
+// Only built with `--features wasm`.
+
+use crate::engine::collection;
+use crate::models::{Result, Vector, VectorDbError};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use wasmtime::{
+    Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, TypedFunc,
+};
+
+/// Directory under the data directory that holds uploaded modules
+pub const UDF_DIR: &str = "udfs";
+
+/// Default fuel for one call (about that many WASM instructions)
+pub const DEFAULT_FUEL: u64 = 100_000;
+
+/// Most linear memory a module may grow to
+pub const MAX_MEMORY: usize = 16 << 20;
+
+/// Largest module accepted for upload
+pub const MAX_MODULE_SIZE: usize = 4 << 20;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// REGISTRY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// An uploaded, compiled module.
+struct Udf {
+    module: Module,
+    size: usize,
+    filter: bool,
+    score: bool,
+}
+
+/// Uploaded UDFs by name.
+pub struct UdfRegistry {
+    engine: Engine,
+    udfs: BTreeMap<String, Udf>,
+    /// Where modules are kept (None: in memory only)
+    dir: Option<PathBuf>,
+    /// Fuel for each call
+    pub fuel: u64,
+}
+
+impl Default for UdfRegistry {
+    fn default() -> Self {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        Self {
+            engine: Engine::new(&config).expect("fuel-metered engine config is valid"),
+            udfs: BTreeMap::new(),
+            dir: None,
+            fuel: DEFAULT_FUEL,
+        }
+    }
+}
+
+impl std::fmt::Debug for UdfRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UdfRegistry")
+            .field("udfs", &self.udfs.keys().collect::<Vec<_>>())
+            .field("fuel", &self.fuel)
+            .finish()
+    }
+}
+
+impl UdfRegistry {
+    /// Load every module in `dir` (created if missing), giving each call
+    /// `fuel`
+    pub fn open(dir: &Path, fuel: u64) -> Result<Self> {
+        let mut registry = Self {
+            dir: Some(dir.to_path_buf()),
+            fuel,
+            ..Self::default()
+        };
+
+        std::fs::create_dir_all(dir)?;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) if path.extension().is_some_and(|e| e == "wasm") => name.to_string(),
+                _ => continue,
+            };
+            let udf = registry.compile(&name, &std::fs::read(&path)?)?;
+            registry.udfs.insert(name, udf);
+        }
+        Ok(registry)
+    }
+
+    /// Compile and check a module
+    fn compile(&self, name: &str, bytes: &[u8]) -> Result<Udf> {
+        let invalid = |e: String| VectorDbError::InvalidParameter(format!("UDF '{}': {}", name, e));
+        if bytes.len() > MAX_MODULE_SIZE {
+            return Err(invalid(format!(
+                "module is {} bytes, the limit is {}",
+                bytes.len(),
+                MAX_MODULE_SIZE
+            )));
+        }
+        let module = Module::new(&self.engine, bytes).map_err(|e| invalid(format!("{:#}", e)))?;
+        if let Some(import) = module.imports().next() {
+            return Err(invalid(format!(
+                "modules can't import anything (imports {}.{})",
+                import.module(),
+                import.name()
+            )));
+        }
+
+        // Instantiate once to check the exports have the right types
+        let mut sandbox = Sandbox::new(&self.engine, &module, name, self.fuel)?;
+        let filter = module.get_export("filter").is_some();
+        let score = module.get_export("score").is_some();
+        if filter {
+            sandbox.filter_fn()?;
+        }
+        if score {
+            sandbox.score_fn()?;
+        }
+        if !filter && !score {
+            return Err(invalid("exports neither `filter` nor `score`".into()));
+        }
+        Ok(Udf {
+            module,
+            size: bytes.len(),
+            filter,
+            score,
+        })
+    }
+
+    /// Compile and store a module, replacing any with the same name
+    pub fn put(&mut self, name: &str, bytes: &[u8]) -> Result<()> {
+        collection::validate_name(name)?;
+        let udf = self.compile(name, bytes)?;
+        if let Some(dir) = &self.dir {
+            let tmp = dir.join(format!("{}.wasm.tmp", name));
+            std::fs::write(&tmp, bytes)?;
+            std::fs::rename(&tmp, dir.join(format!("{}.wasm", name)))?;
+        }
+        self.udfs.insert(name.to_string(), udf);
+        Ok(())
+    }
+
+    /// Remove a module; false if there was none
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        if self.udfs.remove(name).is_none() {
+            return Ok(false);
+        }
+        if let Some(dir) = &self.dir {
+            std::fs::remove_file(dir.join(format!("{}.wasm", name)))?;
+        }
+        Ok(true)
+    }
+
+    /// Summary returned by the UDF API
+    pub fn list(&self) -> Vec<serde_json::Value> {
+        self.udfs
+            .iter()
+            .map(|(name, udf)| {
+                serde_json::json!({
+                    "name": name,
+                    "size": udf.size,
+                    "filter": udf.filter,
+                    "score": udf.score,
+                })
+            })
+            .collect()
+    }
+
+    fn sandbox(&self, name: &str) -> Result<(Sandbox, &Udf)> {
+        let udf = self
+            .udfs
+            .get(name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("UDF '{}'", name)))?;
+        Ok((
+            Sandbox::new(&self.engine, &udf.module, name, self.fuel)?,
+            udf,
+        ))
+    }
+
+    /// A fresh instance of `name` for running its `filter` over one search
+    pub fn filter(&self, name: &str) -> Result<FilterUdf> {
+        let (mut sandbox, udf) = self.sandbox(name)?;
+        if !udf.filter {
+            return Err(sandbox.error("has no `filter` export".into()));
+        }
+        let func = sandbox.filter_fn()?;
+        Ok(FilterUdf { sandbox, func })
+    }
+
+    /// A fresh instance of `name` for running its `score` over one search
+    pub fn scorer(&self, name: &str) -> Result<ScoreUdf> {
+        let (mut sandbox, udf) = self.sandbox(name)?;
+        if !udf.score {
+            return Err(sandbox.error("has no `score` export".into()));
+        }
+        let func = sandbox.score_fn()?;
+        Ok(ScoreUdf { sandbox, func })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SANDBOX
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a module sees of each candidate
+#[derive(Serialize)]
+struct Candidate<'a> {
+    id: &'a str,
+    metadata: &'a HashMap<String, String>,
+}
+
+/// One instance of a module, with the host's input buffer in its memory.
+struct Sandbox {
+    name: String,
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    fuel: u64,
+    /// Input buffer: guest pointer and capacity (0: not allocated yet)
+    buffer: (i32, usize),
+}
+
+impl Sandbox {
+    fn new(engine: &Engine, module: &Module, name: &str, fuel: u64) -> Result<Self> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(fuel).map_err(internal)?;
+        let error = |e: String| VectorDbError::InvalidParameter(format!("UDF '{}' {}", name, e));
+
+        let instance = Instance::new(&mut store, module, &[])
+            .map_err(|e| error(format!("failed to start: {:#}", e)))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| error("doesn't export `memory`".into()))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(|e| error(format!("needs `alloc(i32) -> i32`: {:#}", e)))?;
+        Ok(Self {
+            name: name.to_string(),
+            store,
+            instance,
+            memory,
+            alloc,
+            fuel,
+            buffer: (0, 0),
+        })
+    }
+
+    fn error(&self, message: String) -> VectorDbError {
+        VectorDbError::InvalidParameter(format!("UDF '{}' {}", self.name, message))
+    }
+
+    fn filter_fn(&mut self) -> Result<TypedFunc<(i32, i32), i32>> {
+        self.instance
+            .get_typed_func(&mut self.store, "filter")
+            .map_err(|e| self.error(format!("needs `filter(i32, i32) -> i32`: {:#}", e)))
+    }
+
+    fn score_fn(&mut self) -> Result<TypedFunc<(f32, i32, i32), f32>> {
+        self.instance
+            .get_typed_func(&mut self.store, "score")
+            .map_err(|e| self.error(format!("needs `score(f32, i32, i32) -> f32`: {:#}", e)))
+    }
+
+    /// Refill the fuel, write the candidate into the input buffer, and
+    /// return (ptr, len) for the call
+    fn prepare(&mut self, id: &str, vector: &Vector) -> Result<(i32, i32)> {
+        self.store.set_fuel(self.fuel).map_err(internal)?;
+        let input = serde_json::to_vec(&Candidate {
+            id,
+            metadata: &vector.metadata,
+        })?;
+        if input.len() > self.buffer.1 {
+            let capacity = input.len().next_power_of_two().max(1024);
+            let ptr = self
+                .alloc
+                .call(&mut self.store, capacity as i32)
+                .map_err(|e| self.trap(id, e))?;
+            self.buffer = (ptr, capacity);
+        }
+        let (ptr, _) = self.buffer;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, &input)
+            .map_err(|_| self.error(format!("returned a buffer outside its memory ({})", ptr)))?;
+        Ok((ptr, input.len() as i32))
+    }
+
+    fn trap(&self, id: &str, e: wasmtime::Error) -> VectorDbError {
+        match e.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => self.error(format!(
+                "ran out of fuel on '{}' (limit {}, udf_fuel)",
+                id, self.fuel
+            )),
+            _ => self.error(format!("failed on '{}': {:#}", id, e)),
+        }
+    }
+}
+
+fn internal(e: wasmtime::Error) -> VectorDbError {
+    VectorDbError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        format!("{:#}", e),
+    ))
+}
+
+/// A filter UDF instantiated for one search.
+pub struct FilterUdf {
+    sandbox: Sandbox,
+    func: TypedFunc<(i32, i32), i32>,
+}
+
+impl FilterUdf {
+    /// Should `id` stay in the results?
+    pub fn keep(&mut self, id: &str, vector: &Vector) -> Result<bool> {
+        let (ptr, len) = self.sandbox.prepare(id, vector)?;
+        let keep = self
+            .func
+            .call(&mut self.sandbox.store, (ptr, len))
+            .map_err(|e| self.sandbox.trap(id, e))?;
+        Ok(keep != 0)
+    }
+}
+
+/// A score UDF instantiated for one search.
+pub struct ScoreUdf {
+    sandbox: Sandbox,
+    func: TypedFunc<(f32, i32, i32), f32>,
+}
+
+impl ScoreUdf {
+    /// `id`'s adjusted score
+    pub fn score(&mut self, score: f32, id: &str, vector: &Vector) -> Result<f32> {
+        let (ptr, len) = self.sandbox.prepare(id, vector)?;
+        let adjusted = self
+            .func
+            .call(&mut self.sandbox.store, (score, ptr, len))
+            .map_err(|e| self.sandbox.trap(id, e))?;
+        if !adjusted.is_finite() {
+            return Err(self
+                .sandbox
+                .error(format!("returned {} for '{}'", adjusted, id)));
+        }
+        Ok(adjusted)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::collection::Collection;
+    use crate::models::DistanceMetric;
+
+    /// Keeps IDs starting with "a" (the byte after `{"id":"`), scores
+    /// everything as -score, and loops forever on request
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "filter") (param $ptr i32) (param $len i32) (result i32)
+            (i32.eq (i32.load8_u offset=7 (local.get $ptr)) (i32.const 97)))
+          (func (export "score") (param $s f32) (param $ptr i32) (param $len i32) (result f32)
+            (f32.neg (local.get $s))))
+    "#;
+
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "filter") (param i32 i32) (result i32)
+            (loop $forever (br $forever))
+            (i32.const 1)))
+    "#;
+
+    fn collection() -> Collection {
+        let mut c = Collection::new("docs", 2, DistanceMetric::Cosine).unwrap();
+        for (id, data) in [("a1", [1.0, 0.0]), ("b1", [0.9, 0.1]), ("a2", [0.0, 1.0])] {
+            c.insert(id.into(), Vector::new(data.to_vec())).unwrap();
+        }
+        c
+    }
+
+    #[test]
+    fn test_filter_and_score() {
+        let mut udfs = UdfRegistry::default();
+        udfs.put("by_a", MODULE.as_bytes()).unwrap();
+        assert_eq!(udfs.list()[0]["score"], true);
+        let c = collection();
+
+        let mut filter = udfs.filter("by_a").unwrap();
+        let hits = c
+            .search_adjusted(&[1.0, 0.0], 3, DistanceMetric::Cosine, &mut |id, v, s| {
+                Ok(filter.keep(id, v)?.then_some(s))
+            })
+            .unwrap();
+        let ids: Vec<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, ["a1", "a2"]);
+
+        // Negated cosine turns the ranking upside down
+        let mut scorer = udfs.scorer("by_a").unwrap();
+        let hits = c
+            .search_adjusted(&[1.0, 0.0], 1, DistanceMetric::Cosine, &mut |id, v, s| {
+                scorer.score(s, id, v).map(Some)
+            })
+            .unwrap();
+        assert_eq!(hits[0].id, "a2");
+    }
+
+    #[test]
+    fn test_fuel_limit_fails_the_search() {
+        let mut udfs = UdfRegistry::default();
+        udfs.put("spin", SPIN.as_bytes()).unwrap();
+        let mut filter = udfs.filter("spin").unwrap();
+        let err = filter.keep("a1", &Vector::new(vec![1.0, 0.0])).unwrap_err();
+        assert!(err.to_string().contains("ran out of fuel"), "{}", err);
+        assert!(udfs.scorer("spin").is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_modules() {
+        let mut udfs = UdfRegistry::default();
+        let imports = r#"(module (import "env" "clock" (func)) (memory (export "memory") 1))"#;
+        let err = udfs.put("clock", imports.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("can't import"), "{}", err);
+
+        let no_entry = r#"(module (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#;
+        assert!(udfs.put("idle", no_entry.as_bytes()).is_err());
+        assert!(udfs.put("junk", b"\0asm garbage").is_err());
+        assert!(udfs.list().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+// src/faults.rs
+//
+// Fault injection for resilience tests.
+//
+// Storage code calls `faults::io(point)` right before each I/O operation it
+// wants to be testable:
+//
+//   if faults::io(FaultPoint::WalAppend)? {
+//       file.write_all(&record)?;
+//   }
+//
+// In normal builds that's an inlined `Ok(true)` and costs nothing. Built
+// with `--features fault-injection`, the server exposes /admin/faults and
+// each point can be told to delay, fail, or silently drop the operation —
+// after skipping the first N hits, for a limited number of hits — so a test
+// can say "fail the third fsync" and get the same result every run.
+
+use serde::{Deserialize, Serialize};
+
+/// Places in the storage engine where a fault can be injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultPoint {
+    /// Any data file write
+    Write,
+
+    /// fsync / fdatasync after a write
+    Fsync,
+
+    /// Appending a record to the write-ahead log
+    WalAppend,
+}
+
+/// What happens when a fault fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum FaultAction {
+    /// Sleep, then perform the operation normally
+    Delay { ms: u64 },
+
+    /// Return an I/O error instead of performing the operation
+    Fail,
+
+    /// Skip the operation but report success (a lost write)
+    Drop,
+}
+
+/// An armed fault (also the request body for POST /admin/faults)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fault {
+    pub point: FaultPoint,
+    pub action: FaultAction,
+
+    /// Let this many hits through before firing
+    #[serde(default)]
+    pub skip: u32,
+
+    /// Fire this many times, then disarm (forever if absent)
+    #[serde(default)]
+    pub times: Option<u32>,
+
+    /// How many times the fault has fired so far
+    #[serde(default)]
+    pub fired: u32,
+}
+
+/// Check `point` before performing an I/O operation.
+///
+/// Returns `Ok(true)` to go ahead, `Ok(false)` to skip the operation as if
+/// it succeeded, or the injected error.
+#[cfg(not(feature = "fault-injection"))]
+#[inline(always)]
+pub fn io(_point: FaultPoint) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(feature = "fault-injection")]
+pub use registry::{arm, clear, io, list};
+
+#[cfg(feature = "fault-injection")]
+mod registry {
+    use super::{Fault, FaultAction, FaultPoint};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// Armed faults plus the number of hits seen per point. Global because
+    /// the I/O call sites are deep in the storage code, far from AppState.
+    static FAULTS: Mutex<BTreeMap<FaultPoint, (Fault, u32)>> = Mutex::new(BTreeMap::new());
+
+    /// Arm (or re-arm) a fault, resetting its counters
+    pub fn arm(mut fault: Fault) {
+        fault.fired = 0;
+        FAULTS.lock().unwrap().insert(fault.point, (fault, 0));
+    }
+
+    /// Disarm one point, or every point if `None`
+    pub fn clear(point: Option<FaultPoint>) {
+        let mut faults = FAULTS.lock().unwrap();
+        match point {
+            Some(point) => {
+                faults.remove(&point);
+            }
+            None => faults.clear(),
+        }
+    }
+
+    /// Currently armed faults
+    pub fn list() -> Vec<Fault> {
+        FAULTS
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(f, _)| f.clone())
+            .collect()
+    }
+
+    /// See the non-feature `io` for the contract
+    pub fn io(point: FaultPoint) -> std::io::Result<bool> {
+        let action = {
+            let mut faults = FAULTS.lock().unwrap();
+            let Some((fault, hits)) = faults.get_mut(&point) else {
+                return Ok(true);
+            };
+            *hits += 1;
+            let exhausted = fault.times.is_some_and(|t| fault.fired >= t);
+            if *hits <= fault.skip || exhausted {
+                return Ok(true);
+            }
+            fault.fired += 1;
+            fault.action
+        }; // Don't hold the lock while sleeping
+
+        tracing::warn!("Injected fault at {:?}: {:?}", point, action);
+        match action {
+            FaultAction::Delay { ms } => {
+                std::thread::sleep(std::time::Duration::from_millis(ms));
+                Ok(true)
+            }
+            FaultAction::Fail => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("injected {:?} failure", point),
+            )),
+            FaultAction::Drop => Ok(false),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(all(test, feature = "fault-injection"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_and_times_are_deterministic() {
+        arm(Fault {
+            point: FaultPoint::Fsync,
+            action: FaultAction::Fail,
+            skip: 2,
+            times: Some(1),
+            fired: 0,
+        });
+
+        let outcomes: Vec<bool> = (0..5).map(|_| io(FaultPoint::Fsync).is_err()).collect();
+        assert_eq!(outcomes, vec![false, false, true, false, false]);
+        assert_eq!(list()[0].fired, 1);
+
+        arm(Fault {
+            point: FaultPoint::WalAppend,
+            action: FaultAction::Drop,
+            skip: 0,
+            times: None,
+            fired: 0,
+        });
+        assert!(!io(FaultPoint::WalAppend).unwrap());
+        clear(None);
+        assert!(io(FaultPoint::WalAppend).unwrap());
+    }
+}
@@ -0,0 +1,367 @@
+// src/fds.rs
+//
+// File descriptor budget.
+//
+// Every open segment, client socket, and storage file (WAL, snapshots,
+// uploads, usage rollups) costs a descriptor, and when the process runs
+// out the failure lands wherever the next open() happens to be: often an
+// accept loop spinning on EMFILE, or a WAL append failing a write. The
+// budget hands out descriptors up front so that doesn't happen:
+//
+//   limit    RLIMIT_NOFILE, raised from the soft to the hard limit at
+//            startup (the max_fds setting can lower it)
+//   budget   limit - RESERVED, for stdio, DNS lookups, and other
+//            descriptors nobody counts
+//
+//   storage  never refused; STORAGE_RESERVE of the budget is kept for it,
+//            so the WAL can always be written
+//   segments at most half of what's left; the segment catalog closes its
+//            least recently used handle when refused
+//   sockets  whatever segments don't use; connections past that are
+//            closed on accept instead of starving storage
+//
+// Permits are RAII: the count goes down when the permit is dropped.
+// `usage()` reports the counts next to the descriptors the process really
+// has open (/proc/self/fd on Linux), so leaks outside the budget show up
+// as a gap between the two.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Descriptors kept out of the budget for stdio, logging, DNS, and such
+pub const RESERVED: usize = 16;
+
+/// Budget kept for storage files, which are never refused
+pub const STORAGE_RESERVE: usize = 64;
+
+/// Limit assumed where RLIMIT_NOFILE can't be read
+const FALLBACK_LIMIT: usize = 1024;
+
+/// Highest soft limit we raise to, even if the hard limit is unlimited
+const MAX_RAISED_LIMIT: u64 = 1 << 20;
+
+/// What a descriptor is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FdKind {
+    Segment,
+    Socket,
+    Storage,
+}
+
+/// Counts for one process-wide budget
+#[derive(Debug)]
+pub struct FdBudget {
+    budget: AtomicUsize,
+    segments: AtomicUsize,
+    sockets: AtomicUsize,
+    storage: AtomicUsize,
+    refused_segments: AtomicU64,
+    refused_sockets: AtomicU64,
+}
+
+/// The budget shared by the whole process
+pub static BUDGET: FdBudget = FdBudget::new(FALLBACK_LIMIT - RESERVED);
+
+/// RLIMIT_NOFILE as found and as set at startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FdLimits {
+    /// Limit the budget is sized from: the soft limit after raising it,
+    /// or the max_fds setting if that's lower
+    pub soft: u64,
+    /// Hard limit (None if unlimited or unknown)
+    pub hard: Option<u64>,
+    /// Soft limit the process started with
+    pub original_soft: u64,
+}
+
+/// Snapshot of descriptor use, for /api/admin/fds and metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct FdUsage {
+    pub limits: FdLimits,
+    pub budget: usize,
+    pub in_use: usize,
+    pub segments: usize,
+    pub segment_cap: usize,
+    pub sockets: usize,
+    pub socket_cap: usize,
+    pub storage: usize,
+    /// Segment opens refused (each one closed an older handle or failed)
+    pub refused_segments: u64,
+    /// Connections closed on accept for lack of descriptors
+    pub refused_sockets: u64,
+    /// Descriptors the process actually has open, where the OS says
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process_open: Option<usize>,
+}
+
+/// A counted descriptor; the count drops with the permit
+#[derive(Debug)]
+#[must_use = "the descriptor is only counted while the permit is held"]
+pub struct FdPermit {
+    budget: &'static FdBudget,
+    kind: FdKind,
+}
+
+impl Drop for FdPermit {
+    fn drop(&mut self) {
+        self.budget
+            .counter(self.kind)
+            .fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl FdBudget {
+    pub const fn new(budget: usize) -> Self {
+        Self {
+            budget: AtomicUsize::new(budget),
+            segments: AtomicUsize::new(0),
+            sockets: AtomicUsize::new(0),
+            storage: AtomicUsize::new(0),
+            refused_segments: AtomicU64::new(0),
+            refused_sockets: AtomicU64::new(0),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    /// Most segments open at once
+    pub fn segment_cap(&self) -> usize {
+        (self.shared() / 2).max(1)
+    }
+
+    /// Most sockets open at once, given the segments open now
+    pub fn socket_cap(&self) -> usize {
+        self.shared()
+            .saturating_sub(self.segments.load(Ordering::Acquire))
+            .max(1)
+    }
+
+    /// Budget left for segments and sockets after the storage reserve
+    fn shared(&self) -> usize {
+        self.budget().saturating_sub(STORAGE_RESERVE)
+    }
+
+    fn counter(&self, kind: FdKind) -> &AtomicUsize {
+        match kind {
+            FdKind::Segment => &self.segments,
+            FdKind::Socket => &self.sockets,
+            FdKind::Storage => &self.storage,
+        }
+    }
+
+    /// Count a segment or socket descriptor, if its share allows one more
+    pub fn try_acquire(&'static self, kind: FdKind) -> Option<FdPermit> {
+        let (cap, refused) = match kind {
+            FdKind::Segment => (self.segment_cap(), &self.refused_segments),
+            FdKind::Socket => (self.socket_cap(), &self.refused_sockets),
+            FdKind::Storage => return Some(self.acquire_storage()),
+        };
+        let counted = self
+            .counter(kind)
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < cap).then_some(n + 1)
+            });
+        match counted {
+            Ok(_) => Some(FdPermit { budget: self, kind }),
+            Err(_) => {
+                refused.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Count a storage descriptor (never refused)
+    pub fn acquire_storage(&'static self) -> FdPermit {
+        self.storage.fetch_add(1, Ordering::AcqRel);
+        FdPermit {
+            budget: self,
+            kind: FdKind::Storage,
+        }
+    }
+
+    fn usage(&self, limits: FdLimits) -> FdUsage {
+        let segments = self.segments.load(Ordering::Acquire);
+        let sockets = self.sockets.load(Ordering::Acquire);
+        let storage = self.storage.load(Ordering::Acquire);
+        FdUsage {
+            limits,
+            budget: self.budget(),
+            in_use: segments + sockets + storage,
+            segments,
+            segment_cap: self.segment_cap(),
+            sockets,
+            socket_cap: self.socket_cap(),
+            storage,
+            refused_segments: self.refused_segments.load(Ordering::Relaxed),
+            refused_sockets: self.refused_sockets.load(Ordering::Relaxed),
+            process_open: process_open_fds(),
+        }
+    }
+}
+
+static LIMITS: std::sync::Mutex<Option<FdLimits>> = std::sync::Mutex::new(None);
+
+/// Raise RLIMIT_NOFILE to the hard limit, lower it to `max` if given (the
+/// max_fds setting, checked in config.rs), and size the process budget
+/// from the result
+pub fn init(max: Option<u64>) -> FdLimits {
+    let mut limits = raise_nofile_limit();
+    if let Some(max) = max {
+        limits.soft = limits.soft.min(max);
+    }
+    BUDGET.budget.store(
+        (limits.soft as usize).saturating_sub(RESERVED),
+        Ordering::Relaxed,
+    );
+    *LIMITS.lock().unwrap() = Some(limits);
+    limits
+}
+
+/// Count a segment or socket descriptor against the process budget
+pub fn try_acquire(kind: FdKind) -> Option<FdPermit> {
+    BUDGET.try_acquire(kind)
+}
+
+/// Count a storage descriptor against the process budget
+pub fn acquire_storage() -> FdPermit {
+    BUDGET.acquire_storage()
+}
+
+/// Current descriptor use
+pub fn usage() -> FdUsage {
+    let limits = LIMITS.lock().unwrap().unwrap_or(FdLimits {
+        soft: FALLBACK_LIMIT as u64,
+        hard: None,
+        original_soft: FALLBACK_LIMIT as u64,
+    });
+    BUDGET.usage(limits)
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // rlim_t isn't u64 on every platform
+fn raise_nofile_limit() -> FdLimits {
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the struct we pass
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim) } != 0 {
+        tracing::warn!(
+            "Can't read RLIMIT_NOFILE ({}); assuming {}",
+            std::io::Error::last_os_error(),
+            FALLBACK_LIMIT
+        );
+        return FdLimits {
+            soft: FALLBACK_LIMIT as u64,
+            hard: None,
+            original_soft: FALLBACK_LIMIT as u64,
+        };
+    }
+
+    let original_soft = rlim.rlim_cur as u64;
+    let hard = (rlim.rlim_max != libc::RLIM_INFINITY).then_some(rlim.rlim_max as u64);
+    let target = hard.unwrap_or(MAX_RAISED_LIMIT).min(MAX_RAISED_LIMIT);
+    let mut soft = original_soft;
+    if target > original_soft {
+        let raised = libc::rlimit {
+            rlim_cur: target as libc::rlim_t,
+            rlim_max: rlim.rlim_max,
+        };
+        // SAFETY: setrlimit only reads the struct we pass
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            soft = target;
+        } else {
+            tracing::warn!(
+                "Can't raise RLIMIT_NOFILE from {} to {}: {}",
+                original_soft,
+                target,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    FdLimits {
+        soft,
+        hard,
+        original_soft,
+    }
+}
+
+#[cfg(not(unix))]
+fn raise_nofile_limit() -> FdLimits {
+    FdLimits {
+        soft: FALLBACK_LIMIT as u64,
+        hard: None,
+        original_soft: FALLBACK_LIMIT as u64,
+    }
+}
+
+fn process_open_fds() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_dir("/proc/self/fd").ok().map(|d| d.count())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(n: usize) -> &'static FdBudget {
+        Box::leak(Box::new(FdBudget::new(n)))
+    }
+
+    #[test]
+    fn test_segments_and_sockets_share_what_storage_leaves() {
+        let b = budget(STORAGE_RESERVE + 8);
+        assert_eq!(b.segment_cap(), 4);
+
+        let segments: Vec<_> = (0..4).map(|_| b.try_acquire(FdKind::Segment)).collect();
+        assert!(segments.iter().all(Option::is_some));
+        assert!(b.try_acquire(FdKind::Segment).is_none());
+
+        // Sockets get the other half, and more once segments close
+        let mut sockets: Vec<_> = (0..4)
+            .map(|_| b.try_acquire(FdKind::Socket).unwrap())
+            .collect();
+        assert!(b.try_acquire(FdKind::Socket).is_none());
+        drop(segments);
+        sockets.extend((0..4).map(|_| b.try_acquire(FdKind::Socket).unwrap()));
+        assert!(b.try_acquire(FdKind::Socket).is_none());
+
+        // Storage is counted but never refused
+        let storage: Vec<_> = (0..100).map(|_| b.acquire_storage()).collect();
+        let usage = b.usage(FdLimits::default());
+        assert_eq!((usage.sockets, usage.storage, usage.in_use), (8, 100, 108));
+        assert_eq!((usage.refused_segments, usage.refused_sockets), (1, 2));
+
+        drop(sockets);
+        drop(storage);
+        assert_eq!(b.usage(FdLimits::default()).in_use, 0);
+    }
+
+    #[test]
+    fn test_tiny_budget_still_admits_one_of_each() {
+        let b = budget(10);
+        let segment = b.try_acquire(FdKind::Segment);
+        assert!(segment.is_some());
+        assert!(b.try_acquire(FdKind::Segment).is_none());
+        assert!(b.try_acquire(FdKind::Socket).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_nofile_limit_is_at_least_the_original() {
+        let limits = raise_nofile_limit();
+        assert!(limits.soft >= limits.original_soft);
+        if let Some(hard) = limits.hard {
+            assert!(limits.soft <= hard);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// src/grpc.rs
+//
+// Types for the gRPC API, for `--features grpc` builds.
+//
+// The service and its messages are generated from proto/vectordb.proto at
+// build time (see build.rs) into `proto`. The server side lives in main.rs
+// next to the HTTP handlers it delegates to, so both APIs share one
+// AppState; this module only holds the conversions between the generated
+// messages and the model types, plus the mapping from HTTP status codes to
+// gRPC ones:
+//
+//   400 → INVALID_ARGUMENT     404 → NOT_FOUND        409 → ALREADY_EXISTS
+//   401 → UNAUTHENTICATED      403 → PERMISSION_DENIED
+//   412, 423 → FAILED_PRECONDITION                    429 → RESOURCE_EXHAUSTED
+//   503 → UNAVAILABLE          anything else → INTERNAL
+//
+// The generated client is public too, for tests and Rust callers.
+
+use crate::models::{
+    DistanceMetric, OnConflict, PointStatus, Result, SearchResult, Vector, VectorDbError,
+};
+
+/// Code generated from proto/vectordb.proto
+pub mod proto {
+    tonic::include_proto!("vectordb.v1");
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONVERSIONS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The metric a search asked for (None = the collection's own)
+pub fn metric(value: i32) -> Result<Option<DistanceMetric>> {
+    match proto::DistanceMetric::try_from(value) {
+        Ok(proto::DistanceMetric::Unspecified) => Ok(None),
+        Ok(proto::DistanceMetric::Cosine) => Ok(Some(DistanceMetric::Cosine)),
+        Ok(proto::DistanceMetric::Euclidean) => Ok(Some(DistanceMetric::Euclidean)),
+        Ok(proto::DistanceMetric::Dot) => Ok(Some(DistanceMetric::Dot)),
+        Ok(proto::DistanceMetric::Hamming) => Ok(Some(DistanceMetric::Hamming)),
+        Err(_) => Err(VectorDbError::InvalidParameter(format!(
+            "unknown distance metric {}",
+            value
+        ))),
+    }
+}
+
+/// The conflict policy an insert asked for
+pub fn on_conflict(value: i32) -> Result<OnConflict> {
+    match proto::OnConflict::try_from(value) {
+        Ok(proto::OnConflict::Overwrite) => Ok(OnConflict::Overwrite),
+        Ok(proto::OnConflict::Error) => Ok(OnConflict::Error),
+        Ok(proto::OnConflict::Skip) => Ok(OnConflict::Skip),
+        Ok(proto::OnConflict::MergeMetadata) => Ok(OnConflict::MergeMetadata),
+        Err(_) => Err(VectorDbError::InvalidParameter(format!(
+            "unknown on_conflict {}",
+            value
+        ))),
+    }
+}
+
+impl From<PointStatus> for proto::PointStatus {
+    fn from(status: PointStatus) -> Self {
+        match status {
+            PointStatus::Inserted => Self::Inserted,
+            PointStatus::Overwritten => Self::Overwritten,
+            PointStatus::Merged => Self::Merged,
+            PointStatus::Skipped => Self::Skipped,
+            PointStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<SearchResult> for proto::SearchHit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            id: result.id,
+            score: result.score,
+            probability: result.probability,
+            vector: result.vector.unwrap_or_default(),
+            metadata: result.metadata.unwrap_or_default(),
+        }
+    }
+}
+
+impl proto::Point {
+    /// A stored point as a message
+    pub fn new(id: String, vector: Vector) -> Self {
+        Self {
+            id,
+            vector: vector.data,
+            metadata: vector.metadata,
+        }
+    }
+}
+
+/// The gRPC status for an HTTP error with `code`
+pub fn status(code: u16, message: String) -> tonic::Status {
+    let code = match code {
+        400 => tonic::Code::InvalidArgument,
+        401 => tonic::Code::Unauthenticated,
+        403 => tonic::Code::PermissionDenied,
+        404 => tonic::Code::NotFound,
+        409 => tonic::Code::AlreadyExists,
+        412 | 423 => tonic::Code::FailedPrecondition,
+        429 => tonic::Code::ResourceExhausted,
+        503 => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    };
+    tonic::Status::new(code, message)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enum_conversions() {
+        assert_eq!(metric(0).unwrap(), None);
+        assert_eq!(metric(2).unwrap(), Some(DistanceMetric::Euclidean));
+        assert!(matches!(metric(9), Err(VectorDbError::InvalidParameter(_))));
+        assert_eq!(on_conflict(0).unwrap(), OnConflict::Overwrite);
+        assert_eq!(on_conflict(3).unwrap(), OnConflict::MergeMetadata);
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(status(404, "x".into()).code(), tonic::Code::NotFound);
+        assert_eq!(
+            status(423, "x".into()).code(),
+            tonic::Code::FailedPrecondition
+        );
+        assert_eq!(status(500, "x".into()).code(), tonic::Code::Internal);
+    }
+}
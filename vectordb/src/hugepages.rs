@@ -0,0 +1,358 @@
+// src/hugepages.rs
+//
+// Huge-page backing for the vector arena.
+//
+// A 768-dim scan streams 3 KiB per vector, so a million vectors cover
+// about 750,000 4 KiB pages. The TLB holds a few thousand translations,
+// so nearly every page costs a page-table walk, and those walks show up
+// prominently in perf profiles of large scans. With 2 MiB pages the same
+// data spans about 1,500 pages, and the walks all but disappear.
+//
+// The `huge_pages` setting (--huge-pages / VECTORDB_HUGE_PAGES) picks how
+// the arena's buffers (numa.rs) are allocated:
+//
+//   off          the heap, like every other Vec (default)
+//   transparent  anonymous mmap + madvise(MADV_HUGEPAGE): the kernel backs
+//                it with huge pages when it can find them (Linux THP)
+//   explicit     mmap(MAP_HUGETLB) from the pool reserved in
+//                /proc/sys/vm/nr_hugepages; falls back to transparent if
+//                the pool is empty or missing
+//
+// Buffers under one huge page stay on the heap whatever the setting: the
+// TLB covers them anyway, and a 2 MiB mapping would mostly be waste.
+//
+// Mappings are lazily backed, so the thread that first writes a page
+// decides its NUMA node, just as for heap memory.
+//
+// This is the crate's main unsafe code: the mapping itself, the slices
+// over it, and Send/Sync for the Buffer holding its pointer. Miri can't
+// run mmap, so under cfg(miri) a "mapping" comes from the allocator
+// instead, zeroed and aligned as mmap's would be, and the rest of the
+// code runs unchanged. The tests below check it there:
+//
+//   cargo +nightly miri test --lib hugepages::
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Size of one huge page (x86-64 and aarch64 defaults)
+pub const HUGE_PAGE: usize = 2 * 1024 * 1024;
+
+/// How arena buffers are backed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HugePages {
+    #[default]
+    Off,
+    Transparent,
+    Explicit,
+}
+
+impl std::str::FromStr for HugePages {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "off" => Ok(Self::Off),
+            "transparent" => Ok(Self::Transparent),
+            "explicit" => Ok(Self::Explicit),
+            _ => Err(format!(
+                "unknown huge page mode '{}' (expected off, transparent or explicit)",
+                s
+            )),
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+/// The configured mode
+pub fn mode() -> HugePages {
+    match MODE.load(Ordering::Relaxed) {
+        1 => HugePages::Transparent,
+        2 => HugePages::Explicit,
+        _ => HugePages::Off,
+    }
+}
+
+/// Set the mode (the `huge_pages` server setting)
+pub fn set_mode(mode: HugePages) {
+    let value = match mode {
+        HugePages::Off => 0,
+        HugePages::Transparent => 1,
+        HugePages::Explicit => 2,
+    };
+    MODE.store(value, Ordering::Relaxed);
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// BUFFER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a `Buffer`'s memory actually came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backing {
+    Heap,
+    Transparent,
+    Explicit,
+}
+
+/// A fixed-length, zeroed f32 buffer, backed as the mode asks and the
+/// system allows
+#[derive(Debug)]
+pub struct Buffer {
+    memory: Memory,
+    len: usize,
+}
+
+#[derive(Debug)]
+enum Memory {
+    Heap(Vec<f32>),
+    #[cfg(unix)]
+    Mapped {
+        ptr: std::ptr::NonNull<f32>,
+        bytes: usize,
+        backing: Backing,
+    },
+}
+
+// SAFETY: the mapping is owned by the Buffer and only reached through it,
+// so it's as thread-safe as the Vec it stands in for
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    /// `len` zeroed floats
+    pub fn zeroed(len: usize, mode: HugePages) -> Self {
+        let bytes = len * std::mem::size_of::<f32>();
+        let memory = if mode == HugePages::Off || bytes < HUGE_PAGE {
+            Memory::Heap(vec![0.0; len])
+        } else {
+            map(bytes, mode).unwrap_or_else(|| Memory::Heap(vec![0.0; len]))
+        };
+        Self { memory, len }
+    }
+
+    pub fn backing(&self) -> Backing {
+        match &self.memory {
+            Memory::Heap(_) => Backing::Heap,
+            #[cfg(unix)]
+            Memory::Mapped { backing, .. } => *backing,
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        match &self.memory {
+            Memory::Heap(v) => v,
+            // SAFETY: the mapping holds at least len floats, zero-filled by
+            // the kernel, and lives as long as self
+            #[cfg(unix)]
+            Memory::Mapped { ptr, .. } => unsafe {
+                std::slice::from_raw_parts(ptr.as_ptr(), self.len)
+            },
+        }
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut [f32] {
+        match &mut self.memory {
+            Memory::Heap(v) => v,
+            // SAFETY: as for deref, and &mut self makes the access unique
+            #[cfg(unix)]
+            Memory::Mapped { ptr, .. } => unsafe {
+                std::slice::from_raw_parts_mut(ptr.as_ptr(), self.len)
+            },
+        }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        #[cfg(all(unix, not(miri)))]
+        if let Memory::Mapped { ptr, bytes, .. } = &self.memory {
+            // SAFETY: unmapping exactly the region map() returned, once
+            unsafe { libc::munmap(ptr.as_ptr().cast(), *bytes) };
+        }
+        #[cfg(all(unix, miri))]
+        if let Memory::Mapped { ptr, bytes, .. } = &self.memory {
+            // SAFETY: freeing exactly the allocation map() returned, once,
+            // with the layout it was allocated with
+            unsafe { std::alloc::dealloc(ptr.as_ptr().cast(), miri_layout(*bytes)) };
+        }
+    }
+}
+
+/// An anonymous mapping of at least `bytes`, rounded up to whole huge
+/// pages; None if even a plain mapping fails
+#[cfg(all(unix, not(miri)))]
+fn map(bytes: usize, mode: HugePages) -> Option<Memory> {
+    let bytes = (bytes + HUGE_PAGE - 1) / HUGE_PAGE * HUGE_PAGE;
+    let mmap = |extra_flags: libc::c_int| {
+        // SAFETY: an anonymous private mapping touches no existing memory
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | extra_flags,
+                -1,
+                0,
+            )
+        };
+        (ptr != libc::MAP_FAILED).then(|| std::ptr::NonNull::new(ptr.cast::<f32>()))?
+    };
+
+    #[cfg(target_os = "linux")]
+    if mode == HugePages::Explicit {
+        if let Some(ptr) = mmap(libc::MAP_HUGETLB) {
+            return Some(Memory::Mapped {
+                ptr,
+                bytes,
+                backing: Backing::Explicit,
+            });
+        }
+        warn_no_pool();
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = mode;
+
+    let ptr = mmap(0)?;
+    #[cfg(target_os = "linux")]
+    // SAFETY: advice on a mapping we own; failure only means no THP
+    unsafe {
+        libc::madvise(ptr.as_ptr().cast(), bytes, libc::MADV_HUGEPAGE);
+    }
+    Some(Memory::Mapped {
+        ptr,
+        bytes,
+        backing: Backing::Transparent,
+    })
+}
+
+/// Under Miri, which has no mmap: zeroed memory from the allocator, in
+/// whole huge pages aligned to one, standing in for the mapping
+#[cfg(all(unix, miri))]
+fn map(bytes: usize, mode: HugePages) -> Option<Memory> {
+    let bytes = (bytes + HUGE_PAGE - 1) / HUGE_PAGE * HUGE_PAGE;
+    // SAFETY: the layout has a nonzero size (bytes is at least HUGE_PAGE)
+    let ptr = unsafe { std::alloc::alloc_zeroed(miri_layout(bytes)) };
+    let backing = match mode {
+        HugePages::Explicit => Backing::Explicit,
+        _ => Backing::Transparent,
+    };
+    Some(Memory::Mapped {
+        ptr: std::ptr::NonNull::new(ptr.cast::<f32>())?,
+        bytes,
+        backing,
+    })
+}
+
+#[cfg(all(unix, miri))]
+fn miri_layout(bytes: usize) -> std::alloc::Layout {
+    std::alloc::Layout::from_size_align(bytes, HUGE_PAGE).expect("huge page layout")
+}
+
+#[cfg(not(unix))]
+fn map(_bytes: usize, _mode: HugePages) -> Option<Memory> {
+    None
+}
+
+/// Say once that explicit huge pages weren't available
+#[cfg(all(target_os = "linux", not(miri)))]
+fn warn_no_pool() {
+    static WARNED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        tracing::warn!(
+            "No explicit huge pages available ({}); using transparent huge pages. \
+             Reserve some with /proc/sys/vm/nr_hugepages.",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_buffers_stay_on_the_heap() {
+        let buffer = Buffer::zeroed(1024, HugePages::Explicit);
+        assert_eq!(buffer.backing(), Backing::Heap);
+        assert_eq!(buffer.len(), 1024);
+    }
+
+    #[test]
+    fn test_large_buffers_are_mapped_and_zeroed() {
+        let len = 3 * HUGE_PAGE / 4 + 5;
+        for mode in [HugePages::Transparent, HugePages::Explicit] {
+            let mut buffer = Buffer::zeroed(len, mode);
+            assert_eq!(buffer.len(), len);
+            assert!(buffer.iter().all(|&x| x == 0.0));
+            buffer[len - 1] = 1.5;
+            assert_eq!(buffer[len - 1], 1.5);
+            #[cfg(unix)]
+            assert_ne!(buffer.backing(), Backing::Heap);
+        }
+        assert_eq!(Buffer::zeroed(len, HugePages::Off).backing(), Backing::Heap);
+    }
+
+    #[test]
+    fn test_mapped_buffers_move_and_share_across_threads() {
+        let len = HUGE_PAGE / std::mem::size_of::<f32>() + 3;
+        let mut buffer = Buffer::zeroed(len, HugePages::Transparent);
+        for (i, x) in buffer.iter_mut().enumerate().step_by(4096) {
+            *x = i as f32;
+        }
+        buffer[len - 1] = -1.0;
+
+        // Send: read and written on another thread, then dropped there
+        let buffer = std::thread::spawn(move || {
+            buffer[1] = 2.0;
+            buffer
+        })
+        .join()
+        .unwrap();
+        assert_eq!(
+            (buffer[1], buffer[4096], buffer[len - 1]),
+            (2.0, 4096.0, -1.0)
+        );
+
+        // Sync: read from several threads at once
+        let buffer = &buffer;
+        let sums: Vec<f32> = std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..2)
+                .map(|_| scope.spawn(move || buffer.iter().step_by(4096).sum()))
+                .collect();
+            readers.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let want: f32 = (0..len).step_by(4096).map(|i| i as f32).sum();
+        assert_eq!(sums, [want, want]);
+    }
+
+    #[test]
+    fn test_empty_and_tiny_buffers() {
+        for mode in [HugePages::Off, HugePages::Transparent, HugePages::Explicit] {
+            assert!(Buffer::zeroed(0, mode).is_empty());
+            let mut one = Buffer::zeroed(1, mode);
+            one[0] = 1.0;
+            assert_eq!(&one[..], &[1.0]);
+        }
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("explicit".parse(), Ok(HugePages::Explicit));
+        assert!("always".parse::<HugePages>().is_err());
+    }
+}
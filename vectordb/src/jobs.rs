@@ -0,0 +1,229 @@
+// src/jobs.rs
+//
+// Background job registry.
+//
+// Some operations touch every vector in a collection (bulk metadata
+// updates, index builds, migrations). Running them inside the request
+// would hold the HTTP connection — and the write lock — for minutes.
+// Instead the handler registers a Job, spawns a tokio task that works in
+// batches, and returns the job ID immediately:
+//
+//   POST /api/collections/docs/update_by_filter  →  202 { "job_id": 7 }
+//   GET  /api/jobs/7                             →  { "progress": 0.42, ... }
+//
+// Progress counters are atomics so the worker can update them without
+// taking the application lock.
+
+use crate::sync::{Arc, AtomicU64, Mutex, Ordering};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Lifecycle of a background job
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Mutable part of a job that isn't a simple counter
+#[derive(Debug)]
+struct JobOutcome {
+    status: JobStatus,
+    finished_at: Option<Instant>,
+    error: Option<String>,
+    result: Option<serde_json::Value>,
+}
+
+/// A single background job. Shared between the registry and its worker.
+#[derive(Debug)]
+pub struct Job {
+    pub id: u64,
+    pub kind: String,
+    started_at: Instant,
+    total: AtomicU64,
+    processed: AtomicU64,
+    outcome: Mutex<JobOutcome>,
+}
+
+impl Job {
+    fn new(id: u64, kind: &str, total: u64) -> Self {
+        Self {
+            id,
+            kind: kind.to_string(),
+            started_at: Instant::now(),
+            total: AtomicU64::new(total),
+            processed: AtomicU64::new(0),
+            outcome: Mutex::new(JobOutcome {
+                status: JobStatus::Running,
+                finished_at: None,
+                error: None,
+                result: None,
+            }),
+        }
+    }
+
+    /// Update the total amount of work (if it wasn't known up front)
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Record `n` more units of work as done
+    pub fn advance(&self, n: u64) {
+        self.processed.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Mark the job as finished successfully with an optional result
+    pub fn complete(&self, result: serde_json::Value) {
+        let mut outcome = self.outcome.lock().unwrap();
+        outcome.status = JobStatus::Completed;
+        outcome.finished_at = Some(Instant::now());
+        outcome.result = Some(result);
+    }
+
+    /// Mark the job as failed
+    pub fn fail(&self, error: impl Into<String>) {
+        let mut outcome = self.outcome.lock().unwrap();
+        outcome.status = JobStatus::Failed;
+        outcome.finished_at = Some(Instant::now());
+        outcome.error = Some(error.into());
+    }
+
+    /// Current status
+    pub fn status(&self) -> JobStatus {
+        self.outcome.lock().unwrap().status
+    }
+
+    /// Fraction of work done, 0.0..=1.0
+    pub fn progress(&self) -> f64 {
+        if self.status() == JobStatus::Completed {
+            return 1.0;
+        }
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        (self.processed.load(Ordering::Relaxed) as f64 / total as f64).min(1.0)
+    }
+
+    /// Estimated time remaining, extrapolated from the rate so far
+    pub fn eta(&self) -> Option<Duration> {
+        if self.status() != JobStatus::Running {
+            return None;
+        }
+        let progress = self.progress();
+        if progress <= 0.0 {
+            return None;
+        }
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        Some(Duration::from_secs_f64(
+            elapsed / progress * (1.0 - progress),
+        ))
+    }
+
+    /// Time since the job started (or its total runtime once finished)
+    pub fn elapsed(&self) -> Duration {
+        let finished_at = self.outcome.lock().unwrap().finished_at;
+        finished_at
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started_at)
+    }
+
+    /// JSON snapshot for GET /api/jobs/:id
+    pub fn snapshot(&self) -> serde_json::Value {
+        let (status, error, result) = {
+            let outcome = self.outcome.lock().unwrap();
+            (
+                outcome.status,
+                outcome.error.clone(),
+                outcome.result.clone(),
+            )
+        };
+
+        serde_json::json!({
+            "id": self.id,
+            "kind": self.kind,
+            "status": status,
+            "total": self.total.load(Ordering::Relaxed),
+            "processed": self.processed.load(Ordering::Relaxed),
+            "progress": self.progress(),
+            "elapsed_secs": self.elapsed().as_secs_f64(),
+            "eta_secs": self.eta().map(|d| d.as_secs_f64()),
+            "error": error,
+            "result": result,
+        })
+    }
+}
+
+/// All jobs started since the server came up.
+#[derive(Debug, Default)]
+pub struct JobRegistry {
+    next_id: u64,
+    jobs: HashMap<u64, Arc<Job>>,
+}
+
+impl JobRegistry {
+    /// Register a new running job and return a handle for its worker
+    pub fn start(&mut self, kind: &str, total: u64) -> Arc<Job> {
+        self.next_id += 1;
+        let job = Arc::new(Job::new(self.next_id, kind, total));
+        self.jobs.insert(job.id, job.clone());
+        job
+    }
+
+    /// Look up a job by ID
+    pub fn get(&self, id: u64) -> Option<Arc<Job>> {
+        self.jobs.get(&id).cloned()
+    }
+
+    /// All jobs, oldest first
+    pub fn list(&self) -> Vec<Arc<Job>> {
+        let mut jobs: Vec<_> = self.jobs.values().cloned().collect();
+        jobs.sort_by_key(|j| j.id);
+        jobs
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_progress_and_completion() {
+        let mut registry = JobRegistry::default();
+        let job = registry.start("test", 4);
+        assert_eq!(job.status(), JobStatus::Running);
+        assert_eq!(job.progress(), 0.0);
+        assert!(job.eta().is_none());
+
+        job.advance(1);
+        assert_eq!(job.progress(), 0.25);
+        assert!(job.eta().is_some());
+
+        job.advance(3);
+        job.complete(serde_json::json!({ "updated": 4 }));
+        let snapshot = registry.get(job.id).unwrap().snapshot();
+        assert_eq!(snapshot["status"], "completed");
+        assert_eq!(snapshot["progress"], 1.0);
+        assert_eq!(snapshot["result"]["updated"], 4);
+        assert!(snapshot["eta_secs"].is_null());
+    }
+
+    #[test]
+    fn test_registry_assigns_increasing_ids() {
+        let mut registry = JobRegistry::default();
+        let a = registry.start("a", 0);
+        let b = registry.start("b", 0);
+        b.fail("boom");
+        assert!(b.id > a.id);
+        assert_eq!(registry.list().len(), 2);
+        assert_eq!(registry.get(b.id).unwrap().snapshot()["error"], "boom");
+        assert!(registry.get(99).is_none());
+    }
+}
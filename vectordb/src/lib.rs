@@ -9,4 +9,29 @@
 //   Phase 3: pub mod engine;    (search, HNSW index)
 //   Phase 4: pub mod transport; (Axum HTTP handlers)
 
+pub mod auth;
+pub mod config;
+pub mod engine;
+pub mod faults;
+pub mod fds;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hugepages;
+pub mod jobs;
+pub mod limits;
+pub mod locks;
 pub mod models;
+pub mod monitoring;
+pub mod numa;
+pub mod oidc;
+#[cfg(feature = "pprof")]
+pub mod profiling;
+pub mod quantization;
+pub mod querylog;
+pub mod storage;
+pub mod sync;
+pub mod synthetic;
+pub mod telemetry;
+pub mod tls;
+pub mod uploads;
+pub mod usage;
@@ -0,0 +1,97 @@
+// src/limits.rs
+//
+// Process-wide size limits.
+//
+// Every float is 4 bytes, so a single 1,000,000-dimension vector is 4 MB
+// before any index overhead. The max dimension caps that per-element cost.
+// It's checked when collections are created, on every legacy insert, and
+// when segment headers are read, so a bad file can't sneak past it either.
+//
+// The limit is set once at startup from `max_dimension` (see config.rs)
+// and is the same for every collection.
+//
+// Radius searches return every hit within a threshold, which for a loose
+// threshold can be the whole collection; `max_radius_results` caps how
+// many come back per page (the rest follow via the cursor).
+
+use crate::models::{Result, VectorDbError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default largest dimension (covers every common embedding model)
+pub const DEFAULT_MAX_DIMENSION: usize = 65_536;
+
+/// Hard ceiling for the configurable limit: one vector ≤ 64 MiB
+pub const MAX_CONFIGURABLE_DIMENSION: usize = 16 * 1024 * 1024;
+
+static MAX_DIMENSION: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_DIMENSION);
+
+/// Default largest page of radius search hits
+pub const DEFAULT_MAX_RADIUS_RESULTS: usize = 1_000;
+
+static MAX_RADIUS_RESULTS: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_RADIUS_RESULTS);
+
+/// The largest dimension currently accepted
+pub fn max_dimension() -> usize {
+    MAX_DIMENSION.load(Ordering::Relaxed)
+}
+
+/// Change the largest accepted dimension (validated like the setting)
+pub fn set_max_dimension(limit: usize) -> Result<()> {
+    if !(1..=MAX_CONFIGURABLE_DIMENSION).contains(&limit) {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "max dimension must be in 1..={}, got {}",
+            MAX_CONFIGURABLE_DIMENSION, limit
+        )));
+    }
+    MAX_DIMENSION.store(limit, Ordering::Relaxed);
+    Ok(())
+}
+
+/// The most hits one page of a radius search returns
+pub fn max_radius_results() -> usize {
+    MAX_RADIUS_RESULTS.load(Ordering::Relaxed)
+}
+
+/// Change the radius search page cap (at least 1)
+pub fn set_max_radius_results(limit: usize) -> Result<()> {
+    if limit == 0 {
+        return Err(VectorDbError::InvalidParameter(
+            "max radius results must be at least 1".into(),
+        ));
+    }
+    MAX_RADIUS_RESULTS.store(limit, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Check a dimension against the supported range, 1..=max_dimension()
+pub fn check_dimension(dimension: usize) -> Result<()> {
+    let max = max_dimension();
+    if (1..=max).contains(&dimension) {
+        return Ok(());
+    }
+    Err(VectorDbError::InvalidParameter(format!(
+        "dimension {} is out of range: supported dimensions are 1..={} \
+         (raise with max_dimension, up to {})",
+        dimension, max, MAX_CONFIGURABLE_DIMENSION
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_dimension_range() {
+        assert!(check_dimension(1).is_ok());
+        assert!(check_dimension(DEFAULT_MAX_DIMENSION).is_ok());
+
+        assert!(check_dimension(0).is_err());
+        let err = check_dimension(1_000_000).unwrap_err().to_string();
+        assert!(err.contains("1..=65536"), "{}", err);
+
+        // Out-of-range limits are rejected without changing the current one
+        assert!(set_max_dimension(0).is_err());
+        assert!(set_max_dimension(MAX_CONFIGURABLE_DIMENSION + 1).is_err());
+        assert_eq!(max_dimension(), DEFAULT_MAX_DIMENSION);
+    }
+}
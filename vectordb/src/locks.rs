@@ -0,0 +1,442 @@
+// src/locks.rs
+//
+// Instrumented read/write lock for the server's shared state.
+//
+// All state sits behind one RwLock, so a writer that never finishes shows
+// up only as searches that hang. `TrackedRwLock` wraps tokio's RwLock and
+// keeps a registry of who holds the lock and who's queued for it:
+//
+//   GET /api/admin/locks
+//   { "holders": [{ "mode": "write", "operation": "POST /api/collections/docs/points",
+//                   "collection": "docs", "elapsed_ms": 8412 }],
+//     "waiters": [{ "mode": "read",  "operation": "POST /api/collections/docs/search", ... }] }
+//
+// Each acquisition is labelled with the operation running on the current
+// task (set per request by middleware via `with_operation`), and with the
+// collection that operation targets. A watchdog logs holders that exceed
+// the warning threshold while they still hold the lock, and every release
+// past the threshold is logged with how long it took.
+//
+// Each wait also runs in a `lock.wait` span that names the operations
+// holding the lock when it started (blocked_by), so traces show what a
+// slow request was queued behind.
+
+use crate::sync::{AtomicU64, Mutex, Ordering};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::Instrument;
+
+/// Default hold time after which a lock holder is logged as long-held
+pub const DEFAULT_WARN_AFTER: Duration = Duration::from_secs(1);
+
+/// What the current task is doing, for attributing lock acquisitions
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// e.g. "POST /api/collections/docs/points"
+    pub label: String,
+    /// Collection the operation targets, if any
+    pub collection: Option<String>,
+}
+
+impl Operation {
+    /// Label an HTTP request, picking the collection out of its path
+    pub fn from_request(method: &str, path: &str) -> Self {
+        let collection = path
+            .strip_prefix("/api/collections/")
+            .and_then(|rest| rest.split('/').next())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        Self {
+            label: format!("{} {}", method, path),
+            collection,
+        }
+    }
+
+    /// Label work that isn't tied to a request
+    pub fn background(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            collection: None,
+        }
+    }
+}
+
+tokio::task_local! {
+    static OPERATION: Operation;
+}
+
+/// Run `fut` with `op` as the label for every lock it acquires
+pub async fn with_operation<F: Future>(op: Operation, fut: F) -> F::Output {
+    OPERATION.scope(op, fut).await
+}
+
+fn current_operation() -> Operation {
+    OPERATION
+        .try_with(Operation::clone)
+        .unwrap_or_else(|_| Operation::background("unlabelled"))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// REGISTRY
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockMode {
+    Read,
+    Write,
+}
+
+#[derive(Debug)]
+struct Entry {
+    mode: LockMode,
+    op: Operation,
+    /// When it started waiting, then when it acquired the lock
+    since: Instant,
+    held: bool,
+    /// Already reported by the watchdog
+    warned: bool,
+}
+
+/// One holder or waiter, as reported by the admin endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct LockInfo {
+    pub id: u64,
+    pub mode: LockMode,
+    pub operation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Time spent holding (holders) or waiting (waiters)
+    pub elapsed_ms: u64,
+}
+
+/// Point-in-time view of a lock
+#[derive(Debug, Clone, Serialize)]
+pub struct LockSnapshot {
+    pub holders: Vec<LockInfo>,
+    pub waiters: Vec<LockInfo>,
+    pub warn_after_ms: u64,
+}
+
+impl LockSnapshot {
+    /// The same holders and waiters, grouped by target collection
+    /// (requests that don't target one are left out)
+    pub fn by_collection(&self) -> HashMap<String, LockSnapshot> {
+        let mut grouped: HashMap<String, LockSnapshot> = HashMap::new();
+        let mut group = |info: &LockInfo, holder: bool| {
+            if let Some(name) = &info.collection {
+                let entry = grouped.entry(name.clone()).or_insert_with(|| LockSnapshot {
+                    holders: Vec::new(),
+                    waiters: Vec::new(),
+                    warn_after_ms: self.warn_after_ms,
+                });
+                if holder {
+                    entry.holders.push(info.clone());
+                } else {
+                    entry.waiters.push(info.clone());
+                }
+            }
+        };
+        self.holders.iter().for_each(|i| group(i, true));
+        self.waiters.iter().for_each(|i| group(i, false));
+        grouped
+    }
+}
+
+#[derive(Debug)]
+struct Registry {
+    next_id: AtomicU64,
+    warn_after_ms: AtomicU64,
+    entries: Mutex<HashMap<u64, Entry>>,
+}
+
+impl Registry {
+    fn wait(&self, mode: LockMode) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = Entry {
+            mode,
+            op: current_operation(),
+            since: Instant::now(),
+            held: false,
+            warned: false,
+        };
+        self.entries.lock().unwrap().insert(id, entry);
+        id
+    }
+
+    fn acquired(&self, id: u64) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&id) {
+            entry.held = true;
+            entry.since = Instant::now();
+        }
+    }
+
+    /// Operations currently holding the lock
+    fn holders(&self) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|e| e.held)
+            .map(|e| e.op.label.clone())
+            .collect()
+    }
+
+    /// Forget an entry (lock released, or the wait was cancelled)
+    fn remove(&self, id: u64) {
+        let Some(entry) = self.entries.lock().unwrap().remove(&id) else {
+            return;
+        };
+        let elapsed = entry.since.elapsed();
+        if entry.held && elapsed.as_millis() as u64 >= self.warn_after_ms.load(Ordering::Relaxed) {
+            tracing::warn!(
+                "{:?} lock released after {} ms by {}",
+                entry.mode,
+                elapsed.as_millis(),
+                entry.op.label
+            );
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// LOCK + GUARDS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A tokio RwLock that records its holders and waiters
+#[derive(Debug)]
+pub struct TrackedRwLock<T> {
+    inner: RwLock<T>,
+    registry: Registry,
+}
+
+/// Guard returned by `TrackedRwLock`; unregisters the holder on drop
+pub struct TrackedGuard<'a, G> {
+    guard: G,
+    registry: &'a Registry,
+    id: u64,
+}
+
+/// Unregisters a waiter whose acquisition future was dropped
+struct Waiting<'a> {
+    registry: &'a Registry,
+    id: u64,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.registry.remove(self.id);
+    }
+}
+
+impl<T> TrackedRwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: RwLock::new(value),
+            registry: Registry {
+                next_id: AtomicU64::new(1),
+                warn_after_ms: AtomicU64::new(DEFAULT_WARN_AFTER.as_millis() as u64),
+                entries: Mutex::new(HashMap::new()),
+            },
+        }
+    }
+
+    pub async fn read(&self) -> TrackedGuard<'_, RwLockReadGuard<'_, T>> {
+        let waiting = self.wait(LockMode::Read);
+        let guard = self
+            .inner
+            .read()
+            .instrument(self.wait_span(LockMode::Read))
+            .await;
+        self.hold(waiting, guard)
+    }
+
+    pub async fn write(&self) -> TrackedGuard<'_, RwLockWriteGuard<'_, T>> {
+        let waiting = self.wait(LockMode::Write);
+        let guard = self
+            .inner
+            .write()
+            .instrument(self.wait_span(LockMode::Write))
+            .await;
+        self.hold(waiting, guard)
+    }
+
+    fn wait(&self, mode: LockMode) -> Waiting<'_> {
+        Waiting {
+            registry: &self.registry,
+            id: self.registry.wait(mode),
+        }
+    }
+
+    /// Span covering one wait, naming whoever held the lock when it began
+    fn wait_span(&self, mode: LockMode) -> tracing::Span {
+        let span = tracing::info_span!(
+            "lock.wait",
+            mode = ?mode,
+            blocked_by = tracing::field::Empty
+        );
+        if !span.is_disabled() {
+            let holders = self.registry.holders();
+            if !holders.is_empty() {
+                span.record("blocked_by", holders.join(", "));
+            }
+        }
+        span
+    }
+
+    fn hold<G>(&self, waiting: Waiting<'_>, guard: G) -> TrackedGuard<'_, G> {
+        let id = waiting.id;
+        std::mem::forget(waiting);
+        self.registry.acquired(id);
+        TrackedGuard {
+            guard,
+            registry: &self.registry,
+            id,
+        }
+    }
+
+    /// Log holders (and count releases) past this hold time
+    pub fn set_warn_after(&self, after: Duration) {
+        self.registry
+            .warn_after_ms
+            .store(after.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Current holders and waiters, longest first
+    pub fn snapshot(&self) -> LockSnapshot {
+        let entries = self.registry.entries.lock().unwrap();
+        let mut holders = Vec::new();
+        let mut waiters = Vec::new();
+        for (&id, entry) in entries.iter() {
+            let info = LockInfo {
+                id,
+                mode: entry.mode,
+                operation: entry.op.label.clone(),
+                collection: entry.op.collection.clone(),
+                elapsed_ms: entry.since.elapsed().as_millis() as u64,
+            };
+            if entry.held {
+                holders.push(info);
+            } else {
+                waiters.push(info);
+            }
+        }
+        holders.sort_by_key(|h| std::cmp::Reverse(h.elapsed_ms));
+        waiters.sort_by_key(|w| std::cmp::Reverse(w.elapsed_ms));
+        LockSnapshot {
+            holders,
+            waiters,
+            warn_after_ms: self.registry.warn_after_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Log every holder past the warning threshold that hasn't been
+    /// reported yet, along with how many tasks are queued behind it.
+    /// Returns how many were reported.
+    pub fn warn_long_held(&self) -> usize {
+        let warn_after = self.registry.warn_after_ms.load(Ordering::Relaxed);
+        let mut entries = self.registry.entries.lock().unwrap();
+        let waiting = entries.values().filter(|e| !e.held).count();
+        let mut reported = 0;
+        for entry in entries.values_mut() {
+            let held_ms = entry.since.elapsed().as_millis() as u64;
+            if entry.held && !entry.warned && held_ms >= warn_after {
+                entry.warned = true;
+                reported += 1;
+                tracing::warn!(
+                    "{:?} lock held for {} ms by {} ({} waiting)",
+                    entry.mode,
+                    held_ms,
+                    entry.op.label,
+                    waiting
+                );
+            }
+        }
+        reported
+    }
+}
+
+impl<G> Drop for TrackedGuard<'_, G> {
+    fn drop(&mut self) {
+        self.registry.remove(self.id);
+    }
+}
+
+impl<G: Deref> Deref for TrackedGuard<'_, G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut> DerefMut for TrackedGuard<'_, G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_operation_from_request() {
+        let op = Operation::from_request("POST", "/api/collections/docs/points");
+        assert_eq!(op.collection.as_deref(), Some("docs"));
+        assert_eq!(op.label, "POST /api/collections/docs/points");
+        assert_eq!(Operation::from_request("GET", "/stats").collection, None);
+        assert_eq!(
+            Operation::from_request("GET", "/api/collections").collection,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_shows_holders_and_waiters() {
+        let lock = Arc::new(TrackedRwLock::new(0u32));
+        lock.set_warn_after(Duration::ZERO);
+
+        let writer = lock.write().await;
+        let waiter = {
+            let lock = lock.clone();
+            tokio::spawn(with_operation(
+                Operation::from_request("POST", "/api/collections/docs/search"),
+                async move { *lock.read().await },
+            ))
+        };
+        while lock.snapshot().waiters.is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let snap = lock.snapshot();
+        assert_eq!(snap.holders.len(), 1);
+        assert_eq!(snap.holders[0].mode, LockMode::Write);
+        assert_eq!(snap.holders[0].operation, "unlabelled");
+        assert_eq!(snap.waiters[0].mode, LockMode::Read);
+        let docs = &snap.by_collection()["docs"];
+        assert_eq!((docs.holders.len(), docs.waiters.len()), (0, 1));
+
+        assert_eq!(lock.warn_long_held(), 1);
+        assert_eq!(lock.warn_long_held(), 0); // reported once
+
+        drop(writer);
+        waiter.await.unwrap();
+        let snap = lock.snapshot();
+        assert!(snap.holders.is_empty() && snap.waiters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_wait_is_unregistered() {
+        let lock = TrackedRwLock::new(());
+        let _writer = lock.write().await;
+        let attempt = tokio::time::timeout(Duration::from_millis(10), lock.read()).await;
+        assert!(attempt.is_err());
+        assert!(lock.snapshot().waiters.is_empty());
+    }
+}
@@ -3,60 +3,469 @@
 // VectorDB HTTP Server — Post #5
 //
 // A real Axum server with:
-// - Shared state (Arc<RwLock<AppState>>)
+// - Shared state (Arc<TrackedRwLock<AppState>>)
 // - CRUD endpoints (insert, get, search)
 // - JSON error handling (ApiError → IntoResponse)
 // - Request logging middleware (TraceLayer)
 // - Graceful shutdown (Ctrl+C)
+// - Optionally, a gRPC API over the same state (`--features grpc`)
 //
 // Run with: cargo run
 // Test with: curl http://localhost:3000/health
 
-mod models;
-
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    body::Body,
+    extract::{DefaultBodyLimit, MatchedPath, Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
 };
-use models::{SearchResult, Vector};
+use futures_util::StreamExt;
+use metrics_exporter_prometheus::PrometheusHandle;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::path::{Path as FsPath, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::trace::TraceLayer;
+use std::time::{Duration, Instant};
+use tokio_rustls::TlsAcceptor;
+use tower_http::trace::{DefaultMakeSpan, TraceLayer};
+use tracing::Level;
+use vectordb::auth::{self, Access, KeyCheck, Principal, Verb};
+use vectordb::config::Config;
+use vectordb::engine::alias::Alias;
+use vectordb::engine::arith;
+use vectordb::engine::cache::{Fingerprint, ResultCache};
+use vectordb::engine::calibration;
+use vectordb::engine::collection::{self, Collection};
+use vectordb::engine::export::{ExportFormat, Projection};
+use vectordb::engine::filter::Filter;
+use vectordb::engine::hnsw::{self, FilterStrategy, Layout};
+use vectordb::engine::import::ImportValidator;
+use vectordb::engine::index::IndexInfo;
+use vectordb::engine::metric;
+use vectordb::engine::normalize;
+#[cfg(feature = "parquet")]
+use vectordb::engine::parquet::{self, ParquetExport, ParquetRows};
+use vectordb::engine::search::{self, Cursor, PageToken};
+use vectordb::engine::shadow::{self, DivergentQuery, ShadowReport};
+use vectordb::engine::template::QueryTemplate;
+use vectordb::engine::transaction::{self, MAX_TRANSACTION_OPS};
+use vectordb::engine::trash::{self, Trash};
+#[cfg(feature = "wasm")]
+use vectordb::engine::udf::{self, UdfRegistry};
+use vectordb::fds::{self, FdKind};
+use vectordb::hugepages::{self, HugePages};
+use vectordb::jobs::{Job, JobRegistry};
+use vectordb::limits;
+use vectordb::locks::{self, Operation, TrackedRwLock};
+use vectordb::models::{
+    point_failed, point_ok, ArithRequest, BatchSearchRequest, BuildIndexRequest, CalibrateRequest,
+    CollectionInfo, ConditionQuery, CreateAliasRequest, CreateCollectionRequest,
+    CreateTemplateRequest, DeleteCollectionQuery, DistanceMetric, ErrorResponse, ExportQuery,
+    FieldError, HnswConfig, HybridSearchRequest, ImportQuery, IncludeFields, MultiSearchRequest,
+    MultiSearchResult, NormalizeQuery, PointResult, PointStatus, PutPointRequest, RollbackRequest,
+    ScoreNormalization, SearchRequest, SearchResult, ShadowCompareRequest, ShadowRequest,
+    SparseSearchRequest, StatsQuery, StreamSearchQuery, TemplateSearchRequest, TransactionRequest,
+    UpdateByFilterRequest, UpsertRequest, UpsertResponse, UsageQuery, Vector, VectorDbError,
+    WriteCounts, WriteOutcome,
+};
+use vectordb::monitoring;
+use vectordb::numa;
+use vectordb::oidc::{self, Verifier};
+#[cfg(feature = "pprof")]
+use vectordb::profiling;
+use vectordb::querylog::QueryLog;
+use vectordb::storage::access::AccessTracker;
+use vectordb::storage::bloom::BloomFilter;
+use vectordb::storage::clock::{Clock, SystemClock};
+use vectordb::storage::fs::DiskStorage;
+use vectordb::storage::memtable::{self, FlushPolicy};
+use vectordb::storage::object::{ObjectStore, S3Store};
+use vectordb::storage::segments::{SegmentCatalog, SegmentId};
+use vectordb::storage::snapshot::{self, Snapshot};
+use vectordb::storage::tiering::ColdTier;
+use vectordb::storage::wal::{self, SyncPolicy, Wal, WalRecord};
+use vectordb::telemetry;
+use vectordb::tls;
+use vectordb::uploads::UploadStore;
+use vectordb::usage::{self, RequestKind, UsageCounters, UsageLog};
+
+/// jemalloc, for its heap profiler (see profiling.rs)
+#[cfg(feature = "pprof")]
+#[global_allocator]
+static ALLOCATOR: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // APPLICATION STATE
 // ═══════════════════════════════════════════════════════════════════════════
 
 /// Shared state across all handlers.
-/// Arc provides shared ownership, RwLock provides safe concurrent access.
+/// Arc provides shared ownership, RwLock provides safe concurrent access
+/// (tracked, so stuck holders can be diagnosed at /api/admin/locks).
 #[derive(Default)]
 struct AppState {
-    /// In-memory vector storage: id → vector
+    /// In-memory vector storage (hot tier): id → vector
     vectors: HashMap<String, Vector>,
+    /// Quantized vectors that haven't been accessed in a while
+    cold: ColdTier,
+    /// Where the cold tier is saved (None: the data directory)
+    cold_store: Option<Arc<ObjectStore>>,
+    /// Approximate read frequency per vector and per tier
+    access: AccessTracker,
+    /// Every flat-store ID written since the last rebuild, either tier
+    /// (answers most existence checks without the maps)
+    id_filter: BloomFilter,
+    /// Named collections: name → collection
+    collections: HashMap<String, Collection>,
+    /// Collections from the snapshot whose segment hasn't been read yet
+    /// (see `load_collection`); never in `collections` at the same time
+    unloaded: HashMap<String, Unloaded>,
+    /// Where unloaded collections' segments are registered, opened on
+    /// first access
+    segments: Arc<SegmentCatalog>,
+    /// Aliases that resolve (and A/B split) to collections
+    aliases: HashMap<String, Alias>,
+    /// Pre-compiled search shapes, by name
+    templates: HashMap<String, QueryTemplate>,
+    /// Deleted collections, restorable until their retention runs out
+    trash: Trash,
+    /// Background jobs (bulk updates, ...)
+    jobs: JobRegistry,
+    /// Resumable bulk-import uploads
+    uploads: Arc<UploadStore>,
+    /// Where snapshots are written (see storage::snapshot)
+    data_dir: PathBuf,
+    /// Every write since the last snapshot (None: writes aren't logged)
+    wal: Option<Wal>,
+    /// Held while a memtable flush or a snapshot is being written, so a
+    /// flush sealed before a snapshot can't land after it
+    flush_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Per-API-key daily usage (fed by the track_usage middleware)
+    usage: Arc<UsageLog>,
+    /// VECTORDB_ADMIN_KEY, which destructive operations on protected
+    /// collections require
+    admin_key: Option<Arc<String>>,
+    /// Uploaded WASM filter/score functions
+    #[cfg(feature = "wasm")]
+    udfs: UdfRegistry,
+    /// Recent collection search results (None: caching is off)
+    result_cache: Option<ResultCache>,
+    /// Renders the Prometheus recorder for /metrics (None: not installed)
+    metrics: Option<PrometheusHandle>,
     /// Total requests served (for stats)
     request_count: u64,
 }
 
-/// Type alias — saves typing Arc<RwLock<AppState>> everywhere.
-type SharedState = Arc<RwLock<AppState>>;
+/// A collection the snapshot holds but nothing has reached since startup
+struct Unloaded {
+    info: CollectionInfo,
+    segment: SegmentId,
+    /// Next auto-increment ID, if the collection draws them
+    next_id: Option<u64>,
+}
+
+/// Which collections a request can reach, for `load_segments`
+#[derive(Debug, PartialEq)]
+enum Reach {
+    Nothing,
+    /// The collection or alias named in the path
+    One(String),
+    Any,
+}
+
+impl Reach {
+    fn of(method: &Method, path: &str) -> Self {
+        let Some(access) = Access::of(method, path) else {
+            return Reach::Nothing;
+        };
+        if let Some(name) = access.collection {
+            return Reach::One(name);
+        }
+        let flat = path == "/vectors"
+            || path.starts_with("/vectors/")
+            || path.starts_with("/api/vectors/")
+            || path == "/search";
+        // Listing shows unloaded collections from their sidecars, and
+        // creating one checks their names
+        let catalog = path == "/api/collections";
+        if flat
+            || catalog
+            || path == "/stats"
+            || path.starts_with("/api/jobs")
+            || path.starts_with("/api/admin/")
+            || path.starts_with("/debug/")
+        {
+            Reach::Nothing
+        } else {
+            Reach::Any
+        }
+    }
+}
+
+impl AppState {
+    /// Read the segment of collection `name` if it's still unloaded,
+    /// making it an ordinary in-memory collection. Returns whether it was
+    /// unloaded; on error it stays unloaded.
+    fn load_collection(&mut self, name: &str) -> Result<bool, VectorDbError> {
+        let Some(unloaded) = self.unloaded.remove(name) else {
+            return Ok(false);
+        };
+        let started = Instant::now();
+        let read = || -> Result<Collection, VectorDbError> {
+            let points = self
+                .segments
+                .read_points(unloaded.segment)?
+                .into_iter()
+                .map(|(id, vector)| {
+                    id.map(|id| (id, vector)).ok_or_else(|| {
+                        VectorDbError::Corrupted(format!(
+                            "segment of collection '{}' has no ID table",
+                            name
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut collection = Collection::restore(&unloaded.info, points)?;
+            if let Some(next) = unloaded.next_id {
+                collection.resume_ids(next);
+            }
+            Ok(collection)
+        };
+        match read() {
+            Ok(collection) => {
+                self.segments.unregister(unloaded.segment);
+                tracing::info!(
+                    "Loaded collection '{}' ({} points) in {:?}",
+                    name,
+                    collection.len(),
+                    started.elapsed()
+                );
+                self.collections.insert(name.to_string(), collection);
+                Ok(true)
+            }
+            Err(e) => {
+                self.unloaded.insert(name.to_string(), unloaded);
+                Err(e)
+            }
+        }
+    }
+
+    /// The collections a request naming `name` can reach: the collection
+    /// or an alias's targets, and their shadows
+    fn reachable(&self, name: &str) -> Vec<String> {
+        let targets: Vec<&str> = match self.aliases.get(name) {
+            Some(alias) => alias.targets().collect(),
+            None => vec![name],
+        };
+        let mut names = Vec::new();
+        for target in targets {
+            let shadow = match (self.collections.get(target), self.unloaded.get(target)) {
+                (Some(collection), _) => collection.shadow.clone(),
+                (None, Some(unloaded)) => unloaded.info.shadow.clone(),
+                (None, None) => None,
+            };
+            names.push(target.to_string());
+            names.extend(shadow);
+        }
+        names
+    }
+
+    /// Is `name` taken by a collection (loaded or not) or an alias?
+    fn name_taken(&self, name: &str) -> bool {
+        self.collections.contains_key(name)
+            || self.unloaded.contains_key(name)
+            || self.aliases.contains_key(name)
+    }
+
+    /// Append `records` to the write-ahead log. Handlers call this before
+    /// applying a write (and always before acknowledging it), under the
+    /// same write lock, so log order matches apply order. Returns the
+    /// sequence number the records were assigned (None without a WAL).
+    fn log(&self, records: &[WalRecord]) -> Result<Option<u64>, VectorDbError> {
+        match &self.wal {
+            Some(wal) => Ok(Some(wal.append(records)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record a newly written flat-store ID in the existence filter,
+    /// rebuilding it larger first if it has filled up
+    fn remember_id(&mut self, id: &str) {
+        if self.id_filter.is_saturated() {
+            self.rebuild_id_filter();
+        }
+        self.id_filter.insert(id);
+    }
+
+    /// Rename collection `from` to `to`, re-pointing aliases and shadow
+    /// links in the same step
+    fn rename_collection(&mut self, from: &str, to: &str) -> Result<(), VectorDbError> {
+        // Shadow links are kept in the collections, so ones still
+        // unloaded have to be read to re-point theirs
+        let shadowing: Vec<String> = self
+            .unloaded
+            .iter()
+            .filter(|(_, u)| u.info.shadow.as_deref() == Some(from))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in shadowing {
+            self.load_collection(&name)?;
+        }
+        let mut collection = self
+            .collections
+            .remove(from)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", from)))?;
+        collection.name = to.to_string();
+        self.collections.insert(to.to_string(), collection);
+
+        for other in self.collections.values_mut() {
+            if other.shadow.as_deref() == Some(from) {
+                other.shadow = Some(to.to_string());
+            }
+        }
+        for alias in self.aliases.values_mut() {
+            if alias.collection == from {
+                alias.collection = to.to_string();
+            }
+            if let Some(exp) = alias.experiment.as_mut().filter(|e| e.collection == from) {
+                exp.collection = to.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Move collection `name` into the trash
+    fn trash_collection(&mut self, name: &str, deleted_at: u64) -> Result<(), VectorDbError> {
+        let collection = self
+            .collections
+            .remove(name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        if let Some(older) = self.trash.put(collection, deleted_at) {
+            tracing::info!(
+                "Purged the older trashed copy of '{}' (deleted at {})",
+                name,
+                older.deleted_at
+            );
+        }
+        Ok(())
+    }
+
+    /// Move trashed collection `name` back among the live ones
+    fn restore_collection(&mut self, name: &str) -> Result<(), VectorDbError> {
+        if self.name_taken(name) {
+            return Err(VectorDbError::AlreadyExists(format!(
+                "collection '{}' (rename it before restoring the trashed one)",
+                name
+            )));
+        }
+        let trashed = self
+            .trash
+            .take(name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("trashed collection '{}'", name)))?;
+        self.collections
+            .insert(name.to_string(), trashed.collection);
+        Ok(())
+    }
+
+    /// Rebuild the existence filter from the IDs currently stored, which
+    /// also drops deleted IDs
+    fn rebuild_id_filter(&mut self) {
+        self.id_filter = self
+            .vectors
+            .keys()
+            .chain(self.cold.iter().map(|(id, _)| id))
+            .map(String::as_str)
+            .collect();
+    }
+}
+
+/// Type alias — saves typing Arc<TrackedRwLock<AppState>> everywhere.
+type SharedState = Arc<TrackedRwLock<AppState>>;
+
+/// Longest a search waits for its `min_seq` to be applied
+const MAX_SEQ_WAIT: Duration = Duration::from_secs(5);
+
+/// Add the WAL sequence number a write was assigned to its response body
+/// (left out when there's no WAL)
+fn with_seq(mut body: serde_json::Value, seq: Option<u64>) -> serde_json::Value {
+    if let Some(seq) = seq {
+        body["seq"] = seq.into();
+    }
+    body
+}
+
+/// Wait until the write with sequence number `min_seq` has been applied,
+/// for up to MAX_SEQ_WAIT; 503 if it still hasn't.
+///
+/// The WAL bumps its sequence while the writer holds the write lock, so
+/// once it reaches `min_seq` the next read lock sees that write applied.
+async fn wait_for_seq(shared: &SharedState, min_seq: Option<u64>) -> Result<(), ApiError> {
+    let Some(min_seq) = min_seq else {
+        return Ok(());
+    };
+    let Some(mut applied) = shared.read().await.wal.as_ref().map(Wal::subscribe) else {
+        return Err(ApiError::bad_request(
+            "min_seq needs the write-ahead log, which is off",
+        ));
+    };
+    let reached = tokio::time::timeout(MAX_SEQ_WAIT, applied.wait_for(|&seq| seq >= min_seq))
+        .await
+        .is_ok_and(|r| r.is_ok());
+    if reached {
+        return Ok(());
+    }
+    Err(ApiError {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        message: format!(
+            "write {} not applied within {}s (applied through {})",
+            min_seq,
+            MAX_SEQ_WAIT.as_secs(),
+            *applied.borrow()
+        ),
+        fields: Vec::new(),
+    })
+}
 
 // ═══════════════════════════════════════════════════════════════════════════
 // REQUEST TYPES
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// Payload for POST /vectors
+/// Payload for POST /vectors (and each item of POST /api/vectors/batch)
 #[derive(Debug, Deserialize)]
 struct InsertRequest {
     id: String,
     vector: Vector,
 }
 
+/// Payload for PUT /api/admin/collections/:name/protection
+#[derive(Debug, Deserialize)]
+struct ProtectionRequest {
+    protected: bool,
+}
+
+/// Payload for PUT /api/admin/collections/:name/read_only
+#[derive(Debug, Deserialize)]
+struct ReadOnlyRequest {
+    read_only: bool,
+}
+
+/// Payload for POST /api/collections/:name/rename
+#[derive(Debug, Deserialize)]
+struct RenameCollectionRequest {
+    to: String,
+}
+
+/// Payload for POST /api/vectors/get and POST /api/vectors/exists
+#[derive(Debug, Deserialize)]
+struct BatchGetRequest {
+    ids: Vec<String>,
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // ERROR HANDLING
 // ═══════════════════════════════════════════════════════════════════════════
@@ -65,6 +474,8 @@ struct InsertRequest {
 struct ApiError {
     status: StatusCode,
     message: String,
+    /// Per-field validation errors (schema violations)
+    fields: Vec<FieldError>,
 }
 
 impl ApiError {
@@ -72,6 +483,7 @@ impl ApiError {
         Self {
             status: StatusCode::BAD_REQUEST,
             message: msg.into(),
+            fields: Vec::new(),
         }
     }
 
@@ -79,6 +491,36 @@ impl ApiError {
         Self {
             status: StatusCode::NOT_FOUND,
             message: msg.into(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+/// Map domain errors onto HTTP status codes.
+impl From<VectorDbError> for ApiError {
+    fn from(err: VectorDbError) -> Self {
+        let status = match &err {
+            VectorDbError::EmptyVector
+            | VectorDbError::DimensionMismatch { .. }
+            | VectorDbError::InvalidParameter(_)
+            | VectorDbError::SchemaViolation(_) => StatusCode::BAD_REQUEST,
+            VectorDbError::NotFound(_) => StatusCode::NOT_FOUND,
+            VectorDbError::AlreadyExists(_) | VectorDbError::Conflict(_) => StatusCode::CONFLICT,
+            VectorDbError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            VectorDbError::ReadOnly(_) => StatusCode::LOCKED,
+            VectorDbError::IoError(_)
+            | VectorDbError::Corrupted(_)
+            | VectorDbError::SerializationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let message = err.to_string();
+        let fields = match err {
+            VectorDbError::SchemaViolation(errors) => errors,
+            _ => Vec::new(),
+        };
+        Self {
+            status,
+            message,
+            fields,
         }
     }
 }
@@ -86,10 +528,11 @@ impl ApiError {
 /// Convert ApiError into an HTTP response with JSON body.
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let body = serde_json::json!({
-            "error": true,
-            "message": self.message,
-        });
+        let body = ErrorResponse {
+            error: true,
+            message: self.message,
+            fields: self.fields,
+        };
         (self.status, Json(body)).into_response()
     }
 }
@@ -100,186 +543,5330 @@ impl IntoResponse for ApiError {
 
 #[tokio::main]
 async fn main() {
-    // 1. Initialize structured logging
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .with_level(true)
-        .init();
+    // 1. Read flags, environment and config file (see src/config.rs); a
+    //    bad setting stops here, before anything is opened
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(2);
+    });
+
+    //    Initialize structured logging (and OTLP trace export, if configured)
+    let telemetry = telemetry::init();
 
     tracing::info!("Starting VectorDB server...");
 
+    limits::set_max_dimension(config.max_dimension).expect("max_dimension is validated");
+    limits::set_max_radius_results(config.max_radius_results)
+        .expect("max_radius_results is validated");
+    tracing::info!(
+        "Accepting vectors of up to {} dimensions",
+        config.max_dimension
+    );
+    metric::set_default_metric(config.default_metric);
+    tracing::info!("Default distance metric: {:?}", config.default_metric);
+    numa::set_enabled(config.numa);
+    hugepages::set_mode(config.huge_pages);
+    if config.huge_pages != HugePages::Off {
+        tracing::info!("Vector arena huge pages: {:?}", config.huge_pages);
+    }
+    if config.numa {
+        let nodes = numa::Topology::get().nodes();
+        tracing::info!(
+            "NUMA placement on: {} node(s), cores {:?}",
+            nodes.len(),
+            nodes.iter().map(|n| n.cores.len()).collect::<Vec<_>>()
+        );
+    }
+    let wal_sync = config.wal_sync;
+    let fd_limits = fds::init(config.max_fds);
+    tracing::info!(
+        "File descriptor limit {} (started at {}), budget {}",
+        fd_limits.soft,
+        fd_limits.original_soft,
+        fds::BUDGET.budget()
+    );
+
     // 2. Create shared state
-    let state: SharedState = Arc::new(RwLock::new(AppState::default()));
+    //    upload_dir holds in-progress resumable uploads
+    let uploads = UploadStore::open(&config.upload_dir).expect("Failed to open upload directory");
+    //    data_dir holds the snapshot written at shutdown and the
+    //    write-ahead log of everything since
+    let data_dir = config.data_dir.clone();
+    let usage = Arc::new(
+        UsageLog::open(&data_dir.join(usage::USAGE_DIR)).expect("Failed to open usage directory"),
+    );
+    let admin_key = config.admin_key.clone().map(Arc::new);
+    let metrics = monitoring::install().expect("Failed to install metrics recorder");
+    let result_cache = config.result_cache.map(|config| {
+        tracing::info!(
+            "Caching up to {} search results for {:?}",
+            config.capacity,
+            config.ttl
+        );
+        ResultCache::new(config)
+    });
+    let mut app_state = AppState {
+        metrics: Some(metrics.clone()),
+        result_cache,
+        admin_key: admin_key.clone(),
+        cold: ColdTier::new(config.tiering),
+        trash: Trash::new(config.trash),
+        uploads: Arc::new(uploads),
+        data_dir: data_dir.clone(),
+        usage: usage.clone(),
+        ..AppState::default()
+    };
+    #[cfg(feature = "wasm")]
+    {
+        let fuel = config.udf_fuel.unwrap_or(udf::DEFAULT_FUEL);
+        app_state.udfs =
+            UdfRegistry::open(&data_dir.join(udf::UDF_DIR), fuel).expect("Failed to load UDFs");
+    }
+    //    The cold tier comes back first, from its own segment, so vectors
+    //    the snapshot or log hold hot are taken back out of it
+    let cold_store = match config.cold_store.clone() {
+        Some(s3) => ObjectStore::S3(Box::new(
+            S3Store::new(s3).expect("Failed to set up cold store"),
+        )),
+        None => ObjectStore::local(Arc::new(DiskStorage), &data_dir),
+    };
+    let cold_count = app_state
+        .cold
+        .load(&cold_store)
+        .await
+        .expect("Failed to load cold tier");
+    tracing::info!(
+        "Loaded {} cold vectors from {}",
+        cold_count,
+        cold_store.describe()
+    );
+    app_state.cold_store = Some(Arc::new(cold_store));
+    restore_state(&mut app_state, &data_dir, wal_sync).expect("Failed to load data directory");
+    let state: SharedState = Arc::new(TrackedRwLock::new(app_state));
+
+    //    lock_warn_ms: log lock holders past this hold time
+    if let Some(after) = config.lock_warn {
+        state.set_warn_after(after);
+    }
+
+    // 3. Background tasks: demote cold vectors on a fixed interval, and
+    //    report long-held state locks
+    tokio::spawn(locks::with_operation(
+        Operation::background("tiering sweep"),
+        tiering_sweep(state.clone()),
+    ));
+    tokio::spawn(locks::with_operation(
+        Operation::background("trash purge"),
+        trash_purger(state.clone()),
+    ));
+    tokio::spawn(lock_watchdog(state.clone()));
+    tokio::spawn(usage_flusher(usage.clone()));
+    tokio::spawn(metrics_upkeep(metrics));
+    let flush_policy = config.memtable;
+    tracing::info!(
+        "Flushing memtables to segments at {} bytes or after {:?}",
+        flush_policy.max_bytes,
+        flush_policy.max_age
+    );
+    tokio::spawn(locks::with_operation(
+        Operation::background("memtable flush"),
+        memtable_flusher(state.clone(), flush_policy),
+    ));
+    tokio::spawn(locks::with_operation(
+        Operation::background("auto index"),
+        index_auto_builder(state.clone()),
+    ));
+    if let SyncPolicy::Periodic(interval) = wal_sync {
+        tokio::spawn(locks::with_operation(
+            Operation::background("wal sync"),
+            wal_syncer(state.clone(), interval),
+        ));
+    }
 
-    // 3. Build router with all routes + middleware
+    // 4. Build router with all routes + middleware
     let app = Router::new()
         // Public endpoints
         .route("/", get(handler_home))
         .route("/health", get(handler_health))
         // CRUD endpoints
         .route("/vectors", post(handler_insert))
-        .route("/vectors/{id}", get(handler_get_vector))
+        .route("/api/vectors/batch", post(handler_insert_batch))
+        .route("/api/vectors/get", post(handler_get_batch))
+        .route("/api/vectors/exists", post(handler_exists_batch))
+        .route(
+            "/vectors/:id",
+            get(handler_get_vector)
+                .head(handler_vector_exists)
+                .put(handler_update_vector)
+                .delete(handler_delete_vector),
+        )
+        .route(
+            "/api/vectors/:id",
+            get(handler_get_vector)
+                .head(handler_vector_exists)
+                .put(handler_update_vector)
+                .delete(handler_delete_vector),
+        )
         .route("/search", post(handler_search))
         .route("/stats", get(handler_stats))
+        // Collections
+        .route(
+            "/api/collections",
+            get(handler_list_collections).post(handler_create_collection),
+        )
+        .route(
+            "/api/collections/:name",
+            get(handler_get_collection).delete(handler_delete_collection),
+        )
+        .route(
+            "/api/collections/:name/rename",
+            post(handler_rename_collection),
+        )
+        .route("/api/collections/:name/points", post(handler_upsert_points))
+        .route(
+            "/api/collections/:name/transactions",
+            post(handler_transaction),
+        )
+        .route(
+            "/api/collections/:name/index",
+            get(handler_index_info).post(handler_build_index),
+        )
+        .route(
+            "/api/collections/:name/index/relayout",
+            post(handler_relayout_index),
+        )
+        .route(
+            "/api/collections/:name/stats",
+            get(handler_collection_stats),
+        )
+        .route(
+            "/api/collections/:name/calibration",
+            get(handler_get_calibration)
+                .post(handler_calibrate)
+                .delete(handler_clear_calibration),
+        )
+        .route("/api/collections/:name/export", get(handler_export))
+        .route(
+            "/api/collections/:name/import",
+            // Streamed line by line, so the default 2 MB cap doesn't apply
+            post(handler_import).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/collections/:name/uploads",
+            post(handler_create_upload),
+        )
+        .route(
+            "/api/uploads/:id",
+            get(handler_get_upload)
+                .patch(handler_append_upload)
+                .delete(handler_delete_upload)
+                .layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/uploads/:id/complete", post(handler_complete_upload))
+        .route(
+            "/api/collections/:name/shadow",
+            put(handler_set_shadow).delete(handler_clear_shadow),
+        )
+        .route(
+            "/api/collections/:name/shadow/compare",
+            post(handler_compare_shadow),
+        )
+        .route(
+            "/api/collections/:name/points/:id",
+            get(handler_get_point)
+                .put(handler_put_point)
+                .delete(handler_delete_point),
+        )
+        .route(
+            "/api/collections/:name/points/:id/history",
+            get(handler_point_history),
+        )
+        .route(
+            "/api/collections/:name/points/:id/rollback",
+            post(handler_rollback_point),
+        )
+        .route(
+            "/api/collections/:name/search",
+            post(handler_collection_search),
+        )
+        .route(
+            "/api/collections/:name/search/sparse",
+            post(handler_sparse_search),
+        )
+        .route(
+            "/api/collections/:name/search/hybrid",
+            post(handler_hybrid_search),
+        )
+        .route(
+            "/api/collections/:name/update_by_filter",
+            post(handler_update_by_filter),
+        )
+        .route("/api/collections/:name/normalize", post(handler_normalize))
+        .route("/api/search/multi", post(handler_multi_search))
+        .route("/api/search/batch", post(handler_batch_search))
+        .route("/api/search/stream", get(handler_search_stream))
+        .route("/api/search/templates", get(handler_list_templates))
+        .route(
+            "/api/search/template/:name",
+            get(handler_get_template)
+                .put(handler_put_template)
+                .post(handler_template_search)
+                .delete(handler_delete_template),
+        )
+        .route("/api/compute/arith", post(handler_arith))
+        // Trash (soft-deleted collections)
+        .route("/api/trash", get(handler_list_trash))
+        .route("/api/trash/:name", delete(handler_purge_trash))
+        .route("/api/trash/:name/restore", post(handler_restore_trash))
+        // Aliases
+        .route(
+            "/api/aliases",
+            get(handler_list_aliases).post(handler_create_alias),
+        )
+        .route(
+            "/api/aliases/:name",
+            get(handler_get_alias).delete(handler_delete_alias),
+        )
+        // Background jobs
+        .route("/api/jobs", get(handler_list_jobs))
+        .route("/api/jobs/:id", get(handler_get_job))
+        // Read collections' snapshot segments on first access
+        .layer(middleware::from_fn_with_state(state.clone(), load_segments))
         // Attach shared state
-        .with_state(state)
-        // Middleware: automatic request logging
-        .layer(TraceLayer::new_for_http());
+        .with_state(state.clone());
 
-    // 4. Bind and serve with graceful shutdown
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    tracing::info!("🚀 Listening on http://{}", addr);
+    // Diagnostics and profiling (behind VECTORDB_ADMIN_KEY, if set) and
+    // metrics: on their own listener if there's an admin address, else
+    // next to the public API
+    let admin = admin_routes(admin_key.clone())
+        .route("/metrics", get(handler_metrics))
+        .with_state(state.clone());
+    let (app, admin) = match config.admin_addr {
+        Some(addr) => (app, Some((addr, admin))),
+        None => (app.merge(admin), None),
+    };
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    // Optional: sample search requests into a replayable query log
+    let app = match config.query_log.clone() {
+        Some(config) => {
+            tracing::info!(
+                "Logging {:.2}% of searches to {}",
+                config.sample_rate * 100.0,
+                config.path.display()
+            );
+            let log = Arc::new(QueryLog::open(config).expect("Failed to open query log"));
+            app.layer(middleware::from_fn_with_state(log, log_queries))
+        }
+        None => app,
+    };
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+    // Optional: require a key (api_keys) or access token ([oidc]) allowing
+    // each request
+    let keys = config.api_keys.clone();
+    let oidc = match config.oidc.clone() {
+        Some(oidc) => {
+            let verifier = Verifier::load(oidc)
+                .await
+                .expect("Failed to load the OIDC signing keys");
+            tracing::info!(
+                "Accepting access tokens from {} (keys from {})",
+                verifier.config().issuer,
+                verifier.source()
+            );
+            let verifier = Arc::new(verifier);
+            tokio::spawn(jwks_refresher(verifier.clone()));
+            Some(verifier)
+        }
+        None => None,
+    };
+    let check = if keys.is_some() || oidc.is_some() {
+        if let Some(keys) = &keys {
+            tracing::info!("Requiring one of {} scoped API keys", keys.len());
+        }
+        Some(KeyCheck {
+            keys: keys.map(Arc::new),
+            oidc,
+            admin_key: admin_key.clone(),
+        })
+    } else {
+        None
+    };
+    let app = with_request_layers(app, check.clone(), usage.clone());
+    let admin = admin.map(|(addr, admin)| {
+        let layered = with_request_layers(admin, check.clone(), usage.clone());
+        (addr, layered)
+    });
+
+    // One shutdown signal, broadcast to every listener
+    let (stop, _) = tokio::sync::watch::channel(false);
+    let stopped = || {
+        let mut stop = stop.subscribe();
+        async move {
+            let _ = stop.wait_for(|&stopping| stopping).await;
+        }
+    };
+
+    // Optional: the gRPC API on its own port, stopped along with HTTP so
+    // nothing writes after the snapshot below
+    #[cfg(feature = "grpc")]
+    let grpc_server =
+        grpc_api::start(config.grpc_addr, state.clone(), check.clone(), stopped()).await;
+
+    // 5. Bind and serve with graceful shutdown
+    //    (port 0 = pick a free port); every listener stops on the same
+    //    signal and drains at the same time
+    let admin_server = match admin {
+        Some((addr, admin)) => {
+            // With [admin_tls], only clients presenting a certificate from
+            // the configured CA get past the handshake
+            let tls = config.admin_tls.as_ref().map(|tls| {
+                tls.acceptor()
+                    .expect("Invalid admin TLS certificate, key or client CA")
+            });
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .expect("Failed to bind the admin listener");
+            tracing::info!(
+                "Admin API listening on {}://{}{}",
+                if tls.is_some() { "https" } else { "http" },
+                listener.local_addr().unwrap(),
+                if tls.is_some() {
+                    " (client certificates required)"
+                } else {
+                    ""
+                }
+            );
+            Some(tokio::spawn(serve(
+                listener,
+                admin,
+                tls,
+                stopped(),
+                config.drain_timeout,
+            )))
+        }
+        None => None,
+    };
+    let listener = tokio::net::TcpListener::bind(config.addr).await.unwrap();
+    tracing::info!("🚀 Listening on http://{}", listener.local_addr().unwrap());
+
+    let signal = async {
+        shutdown_signal().await;
+        stop.send_replace(true);
+    };
+    let admin_drain = async {
+        if let Some(server) = admin_server {
+            let _ = server.await;
+        }
+    };
+    // tonic waits for clients to hang up, and an idle channel never does:
+    // give it the same drain budget as HTTP
+    #[cfg(feature = "grpc")]
+    let grpc_drain = async {
+        let mut server = grpc_server;
+        let abort = server.abort_handle();
+        tokio::select! {
+            _ = &mut server => return,
+            () = stopped() => {}
+        }
+        if tokio::time::timeout(config.drain_timeout, server)
+            .await
+            .is_err()
+        {
+            tracing::warn!("gRPC drain timed out after {:?}", config.drain_timeout);
+            abort.abort();
+        }
+    };
+    #[cfg(not(feature = "grpc"))]
+    let grpc_drain = async {};
+    tokio::join!(
+        signal,
+        serve(listener, app, None, stopped(), config.drain_timeout),
+        admin_drain,
+        grpc_drain,
+    );
+
+    // 6. Persist everything for the next start: fsync the log first, so
+    //    acknowledged writes are durable even if the snapshot fails
+    if let Some(Err(e)) = state.read().await.wal.as_ref().map(Wal::sync) {
+        tracing::error!("Failed to sync write-ahead log: {}", e);
+    }
+    if let Err(e) = save_state(&state, &data_dir).await {
+        tracing::error!("Failed to save snapshot to {}: {}", data_dir.display(), e);
+    }
+    if let Err(e) = usage.flush() {
+        tracing::error!("Failed to save usage rollups: {}", e);
+    }
 
     tracing::info!("Server shut down gracefully");
+    telemetry.shutdown();
 }
 
-/// Wait for Ctrl+C to initiate graceful shutdown.
-async fn shutdown_signal() {
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to install Ctrl+C handler");
-    tracing::info!("Shutdown signal received, finishing in-flight requests...");
-}
+/// The middleware every request goes through, on either listener: the
+/// key or token check (if `check` is set), usage accounting, metrics,
+/// lock labels and the request span
+fn with_request_layers(app: Router, check: Option<KeyCheck>, usage: Arc<UsageLog>) -> Router {
+    let app = match check {
+        Some(check) => app.layer(middleware::from_fn_with_state(check, authorize)),
+        None => app,
+    };
 
-// ═══════════════════════════════════════════════════════════════════════════
-// HANDLERS
-// ═══════════════════════════════════════════════════════════════════════════
+    // Middleware: count every request against its API key
+    let app = app.layer(middleware::from_fn_with_state(usage, track_usage));
 
-/// Home page — returns a simple HTML overview of available endpoints.
-async fn handler_home() -> Html<&'static str> {
-    Html(
-        r#"
-        <!DOCTYPE html>
-        <html>
-        <head><title>VectorDB</title></head>
-        <body>
-            <h1>🦀 VectorDB</h1>
-            <p>A vector database built from scratch in Rust.</p>
-            <h2>Endpoints:</h2>
-            <ul>
-                <li>GET /health — Health check</li>
-                <li>POST /vectors — Insert a vector</li>
-                <li>GET /vectors/:id — Get a vector by ID</li>
-                <li>POST /search — Search for similar vectors</li>
-                <li>GET /stats — Server statistics</li>
-            </ul>
-        </body>
-        </html>
-    "#,
-    )
-}
+    // Middleware: per-route request counts and latency for /metrics
+    let app = app.layer(middleware::from_fn(track_metrics));
 
-/// Health check — for load balancers and k8s probes.
-async fn handler_health() -> &'static str {
-    "OK"
+    // Middleware: label state-lock acquisitions with the request
+    let app = app.layer(middleware::from_fn(label_lock_operations));
+
+    // Middleware: automatic request logging, one span per request (at INFO,
+    // so storage spans and lock waits nest under it in exported traces)
+    app.layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
 }
 
-/// Insert a new vector into the database.
-///
-/// POST /vectors
-/// Body: { "id": "doc_001", "vector": { "data": [0.1, 0.2], "metadata": {} } }
-async fn handler_insert(
-    State(state): State<SharedState>,
-    Json(req): Json<InsertRequest>,
-) -> Result<Json<serde_json::Value>, ApiError> {
-    // Validate input
-    if req.id.is_empty() {
-        return Err(ApiError::bad_request("Vector ID cannot be empty"));
+/// Load the snapshot in `data_dir` (if any) into `state`, replay the
+/// write-ahead log on top of it, and keep the log open for new writes.
+fn restore_state(
+    state: &mut AppState,
+    data_dir: &FsPath,
+    wal_sync: SyncPolicy,
+) -> vectordb::models::Result<()> {
+    let snapshot = snapshot::load_lazily(&DiskStorage, data_dir)?;
+    let vector_count = snapshot.vectors.len();
+    let collection_count = snapshot.collections.len() + snapshot.unloaded.len();
+
+    let now = Instant::now();
+    for (id, vector) in snapshot.vectors {
+        state.cold.remove(&id);
+        state.cold.touch(&id, now);
+        state.vectors.insert(id, vector);
     }
-    if req.vector.data.is_empty() {
-        return Err(ApiError::bad_request("Vector data cannot be empty"));
+    let id_counters: HashMap<_, _> = snapshot.id_counters.into_iter().collect();
+    for (info, points) in snapshot.collections {
+        let mut collection = Collection::restore(&info, points)?;
+        if let Some(&next) = id_counters.get(&info.name) {
+            collection.resume_ids(next);
+        }
+        state.collections.insert(info.name, collection);
+    }
+    //    Live collections' segments are only registered here, and read the
+    //    first time something reaches them
+    for (info, path) in snapshot.unloaded {
+        let unloaded = Unloaded {
+            segment: state.segments.register(path),
+            next_id: id_counters.get(&info.name).copied(),
+            info,
+        };
+        state.unloaded.insert(unloaded.info.name.clone(), unloaded);
+    }
+    let trash_id_counters: HashMap<_, _> = snapshot.trash_id_counters.into_iter().collect();
+    for (deleted_at, info, points) in snapshot.trash {
+        let mut collection = Collection::restore(&info, points)?;
+        if let Some(&next) = trash_id_counters.get(&info.name) {
+            collection.resume_ids(next);
+        }
+        state.trash.put(collection, deleted_at);
+    }
+    for config in snapshot.aliases {
+        let alias = Alias::from_request(&config)?;
+        state.aliases.insert(alias.name.clone(), alias);
+    }
+    for (name, config) in snapshot.templates {
+        let template = QueryTemplate::from_request(&name, &config)?;
+        state.templates.insert(name, template);
     }
 
-    let dimension = req.vector.dimension();
+    if vector_count + collection_count > 0 {
+        tracing::info!(
+            "Loaded {} vectors and {} collections ({} not read until used) from {}",
+            vector_count,
+            collection_count,
+            state.unloaded.len(),
+            data_dir.display()
+        );
+    }
 
-    // Write to shared state — lock scoped to this block
-    {
-        let mut state = state.write().await;
-        state.vectors.insert(req.id.clone(), req.vector);
-        state.request_count += 1;
-    } // Lock released here
+    std::fs::create_dir_all(data_dir)?;
+    let (wal, records) = Wal::open(
+        Arc::new(DiskStorage),
+        data_dir.join(wal::WAL_FILE),
+        wal_sync,
+    )?;
+    if !records.is_empty() {
+        tracing::info!(
+            "Replaying {} write-ahead log records from {}",
+            records.len(),
+            wal.path().display()
+        );
+    }
 
-    tracing::info!("Inserted vector '{}' ({} dims)", req.id, dimension);
+    //    Memtables flushed since the snapshot, each applied where it was
+    //    sealed in the WAL's order, to the collection that had its name
+    //    then. Untagged (older) flushes go first, as they used to; tagged
+    //    ones the snapshot already holds are skipped.
+    let flushes = memtable::load(&DiskStorage, data_dir)?;
+    let base = wal.base_sequence();
+    let count = flushes.len();
+    let (untagged, tagged): (Vec<_>, Vec<_>) = flushes.into_iter().partition(|f| f.seq == 0);
+    let mut flushes = tagged.into_iter().filter(|f| f.seq > base).peekable();
+    let mut applied = 0;
+    let mut apply = |state: &mut AppState, flush: memtable::Flush| {
+        state.load_collection(&flush.collection)?;
+        if let Some(collection) = state.collections.get_mut(&flush.collection) {
+            applied += collection.apply_flush(flush);
+        }
+        Ok::<_, VectorDbError>(())
+    };
+    for flush in untagged {
+        apply(state, flush)?;
+    }
+    for (seq, record) in records {
+        while let Some(flush) = flushes.next_if(|f| f.seq < seq) {
+            apply(state, flush)?;
+        }
+        replay_record(state, record)?;
+    }
+    for flush in flushes {
+        apply(state, flush)?;
+    }
+    if count > 0 {
+        tracing::info!("Applied {} writes from {} flushed segments", applied, count);
+    }
+    state.rebuild_id_filter();
+    for name in state.trash.purge_expired(SystemClock.unix_secs()) {
+        tracing::info!("Purged trashed collection '{}' (retention ended)", name);
+    }
+    tracing::info!("Write-ahead log sync policy: {:?}", wal.policy());
+    state.wal = Some(wal);
+    Ok(())
+}
 
-    Ok(Json(serde_json::json!({
-        "status": "inserted",
-        "id": req.id,
-        "dimension": dimension
-    })))
+/// Re-apply one logged write during startup, loading the collection it
+/// touches first.
+fn replay_record(state: &mut AppState, record: WalRecord) -> vectordb::models::Result<()> {
+    let not_found = |name: &str| VectorDbError::NotFound(format!("collection '{}'", name));
+    let touched = match &record {
+        WalRecord::UpdateCollection(info) => Some(info.name.as_str()),
+        WalRecord::RenameCollection { from: name, .. }
+        | WalRecord::TrashCollection { name, .. }
+        | WalRecord::RestoreCollection(name)
+        | WalRecord::IdCounter {
+            collection: name, ..
+        }
+        | WalRecord::Insert {
+            collection: Some(name),
+            ..
+        }
+        | WalRecord::Delete {
+            collection: Some(name),
+            ..
+        } => Some(name.as_str()),
+        _ => None,
+    };
+    if let Some(name) = touched {
+        state.load_collection(name)?;
+    }
+    match record {
+        WalRecord::CreateCollection(info) => {
+            if let Entry::Vacant(slot) = state.collections.entry(info.name.clone()) {
+                slot.insert(Collection::restore(&info, Vec::new())?);
+            }
+        }
+        WalRecord::UpdateCollection(info) => {
+            let collection = state
+                .collections
+                .get_mut(&info.name)
+                .ok_or_else(|| not_found(&info.name))?;
+            collection.reconfigure(&info);
+        }
+        WalRecord::RenameCollection { from, to } => state.rename_collection(&from, &to)?,
+        WalRecord::TrashCollection { name, deleted_at } => {
+            state.trash_collection(&name, deleted_at)?
+        }
+        WalRecord::RestoreCollection(name) => state.restore_collection(&name)?,
+        WalRecord::PurgeCollection(name) => {
+            state.trash.take(&name);
+        }
+        WalRecord::SetAlias(config) => {
+            let alias = Alias::from_request(&config)?;
+            state.aliases.insert(alias.name.clone(), alias);
+        }
+        WalRecord::RemoveAlias(name) => {
+            state.aliases.remove(&name);
+        }
+        WalRecord::SetTemplate { name, config } => {
+            let template = QueryTemplate::from_request(&name, &config)?;
+            state.templates.insert(name, template);
+        }
+        WalRecord::RemoveTemplate(name) => {
+            state.templates.remove(&name);
+        }
+        WalRecord::IdCounter { collection, next } => {
+            state
+                .collections
+                .get_mut(&collection)
+                .ok_or_else(|| not_found(&collection))?
+                .resume_ids(next);
+        }
+        WalRecord::Insert {
+            collection: None,
+            id,
+            vector,
+        } => {
+            state.cold.remove(&id);
+            state.cold.touch(&id, Instant::now());
+            state.vectors.insert(id, vector);
+        }
+        WalRecord::Insert {
+            collection: Some(name),
+            id,
+            vector,
+        } => {
+            let collection = state
+                .collections
+                .get_mut(&name)
+                .ok_or_else(|| not_found(&name))?;
+            collection.insert_prepared(id, vector);
+        }
+        WalRecord::Delete {
+            collection: None,
+            id,
+        } => {
+            state.cold.remove(&id);
+            state.vectors.remove(&id);
+        }
+        WalRecord::Delete {
+            collection: Some(name),
+            id,
+        } => {
+            let collection = state
+                .collections
+                .get_mut(&name)
+                .ok_or_else(|| not_found(&name))?;
+            collection.delete(&id);
+        }
+    }
+    Ok(())
 }
 
-/// Get a vector by its ID.
-///
-/// GET /vectors/:id
-async fn handler_get_vector(
-    State(state): State<SharedState>,
-    Path(id): Path<String>,
-) -> Result<Json<Vector>, ApiError> {
+/// Write every vector (both tiers) and collection to `data_dir`.
+async fn save_state(state: &SharedState, data_dir: &FsPath) -> std::io::Result<()> {
+    std::fs::create_dir_all(data_dir)?;
+    let flush_lock = state.read().await.flush_lock.clone();
+    let _flushing = flush_lock.lock().await;
     let state = state.read().await;
 
-    match state.vectors.get(&id) {
-        Some(vector) => Ok(Json(vector.clone())),
-        None => Err(ApiError::not_found(format!("Vector '{}' not found", id))),
+    // Cold vectors go to their own segment, still quantized, and before
+    // the snapshot: a crash in between leaves the old snapshot and the log,
+    // whose hot copies win over the cold ones on the next start
+    let cold_store = match &state.cold_store {
+        Some(store) => store.clone(),
+        None => Arc::new(ObjectStore::local(Arc::new(DiskStorage), data_dir)),
+    };
+    state.cold.save(&cold_store).await?;
+    let vectors: Vec<(String, Vector)> = state
+        .vectors
+        .iter()
+        .map(|(id, v)| (id.clone(), v.clone()))
+        .collect();
+
+    let collections = state
+        .collections
+        .values()
+        .map(|c| {
+            let points = c
+                .points()
+                .map(|(id, v)| (id.clone(), v.into_owned()))
+                .collect();
+            (c.info(), points)
+        })
+        .collect();
+
+    let trash = state
+        .trash
+        .iter()
+        .map(|t| {
+            let c = &t.collection;
+            let points = c
+                .points()
+                .map(|(id, v)| (id.clone(), v.into_owned()))
+                .collect();
+            (t.deleted_at, c.info(), points)
+        })
+        .collect();
+
+    let mut aliases: Vec<_> = state.aliases.values().map(Alias::config).collect();
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    let templates = state
+        .templates
+        .iter()
+        .map(|(name, t)| (name.clone(), t.config()))
+        .collect();
+    let id_counters = state
+        .collections
+        .values()
+        .filter_map(|c| Some((c.name.clone(), c.id_counter()?)))
+        .chain(
+            state
+                .unloaded
+                .iter()
+                .filter_map(|(name, u)| Some((name.clone(), u.next_id?))),
+        )
+        .collect();
+    // Unloaded collections' files are still what the last snapshot wrote
+    let unloaded = state
+        .unloaded
+        .values()
+        .filter_map(|u| Some((u.info.clone(), state.segments.path(u.segment)?)))
+        .collect();
+    let trash_id_counters = state
+        .trash
+        .iter()
+        .filter_map(|t| Some((t.collection.name.clone(), t.collection.id_counter()?)))
+        .collect();
+
+    let snapshot = Snapshot {
+        vectors,
+        collections,
+        trash,
+        aliases,
+        templates,
+        id_counters,
+        trash_id_counters,
+        unloaded,
+    };
+    snapshot::save(&DiskStorage, data_dir, &snapshot)?;
+    // Everything logged is in the snapshot now (writers need the write
+    // lock, so nothing was appended while we held the read lock)
+    if let Some(wal) = &state.wal {
+        wal.reset()?;
     }
+    // ...and so is everything flushed
+    memtable::remove_all(&DiskStorage, data_dir)?;
+    tracing::info!(
+        "Saved {} vectors ({} cold) and {} collections to {}",
+        snapshot.vectors.len() + state.cold.len(),
+        state.cold.len(),
+        snapshot.collections.len() + snapshot.unloaded.len(),
+        data_dir.display()
+    );
+    Ok(())
 }
 
-/// Search for similar vectors.
-///
-/// POST /search
-/// Body: { "data": [0.1, 0.2, 0.3] }
-async fn handler_search(
-    State(state): State<SharedState>,
-    Json(query): Json<Vector>,
-) -> Result<Json<Vec<SearchResult>>, ApiError> {
-    // Validate input
-    if query.data.is_empty() {
-        return Err(ApiError::bad_request("Vector data cannot be empty"));
+/// Background task: with SyncPolicy::Periodic, fsync the write-ahead log
+/// every `interval` so a quiet server doesn't hold unsynced records.
+async fn wal_syncer(state: SharedState, interval: std::time::Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let state = state.read().await;
+        if let Some(Err(e)) = state.wal.as_ref().map(Wal::sync) {
+            tracing::error!("Failed to sync write-ahead log: {}", e);
+        }
     }
+}
 
-    let state = state.read().await;
+/// Wait for SIGINT (Ctrl+C) or SIGTERM (what Kubernetes and systemd send)
+/// to initiate graceful shutdown.
+async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+        "SIGINT"
+    };
 
-    tracing::info!(
-        "Search: {} dims across {} stored vectors",
-        query.dimension(),
-        state.vectors.len()
-    );
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+        "SIGTERM"
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<&str>();
 
-    // TODO: Real similarity search (Post #12).
-    // For now, return dummy results.
-    let results = vec![
-        SearchResult {
-            id: "doc_001".into(),
-            score: 0.95,
-        },
-        SearchResult {
-            id: "doc_002".into(),
-            score: 0.87,
-        },
-        SearchResult {
-            id: "doc_003".into(),
-            score: 0.72,
-        },
-    ];
+    let signal = tokio::select! {
+        signal = interrupt => signal,
+        signal = terminate => signal,
+    };
+    tracing::info!("{} received, finishing in-flight requests...", signal);
+}
 
-    Ok(Json(results))
+/// Serve HTTP/1 connections from `listener` until `shutdown` completes,
+/// then wait up to `drain_timeout` for open connections to finish their
+/// requests. Idle keep-alive connections close right away; requests still
+/// running at the deadline are cut off when the process exits.
+///
+/// With `tls`, each connection is served once its handshake completes
+/// (within tls::HANDSHAKE_TIMEOUT); failed handshakes are dropped.
+///
+/// Each connection holds a socket permit from the fd budget for as long as
+/// it's open. Past the budget, new connections are closed as soon as
+/// they're accepted: the client sees a reset it can retry, while the
+/// storage files and segments keep the descriptors they need.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    tls: Option<TlsAcceptor>,
+    shutdown: impl std::future::Future<Output = ()>,
+    drain_timeout: std::time::Duration,
+) {
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    let mut shutdown = std::pin::pin!(shutdown);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // EMFILE and friends: back off instead of spinning
+                    tracing::error!("Failed to accept a connection: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            () = &mut shutdown => break,
+        };
+        let Some(permit) = fds::try_acquire(FdKind::Socket) else {
+            let refused = fds::usage().refused_sockets;
+            if refused.is_power_of_two() {
+                tracing::warn!(
+                    "Closing new connections: the fd budget allows {} sockets ({} refused so far)",
+                    fds::BUDGET.socket_cap(),
+                    refused
+                );
+            }
+            continue;
+        };
+
+        let service = hyper_util::service::TowerToHyperService::new(app.clone());
+        let http = hyper::server::conn::http1::Builder::new();
+        let watcher = graceful.watcher();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let served = match tls {
+                None => {
+                    let io = hyper_util::rt::TokioIo::new(stream);
+                    watcher.watch(http.serve_connection(io, service)).await
+                }
+                Some(tls) => {
+                    match tokio::time::timeout(tls::HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let io = hyper_util::rt::TokioIo::new(stream);
+                            watcher.watch(http.serve_connection(io, service)).await
+                        }
+                        Ok(Err(e)) => {
+                            tracing::debug!("TLS handshake failed: {}", e);
+                            Ok(())
+                        }
+                        Err(_) => {
+                            tracing::debug!("TLS handshake timed out");
+                            Ok(())
+                        }
+                    }
+                }
+            };
+            if let Err(e) = served {
+                tracing::debug!("Connection closed with an error: {}", e);
+            }
+            drop(permit);
+        });
+    }
+
+    // Stop accepting: new connections are refused from here on
+    drop(listener);
+    let open = graceful.count();
+    if open > 0 {
+        tracing::info!(
+            "Waiting up to {:?} for {} open connections",
+            drain_timeout,
+            open
+        );
+    }
+    if tokio::time::timeout(drain_timeout, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "Drain timed out after {:?} with requests still in flight; flushing anyway",
+            drain_timeout
+        );
+    }
 }
 
-/// Get server statistics.
+/// Middleware: attribute every state-lock acquisition in this request to
+/// it (and to the collection it targets) for lock diagnostics.
+async fn label_lock_operations(req: Request, next: Next) -> Response {
+    let op = Operation::from_request(req.method().as_str(), req.uri().path());
+    locks::with_operation(op, next.run(req)).await
+}
+
+/// Periodically log state-lock holders past the warning threshold.
 ///
-/// GET /stats
-async fn handler_stats(State(state): State<SharedState>) -> Json<serde_json::Value> {
-    let state = state.read().await;
+/// Catches writers that never release: those would otherwise show up only
+/// as searches hanging behind them, with nothing in the logs.
+async fn lock_watchdog(state: SharedState) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(250));
+    loop {
+        ticker.tick().await;
+        state.warn_long_held();
+    }
+}
 
-    Json(serde_json::json!({
-        "vector_count": state.vectors.len(),
-        "request_count": state.request_count,
-        "status": "running"
-    }))
+/// Largest request body copied into the query log
+const MAX_LOGGED_BODY: usize = 16 * 1024 * 1024;
+
+/// Middleware: record a sample of search requests in the query log.
+///
+/// The body has to be buffered to be logged, so it's read into memory and
+/// handed on to the handler unchanged.
+async fn log_queries(State(log): State<Arc<QueryLog>>, req: Request, next: Next) -> Response {
+    if req.method() != Method::POST || !is_search_path(req.uri().path()) || !log.should_sample() {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path().to_string();
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_LOGGED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return ApiError::bad_request(format!("Failed to read body: {}", e)).into_response()
+        }
+    };
+
+    if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+        if let Err(e) = log.record(&path, json) {
+            tracing::warn!("Query log write failed: {}", e);
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+/// Middleware: count and time each request by method, matched route and
+/// status (see src/monitoring.rs).
+async fn track_metrics(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    monitoring::record_request(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        start.elapsed(),
+    );
+    response
+}
+
+/// Drain the metrics recorder's histogram buffers now and then, so they
+/// don't grow between scrapes.
+async fn metrics_upkeep(handle: PrometheusHandle) {
+    let mut ticker = tokio::time::interval(monitoring::UPKEEP_INTERVAL);
+    loop {
+        ticker.tick().await;
+        handle.run_upkeep();
+    }
+}
+
+/// Middleware: add each request to its API key's usage for the day.
+///
+/// Bodies of known length are counted up front; streamed ones (imports,
+/// exports, upload chunks) are counted chunk by chunk as they pass
+/// through. Searches and inserts only count when they succeed.
+async fn track_usage(State(usage): State<Arc<UsageLog>>, req: Request, next: Next) -> Response {
+    let key =
+        api_key(req.headers()).map_or_else(|| usage::ANONYMOUS.to_string(), usage::fingerprint);
+    let kind = request_kind(req.method(), req.uri().path());
+
+    let (parts, body) = req.into_parts();
+    let (body, bytes_in) = count_body(body, usage.clone(), key.clone(), true);
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let kind = if response.status().is_success() {
+        kind
+    } else {
+        RequestKind::Other
+    };
+    let (parts, body) = response.into_parts();
+    let (body, bytes_out) = count_body(body, usage.clone(), key.clone(), false);
+    usage.record(
+        &key,
+        UsageCounters::request(kind, bytes_in, bytes_out),
+        SystemClock.unix_secs(),
+    );
+    Response::from_parts(parts, body)
+}
+
+/// The body's length if it's known up front (0 otherwise), and a body that
+/// counts its chunks into `usage` as they're read if it isn't
+fn count_body(body: Body, usage: Arc<UsageLog>, key: String, incoming: bool) -> (Body, u64) {
+    use axum::body::HttpBody;
+    if let Some(len) = body.size_hint().exact() {
+        return (body, len);
+    }
+    let counted = body.into_data_stream().inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            let len = bytes.len() as u64;
+            let counters = if incoming {
+                UsageCounters::bytes(len, 0)
+            } else {
+                UsageCounters::bytes(0, len)
+            };
+            usage.record(&key, counters, SystemClock.unix_secs());
+        }
+    });
+    (Body::from_stream(counted), 0)
+}
+
+/// The API key a request presents: `X-API-Key`, or `Authorization: Bearer`
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    let key = match headers.get("x-api-key") {
+        Some(value) => value.to_str().ok()?,
+        None => headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?,
+    };
+    Some(key.trim()).filter(|k| !k.is_empty())
+}
+
+/// How a request counts in the usage rollups
+fn request_kind(method: &Method, path: &str) -> RequestKind {
+    let inserts = match *method {
+        Method::POST => {
+            path == "/vectors"
+                || (path.starts_with("/api/collections/")
+                    && (path.ends_with("/points")
+                        || path.ends_with("/import")
+                        || path.ends_with("/transactions")))
+                || (path.starts_with("/api/uploads/") && path.ends_with("/complete"))
+        }
+        Method::PUT => path.starts_with("/api/collections/") && path.contains("/points/"),
+        _ => false,
+    };
+    if *method == Method::POST && is_search_path(path) {
+        RequestKind::Search
+    } else if inserts {
+        RequestKind::Insert
+    } else {
+        RequestKind::Other
+    }
+}
+
+/// Background task: flush collection memtables that reached `policy`'s
+/// size or age to immutable segments (see storage/memtable.rs), then drop
+/// the WAL records they hold.
+async fn memtable_flusher(state: SharedState, policy: FlushPolicy) {
+    let mut ticker = tokio::time::interval(memtable::CHECK_INTERVAL.min(policy.max_age));
+    loop {
+        ticker.tick().await;
+        let (flush_lock, data_dir) = {
+            let state = state.read().await;
+            (state.flush_lock.clone(), state.data_dir.clone())
+        };
+        let _flushing = flush_lock.lock().await;
+
+        // Seal under the write lock, at the WAL's current sequence (writers
+        // log under it too), and write the files without it
+        let (seq, flushes): (u64, Vec<_>) = {
+            let mut state = state.write().await;
+            let seq = state.wal.as_ref().map_or(0, Wal::sequence);
+            let now = Instant::now();
+            let flushes = state
+                .collections
+                .values_mut()
+                .filter(|c| c.memtable().is_due(&policy, now))
+                .filter_map(|c| c.seal_memtable(seq))
+                .collect();
+            (seq, flushes)
+        };
+        let mut durable = Vec::new();
+        for flush in flushes {
+            let start = Instant::now();
+            let dir = data_dir.clone();
+            let (name, points, deleted, complete) = (
+                flush.collection.clone(),
+                flush.points.len(),
+                flush.deleted.len(),
+                flush.complete,
+            );
+            let written =
+                tokio::task::spawn_blocking(move || memtable::write(&DiskStorage, &dir, &flush))
+                    .await;
+            match written {
+                Ok(Ok(path)) => {
+                    monitoring::record_memtable_flush(&name, points + deleted, start.elapsed());
+                    if complete {
+                        durable.push(name.clone());
+                    }
+                    tracing::info!(
+                        "Flushed memtable of '{}' ({} points, {} deletes) to {}",
+                        name,
+                        points,
+                        deleted,
+                        path.display()
+                    );
+                }
+                // The WAL still has these writes until the next snapshot
+                Ok(Err(e)) => tracing::error!("Failed to flush memtable of '{}': {}", name, e),
+                Err(e) => tracing::error!("Memtable flush of '{}' panicked: {}", name, e),
+            }
+        }
+
+        // Their records aren't needed for replay any more
+        if durable.is_empty() {
+            continue;
+        }
+        let state = state.read().await;
+        if let Some(wal) = &state.wal {
+            let names: Vec<&str> = durable.iter().map(String::as_str).collect();
+            match wal.drop_flushed(seq, &names) {
+                Ok(dropped) => tracing::debug!("Dropped {} flushed WAL records", dropped),
+                Err(e) => tracing::error!("Failed to drop flushed WAL records: {}", e),
+            }
+        }
+    }
+}
+
+/// How often collections are checked against their auto_index threshold
+const AUTO_INDEX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Background task: start building an HNSW graph for each collection that
+/// reached its auto_index threshold (see engine/index.rs). Until the graph
+/// is installed the collection keeps answering searches exactly.
+async fn index_auto_builder(state: SharedState) {
+    let mut ticker = tokio::time::interval(AUTO_INDEX_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let due = {
+            let state = state.read().await;
+            state
+                .collections
+                .values()
+                .any(|c| c.wants_index().is_some())
+        };
+        if !due {
+            continue;
+        }
+
+        let mut guard = state.write().await;
+        let due: Vec<_> = guard
+            .collections
+            .iter()
+            .filter_map(|(name, c)| Some((name.clone(), c.wants_index()?)))
+            .collect();
+        for (name, config) in due {
+            let count = guard.collections.get(&name).map_or(0, Collection::len);
+            match start_index_build(&state, &mut guard, &name, config) {
+                Ok(job) => tracing::info!(
+                    "Job {}: '{}' reached its auto_index threshold ({} points)",
+                    job.id,
+                    name,
+                    count
+                ),
+                Err(e) => tracing::error!("Failed to start an index build on '{}': {}", name, e),
+            }
+        }
+    }
+}
+
+/// Background task: write usage rollups to disk every FLUSH_INTERVAL.
+async fn usage_flusher(usage: Arc<UsageLog>) {
+    let mut ticker = tokio::time::interval(usage::FLUSH_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = usage.flush() {
+            tracing::error!("Failed to save usage rollups: {}", e);
+        }
+    }
+}
+
+/// Search endpoints whose requests are worth replaying
+fn is_search_path(path: &str) -> bool {
+    path == "/search"
+        || path.starts_with("/api/search/")
+        || (path.starts_with("/api/collections/")
+            && (path.ends_with("/search")
+                || path.ends_with("/search/sparse")
+                || path.ends_with("/search/hybrid")))
+}
+
+/// Periodically purge trashed collections whose retention has run out.
+async fn trash_purger(state: SharedState) {
+    let mut ticker = tokio::time::interval(trash::PURGE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let mut state = state.write().await;
+        for name in state.trash.purge_expired(SystemClock.unix_secs()) {
+            tracing::info!("Purged trashed collection '{}' (retention ended)", name);
+        }
+    }
+}
+
+/// Periodically move vectors that haven't been accessed into the cold tier.
+async fn tiering_sweep(state: SharedState) {
+    let interval = state.read().await.cold.policy.sweep_interval;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let mut state = state.write().await;
+        let AppState {
+            vectors,
+            cold,
+            access,
+            ..
+        } = &mut *state;
+        let demoted = cold.sweep(vectors, access, Instant::now());
+        access.decay();
+        if demoted > 0 {
+            tracing::info!(
+                "Tiering: demoted {} vectors to cold tier ({} cold total)",
+                demoted,
+                cold.len()
+            );
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Home page — returns a simple HTML overview of available endpoints.
+async fn handler_home() -> Html<&'static str> {
+    Html(
+        r#"
+        <!DOCTYPE html>
+        <html>
+        <head><title>VectorDB</title></head>
+        <body>
+            <h1>🦀 VectorDB</h1>
+            <p>A vector database built from scratch in Rust.</p>
+            <h2>Endpoints:</h2>
+            <ul>
+                <li>GET /health — Health check</li>
+                <li>GET /metrics — Prometheus metrics</li>
+                <li>POST /vectors — Insert a vector</li>
+                <li>POST /api/vectors/batch — Insert many vectors, with a per-item report</li>
+                <li>POST /api/vectors/get — Fetch many vectors by ID: found and missing</li>
+                <li>HEAD /api/vectors/:id — Check a vector exists without reading it</li>
+                <li>POST /api/vectors/exists — Check many IDs at once: existing and missing</li>
+                <li>GET|PUT|DELETE /vectors/:id — Get, replace, or delete a vector (also /api/vectors/:id)</li>
+                <li>POST /search — Search for similar vectors</li>
+                <li>GET /stats — Server statistics</li>
+                <li>GET|POST /api/collections — List or create collections</li>
+                <li>GET|DELETE /api/collections/:name — Collection info, or move it to the trash</li>
+                <li>POST /api/collections/:name/rename — Rename, re-pointing aliases</li>
+                <li>GET /api/trash — Deleted collections and when they're purged</li>
+                <li>POST /api/trash/:name/restore — Undo a delete (DELETE /api/trash/:name purges now)</li>
+                <li>POST /api/collections/:name/points — Upsert points</li>
+                <li>POST /api/collections/:name/transactions — Apply several writes atomically</li>
+                <li>GET /api/collections/:name/index — Index type, size, and memory</li>
+                <li>POST /api/collections/:name/index — Build an HNSW index in the background</li>
+                <li>POST /api/collections/:name/index/relayout — Renumber the HNSW graph for memory locality</li>
+                <li>GET /api/collections/:name/stats — Distinct values, histograms and filter selectivity per schema field</li>
+                <li>GET|POST|DELETE /api/collections/:name/calibration — Score → probability calibration</li>
+                <li>GET /api/collections/:name/export — Stream as JSONL, CSV or Parquet</li>
+                <li>POST /api/collections/:name/import — Bulk JSONL or Parquet import (?validate_only=true)</li>
+                <li>POST /api/collections/:name/uploads — Start a resumable import upload</li>
+                <li>GET|PATCH|DELETE /api/uploads/:id — Resume point, append bytes, abort</li>
+                <li>POST /api/uploads/:id/complete — Import the assembled upload</li>
+                <li>GET|PUT|DELETE /api/collections/:name/points/:id — One point (?if=metadata.status=="draft")</li>
+                <li>GET /api/collections/:name/points/:id/history — Previous versions</li>
+                <li>POST /api/collections/:name/points/:id/rollback — Restore a version</li>
+                <li>PUT|DELETE /api/collections/:name/shadow — Mirror inserts into a shadow</li>
+                <li>POST /api/collections/:name/shadow/compare — Ranking divergence vs shadow</li>
+                <li>POST /api/collections/:name/search — Search one collection</li>
+                <li>POST /api/collections/:name/search/sparse — Search its sparse embeddings by dot product</li>
+                <li>POST /api/collections/:name/search/hybrid — Search dense and sparse at once, fusing the rankings</li>
+                <li>POST /api/collections/:name/update_by_filter — Bulk metadata edit</li>
+                <li>POST /api/collections/:name/normalize — Rewrite legacy vectors as unit length (job)</li>
+                <li>GET|POST /api/aliases — List or create aliases (with A/B splits)</li>
+                <li>POST /api/search/multi — Search several collections</li>
+                <li>POST /api/search/batch — Many query vectors against one collection</li>
+                <li>PUT|POST /api/search/template/:name — Register or run a query template</li>
+                <li>GET /api/search/stream — Search one collection, results as Server-Sent Events</li>
+                <li>POST /api/compute/arith — Add/subtract/average stored vectors (and search)</li>
+                <li>GET /api/jobs/:id — Background job progress</li>
+                <li>GET /api/admin/locks — State lock holders and waiters</li>
+                <li>GET /api/admin/fds — File descriptor budget and use</li>
+                <li>GET /api/admin/usage — Daily usage per API key (?from=&to=&key=)</li>
+                <li>GET|PUT|DELETE /api/admin/udfs/:name — WASM filter/score UDFs (--features wasm)</li>
+                <li>PUT /api/admin/collections/:name/protection — Protect a collection from deletes and rewrites</li>
+                <li>PUT /api/admin/collections/:name/read_only — Freeze or unfreeze writes to a collection</li>
+                <li>GET /debug/pprof/profile, /debug/pprof/heap — CPU and heap profiles (pprof builds, admin key)</li>
+            </ul>
+        </body>
+        </html>
+    "#,
+    )
+}
+
+/// Health check — for load balancers and k8s probes.
+async fn handler_health() -> &'static str {
+    "OK"
+}
+
+/// Prometheus metrics: request counts and latencies, search latency per
+/// collection, index build times, and current sizes (see src/monitoring.rs).
+///
+/// GET /metrics
+async fn handler_metrics(State(state): State<SharedState>) -> Response {
+    let state = state.read().await;
+    let (wal_records, wal_bytes) = state.wal.as_ref().map_or((0, 0), Wal::size);
+    let sizes = monitoring::Sizes {
+        vectors: state.vectors.len() + state.cold.len(),
+        collections: state
+            .collections
+            .iter()
+            .map(|(name, c)| (name.clone(), c.len()))
+            .collect(),
+        wal_records,
+        wal_bytes,
+    };
+    let mut body = state
+        .metrics
+        .as_ref()
+        .map(PrometheusHandle::render)
+        .unwrap_or_default();
+    body.push_str(&sizes.render());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+/// Insert a new vector into the database.
+///
+/// POST /vectors
+/// Body: { "id": "doc_001", "vector": { "data": [0.1, 0.2], "metadata": {} } }
+async fn handler_insert(
+    State(state): State<SharedState>,
+    Json(req): Json<InsertRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    // Validate input
+    if req.id.is_empty() {
+        return Err(ApiError::bad_request("Vector ID cannot be empty"));
+    }
+    if req.vector.data.is_empty() {
+        return Err(ApiError::bad_request("Vector data cannot be empty"));
+    }
+
+    let dimension = req.vector.dimension();
+    limits::check_dimension(dimension)?;
+
+    // Write to shared state — lock scoped to this block
+    let seq = {
+        let mut state = state.write().await;
+        let seq = state.log(&[WalRecord::insert(None, &req.id, &req.vector)])?;
+        state.cold.remove(&req.id);
+        state.cold.touch(&req.id, Instant::now());
+        state.remember_id(&req.id);
+        state.vectors.insert(req.id.clone(), req.vector);
+        state.request_count += 1;
+        seq
+    }; // Lock released here
+
+    tracing::info!("Inserted vector '{}' ({} dims)", req.id, dimension);
+
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": "inserted",
+            "id": req.id,
+            "dimension": dimension
+        }),
+        seq,
+    )))
+}
+
+/// Most vectors in one batch insert
+const MAX_BATCH_INSERT: usize = 10_000;
+
+/// Insert many vectors in one request.
+///
+/// Each item is validated on its own and either written in full or
+/// reported as failed; one bad item doesn't reject the rest. Every item
+/// must have the flat store's dimension (the dimension of the vectors
+/// already stored, or of the first valid item when the store is empty).
+/// The accepted items are logged to the WAL in one append before any of
+/// them is applied.
+///
+/// POST /api/vectors/batch
+/// Body: [{ "id": "doc_001", "vector": { "data": [0.1, 0.2] } }, ...]
+async fn handler_insert_batch(
+    State(state): State<SharedState>,
+    Json(items): Json<Vec<InsertRequest>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if items.len() > MAX_BATCH_INSERT {
+        return Err(ApiError::bad_request(format!(
+            "A batch can hold at most {} vectors",
+            MAX_BATCH_INSERT
+        )));
+    }
+
+    let mut state = state.write().await;
+    let mut dimension = state
+        .vectors
+        .values()
+        .map(|v| v.dimension())
+        .chain(state.cold.iter().map(|(_, c)| c.dimension()))
+        .next();
+
+    let mut seen = HashSet::with_capacity(items.len());
+    let mut accepted = Vec::with_capacity(items.len());
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        let checked = if item.id.is_empty() {
+            Err(VectorDbError::InvalidParameter(
+                "vector ID cannot be empty".into(),
+            ))
+        } else if !seen.insert(item.id.clone()) {
+            Err(VectorDbError::InvalidParameter(format!(
+                "duplicate ID '{}' in batch",
+                item.id
+            )))
+        } else if item.vector.data.is_empty() {
+            Err(VectorDbError::EmptyVector)
+        } else {
+            limits::check_dimension(item.vector.dimension()).and_then(|()| match dimension {
+                Some(expected) if expected != item.vector.dimension() => {
+                    Err(VectorDbError::DimensionMismatch {
+                        expected,
+                        got: item.vector.dimension(),
+                    })
+                }
+                _ => Ok(()),
+            })
+        };
+        match checked {
+            Ok(()) => {
+                dimension = Some(item.vector.dimension());
+                let outcome =
+                    if state.vectors.contains_key(&item.id) || state.cold.get(&item.id).is_some() {
+                        WriteOutcome::Overwritten
+                    } else {
+                        WriteOutcome::Inserted
+                    };
+                results.push(point_ok(item.id.clone(), outcome));
+                accepted.push((item.id, item.vector));
+            }
+            Err(e) => results.push(point_failed(item.id, e)),
+        }
+    }
+
+    let records: Vec<WalRecord> = accepted
+        .iter()
+        .map(|(id, vector)| WalRecord::insert(None, id, vector))
+        .collect();
+    let seq = state.log(&records)?;
+
+    let now = Instant::now();
+    let count = accepted.len();
+    for (id, vector) in accepted {
+        state.cold.remove(&id);
+        state.cold.touch(&id, now);
+        state.remember_id(&id);
+        state.vectors.insert(id, vector);
+    }
+    state.request_count += 1;
+    drop(state);
+
+    let failed = results.len() - count;
+    tracing::info!("Batch insert: {} vectors written, {} failed", count, failed);
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": if failed == 0 { "inserted" } else { "partial" },
+            "count": count,
+            "failed": failed,
+            "results": results,
+        }),
+        seq,
+    )))
+}
+
+/// Get a vector by its ID.
+///
+/// Cold vectors are promoted back into the hot tier on read.
+///
+/// GET /vectors/:id
+async fn handler_get_vector(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vector>, ApiError> {
+    let mut state = state.write().await;
+    let now = Instant::now();
+
+    let AppState {
+        vectors,
+        cold,
+        access,
+        ..
+    } = &mut *state;
+    if cold.promote(&id, vectors, now) {
+        access.record(&id, "cold");
+    } else if vectors.contains_key(&id) {
+        cold.touch(&id, now);
+        access.record(&id, "hot");
+    }
+
+    match state.vectors.get(&id) {
+        Some(vector) => Ok(Json(vector.clone())),
+        None => Err(ApiError::not_found(format!("Vector '{}' not found", id))),
+    }
+}
+
+/// Most IDs in one batch get
+const MAX_BATCH_GET: usize = 1_000;
+
+/// Get many vectors by ID in one request.
+///
+/// Takes the lock once for the whole batch. Found vectors come back in the
+/// order asked for, each with its ID; IDs that don't exist are listed under
+/// `missing`. Repeated IDs are answered once. Cold vectors are promoted,
+/// as for a single get.
+///
+/// POST /api/vectors/get
+/// Body: { "ids": ["doc_001", "doc_002"] }
+async fn handler_get_batch(
+    State(state): State<SharedState>,
+    Json(req): Json<BatchGetRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if req.ids.len() > MAX_BATCH_GET {
+        return Err(ApiError::bad_request(format!(
+            "A batch get can ask for at most {} IDs",
+            MAX_BATCH_GET
+        )));
+    }
+
+    let mut state = state.write().await;
+    let now = Instant::now();
+    let AppState {
+        vectors,
+        cold,
+        access,
+        ..
+    } = &mut *state;
+
+    let mut seen = HashSet::with_capacity(req.ids.len());
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for id in req.ids {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        if cold.promote(&id, vectors, now) {
+            access.record(&id, "cold");
+        } else if vectors.contains_key(&id) {
+            cold.touch(&id, now);
+            access.record(&id, "hot");
+        }
+        match vectors.get(&id) {
+            Some(vector) => found.push(serde_json::json!({
+                "id": id,
+                "data": vector.data,
+                "metadata": vector.metadata,
+            })),
+            None => missing.push(id),
+        }
+    }
+    state.request_count += 1;
+
+    Ok(Json(serde_json::json!({
+        "found": found,
+        "missing": missing,
+    })))
+}
+
+/// Where a stored flat-store vector lives, without reading it
+fn locate(state: &AppState, id: &str) -> Option<&'static str> {
+    if !state.id_filter.might_contain(id) {
+        return None;
+    }
+    if state.vectors.contains_key(id) {
+        Some("hot")
+    } else if state.cold.get(id).is_some() {
+        Some("cold")
+    } else {
+        None
+    }
+}
+
+/// Check whether a vector exists without reading it.
+///
+/// Answers from the ID filter and the ID maps under a read lock, so it
+/// doesn't promote cold vectors or count as an access. Responds 200 with
+/// an `X-Vector-Tier` header, or 404.
+///
+/// HEAD /vectors/:id
+async fn handler_vector_exists(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Response {
+    let state = state.read().await;
+    match locate(&state, &id) {
+        Some(tier) => (StatusCode::OK, [("x-vector-tier", tier)]).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Check many IDs at once for ingestion dedup.
+///
+/// Like HEAD, this never reads vector data, promotes, or counts as an
+/// access. Repeated IDs are answered once.
+///
+/// POST /api/vectors/exists
+/// Body: { "ids": ["doc_001", "doc_002"] }
+async fn handler_exists_batch(
+    State(state): State<SharedState>,
+    Json(req): Json<BatchGetRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if req.ids.len() > MAX_BATCH_GET {
+        return Err(ApiError::bad_request(format!(
+            "An exists check can ask for at most {} IDs",
+            MAX_BATCH_GET
+        )));
+    }
+
+    let state = state.read().await;
+    let mut seen = HashSet::with_capacity(req.ids.len());
+    let (mut existing, mut missing) = (Vec::new(), Vec::new());
+    for id in req.ids {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        match locate(&state, &id) {
+            Some(_) => existing.push(id),
+            None => missing.push(id),
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "existing": existing,
+        "missing": missing,
+    })))
+}
+
+/// Replace an existing vector's data and metadata. The dimension can't
+/// change; insert under a new ID for that.
+///
+/// PUT /vectors/:id (or /api/vectors/:id)
+/// Body: { "data": [0.1, 0.2, 0.3], "metadata": { "title": "..." } }
+async fn handler_update_vector(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Json(vector): Json<Vector>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if vector.data.is_empty() {
+        return Err(ApiError::bad_request("Vector data cannot be empty"));
+    }
+
+    let seq = {
+        let mut state = state.write().await;
+        let current = match state.vectors.get(&id) {
+            Some(v) => Some(v.dimension()),
+            None => state.cold.get(&id).map(|c| c.dimension()),
+        };
+        let Some(dimension) = current else {
+            return Err(ApiError::not_found(format!("Vector '{}' not found", id)));
+        };
+        if vector.dimension() != dimension {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: dimension,
+                got: vector.dimension(),
+            }
+            .into());
+        }
+
+        let seq = state.log(&[WalRecord::insert(None, &id, &vector)])?;
+        state.cold.remove(&id);
+        state.cold.touch(&id, Instant::now());
+        state.vectors.insert(id.clone(), vector);
+        state.request_count += 1;
+        seq
+    };
+
+    tracing::info!("Updated vector '{}'", id);
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": "updated",
+            "id": id,
+        }),
+        seq,
+    )))
+}
+
+/// Delete a vector from either tier.
+///
+/// The WAL delete record is the tombstone: it is logged before the vector
+/// is dropped, so replay after a restart removes the vector from the last
+/// snapshot again. The next snapshot doesn't contain it at all.
+///
+/// DELETE /vectors/:id (or /api/vectors/:id)
+async fn handler_delete_vector(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let seq = {
+        let mut state = state.write().await;
+        if !state.vectors.contains_key(&id) && state.cold.get(&id).is_none() {
+            return Err(ApiError::not_found(format!("Vector '{}' not found", id)));
+        }
+
+        let seq = state.log(&[WalRecord::delete(None, &id)])?;
+        state.cold.remove(&id);
+        state.vectors.remove(&id);
+        state.request_count += 1;
+        seq
+    };
+
+    tracing::info!("Deleted vector '{}'", id);
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": "deleted",
+            "id": id,
+        }),
+        seq,
+    )))
+}
+
+/// Search for similar vectors.
+///
+/// Scans both tiers; cold vectors are dequantized and scored on the fly.
+/// Takes `min_seq`, `include_vector` and `include_metadata` like the
+/// collection search.
+///
+/// POST /search
+/// Body: { "vector": [0.1, 0.2, 0.3], "top_k": 10, "metric": "cosine" }
+async fn handler_search(
+    State(state): State<SharedState>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<Vec<SearchResult>>, ApiError> {
+    // Validate input
+    if req.vector.is_empty() {
+        return Err(ApiError::bad_request("Vector data cannot be empty"));
+    }
+    wait_for_seq(&state, req.min_seq).await?;
+    let metric = metric::resolve(req.metric, None, req.exact)?;
+
+    let mut state = state.write().await;
+
+    tracing::info!(
+        "Search: {} dims across {} hot + {} cold vectors",
+        req.vector.len(),
+        state.vectors.len(),
+        state.cold.len()
+    );
+
+    let start = Instant::now();
+    let mut results = flat_search(&state, &req.vector, metric, req.top_k);
+    monitoring::record_search("", start.elapsed());
+
+    // Returned hits count as accesses (cold ones wait for a GET to promote)
+    let now = Instant::now();
+    for hit in &results {
+        if state.vectors.contains_key(&hit.id) {
+            state.cold.touch(&hit.id, now);
+            state.access.record(&hit.id, "hot");
+        } else {
+            state.access.record(&hit.id, "cold");
+        }
+    }
+
+    if req.include.any() {
+        for hit in &mut results {
+            if let Some(vector) = state.vectors.get(&hit.id) {
+                include_payload(hit, vector, req.include);
+            } else if let Some(cold) = state.cold.get(&hit.id) {
+                let vector = cold.decode();
+                include_payload(hit, &vector, req.include);
+            }
+        }
+    }
+
+    Ok(Json(results))
+}
+
+/// Copy the parts of `vector` a search asked for onto its hit
+fn include_payload(hit: &mut SearchResult, vector: &Vector, include: IncludeFields) {
+    if include.include_vector {
+        hit.vector = Some(vector.data.clone());
+    }
+    if include.include_metadata {
+        hit.metadata = Some(vector.metadata.clone());
+    }
+}
+
+/// Fill in what a search asked for on each of a collection search's hits
+fn include_payloads(collection: &Collection, include: IncludeFields, hits: &mut [SearchResult]) {
+    if !include.any() {
+        return;
+    }
+    for hit in hits {
+        if let Some(vector) = collection.get(&hit.id) {
+            include_payload(hit, &vector, include);
+        }
+    }
+}
+
+/// Top-k over the flat store, both tiers.
+fn flat_search(
+    state: &AppState,
+    query: &[f32],
+    metric: DistanceMetric,
+    top_k: usize,
+) -> Vec<SearchResult> {
+    let hot: Vec<(&str, &[f32])> = state
+        .vectors
+        .iter()
+        .map(|(id, v)| (id.as_str(), v.data.as_slice()))
+        .collect();
+    let workers = search::scan_workers(hot.len());
+    let mut results = search::parallel_brute_force(query, metric, top_k, &hot, workers);
+
+    // Rescore cold candidates from their quantized form
+    let decoded: Vec<(&str, Vector)> = state
+        .cold
+        .iter()
+        .map(|(id, v)| (id.as_str(), v.decode()))
+        .collect();
+    results.extend(search::brute_force(
+        query,
+        metric,
+        top_k,
+        decoded.iter().map(|(id, v)| (*id, v.data.as_slice())),
+    ));
+    search::rank(results, metric, top_k)
+}
+
+/// Get server statistics.
+///
+/// GET /stats
+async fn handler_stats(State(state): State<SharedState>) -> Json<serde_json::Value> {
+    let state = state.read().await;
+
+    Json(serde_json::json!({
+        "vector_count": state.vectors.len() + state.cold.len(),
+        "cold_vector_count": state.cold.len(),
+        "request_count": state.request_count,
+        "access": state.access.stats(),
+        "segments": {
+            "unloaded_collections": state.unloaded.len(),
+            "catalog": state.segments.stats(),
+        },
+        "wal": state.wal.as_ref().map(|wal| {
+            let (records, bytes) = wal.size();
+            serde_json::json!({ "records": records, "bytes": bytes, "seq": wal.sequence() })
+        }),
+        "status": "running"
+    }))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COLLECTION HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Create a new collection.
+///
+/// POST /api/collections
+/// Body: { "name": "docs_en", "dimension": 768, "distance": "cosine" }
+async fn handler_create_collection(
+    State(state): State<SharedState>,
+    Json(req): Json<CreateCollectionRequest>,
+) -> Result<(StatusCode, Json<CollectionInfo>), ApiError> {
+    let collection = Collection::from_request(&req)?;
+
+    let mut state = state.write().await;
+    if state.name_taken(&req.name) {
+        return Err(VectorDbError::AlreadyExists(req.name).into());
+    }
+
+    let info = collection.info();
+    state.log(&[WalRecord::CreateCollection(info.clone())])?;
+    state.collections.insert(req.name.clone(), collection);
+    tracing::info!("Created collection '{}' ({} dims)", req.name, req.dimension);
+
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// List all collections.
+///
+/// GET /api/collections
+async fn handler_list_collections(State(state): State<SharedState>) -> Json<Vec<CollectionInfo>> {
+    let state = state.read().await;
+    // Unloaded collections can't have changed since their sidecar was written
+    let mut infos: Vec<_> = state
+        .collections
+        .values()
+        .map(|c| c.info())
+        .chain(state.unloaded.values().map(|u| u.info.clone()))
+        .collect();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(infos)
+}
+
+/// Get a single collection's info.
+///
+/// GET /api/collections/:name
+async fn handler_get_collection(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    Ok(Json(collection.info()))
+}
+
+/// Rename a collection. Aliases and shadow links that point at it follow
+/// along in the same step, so searches never see it missing.
+///
+/// A read-only collection can't be renamed, and a protected one needs
+/// `?force=true` and the admin key, as for a delete: clients that address
+/// it by name stop finding it either way.
+///
+/// POST /api/collections/:name/rename
+/// Body: { "to": "docs_v2" }
+async fn handler_rename_collection(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteCollectionQuery>,
+    principal: Option<Extension<Principal>>,
+    headers: HeaderMap,
+    Json(req): Json<RenameCollectionRequest>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    collection::validate_name(&req.to)?;
+    require(principal.as_deref(), &Access::on(Verb::Manage, &req.to))?;
+
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+    check_destructive(&state, collection.protected, &name, query.force, &headers)?;
+    if state.name_taken(&req.to) {
+        return Err(VectorDbError::AlreadyExists(req.to).into());
+    }
+
+    state.log(&[WalRecord::RenameCollection {
+        from: name.clone(),
+        to: req.to.clone(),
+    }])?;
+    state.rename_collection(&name, &req.to)?;
+    tracing::info!("Renamed collection '{}' to '{}'", name, req.to);
+
+    Ok(Json(state.collections[&req.to].info()))
+}
+
+/// Delete a collection. It moves to the trash and can be restored until
+/// the retention window (the trash_retention_hours setting) runs out.
+///
+/// Collections that an alias routes to or that another collection
+/// mirrors into can't be deleted until those links are removed. Protected
+/// collections also need `?force=true` and the admin key.
+///
+/// DELETE /api/collections/:name
+async fn handler_delete_collection(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteCollectionQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+    check_destructive(&state, collection.protected, &name, query.force, &headers)?;
+    if let Some(alias) = state
+        .aliases
+        .values()
+        .find(|a| a.targets().any(|t| t == name))
+    {
+        return Err(VectorDbError::Conflict(format!(
+            "alias '{}' routes to collection '{}'",
+            alias.name, name
+        ))
+        .into());
+    }
+    if let Some(source) = state
+        .collections
+        .values()
+        .find(|c| c.shadow.as_deref() == Some(name.as_str()))
+    {
+        return Err(VectorDbError::Conflict(format!(
+            "collection '{}' mirrors into '{}'",
+            source.name, name
+        ))
+        .into());
+    }
+
+    let deleted_at = SystemClock.unix_secs();
+    state.log(&[WalRecord::TrashCollection {
+        name: name.clone(),
+        deleted_at,
+    }])?;
+    state.trash_collection(&name, deleted_at)?;
+    let purge_at = state.trash.purge_at(state.trash.get(&name).unwrap());
+    tracing::info!("Moved collection '{}' to the trash", name);
+
+    Ok(Json(serde_json::json!({
+        "status": "trashed",
+        "name": name,
+        "deleted_at": deleted_at,
+        "purge_at": purge_at,
+    })))
+}
+
+/// Let a destructive operation on collection `name` through: always if
+/// it isn't protected, otherwise only with `force` and (when one is
+/// configured) the admin key.
+fn check_destructive(
+    state: &AppState,
+    protected: bool,
+    name: &str,
+    force: bool,
+    headers: &HeaderMap,
+) -> Result<(), ApiError> {
+    if !protected {
+        return Ok(());
+    }
+    if !force {
+        return Err(VectorDbError::Conflict(format!(
+            "collection '{}' is protected; pass force=true with the admin key",
+            name
+        ))
+        .into());
+    }
+    match (&state.admin_key, api_key(headers)) {
+        (None, _) => Ok(()),
+        (Some(admin), Some(key)) if auth::constant_time_eq(key.as_bytes(), admin.as_bytes()) => {
+            Ok(())
+        }
+        _ => Err(ApiError {
+            status: StatusCode::FORBIDDEN,
+            message: format!(
+                "collection '{}' is protected; this needs the admin key",
+                name
+            ),
+            fields: Vec::new(),
+        }),
+    }
+}
+
+/// Protect a collection against deletes and destructive rewrites, or lift
+/// the protection.
+///
+/// PUT /api/admin/collections/:name/protection
+/// Body: { "protected": true }
+async fn handler_set_protection(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<ProtectionRequest>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let mut info = collection.info();
+    info.protected = req.protected;
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    if let Some(collection) = state.collections.get_mut(&name) {
+        collection.reconfigure(&info);
+    }
+    tracing::info!(
+        "Collection '{}' is {}",
+        name,
+        if req.protected {
+            "protected"
+        } else {
+            "no longer protected"
+        }
+    );
+    Ok(Json(info))
+}
+
+/// Freeze a collection against writes (searches and reads still work),
+/// e.g. while a migration copies it or a legal hold is in place, or thaw
+/// it again.
+///
+/// While frozen, every write to it gets 423 Locked: point upserts, puts,
+/// deletes, rollbacks, transactions, imports, shadow mirroring into it,
+/// bulk jobs (which stop at their next batch), and deleting the
+/// collection itself.
+///
+/// PUT /api/admin/collections/:name/read_only
+/// Body: { "read_only": true }
+async fn handler_set_read_only(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<ReadOnlyRequest>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let mut info = collection.info();
+    info.read_only = req.read_only;
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    if let Some(collection) = state.collections.get_mut(&name) {
+        collection.reconfigure(&info);
+    }
+    tracing::info!(
+        "Collection '{}' is {}",
+        name,
+        if req.read_only {
+            "read-only"
+        } else {
+            "writable again"
+        }
+    );
+    Ok(Json(info))
+}
+
+/// List uploaded UDFs and which entry points each exports.
+///
+/// GET /api/admin/udfs
+#[cfg(feature = "wasm")]
+async fn handler_list_udfs(State(state): State<SharedState>) -> Json<Vec<serde_json::Value>> {
+    Json(state.read().await.udfs.list())
+}
+
+/// Upload (or replace) a WASM filter/score UDF. The module is compiled and
+/// checked against the ABI in `engine::udf` before it's accepted.
+///
+/// PUT /api/admin/udfs/:name
+/// Body: the module, binary .wasm or .wat text
+#[cfg(feature = "wasm")]
+async fn handler_put_udf(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    body: axum::body::Bytes,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut state = state.write().await;
+    state.udfs.put(&name, &body)?;
+    tracing::info!("Uploaded UDF '{}' ({} bytes)", name, body.len());
+    let list = state.udfs.list();
+    let entry = list.into_iter().find(|u| u["name"] == name.as_str());
+    Ok(Json(entry.unwrap_or_default()))
+}
+
+/// Remove an uploaded UDF.
+///
+/// DELETE /api/admin/udfs/:name
+#[cfg(feature = "wasm")]
+async fn handler_delete_udf(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.write().await.udfs.remove(&name)? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(VectorDbError::NotFound(format!("UDF '{}'", name)).into()),
+    }
+}
+
+/// List trashed collections and when each will be purged.
+///
+/// GET /api/trash
+async fn handler_list_trash(State(state): State<SharedState>) -> Json<Vec<serde_json::Value>> {
+    Json(state.read().await.trash.list())
+}
+
+/// Put a trashed collection back under its old name.
+///
+/// POST /api/trash/:name/restore
+async fn handler_restore_trash(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    let mut state = state.write().await;
+    if state.trash.get(&name).is_none() {
+        return Err(VectorDbError::NotFound(format!("trashed collection '{}'", name)).into());
+    }
+    if state.name_taken(&name) {
+        return Err(VectorDbError::AlreadyExists(format!(
+            "collection '{}' (rename it before restoring the trashed one)",
+            name
+        ))
+        .into());
+    }
+
+    state.log(&[WalRecord::RestoreCollection(name.clone())])?;
+    state.restore_collection(&name)?;
+    tracing::info!("Restored collection '{}' from the trash", name);
+
+    Ok(Json(state.collections[&name].info()))
+}
+
+/// Purge a trashed collection now instead of waiting out its retention.
+/// A collection that was protected when it was deleted needs
+/// `?force=true` and the admin key here too.
+///
+/// DELETE /api/trash/:name
+async fn handler_purge_trash(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteCollectionQuery>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let mut state = state.write().await;
+    let trashed = state
+        .trash
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("trashed collection '{}'", name)))?;
+    let protected = trashed.collection.protected;
+    check_destructive(&state, protected, &name, query.force, &headers)?;
+
+    state.log(&[WalRecord::PurgeCollection(name.clone())])?;
+    state.trash.take(&name);
+    tracing::info!("Purged trashed collection '{}'", name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rows per chunk of a streamed export; the read lock is held per chunk
+const EXPORT_BATCH: usize = 1000;
+
+/// Describe the index behind a collection's searches: type, parameters,
+/// graph/centroid counts where they apply, memory, and build time.
+///
+/// GET /api/collections/:name/index
+async fn handler_index_info(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<IndexInfo>, ApiError> {
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    Ok(Json(collection.index_info()))
+}
+
+/// Build (or rebuild, e.g. with new parameters) a collection's HNSW graph
+/// in the background.
+///
+/// The points are copied and the graph built off the lock, so searches and
+/// writes carry on meanwhile (exact, or through the old graph). The IDs
+/// written during the build are recorded, and replayed onto the new graph
+/// just before it replaces the old one in a single step under the write
+/// lock. Returns 202 with a job ID; GET /api/jobs/:id reports progress and
+/// ETA. 409 if a build on the collection is already running.
+///
+/// POST /api/collections/:name/index
+/// Body: { "hnsw": { "m": 16, "ef_construction": 200, "ef_search": 64 } }
+async fn handler_build_index(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<BuildIndexRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    hnsw::validate(&req.hnsw).map_err(ApiError::bad_request)?;
+
+    let job_id = {
+        let mut guard = state.write().await;
+        guard
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?
+            .check_writable()?;
+        start_index_build(&state, &mut guard, &name, req.hnsw)?.id
+    };
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job_id": job_id })),
+    ))
+}
+
+/// Start a background job building an HNSW graph with `config` for
+/// collection `name`, from a copy of its points taken now. Called with
+/// the state write-locked; when the graph is done the job installs it,
+/// catching up on the writes made meanwhile, and logs the new index.
+fn start_index_build(
+    shared: &SharedState,
+    state: &mut AppState,
+    name: &str,
+    config: HnswConfig,
+) -> Result<vectordb::sync::Arc<Job>, VectorDbError> {
+    let collection = state
+        .collections
+        .get_mut(name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let points = collection.begin_index_build()?;
+    let index = collection.empty_index(config);
+    let job = state.jobs.start("index_build", points.len() as u64);
+
+    tracing::info!(
+        "Job {}: building HNSW index on '{}' ({} points)",
+        job.id,
+        name,
+        points.len()
+    );
+
+    let op = Operation {
+        label: format!("job {} index_build", job.id),
+        collection: Some(name.to_string()),
+    };
+    let (state, name, job_handle) = (shared.clone(), name.to_string(), job.clone());
+    tokio::spawn(locks::with_operation(op, async move {
+        let job = job_handle;
+        let start = Instant::now();
+        let builder = job.clone();
+        let built = tokio::task::spawn_blocking(move || {
+            let mut index = index;
+            for batch in points.chunks(BULK_BATCH_SIZE) {
+                for (id, data) in batch {
+                    index.insert(id, data);
+                }
+                builder.advance(batch.len() as u64);
+            }
+            index.relayout();
+            index
+        })
+        .await;
+
+        let mut state = state.write().await;
+        let Some(collection) = state.collections.get_mut(&name) else {
+            job.fail(format!("collection '{}' was deleted", name));
+            return;
+        };
+        let installed = built
+            .map_err(|e| format!("index build panicked: {}", e))
+            .and_then(|index| collection.install_index(index).map_err(|e| e.to_string()));
+        let caught_up = match installed {
+            Ok(n) => n,
+            Err(e) => {
+                collection.abandon_index_build();
+                job.fail(e);
+                return;
+            }
+        };
+        let nodes = collection.index_info().nodes;
+        let info = collection.info();
+        if let Err(e) = state.log(&[WalRecord::UpdateCollection(info)]) {
+            job.fail(format!("failed to log the new index: {}", e));
+            return;
+        }
+        monitoring::record_index_build(&name, "hnsw", start.elapsed());
+        tracing::info!("Job {}: index on '{}' ready", job.id, name);
+        job.complete(serde_json::json!({ "nodes": nodes, "caught_up": caught_up }));
+    }));
+    Ok(job)
+}
+
+/// Renumber the collection's HNSW graph so traversals read nearby memory
+/// (see engine/hnsw.rs). For graphs that have grown by inserts since they
+/// were built; searches return the same results before and after.
+///
+/// POST /api/collections/:name/index/relayout
+/// Returns: { "nodes": 100000, "edge_span_before": 31204.5, "edge_span_after": 2210.8 }
+async fn handler_relayout_index(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Layout>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get_mut(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let layout = collection.relayout_index().ok_or_else(|| {
+        VectorDbError::InvalidParameter(format!("collection '{}' has no HNSW index", name))
+    })?;
+    tracing::info!(
+        "Relaid out the index on '{}': {} nodes, mean link span {:.1} -> {:.1}",
+        name,
+        layout.nodes,
+        layout.edge_span_before,
+        layout.edge_span_after
+    );
+    Ok(Json(layout))
+}
+
+/// Per-field statistics for the collection's schema fields: approximate
+/// distinct values, the selectivity of an equality filter on each, and a
+/// histogram for numeric fields. `index.state` says whether searches scan
+/// exactly, wait on an auto_index threshold, or use a graph that's being
+/// built or ready.
+///
+/// GET /api/collections/:name/stats?percentiles=0.5,0.99&filter=price<20
+///
+/// `percentiles` adds approximate percentiles to each numeric field;
+/// `filter` adds the estimated fraction (and number) of points a filter
+/// expression keeps, and how a search with it would run (`pre_filter` or
+/// `in_graph`, see engine/hnsw.rs).
+async fn handler_collection_stats(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let percentiles = match &query.percentiles {
+        Some(list) => list
+            .split(',')
+            .map(|p| match p.trim().parse::<f64>() {
+                Ok(f) if (0.0..=1.0).contains(&f) => Ok((p.trim().to_string(), f)),
+                _ => Err(VectorDbError::InvalidParameter(format!(
+                    "percentiles must be fractions between 0 and 1, got '{}'",
+                    p
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let filter = query.filter.as_deref().map(Filter::parse).transpose()?;
+
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let stats = collection.field_stats();
+    let total = collection.len();
+
+    let mut fields = serde_json::to_value(stats.summary(total)).map_err(VectorDbError::from)?;
+    if let Some(fields) = fields.as_object_mut() {
+        for (field, summary) in fields.iter_mut() {
+            let Some(histogram) = stats.histogram(field) else {
+                continue;
+            };
+            if !percentiles.is_empty() {
+                let values: serde_json::Map<_, _> = percentiles
+                    .iter()
+                    .map(|(key, p)| (key.clone(), histogram.percentile(*p).into()))
+                    .collect();
+                summary["percentiles"] = values.into();
+            }
+        }
+    }
+
+    let mut body = serde_json::json!({
+        "collection": name,
+        "count": total,
+        "index": {
+            "state": collection.index_state(),
+            "auto_index_threshold": collection.auto_index().map(|auto| auto.threshold),
+        },
+        "fields": fields,
+    });
+    if let (Some(source), Some(filter)) = (&query.filter, &filter) {
+        let selectivity = stats.selectivity(filter, total);
+        body["filter"] = serde_json::json!({
+            "expression": source,
+            "selectivity": selectivity,
+            "estimated_matches": (selectivity * total as f64).round() as u64,
+            "strategy": collection.filter_strategy(filter, collection.distance),
+        });
+    }
+    Ok(Json(body))
+}
+
+/// Stream a collection as JSON Lines or CSV with selectable fields.
+///
+/// The ID list is snapshotted up front and rows are formatted one batch at
+/// a time, so memory stays flat however large the collection is. Points
+/// deleted mid-export are skipped; if the collection itself is dropped the
+/// stream is aborted so the client sees an incomplete transfer.
+///
+/// GET /api/collections/:name/export?format=jsonl&fields=id,metadata.title
+async fn handler_export(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let format = params.format.as_deref().unwrap_or("jsonl");
+    let projection = match params.fields.as_deref() {
+        Some(fields) => Projection::parse(fields)?,
+        None => Projection::default(),
+    };
+    if format == "parquet" {
+        #[cfg(feature = "parquet")]
+        return export_parquet(state, name, projection).await;
+        #[cfg(not(feature = "parquet"))]
+        return Err(parquet_unavailable().into());
+    }
+    let format = ExportFormat::parse(format)?;
+
+    let mut ids = {
+        let state = state.read().await;
+        state
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?
+            .ids()
+    };
+    ids.sort_unstable();
+    tracing::info!("Exporting {} points from '{}'", ids.len(), name);
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", name, format.extension());
+    let header_row = projection.header(format);
+    let ids = Arc::new(ids);
+    let starts = (0..ids.len()).step_by(EXPORT_BATCH);
+    let chunks = futures_util::stream::iter(header_row.map(Ok)).chain(
+        futures_util::stream::iter(starts).then(move |start| {
+            let state = state.clone();
+            let ids = ids.clone();
+            let name = name.clone();
+            let projection = projection.clone();
+            async move {
+                let state = state.read().await;
+                let collection = state.collections.get(&name).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("collection '{}' dropped during export", name),
+                    )
+                })?;
+                let mut chunk = String::new();
+                for id in &ids[start..(start + EXPORT_BATCH).min(ids.len())] {
+                    if let Some(vector) = collection.get(id) {
+                        chunk.push_str(&projection.row(format, id, &vector));
+                    }
+                }
+                Ok::<_, std::io::Error>(chunk)
+            }
+        }),
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+/// Stream a collection as a Parquet file (see engine/parquet.rs).
+///
+/// Like the text formats, the ID list is snapshotted up front and rows are
+/// encoded a batch at a time; the metadata columns are fixed by the keys
+/// present when the export starts.
+#[cfg(feature = "parquet")]
+async fn export_parquet(
+    state: SharedState,
+    name: String,
+    projection: Projection,
+) -> Result<Response, ApiError> {
+    let (mut ids, export) = {
+        let state = state.read().await;
+        let collection = state
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        let points: Vec<_> = collection.points().map(|(_, v)| v).collect();
+        let keys = parquet::metadata_keys(points.iter().map(|v| v.as_ref()));
+        let export = ParquetExport::new(&projection, collection.dimension, &keys)?;
+        (collection.ids(), export)
+    };
+    ids.sort_unstable();
+    tracing::info!("Exporting {} points from '{}' as Parquet", ids.len(), name);
+
+    let disposition = format!("attachment; filename=\"{}.parquet\"", name);
+    let export = Arc::new(std::sync::Mutex::new(Some(export)));
+    let finish = export.clone();
+    let ids = Arc::new(ids);
+    let starts = (0..ids.len()).step_by(EXPORT_BATCH);
+    let to_io = |e: VectorDbError| std::io::Error::new(std::io::ErrorKind::Other, e.to_string());
+    let chunks = futures_util::stream::iter(starts)
+        .then(move |start| {
+            let state = state.clone();
+            let ids = ids.clone();
+            let name = name.clone();
+            let export = export.clone();
+            async move {
+                let state = state.read().await;
+                let collection = state.collections.get(&name).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("collection '{}' dropped during export", name),
+                    )
+                })?;
+                let points: Vec<_> = ids[start..(start + EXPORT_BATCH).min(ids.len())]
+                    .iter()
+                    .filter_map(|id| collection.get(id).map(|v| (id.as_str(), v)))
+                    .collect();
+                let rows: Vec<(&str, &Vector)> =
+                    points.iter().map(|(id, v)| (*id, v.as_ref())).collect();
+                let mut export = export.lock().unwrap();
+                match export.as_mut() {
+                    Some(export) => export.write(&rows).map_err(to_io),
+                    None => Ok(Vec::new()),
+                }
+            }
+        })
+        .chain(futures_util::stream::once(async move {
+            match finish.lock().unwrap().take() {
+                Some(export) => export.finish().map_err(to_io),
+                None => Ok(Vec::new()),
+            }
+        }));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, parquet::CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+/// Error for format=parquet on a server built without it
+#[cfg(not(feature = "parquet"))]
+fn parquet_unavailable() -> VectorDbError {
+    VectorDbError::InvalidParameter(
+        "Parquet support isn't built into this server (enable the `parquet` feature)".into(),
+    )
+}
+
+/// Bulk-import JSON Lines (the export format) into a collection.
+///
+/// Every row is validated first and errors are reported per line. The
+/// import is all-or-nothing: if any row is invalid nothing is written and
+/// the report comes back with 400. With `validate_only=true` nothing is
+/// ever written and only the report is returned, so a file can be checked
+/// before a long load. IDs that already exist are handled by
+/// `on_conflict` (error | skip | overwrite | merge_metadata). With
+/// `format=parquet` the body is a Parquet file instead, checked the same
+/// way row by row.
+///
+/// POST /api/collections/:name/import?validate_only=true&on_conflict=skip
+async fn handler_import(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(params): Query<ImportQuery>,
+    body: Body,
+) -> Result<Response, ApiError> {
+    run_import(&state, &name, &params, body.into_data_stream()).await
+}
+
+/// Validate (and unless `validate_only`, write) a JSONL stream.
+///
+/// The input is checked as it arrives; the read lock is only held while a
+/// received chunk's lines are checked.
+async fn run_import<S, B, E>(
+    state: &SharedState,
+    name: &str,
+    params: &ImportQuery,
+    mut chunks: S,
+) -> Result<Response, ApiError>
+where
+    S: futures_util::Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let not_found = || VectorDbError::NotFound(format!("collection '{}'", name));
+    if !params.validate_only {
+        let state = state.read().await;
+        let collection = state.collections.get(name).ok_or_else(not_found)?;
+        collection.check_writable()?;
+    }
+    let mut validator = ImportValidator::new(params.on_conflict, !params.validate_only);
+    match params.format.as_deref() {
+        None | Some("jsonl") | Some("ndjson") => {
+            while let Some(chunk) = chunks.next().await {
+                let bytes = chunk
+                    .map_err(|e| ApiError::bad_request(format!("Failed to read input: {}", e)))?;
+                let state = state.read().await;
+                let collection = state.collections.get(name).ok_or_else(not_found)?;
+                validator.feed(collection, bytes.as_ref())?;
+            }
+            let state = state.read().await;
+            let collection = state.collections.get(name).ok_or_else(not_found)?;
+            validator.end_input(collection);
+        }
+        #[cfg(feature = "parquet")]
+        Some("parquet") => {
+            // The footer is at the end, so the whole file is needed first
+            let mut data = Vec::new();
+            while let Some(chunk) = chunks.next().await {
+                let bytes = chunk
+                    .map_err(|e| ApiError::bad_request(format!("Failed to read input: {}", e)))?;
+                data.extend_from_slice(bytes.as_ref());
+            }
+            let mut rows = ParquetRows::open(data.into())?;
+            while let Some(batch) = rows.next_batch() {
+                let batch = batch?;
+                let state = state.read().await;
+                let collection = state.collections.get(name).ok_or_else(not_found)?;
+                for row in batch {
+                    validator.check_row(collection, row.row, row.parsed, row.errors);
+                }
+            }
+        }
+        #[cfg(not(feature = "parquet"))]
+        Some("parquet") => return Err(parquet_unavailable().into()),
+        Some(other) => {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "unknown import format '{}' (expected jsonl or parquet)",
+                other
+            ))
+            .into())
+        }
+    }
+
+    let (mut report, rows) = validator.finish();
+    if params.validate_only {
+        return Ok(Json(report).into_response());
+    }
+    if !report.is_valid() {
+        return Ok((StatusCode::BAD_REQUEST, Json(report)).into_response());
+    }
+
+    // Resolve again under the write lock: points may have been written
+    // since validation. Nothing is stored unless every row still resolves.
+    let mut state = state.write().await;
+    let collection = state.collections.get(name).ok_or_else(not_found)?;
+    collection.check_writable()?;
+    let mut resolved = Vec::with_capacity(rows.len());
+    let mut counts = WriteCounts::default();
+    for (id, vector) in rows {
+        let (outcome, vector) = collection.resolve(&id, vector, params.on_conflict)?;
+        counts.record(outcome);
+        if let Some(vector) = vector {
+            resolved.push((id, vector));
+        }
+    }
+    counts.skipped += report.counts.skipped;
+    let records: Vec<WalRecord> = resolved
+        .iter()
+        .map(|(id, vector)| WalRecord::insert(Some(name), id, vector))
+        .collect();
+    state.log(&records)?;
+    let collection = state.collections.get_mut(name).ok_or_else(not_found)?;
+    for (id, vector) in resolved {
+        collection.insert_prepared(id, vector);
+    }
+    report.counts = counts;
+    report.written = counts.written();
+    tracing::info!("Imported {} points into '{}'", report.written, name);
+    Ok(Json(report).into_response())
+}
+
+/// Start a resumable upload for a bulk import into `name`.
+///
+/// POST /api/collections/:name/uploads
+async fn handler_create_upload(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let uploads = {
+        let state = state.read().await;
+        if !state.collections.contains_key(&name) {
+            return Err(VectorDbError::NotFound(format!("collection '{}'", name)).into());
+        }
+        state.uploads.clone()
+    };
+    let id = uploads.create(&name)?;
+    let session = uploads.get(&id)?;
+    let info = session.lock().await.info();
+    tracing::info!("Started upload {} for '{}'", id, name);
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// Where an upload stands — the offset to resume from.
+///
+/// GET /api/uploads/:id
+async fn handler_get_upload(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let session = state.read().await.uploads.get(&id)?;
+    let info = session.lock().await.info();
+    Ok(Json(info))
+}
+
+/// Append bytes to an upload, starting at the `Upload-Offset` header.
+///
+/// A wrong offset is a 409 naming the real one. Bytes received before a
+/// dropped connection are kept, so the client can always resume from
+/// GET /api/uploads/:id.
+///
+/// PATCH /api/uploads/:id
+async fn handler_append_upload(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let offset = headers
+        .get("upload-offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .ok_or_else(|| ApiError::bad_request("Upload-Offset header must be a byte offset"))?;
+
+    let session = state.read().await.uploads.get(&id)?;
+    let mut session = session.lock().await;
+    session.append(offset, body.into_data_stream()).await?;
+    Ok(Json(session.info()))
+}
+
+/// Abandon an upload and delete what was received.
+///
+/// DELETE /api/uploads/:id
+async fn handler_delete_upload(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state.read().await.uploads.remove(&id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Import an assembled upload, exactly like POST .../import.
+///
+/// The session is removed once its rows are written; after a validation
+/// pass or a rejected import it's kept so the report can be re-run.
+///
+/// POST /api/uploads/:id/complete?validate_only=true
+async fn handler_complete_upload(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Query(params): Query<ImportQuery>,
+) -> Result<Response, ApiError> {
+    let uploads = state.read().await.uploads.clone();
+    let session = uploads.get(&id)?;
+    let session = session.lock().await;
+    let chunks = session.read_chunks().await?;
+    let response = run_import(&state, &session.collection, &params, chunks).await?;
+
+    if !params.validate_only && response.status().is_success() {
+        drop(session);
+        uploads.remove(&id)?;
+        tracing::info!("Completed upload {}", id);
+    }
+    Ok(response)
+}
+
+/// Insert or replace points in a collection.
+///
+/// Every point gets its own entry in `results` (inserted, overwritten,
+/// merged, skipped, or failed with a reason); points that fail don't stop
+/// the rest. With `"atomic": true` the batch is all-or-nothing instead:
+/// every point is resolved and validated before anything is written, and
+/// any failure rejects the whole batch.
+///
+/// If the collection has a shadow attached, points carrying a
+/// `shadow_vector` are mirrored into it as well. Existing IDs are handled
+/// by `on_conflict` (default: overwrite). Points without an `id` get one
+/// from the collection's `id_strategy`, if it has one.
+///
+/// POST /api/collections/:name/points
+/// Body: { "points": [{ "id": "doc_001", "vector": [0.1, 0.2], "metadata": {} }],
+///         "on_conflict": "skip", "atomic": true }
+async fn handler_upsert_points(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpsertRequest>,
+) -> Result<Response, ApiError> {
+    let upserted = upsert_points(&mut *state.write().await, &name, req)?;
+    let failed = upserted.failed();
+    if upserted.rejected {
+        let body = serde_json::json!({
+            "error": true,
+            "message": upserted.rejection(),
+            "results": upserted.results,
+        });
+        return Ok((StatusCode::BAD_REQUEST, Json(body)).into_response());
+    }
+
+    Ok(Json(UpsertResponse {
+        status: if failed == 0 { "upserted" } else { "partial" }.to_string(),
+        collection: name,
+        count: upserted.count,
+        mirrored: upserted.mirrored,
+        inserted: upserted.counts.inserted,
+        overwritten: upserted.counts.overwritten,
+        merged: upserted.counts.merged,
+        skipped: upserted.counts.skipped,
+        failed,
+        results: upserted.results,
+        seq: upserted.seq,
+    })
+    .into_response())
+}
+
+/// What a batch upsert did
+struct Upserted {
+    /// Points written to the collection
+    count: usize,
+    /// Points mirrored into its shadow
+    mirrored: usize,
+    counts: WriteCounts,
+    /// One entry per requested point
+    results: Vec<PointResult>,
+    /// An atomic batch had failures, so nothing was written
+    rejected: bool,
+    /// WAL sequence number the write was logged with
+    seq: Option<u64>,
+}
+
+impl Upserted {
+    fn failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == PointStatus::Failed)
+            .count()
+    }
+
+    /// Why an atomic batch was rejected
+    fn rejection(&self) -> String {
+        format!(
+            "{} of {} points failed; nothing was written (atomic batch)",
+            self.failed(),
+            self.results.len()
+        )
+    }
+}
+
+/// Upsert points into collection `name` (see handler_upsert_points),
+/// logging them first. Shared by the HTTP and gRPC APIs.
+fn upsert_points(
+    state: &mut AppState,
+    name: &str,
+    mut req: UpsertRequest,
+) -> Result<Upserted, ApiError> {
+    if let Some(collection) = state.collections.get(name) {
+        collection.check_writable()?;
+    }
+    let mut id_counter = None;
+    if let Some(collection) = state.collections.get_mut(name) {
+        for point in req.points.iter_mut().filter(|p| p.id.is_empty()) {
+            match collection.generate_id() {
+                Some(id) => point.id = id,
+                None => break, // client IDs only; reported per point below
+            }
+            id_counter = collection.id_counter();
+        }
+    }
+    let collection = state
+        .collections
+        .get(name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let shadow = match &collection.shadow {
+        Some(shadow_name) => state.collections.get(shadow_name),
+        None => None,
+    };
+    if let Some(shadow) = shadow {
+        if req.points.iter().any(|p| p.shadow_vector.is_some()) {
+            shadow.check_writable()?;
+        }
+    }
+
+    // Resolve and validate everything (including the mirrored points)
+    // before writing anything
+    let mut prepared: Vec<(String, Vector)> = Vec::with_capacity(req.points.len());
+    let mut mirrored = Vec::new();
+    let mut results = Vec::with_capacity(req.points.len());
+    let mut counts = WriteCounts::default();
+    // A repeated ID is resolved against what the earlier item staged, so
+    // the policy applies within the batch just as it does across batches
+    let mut staged: HashMap<String, usize> = HashMap::new();
+    let mut staged_mirrors: HashMap<String, usize> = HashMap::new();
+    for point in req.points {
+        let vector = Vector {
+            sparse: point.sparse.clone(),
+            ..Vector::with_metadata(point.vector, point.metadata.clone())
+        };
+        let resolved = match staged.get(&point.id) {
+            Some(&i) => {
+                collection.resolve_over(&point.id, vector, req.on_conflict, Some(&prepared[i].1))
+            }
+            None => collection.resolve(&point.id, vector, req.on_conflict),
+        };
+        let resolved = resolved.and_then(|(outcome, vector)| {
+            let mirror = match (shadow, point.shadow_vector, &vector) {
+                (Some(shadow), Some(shadow_vector), Some(_)) => {
+                    // Sparse embeddings don't depend on the dense model
+                    let shadow_vector = Vector {
+                        sparse: point.sparse,
+                        ..Vector::with_metadata(shadow_vector, point.metadata)
+                    };
+                    Some(shadow.prepare(&point.id, shadow_vector)?)
+                }
+                _ => None,
+            };
+            Ok((outcome, vector, mirror))
+        });
+        match resolved {
+            Ok((outcome, vector, mirror)) => {
+                counts.record(outcome);
+                if let Some(vector) = vector {
+                    stage(&mut prepared, &mut staged, &point.id, vector);
+                }
+                if let Some(mirror) = mirror {
+                    stage(&mut mirrored, &mut staged_mirrors, &point.id, mirror);
+                }
+                results.push(point_ok(point.id, outcome));
+            }
+            Err(e) => results.push(point_failed(point.id, e)),
+        }
+    }
+
+    let upserted = Upserted {
+        count: prepared.len(),
+        mirrored: mirrored.len(),
+        counts,
+        results,
+        rejected: false,
+        seq: None,
+    };
+    if req.atomic && upserted.failed() > 0 {
+        return Ok(Upserted {
+            rejected: true,
+            ..upserted
+        });
+    }
+    let shadow_name = shadow.map(|s| s.name.clone());
+
+    let mut records: Vec<WalRecord> = prepared
+        .iter()
+        .map(|(id, vector)| WalRecord::insert(Some(name), id, vector))
+        .collect();
+    if let Some(next) = id_counter {
+        records.push(WalRecord::IdCounter {
+            collection: name.to_string(),
+            next,
+        });
+    }
+    if let Some(shadow_name) = &shadow_name {
+        records.extend(
+            mirrored
+                .iter()
+                .map(|(id, vector)| WalRecord::insert(Some(shadow_name), id, vector)),
+        );
+    }
+    let seq = state.log(&records)?;
+
+    let collection = state.collections.get_mut(name).unwrap();
+    for (id, vector) in prepared {
+        collection.insert_prepared(id, vector);
+    }
+    if let Some(shadow) = shadow_name.and_then(|s| state.collections.get_mut(&s)) {
+        for (id, vector) in mirrored {
+            shadow.insert_prepared(id, vector);
+        }
+    }
+
+    Ok(Upserted { seq, ..upserted })
+}
+
+/// Add `vector` to a batch's staged writes, replacing the one an earlier
+/// item staged under the same ID
+fn stage(
+    batch: &mut Vec<(String, Vector)>,
+    positions: &mut HashMap<String, usize>,
+    id: &str,
+    vector: Vector,
+) {
+    match positions.get(id) {
+        Some(&i) => batch[i].1 = vector,
+        None => {
+            positions.insert(id.to_string(), batch.len());
+            batch.push((id.to_string(), vector));
+        }
+    }
+}
+
+/// Deletes mirrored into a collection's shadow, so points removed from
+/// the collection stop turning up in the shadow's rankings too
+struct ShadowDeletes {
+    shadow: String,
+    ids: Vec<String>,
+}
+
+impl ShadowDeletes {
+    /// The IDs among `ids` that collection `name`'s shadow holds (None if
+    /// it has no shadow or the shadow holds none of them)
+    fn plan<'a>(
+        state: &AppState,
+        name: &str,
+        ids: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Option<Self>, VectorDbError> {
+        let shadow = state
+            .collections
+            .get(name)
+            .and_then(|c| c.shadow.as_ref())
+            .and_then(|s| state.collections.get(s));
+        let Some(shadow) = shadow else {
+            return Ok(None);
+        };
+        let ids: Vec<String> = ids
+            .into_iter()
+            .filter(|id| shadow.contains(id))
+            .map(str::to_string)
+            .collect();
+        if ids.is_empty() {
+            return Ok(None);
+        }
+        shadow.check_writable()?;
+        Ok(Some(Self {
+            shadow: shadow.name.clone(),
+            ids,
+        }))
+    }
+
+    /// Log records for the deletes, to go out with the collection's own
+    fn records(&self) -> impl Iterator<Item = WalRecord> + '_ {
+        self.ids
+            .iter()
+            .map(|id| WalRecord::delete(Some(&self.shadow), id))
+    }
+
+    /// Delete the points from the shadow, once the records are logged
+    fn apply(self, state: &mut AppState) {
+        if let Some(shadow) = state.collections.get_mut(&self.shadow) {
+            for id in &self.ids {
+                shadow.delete(id);
+            }
+        }
+    }
+}
+
+/// Apply a list of upserts, deletes, and metadata updates atomically.
+///
+/// Everything is validated against a staged view of the collection first;
+/// if any operation fails, nothing is written and every failure is listed.
+/// Readers never see part of a transaction because it's applied under a
+/// single write lock, and a crash can't leave part of one behind because
+/// its records go to the log as one group.
+///
+/// POST /api/collections/:name/transactions
+/// Body: { "operations": [{ "op": "upsert", "id": "doc1#0", "vector": [..] },
+///                        { "op": "delete", "id": "doc1#9" }] }
+async fn handler_transaction(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<TransactionRequest>,
+) -> Result<Response, ApiError> {
+    if req.operations.len() > MAX_TRANSACTION_OPS {
+        return Err(ApiError::bad_request(format!(
+            "A transaction can hold at most {} operations",
+            MAX_TRANSACTION_OPS
+        )));
+    }
+
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+
+    let plan = match transaction::plan(collection, req.operations) {
+        Ok(plan) => plan,
+        Err(errors) => {
+            let body = serde_json::json!({
+                "error": true,
+                "message": format!(
+                    "Transaction aborted: {} operation(s) failed, nothing was written",
+                    errors.len()
+                ),
+                "failed": errors,
+            });
+            return Ok((StatusCode::BAD_REQUEST, Json(body)).into_response());
+        }
+    };
+
+    let body = serde_json::json!({
+        "status": "committed",
+        "collection": name,
+        "upserted": plan.upserted,
+        "deleted": plan.deleted,
+        "updated": plan.updated,
+        "points": plan.touched(),
+    });
+    let deleted = plan
+        .writes()
+        .iter()
+        .filter(|(_, state)| state.is_none())
+        .map(|(id, _)| id.as_str());
+    let mirrored = ShadowDeletes::plan(&state, &name, deleted)?;
+    let mut records: Vec<WalRecord> = plan
+        .writes()
+        .iter()
+        .map(|(id, state)| match state {
+            Some(vector) => WalRecord::insert(Some(&name), id, vector),
+            None => WalRecord::delete(Some(&name), id),
+        })
+        .collect();
+    records.extend(mirrored.iter().flat_map(ShadowDeletes::records));
+    let seq = state.log(&records)?;
+    plan.apply(state.collections.get_mut(&name).unwrap());
+    if let Some(mirrored) = mirrored {
+        mirrored.apply(&mut state);
+    }
+    tracing::info!("Committed transaction on '{}'", name);
+    Ok(Json(with_seq(body, seq)).into_response())
+}
+
+/// Get a single point with its current version number.
+///
+/// GET /api/collections/:name/points/:id
+async fn handler_get_point(
+    State(state): State<SharedState>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let vector = collection
+        .get(&id)
+        .ok_or_else(|| VectorDbError::NotFound(format!("vector '{}'", id)))?;
+
+    Ok(Json(serde_json::json!({
+        "id": id,
+        "version": collection.version(&id),
+        "vector": vector,
+    })))
+}
+
+/// Parse the `if` predicate of a conditional write, if there is one
+fn parse_condition(query: &ConditionQuery) -> Result<Option<Filter>, ApiError> {
+    match &query.condition {
+        Some(source) => Ok(Some(Filter::parse(source)?)),
+        None => Ok(None),
+    }
+}
+
+/// Replace a single point, optionally only if it currently matches `if`.
+///
+/// The predicate uses the filter syntax and is checked under the same write
+/// lock as the write itself, so two clients racing to move a point out of
+/// "draft" can't both win: the loser gets 412 Precondition Failed.
+/// `vector` and `sparse` may be omitted to keep the stored ones.
+///
+/// PUT /api/collections/:name/points/:id?if=metadata.status=="draft"
+/// Body: { "vector": [0.1, 0.2], "metadata": { "status": "published" } }
+async fn handler_put_point(
+    State(state): State<SharedState>,
+    Path((name, id)): Path<(String, String)>,
+    Query(query): Query<ConditionQuery>,
+    Json(req): Json<PutPointRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let condition = parse_condition(&query)?;
+
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+    if let (Some(filter), Some(source)) = (&condition, &query.condition) {
+        collection.check_condition(&id, filter, source)?;
+    }
+
+    // Whatever the request leaves out is kept from the stored point
+    let current = collection.get(&id);
+    let data = match (req.vector, &current) {
+        (Some(data), _) => data,
+        (None, Some(current)) => current.data.clone(),
+        (None, None) => {
+            return Err(ApiError::bad_request(format!(
+                "point '{}' does not exist, so a vector is required",
+                id
+            )))
+        }
+    };
+    let sparse = req
+        .sparse
+        .or_else(|| current.and_then(|current| current.sparse.clone()));
+    let vector = collection.prepare(
+        &id,
+        Vector {
+            sparse,
+            ..Vector::with_metadata(data, req.metadata)
+        },
+    )?;
+    let seq = state.log(&[WalRecord::insert(Some(&name), &id, &vector)])?;
+    let collection = state.collections.get_mut(&name).unwrap();
+    let existed = collection.insert_prepared(id.clone(), vector);
+
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": if existed { "overwritten" } else { "inserted" },
+            "id": id,
+            "version": collection.version(&id),
+        }),
+        seq,
+    )))
+}
+
+/// Delete a single point, optionally only if it currently matches `if`.
+/// The point is deleted from the collection's shadow as well.
+///
+/// DELETE /api/collections/:name/points/:id?if=metadata.status=="archived"
+async fn handler_delete_point(
+    State(state): State<SharedState>,
+    Path((name, id)): Path<(String, String)>,
+    Query(query): Query<ConditionQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let condition = parse_condition(&query)?;
+
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+    if let (Some(filter), Some(source)) = (&condition, &query.condition) {
+        collection.check_condition(&id, filter, source)?;
+    }
+    if !collection.contains(&id) {
+        return Err(VectorDbError::NotFound(format!("vector '{}'", id)).into());
+    }
+    let mirrored = ShadowDeletes::plan(&state, &name, [id.as_str()])?;
+    let mut records = vec![WalRecord::delete(Some(&name), &id)];
+    records.extend(mirrored.iter().flat_map(ShadowDeletes::records));
+    let seq = state.log(&records)?;
+    state.collections.get_mut(&name).unwrap().delete(&id);
+    if let Some(mirrored) = mirrored {
+        mirrored.apply(&mut state);
+    }
+
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": "deleted",
+            "id": id,
+        }),
+        seq,
+    )))
+}
+
+/// List the retained previous versions of a point.
+///
+/// GET /api/collections/:name/points/:id/history
+async fn handler_point_history(
+    State(state): State<SharedState>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    if collection.get(&id).is_none() {
+        return Err(VectorDbError::NotFound(format!("vector '{}'", id)).into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "id": id,
+        "current_version": collection.version(&id),
+        "versions": collection.history(&id),
+    })))
+}
+
+/// Restore a point to one of its retained versions.
+///
+/// POST /api/collections/:name/points/:id/rollback
+/// Body: { "version": 3 }
+async fn handler_rollback_point(
+    State(state): State<SharedState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(req): Json<RollbackRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get_mut(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection.check_writable()?;
+
+    let version = collection.rollback(&id, req.version)?;
+    // The restored state is only known once applied; it's logged before
+    // the response goes out
+    let record = WalRecord::insert(Some(&name), &id, &collection.get(&id).unwrap());
+    let seq = state.log(&[record])?;
+    tracing::info!(
+        "Rolled back '{}' in '{}' to version {} (now version {})",
+        id,
+        name,
+        req.version,
+        version
+    );
+
+    Ok(Json(with_seq(
+        serde_json::json!({
+            "status": "rolled_back",
+            "id": id,
+            "restored_from": req.version,
+            "version": version,
+        }),
+        seq,
+    )))
+}
+
+/// Fit a score calibration from labeled pairs and attach it, so searches
+/// return a relevance probability next to each raw score.
+///
+/// Pairs are scored with the collection's metric. Posting again replaces
+/// the previous fit (e.g. after switching embedding models).
+///
+/// POST /api/collections/:name/calibration
+/// Body: { "pairs": [{ "query": [0.1, 0.2], "id": "doc_7", "relevant": true },
+///                   { "score": 0.42, "relevant": false }, ...] }
+async fn handler_calibrate(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<CalibrateRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let metric = collection.distance;
+    let samples = calibration::samples(collection, metric, &req.pairs)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let fitted = calibration::fit(metric, &samples, now)?;
+    let replaced = collection.calibration.is_some();
+    let mut info = collection.info();
+    info.calibration = Some(fitted.clone());
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    // Through reconfigure, so the epoch moves and cached results (without
+    // probabilities, or with the old ones) stop matching
+    state.collections.get_mut(&name).unwrap().reconfigure(&info);
+    tracing::info!(
+        "Calibrated '{}' from {} pairs (a={:.4}, b={:.4})",
+        name,
+        fitted.pairs,
+        fitted.a,
+        fitted.b
+    );
+
+    Ok(Json(serde_json::json!({
+        "status": if replaced { "replaced" } else { "calibrated" },
+        "collection": name,
+        "calibration": fitted,
+    })))
+}
+
+/// Show a collection's calibration.
+///
+/// GET /api/collections/:name/calibration
+async fn handler_get_calibration(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<vectordb::models::Calibration>, ApiError> {
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    collection
+        .calibration
+        .clone()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Collection '{}' is not calibrated", name)))
+}
+
+/// Remove a collection's calibration; searches return raw scores only.
+///
+/// DELETE /api/collections/:name/calibration
+async fn handler_clear_calibration(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    if collection.calibration.is_none() {
+        return Err(ApiError::not_found(format!(
+            "Collection '{}' is not calibrated",
+            name
+        )));
+    }
+    let mut info = collection.info();
+    info.calibration = None;
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    state.collections.get_mut(&name).unwrap().reconfigure(&info);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Attach a shadow collection that receives mirrored inserts and deletes.
+///
+/// PUT /api/collections/:name/shadow
+/// Body: { "collection": "docs_v2" }
+async fn handler_set_shadow(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    principal: Option<Extension<Principal>>,
+    Json(req): Json<ShadowRequest>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    // Inserts will be mirrored into the shadow, and compared against it
+    require(
+        principal.as_deref(),
+        &Access::on(Verb::Manage, &req.collection),
+    )?;
+    let mut state = state.write().await;
+    if req.collection == name {
+        return Err(ApiError::bad_request(
+            "A collection cannot be its own shadow",
+        ));
+    }
+    state.load_collection(&req.collection)?;
+    if !state.collections.contains_key(&req.collection) {
+        return Err(VectorDbError::NotFound(format!("collection '{}'", req.collection)).into());
+    }
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let mut info = collection.info();
+    info.shadow = Some(req.collection.clone());
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    state.collections.get_mut(&name).unwrap().shadow = info.shadow.clone();
+    tracing::info!("Mirroring inserts on '{}' into '{}'", name, req.collection);
+    Ok(Json(info))
+}
+
+/// Stop mirroring writes into the shadow collection.
+///
+/// DELETE /api/collections/:name/shadow
+async fn handler_clear_shadow(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<CollectionInfo>, ApiError> {
+    let mut state = state.write().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let mut info = collection.info();
+    info.shadow = None;
+    state.log(&[WalRecord::UpdateCollection(info.clone())])?;
+    state.collections.get_mut(&name).unwrap().shadow = None;
+    Ok(Json(info))
+}
+
+/// Upper bound on sampled queries per shadow comparison. Each one runs two
+/// exact searches while holding the read lock.
+const MAX_SHADOW_SAMPLE: usize = 1_000;
+
+/// Report how search rankings differ between a collection and its shadow.
+///
+/// Stored points present in both collections are sampled and used as
+/// queries, each with its own embedding in each collection. The query
+/// point itself is excluded from both result lists.
+///
+/// POST /api/collections/:name/shadow/compare
+/// Body: { "sample_size": 100, "top_k": 10, "seed": 0 }
+async fn handler_compare_shadow(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    principal: Option<Extension<Principal>>,
+    Json(req): Json<ShadowCompareRequest>,
+) -> Result<Json<ShadowReport>, ApiError> {
+    if req.sample_size == 0 || req.sample_size > MAX_SHADOW_SAMPLE {
+        return Err(ApiError::bad_request(format!(
+            "sample_size must be between 1 and {}",
+            MAX_SHADOW_SAMPLE
+        )));
+    }
+
+    let state = state.read().await;
+    let primary = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+    let shadow_name = primary.shadow.as_deref().ok_or_else(|| {
+        ApiError::bad_request(format!("Collection '{}' has no shadow attached", name))
+    })?;
+    require(principal.as_deref(), &Access::on(Verb::Search, shadow_name))?;
+    let shadow = state
+        .collections
+        .get(shadow_name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", shadow_name)))?;
+
+    let candidates: Vec<String> = primary
+        .ids()
+        .into_iter()
+        .filter(|id| shadow.get(id).is_some())
+        .collect();
+    let sampled = shadow::sample_ids(candidates, req.sample_size, req.seed);
+
+    let without = |results: Vec<SearchResult>, id: &str| -> Vec<SearchResult> {
+        let mut results: Vec<_> = results.into_iter().filter(|r| r.id != id).collect();
+        results.truncate(req.top_k);
+        results
+    };
+
+    let mut per_query = Vec::with_capacity(sampled.len());
+    for id in sampled {
+        let old = primary.search(&primary.get(&id).unwrap().data, req.top_k + 1)?;
+        let new = shadow.search(&shadow.get(&id).unwrap().data, req.top_k + 1)?;
+        let divergence = shadow::compare_rankings(&without(old, &id), &without(new, &id));
+        per_query.push(DivergentQuery { id, divergence });
+    }
+
+    Ok(Json(ShadowReport::new(
+        &name,
+        shadow_name,
+        req.top_k,
+        per_query,
+    )))
+}
+
+/// Search a single collection with its configured metric, or the
+/// request's `metric` if the collection's index supports it.
+///
+/// `name` may also be an alias. If the alias runs an experiment, the
+/// search is routed to one variant (sticky per `x-routing-key` header, if
+/// given) and the response carries `x-vectordb-variant` and
+/// `x-vectordb-collection` headers.
+///
+/// With a `radius`, every hit within it comes back instead, top_k per page
+/// (capped at max_radius_results). A response with more to come carries
+/// an `x-next-cursor` header; send it back as `cursor` for the next page.
+///
+/// With an `offset` (0 for the first page) or a `page_token`, top-k
+/// searches page too: the top_k hits after the offset or the token's last
+/// hit, and an `x-next-page-token` header while more follow (up to
+/// MAX_PAGE_DEPTH hits deep).
+///
+/// With `min_seq` (the `seq` a write returned), the search first waits
+/// until that write is applied; 503 if it isn't within MAX_SEQ_WAIT.
+///
+/// With `include_vector` or `include_metadata`, each hit carries the
+/// matching vector's data or metadata.
+///
+/// POST /api/collections/:name/search
+/// Body: { "vector": [0.1, 0.2], "top_k": 10 }
+/// Body: { "vector": [0.1, 0.2], "top_k": 100, "radius": 0.5, "cursor": "..." }
+/// Body: { "vector": [0.1, 0.2], "top_k": 20, "page_token": "..." }
+async fn handler_collection_search(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    principal: Option<Extension<Principal>>,
+    headers: HeaderMap,
+    Json(req): Json<SearchRequest>,
+) -> Result<(HeaderMap, Json<Vec<SearchResult>>), ApiError> {
+    wait_for_seq(&state, req.min_seq).await?;
+    let state = state.read().await;
+    let mut response_headers = HeaderMap::new();
+
+    let Some(alias) = state.aliases.get(&name) else {
+        let collection = state
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        let metric = metric::resolve(req.metric, Some(collection), req.exact)?;
+        let (mut results, next) = search_collection_page(&state, collection, &req, metric)?;
+        include_payloads(collection, req.include, &mut results);
+        insert_next_page(&mut response_headers, next);
+        return Ok((response_headers, Json(results)));
+    };
+
+    let routing_key = headers.get("x-routing-key").and_then(|v| v.to_str().ok());
+    let (variant, target) = alias.route(routing_key);
+    require(principal.as_deref(), &Access::on(Verb::Search, target))?;
+    let collection = state
+        .collections
+        .get(target)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+
+    let metric = metric::resolve(req.metric, Some(collection), req.exact)?;
+    let start = Instant::now();
+    let (mut results, next) = search_collection_page(&state, collection, &req, metric)?;
+    alias.stats(variant).record(start.elapsed());
+    include_payloads(collection, req.include, &mut results);
+    insert_next_page(&mut response_headers, next);
+
+    response_headers.insert(
+        "x-vectordb-variant",
+        HeaderValue::from_static(variant.as_str()),
+    );
+    if let Ok(value) = HeaderValue::from_str(target) {
+        response_headers.insert("x-vectordb-collection", value);
+    }
+    Ok((response_headers, Json(results)))
+}
+
+/// Search a collection's sparse embeddings: the points sharing a nonzero
+/// component with the query, best dot product first. Points stored without
+/// a sparse embedding are never returned.
+///
+/// POST /api/collections/:name/search/sparse
+/// Body: { "vector": { "indices": [7, 4021], "values": [0.8, 1.3] }, "top_k": 10,
+///         "filter": "lang == \"en\"" }
+async fn handler_sparse_search(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<SparseSearchRequest>,
+) -> Result<Json<Vec<SearchResult>>, ApiError> {
+    let filter = req.filter.as_deref().map(Filter::parse).transpose()?;
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let start = Instant::now();
+    let mut results = collection.search_sparse(&req.vector, req.top_k, filter.as_ref())?;
+    monitoring::record_search(&collection.name, start.elapsed());
+    include_payloads(collection, req.include, &mut results);
+    Ok(Json(results))
+}
+
+/// Search a collection by dense vector and by sparse embedding, and fuse
+/// the two rankings into one: by Reciprocal Rank Fusion (the default) or a
+/// weighted sum of normalized scores.
+///
+/// POST /api/collections/:name/search/hybrid
+/// Body: { "vector": [0.1, 0.2], "sparse": { "indices": [7], "values": [0.8] },
+///         "top_k": 10, "fusion": { "method": "rrf", "k": 60 } }
+///   or    "fusion": { "method": "weighted", "dense_weight": 0.7, "sparse_weight": 0.3 }
+async fn handler_hybrid_search(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<HybridSearchRequest>,
+) -> Result<Json<Vec<SearchResult>>, ApiError> {
+    let filter = req.filter.as_deref().map(Filter::parse).transpose()?;
+    let state = state.read().await;
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+
+    let candidates = req
+        .candidates
+        .unwrap_or(req.top_k * search::DEFAULT_CANDIDATES_PER_RESULT);
+    let start = Instant::now();
+    let mut results = collection.search_hybrid(
+        &req.vector,
+        &req.sparse,
+        req.top_k,
+        candidates,
+        req.fusion,
+        filter.as_ref(),
+    )?;
+    monitoring::record_search(&collection.name, start.elapsed());
+    include_payloads(collection, req.include, &mut results);
+    Ok(Json(results))
+}
+
+/// Where the next page of a search starts
+enum NextPage {
+    /// Radius searches: the `cursor` to send back
+    Cursor(Cursor),
+    /// Paged top-k searches: the `page_token` to send back
+    Token(PageToken),
+}
+
+/// Run a collection search, or one page of a paged or radius search along
+/// with where the next page starts if there is one
+fn search_collection_page(
+    state: &AppState,
+    collection: &Collection,
+    req: &SearchRequest,
+    metric: DistanceMetric,
+) -> Result<(Vec<SearchResult>, Option<NextPage>), ApiError> {
+    let Some(radius) = req.radius else {
+        if req.cursor.is_some() {
+            return Err(ApiError::bad_request("cursor is only valid with radius"));
+        }
+        if req.offset.is_none() && req.page_token.is_none() {
+            return Ok((
+                search_collection(state, collection, req, None, metric)?,
+                None,
+            ));
+        }
+        let (page, next) = search_top_k_page(state, collection, req, metric)?;
+        return Ok((page, next.map(NextPage::Token)));
+    };
+    if req.offset.is_some() || req.page_token.is_some() {
+        return Err(ApiError::bad_request(
+            "radius searches page with cursor, not offset or page_token",
+        ));
+    }
+    if req.filter_udf.is_some() || req.score_udf.is_some() {
+        return Err(ApiError::bad_request(
+            "radius searches don't support filter_udf or score_udf",
+        ));
+    }
+    let after = req
+        .cursor
+        .as_deref()
+        .map(|token| Cursor::parse(token).ok_or_else(|| ApiError::bad_request("Invalid cursor")))
+        .transpose()?;
+    let page_size = req.top_k.min(limits::max_radius_results());
+
+    let start = Instant::now();
+    let (page, next) =
+        collection.search_radius(&req.vector, radius, metric, page_size, after.as_ref(), None)?;
+    monitoring::record_search(&collection.name, start.elapsed());
+    Ok((page, next.map(NextPage::Cursor)))
+}
+
+/// One page of a top-k search, `offset` hits or the page token's worth in:
+/// a search deep enough to cover the page and one hit past it, cut down to
+/// the page
+fn search_top_k_page(
+    state: &AppState,
+    collection: &Collection,
+    req: &SearchRequest,
+    metric: DistanceMetric,
+) -> Result<(Vec<SearchResult>, Option<PageToken>), ApiError> {
+    let token = req
+        .page_token
+        .as_deref()
+        .map(|token| {
+            PageToken::parse(token).ok_or_else(|| ApiError::bad_request("Invalid page_token"))
+        })
+        .transpose()?;
+    if token.is_some() && req.offset.is_some() {
+        return Err(ApiError::bad_request(
+            "offset and page_token can't be combined",
+        ));
+    }
+    let offset = token.as_ref().map_or(req.offset.unwrap_or(0), |t| t.offset);
+    let depth = offset.saturating_add(req.top_k);
+    if depth > search::MAX_PAGE_DEPTH {
+        return Err(ApiError::bad_request(format!(
+            "offset + top_k can't exceed {}",
+            search::MAX_PAGE_DEPTH
+        )));
+    }
+
+    let deep = SearchRequest {
+        top_k: depth + 1,
+        ..req.clone()
+    };
+    let hits = search_collection(state, collection, &deep, None, metric)?;
+    Ok(search::top_k_page(
+        hits,
+        metric,
+        offset,
+        req.top_k,
+        token.as_ref(),
+    ))
+}
+
+fn insert_next_page(headers: &mut HeaderMap, next: Option<NextPage>) {
+    let (name, token) = match next {
+        Some(NextPage::Cursor(cursor)) => ("x-next-cursor", cursor.encode()),
+        Some(NextPage::Token(token)) => ("x-next-page-token", token.encode()),
+        None => return,
+    };
+    if let Ok(value) = HeaderValue::from_str(&token) {
+        headers.insert(name, value);
+    }
+}
+
+/// Run a collection search, keeping only hits that match `filter` (if
+/// given) and going through the request's UDFs if it names any.
+fn search_collection(
+    state: &AppState,
+    collection: &Collection,
+    req: &SearchRequest,
+    filter: Option<&Filter>,
+    metric: DistanceMetric,
+) -> Result<Vec<SearchResult>, VectorDbError> {
+    let start = Instant::now();
+    let results = search_collection_inner(state, collection, req, filter, metric);
+    monitoring::record_search(&collection.name, start.elapsed());
+    results
+}
+
+fn search_collection_inner(
+    state: &AppState,
+    collection: &Collection,
+    req: &SearchRequest,
+    filter: Option<&Filter>,
+    metric: DistanceMetric,
+) -> Result<Vec<SearchResult>, VectorDbError> {
+    let search = || match (filter, req.exact) {
+        (Some(filter), false) => collection.search_filtered(&req.vector, req.top_k, metric, filter),
+        (Some(filter), true) => collection.search_filtered_by(
+            &req.vector,
+            req.top_k,
+            metric,
+            filter,
+            FilterStrategy::PreFilter,
+        ),
+        (None, false) => collection.search_with(&req.vector, req.top_k, metric),
+        (None, true) => collection.search_exact(&req.vector, req.top_k, metric),
+    };
+    if req.filter_udf.is_none() && req.score_udf.is_none() && !collection.hooks.has_search() {
+        // UDF searches aren't cached: a module can be replaced under the
+        // same name. Nor are searches with hooks, which must see each one.
+        let Some(cache) = &state.result_cache else {
+            return search();
+        };
+        let key = Fingerprint::new(
+            &collection.name,
+            collection.epoch(),
+            &req.vector,
+            format!(
+                "top_k={} metric={} exact={} filter={:?}",
+                req.top_k,
+                metric::name(metric),
+                req.exact,
+                filter
+            ),
+        );
+        let now = Instant::now();
+        if let Some(results) = cache.get(&key, now) {
+            monitoring::record_cache_lookup(true);
+            return Ok(results);
+        }
+        monitoring::record_cache_lookup(false);
+        let results = search()?;
+        cache.put(key, results.clone(), now);
+        return Ok(results);
+    }
+
+    #[cfg(feature = "wasm")]
+    {
+        let mut udf_filter = req
+            .filter_udf
+            .as_deref()
+            .map(|name| state.udfs.filter(name))
+            .transpose()?;
+        let mut scorer = req
+            .score_udf
+            .as_deref()
+            .map(|name| state.udfs.scorer(name))
+            .transpose()?;
+        collection.search_adjusted(&req.vector, req.top_k, metric, &mut |id, vector, score| {
+            if filter.is_some_and(|f| !f.matches(&vector.metadata)) {
+                return Ok(None);
+            }
+            if let Some(udf_filter) = udf_filter.as_mut() {
+                if !udf_filter.keep(id, vector)? {
+                    return Ok(None);
+                }
+            }
+            match scorer.as_mut() {
+                Some(scorer) => scorer.score(score, id, vector).map(Some),
+                None => Ok(Some(score)),
+            }
+        })
+    }
+    #[cfg(not(feature = "wasm"))]
+    {
+        let _ = (state, search);
+        Err(VectorDbError::InvalidParameter(
+            "filter_udf and score_udf need a server built with --features wasm".into(),
+        ))
+    }
+}
+
+/// Search one collection and stream the hits as Server-Sent Events.
+///
+/// Each hit is sent as its own `result` event, best first, followed by a
+/// `done` event with the count and timing, so a client can render the top
+/// matches as they arrive instead of waiting for one large JSON array.
+/// Ranking happens before the first event; the read lock is released
+/// before anything is sent, so a slow reader never holds up writers.
+/// Errors found before streaming starts (unknown collection, bad vector)
+/// come back as ordinary JSON errors. Aliases are routed as for
+/// `/api/collections/:name/search`.
+///
+/// GET /api/search/stream?collection=docs&vector=0.1,0.2,0.3&top_k=100
+async fn handler_search_stream(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(params): Query<StreamSearchQuery>,
+) -> Result<Response, ApiError> {
+    let vector = params.query_vector()?;
+    let start = Instant::now();
+    let results = {
+        let state = state.read().await;
+        let (variant, target) = match state.aliases.get(&params.collection) {
+            Some(alias) => {
+                let routing_key = headers.get("x-routing-key").and_then(|v| v.to_str().ok());
+                let (variant, target) = alias.route(routing_key);
+                (Some((alias, variant)), target)
+            }
+            None => (None, params.collection.as_str()),
+        };
+        let collection = state
+            .collections
+            .get(target)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+        let metric = metric::resolve(params.metric, Some(collection), params.exact)?;
+        let mut results = collection.search_with(&vector, params.top_k, metric)?;
+        monitoring::record_search(&collection.name, start.elapsed());
+        if let Some((alias, variant)) = variant {
+            alias.stats(variant).record(start.elapsed());
+        }
+        include_payloads(collection, params.include(), &mut results);
+        results
+    };
+
+    let done = serde_json::json!({
+        "count": results.len(),
+        "took_ms": start.elapsed().as_secs_f64() * 1000.0,
+    });
+    let events = results
+        .into_iter()
+        .enumerate()
+        .map(|(rank, hit)| {
+            let mut data = serde_json::to_value(hit).unwrap_or_default();
+            data["rank"] = (rank + 1).into();
+            Event::default().event("result").json_data(data)
+        })
+        .chain(std::iter::once(
+            Event::default().event("done").json_data(done),
+        ));
+
+    Ok(Sse::new(futures_util::stream::iter(events))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+/// Upper bound on query vectors per batch search. The whole batch is
+/// scored while holding the read lock.
+const MAX_BATCH_QUERIES: usize = 1_024;
+
+/// Search one collection with many query vectors, returning one result
+/// list per query, in order.
+///
+/// Exact batches are scored together as a blocked matrix product (see
+/// engine/gemm.rs), which reads every stored vector once per batch
+/// instead of once per query. `name` may be an alias; every query in the
+/// batch goes to the same variant.
+///
+/// POST /api/search/batch
+/// Body: { "collection": "docs", "vectors": [[0.1, 0.2], [0.3, 0.4]], "top_k": 10 }
+async fn handler_batch_search(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(req): Json<BatchSearchRequest>,
+) -> Result<Json<Vec<Vec<SearchResult>>>, ApiError> {
+    if req.vectors.is_empty() || req.vectors.len() > MAX_BATCH_QUERIES {
+        return Err(ApiError::bad_request(format!(
+            "vectors must hold between 1 and {} queries",
+            MAX_BATCH_QUERIES
+        )));
+    }
+
+    let state = state.read().await;
+    let (variant, target) = match state.aliases.get(&req.collection) {
+        Some(alias) => {
+            let routing_key = headers.get("x-routing-key").and_then(|v| v.to_str().ok());
+            let (variant, target) = alias.route(routing_key);
+            (Some((alias, variant)), target)
+        }
+        None => (None, req.collection.as_str()),
+    };
+    let collection = state
+        .collections
+        .get(target)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+    let metric = metric::resolve(req.metric, Some(collection), req.exact)?;
+
+    let queries: Vec<&[f32]> = req.vectors.iter().map(|v| v.as_slice()).collect();
+    let start = Instant::now();
+    let mut results = collection.search_batch(&queries, req.top_k, metric, req.exact)?;
+    monitoring::record_search(&collection.name, start.elapsed());
+    if let Some((alias, variant)) = variant {
+        alias.stats(variant).record(start.elapsed());
+    }
+    for hits in &mut results {
+        include_payloads(collection, req.include, hits);
+    }
+    Ok(Json(results))
+}
+
+/// Search several collections and merge the results.
+///
+/// Each collection is searched with its own metric, its scores are
+/// normalized, and the hits are merged into one list labeled by source.
+///
+/// POST /api/search/multi
+/// Body: { "collections": ["docs_en", "docs_de"], "vector": [...], "top_k": 10 }
+async fn handler_multi_search(
+    State(state): State<SharedState>,
+    Json(req): Json<MultiSearchRequest>,
+) -> Result<Json<Vec<MultiSearchResult>>, ApiError> {
+    if req.collections.is_empty() {
+        return Err(ApiError::bad_request("At least one collection is required"));
+    }
+    if req.vector.is_empty() {
+        return Err(ApiError::bad_request("Vector data cannot be empty"));
+    }
+
+    let state = state.read().await;
+
+    // Resolve and validate every collection before doing any work
+    let mut targets = Vec::with_capacity(req.collections.len());
+    for name in &req.collections {
+        let collection = state
+            .collections
+            .get(name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        if collection.dimension != req.vector.len() {
+            return Err(ApiError::bad_request(format!(
+                "Collection '{}' has dimension {}, query has {}",
+                name,
+                collection.dimension,
+                req.vector.len()
+            )));
+        }
+        targets.push(collection);
+    }
+
+    if req.normalization == ScoreNormalization::None
+        && targets.iter().any(|c| c.distance != targets[0].distance)
+    {
+        return Err(ApiError::bad_request(
+            "Collections use different metrics; choose min_max or z_score normalization",
+        ));
+    }
+
+    let mut merged = Vec::new();
+    for collection in targets {
+        let start = Instant::now();
+        let hits = collection.search(&req.vector, req.top_k)?;
+        monitoring::record_search(&collection.name, start.elapsed());
+        let normalized = search::normalize_scores(&hits, collection.distance, req.normalization);
+        merged.extend(
+            hits.into_iter()
+                .zip(normalized)
+                .map(|(hit, score)| MultiSearchResult {
+                    id: hit.id,
+                    collection: collection.name.clone(),
+                    score,
+                    raw_score: hit.score,
+                    probability: hit.probability,
+                    vector: None,
+                    metadata: None,
+                }),
+        );
+    }
+
+    // With normalization the merged scale is always "higher is better";
+    // without it, every collection shares one metric.
+    let metric = match req.normalization {
+        ScoreNormalization::None => state.collections[&req.collections[0]].distance,
+        _ => DistanceMetric::Cosine,
+    };
+    let mut top = search::TopK::new(req.top_k, metric);
+    for hit in merged {
+        top.push(hit.score, hit);
+    }
+    let mut results = top.into_sorted_vec();
+
+    if req.include.any() {
+        for hit in &mut results {
+            let Some(vector) = state.collections[&hit.collection].get(&hit.id) else {
+                continue;
+            };
+            if req.include.include_vector {
+                hit.vector = Some(vector.data.clone());
+            }
+            if req.include.include_metadata {
+                hit.metadata = Some(vector.metadata.clone());
+            }
+        }
+    }
+
+    Ok(Json(results))
+}
+
+/// Combine stored vectors (e.g. king − man + woman) and optionally search
+/// with the result, without the client downloading any of them.
+///
+/// POST /api/compute/arith
+/// Body: { "collection": "words", "add": ["king", "woman"], "subtract": ["man"],
+///         "mode": "sum", "normalize": true, "search": { "top_k": 5 } }
+async fn handler_arith(
+    State(state): State<SharedState>,
+    Json(req): Json<ArithRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let collection = match &req.collection {
+        Some(name) => Some(
+            state
+                .collections
+                .get(name)
+                .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?,
+        ),
+        None => None,
+    };
+
+    let vector =
+        arith::evaluate(
+            &req.add,
+            &req.subtract,
+            req.mode,
+            req.normalize,
+            |id| match collection {
+                Some(collection) => collection.get(id).map(|v| v.data.clone()),
+                None => match state.vectors.get(id) {
+                    Some(v) => Some(v.data.clone()),
+                    None => state.cold.get(id).map(|v| v.decode().data),
+                },
+            },
+        )?;
+
+    let results = match &req.search {
+        Some(params) => {
+            let metric = metric::resolve(params.metric, collection, false)?;
+            // Over-fetch so excluding the inputs still leaves top_k
+            let excluded = if params.exclude_inputs {
+                req.add.len() + req.subtract.len()
+            } else {
+                0
+            };
+            let k = params.top_k + excluded;
+            let hits = match collection {
+                Some(collection) => collection.search_with(&vector, k, metric)?,
+                None => flat_search(&state, &vector, metric, k),
+            };
+            let mut hits: Vec<SearchResult> = hits
+                .into_iter()
+                .filter(|hit| {
+                    !params.exclude_inputs
+                        || !(req.add.contains(&hit.id) || req.subtract.contains(&hit.id))
+                })
+                .collect();
+            hits.truncate(params.top_k);
+            Some(hits)
+        }
+        None => None,
+    };
+
+    Ok(Json(serde_json::json!({
+        "vector": vector,
+        "dimension": vector.len(),
+        "terms": req.add.len() + req.subtract.len(),
+        "results": results,
+    })))
+}
+
+/// Vectors processed per write-lock acquisition in bulk jobs.
+/// Small enough that searches interleave, large enough to amortize locking.
+const BULK_BATCH_SIZE: usize = 1_000;
+
+/// Set/remove metadata keys on every vector matching a filter.
+///
+/// Runs as a background job; poll GET /api/jobs/:id for progress.
+///
+/// POST /api/collections/:name/update_by_filter
+/// Body: { "filter": "category == \"old\"", "set": { "category": "new" }, "remove": ["tmp"] }
+async fn handler_update_by_filter(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateByFilterRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let filter = Filter::parse(&req.filter)?;
+    if req.set.is_empty() && req.remove.is_empty() {
+        return Err(ApiError::bad_request(
+            "Nothing to do: provide 'set' and/or 'remove'",
+        ));
+    }
+
+    let (job, ids) = {
+        let mut state = state.write().await;
+        let collection = state
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        collection.check_writable()?;
+        collection.validate_metadata_update(&req.set, &req.remove)?;
+
+        let ids = collection.ids();
+        let job = state.jobs.start("update_by_filter", ids.len() as u64);
+        (job, ids)
+    };
+
+    let job_id = job.id;
+    tracing::info!(
+        "Job {}: update_by_filter on '{}' ({} candidates)",
+        job_id,
+        name,
+        ids.len()
+    );
+
+    let op = Operation {
+        label: format!("job {} update_by_filter", job_id),
+        collection: Some(name.clone()),
+    };
+    tokio::spawn(locks::with_operation(op, async move {
+        let mut updated = 0u64;
+        for batch in ids.chunks(BULK_BATCH_SIZE) {
+            {
+                let mut state = state.write().await;
+                let Some(collection) = state.collections.get_mut(&name) else {
+                    job.fail(format!("collection '{}' was deleted", name));
+                    return;
+                };
+                if let Err(e) = collection.check_writable() {
+                    job.fail(format!("stopped after updating {} vectors: {}", updated, e));
+                    return;
+                }
+                // Staged, then logged, then applied, all under the lock
+                let staged: Vec<(&String, Vector)> = batch
+                    .iter()
+                    .filter_map(|id| {
+                        let vector =
+                            collection.metadata_updated(id, &filter, &req.set, &req.remove)?;
+                        Some((id, vector))
+                    })
+                    .collect();
+                let records: Vec<_> = staged
+                    .iter()
+                    .map(|(id, vector)| WalRecord::insert(Some(&name), id, vector))
+                    .collect();
+                if let Err(e) = state.log(&records) {
+                    job.fail(format!("failed to log updates: {}", e));
+                    return;
+                }
+                let collection = state.collections.get_mut(&name).unwrap();
+                updated += staged.len() as u64;
+                for (id, vector) in staged {
+                    collection.insert_prepared(id.clone(), vector);
+                }
+            } // Release the lock between batches so searches can run
+            job.advance(batch.len() as u64);
+            tokio::task::yield_now().await;
+        }
+
+        tracing::info!("Job {}: updated {} vectors", job.id, updated);
+        job.complete(serde_json::json!({ "updated": updated }));
+    }));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job_id": job_id })),
+    ))
+}
+
+/// Rewrite a cosine/dot collection's unnormalized vectors as unit
+/// vectors, keeping each one's original norm in `metadata.original_norm`.
+///
+/// Runs as a background job in batches, like update_by_filter. When it's
+/// done the collection's segment files are rewritten with the normalized
+/// data. With `?dry_run=true` it only counts what it would change. A
+/// protected collection needs `?force=true` and the admin key.
+///
+/// POST /api/collections/:name/normalize
+async fn handler_normalize(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<NormalizeQuery>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let (job, ids) = {
+        let mut state = state.write().await;
+        let collection = state
+            .collections
+            .get(&name)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", name)))?;
+        normalize::check_metric(collection.distance)?;
+        if !query.dry_run {
+            collection.check_writable()?;
+            check_destructive(&state, collection.protected, &name, query.force, &headers)?;
+            // The norm is recorded as a float; a schema that says otherwise
+            // would reject every rewrite
+            let norm = HashMap::from([(normalize::ORIGINAL_NORM_KEY.to_string(), "1.5".into())]);
+            collection.validate_metadata_update(&norm, &[])?;
+        }
+
+        let ids = collection.ids();
+        let job = state.jobs.start("normalize", ids.len() as u64);
+        (job, ids)
+    };
+
+    let job_id = job.id;
+    tracing::info!(
+        "Job {}: normalize '{}' ({} vectors{})",
+        job_id,
+        name,
+        ids.len(),
+        if query.dry_run { ", dry run" } else { "" }
+    );
+
+    let op = Operation {
+        label: format!("job {} normalize", job_id),
+        collection: Some(name.clone()),
+    };
+    tokio::spawn(locks::with_operation(op, async move {
+        let mut normalized = 0u64;
+        for batch in ids.chunks(BULK_BATCH_SIZE) {
+            {
+                let mut state = state.write().await;
+                let Some(collection) = state.collections.get_mut(&name) else {
+                    job.fail(format!("collection '{}' was deleted", name));
+                    return;
+                };
+                let writable = match query.dry_run {
+                    true => Ok(()),
+                    false => collection.check_writable(),
+                };
+                if let Err(e) = writable {
+                    job.fail(format!(
+                        "stopped after normalizing {} vectors: {}",
+                        normalized, e
+                    ));
+                    return;
+                }
+                if query.dry_run {
+                    normalized += batch
+                        .iter()
+                        .filter(|id| {
+                            collection
+                                .get(id)
+                                .is_some_and(|v| normalize::needs_normalizing(&v))
+                        })
+                        .count() as u64;
+                } else {
+                    // As in update_by_filter: stage, log, then apply
+                    let mut staged = Vec::new();
+                    for id in batch {
+                        match collection.normalized_point(id) {
+                            Ok(Some(vector)) => staged.push((id, vector)),
+                            Ok(None) => {}
+                            Err(e) => {
+                                job.fail(format!(
+                                    "stopped after normalizing {} vectors: {}",
+                                    normalized, e
+                                ));
+                                return;
+                            }
+                        }
+                    }
+                    let records: Vec<_> = staged
+                        .iter()
+                        .map(|(id, vector)| WalRecord::insert(Some(&name), id, vector))
+                        .collect();
+                    if let Err(e) = state.log(&records) {
+                        job.fail(format!("failed to log normalized vectors: {}", e));
+                        return;
+                    }
+                    let collection = state.collections.get_mut(&name).unwrap();
+                    normalized += staged.len() as u64;
+                    for (id, vector) in staged {
+                        collection.insert_prepared(id.clone(), vector);
+                    }
+                }
+            } // Release the lock between batches so searches can run
+            job.advance(batch.len() as u64);
+            tokio::task::yield_now().await;
+        }
+
+        if !query.dry_run && normalized > 0 {
+            let data_dir = state.read().await.data_dir.clone();
+            if let Err(e) = save_state(&state, &data_dir).await {
+                job.fail(format!(
+                    "normalized {} vectors but failed to rewrite segments: {}",
+                    normalized, e
+                ));
+                return;
+            }
+        }
+
+        tracing::info!("Job {}: normalized {} vectors", job.id, normalized);
+        job.complete(serde_json::json!({
+            "scanned": ids.len(),
+            "normalized": normalized,
+            "dry_run": query.dry_run,
+        }));
+    }));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job_id": job_id })),
+    ))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ALIAS HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Create or re-point an alias, optionally with an A/B experiment.
+///
+/// POST /api/aliases
+/// Body: { "name": "docs", "collection": "docs_v1",
+///         "experiment": { "collection": "docs_v2", "percent": 10 } }
+async fn handler_create_alias(
+    State(state): State<SharedState>,
+    Json(req): Json<CreateAliasRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let alias = Alias::from_request(&req)?;
+
+    let mut state = state.write().await;
+    if state.collections.contains_key(&alias.name) {
+        return Err(VectorDbError::AlreadyExists(format!(
+            "collection '{}' (aliases share the collection namespace)",
+            alias.name
+        ))
+        .into());
+    }
+
+    // Every variant must exist and accept the same queries
+    let mut dimension = None;
+    for target in alias.targets() {
+        let collection = state
+            .collections
+            .get(target)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+        match dimension {
+            Some(d) if d != collection.dimension => {
+                return Err(ApiError::bad_request(format!(
+                    "Experiment collections must share a dimension ({} vs {})",
+                    d, collection.dimension
+                )));
+            }
+            _ => dimension = Some(collection.dimension),
+        }
+    }
+
+    state.log(&[WalRecord::SetAlias(alias.config())])?;
+    let info = alias.info();
+    let replaced = state.aliases.insert(alias.name.clone(), alias).is_some();
+    tracing::info!("Alias '{}' → {}", req.name, info);
+
+    let status = if replaced {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((status, Json(info)))
+}
+
+/// List aliases with per-variant search counts.
+///
+/// GET /api/aliases
+async fn handler_list_aliases(State(state): State<SharedState>) -> Json<Vec<serde_json::Value>> {
+    let state = state.read().await;
+    let mut aliases: Vec<_> = state.aliases.values().collect();
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(aliases.into_iter().map(|a| a.info()).collect())
+}
+
+/// Get one alias and its experiment metrics.
+///
+/// GET /api/aliases/:name
+async fn handler_get_alias(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let alias = state
+        .aliases
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("alias '{}'", name)))?;
+    Ok(Json(alias.info()))
+}
+
+/// Delete an alias (the collections it points to are untouched).
+///
+/// DELETE /api/aliases/:name
+async fn handler_delete_alias(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut state = state.write().await;
+    if !state.aliases.contains_key(&name) {
+        return Err(VectorDbError::NotFound(format!("alias '{}'", name)).into());
+    }
+    state.log(&[WalRecord::RemoveAlias(name.clone())])?;
+    state.aliases.remove(&name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// QUERY TEMPLATE HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Register (or replace) a named query template.
+///
+/// The filter is parsed and the metric checked against the target here,
+/// once, instead of on every search.
+///
+/// PUT /api/search/template/:name
+/// Body: { "collection": "docs", "filter": "category == \"news\"",
+///         "top_k": 5, "metric": "cosine" }
+async fn handler_put_template(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<CreateTemplateRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let template = QueryTemplate::from_request(&name, &req)?;
+
+    let mut state = state.write().await;
+    let targets: Vec<&str> = match state.aliases.get(&template.collection) {
+        Some(alias) => alias.targets().collect(),
+        None => vec![template.collection.as_str()],
+    };
+    for target in targets {
+        let collection = state
+            .collections
+            .get(target)
+            .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+        metric::resolve(template.metric, Some(collection), template.exact)?;
+    }
+
+    state.log(&[WalRecord::SetTemplate {
+        name: name.clone(),
+        config: template.config(),
+    }])?;
+    let info = template.info();
+    let replaced = state.templates.insert(name.clone(), template).is_some();
+    tracing::info!("Query template '{}' → {}", name, req.collection);
+
+    let status = if replaced {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((status, Json(info)))
+}
+
+/// List query templates with their invocation counts.
+///
+/// GET /api/search/templates
+async fn handler_list_templates(State(state): State<SharedState>) -> Json<Vec<serde_json::Value>> {
+    let state = state.read().await;
+    let mut templates: Vec<_> = state.templates.values().collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(templates.into_iter().map(|t| t.info()).collect())
+}
+
+/// Get one query template.
+///
+/// GET /api/search/template/:name
+async fn handler_get_template(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let template = state
+        .templates
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("template '{}'", name)))?;
+    Ok(Json(template.info()))
+}
+
+/// Delete a query template.
+///
+/// DELETE /api/search/template/:name
+async fn handler_delete_template(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut state = state.write().await;
+    if !state.templates.contains_key(&name) {
+        return Err(VectorDbError::NotFound(format!("template '{}'", name)).into());
+    }
+    state.log(&[WalRecord::RemoveTemplate(name.clone())])?;
+    state.templates.remove(&name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run a query template with the given vector.
+///
+/// Aliases are routed as for `/api/collections/:name/search`, with the
+/// same response headers.
+///
+/// POST /api/search/template/:name
+/// Body: { "vector": [0.1, 0.2] }
+async fn handler_template_search(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<TemplateSearchRequest>,
+) -> Result<(HeaderMap, Json<Vec<SearchResult>>), ApiError> {
+    let state = state.read().await;
+    let template = state
+        .templates
+        .get(&name)
+        .ok_or_else(|| VectorDbError::NotFound(format!("template '{}'", name)))?;
+    template.record();
+    let search = template.request(req.vector);
+
+    let mut response_headers = HeaderMap::new();
+    let (variant, target) = match state.aliases.get(&template.collection) {
+        Some(alias) => {
+            let routing_key = headers.get("x-routing-key").and_then(|v| v.to_str().ok());
+            let (variant, target) = alias.route(routing_key);
+            response_headers.insert(
+                "x-vectordb-variant",
+                HeaderValue::from_static(variant.as_str()),
+            );
+            if let Ok(value) = HeaderValue::from_str(target) {
+                response_headers.insert("x-vectordb-collection", value);
+            }
+            (Some((alias, variant)), target)
+        }
+        None => (None, template.collection.as_str()),
+    };
+    let collection = state
+        .collections
+        .get(target)
+        .ok_or_else(|| VectorDbError::NotFound(format!("collection '{}'", target)))?;
+
+    let metric = metric::resolve(search.metric, Some(collection), search.exact)?;
+    let start = Instant::now();
+    let mut results = search_collection(&state, collection, &search, template.filter(), metric)?;
+    if let Some((alias, variant)) = variant {
+        alias.stats(variant).record(start.elapsed());
+    }
+    include_payloads(collection, req.include, &mut results);
+    Ok((response_headers, Json(results)))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCOPED KEYS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Load the collections `reach` covers that startup left unloaded. Checks
+/// under the read lock first, so requests to loaded collections don't
+/// queue for the write lock.
+async fn ensure_loaded(state: &SharedState, reach: Reach) -> Result<(), VectorDbError> {
+    let pending = |state: &AppState| match &reach {
+        Reach::Nothing => Vec::new(),
+        Reach::One(name) => state
+            .reachable(name)
+            .into_iter()
+            .filter(|n| state.unloaded.contains_key(n))
+            .collect(),
+        Reach::Any => state.unloaded.keys().cloned().collect(),
+    };
+    if pending(&*state.read().await).is_empty() {
+        return Ok(());
+    }
+    let mut state = state.write().await;
+    for name in pending(&state) {
+        state.load_collection(&name)?;
+    }
+    Ok(())
+}
+
+/// Middleware: read the segments of the collections a request can reach
+/// before its handler runs, so handlers only ever see loaded collections
+async fn load_segments(State(state): State<SharedState>, req: Request, next: Next) -> Response {
+    let reach = Reach::of(req.method(), req.uri().path());
+    if let Err(e) = ensure_loaded(&state, reach).await {
+        return ApiError::from(e).into_response();
+    }
+    next.run(req).await
+}
+
+/// Middleware: let through only requests whose key or access token allows
+/// what they do (see src/auth.rs and src/oidc.rs), leaving the credential's
+/// `Principal` in the request for handlers that reach a second collection.
+async fn authorize(State(check): State<KeyCheck>, mut req: Request, next: Next) -> Response {
+    let Some(access) = Access::of(req.method(), req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let Some(credential) = api_key(req.headers()) else {
+        return ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            message: "An API key or access token is required (X-API-Key or Authorization: Bearer)"
+                .into(),
+            fields: Vec::new(),
+        }
+        .into_response();
+    };
+    let principal = match check.authenticate(credential, SystemClock.unix_secs()) {
+        Ok(principal) => principal,
+        Err(message) => {
+            return ApiError {
+                status: StatusCode::UNAUTHORIZED,
+                message,
+                fields: Vec::new(),
+            }
+            .into_response()
+        }
+    };
+    if let Err(e) = require(Some(&principal), &access) {
+        return e.into_response();
+    }
+    req.extensions_mut().insert(principal);
+    next.run(req).await
+}
+
+/// Does the request's credential allow `access`? For the collections a
+/// request reaches besides the one in its path; `principal` is None when
+/// keys aren't enforced.
+fn require(principal: Option<&Principal>, access: &Access) -> Result<(), ApiError> {
+    match principal {
+        Some(principal) if !principal.allows(access) => Err(ApiError {
+            status: StatusCode::FORBIDDEN,
+            message: format!("This {} may not {}", principal.kind(), access),
+            fields: Vec::new(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Background task: read the OIDC provider's signing keys again every
+/// JWKS_REFRESH_INTERVAL, so rotated keys are picked up.
+async fn jwks_refresher(verifier: Arc<Verifier>) {
+    let mut ticker = tokio::time::interval(oidc::JWKS_REFRESH_INTERVAL);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        match verifier.refresh().await {
+            Ok(count) => tracing::debug!("Reloaded {} OIDC signing keys", count),
+            Err(e) => tracing::warn!("Keeping the current OIDC signing keys: {}", e),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ADMIN SCOPE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Admin endpoints. With an admin key they all require it, and the
+/// /debug/pprof profiling endpoints are mounted (in `pprof` builds).
+/// On a separate admin listener with [admin_tls], clients also need a
+/// certificate from the configured CA (see src/tls.rs).
+fn admin_routes(admin_key: Option<Arc<String>>) -> Router<SharedState> {
+    let admin = Router::new()
+        .route("/api/admin/locks", get(handler_locks))
+        .route("/api/admin/fds", get(handler_fds))
+        .route("/api/admin/usage", get(handler_usage))
+        .route(
+            "/api/admin/collections/:name/protection",
+            put(handler_set_protection),
+        )
+        .route(
+            "/api/admin/collections/:name/read_only",
+            put(handler_set_read_only),
+        );
+    #[cfg(feature = "wasm")]
+    let admin = admin
+        .route("/api/admin/udfs", get(handler_list_udfs))
+        .route(
+            "/api/admin/udfs/:name",
+            put(handler_put_udf).delete(handler_delete_udf),
+        );
+    // Test builds only: arm and clear injected I/O faults
+    #[cfg(feature = "fault-injection")]
+    let admin = admin.route(
+        "/admin/faults",
+        get(handler_list_faults)
+            .post(handler_arm_fault)
+            .delete(handler_clear_faults),
+    );
+    let Some(key) = admin_key else {
+        #[cfg(feature = "pprof")]
+        tracing::info!("Profiling endpoints disabled: set VECTORDB_ADMIN_KEY to enable them");
+        return admin;
+    };
+
+    #[cfg(feature = "pprof")]
+    let admin = admin
+        .route("/debug/pprof/profile", get(handler_cpu_profile))
+        .route("/debug/pprof/heap", get(handler_heap_profile));
+    admin.route_layer(middleware::from_fn_with_state(key, require_admin))
+}
+
+/// Middleware: let through only requests presenting the admin key
+async fn require_admin(State(admin_key): State<Arc<String>>, req: Request, next: Next) -> Response {
+    match api_key(req.headers()) {
+        Some(key) if auth::constant_time_eq(key.as_bytes(), admin_key.as_bytes()) => {
+            next.run(req).await
+        }
+        Some(_) => ApiError {
+            status: StatusCode::FORBIDDEN,
+            message: "This endpoint requires the admin key".into(),
+            fields: Vec::new(),
+        }
+        .into_response(),
+        None => ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            message: "This endpoint requires the admin key (X-API-Key or Authorization: Bearer)"
+                .into(),
+            fields: Vec::new(),
+        }
+        .into_response(),
+    }
+}
+
+/// Sample the CPU and return a pprof protobuf or flamegraph SVG.
+///
+/// GET /debug/pprof/profile?seconds=30&format=proto|flamegraph
+#[cfg(feature = "pprof")]
+async fn handler_cpu_profile(
+    Query(query): Query<profiling::ProfileQuery>,
+) -> Result<Response, ApiError> {
+    tracing::info!("Taking a {}s CPU profile", query.seconds);
+    let (content_type, body) = profiling::cpu_profile(&query).await?;
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// Dump the sampled heap profile (jemalloc format, for `jeprof`).
+///
+/// GET /debug/pprof/heap
+#[cfg(feature = "pprof")]
+async fn handler_heap_profile() -> Result<Response, ApiError> {
+    let body = profiling::heap_profile()?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], body).into_response())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FAULT INJECTION (feature = "fault-injection")
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// List armed faults and how often each has fired.
+///
+/// GET /admin/faults
+#[cfg(feature = "fault-injection")]
+async fn handler_list_faults() -> Json<Vec<vectordb::faults::Fault>> {
+    Json(vectordb::faults::list())
+}
+
+/// Arm a fault at an I/O point.
+///
+/// POST /admin/faults
+/// Body: { "point": "fsync", "action": { "type": "fail" }, "skip": 2, "times": 1 }
+#[cfg(feature = "fault-injection")]
+async fn handler_arm_fault(Json(fault): Json<vectordb::faults::Fault>) -> StatusCode {
+    tracing::warn!("Arming fault: {:?}", fault);
+    vectordb::faults::arm(fault);
+    StatusCode::NO_CONTENT
+}
+
+/// Disarm every fault.
+///
+/// DELETE /admin/faults
+#[cfg(feature = "fault-injection")]
+async fn handler_clear_faults() -> StatusCode {
+    vectordb::faults::clear(None);
+    StatusCode::NO_CONTENT
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// GRPC API (feature = "grpc")
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The gRPC service from proto/vectordb.proto. Each RPC goes through the
+/// same code as its HTTP route, on the same state.
+///
+/// With scoped keys or OIDC configured, an interceptor authenticates every
+/// call's `x-api-key` or `authorization` metadata as the HTTP middleware
+/// does, and each call checks the collection its message names.
+#[cfg(feature = "grpc")]
+// Interceptors must return tonic's (large) Status as their error
+#[allow(clippy::result_large_err)]
+mod grpc_api {
+    use super::*;
+    use vectordb::grpc::{self, proto};
+    use vectordb::models::PointInput;
+
+    impl From<ApiError> for tonic::Status {
+        fn from(err: ApiError) -> Self {
+            grpc::status(err.status.as_u16(), err.message)
+        }
+    }
+
+    struct Service {
+        state: SharedState,
+    }
+
+    impl Service {
+        /// Read the collection's segment if it hasn't been yet, as the
+        /// load_segments middleware does over HTTP
+        async fn load(&self, collection: &str) -> Result<(), tonic::Status> {
+            ensure_loaded(&self.state, Reach::One(collection.to_string()))
+                .await
+                .map_err(|e| ApiError::from(e).into())
+        }
+    }
+
+    /// Interceptor: resolve the call's credential to a `Principal` (left in
+    /// the request's extensions), or refuse the call
+    fn authenticate(
+        check: &KeyCheck,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let headers = request.metadata().clone().into_headers();
+        let credential = api_key(&headers).ok_or_else(|| {
+            tonic::Status::unauthenticated(
+                "An API key or access token is required (x-api-key or authorization: Bearer)",
+            )
+        })?;
+        let principal = check
+            .authenticate(credential, SystemClock.unix_secs())
+            .map_err(tonic::Status::unauthenticated)?;
+        request.extensions_mut().insert(principal);
+        Ok(request)
+    }
+
+    /// The call's principal, after checking it allows `verb` on `collection`
+    fn authorize<T>(
+        request: &tonic::Request<T>,
+        verb: Verb,
+        collection: &str,
+    ) -> Result<Option<Principal>, tonic::Status> {
+        let principal = request.extensions().get::<Principal>().cloned();
+        require(principal.as_ref(), &Access::on(verb, collection))?;
+        Ok(principal)
+    }
+
+    #[tonic::async_trait]
+    impl proto::vector_db_server::VectorDb for Service {
+        async fn insert(
+            &self,
+            request: tonic::Request<proto::InsertRequest>,
+        ) -> Result<tonic::Response<proto::InsertResponse>, tonic::Status> {
+            authorize(&request, Verb::Write, &request.get_ref().collection)?;
+            self.load(&request.get_ref().collection).await?;
+            let req = request.into_inner();
+            let upsert = UpsertRequest {
+                points: req
+                    .points
+                    .into_iter()
+                    .map(|p| PointInput {
+                        id: p.id,
+                        vector: p.vector,
+                        metadata: p.metadata,
+                        shadow_vector: None,
+                        sparse: None,
+                    })
+                    .collect(),
+                on_conflict: grpc::on_conflict(req.on_conflict).map_err(ApiError::from)?,
+                atomic: req.atomic,
+            };
+            let upserted = upsert_points(&mut *self.state.write().await, &req.collection, upsert)?;
+            if upserted.rejected {
+                let first = upserted
+                    .results
+                    .iter()
+                    .find_map(|r| Some(format!("'{}': {}", r.id, r.error.as_ref()?)));
+                return Err(tonic::Status::invalid_argument(format!(
+                    "{} (first: {})",
+                    upserted.rejection(),
+                    first.unwrap_or_default()
+                )));
+            }
+            Ok(tonic::Response::new(proto::InsertResponse {
+                count: upserted.count as u64,
+                results: upserted
+                    .results
+                    .into_iter()
+                    .map(|r| proto::PointResult {
+                        id: r.id,
+                        status: proto::PointStatus::from(r.status).into(),
+                        error: r.error.unwrap_or_default(),
+                    })
+                    .collect(),
+            }))
+        }
+
+        async fn search(
+            &self,
+            request: tonic::Request<proto::SearchRequest>,
+        ) -> Result<tonic::Response<proto::SearchResponse>, tonic::Status> {
+            let principal = authorize(&request, Verb::Search, &request.get_ref().collection)?;
+            self.load(&request.get_ref().collection).await?;
+            // x-routing-key picks the experiment variant, as over HTTP
+            let headers = request.metadata().clone().into_headers();
+            let req = request.into_inner();
+            let search = SearchRequest {
+                vector: req.vector,
+                // 0 is proto3's "unset"; use the HTTP default
+                top_k: match req.top_k {
+                    0 => 10,
+                    k => k as usize,
+                },
+                metric: grpc::metric(req.metric).map_err(ApiError::from)?,
+                exact: req.exact,
+                filter_udf: None,
+                score_udf: None,
+                radius: None,
+                cursor: None,
+                min_seq: None,
+                offset: None,
+                page_token: None,
+                include: IncludeFields {
+                    include_vector: req.include_vector,
+                    include_metadata: req.include_metadata,
+                },
+            };
+            let (_, Json(results)) = handler_collection_search(
+                State(self.state.clone()),
+                Path(req.collection),
+                principal.map(Extension),
+                headers,
+                Json(search),
+            )
+            .await?;
+            Ok(tonic::Response::new(proto::SearchResponse {
+                hits: results.into_iter().map(Into::into).collect(),
+            }))
+        }
+
+        async fn get(
+            &self,
+            request: tonic::Request<proto::GetRequest>,
+        ) -> Result<tonic::Response<proto::GetResponse>, tonic::Status> {
+            authorize(&request, Verb::Read, &request.get_ref().collection)?;
+            self.load(&request.get_ref().collection).await?;
+            let req = request.into_inner();
+            let Json(mut body) =
+                handler_get_point(State(self.state.clone()), Path((req.collection, req.id)))
+                    .await?;
+            let version = body["version"].as_u64().unwrap_or_default();
+            let vector: Vector = serde_json::from_value(body["vector"].take())
+                .map_err(|e| tonic::Status::internal(e.to_string()))?;
+            let id = body["id"].as_str().unwrap_or_default().to_string();
+            Ok(tonic::Response::new(proto::GetResponse {
+                point: Some(proto::Point::new(id, vector)),
+                version,
+            }))
+        }
+
+        async fn delete(
+            &self,
+            request: tonic::Request<proto::DeleteRequest>,
+        ) -> Result<tonic::Response<proto::DeleteResponse>, tonic::Status> {
+            authorize(&request, Verb::Write, &request.get_ref().collection)?;
+            self.load(&request.get_ref().collection).await?;
+            let req = request.into_inner();
+            let _ = handler_delete_point(
+                State(self.state.clone()),
+                Path((req.collection, req.id)),
+                Query(ConditionQuery { condition: None }),
+            )
+            .await?;
+            Ok(tonic::Response::new(proto::DeleteResponse {}))
+        }
+    }
+
+    /// Serve the gRPC API on `addr` until `shutdown` completes; await the
+    /// handle to let in-flight calls finish. With `check`, every call
+    /// needs a credential it accepts.
+    pub(super) async fn start(
+        addr: std::net::SocketAddr,
+        state: SharedState,
+        check: Option<KeyCheck>,
+        shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> tokio::task::JoinHandle<()> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .expect("Failed to bind the gRPC listener");
+        tracing::info!("gRPC listening on {}", listener.local_addr().unwrap());
+        let incoming = tonic::transport::server::TcpIncoming::from_listener(listener, true, None)
+            .expect("Failed to accept gRPC connections");
+
+        let service = proto::vector_db_server::VectorDbServer::with_interceptor(
+            Service { state },
+            move |request| match &check {
+                Some(check) => authenticate(check, request),
+                None => Ok(request),
+            },
+        );
+        tokio::spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, shutdown)
+                .await;
+            if let Err(e) = result {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// JOB HANDLERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// List background jobs.
+///
+/// GET /api/jobs
+async fn handler_list_jobs(State(state): State<SharedState>) -> Json<Vec<serde_json::Value>> {
+    let state = state.read().await;
+    Json(state.jobs.list().iter().map(|j| j.snapshot()).collect())
+}
+
+/// Get a background job's status, progress and ETA.
+///
+/// GET /api/jobs/:id
+async fn handler_get_job(
+    State(state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let state = state.read().await;
+    let job = state
+        .jobs
+        .get(id)
+        .ok_or_else(|| ApiError::not_found(format!("Job {} not found", id)))?;
+    Ok(Json(job.snapshot()))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DIAGNOSTICS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Show who holds the state lock and who's queued for it, overall and
+/// grouped by the collection each request targets.
+///
+/// Reads the lock's registry without taking the lock itself, so it still
+/// answers while a stuck writer is blocking everything else.
+///
+/// GET /api/admin/locks
+async fn handler_locks(State(state): State<SharedState>) -> Json<serde_json::Value> {
+    let snapshot = state.snapshot();
+    Json(serde_json::json!({
+        "warn_after_ms": snapshot.warn_after_ms,
+        "holders": snapshot.holders,
+        "waiters": snapshot.waiters,
+        "collections": snapshot.by_collection(),
+    }))
+}
+
+/// File descriptor budget and use: segments, sockets, and storage files
+/// against RLIMIT_NOFILE, plus what the process really has open.
+///
+/// GET /api/admin/fds
+async fn handler_fds() -> Json<fds::FdUsage> {
+    Json(fds::usage())
+}
+
+/// Usage per API key fingerprint, by day and totalled over the range, for
+/// chargeback. Raw keys passed as `?key=` are fingerprinted before lookup.
+///
+/// GET /api/admin/usage?from=2026-10-01&to=2026-10-31
+async fn handler_usage(
+    State(state): State<SharedState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let from = query.from.as_deref().map(usage::parse_date).transpose()?;
+    let to = query.to.as_deref().map(usage::parse_date).transpose()?;
+    let fingerprint = match (query.key, query.fingerprint) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request(
+                "Pass either 'key' or 'fingerprint', not both",
+            ))
+        }
+        (Some(key), None) => Some(usage::fingerprint(&key)),
+        (None, fingerprint) => fingerprint,
+    };
+
+    let usage = state.read().await.usage.clone();
+    let days = usage.query(from, to, fingerprint.as_deref());
+    Ok(Json(serde_json::json!({
+        "totals": usage::totals(&days),
+        "days": days,
+    })))
 }
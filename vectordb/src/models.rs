@@ -7,215 +7,659 @@
 // - Phase 2 (Storage) serializes Vector to binary
 // - Phase 3 (Search) uses DistanceMetric and SearchResult
 // - Phase 4 (Hybrid) extends metadata filtering
+//
+// The types that make up the HTTP API's JSON (vectors, metrics, collection,
+// point and search bodies) live in crates/vectordb-types, where the Rust
+// client can share them, and are re-exported here. What remains is
+// server-side: errors, write outcomes, and the bodies of endpoints the
+// client doesn't cover yet.
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
+pub use vectordb_types::{
+    AutoIndex, Calibration, CollectionInfo, CollectionSchema, ComputedField,
+    CreateCollectionRequest, DistanceMetric, ErrorResponse, FieldError, FieldSchema, FieldType,
+    HnswConfig, IdStrategy, IncludeFields, OnConflict, PointInput, PointResult, PointStatus,
+    SearchRequest, SearchResult, SparseVector, UpsertRequest, UpsertResponse, Vector, VectorType,
+};
+
 // ═══════════════════════════════════════════════════════════════════════════
-// CORE DATA TYPES
+// SEARCH TYPES
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// A vector embedding with metadata.
-///
-/// This is the fundamental unit stored in our database.
-/// Each vector has embedding data and optional key-value metadata.
-///
-/// # Example
-/// ```
-/// use vectordb::models::Vector;
-/// let v = Vector::new(vec![0.1, 0.2, 0.3]);
-/// assert_eq!(v.dimension(), 3);
-/// ```
+fn default_top_k() -> usize {
+    10
+}
+
+/// How per-collection scores are made comparable before merging.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreNormalization {
+    /// Keep raw scores (only valid when all collections share a metric)
+    None,
+
+    /// Rescale each collection's scores to 0..1 (1 = best hit)
+    #[default]
+    MinMax,
+
+    /// Standard score per collection: (score - mean) / stddev
+    ZScore,
+}
+
+/// Search several collections with one query (POST /api/search/multi).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Vector {
-    /// The raw embedding data (e.g., 768 floats for BERT)
-    pub data: Vec<f32>,
+pub struct MultiSearchRequest {
+    /// Collections to search; all must share the query's dimension
+    pub collections: Vec<String>,
+
+    /// The query vector
+    pub vector: Vec<f32>,
 
-    /// Key-value metadata: {"title": "Document Name", "category": "tech"}
+    /// Number of merged results to return (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// How to make scores comparable across collections (default: min_max)
     #[serde(default)]
-    pub metadata: HashMap<String, String>,
+    pub normalization: ScoreNormalization,
+
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
 }
 
-impl Vector {
-    /// Create a new vector with just data (no metadata)
-    pub fn new(data: Vec<f32>) -> Self {
-        Self {
-            data,
-            metadata: HashMap::new(),
-        }
-    }
+/// Search one collection with many query vectors (POST /api/search/batch).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSearchRequest {
+    /// Collection (or alias) to search
+    pub collection: String,
 
-    /// Create a vector with metadata
-    pub fn with_metadata(data: Vec<f32>, metadata: HashMap<String, String>) -> Self {
-        Self { data, metadata }
-    }
+    /// The query vectors; results come back in the same order
+    pub vectors: Vec<Vec<f32>>,
 
-    /// Get the dimensionality of this vector
-    pub fn dimension(&self) -> usize {
-        self.data.len()
-    }
+    /// Number of results per query (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
 
-    /// Calculate the L2 norm (magnitude)
-    pub fn magnitude(&self) -> f32 {
-        self.data.iter().map(|x| x * x).sum::<f32>().sqrt()
-    }
+    /// Rank by this metric instead of the collection's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<DistanceMetric>,
 
-    /// Normalize the vector in-place (make magnitude = 1.0)
-    pub fn normalize(&mut self) {
-        let mag = self.magnitude();
-        if mag > 0.0 {
-            for x in &mut self.data {
-                *x /= mag;
-            }
-        }
-    }
+    /// Skip any graph index and scan every point
+    #[serde(default)]
+    pub exact: bool,
 
-    /// Get a normalized copy (original unchanged)
-    pub fn normalized(&self) -> Self {
-        let mut copy = self.clone();
-        copy.normalize();
-        copy
-    }
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
 }
 
-// ═══════════════════════════════════════════════════════════════════════════
-// DISTANCE METRICS
-// ═══════════════════════════════════════════════════════════════════════════
+/// Search a collection's sparse embeddings
+/// (POST /api/collections/:name/search/sparse).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SparseSearchRequest {
+    /// The sparse query; indices needn't be sorted
+    pub vector: SparseVector,
 
-/// Supported distance/similarity metrics.
-///
-/// Different use cases require different metrics:
-/// - Cosine: Good for text embeddings (direction matters, not magnitude)
-/// - Euclidean: Good for spatial data
-/// - Dot: Fast, works well with normalized vectors
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum DistanceMetric {
-    /// Cosine similarity: 1 = identical, 0 = orthogonal, -1 = opposite
-    Cosine,
+    /// Number of results to return (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
 
-    /// Euclidean distance: 0 = identical, larger = more different
-    Euclidean,
+    /// Only points whose metadata matches this filter expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
 
-    /// Dot product: higher = more similar (assumes normalized vectors)
-    Dot,
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
 }
 
-impl DistanceMetric {
-    /// Calculate distance/similarity between two vectors.
-    ///
-    /// # Panics
-    /// Silently truncates if vectors have different lengths (zip behavior).
-    /// Use `validate_dimensions` before calling this in production.
-    pub fn calculate(&self, a: &[f32], b: &[f32]) -> f32 {
-        match self {
-            DistanceMetric::Cosine => {
-                let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
-                let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-                let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-                if norm_a == 0.0 || norm_b == 0.0 {
-                    0.0
-                } else {
-                    dot / (norm_a * norm_b)
-                }
-            }
-            DistanceMetric::Euclidean => a
-                .iter()
-                .zip(b)
-                .map(|(x, y)| (x - y).powi(2))
-                .sum::<f32>()
-                .sqrt(),
-            DistanceMetric::Dot => a.iter().zip(b).map(|(x, y)| x * y).sum(),
-        }
-    }
+/// How a hybrid search combines its dense and sparse rankings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Fusion {
+    /// Reciprocal Rank Fusion: a hit scores the sum of 1 / (k + rank)
+    /// over the rankings it appears in, so only positions matter
+    Rrf {
+        #[serde(default = "default_rrf_k")]
+        k: f32,
+    },
+
+    /// Weighted sum of each ranking's min-max normalized scores (a hit
+    /// missing from a ranking gets 0 from it)
+    Weighted {
+        #[serde(default = "default_fusion_weight")]
+        dense_weight: f32,
+        #[serde(default = "default_fusion_weight")]
+        sparse_weight: f32,
+    },
 }
 
-impl Default for DistanceMetric {
+impl Default for Fusion {
     fn default() -> Self {
-        DistanceMetric::Cosine
+        Self::Rrf { k: default_rrf_k() }
     }
 }
 
-// ═══════════════════════════════════════════════════════════════════════════
-// SEARCH TYPES
-// ═══════════════════════════════════════════════════════════════════════════
+fn default_rrf_k() -> f32 {
+    60.0
+}
+
+fn default_fusion_weight() -> f32 {
+    0.5
+}
+
+/// Search a collection's dense vectors and sparse embeddings at once and
+/// fuse the two rankings (POST /api/collections/:name/search/hybrid).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridSearchRequest {
+    /// The dense query
+    pub vector: Vec<f32>,
+
+    /// The sparse query; indices needn't be sorted
+    pub sparse: SparseVector,
+
+    /// Number of fused results to return (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Hits taken from each ranking before fusing (default: 4 × top_k)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<usize>,
+
+    /// How to combine the rankings (default: RRF with k = 60)
+    #[serde(default)]
+    pub fusion: Fusion,
 
-/// A single search result with ID and similarity score.
+    /// Only points whose metadata matches this filter expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
+}
+
+/// A merged hit labeled with the collection it came from.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchResult {
+pub struct MultiSearchResult {
     /// The ID of the matching vector
     pub id: String,
 
-    /// Similarity/distance score
+    /// Collection the vector lives in
+    pub collection: String,
+
+    /// Normalized score used for the merged ranking
     pub score: f32,
+
+    /// Score as computed by the collection's own metric
+    pub raw_score: f32,
+
+    /// Calibrated relevance probability, if the collection has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f32>,
+
+    /// The matching vector's data (searches with `include_vector` only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+
+    /// The matching vector's metadata (searches with `include_metadata`
+    /// only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
 }
 
-/// Parameters for a search query (received from clients).
+/// How the terms of a vector arithmetic expression are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArithMode {
+    /// Σ add − Σ subtract
+    #[default]
+    Sum,
+    /// The sum divided by the number of terms
+    Average,
+}
+
+/// Vector arithmetic over stored IDs (POST /api/compute/arith).
+///
+/// `{ "add": ["king", "woman"], "subtract": ["man"] }` computes
+/// king − man + woman; `search` then finds what's nearest to it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchRequest {
-    /// The query vector
-    pub vector: Vec<f32>,
+pub struct ArithRequest {
+    /// Collection holding the IDs (omit for the flat /vectors store)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+
+    /// IDs whose vectors are added
+    #[serde(default)]
+    pub add: Vec<String>,
+
+    /// IDs whose vectors are subtracted
+    #[serde(default)]
+    pub subtract: Vec<String>,
+
+    #[serde(default)]
+    pub mode: ArithMode,
+
+    /// Scale the result to unit length
+    #[serde(default)]
+    pub normalize: bool,
+
+    /// Also search with the result as the query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<ArithSearch>,
+}
 
+/// The optional search step of an arithmetic request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArithSearch {
     /// Number of results to return (default: 10)
     #[serde(default = "default_top_k")]
     pub top_k: usize,
 
-    /// Distance metric to use (default: cosine)
-    #[serde(default)]
-    pub metric: DistanceMetric,
+    /// Distance metric (default: the collection's, else the global default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<DistanceMetric>,
+
+    /// Leave the input IDs out of the results (default: true) — "king"
+    /// is almost always the nearest neighbour of king − man + woman
+    #[serde(default = "default_exclude_inputs")]
+    pub exclude_inputs: bool,
 }
 
-fn default_top_k() -> usize {
-    10
+fn default_exclude_inputs() -> bool {
+    true
 }
 
-impl SearchRequest {
-    /// Create a simple search request
-    pub fn new(vector: Vec<f32>, top_k: usize) -> Self {
-        Self {
-            vector,
-            top_k,
-            metric: DistanceMetric::Cosine,
+impl From<WriteOutcome> for PointStatus {
+    fn from(outcome: WriteOutcome) -> Self {
+        match outcome {
+            WriteOutcome::Inserted => Self::Inserted,
+            WriteOutcome::Overwritten => Self::Overwritten,
+            WriteOutcome::Merged => Self::Merged,
+            WriteOutcome::Skipped => Self::Skipped,
         }
     }
 }
 
-/// Wrapper for upsert payloads
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UpsertRequest {
-    pub points: Vec<PointInput>,
+/// Result entry for a point that was written
+pub fn point_ok(id: String, outcome: WriteOutcome) -> PointResult {
+    PointResult::ok(id, outcome.into())
 }
 
-/// A single point to insert/update
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PointInput {
-    pub id: String,
-    pub vector: Vec<f32>,
-    #[serde(default)]
-    pub metadata: HashMap<String, String>,
+/// Result entry for a point that failed, with its schema violations
+pub fn point_failed(id: String, err: VectorDbError) -> PointResult {
+    let error = err.to_string();
+    let fields = match err {
+        VectorDbError::SchemaViolation(fields) => fields,
+        _ => Vec::new(),
+    };
+    PointResult::failed(id, error, fields)
+}
+
+/// What a single write did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Inserted,
+    Overwritten,
+    Merged,
+    Skipped,
+}
+
+/// Per-outcome counts for a batch of writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WriteCounts {
+    pub inserted: usize,
+    pub overwritten: usize,
+    pub merged: usize,
+    pub skipped: usize,
+}
+
+impl WriteCounts {
+    pub fn record(&mut self, outcome: WriteOutcome) {
+        match outcome {
+            WriteOutcome::Inserted => self.inserted += 1,
+            WriteOutcome::Overwritten => self.overwritten += 1,
+            WriteOutcome::Merged => self.merged += 1,
+            WriteOutcome::Skipped => self.skipped += 1,
+        }
+    }
+
+    /// Points actually stored
+    pub fn written(&self) -> usize {
+        self.inserted + self.overwritten + self.merged
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
 // COLLECTION TYPES
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// Request to create a new collection
+/// One labeled example for calibration: either a raw `score`, or a
+/// `query` vector and the `id` of a stored point to score it against.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CreateCollectionRequest {
-    pub name: String,
-    pub dimension: usize,
+pub struct LabeledPair {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Whether the pair is a relevant match
+    pub relevant: bool,
+}
+
+/// Request body for POST /api/collections/:name/calibration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrateRequest {
+    pub pairs: Vec<LabeledPair>,
+}
+
+/// Request body for attaching a shadow collection
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShadowRequest {
+    /// Collection that receives mirrored inserts
+    pub collection: String,
+}
+
+/// Request body for comparing a collection against its shadow
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShadowCompareRequest {
+    /// How many stored points to use as queries
+    #[serde(default = "default_sample_size")]
+    pub sample_size: usize,
+
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Sampling seed, so a comparison can be repeated exactly
     #[serde(default)]
-    pub distance: DistanceMetric,
+    pub seed: u64,
 }
 
-/// Information about a collection
+fn default_sample_size() -> usize {
+    100
+}
+
+/// Request to create or re-point a collection alias (also how aliases
+/// are persisted, in snapshots and the write-ahead log)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CollectionInfo {
+pub struct CreateAliasRequest {
     pub name: String,
-    pub dimension: usize,
-    pub distance: DistanceMetric,
-    pub count: usize,
+    /// Collection that receives (control) traffic
+    pub collection: String,
+    /// Optional A/B split to a second collection
+    #[serde(default)]
+    pub experiment: Option<ExperimentConfig>,
+}
+
+/// Request to register a query template (the name comes from the path;
+/// also how templates are persisted, keyed by name)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateTemplateRequest {
+    /// Collection (or alias) the template searches
+    pub collection: String,
+
+    /// Filter expression every hit must match
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Number of results to return (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Distance metric to use (default: as for `SearchRequest`)
+    #[serde(default)]
+    pub metric: Option<DistanceMetric>,
+
+    /// If the collection's index can't rank by `metric`, scan exactly
+    /// instead of failing
+    #[serde(default)]
+    pub exact: bool,
+}
+
+/// Request body for invoking a query template
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateSearchRequest {
+    /// The query vector
+    pub vector: Vec<f32>,
+
+    /// What each hit carries besides its ID and score
+    #[serde(flatten)]
+    pub include: IncludeFields,
+}
+
+/// Share of an alias's searches routed to an alternate collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    pub collection: String,
+    /// Percentage of searches (0-100) sent to `collection`
+    pub percent: f32,
+}
+
+/// One operation inside a transaction
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionOp {
+    /// Insert or replace a point
+    Upsert {
+        id: String,
+        vector: Vec<f32>,
+        #[serde(default)]
+        metadata: HashMap<String, String>,
+    },
+    /// Remove a point (it must exist)
+    Delete { id: String },
+    /// Set and/or remove metadata keys on a point (it must exist)
+    UpdateMetadata {
+        id: String,
+        #[serde(default)]
+        set: HashMap<String, String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+}
+
+impl TransactionOp {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Upsert { id, .. } | Self::Delete { id } | Self::UpdateMetadata { id, .. } => id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Upsert { .. } => "upsert",
+            Self::Delete { .. } => "delete",
+            Self::UpdateMetadata { .. } => "update_metadata",
+        }
+    }
+}
+
+/// Request body for applying several operations atomically
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransactionRequest {
+    pub operations: Vec<TransactionOp>,
+}
+
+/// Query parameters for exporting a collection
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportQuery {
+    /// "jsonl" (default), "csv", or "parquet" (with `--features parquet`)
+    pub format: Option<String>,
+
+    /// Comma-separated fields, e.g. "id,metadata.title" (default: all)
+    pub fields: Option<String>,
+}
+
+/// Query parameters for collection stats
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StatsQuery {
+    /// Comma-separated fractions, e.g. "0.5,0.99", to report for each
+    /// numeric field
+    pub percentiles: Option<String>,
+
+    /// Filter expression whose selectivity to estimate
+    pub filter: Option<String>,
+}
+
+/// Query parameters for a streaming search
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamSearchQuery {
+    /// Collection (or alias) to search
+    pub collection: String,
+
+    /// Comma-separated query vector, e.g. "0.1,0.2,0.3"
+    pub vector: String,
+
+    /// Number of results to stream (default: 10)
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Distance metric to use (default: as for `SearchRequest`)
+    #[serde(default)]
+    pub metric: Option<DistanceMetric>,
+
+    /// If the collection's index can't rank by `metric`, scan exactly
+    /// instead of failing
+    #[serde(default)]
+    pub exact: bool,
+
+    /// Send each hit's vector data with it
+    #[serde(default)]
+    pub include_vector: bool,
+
+    /// Send each hit's metadata with it
+    #[serde(default)]
+    pub include_metadata: bool,
+}
+
+impl StreamSearchQuery {
+    /// Parse the comma-separated `vector` parameter
+    pub fn query_vector(&self) -> Result<Vec<f32>> {
+        self.vector
+            .split(',')
+            .map(|x| {
+                x.trim().parse::<f32>().map_err(|_| {
+                    VectorDbError::InvalidParameter(format!("'{}' is not a number", x.trim()))
+                })
+            })
+            .collect()
+    }
+
+    /// The include flags, as a search body would carry them
+    pub fn include(&self) -> IncludeFields {
+        IncludeFields {
+            include_vector: self.include_vector,
+            include_metadata: self.include_metadata,
+        }
+    }
+}
+
+/// Query parameters for a bulk import
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImportQuery {
+    /// Check every row and report errors without writing anything
+    #[serde(default)]
+    pub validate_only: bool,
+
+    /// What to do with rows whose ID already exists in the collection
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+
+    /// "jsonl" (default) or "parquet" (with `--features parquet`)
+    pub format: Option<String>,
+}
+
+/// Query parameters for a conditional point write
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConditionQuery {
+    /// Filter expression the stored point must match, e.g.
+    /// `metadata.status=="draft"`
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+}
+
+/// Request body for replacing a single point
+#[derive(Debug, Clone, Deserialize)]
+pub struct PutPointRequest {
+    /// New vector data (omit to keep the stored vector)
+    #[serde(default)]
+    pub vector: Option<Vec<f32>>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// New sparse embedding (omit to keep the stored one, send an empty
+    /// one to remove it)
+    #[serde(default)]
+    pub sparse: Option<SparseVector>,
+}
+
+/// Request body for rolling a vector back to an earlier version
+#[derive(Debug, Clone, Deserialize)]
+pub struct RollbackRequest {
+    pub version: u64,
+}
+
+/// Query parameters for POST /api/collections/:name/normalize
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NormalizeQuery {
+    /// Count the vectors that would be rewritten without changing them
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Rewrite a protected collection (also needs the admin key)
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Query parameters for DELETE /api/collections/:name,
+/// POST /api/collections/:name/rename and DELETE /api/trash/:name
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeleteCollectionQuery {
+    /// Delete or rename a protected collection (also needs the admin key)
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Query parameters for GET /api/admin/usage
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageQuery {
+    /// First day to include, YYYY-MM-DD (default: the earliest on record)
+    pub from: Option<String>,
+
+    /// Last day to include, YYYY-MM-DD (default: today)
+    pub to: Option<String>,
+
+    /// Only this API key (fingerprinted before lookup)
+    pub key: Option<String>,
+
+    /// Only this key fingerprint, as reported by the endpoint
+    pub fingerprint: Option<String>,
+}
+
+/// Background index build (POST /api/collections/:name/index).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildIndexRequest {
+    /// Graph parameters (default: m 16, ef_construction 200, ef_search 64)
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+}
+
+/// Bulk metadata edit (POST /api/collections/:name/update_by_filter).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateByFilterRequest {
+    /// Filter expression selecting the vectors to update
+    pub filter: String,
+
+    /// Keys to set (overwriting existing values)
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+
+    /// Keys to remove
+    #[serde(default)]
+    pub remove: Vec<String>,
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -237,14 +681,29 @@ pub enum VectorDbError {
     /// Collection already exists
     AlreadyExists(String),
 
+    /// Request conflicts with the current state (e.g. a stale offset)
+    Conflict(String),
+
+    /// A conditional write's `if` predicate didn't hold
+    PreconditionFailed(String),
+
     /// Invalid parameter value
     InvalidParameter(String),
 
     /// I/O error (file operations)
     IoError(std::io::Error),
 
+    /// Stored data failed an integrity check (bad checksum, truncated file)
+    Corrupted(String),
+
     /// JSON serialization error
     SerializationError(String),
+
+    /// Metadata doesn't match the collection's schema
+    SchemaViolation(Vec<FieldError>),
+
+    /// The collection is frozen against writes
+    ReadOnly(String),
 }
 
 // Implement Display for user-friendly error messages
@@ -263,15 +722,40 @@ impl fmt::Display for VectorDbError {
             VectorDbError::AlreadyExists(name) => {
                 write!(f, "Already exists: {}", name)
             }
+            VectorDbError::Conflict(msg) => {
+                write!(f, "Conflict: {}", msg)
+            }
+            VectorDbError::PreconditionFailed(msg) => {
+                write!(f, "Precondition failed: {}", msg)
+            }
             VectorDbError::InvalidParameter(msg) => {
                 write!(f, "Invalid parameter: {}", msg)
             }
             VectorDbError::IoError(e) => {
                 write!(f, "I/O error: {}", e)
             }
+            VectorDbError::Corrupted(msg) => {
+                write!(f, "Corrupted data: {}", msg)
+            }
             VectorDbError::SerializationError(msg) => {
                 write!(f, "Serialization error: {}", msg)
             }
+            VectorDbError::ReadOnly(msg) => {
+                write!(f, "Read-only: {}", msg)
+            }
+            VectorDbError::SchemaViolation(errors) => {
+                write!(f, "Schema violation: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    if let Some(id) = &e.id {
+                        write!(f, "'{}' ", id)?;
+                    }
+                    write!(f, "field '{}': {}", e.field, e.message)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -288,9 +772,14 @@ impl std::error::Error for VectorDbError {
 
 // Allow automatic conversion from io::Error → VectorDbError
 // This lets the ? operator work: File::open("x")?
+// Segment integrity failures travel as io::Errors and come out as Corrupted.
 impl From<std::io::Error> for VectorDbError {
     fn from(err: std::io::Error) -> Self {
-        VectorDbError::IoError(err)
+        if crate::storage::segment::is_corruption(&err) {
+            VectorDbError::Corrupted(err.to_string())
+        } else {
+            VectorDbError::IoError(err)
+        }
     }
 }
 
@@ -317,65 +806,6 @@ pub type Result<T> = std::result::Result<T, VectorDbError>;
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_vector_creation() {
-        let v = Vector::new(vec![0.1, 0.2, 0.3]);
-        assert_eq!(v.dimension(), 3);
-        assert!(v.metadata.is_empty());
-    }
-
-    #[test]
-    fn test_vector_with_metadata() {
-        let mut meta = HashMap::new();
-        meta.insert("title".to_string(), "Test Doc".to_string());
-        let v = Vector::with_metadata(vec![1.0, 2.0], meta);
-        assert_eq!(v.metadata["title"], "Test Doc");
-    }
-
-    #[test]
-    fn test_vector_magnitude() {
-        let v = Vector::new(vec![3.0, 4.0]);
-        assert!((v.magnitude() - 5.0).abs() < 0.0001);
-    }
-
-    #[test]
-    fn test_vector_normalize() {
-        let mut v = Vector::new(vec![3.0, 4.0]);
-        v.normalize();
-        assert!((v.magnitude() - 1.0).abs() < 0.0001);
-        assert!((v.data[0] - 0.6).abs() < 0.0001);
-        assert!((v.data[1] - 0.8).abs() < 0.0001);
-    }
-
-    #[test]
-    fn test_cosine_similarity() {
-        let a = vec![1.0, 0.0];
-        let b = vec![0.0, 1.0];
-        // Orthogonal vectors → cosine = 0
-        let score = DistanceMetric::Cosine.calculate(&a, &b);
-        assert!((score - 0.0).abs() < 0.0001);
-
-        // Identical vectors → cosine = 1
-        let score = DistanceMetric::Cosine.calculate(&a, &a);
-        assert!((score - 1.0).abs() < 0.0001);
-    }
-
-    #[test]
-    fn test_euclidean_distance() {
-        let a = vec![0.0, 0.0];
-        let b = vec![3.0, 4.0];
-        let dist = DistanceMetric::Euclidean.calculate(&a, &b);
-        assert!((dist - 5.0).abs() < 0.0001);
-    }
-
-    #[test]
-    fn test_dot_product() {
-        let a = vec![1.0, 2.0, 3.0];
-        let b = vec![4.0, 5.0, 6.0];
-        let dot = DistanceMetric::Dot.calculate(&a, &b);
-        assert!((dot - 32.0).abs() < 0.0001); // 1*4 + 2*5 + 3*6 = 32
-    }
-
     #[test]
     fn test_error_display() {
         let err = VectorDbError::DimensionMismatch {
@@ -392,5 +822,13 @@ mod tests {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file missing");
         let db_err: VectorDbError = io_err.into();
         assert!(matches!(db_err, VectorDbError::IoError(_)));
+
+        // A segment that fails its checksum surfaces as Corrupted
+        let mut bytes = Vec::new();
+        crate::storage::segment::write_segment_to(&mut bytes, &[Vector::new(vec![1.0])]).unwrap();
+        bytes[40] ^= 1;
+        let io_err = crate::storage::segment::read_segment_from(&mut bytes.as_slice()).unwrap_err();
+        let db_err: VectorDbError = io_err.into();
+        assert!(matches!(db_err, VectorDbError::Corrupted(_)), "{}", db_err);
     }
 }
@@ -0,0 +1,295 @@
+// src/monitoring.rs
+//
+// Prometheus metrics, served at GET /metrics.
+//
+// Counters and histograms go through the `metrics` facade and are kept by
+// the Prometheus recorder installed at startup:
+//
+//   vectordb_http_requests_total{method,route,status}
+//   vectordb_http_request_duration_seconds{method,route}     (histogram)
+//   vectordb_search_duration_seconds{collection}             (histogram)
+//   vectordb_index_build_duration_seconds{collection,index}  (histogram)
+//   vectordb_result_cache_lookups_total{result="hit"|"miss"}
+//   vectordb_numa_scanned_vectors_total{node}
+//   vectordb_numa_scan_duration_seconds{node}                (histogram)
+//
+// `route` is the matched route pattern (/api/collections/:name/points),
+// never the raw path, so IDs don't explode the label set. Flat-store
+// searches are labeled collection="".
+//
+// Sizes are read from the state at scrape time instead of being kept as
+// gauges, so a dropped collection disappears from the next scrape rather
+// than lingering at its last count:
+//
+//   vectordb_vectors                        flat-store vectors, both tiers
+//   vectordb_collection_vectors{collection}
+//   vectordb_wal_records / vectordb_wal_bytes
+//
+// Without an installed recorder (library use, unit tests) every record_*
+// call is a no-op.
+
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::fmt::Write;
+use std::time::Duration;
+
+pub const REQUESTS: &str = "vectordb_http_requests_total";
+pub const REQUEST_DURATION: &str = "vectordb_http_request_duration_seconds";
+pub const SEARCH_DURATION: &str = "vectordb_search_duration_seconds";
+pub const INDEX_BUILD_DURATION: &str = "vectordb_index_build_duration_seconds";
+pub const CACHE_LOOKUPS: &str = "vectordb_result_cache_lookups_total";
+pub const NUMA_SCANNED: &str = "vectordb_numa_scanned_vectors_total";
+pub const NUMA_SCAN_DURATION: &str = "vectordb_numa_scan_duration_seconds";
+pub const MEMTABLE_FLUSHED: &str = "vectordb_memtable_flushed_writes_total";
+pub const MEMTABLE_FLUSH_DURATION: &str = "vectordb_memtable_flush_duration_seconds";
+
+/// Histogram buckets (seconds) for request and search latency
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// Histogram buckets (seconds) for index builds, which run much longer
+const BUILD_BUCKETS: [f64; 9] = [0.01, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0, 1200.0];
+
+/// How often the server should call `PrometheusHandle::run_upkeep`
+pub const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RECORDER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Recorder builder with this crate's histogram buckets
+pub fn builder() -> std::result::Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(INDEX_BUILD_DURATION.to_string()),
+            &BUILD_BUCKETS,
+        )?
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &LATENCY_BUCKETS)
+}
+
+/// Install the process-wide recorder and describe the metrics
+pub fn install() -> std::result::Result<PrometheusHandle, BuildError> {
+    let handle = builder()?.install_recorder()?;
+    describe();
+    Ok(handle)
+}
+
+fn describe() {
+    metrics::describe_counter!(REQUESTS, "HTTP requests served");
+    metrics::describe_histogram!(
+        REQUEST_DURATION,
+        metrics::Unit::Seconds,
+        "HTTP request latency by route"
+    );
+    metrics::describe_histogram!(
+        SEARCH_DURATION,
+        metrics::Unit::Seconds,
+        "Search latency by collection"
+    );
+    metrics::describe_histogram!(
+        INDEX_BUILD_DURATION,
+        metrics::Unit::Seconds,
+        "Index build time by collection and index type"
+    );
+    metrics::describe_counter!(CACHE_LOOKUPS, "Search result cache lookups");
+    metrics::describe_counter!(NUMA_SCANNED, "Vectors scanned by workers on each NUMA node");
+    metrics::describe_histogram!(
+        NUMA_SCAN_DURATION,
+        metrics::Unit::Seconds,
+        "Time each NUMA node's scan workers spend per search"
+    );
+    metrics::describe_counter!(
+        MEMTABLE_FLUSHED,
+        "Upserts and deletes flushed from memtables to segments"
+    );
+    metrics::describe_histogram!(
+        MEMTABLE_FLUSH_DURATION,
+        metrics::Unit::Seconds,
+        "Time to write one memtable flush"
+    );
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RECORDING
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Count and time one HTTP request
+pub fn record_request(method: &str, route: &str, status: u16, elapsed: Duration) {
+    metrics::counter!(
+        REQUESTS,
+        "method" => method.to_string(),
+        "route" => route.to_string(),
+        "status" => status.to_string()
+    )
+    .increment(1);
+    metrics::histogram!(
+        REQUEST_DURATION,
+        "method" => method.to_string(),
+        "route" => route.to_string()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+/// Time one search of `collection` ("" for the flat store)
+pub fn record_search(collection: &str, elapsed: Duration) {
+    metrics::histogram!(SEARCH_DURATION, "collection" => collection.to_string())
+        .record(elapsed.as_secs_f64());
+}
+
+/// Time one index build
+pub fn record_index_build(collection: &str, index: &str, elapsed: Duration) {
+    metrics::histogram!(
+        INDEX_BUILD_DURATION,
+        "collection" => collection.to_string(),
+        "index" => index.to_string()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+/// Count one result cache lookup
+pub fn record_cache_lookup(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    metrics::counter!(CACHE_LOOKUPS, "result" => result).increment(1);
+}
+
+/// Count and time one scan worker's slice of a NUMA arena search
+pub fn record_numa_scan(node: usize, scanned: usize, elapsed: Duration) {
+    let node = node.to_string();
+    metrics::counter!(NUMA_SCANNED, "node" => node.clone()).increment(scanned as u64);
+    metrics::histogram!(NUMA_SCAN_DURATION, "node" => node).record(elapsed.as_secs_f64());
+}
+
+/// Count and time one memtable flush of `writes` upserts and deletes
+pub fn record_memtable_flush(collection: &str, writes: usize, elapsed: Duration) {
+    let collection = collection.to_string();
+    metrics::counter!(MEMTABLE_FLUSHED, "collection" => collection.clone())
+        .increment(writes as u64);
+    metrics::histogram!(MEMTABLE_FLUSH_DURATION, "collection" => collection)
+        .record(elapsed.as_secs_f64());
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCRAPE-TIME GAUGES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Sizes read from the state when /metrics is scraped
+#[derive(Debug, Clone, Default)]
+pub struct Sizes {
+    /// Flat-store vectors, hot and cold
+    pub vectors: usize,
+    /// (collection, vectors), in any order
+    pub collections: Vec<(String, usize)>,
+    pub wal_records: u64,
+    pub wal_bytes: u64,
+}
+
+impl Sizes {
+    /// Render as Prometheus text, to append after the recorder's output
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        gauge(&mut out, "vectordb_vectors", "Vectors in the flat store");
+        let _ = writeln!(out, "vectordb_vectors {}", self.vectors);
+
+        gauge(
+            &mut out,
+            "vectordb_collection_vectors",
+            "Vectors per collection",
+        );
+        let mut collections = self.collections.clone();
+        collections.sort();
+        for (name, count) in collections {
+            let _ = writeln!(
+                out,
+                "vectordb_collection_vectors{{collection=\"{}\"}} {}",
+                escape(&name),
+                count
+            );
+        }
+
+        gauge(
+            &mut out,
+            "vectordb_wal_records",
+            "Records in the write-ahead log since the last snapshot",
+        );
+        let _ = writeln!(out, "vectordb_wal_records {}", self.wal_records);
+        gauge(
+            &mut out,
+            "vectordb_wal_bytes",
+            "Size of the write-ahead log in bytes",
+        );
+        let _ = writeln!(out, "vectordb_wal_bytes {}", self.wal_bytes);
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+}
+
+/// Escape a label value for the text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_metrics_render() {
+        let recorder = builder().unwrap().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            describe();
+            record_request(
+                "GET",
+                "/api/collections/:name",
+                200,
+                Duration::from_millis(3),
+            );
+            record_request(
+                "GET",
+                "/api/collections/:name",
+                200,
+                Duration::from_millis(7),
+            );
+            record_search("docs", Duration::from_micros(800));
+            record_index_build("docs", "hnsw", Duration::from_secs(2));
+        });
+
+        let text = handle.render();
+        assert!(text.contains(
+            "vectordb_http_requests_total{method=\"GET\",route=\"/api/collections/:name\",status=\"200\"} 2"
+        ), "{}", text);
+        assert!(
+            text.contains(
+                "vectordb_search_duration_seconds_bucket{collection=\"docs\",le=\"0.001\"} 1"
+            ),
+            "{}",
+            text
+        );
+        // Builds get their own, longer buckets
+        assert!(text.contains("le=\"1200\""), "{}", text);
+    }
+
+    #[test]
+    fn test_sizes_render() {
+        let sizes = Sizes {
+            vectors: 3,
+            collections: vec![("b".into(), 2), ("a\"x".into(), 1)],
+            wal_records: 4,
+            wal_bytes: 512,
+        };
+        let text = sizes.render();
+        assert!(text.contains("# TYPE vectordb_collection_vectors gauge"));
+        assert!(text.contains("vectordb_collection_vectors{collection=\"a\\\"x\"} 1\nvectordb_collection_vectors{collection=\"b\"} 2\n"));
+        assert!(text.contains("vectordb_wal_bytes 512\n"));
+    }
+}
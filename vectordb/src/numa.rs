@@ -0,0 +1,579 @@
+// src/numa.rs
+//
+// NUMA-aware placement for exact scans on multi-socket servers.
+//
+// On a two-socket machine every core can read all of memory, but reading
+// the other socket's memory crosses the interconnect at roughly half the
+// bandwidth. A scan is bandwidth-bound, so a worker on node 1 streaming
+// vectors that live on node 0 runs at the slower speed. By default a
+// collection keeps each vector in its own heap allocation, on whichever
+// node the inserting thread happened to run, so a plain parallel scan
+// mixes local and remote reads arbitrarily.
+//
+// With NUMA placement on (--numa / VECTORDB_NUMA / `numa = true`), a
+// collection keeps its vectors' components in a `NodeArena` instead:
+//
+//   rows ──split──► part 0 ─ buffer written first by a thread on node 0
+//                   part 1 ─ buffer written first by a thread on node 1
+//
+// Linux places a page on the node of the thread that first writes it, so
+// each part ends up in its node's memory. The arena's scan then pins each
+// worker to a core of the node that holds its slice, and every read is
+// local. Workers still share one top-k threshold across nodes, and the
+// results are exactly those of `search::brute_force`.
+//
+// The arena is the collection's only copy of the components, updated in
+// place on writes: an overwrite rewrites its row, a new point goes to the
+// part holding the fewest rows per core, and a delete moves its part's
+// last row into the hole. A full part moves to a buffer twice the size,
+// allocated and filled by a thread on its node, so nothing is copied or
+// rebuilt on the query path. The buffers are also where huge pages go
+// (see hugepages.rs): with huge pages on, collections keep an arena even
+// if NUMA placement is off.
+//
+// The topology comes from /sys/devices/system/node. Anywhere else (or if
+// sysfs can't be read) the machine is one node, and placement degrades to
+// a pinned parallel scan.
+//
+// Arena scans are counted and timed per node in /metrics (see
+// monitoring::record_numa_scan), which shows whether the nodes share the
+// work evenly.
+
+use crate::engine::search::{self, SharedThreshold};
+use crate::hugepages::{self, Buffer, HugePages};
+use crate::models::{DistanceMetric, SearchResult};
+use crate::monitoring;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Rows a part makes room for when it first grows
+const MIN_PART_ROWS: usize = 64;
+
+/// Is NUMA placement on?
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Do collections keep their vectors in an arena? Yes with NUMA placement
+/// or huge pages on.
+pub fn arena_storage() -> bool {
+    enabled() || hugepages::mode() != HugePages::Off
+}
+
+/// Turn NUMA placement on or off (the `numa` server setting)
+pub fn set_enabled(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TOPOLOGY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One NUMA node and the cores this process may run on there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: usize,
+    pub cores: Vec<usize>,
+}
+
+/// The machine's NUMA nodes; always at least one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology {
+    nodes: Vec<Node>,
+}
+
+impl Topology {
+    /// The topology of this machine, read once
+    pub fn get() -> &'static Topology {
+        static TOPOLOGY: OnceLock<Topology> = OnceLock::new();
+        TOPOLOGY.get_or_init(Self::detect)
+    }
+
+    /// Read the nodes from sysfs, keeping only cores in this process's
+    /// affinity mask
+    pub fn detect() -> Self {
+        let allowed: Vec<usize> = core_affinity::get_core_ids()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|core| core.id)
+            .collect();
+        Self::from_sysfs(Path::new("/sys/devices/system/node"), &allowed)
+            .unwrap_or_else(|| Self::single(allowed))
+    }
+
+    /// Nodes listed under `root` (node0/cpulist, node1/cpulist, ...), or
+    /// None if there are none with an allowed core
+    fn from_sysfs(root: &Path, allowed: &[usize]) -> Option<Self> {
+        let mut nodes = Vec::new();
+        for entry in std::fs::read_dir(root).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(id) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix("node"))
+                .and_then(|n| n.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let Ok(list) = std::fs::read_to_string(entry.path().join("cpulist")) else {
+                continue;
+            };
+            let cores: Vec<usize> = parse_cpulist(&list)?
+                .into_iter()
+                .filter(|core| allowed.contains(core))
+                .collect();
+            if !cores.is_empty() {
+                nodes.push(Node { id, cores });
+            }
+        }
+        nodes.sort_by_key(|node| node.id);
+        (!nodes.is_empty()).then_some(Self { nodes })
+    }
+
+    /// A machine with one node holding `cores`
+    pub fn single(cores: Vec<usize>) -> Self {
+        Self {
+            nodes: vec![Node { id: 0, cores }],
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+/// Parse a kernel CPU list such as "0-3,8-11,16"
+fn parse_cpulist(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (first.parse::<usize>().ok()?, last.parse::<usize>().ok()?);
+                cores.extend(first..=last);
+            }
+            None => cores.push(range.parse().ok()?),
+        }
+    }
+    Some(cores)
+}
+
+/// Pin the current thread to one of `node`'s cores, chosen by `worker`.
+/// Returns false if the OS refused (the thread then runs unpinned).
+fn pin(node: &Node, worker: usize) -> bool {
+    if node.cores.is_empty() {
+        return false;
+    }
+    let id = node.cores[worker % node.cores.len()];
+    core_affinity::set_for_current(core_affinity::CoreId { id })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ARENA
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A node's contiguous share of the arena's vectors
+#[derive(Debug)]
+struct Part {
+    node: Node,
+    ids: Vec<String>,
+    /// Row-major, `dimension` floats per vector; rows past `ids` are spare
+    data: Buffer,
+}
+
+impl Part {
+    fn rows(&self, dimension: usize) -> impl Iterator<Item = (&str, &[f32])> {
+        self.ids
+            .iter()
+            .map(|id| id.as_str())
+            .zip(self.data.chunks_exact(dimension.max(1)))
+    }
+
+    /// Make room for one more row: a full part moves to a buffer twice the
+    /// size, allocated and written through by a thread on its node so the
+    /// new pages (spare rows included) are local
+    fn reserve_row(&mut self, dimension: usize) {
+        let used = self.ids.len() * dimension;
+        if used + dimension <= self.data.len() {
+            return;
+        }
+        let len = (self.ids.len() * 2).max(MIN_PART_ROWS) * dimension;
+        let (node, old) = (&self.node, &self.data[..used]);
+        self.data = std::thread::scope(|scope| {
+            scope
+                .spawn(move || {
+                    pin(node, 0);
+                    let mut data = Buffer::zeroed(len, hugepages::mode());
+                    data[..used].copy_from_slice(old);
+                    // Zeroed, but maybe not touched yet: touch it from here
+                    data[used..].fill(0.0);
+                    data
+                })
+                .join()
+                .expect("arena builder panicked")
+        });
+    }
+}
+
+/// Vectors of one dimension split across NUMA nodes, each part in its own
+/// node's memory
+#[derive(Debug)]
+pub struct NodeArena {
+    dimension: usize,
+    parts: Vec<Part>,
+    /// id → (part, row)
+    slots: HashMap<String, (usize, usize)>,
+}
+
+impl Clone for NodeArena {
+    /// The same rows, placed afresh
+    fn clone(&self) -> Self {
+        let topology = Topology {
+            nodes: self.parts.iter().map(|p| p.node.clone()).collect(),
+        };
+        let rows: Vec<_> = self.rows().collect();
+        Self::build(&topology, self.dimension, &rows)
+    }
+}
+
+impl NodeArena {
+    /// An empty arena with a part on each of `topology`'s nodes
+    pub fn new(topology: &Topology, dimension: usize) -> Self {
+        Self::build(topology, dimension, &[])
+    }
+
+    /// Copy `candidates` into per-node parts, in order, sized by each
+    /// node's share of the cores. Candidates of another dimension than
+    /// `dimension` are left out.
+    pub fn build(topology: &Topology, dimension: usize, candidates: &[(&str, &[f32])]) -> Self {
+        let usable: Vec<(&str, &[f32])> = candidates
+            .iter()
+            .copied()
+            .filter(|(_, data)| data.len() == dimension)
+            .collect();
+        let total_cores: usize = topology.nodes.iter().map(|n| n.cores.len().max(1)).sum();
+
+        let mut slices = Vec::with_capacity(topology.nodes.len());
+        let mut start = 0;
+        let mut cores_so_far = 0;
+        for node in &topology.nodes {
+            cores_so_far += node.cores.len().max(1);
+            let end = usable.len() * cores_so_far / total_cores;
+            slices.push((node, &usable[start..end]));
+            start = end;
+        }
+
+        let parts = std::thread::scope(|scope| {
+            let handles: Vec<_> = slices
+                .into_iter()
+                .map(|(node, slice)| {
+                    scope.spawn(move || {
+                        // First touch from this node puts the pages there
+                        pin(node, 0);
+                        let mut data = Buffer::zeroed(slice.len() * dimension, hugepages::mode());
+                        let mut ids = Vec::with_capacity(slice.len());
+                        for (row, (id, vector)) in slice.iter().enumerate() {
+                            ids.push(id.to_string());
+                            data[row * dimension..(row + 1) * dimension].copy_from_slice(vector);
+                        }
+                        Part {
+                            node: node.clone(),
+                            ids,
+                            data,
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("arena builder panicked"))
+                .collect()
+        });
+        let mut arena = Self {
+            dimension,
+            parts,
+            slots: HashMap::new(),
+        };
+        arena.slots = arena.index_rows();
+        arena
+    }
+
+    /// Where each row is: id → (part, row)
+    fn index_rows(&self) -> HashMap<String, (usize, usize)> {
+        let mut slots = HashMap::with_capacity(self.len());
+        for (p, part) in self.parts.iter().enumerate() {
+            for (row, id) in part.ids.iter().enumerate() {
+                slots.insert(id.clone(), (p, row));
+            }
+        }
+        slots
+    }
+
+    /// Vectors held across all nodes
+    pub fn len(&self) -> usize {
+        self.parts.iter().map(|p| p.ids.len()).sum()
+    }
+
+    /// The components stored under `id`
+    pub fn get(&self, id: &str) -> Option<&[f32]> {
+        let &(p, row) = self.slots.get(id)?;
+        let d = self.dimension;
+        Some(&self.parts[p].data[row * d..(row + 1) * d])
+    }
+
+    /// Every (id, components), part by part
+    pub fn rows(&self) -> impl Iterator<Item = (&str, &[f32])> {
+        self.parts.iter().flat_map(|p| p.rows(self.dimension))
+    }
+
+    /// Store `row` under `id`: in place over its current row if it has
+    /// one, else in the part holding the fewest rows per core. Returns the
+    /// components it replaced.
+    ///
+    /// Panics if `row` doesn't have the arena's dimension.
+    pub fn upsert(&mut self, id: &str, row: &[f32]) -> Option<Vec<f32>> {
+        assert_eq!(
+            row.len(),
+            self.dimension,
+            "arena row of the wrong dimension"
+        );
+        let d = self.dimension;
+        if let Some(&(p, r)) = self.slots.get(id) {
+            let stored = &mut self.parts[p].data[r * d..(r + 1) * d];
+            let replaced = stored.to_vec();
+            stored.copy_from_slice(row);
+            return Some(replaced);
+        }
+        let p = (0..self.parts.len())
+            .min_by(|&a, &b| {
+                let (a, b) = (&self.parts[a], &self.parts[b]);
+                let a_load = a.ids.len() * b.node.cores.len().max(1);
+                let b_load = b.ids.len() * a.node.cores.len().max(1);
+                a_load.cmp(&b_load)
+            })
+            .expect("an arena has a part per node");
+        let part = &mut self.parts[p];
+        part.reserve_row(d);
+        let r = part.ids.len();
+        part.data[r * d..(r + 1) * d].copy_from_slice(row);
+        part.ids.push(id.to_string());
+        self.slots.insert(id.to_string(), (p, r));
+        None
+    }
+
+    /// Drop the row under `id`, moving its part's last row into the hole.
+    /// Returns the components it held.
+    pub fn remove(&mut self, id: &str) -> Option<Vec<f32>> {
+        let (p, r) = self.slots.remove(id)?;
+        let d = self.dimension;
+        let part = &mut self.parts[p];
+        let removed = part.data[r * d..(r + 1) * d].to_vec();
+        let last = part.ids.len() - 1;
+        part.ids.swap_remove(r);
+        if r != last {
+            part.data.copy_within(last * d..(last + 1) * d, r * d);
+            if let Some(slot) = self.slots.get_mut(&part.ids[r]) {
+                slot.1 = r;
+            }
+        }
+        Some(removed)
+    }
+
+    /// Bytes of the parts' buffers, spare rows included
+    pub fn memory_bytes(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|p| p.data.len() * std::mem::size_of::<f32>())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// (node id, vectors held there) for each node
+    pub fn placement(&self) -> Vec<(usize, usize)> {
+        self.parts
+            .iter()
+            .map(|p| (p.node.id, p.ids.len()))
+            .collect()
+    }
+
+    /// Exact top-k with about `workers` threads, each pinned to the node
+    /// holding the slice it scans. Same hits, in the same order, as
+    /// `search::brute_force` over the arena's vectors.
+    pub fn search(
+        &self,
+        query: &[f32],
+        metric: DistanceMetric,
+        k: usize,
+        workers: usize,
+    ) -> Vec<SearchResult> {
+        let total = self.len();
+        if query.len() != self.dimension || k == 0 || total == 0 {
+            return Vec::new();
+        }
+        let shared = SharedThreshold::new(metric);
+        let partials: Vec<Vec<SearchResult>> = std::thread::scope(|scope| {
+            let mut handles = Vec::new();
+            for part in self.parts.iter().filter(|p| !p.ids.is_empty()) {
+                // Each node gets workers in proportion to the vectors it holds
+                let share = (workers * part.ids.len() + total - 1) / total;
+                let share = share.clamp(1, part.node.cores.len().max(1));
+                let per_worker = (part.ids.len() + share - 1) / share;
+                for worker in 0..share {
+                    let first = worker * per_worker;
+                    let scanned = part.ids.len().saturating_sub(first).min(per_worker);
+                    if scanned == 0 {
+                        break;
+                    }
+                    let rows = part.rows(self.dimension).skip(first).take(scanned);
+                    let shared = &shared;
+                    handles.push(scope.spawn(move || {
+                        pin(&part.node, worker);
+                        let start = Instant::now();
+                        let hits = search::scan_with_threshold(query, metric, k, rows, shared);
+                        monitoring::record_numa_scan(part.node.id, scanned, start.elapsed());
+                        hits
+                    }));
+                }
+            }
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("scan worker panicked"))
+                .collect()
+        });
+        search::rank(partials.into_iter().flatten(), metric, k)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::Rng;
+
+    #[test]
+    fn test_parse_cpulist() {
+        assert_eq!(
+            parse_cpulist("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpulist(""), Some(vec![]));
+        assert_eq!(parse_cpulist("0-x"), None);
+    }
+
+    #[test]
+    fn test_topology_from_sysfs() {
+        let root = std::env::temp_dir().join(format!("vectordb_numa_{}", std::process::id()));
+        for (node, list) in [("node0", "0-3"), ("node1", "4-7"), ("node2", "8-9")] {
+            std::fs::create_dir_all(root.join(node)).unwrap();
+            std::fs::write(root.join(node).join("cpulist"), list).unwrap();
+        }
+        std::fs::create_dir_all(root.join("power")).unwrap();
+
+        // node2 has no core this process may use
+        let topology = Topology::from_sysfs(&root, &[0, 1, 5, 6, 7]).unwrap();
+        assert_eq!(
+            topology.nodes(),
+            &[
+                Node {
+                    id: 0,
+                    cores: vec![0, 1]
+                },
+                Node {
+                    id: 1,
+                    cores: vec![5, 6, 7]
+                },
+            ]
+        );
+        assert!(Topology::from_sysfs(&root.join("missing"), &[0]).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_arena_search_matches_brute_force() {
+        let dimension = 6;
+        let vectors: Vec<(String, Vec<f32>)> = (0..500)
+            .map(|i| {
+                let mut rng = Rng::stream(7, 0, i);
+                let v = (0..dimension).map(|_| rng.normal()).collect();
+                (format!("v{}", i), v)
+            })
+            .collect();
+        let candidates: Vec<(&str, &[f32])> = vectors
+            .iter()
+            .map(|(id, v)| (id.as_str(), v.as_slice()))
+            .collect();
+        let topology = Topology {
+            nodes: vec![
+                Node {
+                    id: 0,
+                    cores: vec![0, 1],
+                },
+                Node {
+                    id: 1,
+                    cores: vec![2],
+                },
+            ],
+        };
+        let arena = NodeArena::build(&topology, dimension, &candidates);
+        assert_eq!(arena.placement(), vec![(0, 333), (1, 167)]);
+
+        let query = vec![0.3; dimension];
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::Cosine] {
+            let expected = search::brute_force(&query, metric, 10, candidates.iter().copied());
+            let got = arena.search(&query, metric, 10, 3);
+            let ids = |r: &[SearchResult]| r.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+            assert_eq!(ids(&got), ids(&expected));
+            assert_eq!(got[0].score, expected[0].score);
+        }
+    }
+
+    #[test]
+    fn test_arena_updates_in_place() {
+        let topology = Topology {
+            nodes: vec![
+                Node {
+                    id: 0,
+                    cores: vec![0],
+                },
+                Node {
+                    id: 1,
+                    cores: vec![1, 2, 3],
+                },
+            ],
+        };
+        let mut arena = NodeArena::new(&topology, 2);
+        for i in 0..200 {
+            assert_eq!(arena.upsert(&format!("v{}", i), &[i as f32, 0.0]), None);
+        }
+        // New rows follow the cores, and parts grow as they fill
+        assert_eq!(arena.placement(), vec![(0, 50), (1, 150)]);
+        assert!(arena.memory_bytes() >= 200 * 2 * 4);
+
+        assert_eq!(arena.upsert("v7", &[7.5, 1.0]), Some(vec![7.0, 0.0]));
+        assert_eq!(arena.get("v7"), Some(&[7.5, 1.0][..]));
+        assert_eq!(arena.remove("v0"), Some(vec![0.0, 0.0]));
+        assert_eq!(arena.remove("v0"), None);
+        assert_eq!(arena.len(), 199);
+        // The row moved into the hole is still found under its ID
+        for i in 1..200 {
+            let want = if i == 7 { [7.5, 1.0] } else { [i as f32, 0.0] };
+            assert_eq!(arena.get(&format!("v{}", i)), Some(&want[..]));
+        }
+        assert_eq!(arena.rows().count(), 199);
+
+        let copy = arena.clone();
+        assert_eq!(copy.len(), 199);
+        assert_eq!(copy.get("v7"), Some(&[7.5, 1.0][..]));
+        let hits = copy.search(&[7.5, 1.0], DistanceMetric::Euclidean, 1, 2);
+        assert_eq!(hits[0].id, "v7");
+    }
+}
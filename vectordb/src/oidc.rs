@@ -0,0 +1,662 @@
+// src/oidc.rs
+//
+// OIDC access tokens as an alternative to static API keys.
+//
+// With an `[oidc]` table in the config file (or --oidc-issuer), a request
+// may present a JWT from the identity provider where it would present a
+// key (`Authorization: Bearer <token>`):
+//
+//   [oidc]
+//   issuer = "https://id.example.com"
+//   jwks = "https://id.example.com/keys"   # optional, see below
+//   audience = "vectordb"                # checked against `aud`, if set
+//   tenant_claim = "org"                 # optional, see below
+//   tenant_collections = "{tenant}-*"
+//   scope_claim = "scope"
+//   scope_prefix = "vectordb:"
+//   leeway_secs = 60
+//
+// A token is accepted if it's signed RS256 by a key in the JWKS (matched
+// by `kid`), names the issuer, hasn't expired (nor is it from the future,
+// `nbf`) give or take the leeway, and lists the audience if one is set.
+// Its claims then map onto the scoped-key model of src/auth.rs:
+//
+//   verbs        scopes `<prefix><verb>` in the scope claim, a space-
+//                separated string or an array ("vectordb:search")
+//   collections  with a tenant claim: the pattern `tenant_collections`
+//                with `{tenant}` replaced by the claim ("acme-*"); tokens
+//                without the claim are refused. Otherwise every collection.
+//
+// Tenant claims may only hold letters, digits and `_`, and the pattern
+// must follow `{tenant}` with a character they can't (the `-` of
+// "{tenant}-*") and have no `*` before it. Otherwise tenant `acme`'s
+// "acme*" or "acme_*" would also match tenant `acme_corp`'s collections.
+//
+// Signatures and the standard claims are checked by the jsonwebtoken
+// crate (ring underneath); keys under 2048 bits are skipped, with a
+// warning, and a JWKS left with no usable key is refused.
+//
+// Without `jwks`, the keys are found through OIDC discovery: the
+// `jwks_uri` of `<issuer>/.well-known/openid-configuration`, whose own
+// `issuer` must match. `jwks` may instead name the JWKS directly, as an
+// https:// URL or a file path. Nothing is fetched over plain http://,
+// where anyone on the path could swap in their own keys.
+//
+// The JWKS is read at startup, where a failure stops the server, and again
+// every JWKS_REFRESH_INTERVAL from the same place, so rotated keys are
+// picked up; a failed refresh keeps the keys already loaded.
+
+use crate::auth::{self, Access, Verb};
+use crate::models::{Result, VectorDbError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// How often the JWKS is read again
+pub const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Smallest RSA modulus accepted, in bits
+pub const MIN_KEY_BITS: usize = 2048;
+
+/// Longest a discovery document or JWKS fetch may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where discovery documents live, under the issuer (OpenID Connect
+/// Discovery 1.0, section 4)
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONFIG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Where tokens come from and how their claims map to scopes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Expected `iss` claim
+    pub issuer: String,
+    /// JWKS URL (https://) or file path; discovered from the issuer if unset
+    #[serde(default)]
+    pub jwks: Option<String>,
+    /// Expected in the `aud` claim, if set
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Claim naming the token's tenant, which limits its collections
+    #[serde(default)]
+    pub tenant_claim: Option<String>,
+    /// A tenant's collections; `{tenant}` is replaced by the claim
+    #[serde(default = "default_tenant_collections")]
+    pub tenant_collections: String,
+    #[serde(default = "default_scope_claim")]
+    pub scope_claim: String,
+    /// Only scopes starting with this grant verbs
+    #[serde(default = "default_scope_prefix")]
+    pub scope_prefix: String,
+    /// Clock skew allowed on `exp` and `nbf`
+    #[serde(default = "default_leeway_secs")]
+    pub leeway_secs: u64,
+}
+
+fn default_tenant_collections() -> String {
+    "{tenant}-*".into()
+}
+
+fn default_scope_claim() -> String {
+    "scope".into()
+}
+
+fn default_scope_prefix() -> String {
+    "vectordb:".into()
+}
+
+fn default_leeway_secs() -> u64 {
+    60
+}
+
+impl OidcConfig {
+    /// Tokens from `issuer`, with keys found by discovery and everything
+    /// else at its default
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            jwks: None,
+            audience: None,
+            tenant_claim: None,
+            tenant_collections: default_tenant_collections(),
+            scope_claim: default_scope_claim(),
+            scope_prefix: default_scope_prefix(),
+            leeway_secs: default_leeway_secs(),
+        }
+    }
+
+    /// Reject settings no token could satisfy, and key sources that could
+    /// be tampered with in transit
+    pub fn validate(&self) -> Result<()> {
+        if self.issuer.is_empty() {
+            return Err(VectorDbError::InvalidParameter(
+                "oidc needs an issuer".into(),
+            ));
+        }
+        match &self.jwks {
+            Some(jwks) if jwks.is_empty() || jwks.starts_with("http://") => {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "oidc jwks '{}' must be an https:// URL or a file path",
+                    jwks
+                )));
+            }
+            None if !self.issuer.starts_with("https://") => {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "oidc issuer '{}' must be an https:// URL to discover its keys \
+                     (or set jwks)",
+                    self.issuer
+                )));
+            }
+            _ => {}
+        }
+        if self.tenant_claim.is_some() {
+            let Some((before, after)) = self.tenant_collections.split_once("{tenant}") else {
+                return Err(VectorDbError::InvalidParameter(
+                    "oidc tenant_collections must contain {tenant}".into(),
+                ));
+            };
+            let delimited = after
+                .chars()
+                .next()
+                .map_or(true, |c| c != '*' && !is_tenant_char(c));
+            if before.contains('*') || !delimited {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "oidc tenant_collections '{}' must have no '*' before {{tenant}} and \
+                     follow it with a character tenant names can't contain, such as '-'",
+                    self.tenant_collections
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the JWKS is read from: `jwks` if set, otherwise the `jwks_uri`
+    /// of the issuer's discovery document
+    pub async fn jwks_source(&self) -> Result<String> {
+        if let Some(jwks) = &self.jwks {
+            return Ok(jwks.clone());
+        }
+        let url = format!("{}{}", self.issuer.trim_end_matches('/'), DISCOVERY_PATH);
+        get_https(&url)
+            .await
+            .and_then(|text| discovered_jwks(&self.issuer, &text))
+            .map_err(|e| {
+                VectorDbError::InvalidParameter(format!("OIDC discovery from {}: {}", url, e))
+            })
+    }
+}
+
+/// The `jwks_uri` of a discovery document, which must be for `issuer`
+fn discovered_jwks(issuer: &str, text: &str) -> std::result::Result<String, String> {
+    let doc: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if doc["issuer"] != issuer {
+        return Err(format!(
+            "the document is for {}, not {}",
+            doc["issuer"], issuer
+        ));
+    }
+    let uri = doc["jwks_uri"]
+        .as_str()
+        .ok_or("the document has no jwks_uri")?;
+    if !uri.starts_with("https://") {
+        return Err(format!("jwks_uri {} is not an https:// URL", uri));
+    }
+    Ok(uri.to_string())
+}
+
+/// GET `url`, which must be (and stay, through redirects) https://
+async fn get_https(url: &str) -> std::result::Result<String, String> {
+    if !url.starts_with("https://") {
+        return Err(format!("{} is not an https:// URL", url));
+    }
+    let client = reqwest::Client::builder()
+        .https_only(true)
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let fetched = async {
+        client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    };
+    fetched.await.map_err(|e| e.to_string())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SIGNING KEYS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// An RSA public key from the JWKS
+#[derive(Clone)]
+struct RsaKey {
+    kid: Option<String>,
+    key: DecodingKey,
+}
+
+impl std::fmt::Debug for RsaKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RsaKey").field("kid", &self.kid).finish()
+    }
+}
+
+/// The provider's signing keys
+#[derive(Debug, Clone, Default)]
+pub struct Jwks {
+    keys: Vec<RsaKey>,
+}
+
+impl Jwks {
+    /// Parse a JWKS document, keeping its RSA signing keys
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let doc: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let entries = doc["keys"]
+            .as_array()
+            .ok_or("a JWKS needs a \"keys\" array")?;
+        let mut keys = Vec::new();
+        for entry in entries {
+            let is_rsa = entry["kty"] == "RSA";
+            let signs = entry.get("use").map_or(true, |u| u == "sig");
+            let rs256 = entry.get("alg").map_or(true, |a| a == "RS256");
+            if !is_rsa || !signs || !rs256 {
+                continue;
+            }
+            let kid = entry["kid"].as_str().map(str::to_string);
+            let component = |field: &str| {
+                entry[field]
+                    .as_str()
+                    .ok_or_else(|| format!("RSA key without a valid \"{}\"", field))
+            };
+            let (n, e) = (component("n")?, component("e")?);
+            let bits = modulus_bits(n).ok_or("RSA key without a valid \"n\"")?;
+            if bits < MIN_KEY_BITS {
+                // One weak key shouldn't lock out tokens signed by the others
+                tracing::warn!(
+                    "Skipping RSA key {:?} from the JWKS: {} bits, under the {} required",
+                    kid.as_deref().unwrap_or("(no kid)"),
+                    bits,
+                    MIN_KEY_BITS
+                );
+                continue;
+            }
+            let key = DecodingKey::from_rsa_components(n, e).map_err(|e| e.to_string())?;
+            keys.push(RsaKey { kid, key });
+        }
+        if keys.is_empty() {
+            return Err(format!(
+                "the JWKS has no RSA signing keys of {} bits or more",
+                MIN_KEY_BITS
+            ));
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The key a token's `kid` names; without one, the only key
+    fn key(&self, kid: Option<&str>) -> Option<&DecodingKey> {
+        let key = match kid {
+            Some(kid) => self.keys.iter().find(|k| k.kid.as_deref() == Some(kid)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        };
+        key.map(|k| &k.key)
+    }
+
+    /// Read a JWKS from an https:// URL or a file
+    pub async fn fetch(source: &str) -> Result<Self> {
+        let text = if source.contains("://") {
+            get_https(source).await
+        } else {
+            tokio::fs::read_to_string(source)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        text.and_then(|text| Self::parse(&text))
+            .map_err(|e| VectorDbError::InvalidParameter(format!("JWKS from {}: {}", source, e)))
+    }
+}
+
+/// Bit length of a base64url big-endian modulus
+fn modulus_bits(n: &str) -> Option<usize> {
+    let bytes = URL_SAFE_NO_PAD.decode(n).ok()?;
+    let first = bytes.iter().position(|&b| b != 0)?;
+    Some((bytes.len() - first) * 8 - bytes[first].leading_zeros() as usize)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TOKENS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a verified token allows
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grant {
+    /// The `sub` claim (empty if absent)
+    pub subject: String,
+    /// Collection name patterns, as in a scoped key
+    pub collections: Vec<String>,
+    pub verbs: Vec<Verb>,
+}
+
+impl Grant {
+    /// Does this token allow `access`?
+    pub fn allows(&self, access: &Access) -> bool {
+        auth::scope_allows(&self.collections, &self.verbs, access)
+    }
+}
+
+/// Checks tokens against the config and the provider's current keys
+#[derive(Debug)]
+pub struct Verifier {
+    config: OidcConfig,
+    /// Where the keys were read from (and are refreshed from)
+    source: String,
+    validation: Validation,
+    jwks: RwLock<Jwks>,
+}
+
+impl Verifier {
+    pub fn new(config: OidcConfig, source: impl Into<String>, jwks: Jwks) -> Self {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&config.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss"]);
+        // The lifetime is checked against the caller's clock, not the
+        // crate's (see check_lifetime)
+        validation.validate_exp = false;
+        validation.validate_nbf = false;
+        match &config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        Self {
+            config,
+            source: source.into(),
+            validation,
+            jwks: RwLock::new(jwks),
+        }
+    }
+
+    /// Find and fetch the JWKS `config` names and build a verifier on it
+    pub async fn load(config: OidcConfig) -> Result<Self> {
+        let source = config.jwks_source().await?;
+        let jwks = Jwks::fetch(&source).await?;
+        Ok(Self::new(config, source, jwks))
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// The JWKS URL or file the keys come from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Read the JWKS again, replacing the keys (kept as they are on
+    /// failure); returns the number of keys now loaded
+    pub async fn refresh(&self) -> Result<usize> {
+        let jwks = Jwks::fetch(&self.source).await?;
+        let count = jwks.len();
+        *self.jwks.write().unwrap_or_else(|e| e.into_inner()) = jwks;
+        Ok(count)
+    }
+
+    /// Verify `token` at unix time `now` and map its claims to a grant;
+    /// the error says why it was refused
+    pub fn verify(&self, token: &str, now: u64) -> std::result::Result<Grant, String> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| self.refusal(e))?;
+        if header.alg != Algorithm::RS256 {
+            return Err(format!("unsupported signing algorithm {:?}", header.alg));
+        }
+        let claims: Value = {
+            let jwks = self.jwks.read().unwrap_or_else(|e| e.into_inner());
+            let key = jwks
+                .key(header.kid.as_deref())
+                .ok_or("signed with an unknown key")?;
+            jsonwebtoken::decode(token, key, &self.validation)
+                .map_err(|e| self.refusal(e))?
+                .claims
+        };
+        self.check_lifetime(&claims, now)?;
+        self.grant(&claims)
+    }
+
+    /// Why the crate refused a token, in this module's words
+    fn refusal(&self, e: jsonwebtoken::errors::Error) -> String {
+        match e.kind() {
+            ErrorKind::InvalidToken | ErrorKind::Base64(_) | ErrorKind::Json(_) => {
+                "not a JWT".into()
+            }
+            ErrorKind::InvalidSignature => "bad signature".into(),
+            ErrorKind::InvalidAlgorithm => "unsupported signing algorithm".into(),
+            ErrorKind::InvalidIssuer => format!("not issued by {}", self.config.issuer),
+            ErrorKind::InvalidAudience => format!(
+                "not issued for audience '{}'",
+                self.config.audience.as_deref().unwrap_or_default()
+            ),
+            ErrorKind::MissingRequiredClaim(claim) => format!("no {} claim", claim),
+            _ => e.to_string(),
+        }
+    }
+
+    /// `exp` and `nbf` at `now`, give or take the leeway
+    fn check_lifetime(&self, claims: &Value, now: u64) -> std::result::Result<(), String> {
+        let leeway = self.config.leeway_secs;
+        let exp = claims["exp"].as_u64().ok_or("no exp claim")?;
+        if now > exp.saturating_add(leeway) {
+            return Err("expired".into());
+        }
+        if claims["nbf"]
+            .as_u64()
+            .is_some_and(|nbf| now.saturating_add(leeway) < nbf)
+        {
+            return Err("not valid yet".into());
+        }
+        Ok(())
+    }
+
+    /// Verbs from the scope claim, collections from the tenant claim
+    fn grant(&self, claims: &Value) -> std::result::Result<Grant, String> {
+        let scopes: Vec<&str> = match &claims[self.config.scope_claim.as_str()] {
+            Value::String(scopes) => scopes.split_whitespace().collect(),
+            Value::Array(scopes) => scopes.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let verbs = scopes
+            .iter()
+            .filter_map(|s| s.strip_prefix(self.config.scope_prefix.as_str()))
+            .filter_map(Verb::parse)
+            .collect();
+
+        let collections = match &self.config.tenant_claim {
+            Some(claim) => {
+                let tenant = claims[claim.as_str()]
+                    .as_str()
+                    .filter(|t| !t.is_empty())
+                    .ok_or_else(|| format!("no '{}' claim", claim))?;
+                if !tenant.chars().all(is_tenant_char) {
+                    return Err(format!(
+                        "'{}' claim may only contain letters, digits and '_'",
+                        claim
+                    ));
+                }
+                vec![self.config.tenant_collections.replace("{tenant}", tenant)]
+            }
+            None => vec!["*".into()],
+        };
+        Ok(Grant {
+            subject: claims["sub"].as_str().unwrap_or_default().to_string(),
+            collections,
+            verbs,
+        })
+    }
+}
+
+/// Can `c` appear in a tenant claim? Never a pattern character or the
+/// delimiter after `{tenant}`, so one tenant's pattern can't reach another's
+fn is_tenant_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Is `credential` a JWT rather than an API key? Decided by its header, a
+/// base64url JSON object naming an algorithm, not by the dots alone, so a
+/// key that happens to contain two dots is still treated as a key.
+pub fn is_token(credential: &str) -> bool {
+    jsonwebtoken::decode_header(credential).is_ok()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JWKS: &str = include_str!("../tests/fixtures/oidc_jwks.json");
+    const TOKENS: &str = include_str!("../tests/fixtures/oidc_tokens.json");
+
+    /// A token from the fixtures, signed by the fixture JWKS's key
+    fn token(name: &str) -> String {
+        let tokens: Value = serde_json::from_str(TOKENS).unwrap();
+        tokens[name].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_tokens_map_claims_to_scopes() {
+        let mut config = OidcConfig::new("https://id.example.com");
+        config.audience = Some("vectordb".into());
+        config.tenant_claim = Some("org".into());
+        let verifier = Verifier::new(config, "fixture", Jwks::parse(JWKS).unwrap());
+        let now = 1_800_000_000;
+
+        let grant = verifier.verify(&token("acme_search"), now).unwrap();
+        assert_eq!(grant.subject, "alice");
+        assert_eq!(grant.collections, ["acme-*"]);
+        assert_eq!(grant.verbs, [Verb::Search, Verb::Read]);
+        let search = |name: &str| Access {
+            verb: Verb::Search,
+            collection: Some(name.into()),
+        };
+        assert!(grant.allows(&search("acme-docs")));
+        assert!(!grant.allows(&search("globex-docs")));
+
+        // An audience array works too
+        let grant = verifier.verify(&token("globex_all"), now).unwrap();
+        assert_eq!(grant.verbs.len(), 4);
+
+        let refused = |token: &str| verifier.verify(token, now).unwrap_err();
+        assert_eq!(refused(&token("expired")), "expired");
+        assert!(refused(&token("other_issuer")).contains("issued by"));
+        assert_eq!(refused("not-a-token"), "not a JWT");
+
+        // Alice's signature over Bob's claims
+        let alice = token("acme_search");
+        let bob = token("globex_all");
+        let forged = format!(
+            "{}.{}.{}",
+            alice.split('.').next().unwrap(),
+            bob.split('.').nth(1).unwrap(),
+            alice.split('.').nth(2).unwrap()
+        );
+        assert_eq!(refused(&forged), "bad signature");
+    }
+
+    #[test]
+    fn test_tenants_cannot_reach_each_others_collections() {
+        let mut config = OidcConfig::new("https://id.example.com");
+        config.tenant_claim = Some("org".into());
+        let verifier = Verifier::new(config.clone(), "fixture", Jwks::parse(JWKS).unwrap());
+        let search = |name: &str| Access {
+            verb: Verb::Search,
+            collection: Some(name.into()),
+        };
+        let claims = |org: &str| serde_json::json!({ "org": org, "scope": "vectordb:search" });
+
+        let acme = verifier.grant(&claims("acme")).unwrap();
+        assert!(acme.allows(&search("acme-docs")));
+        assert!(!acme.allows(&search("acme_corp-docs")));
+        let corp = verifier.grant(&claims("acme_corp")).unwrap();
+        assert!(corp.allows(&search("acme_corp-docs")));
+        assert!(!corp.allows(&search("acme-docs")));
+        // "acme-corp" would get "acme-corp-*", inside acme's "acme-*"
+        assert!(verifier.grant(&claims("acme-corp")).is_err());
+        assert!(verifier.grant(&claims("acme*")).is_err());
+
+        // Patterns where a tenant's prefix could run into another's
+        for pattern in ["{tenant}*", "{tenant}_*", "*-{tenant}", "{tenant}x-*"] {
+            config.tenant_collections = pattern.into();
+            assert!(config.validate().is_err(), "{}", pattern);
+        }
+        for pattern in ["{tenant}-*", "{tenant}", "t.{tenant}.*"] {
+            config.tenant_collections = pattern.into();
+            assert!(config.validate().is_ok(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_weak_keys_are_skipped() {
+        let mut doc: Value = serde_json::from_str(JWKS).unwrap();
+        let mut weak = doc["keys"][0].clone();
+        weak["kid"] = "weak".into();
+        weak["n"] = URL_SAFE_NO_PAD.encode([0xc5u8; 128]).into();
+        doc["keys"].as_array_mut().unwrap().push(weak.clone());
+        let jwks = Jwks::parse(&doc.to_string()).unwrap();
+        assert_eq!(jwks.len(), 1);
+        assert!(jwks.key(Some("test-1")).is_some());
+        assert!(jwks.key(Some("weak")).is_none());
+
+        let only_weak = serde_json::json!({ "keys": [weak] }).to_string();
+        assert!(Jwks::parse(&only_weak).is_err());
+    }
+
+    #[test]
+    fn test_keys_with_dots_are_not_tokens() {
+        assert!(is_token(&token("acme_search")));
+        assert!(!is_token("team.prod.key"));
+        assert!(!is_token("bm90.anNvbg.c2ln"));
+    }
+
+    #[test]
+    fn test_keys_are_only_fetched_over_https() {
+        let mut config = OidcConfig::new("https://id.example.com");
+        assert!(config.validate().is_ok());
+        config.jwks = Some("http://id.internal/jwks.json".into());
+        assert!(config.validate().is_err());
+        config.jwks = Some("/etc/vectordb/jwks.json".into());
+        assert!(config.validate().is_ok());
+        // Discovery needs an https issuer
+        config.jwks = None;
+        config.issuer = "http://id.example.com".into();
+        assert!(config.validate().is_err());
+
+        let doc = |issuer: &str, jwks: &str| {
+            serde_json::json!({ "issuer": issuer, "jwks_uri": jwks }).to_string()
+        };
+        let issuer = "https://id.example.com";
+        assert_eq!(
+            discovered_jwks(issuer, &doc(issuer, "https://id.example.com/keys")).unwrap(),
+            "https://id.example.com/keys"
+        );
+        assert!(discovered_jwks(issuer, &doc("https://evil.example", "https://k")).is_err());
+        assert!(discovered_jwks(issuer, &doc(issuer, "http://id.example.com/keys")).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// src/profiling.rs
+//
+// On-demand CPU and heap profiles, for `--features pprof` builds.
+//
+//   GET /debug/pprof/profile?seconds=30   sample the CPU for a while and
+//       &format=proto|flamegraph         return a pprof protobuf (for
+//                                         `go tool pprof`) or an SVG
+//   GET /debug/pprof/heap                 jemalloc heap profile of live
+//                                         allocations (for `jeprof`)
+//
+// CPU profiling is a SIGPROF sampler (pprof-rs) that only runs while a
+// profile is being taken. Heap profiling samples one allocation per
+// ~512 KiB allocated (lg_prof_sample:19) from startup onwards, which is
+// cheap enough to leave on; a dump writes the current sample set.
+//
+// Both endpoints sit behind the admin key (see main.rs). Profiles reveal
+// symbol names and allocation sizes, not data.
+
+use crate::models::{Result, VectorDbError};
+use serde::Deserialize;
+use std::ffi::CString;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// jemalloc options, read by the allocator at startup. Profiling is
+/// compiled in by the `profiling` feature of tikv-jemallocator and switched
+/// on here.
+#[export_name = "_rjem_malloc_conf"]
+pub static MALLOC_CONF: &[u8] = b"prof:true,prof_active:true,lg_prof_sample:19\0";
+
+/// Longest CPU profile one request may take
+pub const MAX_SECONDS: u64 = 300;
+
+/// Default sampling frequency (Hz); odd, so it doesn't line up with timers
+pub const DEFAULT_FREQUENCY: i32 = 99;
+
+/// Output format for CPU profiles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    /// pprof protobuf (`go tool pprof`, Pyroscope, speedscope)
+    #[default]
+    Proto,
+    /// Flamegraph SVG, viewable in a browser
+    Flamegraph,
+}
+
+/// Query parameters for GET /debug/pprof/profile
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileQuery {
+    /// How long to sample (default 30)
+    #[serde(default = "default_seconds")]
+    pub seconds: u64,
+
+    /// Samples per second (default 99)
+    #[serde(default = "default_frequency")]
+    pub frequency: i32,
+
+    #[serde(default)]
+    pub format: ProfileFormat,
+}
+
+fn default_seconds() -> u64 {
+    30
+}
+
+fn default_frequency() -> i32 {
+    DEFAULT_FREQUENCY
+}
+
+/// Sample the CPU for `query.seconds` and render the result.
+///
+/// Returns the content type and the body. Only one profile can run at a
+/// time; a second request fails while the first is sampling.
+pub async fn cpu_profile(query: &ProfileQuery) -> Result<(&'static str, Vec<u8>)> {
+    if query.seconds == 0 || query.seconds > MAX_SECONDS {
+        return Err(VectorDbError::InvalidParameter(format!(
+            "seconds must be between 1 and {}",
+            MAX_SECONDS
+        )));
+    }
+    if !(1..=1000).contains(&query.frequency) {
+        return Err(VectorDbError::InvalidParameter(
+            "frequency must be between 1 and 1000 Hz".into(),
+        ));
+    }
+
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(query.frequency)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(profiling_error)?;
+    tokio::time::sleep(Duration::from_secs(query.seconds)).await;
+    let report = guard.report().build().map_err(profiling_error)?;
+
+    let mut body = Vec::new();
+    match query.format {
+        ProfileFormat::Proto => {
+            use pprof::protos::Message;
+            let profile = report.pprof().map_err(profiling_error)?;
+            profile
+                .write_to_vec(&mut body)
+                .map_err(|e| VectorDbError::SerializationError(e.to_string()))?;
+            Ok(("application/octet-stream", body))
+        }
+        ProfileFormat::Flamegraph => {
+            report.flamegraph(&mut body).map_err(profiling_error)?;
+            Ok(("image/svg+xml", body))
+        }
+    }
+}
+
+/// Dump jemalloc's sampled heap profile
+pub fn heap_profile() -> Result<Vec<u8>> {
+    let enabled: bool = unsafe { tikv_jemalloc_ctl::raw::read(b"opt.prof\0") }
+        .map_err(|e| profiling_error(format!("reading opt.prof: {}", e)))?;
+    if !enabled {
+        return Err(VectorDbError::InvalidParameter(
+            "jemalloc heap profiling is disabled (opt.prof is false)".into(),
+        ));
+    }
+
+    static DUMPS: AtomicU64 = AtomicU64::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "vectordb-heap-{}-{}.prof",
+        std::process::id(),
+        DUMPS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let c_path = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|e| profiling_error(e.to_string()))?;
+    unsafe { tikv_jemalloc_ctl::raw::write(b"prof.dump\0", c_path.as_ptr()) }
+        .map_err(|e| profiling_error(format!("prof.dump: {}", e)))?;
+    let profile = std::fs::read(&path)?;
+    std::fs::remove_file(&path).ok();
+    Ok(profile)
+}
+
+fn profiling_error(e: impl std::fmt::Display) -> VectorDbError {
+    VectorDbError::IoError(io::Error::new(
+        io::ErrorKind::Other,
+        format!("profiling failed: {}", e),
+    ))
+}
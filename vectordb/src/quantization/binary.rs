@@ -0,0 +1,265 @@
+// src/quantization/binary.rs
+//
+// Binary vectors: one bit per component, packed 64 to a u64 word, 32×
+// smaller than f32. Meant for embeddings from binary quantized models,
+// whose components are 0 or 1 to begin with:
+//
+//   bit d  =  (words[d / 64] >> (d % 64)) & 1
+//
+// Bits past the dimension in the last word are always 0, so the Hamming
+// distance of two vectors is the popcount of their XOR summed over the
+// words: one XOR and one POPCNT per 64 components, rather than 64 float
+// comparisons.
+//
+// Collections created with `vector_type: "binary"` accept only 0s and 1s
+// and keep them packed in memory too (engine/collection.rs): exact scans
+// XOR the packed query with each point and count the differing bits.
+// Points are unpacked only where f32 components are asked for: reads,
+// exports, searches ranked by another metric, and the HNSW graph, which
+// holds its own f32 copy. Their flushed segments use the binary encoding
+// (storage/segment.rs), which decodes back to exactly the stored values.
+// BinaryCodes reads such a segment's words as they are and scans them by
+// popcount without decoding.
+//
+// Queries are read the way `DistanceMetric::Hamming` reads components: a
+// component > 0 is a 1, anything else a 0.
+
+use crate::engine::search::TopK;
+use crate::models::{DistanceMetric, Result, VectorDbError, VectorType};
+use crate::storage::segment::{self, VectorEncoding};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Components per packed word
+pub const BITS_PER_WORD: usize = 64;
+
+/// One vector's components, in the form a collection of its vector type
+/// stores them on disk
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorData {
+    Float32(Vec<f32>),
+    /// Packed bits; the dimension is kept alongside (in the segment header
+    /// or the collection), since the last word may be partly used
+    Binary(Vec<u64>),
+}
+
+impl VectorData {
+    /// Encode `components` for a collection of `vector_type`
+    pub fn encode(vector_type: VectorType, components: &[f32]) -> Result<Self> {
+        match vector_type {
+            VectorType::Float32 => Ok(Self::Float32(components.to_vec())),
+            VectorType::Binary => {
+                check_bits(components)?;
+                Ok(Self::Binary(pack(components)))
+            }
+        }
+    }
+
+    /// The components as f32 (0.0 or 1.0 for binary)
+    pub fn decode(&self, dimension: usize) -> Vec<f32> {
+        match self {
+            Self::Float32(data) => data.clone(),
+            Self::Binary(words) => unpack(words, dimension),
+        }
+    }
+
+    /// Bytes the components take up
+    pub fn size_bytes(&self) -> usize {
+        match self {
+            Self::Float32(data) => data.len() * 4,
+            Self::Binary(words) => words.len() * 8,
+        }
+    }
+}
+
+/// Words needed for `dimension` bits
+pub fn words(dimension: usize) -> usize {
+    (dimension + BITS_PER_WORD - 1) / BITS_PER_WORD
+}
+
+/// Pack components into bits (a component > 0 is a 1)
+pub fn pack(components: &[f32]) -> Vec<u64> {
+    let mut words = vec![0u64; words(components.len())];
+    for (d, &x) in components.iter().enumerate() {
+        if x > 0.0 {
+            words[d / BITS_PER_WORD] |= 1 << (d % BITS_PER_WORD);
+        }
+    }
+    words
+}
+
+/// The first `dimension` bits of `words` as 0.0 / 1.0 components
+pub fn unpack(words: &[u64], dimension: usize) -> Vec<f32> {
+    (0..dimension)
+        .map(|d| ((words[d / BITS_PER_WORD] >> (d % BITS_PER_WORD)) & 1) as f32)
+        .collect()
+}
+
+/// Number of differing bits
+pub fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Reject anything but 0s and 1s, naming the first offending component
+pub fn check_bits(components: &[f32]) -> Result<()> {
+    match components.iter().position(|&x| x != 0.0 && x != 1.0) {
+        None => Ok(()),
+        Some(d) => Err(VectorDbError::InvalidParameter(format!(
+            "binary vectors hold only 0s and 1s; component {} is {}",
+            d, components[d]
+        ))),
+    }
+}
+
+/// The packed bits of a run of vectors, in vector order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCodes {
+    dimension: usize,
+    words: Vec<u64>,
+}
+
+impl BinaryCodes {
+    /// Pack `vectors` (all of dimension `dimension`, 0s and 1s only)
+    pub fn encode<T: AsRef<[f32]>>(dimension: usize, vectors: &[T]) -> Result<Self> {
+        let mut words = Vec::with_capacity(vectors.len() * self::words(dimension));
+        for v in vectors {
+            let v = v.as_ref();
+            if v.len() != dimension {
+                return Err(VectorDbError::DimensionMismatch {
+                    expected: dimension,
+                    got: v.len(),
+                });
+            }
+            check_bits(v)?;
+            words.extend(pack(v));
+        }
+        Ok(Self { dimension, words })
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len() / words(self.dimension).max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Packed bits of vector `i`
+    pub fn code(&self, i: usize) -> &[u64] {
+        let n = words(self.dimension);
+        &self.words[i * n..(i + 1) * n]
+    }
+
+    /// Indices and Hamming distances of the `top_k` nearest vectors to
+    /// `query`, nearest first
+    pub fn search(&self, query: &[f32], top_k: usize) -> Result<Vec<(usize, f32)>> {
+        if query.len() != self.dimension {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: self.dimension,
+                got: query.len(),
+            });
+        }
+        let query = pack(query);
+        let mut top = TopK::new(top_k, DistanceMetric::Hamming);
+        for i in 0..self.len() {
+            let distance = hamming(&query, self.code(i)) as f32;
+            top.push(distance, (i, distance));
+        }
+        Ok(top.into_sorted_vec())
+    }
+
+    /// Load the words of a binary segment as stored (None for a segment
+    /// in another encoding)
+    pub fn read_from_segment(path: &Path) -> io::Result<Option<Self>> {
+        let (file, header) = segment::open_segment(path)?;
+        if header.encoding != VectorEncoding::Binary {
+            return Ok(None);
+        }
+        let mut bytes = Vec::new();
+        io::BufReader::new(file)
+            .take(header.count * header.vector_size())
+            .read_to_end(&mut bytes)?;
+        let words = bytes
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("8-byte chunk")))
+            .collect();
+        Ok(Some(Self {
+            dimension: header.dimension as usize,
+            words,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Vector;
+
+    fn bits(seed: u64, dimension: usize) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..dimension)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state & 1) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_packing_round_trips_and_popcount_matches_the_metric() {
+        // 100 bits: one full word and one partly used
+        let a = bits(1, 100);
+        let b = bits(2, 100);
+        let packed = VectorData::encode(VectorType::Binary, &a).unwrap();
+        assert_eq!(packed.size_bytes(), 16);
+        assert_eq!(packed.decode(100), a);
+        assert_eq!(pack(&a)[1] >> 36, 0, "unused bits stay clear");
+
+        let exact = DistanceMetric::Hamming.calculate(&a, &b);
+        assert_eq!(hamming(&pack(&a), &pack(&b)) as f32, exact);
+
+        let err = VectorData::encode(VectorType::Binary, &[1.0, 0.5]).unwrap_err();
+        assert!(err.to_string().contains("component 1 is 0.5"), "{}", err);
+    }
+
+    #[test]
+    fn test_binary_segments_store_packed_words() {
+        let vectors: Vec<Vec<f32>> = (0..40).map(|i| bits(i, 130)).collect();
+        let path = std::env::temp_dir().join(format!(
+            "vectordb_binary_segment_{}.vec",
+            std::process::id()
+        ));
+        let stored: Vec<Vector> = vectors.iter().cloned().map(Vector::new).collect();
+        let mut bytes = Vec::new();
+        segment::write_segment_encoded_to(&mut bytes, &stored, VectorEncoding::Binary, &[])
+            .unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+
+        // 130 bits take 3 words: 24 bytes per vector instead of 520
+        let header = segment::read_segment_header(&path).unwrap();
+        assert_eq!(header.vector_size(), 24);
+        let decoded = segment::read_segment(&path).unwrap();
+        assert!(decoded.iter().zip(&vectors).all(|(d, v)| &d.data == v));
+
+        let codes = BinaryCodes::read_from_segment(&path).unwrap().unwrap();
+        assert_eq!(codes, BinaryCodes::encode(130, &vectors).unwrap());
+        let hits = codes.search(&vectors[7], 3).unwrap();
+        assert_eq!(hits[0], (7, 0.0));
+        for (i, distance) in hits {
+            let exact = DistanceMetric::Hamming.calculate(&vectors[7], &vectors[i]);
+            assert_eq!(distance, exact);
+        }
+
+        // Only 0s and 1s can be packed without losing them
+        let mut bad = stored.clone();
+        bad[3].data[0] = 0.25;
+        let mut bytes = Vec::new();
+        assert!(
+            segment::write_segment_encoded_to(&mut bytes, &bad, VectorEncoding::Binary, &[])
+                .is_err()
+        );
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,8 @@
+// src/quantization/mod.rs
+//
+// Vector compression: smaller encodings of f32 vectors that can still be
+// scored against a query without decoding them first.
+
+pub mod binary;
+pub mod pq;
+pub mod sq;
@@ -0,0 +1,789 @@
+// src/quantization/pq.rs
+//
+// Product quantization (PQ): a 768-dim f32 vector is 3 KB; its PQ code is
+// one byte per subspace, e.g. 96 bytes with 96 subspaces (32× smaller).
+//
+//   vector   [ x0 .. x7 | x8 .. x15 | ... | x760 .. x767 ]   D floats
+//                 │           │                  │
+//   codebook  256 centroids per subspace, trained with k-means
+//                 │           │                  │
+//   code     [   17     |    203    | ... |      4       ]   M bytes
+//
+// The dimensions are cut into M contiguous subspaces (sizes differ by at
+// most one when M doesn't divide D). Each subspace gets its own codebook
+// of up to 256 centroids, and a vector is stored as the index of the
+// nearest centroid in each subspace.
+//
+// Search uses asymmetric distance computation (ADC): the query stays
+// exact, and a DistanceTable holds the query's partial score against
+// every centroid of every subspace (M × 256 floats). Scoring a code is
+// then M table lookups and adds instead of D multiply-adds, and gives
+// exactly the score the query would have against the decoded vector:
+//
+//   euclidean  sqrt(Σ partial squared distances)
+//   dot        Σ partial dot products
+//   cosine     Σ dots / (|q| · sqrt(Σ centroid norms²)), since subspaces
+//              are disjoint the decoded vector's norm splits the same way
+//
+// Codebook plus codes serialize into a "PQ01" segment section (see
+// storage/segment.rs), all little-endian:
+//
+//   u32 dimension, u32 subspaces, u32 centroids per subspace, u32 reserved
+//   f32 × (centroids × dimension)     subspace 0's centroids, then 1's, ...
+//   u64 count, u8 × (count × subspaces) codes, one vector after another
+
+use crate::engine::search::TopK;
+use crate::models::{DistanceMetric, Result, VectorDbError};
+use crate::storage::segment::{self, Section};
+use crate::synthetic::Rng;
+use std::io::{self, Read};
+use std::path::Path;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONSTANTS & CONFIG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Segment section tag for PQ data
+pub const SECTION_TAG: [u8; 4] = *b"PQ01";
+
+/// Most bits per sub-code (codes are one byte each)
+pub const MAX_BITS: u32 = 8;
+
+/// Dimensions per subspace when `subspaces` is left at 0
+pub const AUTO_SUBSPACE_DIMS: usize = 8;
+
+/// Training parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct PqConfig {
+    /// Number of subspaces M, i.e. bytes per code (0 = one per 8 dims)
+    pub subspaces: usize,
+    /// log2 of the centroids per subspace, 1..=8. Capped by the number of
+    /// training vectors, since k-means can't find more centroids than points.
+    pub bits: u32,
+    /// Lloyd iterations per subspace (stops early once assignments settle)
+    pub iterations: usize,
+    /// Training vectors beyond this are sampled down; 256 per centroid is
+    /// plenty for k-means and keeps training time bounded
+    pub max_training: usize,
+    pub seed: u64,
+}
+
+impl Default for PqConfig {
+    fn default() -> Self {
+        Self {
+            subspaces: 0,
+            bits: 8,
+            iterations: 25,
+            max_training: 65_536,
+            seed: 42,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// PRODUCT QUANTIZER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Trained codebooks for one dimension and subspace layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductQuantizer {
+    dimension: usize,
+    subspaces: usize,
+    centroids: usize,
+    /// Subspace s's centroids start at `centroids × start(s)`, each
+    /// `len(s)` floats long
+    codebooks: Vec<f32>,
+}
+
+impl ProductQuantizer {
+    /// Train codebooks on `vectors` (all of the same dimension)
+    pub fn train<T: AsRef<[f32]> + Sync>(vectors: &[T], config: &PqConfig) -> Result<Self> {
+        let dimension = match vectors.first() {
+            Some(v) => v.as_ref().len(),
+            None => {
+                return Err(VectorDbError::InvalidParameter(
+                    "PQ training needs at least one vector".into(),
+                ))
+            }
+        };
+        crate::limits::check_dimension(dimension)?;
+        if let Some(v) = vectors.iter().find(|v| v.as_ref().len() != dimension) {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: dimension,
+                got: v.as_ref().len(),
+            });
+        }
+        if !(1..=MAX_BITS).contains(&config.bits) {
+            return Err(VectorDbError::InvalidParameter(format!(
+                "bits must be between 1 and {}, got {}",
+                MAX_BITS, config.bits
+            )));
+        }
+        let subspaces = match config.subspaces {
+            0 => ((dimension + AUTO_SUBSPACE_DIMS - 1) / AUTO_SUBSPACE_DIMS).max(1),
+            m if m <= dimension => m,
+            m => {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "subspaces ({}) can't exceed the dimension ({})",
+                    m, dimension
+                )))
+            }
+        };
+
+        let mut rng = Rng::stream(config.seed, 0, 0);
+        let sample = sample_indices(vectors.len(), config.max_training.max(1), &mut rng);
+        let centroids = (1usize << config.bits).min(sample.len());
+
+        let mut pq = Self {
+            dimension,
+            subspaces,
+            centroids,
+            codebooks: vec![0.0; centroids * dimension],
+        };
+
+        // Subspaces are independent, so train them on separate threads
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let trained: Vec<Vec<f32>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(subspaces))
+                .map(|t| {
+                    let (pq, sample) = (&pq, &sample);
+                    scope.spawn(move || {
+                        (t..subspaces)
+                            .step_by(threads)
+                            .map(|s| {
+                                let (start, len) = pq.bounds(s);
+                                let data: Vec<f32> = sample
+                                    .iter()
+                                    .flat_map(|&i| &vectors[i].as_ref()[start..start + len])
+                                    .copied()
+                                    .collect();
+                                let mut rng = Rng::stream(config.seed, 1, s as u64);
+                                (
+                                    s,
+                                    kmeans(&data, len, centroids, config.iterations, &mut rng),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            let mut trained = vec![Vec::new(); subspaces];
+            for worker in workers {
+                for (s, codebook) in worker.join().expect("PQ training thread panicked") {
+                    trained[s] = codebook;
+                }
+            }
+            trained
+        });
+        for (s, codebook) in trained.into_iter().enumerate() {
+            let at = centroids * pq.bounds(s).0;
+            pq.codebooks[at..at + codebook.len()].copy_from_slice(&codebook);
+        }
+        Ok(pq)
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn subspaces(&self) -> usize {
+        self.subspaces
+    }
+
+    /// Centroids per subspace
+    pub fn centroids(&self) -> usize {
+        self.centroids
+    }
+
+    /// Bytes per encoded vector
+    pub fn code_size(&self) -> usize {
+        self.subspaces
+    }
+
+    /// First dimension and width of subspace `s`
+    fn bounds(&self, s: usize) -> (usize, usize) {
+        let start = s * self.dimension / self.subspaces;
+        let end = (s + 1) * self.dimension / self.subspaces;
+        (start, end - start)
+    }
+
+    /// Centroid `c` of subspace `s`
+    fn centroid(&self, s: usize, c: usize) -> &[f32] {
+        let (start, len) = self.bounds(s);
+        let at = self.centroids * start + c * len;
+        &self.codebooks[at..at + len]
+    }
+
+    fn check_dimension(&self, v: &[f32]) -> Result<()> {
+        if v.len() != self.dimension {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: self.dimension,
+                got: v.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Encode one vector as `code_size()` bytes
+    pub fn encode(&self, v: &[f32]) -> Result<Vec<u8>> {
+        self.check_dimension(v)?;
+        Ok((0..self.subspaces)
+            .map(|s| {
+                let (start, len) = self.bounds(s);
+                let part = &v[start..start + len];
+                let (best, _) = (0..self.centroids)
+                    .map(|c| (c, squared_l2(part, self.centroid(s, c))))
+                    .fold(
+                        (0, f32::INFINITY),
+                        |best, cur| {
+                            if cur.1 < best.1 {
+                                cur
+                            } else {
+                                best
+                            }
+                        },
+                    );
+                best as u8
+            })
+            .collect())
+    }
+
+    /// Rebuild the approximate vector a code stands for
+    pub fn decode(&self, code: &[u8]) -> Vec<f32> {
+        code.iter()
+            .enumerate()
+            .flat_map(|(s, &c)| self.centroid(s, c as usize).iter().copied())
+            .collect()
+    }
+
+    /// Precompute `query`'s partial scores against every centroid
+    pub fn distance_table(&self, query: &[f32], metric: DistanceMetric) -> Result<DistanceTable> {
+        self.check_dimension(query)?;
+        if metric == DistanceMetric::Hamming {
+            // A centroid is an average; its bits say little about its members'
+            return Err(VectorDbError::InvalidParameter(
+                "product quantized codes can't be scored by hamming distance".into(),
+            ));
+        }
+        let mut table = Vec::with_capacity(self.subspaces * self.centroids);
+        let mut norms = Vec::new();
+        for s in 0..self.subspaces {
+            let (start, len) = self.bounds(s);
+            let part = &query[start..start + len];
+            for c in 0..self.centroids {
+                let centroid = self.centroid(s, c);
+                table.push(match metric {
+                    DistanceMetric::Euclidean => squared_l2(part, centroid),
+                    DistanceMetric::Cosine | DistanceMetric::Dot => dot(part, centroid),
+                    DistanceMetric::Hamming => unreachable!("refused above"),
+                });
+                if metric == DistanceMetric::Cosine {
+                    norms.push(dot(centroid, centroid));
+                }
+            }
+        }
+        Ok(DistanceTable {
+            metric,
+            centroids: self.centroids,
+            table,
+            norms,
+            query_norm: dot(query, query).sqrt(),
+        })
+    }
+
+    fn write_codebook(&self, out: &mut Vec<u8>) {
+        for n in [self.dimension, self.subspaces, self.centroids, 0] {
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        for f in &self.codebooks {
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+
+    fn read_codebook(r: &mut impl Read) -> io::Result<Self> {
+        let dimension = read_u32(r)? as usize;
+        let subspaces = read_u32(r)? as usize;
+        let centroids = read_u32(r)? as usize;
+        let _reserved = read_u32(r)?;
+        if dimension == 0
+            || dimension > crate::limits::max_dimension()
+            || !(1..=dimension).contains(&subspaces)
+            || !(1..=1usize << MAX_BITS).contains(&centroids)
+        {
+            return Err(invalid_data(format!(
+                "Invalid PQ codebook: dimension {}, {} subspaces, {} centroids",
+                dimension, subspaces, centroids
+            )));
+        }
+        let mut codebooks = Vec::with_capacity(centroids * dimension);
+        for _ in 0..centroids * dimension {
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf)?;
+            codebooks.push(f32::from_le_bytes(buf));
+        }
+        Ok(Self {
+            dimension,
+            subspaces,
+            centroids,
+            codebooks,
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ASYMMETRIC DISTANCE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One query's partial scores, for scoring codes without decoding them
+#[derive(Debug, Clone)]
+pub struct DistanceTable {
+    metric: DistanceMetric,
+    centroids: usize,
+    /// Partial squared distance (euclidean) or dot product, per subspace
+    /// and centroid
+    table: Vec<f32>,
+    /// Squared centroid norms, same layout (cosine only)
+    norms: Vec<f32>,
+    query_norm: f32,
+}
+
+impl DistanceTable {
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
+    /// Score a code the way `metric.calculate` would score the query
+    /// against the decoded vector
+    pub fn score(&self, code: &[u8]) -> f32 {
+        let sum: f32 = code
+            .iter()
+            .enumerate()
+            .map(|(s, &c)| self.table[s * self.centroids + c as usize])
+            .sum();
+        match self.metric {
+            DistanceMetric::Euclidean => sum.sqrt(),
+            DistanceMetric::Dot => sum,
+            DistanceMetric::Hamming => unreachable!("distance_table refuses hamming"),
+            DistanceMetric::Cosine => {
+                let norm: f32 = code
+                    .iter()
+                    .enumerate()
+                    .map(|(s, &c)| self.norms[s * self.centroids + c as usize])
+                    .sum::<f32>()
+                    .sqrt();
+                if self.query_norm == 0.0 || norm == 0.0 {
+                    0.0
+                } else {
+                    sum / (self.query_norm * norm)
+                }
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ENCODED VECTORS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A codebook plus the codes of a run of vectors, in vector order
+#[derive(Debug, Clone, PartialEq)]
+pub struct PqCodes {
+    pub quantizer: ProductQuantizer,
+    codes: Vec<u8>,
+}
+
+impl PqCodes {
+    /// Encode `vectors` with an already trained quantizer
+    pub fn encode<T: AsRef<[f32]>>(quantizer: ProductQuantizer, vectors: &[T]) -> Result<Self> {
+        let mut codes = Vec::with_capacity(vectors.len() * quantizer.code_size());
+        for v in vectors {
+            codes.extend(quantizer.encode(v.as_ref())?);
+        }
+        Ok(Self { quantizer, codes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len() / self.quantizer.code_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Code of vector `i`
+    pub fn code(&self, i: usize) -> &[u8] {
+        let size = self.quantizer.code_size();
+        &self.codes[i * size..(i + 1) * size]
+    }
+
+    /// Keep only the codes of vectors `i` with `keep[i]`, in order
+    pub fn retain(&mut self, keep: &[bool]) {
+        let size = self.quantizer.code_size();
+        let mut i = 0;
+        self.codes.retain(|_| {
+            let kept = keep.get(i / size).copied().unwrap_or(false);
+            i += 1;
+            kept
+        });
+    }
+
+    /// Bytes used by codes and codebook together
+    pub fn size_bytes(&self) -> usize {
+        self.codes.len() + self.quantizer.codebooks.len() * 4
+    }
+
+    /// Indices and approximate scores of the `top_k` best matches for
+    /// `query`, best first
+    pub fn search(
+        &self,
+        query: &[f32],
+        metric: DistanceMetric,
+        top_k: usize,
+    ) -> Result<Vec<(usize, f32)>> {
+        let table = self.quantizer.distance_table(query, metric)?;
+        let mut top = TopK::new(top_k, metric);
+        for i in 0..self.len() {
+            let score = table.score(self.code(i));
+            top.push(score, (i, score));
+        }
+        Ok(top.into_sorted_vec())
+    }
+
+    /// Serialize as a segment section
+    pub fn to_section(&self) -> Section {
+        let mut data = Vec::with_capacity(self.size_bytes() + 24);
+        self.quantizer.write_codebook(&mut data);
+        data.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        data.extend_from_slice(&self.codes);
+        Section::new(SECTION_TAG, data)
+    }
+
+    /// Parse a section written by `to_section`
+    pub fn from_section(section: &Section) -> io::Result<Self> {
+        if section.tag != SECTION_TAG {
+            return Err(invalid_data(format!(
+                "Expected a PQ01 section, got {:?}",
+                section.tag_str()
+            )));
+        }
+        let mut r = section.data.as_slice();
+        let quantizer = ProductQuantizer::read_codebook(&mut r)?;
+        let mut count = [0u8; 8];
+        r.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+        let expected = count.checked_mul(quantizer.code_size() as u64);
+        if expected != Some(r.len() as u64) {
+            return Err(invalid_data(format!(
+                "PQ section holds {} code bytes, expected {} codes of {} bytes",
+                r.len(),
+                count,
+                quantizer.code_size()
+            )));
+        }
+        if let Some(&c) = r.iter().find(|&&c| c as usize >= quantizer.centroids) {
+            return Err(invalid_data(format!(
+                "PQ code {} is out of range for {} centroids",
+                c, quantizer.centroids
+            )));
+        }
+        Ok(Self {
+            codes: r.to_vec(),
+            quantizer,
+        })
+    }
+
+    /// Load the PQ section of a segment file, if it has one
+    pub fn read_from_segment(path: &Path) -> io::Result<Option<Self>> {
+        segment::read_section(path, SECTION_TAG)?
+            .map(|section| Self::from_section(&section))
+            .transpose()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// K-MEANS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// `max` distinct indices out of `0..n` (all of them if n <= max), sorted
+fn sample_indices(n: usize, max: usize, rng: &mut Rng) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..n).collect();
+    if n > max {
+        // Partial Fisher-Yates: the first `max` slots end up a uniform sample
+        for i in 0..max {
+            let j = i + rng.below((n - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices.truncate(max);
+        indices.sort_unstable();
+    }
+    indices
+}
+
+/// Cluster `data` (points of `len` floats, back to back) into `k` centroids
+/// with k-means++ seeding and Lloyd iterations
+fn kmeans(data: &[f32], len: usize, k: usize, iterations: usize, rng: &mut Rng) -> Vec<f32> {
+    let n = data.len() / len;
+    let point = |i: usize| &data[i * len..(i + 1) * len];
+
+    // k-means++: each new centroid is a point drawn with probability
+    // proportional to its squared distance from the nearest centroid so far
+    let mut centroids = Vec::with_capacity(k * len);
+    centroids.extend_from_slice(point(rng.below(n as u64) as usize));
+    let mut nearest: Vec<f32> = (0..n)
+        .map(|i| squared_l2(point(i), &centroids[..len]))
+        .collect();
+    while centroids.len() < k * len {
+        let total: f64 = nearest.iter().map(|&d| d as f64).sum();
+        let pick = if total > 0.0 {
+            let mut target = rng.unit() * total;
+            nearest
+                .iter()
+                .position(|&d| {
+                    target -= d as f64;
+                    target <= 0.0
+                })
+                .unwrap_or(n - 1)
+        } else {
+            // Every point sits on a centroid already: duplicates are fine
+            rng.below(n as u64) as usize
+        };
+        let at = centroids.len();
+        centroids.extend_from_slice(point(pick));
+        for (i, d) in nearest.iter_mut().enumerate() {
+            *d = d.min(squared_l2(point(i), &centroids[at..at + len]));
+        }
+    }
+
+    let mut assignment = vec![usize::MAX; n];
+    for _ in 0..iterations {
+        let mut changed = false;
+        let mut distance = vec![0.0f32; n];
+        for i in 0..n {
+            let (best, d) = nearest_centroid(point(i), &centroids, len);
+            distance[i] = d;
+            if assignment[i] != best {
+                assignment[i] = best;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![0.0f64; k * len];
+        let mut counts = vec![0usize; k];
+        for (i, &c) in assignment.iter().enumerate() {
+            counts[c] += 1;
+            for (sum, x) in sums[c * len..(c + 1) * len].iter_mut().zip(point(i)) {
+                *sum += *x as f64;
+            }
+        }
+        for c in 0..k {
+            let centroid = &mut centroids[c * len..(c + 1) * len];
+            if counts[c] > 0 {
+                for (x, sum) in centroid.iter_mut().zip(&sums[c * len..(c + 1) * len]) {
+                    *x = (*sum / counts[c] as f64) as f32;
+                }
+            } else {
+                // Empty cluster: move it to the worst-served point
+                let far = (0..n)
+                    .max_by(|&a, &b| distance[a].total_cmp(&distance[b]))
+                    .unwrap_or(0);
+                centroid.copy_from_slice(point(far));
+                distance[far] = 0.0;
+            }
+        }
+    }
+    centroids
+}
+
+/// Index of and squared distance to the closest of `centroids`
+fn nearest_centroid(point: &[f32], centroids: &[f32], len: usize) -> (usize, f32) {
+    centroids
+        .chunks_exact(len)
+        .map(|c| squared_l2(point, c))
+        .enumerate()
+        .fold(
+            (0, f32::INFINITY),
+            |best, (c, d)| {
+                if d < best.1 {
+                    (c, d)
+                } else {
+                    best
+                }
+            },
+        )
+}
+
+fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Vector;
+    use crate::synthetic::{DatasetSpec, Distribution, Generator};
+
+    fn dataset(count: u64) -> (Generator, Vec<Vec<f32>>) {
+        let g = Generator::new(DatasetSpec {
+            dimension: 32,
+            count,
+            distribution: Distribution::Clustered,
+            clusters: 20,
+            spread: 0.3,
+            seed: 3,
+        })
+        .unwrap();
+        let vectors = (0..count).map(|i| g.vector(i)).collect();
+        (g, vectors)
+    }
+
+    fn config() -> PqConfig {
+        PqConfig {
+            subspaces: 16,
+            iterations: 8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_adc_matches_decoded_scores_and_finds_neighbours() {
+        let (g, vectors) = dataset(1000);
+        let pq = ProductQuantizer::train(&vectors, &config()).unwrap();
+        assert_eq!(
+            (pq.subspaces(), pq.centroids(), pq.code_size()),
+            (16, 256, 16)
+        );
+        let codes = PqCodes::encode(pq, &vectors).unwrap();
+        assert_eq!(codes.len(), 1000);
+
+        let query = g.query(0);
+        for metric in [
+            DistanceMetric::Cosine,
+            DistanceMetric::Euclidean,
+            DistanceMetric::Dot,
+        ] {
+            let table = codes.quantizer.distance_table(&query, metric).unwrap();
+            for i in [0, 7, 999] {
+                let decoded = codes.quantizer.decode(codes.code(i));
+                let exact = metric.calculate(&query, &decoded);
+                let adc = table.score(codes.code(i));
+                assert!(
+                    (adc - exact).abs() < 1e-3 * exact.abs().max(1.0),
+                    "{:?}",
+                    metric
+                );
+            }
+        }
+
+        // ADC ranking recovers most of the true top 10
+        let metric = DistanceMetric::Euclidean;
+        let mut recalled = 0;
+        for q in 0..10 {
+            let query = g.query(q);
+            let mut exact: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i, metric.calculate(&query, v)))
+                .collect();
+            exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let truth: Vec<usize> = exact[..10].iter().map(|&(i, _)| i).collect();
+            let found = codes.search(&query, metric, 10).unwrap();
+            recalled += found.iter().filter(|(i, _)| truth.contains(i)).count();
+        }
+        assert!(recalled >= 80, "recall@10 {}%", recalled);
+    }
+
+    #[test]
+    fn test_codes_round_trip_through_a_segment_section() {
+        let (_, vectors) = dataset(300);
+        let pq = ProductQuantizer::train(&vectors, &config()).unwrap();
+        let codes = PqCodes::encode(pq, &vectors).unwrap();
+        assert!(codes.size_bytes() < vectors.len() * 32 * 4);
+
+        let path = std::env::temp_dir().join(format!("vectordb_pq_{}.vec", std::process::id()));
+        let points: Vec<Vector> = vectors.iter().cloned().map(Vector::new).collect();
+        segment::write_segment_with_sections(&path, &points, &[codes.to_section()]).unwrap();
+        assert_eq!(
+            PqCodes::read_from_segment(&path).unwrap(),
+            Some(codes.clone())
+        );
+        assert_eq!(segment::read_segment(&path).unwrap().len(), 300);
+
+        segment::write_segment(&path, &points).unwrap();
+        assert_eq!(PqCodes::read_from_segment(&path).unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut section = codes.to_section();
+        section.data.pop();
+        assert!(PqCodes::from_section(&section).is_err());
+        let mut section = codes.to_section();
+        *section.data.last_mut().unwrap() = 255;
+        let centroids = codes.quantizer.centroids();
+        assert_eq!(centroids < 256, PqCodes::from_section(&section).is_err());
+    }
+
+    #[test]
+    fn test_training_validates_and_is_deterministic() {
+        let (_, vectors) = dataset(100);
+        let a = ProductQuantizer::train(&vectors, &config()).unwrap();
+        let b = ProductQuantizer::train(&vectors, &config()).unwrap();
+        assert_eq!(a, b);
+        // Fewer vectors than 2^bits: one centroid per vector at most
+        assert_eq!(a.centroids(), 100);
+
+        // Uneven subspaces and the automatic default
+        let odd = ProductQuantizer::train(
+            &vectors,
+            &PqConfig {
+                subspaces: 5,
+                ..config()
+            },
+        )
+        .unwrap();
+        assert_eq!(odd.decode(&odd.encode(&vectors[0]).unwrap()).len(), 32);
+        let auto = ProductQuantizer::train(&vectors, &PqConfig::default()).unwrap();
+        assert_eq!(auto.subspaces(), 4);
+
+        let empty: &[Vec<f32>] = &[];
+        assert!(ProductQuantizer::train(empty, &config()).is_err());
+        assert!(ProductQuantizer::train(
+            &vectors,
+            &PqConfig {
+                bits: 9,
+                ..config()
+            }
+        )
+        .is_err());
+        assert!(ProductQuantizer::train(
+            &vectors,
+            &PqConfig {
+                subspaces: 33,
+                ..config()
+            }
+        )
+        .is_err());
+        let mixed = vec![vec![1.0; 4], vec![1.0; 3]];
+        assert!(ProductQuantizer::train(&mixed, &config()).is_err());
+        assert!(a.encode(&[1.0; 3]).is_err());
+    }
+}
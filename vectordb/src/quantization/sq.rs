@@ -0,0 +1,380 @@
+// src/quantization/sq.rs
+//
+// Scalar quantization (int8): every component becomes one byte, 4× smaller
+// than f32, with a per-dimension affine map fitted to the data:
+//
+//   code  = round((x - min[d]) / scale[d])       0..=255
+//   x̂     = min[d] + code · scale[d]             scale[d] = (max[d] - min[d]) / 255
+//
+// Per-dimension ranges (rather than one range per vector, as the cold tier
+// uses) keep the error small on embeddings whose dimensions have very
+// different spreads. The worst-case error per component is scale[d] / 2.
+//
+// Scoring dequantizes on the fly: a Sq8Query walks a code once,
+// reconstructing each component with one multiply-add, and returns the
+// same score `metric.calculate` would give against the decoded vector.
+//
+// In segments, SQ8 is a vector encoding rather than a section: the header
+// says int8, the mins and scales sit between the header and the vectors,
+// and the vector area holds codes (see storage/segment.rs).
+
+use crate::engine::search::TopK;
+use crate::models::{DistanceMetric, Result, VectorDbError};
+use crate::storage::segment::{self, VectorEncoding};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Trained per-dimension ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarQuantizer {
+    mins: Vec<f32>,
+    scales: Vec<f32>,
+}
+
+impl ScalarQuantizer {
+    /// Fit each dimension's range to `vectors` (all of the same dimension)
+    pub fn train<T: AsRef<[f32]>>(vectors: &[T]) -> Result<Self> {
+        let dimension = match vectors.first() {
+            Some(v) => v.as_ref().len(),
+            None => {
+                return Err(VectorDbError::InvalidParameter(
+                    "SQ8 training needs at least one vector".into(),
+                ))
+            }
+        };
+        let mut mins = vec![f32::INFINITY; dimension];
+        let mut maxs = vec![f32::NEG_INFINITY; dimension];
+        for v in vectors {
+            let v = v.as_ref();
+            if v.len() != dimension {
+                return Err(VectorDbError::DimensionMismatch {
+                    expected: dimension,
+                    got: v.len(),
+                });
+            }
+            if v.iter().any(|x| !x.is_finite()) {
+                return Err(VectorDbError::InvalidParameter(
+                    "SQ8 can't encode NaN or infinite components".into(),
+                ));
+            }
+            for ((min, max), &x) in mins.iter_mut().zip(maxs.iter_mut()).zip(v) {
+                *min = min.min(x);
+                *max = max.max(x);
+            }
+        }
+        let scales = mins
+            .iter()
+            .zip(&maxs)
+            .map(|(min, max)| (max - min) / 255.0)
+            .collect();
+        Self::from_params(mins, scales)
+    }
+
+    /// Build from stored parameters, checking they make sense
+    pub fn from_params(mins: Vec<f32>, scales: Vec<f32>) -> Result<Self> {
+        if mins.len() != scales.len() {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: mins.len(),
+                got: scales.len(),
+            });
+        }
+        if mins.iter().any(|m| !m.is_finite())
+            || scales.iter().any(|s| !(s.is_finite() && *s >= 0.0))
+        {
+            return Err(VectorDbError::InvalidParameter(
+                "SQ8 mins must be finite and scales finite and >= 0".into(),
+            ));
+        }
+        Ok(Self { mins, scales })
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.mins.len()
+    }
+
+    pub fn mins(&self) -> &[f32] {
+        &self.mins
+    }
+
+    pub fn scales(&self) -> &[f32] {
+        &self.scales
+    }
+
+    fn check_dimension(&self, v: &[f32]) -> Result<()> {
+        if v.len() != self.dimension() {
+            return Err(VectorDbError::DimensionMismatch {
+                expected: self.dimension(),
+                got: v.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Encode one vector; components outside the trained range clamp to it
+    pub fn encode(&self, v: &[f32]) -> Result<Vec<u8>> {
+        self.check_dimension(v)?;
+        Ok(v.iter()
+            .zip(self.mins.iter().zip(&self.scales))
+            .map(|(&x, (&min, &scale))| {
+                if scale > 0.0 {
+                    ((x - min) / scale).round().clamp(0.0, 255.0) as u8
+                } else {
+                    0
+                }
+            })
+            .collect())
+    }
+
+    /// Rebuild the approximate vector a code stands for
+    pub fn decode(&self, code: &[u8]) -> Vec<f32> {
+        code.iter()
+            .zip(self.mins.iter().zip(&self.scales))
+            .map(|(&c, (&min, &scale))| min + c as f32 * scale)
+            .collect()
+    }
+
+    /// Prepare `query` for scoring codes with `metric`
+    pub fn query<'a>(&'a self, query: &'a [f32], metric: DistanceMetric) -> Result<Sq8Query<'a>> {
+        self.check_dimension(query)?;
+        Ok(Sq8Query {
+            quantizer: self,
+            query,
+            metric,
+            query_norm: query.iter().map(|x| x * x).sum::<f32>().sqrt(),
+        })
+    }
+
+    /// Write the mins then the scales, as little-endian f32s
+    pub(crate) fn write_params(&self, w: &mut impl Write) -> io::Result<()> {
+        for f in self.mins.iter().chain(&self.scales) {
+            w.write_all(&f.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read what `write_params` wrote, for `dimension` dimensions
+    pub(crate) fn read_params(r: &mut impl Read, dimension: usize) -> io::Result<Self> {
+        let mut floats = Vec::with_capacity(dimension * 2);
+        let mut buf = [0u8; 4];
+        for _ in 0..dimension * 2 {
+            r.read_exact(&mut buf)?;
+            floats.push(f32::from_le_bytes(buf));
+        }
+        let scales = floats.split_off(dimension);
+        Self::from_params(floats, scales)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+/// A query ready to score SQ8 codes
+#[derive(Debug, Clone)]
+pub struct Sq8Query<'a> {
+    quantizer: &'a ScalarQuantizer,
+    query: &'a [f32],
+    metric: DistanceMetric,
+    query_norm: f32,
+}
+
+impl Sq8Query<'_> {
+    /// Score a code the way `metric.calculate` would score the query
+    /// against the decoded vector
+    pub fn score(&self, code: &[u8]) -> f32 {
+        let params = self.quantizer.mins.iter().zip(&self.quantizer.scales);
+        let decoded = code
+            .iter()
+            .zip(params)
+            .map(|(&c, (&min, &scale))| min + c as f32 * scale);
+        let pairs = self.query.iter().zip(decoded);
+        match self.metric {
+            DistanceMetric::Euclidean => pairs.map(|(q, x)| (q - x) * (q - x)).sum::<f32>().sqrt(),
+            DistanceMetric::Dot => pairs.map(|(q, x)| q * x).sum(),
+            DistanceMetric::Hamming => {
+                pairs.filter(|&(q, x)| (*q > 0.0) != (x > 0.0)).count() as f32
+            }
+            DistanceMetric::Cosine => {
+                let (dot, norm) = pairs.fold((0.0f32, 0.0f32), |(dot, norm), (q, x)| {
+                    (dot + q * x, norm + x * x)
+                });
+                let norm = norm.sqrt();
+                if self.query_norm == 0.0 || norm == 0.0 {
+                    0.0
+                } else {
+                    dot / (self.query_norm * norm)
+                }
+            }
+        }
+    }
+}
+
+/// A quantizer plus the codes of a run of vectors, in vector order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sq8Codes {
+    pub quantizer: ScalarQuantizer,
+    codes: Vec<u8>,
+}
+
+impl Sq8Codes {
+    /// Train a quantizer on `vectors` and encode them with it
+    pub fn encode<T: AsRef<[f32]>>(vectors: &[T]) -> Result<Self> {
+        let quantizer = ScalarQuantizer::train(vectors)?;
+        let mut codes = Vec::with_capacity(vectors.len() * quantizer.dimension());
+        for v in vectors {
+            codes.extend(quantizer.encode(v.as_ref())?);
+        }
+        Ok(Self { quantizer, codes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len() / self.quantizer.dimension().max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Code of vector `i`
+    pub fn code(&self, i: usize) -> &[u8] {
+        let d = self.quantizer.dimension();
+        &self.codes[i * d..(i + 1) * d]
+    }
+
+    /// Indices and scores of the `top_k` best matches for `query`, best
+    /// first
+    pub fn search(
+        &self,
+        query: &[f32],
+        metric: DistanceMetric,
+        top_k: usize,
+    ) -> Result<Vec<(usize, f32)>> {
+        let query = self.quantizer.query(query, metric)?;
+        let mut top = TopK::new(top_k, metric);
+        for i in 0..self.len() {
+            let score = query.score(self.code(i));
+            top.push(score, (i, score));
+        }
+        Ok(top.into_sorted_vec())
+    }
+
+    /// Load the codes of an int8 segment as stored, without dequantizing
+    /// them (None for an f32 segment)
+    pub fn read_from_segment(path: &Path) -> io::Result<Option<Self>> {
+        let (file, header) = segment::open_segment(path)?;
+        if header.encoding != VectorEncoding::Int8 {
+            return Ok(None);
+        }
+        let mut r = io::BufReader::new(file);
+        let quantizer = ScalarQuantizer::read_params(&mut r, header.dimension as usize)?;
+        let mut codes = Vec::new();
+        r.take(header.count * header.vector_size())
+            .read_to_end(&mut codes)?;
+        Ok(Some(Self { quantizer, codes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Vector;
+    use crate::synthetic::{DatasetSpec, Generator};
+
+    fn dataset(count: u64) -> (Generator, Vec<Vec<f32>>) {
+        let g = Generator::new(DatasetSpec {
+            dimension: 48,
+            count,
+            seed: 11,
+            ..Default::default()
+        })
+        .unwrap();
+        let vectors = (0..count).map(|i| g.vector(i)).collect();
+        (g, vectors)
+    }
+
+    #[test]
+    fn test_scores_match_decoded_vectors_and_rank_well() {
+        let (g, vectors) = dataset(500);
+        let codes = Sq8Codes::encode(&vectors).unwrap();
+        let sq = &codes.quantizer;
+
+        // Reconstruction error stays within half a step per component
+        for (i, v) in vectors.iter().enumerate().take(20) {
+            for ((x, y), scale) in v.iter().zip(sq.decode(codes.code(i))).zip(sq.scales()) {
+                assert!((x - y).abs() <= scale / 2.0 + 1e-5);
+            }
+        }
+
+        let query = g.query(0);
+        for metric in [
+            DistanceMetric::Cosine,
+            DistanceMetric::Euclidean,
+            DistanceMetric::Dot,
+        ] {
+            let prepared = sq.query(&query, metric).unwrap();
+            for i in [0, 250, 499] {
+                let exact = metric.calculate(&query, &sq.decode(codes.code(i)));
+                let score = prepared.score(codes.code(i));
+                assert!((score - exact).abs() < 1e-4 * exact.abs().max(1.0));
+            }
+
+            // int8 barely moves the ranking
+            let mut exact: Vec<(usize, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i, metric.calculate(&query, v)))
+                .collect();
+            exact.sort_by(|a, b| {
+                let order = a.1.total_cmp(&b.1);
+                if metric.higher_is_better() {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+            let truth: Vec<usize> = exact[..10].iter().map(|&(i, _)| i).collect();
+            let found = codes.search(&query, metric, 10).unwrap();
+            let hits = found.iter().filter(|(i, _)| truth.contains(i)).count();
+            assert!(hits >= 9, "{:?}: {}/10", metric, hits);
+        }
+    }
+
+    #[test]
+    fn test_int8_segments_store_codes() {
+        let (_, vectors) = dataset(40);
+        let mut points: Vec<Vector> = vectors.iter().cloned().map(Vector::new).collect();
+        points[3].metadata.insert("k".into(), "v".into());
+        let path = std::env::temp_dir().join(format!("vectordb_sq8_{}.vec", std::process::id()));
+
+        segment::write_segment_int8(&path, &points).unwrap();
+        let header = segment::read_segment_header(&path).unwrap();
+        assert_eq!(header.encoding, VectorEncoding::Int8);
+        assert_eq!(header.vector_size(), 48);
+        assert_eq!(header.file_size(), std::fs::metadata(&path).unwrap().len());
+
+        let stored = Sq8Codes::read_from_segment(&path).unwrap().unwrap();
+        assert_eq!(stored, Sq8Codes::encode(&vectors).unwrap());
+
+        // Plain readers get dequantized vectors back
+        let loaded = segment::read_segment(&path).unwrap();
+        assert_eq!(loaded[7].data, stored.quantizer.decode(stored.code(7)));
+        assert_eq!(loaded[3].metadata["k"], "v");
+        let (_, one) = segment::read_vector_at(&path, 39).unwrap();
+        assert_eq!(one.data, stored.quantizer.decode(stored.code(39)));
+
+        segment::write_segment(&path, &points).unwrap();
+        assert_eq!(Sq8Codes::read_from_segment(&path).unwrap(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_training_rejects_bad_input() {
+        let empty: &[Vec<f32>] = &[];
+        assert!(ScalarQuantizer::train(empty).is_err());
+        assert!(ScalarQuantizer::train(&[vec![1.0, 2.0], vec![1.0]]).is_err());
+        assert!(ScalarQuantizer::train(&[vec![f32::NAN]]).is_err());
+        assert!(ScalarQuantizer::from_params(vec![0.0], vec![-1.0]).is_err());
+
+        // A constant dimension has scale 0 and decodes exactly
+        let sq = ScalarQuantizer::train(&[vec![2.0, 0.0], vec![2.0, 1.0]]).unwrap();
+        assert_eq!(sq.decode(&sq.encode(&[2.0, 1.0]).unwrap()), vec![2.0, 1.0]);
+        assert_eq!(sq.encode(&[9.0, -5.0]).unwrap(), vec![0, 0]);
+    }
+}
@@ -0,0 +1,210 @@
+// src/querylog.rs
+//
+// Sampled query log for load testing and regression comparison.
+//
+// When enabled, a fraction of search requests is appended to a JSON Lines
+// file, one request per line:
+//
+//   {"offset_ms":1520,"path":"/api/collections/docs/search","body":{...}}
+//
+// `offset_ms` is relative to server start, so `vectordb-cli replay` can
+// re-issue the requests against another instance with their original
+// spacing (or sped up).
+//
+// Privacy: only the path and the JSON body are recorded — never headers,
+// client addresses, or API keys — and any body fields listed in `redact`
+// (e.g. a filter that may contain user data) are removed before writing.
+
+use crate::faults::{self, FaultPoint};
+use crate::models::{Result, VectorDbError};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Where and how much to log (query_log* in config.rs)
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryLogConfig {
+    /// File the log is appended to
+    pub path: PathBuf,
+
+    /// Fraction of searches to record, 0.0..=1.0
+    pub sample_rate: f64,
+
+    /// Body fields removed before writing
+    pub redact: Vec<String>,
+}
+
+/// One recorded request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedQuery {
+    /// Milliseconds since the server started logging
+    pub offset_ms: u64,
+
+    /// Request path, e.g. `/api/collections/docs/search`
+    pub path: String,
+
+    /// Request body with redacted fields removed
+    pub body: serde_json::Value,
+}
+
+/// Append-only sampled log of search requests.
+#[derive(Debug)]
+pub struct QueryLog {
+    config: QueryLogConfig,
+    started: Instant,
+    seen: AtomicU64,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl QueryLog {
+    /// Open (or create) the log file for appending
+    pub fn open(config: QueryLogConfig) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        Ok(Self {
+            config,
+            started: Instant::now(),
+            seen: AtomicU64::new(0),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn config(&self) -> &QueryLogConfig {
+        &self.config
+    }
+
+    /// Decide whether the next request is recorded.
+    ///
+    /// Deterministic rather than random: the n-th request is sampled when
+    /// `floor(n × rate)` ticks over, so exactly `rate` of requests are kept.
+    pub fn should_sample(&self) -> bool {
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        let rate = self.config.sample_rate;
+        ((n + 1.0) * rate).floor() > (n * rate).floor()
+    }
+
+    /// Redact and append one request
+    pub fn record(&self, path: &str, mut body: serde_json::Value) -> Result<()> {
+        redact(&mut body, &self.config.redact);
+        let entry = LoggedQuery {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            path: path.to_string(),
+            body,
+        };
+
+        let line = serde_json::to_string(&entry)?;
+        if !faults::io(FaultPoint::Write)? {
+            return Ok(());
+        }
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", line)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Remove `fields` from a JSON object body (top level only)
+pub fn redact(body: &mut serde_json::Value, fields: &[String]) {
+    if let Some(object) = body.as_object_mut() {
+        for field in fields {
+            object.remove(field);
+        }
+    }
+}
+
+/// Read a query log written by `QueryLog`. Blank lines are skipped.
+pub fn read_log(path: &Path) -> Result<Vec<LoggedQuery>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| VectorDbError::SerializationError(format!("line {}: {}", i + 1, e)))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// When to send `entry` during a replay, relative to the replay's start.
+///
+/// `speed` 1.0 keeps the original spacing, 2.0 runs twice as fast, and
+/// 0.0 means "as fast as possible" (every request at time zero).
+pub fn replay_delay(entry: &LoggedQuery, first_offset_ms: u64, speed: f64) -> Duration {
+    if speed <= 0.0 {
+        return Duration::ZERO;
+    }
+    let elapsed = entry.offset_ms.saturating_sub(first_offset_ms) as f64;
+    Duration::from_secs_f64(elapsed / speed / 1000.0)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vectordb_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_sampling_keeps_exact_fraction() {
+        let path = temp_path("querylog_sample.jsonl");
+        let log = QueryLog::open(QueryLogConfig {
+            path: path.clone(),
+            sample_rate: 0.25,
+            redact: Vec::new(),
+        })
+        .unwrap();
+
+        let kept = (0..100).filter(|_| log.should_sample()).count();
+        assert_eq!(kept, 25);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_record_redacts_and_round_trips() {
+        let path = temp_path("querylog_roundtrip.jsonl");
+        std::fs::remove_file(&path).ok();
+        let log = QueryLog::open(QueryLogConfig {
+            path: path.clone(),
+            sample_rate: 1.0,
+            redact: vec!["filter".into()],
+        })
+        .unwrap();
+
+        let body =
+            serde_json::json!({ "vector": [1.0, 0.0], "top_k": 3, "filter": "email == \"a@b.c\"" });
+        log.record("/search", body).unwrap();
+
+        let entries = read_log(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "/search");
+        assert_eq!(entries[0].body["top_k"], 3);
+        assert!(entries[0].body.get("filter").is_none());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_replay_delay_scales_with_speed() {
+        let entry = LoggedQuery {
+            offset_ms: 3000,
+            path: "/search".into(),
+            body: serde_json::Value::Null,
+        };
+        assert_eq!(replay_delay(&entry, 1000, 1.0), Duration::from_secs(2));
+        assert_eq!(replay_delay(&entry, 1000, 4.0), Duration::from_millis(500));
+        assert_eq!(replay_delay(&entry, 1000, 0.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,259 @@
+// src/storage/access.rs
+//
+// Approximate read-frequency tracking.
+//
+// Keeping an exact counter per vector costs a HashMap entry per ID, which is
+// more than the vector itself for small embeddings. A count-min sketch gives
+// us "roughly how often was X read?" in a fixed amount of memory:
+//
+//   depth rows × width counters, one hash function per row
+//   increment: bump one counter in every row
+//   estimate:  take the minimum across rows (never under-counts)
+//
+// Counts are halved on every decay() so the sketch reflects *recent* reads;
+// the tiering sweep calls it once per pass.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COUNT-MIN SKETCH
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Fixed-size frequency estimator. Over-counts on hash collisions, never
+/// under-counts.
+#[derive(Debug, Clone)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u32>,
+}
+
+impl CountMinSketch {
+    /// Create a sketch with `depth` rows of `width` counters each.
+    ///
+    /// Error is roughly `total / width` with probability `1 - 2^-depth`.
+    pub fn new(width: usize, depth: usize) -> Self {
+        let width = width.max(1);
+        let depth = depth.max(1);
+        Self {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+        }
+    }
+
+    /// Counter index for `key` in row `row`
+    fn slot(&self, row: usize, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        row * self.width + (hasher.finish() as usize % self.width)
+    }
+
+    /// Record one occurrence of `key`
+    pub fn increment(&mut self, key: &str) {
+        for row in 0..self.depth {
+            let slot = self.slot(row, key);
+            self.counters[slot] = self.counters[slot].saturating_add(1);
+        }
+    }
+
+    /// Estimated number of occurrences of `key`
+    pub fn estimate(&self, key: &str) -> u32 {
+        (0..self.depth)
+            .map(|row| self.counters[self.slot(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Halve every counter (exponential aging)
+    pub fn decay(&mut self) {
+        for c in &mut self.counters {
+            *c /= 2;
+        }
+    }
+
+    /// Memory used by the counters, in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.counters.len() * std::mem::size_of::<u32>()
+    }
+}
+
+impl Default for CountMinSketch {
+    fn default() -> Self {
+        // 4 × 4096 counters = 64 KB, ~0.025% error per read recorded
+        Self::new(4096, 4)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ACCESS TRACKER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Read statistics for vectors and the segments (tiers) they live in.
+#[derive(Debug, Clone)]
+pub struct AccessTracker {
+    /// Per-vector read frequency
+    sketch: CountMinSketch,
+
+    /// Exact read counts per segment — there are few of them
+    segment_reads: HashMap<String, u64>,
+
+    /// Small set of the most-read vectors seen so far: id → estimate
+    hottest: HashMap<String, u32>,
+
+    /// How many entries `hottest` keeps
+    hottest_capacity: usize,
+
+    /// Total reads recorded since startup
+    total_reads: u64,
+}
+
+impl Default for AccessTracker {
+    fn default() -> Self {
+        Self {
+            sketch: CountMinSketch::default(),
+            segment_reads: HashMap::new(),
+            hottest: HashMap::new(),
+            hottest_capacity: 10,
+            total_reads: 0,
+        }
+    }
+}
+
+impl AccessTracker {
+    /// Record a read of vector `id` served from `segment`
+    pub fn record(&mut self, id: &str, segment: &str) {
+        self.total_reads += 1;
+        *self.segment_reads.entry(segment.to_string()).or_insert(0) += 1;
+
+        self.sketch.increment(id);
+        let estimate = self.sketch.estimate(id);
+        self.update_hottest(id, estimate);
+    }
+
+    /// Keep `hottest` as the top-N vectors by estimated frequency
+    fn update_hottest(&mut self, id: &str, estimate: u32) {
+        if self.hottest.contains_key(id) || self.hottest.len() < self.hottest_capacity {
+            self.hottest.insert(id.to_string(), estimate);
+            return;
+        }
+
+        let coldest = self
+            .hottest
+            .iter()
+            .min_by_key(|(_, &count)| count)
+            .map(|(id, &count)| (id.clone(), count));
+
+        if let Some((coldest_id, coldest_count)) = coldest {
+            if estimate > coldest_count {
+                self.hottest.remove(&coldest_id);
+                self.hottest.insert(id.to_string(), estimate);
+            }
+        }
+    }
+
+    /// Estimated recent reads of vector `id`
+    pub fn frequency(&self, id: &str) -> u32 {
+        self.sketch.estimate(id)
+    }
+
+    /// Exact reads served by `segment` since startup
+    pub fn segment_reads(&self, segment: &str) -> u64 {
+        self.segment_reads.get(segment).copied().unwrap_or(0)
+    }
+
+    /// Total reads recorded since startup
+    pub fn total_reads(&self) -> u64 {
+        self.total_reads
+    }
+
+    /// Age all per-vector frequencies so old reads count for less
+    pub fn decay(&mut self) {
+        self.sketch.decay();
+        for count in self.hottest.values_mut() {
+            *count /= 2;
+        }
+        self.hottest.retain(|_, count| *count > 0);
+    }
+
+    /// Summary for the stats endpoint
+    pub fn stats(&self) -> serde_json::Value {
+        let mut hottest: Vec<_> = self.hottest.iter().collect();
+        hottest.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        serde_json::json!({
+            "total_reads": self.total_reads,
+            "segment_reads": self.segment_reads,
+            "hottest": hottest
+                .into_iter()
+                .map(|(id, reads)| serde_json::json!({ "id": id, "reads": reads }))
+                .collect::<Vec<_>>(),
+            "sketch_bytes": self.sketch.size_bytes(),
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sketch_never_undercounts() {
+        let mut sketch = CountMinSketch::new(64, 4);
+        for i in 0..500 {
+            sketch.increment(&format!("key_{}", i % 50));
+        }
+        for i in 0..50 {
+            assert!(sketch.estimate(&format!("key_{}", i)) >= 10);
+        }
+        assert_eq!(CountMinSketch::new(64, 4).estimate("missing"), 0);
+    }
+
+    #[test]
+    fn test_sketch_decay_halves_counts() {
+        let mut sketch = CountMinSketch::default();
+        for _ in 0..8 {
+            sketch.increment("a");
+        }
+        sketch.decay();
+        assert_eq!(sketch.estimate("a"), 4);
+    }
+
+    #[test]
+    fn test_tracker_counts_segments_and_hottest() {
+        let mut tracker = AccessTracker::default();
+        for _ in 0..5 {
+            tracker.record("popular", "hot");
+        }
+        tracker.record("rare", "cold");
+
+        assert_eq!(tracker.total_reads(), 6);
+        assert_eq!(tracker.segment_reads("hot"), 5);
+        assert_eq!(tracker.segment_reads("cold"), 1);
+        assert_eq!(tracker.frequency("popular"), 5);
+
+        let stats = tracker.stats();
+        assert_eq!(stats["hottest"][0]["id"], "popular");
+    }
+
+    #[test]
+    fn test_hottest_is_bounded() {
+        let mut tracker = AccessTracker::default();
+        for i in 0..100 {
+            tracker.record(&format!("v{}", i), "hot");
+        }
+        for _ in 0..3 {
+            tracker.record("star", "hot");
+        }
+        let stats = tracker.stats();
+        assert_eq!(stats["hottest"].as_array().unwrap().len(), 10);
+        assert_eq!(stats["hottest"][0]["id"], "star");
+    }
+}
@@ -0,0 +1,192 @@
+// src/storage/bloom.rs
+//
+// Bloom filter for cheap "have we ever seen this ID?" checks.
+//
+// Ingestion pipelines ask "does doc_123 exist?" far more often than they
+// read vectors back, and most of the answers are "no". A bloom filter
+// answers those in a few hashes without touching the ID index:
+//
+//   m bits, k hash functions (derived from one 64-bit hash by double hashing)
+//   insert:         set k bits
+//   might_contain:  all k bits set → maybe; any bit clear → definitely not
+//
+// Bits can't be cleared, so deleted IDs keep answering "maybe" until the
+// owner rebuilds the filter. Callers must treat "maybe" as "check the
+// index", never as "yes".
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// False-positive rate the filter is sized for by default
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Capacity of a filter created with `Default`
+const DEFAULT_CAPACITY: usize = 1024;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// BLOOM FILTER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Fixed-size set membership test with false positives but no false
+/// negatives.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    /// Number of bits (`bits.len() * 64`)
+    num_bits: u64,
+    /// Number of hash functions
+    hashes: u32,
+    /// IDs the filter was sized for
+    capacity: usize,
+    /// Insertions so far (including repeats)
+    inserted: usize,
+}
+
+impl BloomFilter {
+    /// Create a filter that holds `capacity` keys at roughly
+    /// `false_positive_rate`.
+    ///
+    /// Uses the textbook sizing: m = -n·ln(p) / ln(2)², k = (m/n)·ln(2).
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let wanted = (-(capacity as f64) * p.ln() / (ln2 * ln2)).ceil() as u64;
+        let words = ((wanted.max(64) + 63) / 64) as usize;
+        let num_bits = words as u64 * 64;
+        let hashes = ((num_bits as f64 / capacity as f64) * ln2).round() as u32;
+        Self {
+            bits: vec![0; words],
+            num_bits,
+            hashes: hashes.clamp(1, 16),
+            capacity,
+            inserted: 0,
+        }
+    }
+
+    /// Bit positions for `key`: h1 + i·h2 for i in 0..k
+    fn positions(&self, key: &str) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let h = hasher.finish();
+        let h1 = h & 0xFFFF_FFFF;
+        // Odd, so the probe sequence never collapses onto one bit
+        let h2 = (h >> 32) | 1;
+        let num_bits = self.num_bits;
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    /// Add `key` to the set
+    pub fn insert(&mut self, key: &str) {
+        let positions: Vec<u64> = self.positions(key).collect();
+        for bit in positions {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.inserted += 1;
+    }
+
+    /// `false` means `key` was never inserted; `true` means it probably was
+    pub fn might_contain(&self, key: &str) -> bool {
+        self.positions(key)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Number of keys the filter was sized for
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of insertions so far
+    pub fn inserted(&self) -> usize {
+        self.inserted
+    }
+
+    /// Has the filter taken more keys than it was sized for? Past this
+    /// point the false-positive rate climbs quickly; rebuild it larger.
+    pub fn is_saturated(&self) -> bool {
+        self.inserted > self.capacity
+    }
+
+    /// Memory used by the bit array
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Expected false-positive rate given the current fill:
+    /// (1 - e^(-k·n/m))^k
+    pub fn false_positive_rate(&self) -> f64 {
+        let k = self.hashes as f64;
+        let fill = 1.0 - (-k * self.inserted as f64 / self.num_bits as f64).exp();
+        fill.powf(k)
+    }
+}
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+}
+
+impl<'a> FromIterator<&'a str> for BloomFilter {
+    /// Build a filter sized for twice the keys given, leaving room to grow
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let keys: Vec<&str> = iter.into_iter().collect();
+        let mut filter = Self::new(
+            (keys.len() * 2).max(DEFAULT_CAPACITY),
+            DEFAULT_FALSE_POSITIVE_RATE,
+        );
+        for key in keys {
+            filter.insert(key);
+        }
+        filter
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        for i in 0..1_000 {
+            filter.insert(&format!("doc_{}", i));
+        }
+        for i in 0..1_000 {
+            assert!(filter.might_contain(&format!("doc_{}", i)));
+        }
+        assert!(!filter.is_saturated());
+    }
+
+    #[test]
+    fn test_false_positive_rate_near_target() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        for i in 0..10_000 {
+            filter.insert(&format!("in_{}", i));
+        }
+        let false_positives = (0..10_000)
+            .filter(|i| filter.might_contain(&format!("out_{}", i)))
+            .count();
+        // Sized for 1%; allow generous slack for hash variance
+        assert!(false_positives < 300, "{} false positives", false_positives);
+        assert!((filter.false_positive_rate() - 0.01).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_saturation_and_rebuild() {
+        let mut filter = BloomFilter::new(10, 0.01);
+        for i in 0..11 {
+            filter.insert(&i.to_string());
+        }
+        assert!(filter.is_saturated());
+
+        let ids: Vec<String> = (0..11).map(|i| i.to_string()).collect();
+        let rebuilt: BloomFilter = ids.iter().map(String::as_str).collect();
+        assert!(!rebuilt.is_saturated());
+        assert!(ids.iter().all(|id| rebuilt.might_contain(id)));
+    }
+}
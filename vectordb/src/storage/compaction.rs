@@ -0,0 +1,760 @@
+// src/storage/compaction.rs
+//
+// Merging segments and dropping deleted vectors.
+//
+// Every flush adds a segment and every delete leaves a dead vector behind
+// in one, so over time a collection ends up spread across many small,
+// partly dead files. Compaction rewrites a set of segments as one:
+//
+//   seg_a.vec ─┐
+//   seg_b.vec ─┼─ merge_segments ──▶ merged.vec   (live vectors only,
+//   seg_c.vec ─┘                                  inputs in order)
+//
+// Inputs are streamed, never loaded whole: a first pass verifies each
+// input's checksum and collects the surviving vectors' metadata and IDs,
+// which fix the output header; a second pass copies surviving vectors a
+// chunk at a time. Only the output's metadata block and ID table are held
+// in memory.
+//
+// The caller decides what's deleted given each vector's position (input
+// number, vector index) and its ID, when the inputs have ID tables. Either
+// all non-empty inputs have one or none do: a merged segment with IDs for
+// only some of its vectors couldn't say which. The output is f32 — int8
+// inputs are decoded — unless every input is binary, in which case the
+// packed words are copied as they are. Surviving vectors' sparse
+// embeddings (storage::sparse) come along; other per-segment sections (PQ
+// codebooks and the like) describe vectors that no longer sit where they
+// did, so they're dropped and must be rebuilt for the merged segment. An
+// f32 output gets fresh norm ranges (storage::norms), filled in as
+// vectors stream by.
+//
+// The output is written to `<output>.tmp`, synced, and renamed into place,
+// so a crash mid-merge leaves the inputs untouched and no half-written
+// segment under the output name. The output may be one of the inputs.
+//
+// A collection with a few very large segments needs something cheaper
+// than a full merge after heavy deletes: `defragment_segment` rewrites one
+// segment in place (same tmp + rename) without its dropped rows. Since
+// nothing is merged, the stored bytes carry over as they are — an int8
+// segment stays int8 with its quantization parameters unchanged — and the
+// blocks after the vectors are rebuilt for the survivors: the metadata
+// block, the ID table, the PQ codes (same codebook), the sparse
+// embeddings, the norm ranges (recomputed, f32 segments only), and the
+// footer.
+// Sections it doesn't know how to reindex are dropped and named in the
+// report. A segment with nothing to drop isn't rewritten.
+
+use crate::quantization::pq::{self, PqCodes};
+use crate::storage::norms::{self, NormBlocks};
+use crate::storage::segment::{
+    self, Decoder, Section, SegmentHeader, StreamingWriter, VectorEncoding,
+};
+use crate::storage::sparse::{self, SparseRows};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Vectors copied per read while streaming an input
+const CHUNK: u64 = 4096;
+
+/// Where a vector sits among the merge inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// Index into the input paths
+    pub input: usize,
+    /// Vector index within that segment
+    pub index: u64,
+}
+
+/// What a merge did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Segments merged
+    pub inputs: usize,
+    /// Vectors read across all inputs
+    pub read: u64,
+    /// Vectors skipped as deleted
+    pub dropped: u64,
+    /// Vectors in the output
+    pub written: u64,
+    /// Size of the output file
+    pub bytes: u64,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// MERGE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Merge the segments at `paths` into one segment at `output`, leaving out
+/// every vector for which `is_deleted` returns true. It's given the
+/// vector's position and, if the inputs store IDs, its ID. Surviving
+/// vectors keep their relative order: all of input 0's, then input 1's,
+/// and so on.
+///
+/// Fails without touching `output` if any input is corrupt, or the inputs
+/// disagree on dimension or on having ID tables (empty inputs don't count).
+pub fn merge_segments<P: AsRef<Path>>(
+    paths: &[P],
+    output: &Path,
+    is_deleted: impl Fn(Position, Option<&str>) -> bool,
+) -> io::Result<MergeReport> {
+    let _span = tracing::info_span!(
+        "compaction.merge",
+        inputs = paths.len(),
+        output = %output.display()
+    )
+    .entered();
+
+    // Pass 1: verify inputs, agree on a dimension, collect live metadata
+    // and IDs, and note which vectors survive
+    let mut inputs = Vec::with_capacity(paths.len());
+    let mut dimension = None;
+    let mut has_ids = None;
+    let mut metadata = Vec::new();
+    let mut any_metadata = false;
+    let mut ids = Vec::new();
+    let mut sparse = SparseRows::default();
+    let mut report = MergeReport {
+        inputs: paths.len(),
+        ..MergeReport::default()
+    };
+    for (input, path) in paths.iter().enumerate() {
+        let path = path.as_ref();
+        let header = segment::verify_segment(path)?;
+        if header.count > 0 {
+            match dimension {
+                Some(d) if d != header.dimension => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} has dimension {}, earlier inputs have {}",
+                            path.display(),
+                            header.dimension,
+                            d
+                        ),
+                    ));
+                }
+                _ => dimension = Some(header.dimension),
+            }
+        }
+
+        let mut r = BufReader::new(File::open(path)?);
+        let input_ids = segment::read_ids(&mut r, &header)?;
+        if header.count > 0 {
+            match has_ids {
+                Some(h) if h != input_ids.is_some() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} {}",
+                            path.display(),
+                            match h {
+                                true => "has no ID table, earlier inputs have one",
+                                false => "has an ID table, earlier inputs don't",
+                            }
+                        ),
+                    ));
+                }
+                _ => has_ids = Some(input_ids.is_some()),
+            }
+        }
+
+        let input_sparse = segment::read_sparse_rows(&mut r, &header)?;
+        r.seek(SeekFrom::Start(header.metadata_offset()))?;
+        let mut block = r.take(header.metadata_size);
+        let mut keep = Vec::with_capacity(header.count as usize);
+        for index in 0..header.count {
+            let entry = match header.metadata_size {
+                0 => Default::default(),
+                _ => segment::read_metadata_entry(&mut block)?,
+            };
+            let id = input_ids.as_ref().map(|ids| ids[index as usize].as_str());
+            report.read += 1;
+            let live = !is_deleted(Position { input, index }, id);
+            keep.push(live);
+            if !live {
+                report.dropped += 1;
+                continue;
+            }
+            any_metadata |= !entry.is_empty();
+            segment::encode_metadata_entry(&mut metadata, &entry)?;
+            ids.extend(id.map(str::to_string));
+            sparse.push(
+                input_sparse
+                    .as_ref()
+                    .and_then(|rows| rows.row(index as usize))
+                    .as_ref(),
+            );
+            report.written += 1;
+        }
+        inputs.push((path, header, keep));
+    }
+    if !any_metadata {
+        metadata.clear();
+    }
+    let mut non_empty = inputs.iter().filter(|(_, header, _)| header.count > 0);
+    let encoding = match non_empty.all(|(_, header, _)| header.encoding == VectorEncoding::Binary) {
+        true if dimension.is_some() => VectorEncoding::Binary,
+        _ => VectorEncoding::F32,
+    };
+    let mut sections = match has_ids {
+        Some(true) => vec![segment::id_table_section(&ids)],
+        _ => Vec::new(),
+    };
+    if sparse.has_entries() {
+        sections.push(sparse.to_section());
+    }
+    if encoding == VectorEncoding::F32 {
+        sections.push(NormBlocks::placeholder(
+            report.written,
+            norms::DEFAULT_BLOCK_SIZE,
+        ));
+    }
+
+    // Pass 2: stream live vectors into the output
+    let tmp = tmp_path(output);
+    let result = write_merged(
+        &inputs,
+        &tmp,
+        &report,
+        dimension.unwrap_or(0),
+        encoding,
+        &metadata,
+        sections,
+    );
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, output)?;
+    report.bytes = std::fs::metadata(output)?.len();
+
+    tracing::info!(
+        "Merged {} segments into {}: {} vectors kept, {} dropped",
+        report.inputs,
+        output.display(),
+        report.written,
+        report.dropped
+    );
+    Ok(report)
+}
+
+/// An input segment and which of its vectors survive
+type Input<'a> = (&'a Path, SegmentHeader, Vec<bool>);
+
+/// Write the merged segment to `tmp` and sync it
+fn write_merged(
+    inputs: &[Input],
+    tmp: &Path,
+    report: &MergeReport,
+    dimension: u32,
+    encoding: VectorEncoding,
+    metadata: &[u8],
+    sections: Vec<Section>,
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create(tmp)?);
+    let mut w = StreamingWriter::new_encoded(
+        file,
+        report.written,
+        dimension,
+        encoding,
+        &[],
+        metadata.len() as u64,
+        sections,
+    )?;
+
+    for (path, header, keep) in inputs {
+        if header.count == 0 {
+            continue;
+        }
+        let mut r = BufReader::new(File::open(path)?);
+        if encoding == VectorEncoding::Binary {
+            r.seek(SeekFrom::Start(header.data_offset()))?;
+            let mut bytes = vec![0u8; header.vector_size() as usize];
+            for &live in keep {
+                r.read_exact(&mut bytes)?;
+                if live {
+                    w.write_raw(&bytes)?;
+                }
+            }
+            continue;
+        }
+        // Encoding parameters (if any) sit between the fixed header and data
+        r.seek(SeekFrom::Start(header.data_offset() - header.params_size()))?;
+        let decoder = Decoder::read(&mut r, header)?;
+
+        let mut index = 0;
+        while index < header.count {
+            let n = CHUNK.min(header.count - index);
+            for vector in decoder.read_vectors(&mut r, n)? {
+                if keep[index as usize] {
+                    w.write_vector(&vector.data)?;
+                }
+                index += 1;
+            }
+        }
+    }
+
+    let mut file = w.finish(metadata)?;
+    file.flush()?;
+    file.get_ref().sync_all()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DEFRAGMENT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a defragmentation did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefragReport {
+    /// Vectors in the segment before
+    pub read: u64,
+    pub dropped: u64,
+    /// Vectors in the segment after
+    pub written: u64,
+    /// File size before and after
+    pub bytes_before: u64,
+    pub bytes: u64,
+    /// Tags of sections left out because they couldn't be reindexed
+    pub dropped_sections: Vec<String>,
+}
+
+/// Rewrite the segment at `path` in place without every vector for which
+/// `is_deleted` returns true. It's given the vector's index and, if the
+/// segment stores IDs, its ID. The encoding and surviving vectors' bytes
+/// are kept as they are.
+///
+/// The segment's checksum is verified first; if it's corrupt, or anything
+/// fails midway, the original file is left as it was.
+pub fn defragment_segment(
+    path: &Path,
+    is_deleted: impl Fn(u64, Option<&str>) -> bool,
+) -> io::Result<DefragReport> {
+    let _span = tracing::info_span!("compaction.defragment", segment = %path.display()).entered();
+
+    let header = segment::verify_segment(path)?;
+    let mut r = BufReader::new(File::open(path)?);
+    let ids = segment::read_ids(&mut r, &header)?;
+
+    // Which vectors survive, and their metadata
+    r.seek(SeekFrom::Start(header.metadata_offset()))?;
+    let mut block = (&mut r).take(header.metadata_size);
+    let mut keep = Vec::with_capacity(header.count as usize);
+    let mut metadata = Vec::new();
+    let mut any_metadata = false;
+    let mut report = DefragReport {
+        read: header.count,
+        bytes_before: std::fs::metadata(path)?.len(),
+        ..DefragReport::default()
+    };
+    for index in 0..header.count {
+        let entry = match header.metadata_size {
+            0 => Default::default(),
+            _ => segment::read_metadata_entry(&mut block)?,
+        };
+        let id = ids.as_ref().map(|ids| ids[index as usize].as_str());
+        let live = !is_deleted(index, id);
+        keep.push(live);
+        if !live {
+            report.dropped += 1;
+            continue;
+        }
+        any_metadata |= !entry.is_empty();
+        segment::encode_metadata_entry(&mut metadata, &entry)?;
+    }
+    report.written = header.count - report.dropped;
+    if report.dropped == 0 {
+        report.bytes = report.bytes_before;
+        return Ok(report);
+    }
+    if !any_metadata {
+        metadata.clear();
+    }
+
+    // Rebuild the sections that index vectors by position
+    r.seek(SeekFrom::Start(header.sections_offset()))?;
+    let mut sections = Vec::new();
+    for section in segment::read_sections_at(&mut r, &header)? {
+        if section.tag == segment::ID_TABLE_TAG {
+            let kept: Vec<&String> = ids
+                .iter()
+                .flatten()
+                .zip(&keep)
+                .filter_map(|(id, &k)| k.then_some(id))
+                .collect();
+            sections.push(segment::id_table_section(&kept));
+        } else if section.tag == pq::SECTION_TAG {
+            let mut codes = PqCodes::from_section(&section)?;
+            codes.retain(&keep);
+            sections.push(codes.to_section());
+        } else if section.tag == sparse::SECTION_TAG {
+            let mut rows = SparseRows::from_section(&section, header.count)?;
+            rows.retain(&keep);
+            if rows.has_entries() {
+                sections.push(rows.to_section());
+            }
+        } else if section.tag == norms::SECTION_TAG && header.encoding == VectorEncoding::F32 {
+            let block_size = NormBlocks::section_block_size(&section)?;
+            sections.push(NormBlocks::placeholder(report.written, block_size));
+        } else {
+            report.dropped_sections.push(section.tag_str());
+        }
+    }
+
+    let tmp = tmp_path(path);
+    let result = write_defragmented(
+        path,
+        &tmp,
+        &header,
+        &keep,
+        report.written,
+        &metadata,
+        sections,
+    );
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, path)?;
+    report.bytes = std::fs::metadata(path)?.len();
+
+    tracing::info!(
+        "Defragmented {}: {} of {} vectors dropped, {} -> {} bytes",
+        path.display(),
+        report.dropped,
+        report.read,
+        report.bytes_before,
+        report.bytes
+    );
+    Ok(report)
+}
+
+/// Copy the kept vectors' stored bytes from `path` into a new segment at
+/// `tmp` and sync it
+fn write_defragmented(
+    path: &Path,
+    tmp: &Path,
+    header: &SegmentHeader,
+    keep: &[bool],
+    written: u64,
+    metadata: &[u8],
+    sections: Vec<Section>,
+) -> io::Result<()> {
+    let mut r = BufReader::new(File::open(path)?);
+    r.seek(SeekFrom::Start(header.data_offset() - header.params_size()))?;
+    let mut params = vec![0u8; header.params_size() as usize];
+    r.read_exact(&mut params)?;
+
+    let file = BufWriter::new(File::create(tmp)?);
+    let mut w = StreamingWriter::new_encoded(
+        file,
+        written,
+        header.dimension,
+        header.encoding,
+        &params,
+        metadata.len() as u64,
+        sections,
+    )?;
+    let size = header.vector_size() as usize;
+    let mut buf = Vec::new();
+    let mut index = 0;
+    while index < header.count {
+        let n = CHUNK.min(header.count - index);
+        buf.resize(n as usize * size, 0);
+        r.read_exact(&mut buf)?;
+        for vector in buf.chunks_exact(size) {
+            if keep[index as usize] {
+                w.write_raw(vector)?;
+            }
+            index += 1;
+        }
+    }
+
+    let mut file = w.finish(metadata)?;
+    file.flush()?;
+    file.get_ref().sync_all()
+}
+
+/// `<output>.tmp`, next to the output so the rename stays on one filesystem
+pub(crate) fn tmp_path(output: &Path) -> PathBuf {
+    let mut tmp = output.as_os_str().to_owned();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SparseVector, Vector};
+    use std::collections::HashSet;
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "vectordb_compaction_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn vector(x: f32, tag: Option<&str>) -> Vector {
+        let mut v = Vector::new(vec![x, -x]);
+        if let Some(tag) = tag {
+            v.metadata.insert("tag".into(), tag.into());
+        }
+        v
+    }
+
+    #[test]
+    fn test_merge_skips_deleted_and_keeps_order() {
+        let scratch = Scratch::new("merge");
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        let c = scratch.0.join("c.vec");
+        segment::write_segment(&a, &[vector(1.0, Some("a1")), vector(2.0, None)]).unwrap();
+        segment::write_segment(&b, &[]).unwrap();
+        segment::write_segment_int8(&c, &[vector(3.0, None), vector(4.0, Some("c2"))]).unwrap();
+
+        let deleted: HashSet<Position> = [Position { input: 0, index: 1 }].into();
+        let out = scratch.0.join("merged.vec");
+        let report = merge_segments(&[&a, &b, &c], &out, |p, _| deleted.contains(&p)).unwrap();
+        assert_eq!(
+            report,
+            MergeReport {
+                inputs: 3,
+                read: 4,
+                dropped: 1,
+                written: 3,
+                bytes: std::fs::metadata(&out).unwrap().len(),
+            }
+        );
+
+        let merged = segment::read_segment(&out).unwrap();
+        assert_eq!(merged[0].data, vec![1.0, -1.0]);
+        assert_eq!(merged[0].metadata["tag"], "a1");
+        // int8 input, decoded within a quantization step
+        assert!((merged[1].data[0] - 3.0).abs() < 0.01);
+        assert!(merged[1].metadata.is_empty());
+        assert_eq!(merged[2].metadata["tag"], "c2");
+        let ranges = NormBlocks::read_from_segment(&out).unwrap().unwrap();
+        assert_eq!(ranges.count(), 3);
+        assert!(!tmp_path(&out).exists());
+    }
+
+    #[test]
+    fn test_merge_of_binary_segments_stays_packed() {
+        let scratch = Scratch::new("binary");
+        let bits = |i: u32| Vector::new((0..70).map(|d| ((d + i) % 3 == 0) as u8 as f32).collect());
+        let write = |path: &Path, vectors: &[Vector]| {
+            let mut bytes = Vec::new();
+            segment::write_segment_encoded_to(&mut bytes, vectors, VectorEncoding::Binary, &[])
+                .unwrap();
+            std::fs::write(path, bytes).unwrap();
+        };
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        write(&a, &[bits(0), bits(1)]);
+        write(&b, &[bits(2)]);
+
+        let out = scratch.0.join("merged.vec");
+        merge_segments(&[&a, &b], &out, |p, _| p.input == 0 && p.index == 0).unwrap();
+        let header = segment::read_segment_header(&out).unwrap();
+        assert_eq!(header.encoding, VectorEncoding::Binary);
+        assert_eq!(header.vector_size(), 16);
+        let merged: Vec<_> = segment::read_segment(&out).unwrap();
+        assert_eq!(merged[0].data, bits(1).data);
+        assert_eq!(merged[1].data, bits(2).data);
+
+        // Mixed with f32, the output is f32
+        segment::write_segment(&b, &[bits(2)]).unwrap();
+        merge_segments(&[&a, &b], &out, |_, _| false).unwrap();
+        let header = segment::read_segment_header(&out).unwrap();
+        assert_eq!(header.encoding, VectorEncoding::F32);
+        assert_eq!(segment::read_segment(&out).unwrap()[0].data, bits(0).data);
+    }
+
+    #[test]
+    fn test_sparse_embeddings_follow_their_vectors() {
+        let scratch = Scratch::new("sparse");
+        let sparse = |i: u32| SparseVector::new(vec![i, 100 + i], vec![1.0, i as f32]);
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        segment::write_segment(
+            &a,
+            &[vector(1.0, None).with_sparse(sparse(1)), vector(2.0, None)],
+        )
+        .unwrap();
+        segment::write_segment(&b, &[vector(3.0, None).with_sparse(sparse(3))]).unwrap();
+
+        let out = scratch.0.join("merged.vec");
+        merge_segments(&[&a, &b], &out, |p, _| p.input == 0 && p.index == 0).unwrap();
+        let merged = segment::read_segment(&out).unwrap();
+        assert_eq!(merged[0].sparse, None);
+        assert_eq!(merged[1].sparse, Some(sparse(3)));
+        let ranged = segment::read_vectors_range(&out, 1, 1).unwrap();
+        assert_eq!(ranged[0].sparse, Some(sparse(3)));
+
+        // Dropping the only embedding drops the section
+        defragment_segment(&out, |i, _| i == 1).unwrap();
+        assert!(segment::read_section(&out, sparse::SECTION_TAG)
+            .unwrap()
+            .is_none());
+        defragment_segment(&a, |i, _| i == 1).unwrap();
+        assert_eq!(
+            segment::read_segment(&a).unwrap()[0].sparse,
+            Some(sparse(1))
+        );
+    }
+
+    #[test]
+    fn test_merge_in_place_and_without_metadata() {
+        let scratch = Scratch::new("in_place");
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        segment::write_segment(&a, &[vector(1.0, None), vector(2.0, None)]).unwrap();
+        segment::write_segment(&b, &[vector(3.0, Some("gone"))]).unwrap();
+
+        // The only vector with metadata is dropped, so the block is empty
+        merge_segments(&[&a, &b], &a, |p, _| p.input == 1).unwrap();
+        let header = segment::read_segment_header(&a).unwrap();
+        assert_eq!((header.count, header.metadata_size), (2, 0));
+        assert_eq!(segment::read_segment(&a).unwrap()[1].data, vec![2.0, -2.0]);
+    }
+
+    #[test]
+    fn test_merge_rejects_bad_inputs() {
+        let scratch = Scratch::new("reject");
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        let out = scratch.0.join("out.vec");
+        segment::write_segment(&a, &[vector(1.0, None)]).unwrap();
+        segment::write_segment(&b, &[Vector::new(vec![1.0, 2.0, 3.0])]).unwrap();
+        let err = merge_segments(&[&a, &b], &out, |_, _| false).unwrap_err();
+        assert!(err.to_string().contains("dimension 3"), "{}", err);
+
+        // A flipped bit in an input stops the merge before anything is written
+        let mut bytes = std::fs::read(&b).unwrap();
+        bytes[segment::HEADER_SIZE as usize] ^= 0x40;
+        std::fs::write(&b, bytes).unwrap();
+        let err = merge_segments(&[&b], &out, |_, _| false).unwrap_err();
+        assert!(segment::is_corruption(&err));
+        assert!(!out.exists() && !tmp_path(&out).exists());
+    }
+
+    #[test]
+    fn test_merge_carries_ids() {
+        let scratch = Scratch::new("ids");
+        let a = scratch.0.join("a.vec");
+        let b = scratch.0.join("b.vec");
+        let c = scratch.0.join("c.vec");
+        let points = |xs: &[f32]| xs.iter().map(|&x| vector(x, None)).collect::<Vec<_>>();
+        let write = |path: &Path, xs: &[f32], ids: &[&str]| {
+            let sections = [segment::id_table_section(ids)];
+            segment::write_segment_with_sections(path, &points(xs), &sections).unwrap();
+        };
+        write(&a, &[1.0, 2.0], &["a", "b"]);
+        write(&b, &[3.0], &["c"]);
+
+        let out = scratch.0.join("merged.vec");
+        merge_segments(&[&a, &b], &out, |_, id| id == Some("b")).unwrap();
+        let (id, v) = segment::read_vector_at(&out, 1).unwrap();
+        assert_eq!((id.as_deref(), v.data), (Some("c"), vec![3.0, -3.0]));
+
+        // Inputs without IDs can't be merged with inputs that have them
+        segment::write_segment(&c, &points(&[4.0])).unwrap();
+        let err = merge_segments(&[&a, &c], &out, |_, _| false).unwrap_err();
+        assert!(err.to_string().contains("has no ID table"), "{}", err);
+    }
+
+    #[test]
+    fn test_defragment_keeps_encoding_and_reindexes_sections() {
+        use crate::quantization::pq::{PqConfig, ProductQuantizer};
+
+        let scratch = Scratch::new("defrag");
+        let path = scratch.0.join("big.vec");
+        let points: Vec<Vector> = (0..40)
+            .map(|i| vector(i as f32, (i % 3 == 0).then_some("third")))
+            .collect();
+        let data: Vec<&[f32]> = points.iter().map(|v| v.data.as_slice()).collect();
+        let ids: Vec<String> = (0..40).map(|i| format!("p{}", i)).collect();
+        let pq = ProductQuantizer::train(
+            &data,
+            &PqConfig {
+                subspaces: 1,
+                bits: 4,
+                ..PqConfig::default()
+            },
+        )
+        .unwrap();
+        let codes = PqCodes::encode(pq, &data).unwrap();
+        let sections = [
+            segment::id_table_section(&ids),
+            codes.to_section(),
+            Section::new(*b"XTRA", vec![1, 2, 3]),
+        ];
+        let mut w = BufWriter::new(File::create(&path).unwrap());
+        segment::write_segment_encoded_to(
+            &mut w,
+            &points,
+            segment::VectorEncoding::Int8,
+            &sections,
+        )
+        .unwrap();
+        w.flush().unwrap();
+        drop(w);
+        let before = segment::read_segment(&path).unwrap();
+
+        // Drop every odd vector, plus p10 by ID
+        let report = defragment_segment(&path, |i, id| i % 2 == 1 || id == Some("p10")).unwrap();
+        assert_eq!((report.read, report.dropped, report.written), (40, 21, 19));
+        assert_eq!(report.dropped_sections, ["XTRA"]);
+        assert!(report.bytes < report.bytes_before);
+        assert!(!tmp_path(&path).exists());
+
+        let header = segment::verify_segment(&path).unwrap();
+        assert_eq!(header.encoding, segment::VectorEncoding::Int8);
+        let after = segment::read_segment(&path).unwrap();
+        let kept: Vec<usize> = (0..40).filter(|i| i % 2 == 0 && *i != 10).collect();
+        for (v, &i) in after.iter().zip(&kept) {
+            // Same stored bytes, so the same decoded values
+            assert_eq!(v.data, before[i].data);
+            assert_eq!(v.metadata, before[i].metadata);
+        }
+        let (id, _) = segment::read_vector_at(&path, 5).unwrap();
+        assert_eq!(id.as_deref(), Some("p12"));
+        let defragged = PqCodes::read_from_segment(&path).unwrap().unwrap();
+        assert_eq!(defragged.len(), 19);
+        assert_eq!(defragged.code(5), codes.code(12));
+    }
+
+    #[test]
+    fn test_defragment_without_drops_leaves_file_alone() {
+        let scratch = Scratch::new("defrag_noop");
+        let path = scratch.0.join("a.vec");
+        segment::write_segment(&path, &[vector(1.0, None), vector(2.0, None)]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let report = defragment_segment(&path, |_, _| false).unwrap();
+        assert_eq!((report.dropped, report.bytes), (0, report.bytes_before));
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        // Dropping everything leaves a valid empty segment
+        defragment_segment(&path, |_, _| true).unwrap();
+        assert!(segment::read_segment(&path).unwrap().is_empty());
+    }
+}
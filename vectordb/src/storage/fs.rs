@@ -0,0 +1,361 @@
+// src/storage/fs.rs
+//
+// File access behind a trait, so storage logic can be tested in memory.
+//
+// Flush, compaction and recovery code takes a `&dyn Storage` rather than
+// calling std::fs directly:
+//
+//   DiskStorage — the real filesystem (with fault-injection hooks)
+//   MemStorage  — tests only: a HashMap of files that can fail on command
+//                 and "crash", losing everything written since the last sync
+//
+// The crash model is deliberately simple: each file remembers its contents
+// as of the last sync(); crash() rolls every file back to that (and deletes
+// files that were never synced). That's enough to test "did we fsync before
+// acknowledging?" without a real power cut.
+
+use crate::faults::{self, FaultPoint};
+use crate::fds;
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The file operations the storage engine needs
+pub trait Storage: Send + Sync + Debug {
+    /// Read a whole file
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Create or replace a file with `data`
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Append `data` to a file, creating it if needed
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Make everything written to `path` so far durable
+    fn sync(&self, path: &Path) -> io::Result<()>;
+
+    /// Atomically rename `from` to `to`, replacing `to`
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Delete a file
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Does the file exist?
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Files directly inside `dir`, sorted
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DISK STORAGE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The real filesystem. Every open file is counted against the fd budget
+/// (as storage, which is never refused; see fds.rs).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskStorage;
+
+impl Storage for DiskStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let _fd = fds::acquire_storage();
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        if faults::io(FaultPoint::Write)? {
+            let _fd = fds::acquire_storage();
+            fs::write(path, data)?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        if faults::io(FaultPoint::Write)? {
+            let _fd = fds::acquire_storage();
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        if faults::io(FaultPoint::Fsync)? {
+            let _fd = fds::acquire_storage();
+            File::open(path)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let _fd = fds::acquire_storage();
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// IN-MEMORY STORAGE (tests only)
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mem::{MemStorage, StorageOp};
+
+#[cfg(any(test, feature = "test-util"))]
+mod mem {
+    use super::Storage;
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    /// Operations a failure can be injected into
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum StorageOp {
+        Read,
+        Write,
+        Append,
+        Sync,
+        Rename,
+        Remove,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct MemFile {
+        /// What a reader sees now
+        data: Vec<u8>,
+        /// What survives a crash (None = never synced)
+        durable: Option<Vec<u8>>,
+    }
+
+    #[derive(Debug)]
+    struct PendingFailure {
+        op: StorageOp,
+        skip: usize,
+    }
+
+    #[derive(Debug, Default)]
+    struct Inner {
+        files: BTreeMap<PathBuf, MemFile>,
+        failures: Vec<PendingFailure>,
+    }
+
+    /// In-memory filesystem with injectable failures and crash simulation.
+    #[derive(Debug, Default)]
+    pub struct MemStorage {
+        inner: Mutex<Inner>,
+    }
+
+    impl MemStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Make the next `op` fail after letting `skip` of them through.
+        /// Each call arms one failure.
+        pub fn fail_after(&self, op: StorageOp, skip: usize) {
+            self.inner
+                .lock()
+                .unwrap()
+                .failures
+                .push(PendingFailure { op, skip });
+        }
+
+        /// Simulate a power cut: roll every file back to its last sync
+        pub fn crash(&self) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.files.retain(|_, f| f.durable.is_some());
+            for file in inner.files.values_mut() {
+                file.data = file.durable.clone().unwrap_or_default();
+            }
+        }
+
+        /// Consume an armed failure for `op`, if one is due
+        fn check(inner: &mut Inner, op: StorageOp, path: &Path) -> io::Result<()> {
+            let mut fired = None;
+            for (i, failure) in inner.failures.iter_mut().enumerate() {
+                if failure.op != op {
+                    continue;
+                }
+                if failure.skip == 0 {
+                    fired = Some(i);
+                    break;
+                }
+                failure.skip -= 1;
+            }
+            match fired {
+                Some(i) => {
+                    inner.failures.remove(i);
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("injected {:?} failure on {}", op, path.display()),
+                    ))
+                }
+                None => Ok(()),
+            }
+        }
+
+        fn not_found(path: &Path) -> io::Error {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", path.display()),
+            )
+        }
+    }
+
+    impl Storage for MemStorage {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Read, path)?;
+            inner
+                .files
+                .get(path)
+                .map(|f| f.data.clone())
+                .ok_or_else(|| Self::not_found(path))
+        }
+
+        fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Write, path)?;
+            inner.files.entry(path.to_path_buf()).or_default().data = data.to_vec();
+            Ok(())
+        }
+
+        fn append(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Append, path)?;
+            inner
+                .files
+                .entry(path.to_path_buf())
+                .or_default()
+                .data
+                .extend_from_slice(data);
+            Ok(())
+        }
+
+        fn sync(&self, path: &Path) -> io::Result<()> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Sync, path)?;
+            let file = inner
+                .files
+                .get_mut(path)
+                .ok_or_else(|| Self::not_found(path))?;
+            file.durable = Some(file.data.clone());
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Rename, from)?;
+            let file = inner
+                .files
+                .remove(from)
+                .ok_or_else(|| Self::not_found(from))?;
+            inner.files.insert(to.to_path_buf(), file);
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> io::Result<()> {
+            let mut inner = self.inner.lock().unwrap();
+            Self::check(&mut inner, StorageOp::Remove, path)?;
+            inner
+                .files
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| Self::not_found(path))
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.inner.lock().unwrap().files.contains_key(path)
+        }
+
+        fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+            let inner = self.inner.lock().unwrap();
+            Ok(inner
+                .files
+                .keys()
+                .filter(|p| p.parent() == Some(dir))
+                .cloned()
+                .collect())
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_loses_unsynced_writes() {
+        let fs = MemStorage::new();
+        let log = Path::new("/data/wal.log");
+        let tmp = Path::new("/data/scratch.tmp");
+
+        fs.append(log, b"one;").unwrap();
+        fs.sync(log).unwrap();
+        fs.append(log, b"two;").unwrap();
+        fs.write(tmp, b"never synced").unwrap();
+        assert_eq!(fs.read(log).unwrap(), b"one;two;");
+
+        fs.crash();
+        assert_eq!(fs.read(log).unwrap(), b"one;");
+        assert!(!fs.exists(tmp));
+        assert_eq!(
+            fs.list(Path::new("/data")).unwrap(),
+            vec![log.to_path_buf()]
+        );
+    }
+
+    #[test]
+    fn test_injected_failures_fire_once_in_order() {
+        let fs = MemStorage::new();
+        let path = Path::new("/seg.vec");
+        fs.write(path, b"x").unwrap();
+        fs.fail_after(StorageOp::Sync, 1);
+
+        assert!(fs.sync(path).is_ok());
+        assert!(fs.sync(path).is_err());
+        assert!(fs.sync(path).is_ok());
+    }
+
+    #[test]
+    fn test_disk_storage_round_trip() {
+        let dir = std::env::temp_dir().join(format!("vectordb_fs_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.bin");
+        let disk = DiskStorage;
+
+        disk.write(&path, b"ab").unwrap();
+        disk.append(&path, b"cd").unwrap();
+        disk.sync(&path).unwrap();
+        disk.rename(&path, &dir.join("b.bin")).unwrap();
+        assert_eq!(disk.read(&dir.join("b.bin")).unwrap(), b"abcd");
+        assert_eq!(disk.list(&dir).unwrap(), vec![dir.join("b.bin")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
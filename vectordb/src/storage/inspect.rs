@@ -0,0 +1,630 @@
+// src/storage/inspect.rs
+//
+// Streaming inspector for segment files, used by `vectordb-cli inspect`.
+//
+// Two modes:
+//
+//   dump_range     — hex dump of any byte range, read one page at a time,
+//                    so a 100 GB segment costs the same memory as a 1 KB one
+//   decode_segment — walks the file's structure and annotates every field
+//                    with its offset: header, vector data (with a window of
+//                    sample vectors and their IDs), metadata block,
+//                    sections, ID table, footer, and anything past the
+//                    expected end
+//
+// The decoder only reads what it prints: the header, the sampled vectors
+// and IDs, each section's tag and length, the footer, and a short preview
+// of trailing bytes. The exception is the checksum: to say whether the
+// footer's CRC matches, the whole file is streamed through it once.
+
+use crate::quantization::binary;
+use crate::storage::segment::{self, SegmentHeader, VectorEncoding, MAGIC};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Bytes read per page while dumping (a multiple of the 16-byte row)
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Floats printed per sample vector before eliding the rest
+const MAX_SHOWN_FLOATS: usize = 8;
+
+/// Bits shown per binary vector
+const MAX_SHOWN_BITS: usize = 64;
+
+/// Bytes of unexpected trailing data shown in the decode view
+const TRAILING_PREVIEW: usize = 64;
+
+/// What to print
+#[derive(Debug, Clone)]
+pub struct InspectOptions {
+    /// Annotated structure instead of a raw hex dump
+    pub decode: bool,
+
+    /// First byte of the hex dump
+    pub offset: u64,
+
+    /// Bytes to dump (None = to end of file)
+    pub length: Option<u64>,
+
+    /// Index of the first sample vector in decode mode
+    pub first_vector: u64,
+
+    /// Number of sample vectors in decode mode
+    pub samples: u64,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            decode: false,
+            offset: 0,
+            length: None,
+            first_vector: 0,
+            samples: 3,
+        }
+    }
+}
+
+/// Inspect a segment according to `opts`
+pub fn inspect<R: Read + Seek>(
+    r: &mut R,
+    w: &mut impl Write,
+    opts: &InspectOptions,
+) -> io::Result<()> {
+    if opts.decode {
+        decode_segment(r, w, opts.first_vector, opts.samples)
+    } else {
+        dump_range(r, w, opts.offset, opts.length)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// PAGED HEX DUMP
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Hex dump `length` bytes starting at `offset`, one page in memory at a time
+pub fn dump_range<R: Read + Seek>(
+    r: &mut R,
+    w: &mut impl Write,
+    offset: u64,
+    length: Option<u64>,
+) -> io::Result<()> {
+    r.seek(SeekFrom::Start(offset))?;
+    writeln!(w, "{}", segment::HEX_DUMP_HEADER)?;
+
+    let mut remaining = length.unwrap_or(u64::MAX);
+    let mut pos = offset;
+    let mut page = vec![0u8; PAGE_SIZE];
+    while remaining > 0 {
+        let want = (PAGE_SIZE as u64).min(remaining) as usize;
+        let got = read_full(r, &mut page[..want])?;
+        for chunk in page[..got].chunks(16) {
+            writeln!(w, "{}", segment::hex_row(pos, chunk))?;
+            pos += chunk.len() as u64;
+        }
+        remaining -= got as u64;
+        if got < want {
+            break; // end of file
+        }
+    }
+    Ok(())
+}
+
+/// Read until `buf` is full or the reader is exhausted
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// STRUCTURE DECODER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Walk a segment's structure and annotate each part with its offset.
+///
+/// A header that fails to parse is reported along with a hex preview
+/// rather than returned as an error, since broken files are the main
+/// reason to reach for the inspector.
+pub fn decode_segment<R: Read + Seek>(
+    r: &mut R,
+    w: &mut impl Write,
+    first_vector: u64,
+    samples: u64,
+) -> io::Result<()> {
+    let file_len = r.seek(SeekFrom::End(0))?;
+    writeln!(w, "File: {} bytes", file_len)?;
+
+    r.seek(SeekFrom::Start(0))?;
+    let header = match SegmentHeader::read(r) {
+        Ok(header) => header,
+        Err(e) => {
+            writeln!(w, "Header: invalid ({})", e)?;
+            return dump_range(r, w, 0, Some(TRAILING_PREVIEW as u64));
+        }
+    };
+
+    // Header fields, in file order
+    writeln!(
+        w,
+        "Header (format v{}, {} bytes)",
+        header.version,
+        header.data_offset() - header.params_size()
+    )?;
+    writeln!(
+        w,
+        "  {:#010X}  magic      {:?}",
+        0,
+        String::from_utf8_lossy(MAGIC)
+    )?;
+    writeln!(w, "  {:#010X}  version    {}", 4, header.version)?;
+    writeln!(w, "  {:#010X}  count      {}", 8, header.count)?;
+    let dimension_at = if header.version == 1 { 12 } else { 16 };
+    writeln!(
+        w,
+        "  {:#010X}  dimension  {}",
+        dimension_at, header.dimension
+    )?;
+    if header.version >= 4 {
+        writeln!(w, "  {:#010X}  encoding   {:?}", 20, header.encoding)?;
+    } else if header.version >= 2 {
+        writeln!(w, "  {:#010X}  reserved", 20)?;
+    }
+    if header.version >= 3 {
+        writeln!(
+            w,
+            "  {:#010X}  metadata   {} bytes",
+            24, header.metadata_size
+        )?;
+    }
+    if header.version >= 4 {
+        writeln!(
+            w,
+            "  {:#010X}  sections   {} bytes",
+            32, header.sections_size
+        )?;
+    }
+
+    if header.params_size() > 0 {
+        writeln!(
+            w,
+            "Encoding parameters @ {:#010X}: {} bytes ({} mins, {} scales)",
+            segment::HEADER_SIZE,
+            header.params_size(),
+            header.dimension,
+            header.dimension
+        )?;
+    }
+
+    // Vector data region
+    let data_len = header.metadata_offset() - header.data_offset();
+    writeln!(
+        w,
+        "Vectors @ {:#010X}: {} × {} dims × {} = {} bytes",
+        header.data_offset(),
+        header.count,
+        header.dimension,
+        match header.encoding {
+            VectorEncoding::Binary => format!("1 bit ({} bytes each)", header.vector_size()),
+            _ => format!(
+                "{} bytes",
+                header.vector_size() / header.dimension.max(1) as u64
+            ),
+        },
+        data_len
+    )?;
+
+    // Sample lines show IDs too, if the table can be found
+    let id_table = segment::find_section(r, &header, segment::ID_TABLE_TAG);
+    let has_ids = matches!(id_table, Ok(Some(_)));
+
+    let end = first_vector.saturating_add(samples).min(header.count);
+    let mut shown = 0;
+    for index in first_vector..end {
+        let at = header.vector_offset(index);
+        if at + header.vector_size() > file_len {
+            writeln!(w, "  #{} @ {:#010X}  <past end of file>", index, at)?;
+            break;
+        }
+        r.seek(SeekFrom::Start(at))?;
+        let mut bytes = vec![0u8; header.vector_size() as usize];
+        r.read_exact(&mut bytes)?;
+        let shown_values = match header.encoding {
+            VectorEncoding::F32 => format_floats(&bytes),
+            VectorEncoding::Int8 => format_codes(&bytes),
+            VectorEncoding::Binary => format_bits(&bytes, header.dimension as usize),
+        };
+        let id = match has_ids {
+            true => match segment::read_id_at(r, &header, index) {
+                Ok(Some(id)) => format!("  id {:?}", id),
+                Ok(None) => String::new(),
+                Err(e) => format!("  id <{}>", e),
+            },
+            false => String::new(),
+        };
+        writeln!(w, "  #{} @ {:#010X}  {}{}", index, at, shown_values, id)?;
+        shown += 1;
+    }
+    writeln!(
+        w,
+        "  (showing {} of {} from #{})",
+        shown, header.count, first_vector
+    )?;
+
+    if header.metadata_size > 0 {
+        writeln!(
+            w,
+            "Metadata @ {:#010X}: {} bytes",
+            header.metadata_offset(),
+            header.metadata_size
+        )?;
+    } else {
+        writeln!(w, "Metadata: none")?;
+    }
+    if header.sections_size > 0 {
+        writeln!(
+            w,
+            "Sections @ {:#010X}: {} bytes",
+            header.sections_offset(),
+            header.sections_size
+        )?;
+        decode_sections(r, w, &header, file_len)?;
+    } else {
+        writeln!(w, "Sections: none")?;
+    }
+    match id_table {
+        Ok(Some((at, len))) => writeln!(
+            w,
+            "ID table @ {:#010X}: {} IDs, {} bytes",
+            at, header.count, len
+        )?,
+        Ok(None) if header.version < 4 => writeln!(
+            w,
+            "ID table: none (format v{} doesn't store IDs)",
+            header.version
+        )?,
+        Ok(None) => writeln!(w, "ID table: none")?,
+        Err(e) => writeln!(w, "ID table: unreadable ({})", e)?,
+    }
+    if header.footer_size() > 0 {
+        decode_footer(r, w, &header, file_len)?;
+    } else {
+        writeln!(
+            w,
+            "Footer: none (format v{} has no checksum)",
+            header.version
+        )?;
+    }
+
+    // Anything that doesn't match the header's idea of the file size
+    let expected = header.file_size();
+    if file_len < expected {
+        writeln!(
+            w,
+            "Truncated: header describes {} bytes, file has {} ({} missing)",
+            expected,
+            file_len,
+            expected - file_len
+        )?;
+    } else if file_len > expected {
+        writeln!(
+            w,
+            "Trailing: {} unexpected bytes @ {:#010X}",
+            file_len - expected,
+            expected
+        )?;
+        dump_range(r, w, expected, Some(TRAILING_PREVIEW as u64))?;
+    }
+    Ok(())
+}
+
+/// Show the stored checksum and whether the contents still match it
+fn decode_footer<R: Read + Seek>(
+    r: &mut R,
+    w: &mut impl Write,
+    header: &SegmentHeader,
+    file_len: u64,
+) -> io::Result<()> {
+    let at = header.file_size() - segment::FOOTER_SIZE;
+    if at + segment::FOOTER_SIZE > file_len {
+        return writeln!(w, "Footer @ {:#010X}: <past end of file>", at);
+    }
+    r.seek(SeekFrom::Start(at))?;
+    let mut footer = [0u8; segment::FOOTER_SIZE as usize];
+    r.read_exact(&mut footer)?;
+    let stored = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+    writeln!(
+        w,
+        "Footer @ {:#010X}: crc32 {:#010x}, magic {:?}",
+        at,
+        stored,
+        String::from_utf8_lossy(&footer[4..])
+    )?;
+
+    r.seek(SeekFrom::Start(0))?;
+    match segment::verify_checksum(&mut io::BufReader::new(&mut *r), header) {
+        Ok(()) => writeln!(w, "  checksum ok"),
+        Err(e) if segment::is_corruption(&e) => writeln!(w, "  CORRUPT: {}", e),
+        Err(e) => Err(e),
+    }
+}
+
+/// List each section's tag and payload length, stopping at the first frame
+/// that runs past the sections block or the file
+fn decode_sections<R: Read + Seek>(
+    r: &mut R,
+    w: &mut impl Write,
+    header: &SegmentHeader,
+    file_len: u64,
+) -> io::Result<()> {
+    let end = header.sections_offset() + header.sections_size;
+    let mut at = header.sections_offset();
+    while at < end {
+        if at + 12 > end.min(file_len) {
+            writeln!(w, "  @ {:#010X}  <section frame cut short>", at)?;
+            break;
+        }
+        r.seek(SeekFrom::Start(at))?;
+        let mut frame = [0u8; 12];
+        r.read_exact(&mut frame)?;
+        let tag = String::from_utf8_lossy(&frame[..4]).into_owned();
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&frame[4..]);
+        let len = u64::from_le_bytes(len);
+        writeln!(w, "  {:?} @ {:#010X}  {} bytes", tag, at, len)?;
+        at = match (at + 12).checked_add(len) {
+            Some(next) if next <= end => next,
+            _ => {
+                writeln!(w, "  (section {:?} runs past the sections block)", tag)?;
+                break;
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Render little-endian f32s, eliding all but the first few
+fn format_floats(bytes: &[u8]) -> String {
+    let floats: Vec<String> = bytes
+        .chunks_exact(4)
+        .take(MAX_SHOWN_FLOATS)
+        .map(|b| format!("{:?}", f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+        .collect();
+    format_elided(floats, bytes.len() / 4)
+}
+
+/// Render int8 codes as stored (before dequantizing), eliding the same way
+fn format_codes(bytes: &[u8]) -> String {
+    let codes = bytes
+        .iter()
+        .take(MAX_SHOWN_FLOATS)
+        .map(|c| c.to_string())
+        .collect();
+    format!("codes {}", format_elided(codes, bytes.len()))
+}
+
+fn format_bits(bytes: &[u8], dimension: usize) -> String {
+    let words: Vec<u64> = bytes
+        .chunks_exact(8)
+        .map(|b| u64::from_le_bytes(b.try_into().expect("8-byte chunk")))
+        .collect();
+    let bits: String = binary::unpack(&words, dimension.min(MAX_SHOWN_BITS))
+        .iter()
+        .map(|&bit| if bit > 0.0 { '1' } else { '0' })
+        .collect();
+    if dimension > MAX_SHOWN_BITS {
+        format!("bits {}… (+{} more)", bits, dimension - MAX_SHOWN_BITS)
+    } else {
+        format!("bits {}", bits)
+    }
+}
+
+fn format_elided(shown: Vec<String>, total: usize) -> String {
+    if total > MAX_SHOWN_FLOATS {
+        format!(
+            "[{}, … (+{} more)]",
+            shown.join(", "),
+            total - MAX_SHOWN_FLOATS
+        )
+    } else {
+        format!("[{}]", shown.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Vector;
+    use std::io::Cursor;
+
+    fn sample_segment(count: usize, dimension: usize) -> Vec<u8> {
+        let vectors: Vec<Vector> = (0..count)
+            .map(|i| Vector::new(vec![i as f32; dimension]))
+            .collect();
+        let mut bytes = Vec::new();
+        segment::write_segment_to(&mut bytes, &vectors).unwrap();
+        bytes
+    }
+
+    fn run(bytes: Vec<u8>, opts: &InspectOptions) -> String {
+        let mut out = Vec::new();
+        inspect(&mut Cursor::new(bytes), &mut out, opts).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_decode_annotates_offsets() {
+        let mut bytes = sample_segment(5, 2);
+        bytes.extend_from_slice(b"junk");
+        let out = run(
+            bytes,
+            &InspectOptions {
+                decode: true,
+                first_vector: 3,
+                samples: 10,
+                ..Default::default()
+            },
+        );
+
+        assert!(out.contains("count      5"), "{}", out);
+        assert!(out.contains("Vectors @ 0x00000028: 5 × 2 dims"), "{}", out);
+        // Vector 3 starts at 40 + 3 × 8 = 64
+        assert!(out.contains("#3 @ 0x00000040  [3.0, 3.0]"), "{}", out);
+        assert!(out.contains("(showing 2 of 5 from #3)"), "{}", out);
+        assert!(out.contains("Metadata: none"), "{}", out);
+        assert!(out.contains("Sections: none"), "{}", out);
+        assert!(out.contains("Footer @ 0x00000050"), "{}", out);
+        assert!(out.contains("checksum ok"), "{}", out);
+        assert!(
+            out.contains("Trailing: 4 unexpected bytes @ 0x00000058"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_decode_reports_broken_files() {
+        let mut bytes = sample_segment(4, 2);
+        bytes.truncate(bytes.len() - 16);
+        let out = run(
+            bytes,
+            &InspectOptions {
+                decode: true,
+                samples: 4,
+                ..Default::default()
+            },
+        );
+        assert!(
+            out.contains("#3 @ 0x00000040  <past end of file>"),
+            "{}",
+            out
+        );
+        assert!(out.contains("Truncated: header describes 80 bytes, file has 64"));
+        assert!(
+            out.contains("Footer @ 0x00000048: <past end of file>"),
+            "{}",
+            out
+        );
+
+        let out = run(
+            b"NOPE".to_vec(),
+            &InspectOptions {
+                decode: true,
+                ..Default::default()
+            },
+        );
+        assert!(out.contains("Header: invalid"), "{}", out);
+    }
+
+    #[test]
+    fn test_decode_lists_sections() {
+        let vectors = vec![Vector::new(vec![1.0, 2.0])];
+        let sections = [
+            segment::Section::new(*b"PQ01", vec![0; 5]),
+            segment::Section::new(*b"XTRA", vec![]),
+        ];
+        let mut bytes = Vec::new();
+        segment::write_segment_with_sections_to(&mut bytes, &vectors, &sections).unwrap();
+        let out = run(
+            bytes,
+            &InspectOptions {
+                decode: true,
+                ..Default::default()
+            },
+        );
+        assert!(out.contains("sections   29 bytes"), "{}", out);
+        // 40-byte header + one 8-byte vector
+        assert!(out.contains("Sections @ 0x00000030: 29 bytes"), "{}", out);
+        assert!(out.contains("\"PQ01\" @ 0x00000030  5 bytes"), "{}", out);
+        assert!(out.contains("\"XTRA\" @ 0x00000041  0 bytes"), "{}", out);
+        assert!(out.contains("ID table: none"), "{}", out);
+        assert!(!out.contains("Trailing"), "{}", out);
+    }
+
+    #[test]
+    fn test_decode_shows_ids() {
+        let vectors = vec![Vector::new(vec![1.0]), Vector::new(vec![2.0])];
+        let sections = [segment::id_table_section(&["doc_1", "doc_22"])];
+        let mut bytes = Vec::new();
+        segment::write_segment_with_sections_to(&mut bytes, &vectors, &sections).unwrap();
+        let out = run(
+            bytes,
+            &InspectOptions {
+                decode: true,
+                ..Default::default()
+            },
+        );
+        // 40-byte header + two 4-byte vectors + 12-byte frame
+        assert!(
+            out.contains("ID table @ 0x0000003C: 2 IDs, 43 bytes"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("#1 @ 0x0000002C  [2.0]  id \"doc_22\""),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_decode_shows_int8_codes() {
+        let vectors = vec![Vector::new(vec![0.0, 1.0]), Vector::new(vec![1.0, 0.5])];
+        let mut bytes = Vec::new();
+        segment::write_segment_encoded_to(&mut bytes, &vectors, VectorEncoding::Int8, &[]).unwrap();
+        let out = run(
+            bytes,
+            &InspectOptions {
+                decode: true,
+                ..Default::default()
+            },
+        );
+        assert!(out.contains("encoding   Int8"), "{}", out);
+        assert!(
+            out.contains("Encoding parameters @ 0x00000028: 16 bytes"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("Vectors @ 0x00000038: 2 × 2 dims × 1 bytes = 4 bytes"),
+            "{}",
+            out
+        );
+        assert!(out.contains("Header (format v5, 40 bytes)"), "{}", out);
+        assert!(out.contains("#1 @ 0x0000003A  codes [255, 0]"), "{}", out);
+        assert!(!out.contains("Trailing"), "{}", out);
+    }
+
+    #[test]
+    fn test_dump_range_pages_with_absolute_offsets() {
+        // Larger than one page, so rows straddle the page boundary
+        let bytes: Vec<u8> = (0..PAGE_SIZE + 40).map(|i| i as u8).collect();
+        let out = run(
+            bytes,
+            &InspectOptions {
+                offset: PAGE_SIZE as u64 - 16,
+                ..Default::default()
+            },
+        );
+
+        let rows: Vec<&str> = out.lines().skip(1).collect();
+        assert_eq!(rows.len(), 4); // 56 bytes from the offset → 3 full rows + 8
+        assert!(rows[0].starts_with("0000FFF0  F0 F1"), "{}", rows[0]);
+        assert!(rows[1].starts_with("00010000  00 01"), "{}", rows[1]);
+
+        let out = run(
+            vec![0u8; 100],
+            &InspectOptions {
+                length: Some(20),
+                ..Default::default()
+            },
+        );
+        assert_eq!(out.lines().count(), 3);
+    }
+}
@@ -0,0 +1,280 @@
+// src/storage/manifest.rs
+//
+// The manifest: which segment files in a directory are live.
+//
+// Flushes add segments and compaction replaces them, and a crash can land
+// between writing a segment and anything that refers to it. Listing the
+// directory can't tell a finished flush from one whose caller never got
+// to record it, or a compaction output from the inputs it replaced. So,
+// as in RocksDB's MANIFEST, one small file records the live set:
+//
+//   <dir>/MANIFEST   JSON: { next_generation, segments: [ {file,
+//                    generation, collection, points, deleted,
+//                    min_id, max_id}, ... ] }
+//
+// Every change writes a whole new manifest to MANIFEST.tmp, syncs it and
+// renames it over the old one, so a reader sees the live set before or
+// after a change, never half of one. A segment file is written (and
+// synced) before the manifest that lists it, and dropped from the
+// manifest before it's deleted, so a live entry always has its file.
+//
+// `recover` loads the manifest and deletes everything else in the
+// directory that looks like ours: `.vec` files it doesn't list (a flush
+// cut short before it was recorded, the inputs of a compaction after it
+// was) and `.tmp` leftovers. A listed file that's missing is an error:
+// that segment's writes are gone. With no manifest at all nothing is live.
+//
+// Each entry keeps the range of IDs its segment touches (points and
+// tombstones), so a lookup can skip segments that can't hold an ID
+// without opening them (see segments.rs).
+
+use crate::storage::fs::Storage;
+use crate::storage::snapshot;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest inside the directory it describes
+pub const MANIFEST_FILE: &str = "MANIFEST";
+
+/// One live segment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentEntry {
+    /// File name, relative to the manifest's directory
+    pub file: String,
+    /// Order the segment applies in; never reused
+    pub generation: u64,
+    /// Collection whose writes it holds
+    pub collection: String,
+    /// Vectors stored
+    pub points: u64,
+    /// IDs it deletes (tombstones)
+    pub deleted: u64,
+    /// Smallest and largest ID it touches; None for an empty segment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_id: Option<String>,
+}
+
+impl SegmentEntry {
+    /// Set `min_id`/`max_id` to span `ids`
+    pub fn with_ids<'a>(mut self, ids: impl IntoIterator<Item = &'a str>) -> Self {
+        for id in ids {
+            if self.min_id.as_deref().map_or(true, |min| id < min) {
+                self.min_id = Some(id.to_string());
+            }
+            if self.max_id.as_deref().map_or(true, |max| id > max) {
+                self.max_id = Some(id.to_string());
+            }
+        }
+        self
+    }
+
+    /// Could this segment hold `id`?
+    pub fn may_contain(&self, id: &str) -> bool {
+        match (&self.min_id, &self.max_id) {
+            (Some(min), Some(max)) => min.as_str() <= id && id <= max.as_str(),
+            _ => false,
+        }
+    }
+}
+
+/// The live segments of one directory, oldest generation first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Generation the next segment gets; only grows, so generations stay
+    /// unique after their segments are gone
+    pub next_generation: u64,
+    pub segments: Vec<SegmentEntry>,
+}
+
+impl Manifest {
+    /// Record a new segment
+    pub fn add(&mut self, entry: SegmentEntry) {
+        self.next_generation = self.next_generation.max(entry.generation + 1);
+        self.segments.push(entry);
+        self.segments.sort_by_key(|s| s.generation);
+    }
+
+    /// Swap the segments with generations in `old` for `new` (the output
+    /// of compacting them), as one change. Returns how many were removed.
+    pub fn replace(&mut self, old: &[u64], new: SegmentEntry) -> usize {
+        let before = self.segments.len();
+        self.segments.retain(|s| !old.contains(&s.generation));
+        let removed = before - self.segments.len();
+        self.add(new);
+        removed
+    }
+
+    /// Drop every segment (once something else holds their writes)
+    pub fn clear(&mut self) -> Vec<SegmentEntry> {
+        std::mem::take(&mut self.segments)
+    }
+
+    /// Live segments that could hold `id`, newest first
+    pub fn candidates<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a SegmentEntry> {
+        self.segments
+            .iter()
+            .rev()
+            .filter(move |s| s.may_contain(id))
+    }
+
+    /// Read the manifest in `dir`; None if there isn't one
+    pub fn load(storage: &dyn Storage, dir: &Path) -> io::Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        let bytes = match storage.read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Replace the manifest in `dir` with this one (tmp, sync, rename)
+    pub fn save(&self, storage: &dyn Storage, dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        snapshot::write_atomic(storage, &dir.join(MANIFEST_FILE), &json)
+    }
+}
+
+/// What `recover` found
+#[derive(Debug, Clone, Default)]
+pub struct Recovery {
+    pub manifest: Manifest,
+    /// Files removed because the manifest doesn't list them
+    pub orphans: Vec<PathBuf>,
+}
+
+/// Load the manifest in `dir` and delete the segment files and leftovers
+/// it doesn't list. Fails if a listed segment is missing.
+pub fn recover(storage: &dyn Storage, dir: &Path) -> io::Result<Recovery> {
+    let manifest = Manifest::load(storage, dir)?.unwrap_or_default();
+    for entry in &manifest.segments {
+        if !storage.exists(&dir.join(&entry.file)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} lists segment {} (generation {}), which is missing",
+                    dir.join(MANIFEST_FILE).display(),
+                    entry.file,
+                    entry.generation
+                ),
+            ));
+        }
+    }
+
+    let paths = match storage.list(dir) {
+        Ok(paths) => paths,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let mut orphans = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let live = manifest.segments.iter().any(|s| s.file == name);
+        let ours = name.ends_with(".vec") || name.ends_with(".tmp");
+        if ours && !live {
+            storage.remove(&path)?;
+            orphans.push(path);
+        }
+    }
+    Ok(Recovery { manifest, orphans })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fs::MemStorage;
+
+    fn entry(generation: u64, ids: &[&str]) -> SegmentEntry {
+        SegmentEntry {
+            file: format!("{:020}.vec", generation),
+            generation,
+            collection: "docs".into(),
+            points: ids.len() as u64,
+            deleted: 0,
+            min_id: None,
+            max_id: None,
+        }
+        .with_ids(ids.iter().copied())
+    }
+
+    #[test]
+    fn test_id_ranges_pick_candidate_segments() {
+        let mut manifest = Manifest::default();
+        manifest.add(entry(2, &["m", "q"]));
+        manifest.add(entry(1, &["c", "a", "k"]));
+        manifest.add(entry(3, &[]));
+        assert_eq!(manifest.next_generation, 4);
+        assert_eq!(manifest.segments[0].min_id.as_deref(), Some("a"));
+        assert_eq!(manifest.segments[0].max_id.as_deref(), Some("k"));
+
+        let generations = |id| {
+            manifest
+                .candidates(id)
+                .map(|s| s.generation)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(generations("b"), [1]);
+        assert_eq!(generations("m"), [2]);
+        assert!(generations("z").is_empty());
+
+        let mut compacted = manifest.clone();
+        assert_eq!(compacted.replace(&[1, 2], entry(4, &["a", "q"])), 2);
+        assert_eq!(generations("b"), [1], "the original is unchanged");
+        let live: Vec<_> = compacted.segments.iter().map(|s| s.generation).collect();
+        assert_eq!(live, [3, 4]);
+        assert_eq!(compacted.candidates("m").count(), 1);
+    }
+
+    #[test]
+    fn test_recovery_ignores_unrecorded_segments() {
+        let storage = MemStorage::new();
+        let dir = Path::new("/data/flushed");
+        let write_synced = |name: &str| {
+            let path = dir.join(name);
+            storage.write(&path, b"segment").unwrap();
+            storage.sync(&path).unwrap();
+        };
+        assert!(Manifest::load(&storage, dir).unwrap().is_none());
+
+        let mut manifest = Manifest::default();
+        write_synced(&entry(1, &["a"]).file);
+        manifest.add(entry(1, &["a"]));
+        manifest.save(&storage, dir).unwrap();
+        // A flush that crashed after its segment but before the manifest,
+        // and one cut short mid-write
+        write_synced(&entry(2, &["b"]).file);
+        storage.write(&dir.join("x.vec.tmp"), b"partial").unwrap();
+        storage.crash();
+
+        write_synced(&entry(3, &["c"]).file);
+        let recovery = recover(&storage, dir).unwrap();
+        assert_eq!(recovery.manifest, manifest);
+        let orphans: Vec<_> = recovery
+            .orphans
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(orphans, [entry(2, &[]).file, entry(3, &[]).file]);
+        assert!(storage.exists(&dir.join(&entry(1, &[]).file)));
+        assert!(storage.exists(&dir.join(MANIFEST_FILE)));
+
+        // A live segment that disappeared is lost data, not an orphan
+        storage.remove(&dir.join(&entry(1, &[]).file)).unwrap();
+        let err = recover(&storage, dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,528 @@
+// src/storage/memtable.rs
+//
+// Write buffer (memtable) and the immutable segments it flushes to.
+//
+// Every write to a collection is applied in memory (and logged to the WAL)
+// and noted in the collection's memtable: which IDs were upserted or
+// deleted since the last flush, and roughly how many bytes those writes
+// carried. Once a memtable reaches the flush policy's size or age, the
+// background flusher seals it, copies the current value of every noted
+// point, and writes them as a new immutable segment:
+//
+//   <data_dir>/flushed/<generation>.vec
+//
+// This is a regular segment file (segment.rs). It holds the upserted
+// points with their IDs, a TOMB section listing deleted IDs (in ID table
+// layout), a COLL section naming the collection and a WSEQ section with
+// the WAL sequence the memtable was sealed at. Points of binary
+// collections are stored bit-packed, everything else as f32 with a NRM1
+// section of norm ranges (norms.rs).
+//
+// A flush counts once the directory's manifest (manifest.rs) lists it; the
+// segment is written first, so a crash in between leaves an orphan that
+// recovery deletes. Once it counts, the WAL drops the records it holds
+// (Wal::drop_flushed): the collection's point writes up to its sequence,
+// leaving skip frames so the rest keep their numbers. So the log shrinks
+// with every flush instead of growing until the next snapshot.
+//
+// On startup each segment is applied at its place in the WAL: after the
+// records up to its sequence have replayed, to the collection that had
+// its name then. A name reused after a rename or a trip to the trash
+// can't pick up writes meant for the collection that had it before.
+// Flushes at or before the snapshot's sequence (a crash between writing
+// a snapshot and removing them) are already in it and are skipped. The
+// next snapshot folds them in, empties the manifest and removes them.
+//
+// Flushing also leaves a synced, deduplicated copy of recent writes:
+// under wal_sync = never or every:N a power cut can lose the log's
+// unsynced tail but not what was flushed. And it's the memtable →
+// immutable segment lifecycle that compaction (compaction.rs) and the
+// segment catalog (segments.rs) work on.
+//
+// Policy (settings in config.rs):
+//   memtable_max_bytes      flush once writes reach this (default 64 MiB)
+//   memtable_max_age_secs   or once the oldest is this old (default 300)
+//
+// Bytes count every write, so rewriting one point repeatedly fills the
+// memtable as new points would: each rewrite is work a flush saves the WAL.
+//
+// Writers note into the memtable and the flusher swaps it for an empty one
+// under the same lock (from crate::sync), so every write lands in exactly
+// one sealed batch or the live memtable; tests/loom.rs checks that under
+// every interleaving.
+//
+// In a collection with an HNSW graph, sealing the memtable is also when
+// its upserts go into the graph. Until then searches score them exactly
+// and merge them with the graph's results (Collection::graph_search), so
+// writes don't pay for graph insertion and are searchable immediately.
+
+use crate::models::Vector;
+use crate::storage::fs::Storage;
+use crate::storage::manifest::{self, Manifest, SegmentEntry};
+use crate::storage::norms;
+use crate::storage::segment::{self, Section, SegmentHeader, VectorEncoding};
+use crate::storage::snapshot;
+use crate::sync::Mutex;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Subdirectory of the data dir holding flushed segments
+pub const FLUSH_DIR: &str = "flushed";
+
+/// Section listing the IDs a flush deletes (ID table layout)
+pub const TOMBSTONE_TAG: [u8; 4] = *b"TOMB";
+
+/// Section naming the collection a flush belongs to (UTF-8)
+pub const COLLECTION_TAG: [u8; 4] = *b"COLL";
+
+/// Section holding the WAL sequence a flush was sealed at (u64)
+pub const SEQUENCE_TAG: [u8; 4] = *b"WSEQ";
+
+/// Flush once a memtable's writes reach this many bytes
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Flush once a memtable's oldest write is this old
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// How often the server checks memtables against the policy
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// POLICY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// When a memtable is flushed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushPolicy {
+    pub max_bytes: usize,
+    pub max_age: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// MEMTABLE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A collection's writes since its last flush. The points themselves stay
+/// in the collection; this records which ones to flush.
+#[derive(Debug, Default)]
+pub struct Memtable {
+    writes: Mutex<Writes>,
+}
+
+/// The writes a memtable holds, or held when it was sealed
+#[derive(Debug, Clone, Default)]
+pub struct Writes {
+    /// Each written ID: true if it was last upserted, false if deleted
+    entries: HashMap<String, bool>,
+    bytes: usize,
+    /// When the oldest unflushed write arrived
+    since: Option<Instant>,
+}
+
+impl Clone for Memtable {
+    fn clone(&self) -> Self {
+        Self {
+            writes: Mutex::new(self.with(|writes| writes.clone())),
+        }
+    }
+}
+
+impl Memtable {
+    fn with<R>(&self, f: impl FnOnce(&mut Writes) -> R) -> R {
+        f(&mut self.writes.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Note an upsert of `id` carrying `bytes` of vector and metadata
+    pub fn put(&self, id: &str, bytes: usize) {
+        self.with(|writes| writes.note(id, true, bytes + id.len()));
+    }
+
+    /// Note a delete of `id`
+    pub fn delete(&self, id: &str) {
+        self.with(|writes| writes.note(id, false, id.len()));
+    }
+
+    /// IDs written since the last flush
+    pub fn len(&self) -> usize {
+        self.with(|writes| writes.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.with(|writes| writes.is_empty())
+    }
+
+    /// Bytes written since the last flush
+    pub fn bytes(&self) -> usize {
+        self.with(|writes| writes.bytes())
+    }
+
+    /// How long the oldest unflushed write has waited
+    pub fn age(&self, now: Instant) -> Duration {
+        self.with(|writes| writes.age(now))
+    }
+
+    /// Has it reached the policy's size or age?
+    pub fn is_due(&self, policy: &FlushPolicy, now: Instant) -> bool {
+        self.with(|writes| {
+            !writes.is_empty()
+                && (writes.bytes >= policy.max_bytes || writes.age(now) >= policy.max_age)
+        })
+    }
+
+    /// Swap in an empty memtable, returning the writes this one held
+    pub fn take(&self) -> Writes {
+        self.with(std::mem::take)
+    }
+}
+
+impl Writes {
+    fn note(&mut self, id: &str, upsert: bool, bytes: usize) {
+        match self.entries.get_mut(id) {
+            Some(entry) => *entry = upsert,
+            None => {
+                self.entries.insert(id.to_string(), upsert);
+            }
+        }
+        self.bytes += bytes;
+        self.since.get_or_insert_with(Instant::now);
+    }
+
+    /// IDs written
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Bytes written
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// How long the oldest write has waited
+    pub fn age(&self, now: Instant) -> Duration {
+        self.since
+            .map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    /// IDs last upserted
+    pub fn upserts(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|(_, &upsert)| upsert)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// IDs last deleted
+    pub fn deletes(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|(_, &upsert)| !upsert)
+            .map(|(id, _)| id.as_str())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FLUSHED SEGMENTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One sealed memtable: the points it upserted (at their values when it
+/// was sealed) and the IDs it deleted
+#[derive(Debug, Clone)]
+pub struct Flush {
+    pub generation: u64,
+    /// WAL sequence when it was sealed: it holds the collection's writes up
+    /// to here (0 if written without a WAL, or before flushes were tagged)
+    pub seq: u64,
+    /// The collection's name at `seq`
+    pub collection: String,
+    pub points: Vec<(String, Vector)>,
+    pub deleted: Vec<String>,
+    /// How the points' components are stored
+    pub encoding: VectorEncoding,
+    /// Every upsert made it in. Points of another dimension stay out of a
+    /// segment, so the WAL has to keep the collection's records for them.
+    pub complete: bool,
+}
+
+/// Next flush generation; moved past every file found on disk by `load`.
+/// Process-wide and outside any loom model, so a plain std atomic.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Draw the generation for a new flush
+pub fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Where the flush with `generation` lives under `data_dir`. Zero-padded,
+/// so file name order is generation order.
+pub fn flush_path(data_dir: &Path, generation: u64) -> PathBuf {
+    data_dir.join(FLUSH_DIR).join(file_name(generation))
+}
+
+fn file_name(generation: u64) -> String {
+    format!("{:020}.vec", generation)
+}
+
+/// Write `flush` as an immutable segment under `data_dir` (synced, then
+/// renamed into place) and return its path
+pub fn write(storage: &dyn Storage, data_dir: &Path, flush: &Flush) -> io::Result<PathBuf> {
+    let _span = tracing::info_span!(
+        "memtable.flush",
+        collection = %flush.collection,
+        generation = flush.generation,
+        points = flush.points.len(),
+        deleted = flush.deleted.len()
+    )
+    .entered();
+    std::fs::create_dir_all(data_dir.join(FLUSH_DIR))?;
+
+    let vectors: Vec<Vector> = flush.points.iter().map(|(_, v)| v.clone()).collect();
+    let ids: Vec<&str> = flush.points.iter().map(|(id, _)| id.as_str()).collect();
+    let tombstones = segment::id_table_section(&flush.deleted);
+    let mut sections = vec![
+        segment::id_table_section(&ids),
+        Section::new(TOMBSTONE_TAG, tombstones.data),
+        Section::new(COLLECTION_TAG, flush.collection.as_bytes().to_vec()),
+        Section::new(SEQUENCE_TAG, flush.seq.to_le_bytes().to_vec()),
+    ];
+    sections.extend(norms::section_for(&vectors, flush.encoding));
+    let mut bytes = Vec::new();
+    segment::write_segment_encoded_to(&mut bytes, &vectors, flush.encoding, &sections)?;
+
+    let path = flush_path(data_dir, flush.generation);
+    snapshot::write_atomic(storage, &path, &bytes)?;
+
+    let dir = data_dir.join(FLUSH_DIR);
+    let mut manifest = Manifest::load(storage, &dir)?.unwrap_or_default();
+    let ids = flush.points.iter().map(|(id, _)| id.as_str());
+    manifest.add(
+        SegmentEntry {
+            file: file_name(flush.generation),
+            generation: flush.generation,
+            collection: flush.collection.clone(),
+            points: flush.points.len() as u64,
+            deleted: flush.deleted.len() as u64,
+            min_id: None,
+            max_id: None,
+        }
+        .with_ids(ids.chain(flush.deleted.iter().map(String::as_str))),
+    );
+    manifest.save(storage, &dir)?;
+    Ok(path)
+}
+
+/// Read the live flushed segments under `data_dir`, oldest first, and move
+/// the generation counter past them. Segments the manifest doesn't list
+/// (flushes cut short) are removed, as are `.tmp` leftovers.
+pub fn load(storage: &dyn Storage, data_dir: &Path) -> io::Result<Vec<Flush>> {
+    let dir = data_dir.join(FLUSH_DIR);
+    let recovery = manifest::recover(storage, &dir)?;
+    for orphan in &recovery.orphans {
+        tracing::warn!("Removed unrecorded flush {}", orphan.display());
+    }
+    let mut flushes = Vec::new();
+    for entry in &recovery.manifest.segments {
+        let path = dir.join(&entry.file);
+        let bytes = storage.read(&path)?;
+        flushes.push(decode(entry.generation, &bytes).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("flushed segment {}: {}", path.display(), e),
+            )
+        })?);
+    }
+    NEXT_GENERATION.fetch_max(recovery.manifest.next_generation, Ordering::Relaxed);
+    Ok(flushes)
+}
+
+/// Delete every flushed segment (once a snapshot holds their writes): the
+/// manifest is emptied first, then the files go. Returns how many.
+pub fn remove_all(storage: &dyn Storage, data_dir: &Path) -> io::Result<usize> {
+    let dir = data_dir.join(FLUSH_DIR);
+    let Some(mut manifest) = Manifest::load(storage, &dir)? else {
+        return Ok(0);
+    };
+    let removed = manifest.clear();
+    manifest.save(storage, &dir)?;
+    for entry in &removed {
+        storage.remove(&dir.join(&entry.file))?;
+    }
+    Ok(removed.len())
+}
+
+fn decode(generation: u64, bytes: &[u8]) -> io::Result<Flush> {
+    let encoding = SegmentHeader::read(&mut &bytes[..])?.encoding;
+    let (vectors, sections) = segment::read_segment_with_sections_from(&mut &bytes[..])?;
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let ids = segment::ids_from_sections(&sections, vectors.len() as u64)?
+        .ok_or_else(|| invalid("no ID table"))?;
+    let section = |tag: [u8; 4]| sections.iter().find(|s| s.tag == tag);
+
+    let tombstones = section(TOMBSTONE_TAG).ok_or_else(|| invalid("no TOMB section"))?;
+    let count = tombstones
+        .data
+        .get(..8)
+        .map(|n| u64::from_le_bytes(n.try_into().unwrap()))
+        .ok_or_else(|| invalid("TOMB section is truncated"))?;
+    let deleted = segment::parse_id_table(&tombstones.data, count)?;
+
+    let collection = section(COLLECTION_TAG).ok_or_else(|| invalid("no COLL section"))?;
+    let collection = String::from_utf8(collection.data.clone())
+        .map_err(|_| invalid("collection name is not UTF-8"))?;
+    let seq = match section(SEQUENCE_TAG) {
+        Some(section) => section
+            .data
+            .as_slice()
+            .try_into()
+            .map(u64::from_le_bytes)
+            .map_err(|_| invalid("WSEQ section is not a u64"))?,
+        None => 0,
+    };
+
+    Ok(Flush {
+        generation,
+        seq,
+        collection,
+        points: ids.into_iter().zip(vectors).collect(),
+        deleted,
+        encoding,
+        complete: true,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::fs::DiskStorage;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("vectordb_memtable_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_memtable_tracks_last_write_per_id() {
+        let memtable = Memtable::default();
+        assert!(!memtable.is_due(&FlushPolicy::default(), Instant::now()));
+        memtable.put("a", 16);
+        memtable.put("b", 16);
+        memtable.delete("a");
+        memtable.put("b", 16);
+
+        assert_eq!(memtable.len(), 2);
+        assert_eq!(memtable.bytes(), 17 + 1 + 17 + 17);
+
+        let policy = FlushPolicy {
+            max_bytes: 50,
+            max_age: Duration::from_secs(60),
+        };
+        assert!(memtable.is_due(&policy, Instant::now()));
+        let sealed = memtable.take();
+        assert_eq!(sealed.len(), 2);
+        assert_eq!(sealed.upserts().collect::<Vec<_>>(), ["b"]);
+        assert_eq!(sealed.deletes().collect::<Vec<_>>(), ["a"]);
+        assert!(memtable.is_empty());
+        assert_eq!(memtable.age(Instant::now()), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_memtable_is_due_by_age() {
+        let memtable = Memtable::default();
+        memtable.put("a", 4);
+        let policy = FlushPolicy {
+            max_bytes: usize::MAX,
+            max_age: Duration::from_secs(60),
+        };
+        let now = Instant::now();
+        assert!(!memtable.is_due(&policy, now));
+        assert!(memtable.is_due(&policy, now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_flushes_round_trip_in_generation_order() {
+        let dir = temp_dir("round_trip");
+        let storage = DiskStorage;
+        let first = Flush {
+            generation: next_generation(),
+            seq: 7,
+            collection: "docs".into(),
+            points: vec![
+                ("a".into(), Vector::new(vec![1.0, 2.0])),
+                ("b".into(), Vector::new(vec![3.0, 4.0])),
+            ],
+            deleted: vec!["c".into()],
+            encoding: VectorEncoding::F32,
+            complete: true,
+        };
+        // Deletes only: a segment with no vectors
+        let second = Flush {
+            generation: next_generation(),
+            seq: 9,
+            collection: "docs".into(),
+            points: Vec::new(),
+            deleted: vec!["a".into()],
+            encoding: VectorEncoding::Binary,
+            complete: true,
+        };
+        write(&storage, &dir, &second).unwrap();
+        write(&storage, &dir, &first).unwrap();
+        // A flush that never finished, and one the manifest never listed
+        std::fs::write(dir.join(FLUSH_DIR).join("x.vec.tmp"), b"partial").unwrap();
+        let unrecorded = flush_path(&dir, second.generation + 1);
+        std::fs::copy(flush_path(&dir, first.generation), &unrecorded).unwrap();
+
+        let summary = |f: &Flush| {
+            let points: Vec<(String, Vec<f32>)> = f
+                .points
+                .iter()
+                .map(|(id, v)| (id.clone(), v.data.clone()))
+                .collect();
+            (
+                f.generation,
+                f.seq,
+                f.collection.clone(),
+                points,
+                f.deleted.clone(),
+                f.encoding,
+            )
+        };
+        let loaded: Vec<_> = load(&storage, &dir).unwrap().iter().map(summary).collect();
+        assert_eq!(loaded, vec![summary(&first), summary(&second)]);
+        // f32 flushes carry norm ranges, bit-packed ones can't
+        let has_norms = |f: &Flush| {
+            segment::read_section(&flush_path(&dir, f.generation), norms::SECTION_TAG)
+                .unwrap()
+                .is_some()
+        };
+        assert!(has_norms(&first));
+        assert!(!has_norms(&second));
+        assert!(next_generation() > second.generation);
+        assert!(!dir.join(FLUSH_DIR).join("x.vec.tmp").exists());
+        assert!(!unrecorded.exists());
+
+        assert_eq!(remove_all(&storage, &dir).unwrap(), 2);
+        assert!(load(&storage, &dir).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+// src/storage/mod.rs
+//
+// Storage layer: everything about where vectors live and in what encoding.
+
+pub mod access;
+pub mod bloom;
+pub mod clock;
+pub mod compaction;
+pub mod fs;
+pub mod inspect;
+pub mod manifest;
+pub mod memtable;
+pub mod norms;
+pub mod npy;
+pub mod object;
+pub mod schema;
+pub mod segment;
+pub mod segments;
+pub mod snapshot;
+pub mod sparse;
+pub mod tiering;
+pub mod vecs;
+pub mod wal;
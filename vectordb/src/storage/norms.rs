@@ -0,0 +1,631 @@
+// src/storage/norms.rs
+//
+// Per-block norm ranges, and a segment scan that uses them to skip blocks.
+//
+// A brute-force scan scores every vector. But once the top k is full, its
+// worst score is a threshold, and a whole block of vectors can be ruled
+// out without reading it if no vector in it could beat that. The block's
+// smallest and largest L2 norm are enough to bound its best score:
+//
+//   dot        q·x ≤ |q|·|x| ≤ |q|·max            (Cauchy–Schwarz)
+//   euclidean  |q − x| ≥ ||q| − |x||  ≥  0 if min ≤ |q| ≤ max,
+//                                        else the gap to the range
+//   cosine     q·x / (|q|·|x|) ≤ 1 whatever the norms, so cosine can only
+//              skip blocks of zero vectors (which score 0)
+//
+// The scan visits blocks best bound first, so the threshold rises as fast
+// as it can; the first block whose bound can't beat it ends the scan, as
+// every later block's bound is no better. On skewed data — a few blocks
+// of large-norm vectors for dot product, or clusters at very different
+// norms for Euclidean — most blocks are never read.
+//
+// The ranges are stored in a "NRM1" segment section, all little-endian:
+//
+//   u32 block size, u32 reserved, u64 count
+//   (f32 min, f32 max) × ceil(count / block size)
+//
+// Block b covers vectors b·size .. min((b+1)·size, count). Its size is
+// fixed when the writer starts, so a StreamingWriter can reserve the
+// section with `placeholder` and fill it in as vectors go by (see
+// segment.rs). Only f32 segments carry one: the bounds have to hold for
+// the vectors as stored, and int8 changes them.
+//
+// Snapshots, memtable flushes and compaction all write one, and
+// `scan_segment` (vectordb-cli scan) uses it. The server's own exact
+// searches run over points in memory, which aren't kept in any order, so
+// a collection groups them into a `NormIndex` instead: sorted by norm,
+// then cut into blocks, so ranges are as tight as they get. Its blocks go
+// through the same bounds, visited in parallel against a SharedThreshold
+// (search.rs `bounded_brute_force`).
+//
+// Bounds are loosened by a relative 1e-5 before comparing, so a rounding
+// difference between `DistanceMetric::calculate` and the norms can't skip
+// a block that holds a winner.
+
+use crate::engine::search::{self, TopK};
+use crate::models::{DistanceMetric, SearchResult, Vector};
+use crate::storage::segment::{self, Decoder, Section, SegmentHeader, VectorEncoding};
+use std::cmp::Ordering;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Segment section tag for norm ranges
+pub const SECTION_TAG: [u8; 4] = *b"NRM1";
+
+/// Vectors per block, unless a writer says otherwise
+pub const DEFAULT_BLOCK_SIZE: u32 = 1024;
+
+/// Relative slack on bounds, for rounding
+const BOUND_SLACK: f32 = 1e-5;
+
+/// Section bytes before the per-block ranges
+const PREFIX_SIZE: usize = 16;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// NORM BLOCKS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Smallest and largest norm of each block of consecutive vectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormBlocks {
+    block_size: u32,
+    count: u64,
+    /// (min, max) per block
+    ranges: Vec<(f32, f32)>,
+}
+
+impl NormBlocks {
+    pub fn new(block_size: u32) -> Self {
+        Self {
+            block_size: block_size.max(1),
+            count: 0,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Ranges for `vectors`, in order
+    pub fn from_vectors<'a>(vectors: impl IntoIterator<Item = &'a [f32]>, block_size: u32) -> Self {
+        let mut blocks = Self::new(block_size);
+        for v in vectors {
+            blocks.push(v);
+        }
+        blocks
+    }
+
+    /// Record the next vector
+    pub fn push(&mut self, data: &[f32]) {
+        self.push_norm(norm(data));
+    }
+
+    /// Record the next vector by its norm
+    pub fn push_norm(&mut self, norm: f32) {
+        if self.count % self.block_size as u64 == 0 {
+            self.ranges.push((norm, norm));
+        } else if let Some((min, max)) = self.ranges.last_mut() {
+            *min = min.min(norm);
+            *max = max.max(norm);
+        }
+        self.count += 1;
+    }
+
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Vectors recorded
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// First vector and number of vectors in block `b`
+    pub fn block_span(&self, b: usize) -> (u64, u64) {
+        let start = b as u64 * self.block_size as u64;
+        (start, (self.count - start).min(self.block_size as u64))
+    }
+
+    /// (min, max) norm of block `b`
+    pub fn range(&self, b: usize) -> (f32, f32) {
+        self.ranges[b]
+    }
+
+    /// Serialize as a segment section
+    pub fn to_section(&self) -> Section {
+        let mut data = Vec::with_capacity(section_size(self.count, self.block_size));
+        data.extend_from_slice(&self.block_size.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&self.count.to_le_bytes());
+        for (min, max) in &self.ranges {
+            data.extend_from_slice(&min.to_le_bytes());
+            data.extend_from_slice(&max.to_le_bytes());
+        }
+        Section::new(SECTION_TAG, data)
+    }
+
+    /// Parse a section written by `to_section`
+    pub fn from_section(section: &Section) -> io::Result<Self> {
+        if section.tag != SECTION_TAG {
+            return Err(invalid_data(format!(
+                "Expected a NRM1 section, got {:?}",
+                section.tag_str()
+            )));
+        }
+        let data = &section.data;
+        if data.len() < PREFIX_SIZE {
+            return Err(invalid_data(format!(
+                "NRM1 section is {} bytes, shorter than its prefix",
+                data.len()
+            )));
+        }
+        let block_size = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        let count = u64::from_le_bytes(data[8..16].try_into().unwrap());
+        if block_size == 0 || data.len() != section_size(count, block_size) {
+            return Err(invalid_data(format!(
+                "NRM1 section is {} bytes, expected {} for {} vectors in blocks of {}",
+                data.len(),
+                section_size(count, block_size.max(1)),
+                count,
+                block_size
+            )));
+        }
+        let ranges = data[PREFIX_SIZE..]
+            .chunks_exact(8)
+            .map(|b| {
+                (
+                    f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                    f32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+                )
+            })
+            .collect();
+        Ok(Self {
+            block_size,
+            count,
+            ranges,
+        })
+    }
+
+    /// The segment's norm ranges, if it has them
+    pub fn read_from_segment(path: &Path) -> io::Result<Option<Self>> {
+        segment::read_section(path, SECTION_TAG)?
+            .map(|section| Self::from_section(&section))
+            .transpose()
+    }
+
+    /// A zero-filled section the size `count` vectors' ranges will take,
+    /// for writers that need section sizes up front
+    pub fn placeholder(count: u64, block_size: u32) -> Section {
+        let block_size = block_size.max(1);
+        let mut data = vec![0u8; section_size(count, block_size)];
+        data[0..4].copy_from_slice(&block_size.to_le_bytes());
+        data[8..16].copy_from_slice(&count.to_le_bytes());
+        Section::new(SECTION_TAG, data)
+    }
+
+    /// Block size of a placeholder (or any NRM1 section)
+    pub(crate) fn section_block_size(section: &Section) -> io::Result<u32> {
+        NormBlocks::from_section(section).map(|blocks| blocks.block_size)
+    }
+}
+
+/// The NRM1 section for `vectors` stored with `encoding` (only f32
+/// segments carry one)
+pub fn section_for(vectors: &[Vector], encoding: VectorEncoding) -> Option<Section> {
+    (encoding == VectorEncoding::F32).then(|| {
+        NormBlocks::from_vectors(
+            vectors.iter().map(|v| v.data.as_slice()),
+            DEFAULT_BLOCK_SIZE,
+        )
+        .to_section()
+    })
+}
+
+/// Bytes of a NRM1 payload for `count` vectors
+fn section_size(count: u64, block_size: u32) -> usize {
+    let blocks = (count + block_size as u64 - 1) / block_size as u64;
+    PREFIX_SIZE + blocks as usize * 8
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// L2 norm of `data`
+pub fn norm(data: &[f32]) -> f32 {
+    data.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Can norm ranges rule anything out for searches scored by `metric`?
+/// (Cosine only skips blocks of zero vectors, not worth grouping for.)
+pub fn prunes(metric: DistanceMetric) -> bool {
+    matches!(metric, DistanceMetric::Dot | DistanceMetric::Euclidean)
+}
+
+/// The best score any vector with a norm in `min..=max` could have against
+/// a query of norm `query_norm`, loosened for rounding
+pub fn best_score(metric: DistanceMetric, query_norm: f32, min: f32, max: f32) -> f32 {
+    match metric {
+        DistanceMetric::Dot => {
+            let bound = query_norm * max;
+            bound + bound.abs() * BOUND_SLACK
+        }
+        DistanceMetric::Euclidean => {
+            let gap = if query_norm < min {
+                min - query_norm
+            } else if query_norm > max {
+                query_norm - max
+            } else {
+                0.0
+            };
+            (gap - query_norm.max(max) * BOUND_SLACK).max(0.0)
+        }
+        DistanceMetric::Cosine if max == 0.0 => 0.0,
+        DistanceMetric::Cosine => 1.0 + BOUND_SLACK,
+        // Norms say nothing about which bits are set
+        DistanceMetric::Hamming => 0.0,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// IN-MEMORY BLOCKS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Norm blocks over a collection's points in memory. The points are
+/// grouped in norm order rather than the order they're stored in, so each
+/// block's range is as tight as it can be.
+#[derive(Debug, Clone)]
+pub struct NormIndex {
+    blocks: NormBlocks,
+    /// Point IDs in block order
+    ids: Vec<String>,
+}
+
+impl NormIndex {
+    /// Group `points` (ID, components) into blocks of `block_size`
+    pub fn build<'a>(
+        points: impl IntoIterator<Item = (&'a str, &'a [f32])>,
+        block_size: u32,
+    ) -> Self {
+        let mut sorted: Vec<(f32, &str)> = points
+            .into_iter()
+            .map(|(id, data)| (norm(data), id))
+            .collect();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut blocks = NormBlocks::new(block_size);
+        let mut ids = Vec::with_capacity(sorted.len());
+        for (norm, id) in sorted {
+            blocks.push_norm(norm);
+            ids.push(id.to_string());
+        }
+        Self { blocks, ids }
+    }
+
+    /// Points grouped
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Each block's (min, max) norm and the IDs in it
+    pub fn blocks(&self) -> impl Iterator<Item = ((f32, f32), &[String])> + '_ {
+        (0..self.blocks.blocks()).map(|b| {
+            let (start, len) = self.blocks.block_span(b);
+            let ids = &self.ids[start as usize..(start + len) as usize];
+            (self.blocks.range(b), ids)
+        })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SCAN
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Results of a segment scan, and how much of it was skipped
+#[derive(Debug, Clone)]
+pub struct SegmentScan {
+    /// Best first
+    pub hits: Vec<SearchResult>,
+    /// Blocks in the segment (one pass of DEFAULT_BLOCK_SIZE without ranges)
+    pub blocks: usize,
+    /// Blocks ruled out by their norm range without being read
+    pub skipped: usize,
+}
+
+/// Exact top-k of a segment against `query`, skipping blocks whose norm
+/// range can't beat the running threshold. Hits are named by the ID table,
+/// or by vector index in segments without one. Segments without norm
+/// ranges (or not stored as f32) are scanned in full.
+pub fn scan_segment(
+    path: &Path,
+    query: &[f32],
+    metric: DistanceMetric,
+    k: usize,
+) -> io::Result<SegmentScan> {
+    let (file, header) = segment::open_segment(path)?;
+    if query.len() != header.dimension as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Query has dimension {}, segment {} has {}",
+                query.len(),
+                path.display(),
+                header.dimension
+            ),
+        ));
+    }
+    let mut r = BufReader::new(file);
+    let ids = segment::read_ids(&mut r, &header)?;
+    let norms = read_norms(&mut r, &header)?;
+    r.seek(SeekFrom::Start(header.data_offset() - header.params_size()))?;
+    let decoder = Decoder::read(&mut r, &header)?;
+
+    // (first vector, vectors, best possible score), best bound first
+    let mut order: Vec<(u64, u64, Option<f32>)> = match &norms {
+        Some(norms) => {
+            let query_norm = norm(query);
+            (0..norms.blocks())
+                .map(|b| {
+                    let (start, len) = norms.block_span(b);
+                    let (min, max) = norms.range(b);
+                    (start, len, Some(best_score(metric, query_norm, min, max)))
+                })
+                .collect()
+        }
+        None => (0..header.count)
+            .step_by(DEFAULT_BLOCK_SIZE as usize)
+            .map(|start| {
+                let len = (header.count - start).min(DEFAULT_BLOCK_SIZE as u64);
+                (start, len, None)
+            })
+            .collect(),
+    };
+    order.sort_by(|a, b| match (a.2, b.2) {
+        (Some(x), Some(y)) => search::compare_scores(metric, x, y),
+        _ => Ordering::Equal,
+    });
+
+    let mut top = TopK::new(k, metric);
+    let mut scan = SegmentScan {
+        hits: Vec::new(),
+        blocks: order.len(),
+        skipped: 0,
+    };
+    for (visited, &(start, len, bound)) in order.iter().enumerate() {
+        if bound.is_some_and(|bound| !top.accepts(bound)) {
+            scan.skipped = order.len() - visited;
+            break;
+        }
+        r.seek(SeekFrom::Start(header.vector_offset(start)))?;
+        for (i, vector) in decoder.read_vectors(&mut r, len)?.iter().enumerate() {
+            let index = start + i as u64;
+            let score = metric.calculate(query, &vector.data);
+            top.push_with(score, || match &ids {
+                Some(ids) => SearchResult::new(ids[index as usize].clone(), score),
+                None => SearchResult::new(index.to_string(), score),
+            });
+        }
+    }
+    scan.hits = top.into_sorted_vec();
+    Ok(scan)
+}
+
+/// The norm ranges of an f32 segment, if it has a usable NRM1 section
+fn read_norms<R: Read + Seek>(r: &mut R, header: &SegmentHeader) -> io::Result<Option<NormBlocks>> {
+    if header.encoding != VectorEncoding::F32 {
+        return Ok(None);
+    }
+    let Some((offset, len)) = segment::find_section(r, header, SECTION_TAG)? else {
+        return Ok(None);
+    };
+    r.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; len as usize];
+    r.read_exact(&mut data)?;
+    let norms = NormBlocks::from_section(&Section::new(SECTION_TAG, data))?;
+    if norms.count != header.count {
+        return Err(invalid_data(format!(
+            "NRM1 section covers {} vectors, segment has {}",
+            norms.count, header.count
+        )));
+    }
+    Ok(Some(norms))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::Rng;
+    use std::path::PathBuf;
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "vectordb_norms_{}_{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// `blocks` blocks of `size` random directions, block b scaled to a
+    /// norm around `scale(b)`
+    fn skewed(blocks: usize, size: usize, scale: impl Fn(usize) -> f32) -> Vec<Vector> {
+        let mut rng = Rng::stream(7, 0, 0);
+        (0..blocks * size)
+            .map(|i| {
+                let data: Vec<f32> = (0..8).map(|_| rng.unit() as f32 - 0.5).collect();
+                let norm = data.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let target = scale(i / size) * (0.9 + 0.2 * rng.unit() as f32);
+                Vector::new(data.iter().map(|x| x / norm * target).collect())
+            })
+            .collect()
+    }
+
+    fn write_with_norms(path: &Path, vectors: &[Vector], block_size: u32) {
+        let norms = NormBlocks::from_vectors(vectors.iter().map(|v| v.data.as_slice()), block_size);
+        segment::write_segment_with_sections(path, vectors, &[norms.to_section()]).unwrap();
+    }
+
+    fn exact(vectors: &[Vector], query: &[f32], metric: DistanceMetric, k: usize) -> Vec<String> {
+        let ids: Vec<String> = (0..vectors.len()).map(|i| i.to_string()).collect();
+        search::brute_force(
+            query,
+            metric,
+            k,
+            ids.iter()
+                .zip(vectors)
+                .map(|(id, v)| (id.as_str(), v.data.as_slice())),
+        )
+        .into_iter()
+        .map(|hit| hit.id)
+        .collect()
+    }
+
+    #[test]
+    fn test_section_round_trip_and_spans() {
+        let vectors = [[3.0, 4.0], [0.0, 1.0], [6.0, 8.0]];
+        let norms = NormBlocks::from_vectors(vectors.iter().map(|v| v.as_slice()), 2);
+        assert_eq!(norms.blocks(), 2);
+        assert_eq!(norms.range(0), (1.0, 5.0));
+        assert_eq!(norms.block_span(1), (2, 1));
+
+        let section = norms.to_section();
+        assert_eq!(section.data.len(), NormBlocks::placeholder(3, 2).data.len());
+        assert_eq!(NormBlocks::from_section(&section).unwrap(), norms);
+        let mut truncated = section.clone();
+        truncated.data.pop();
+        assert!(NormBlocks::from_section(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_index_groups_points_by_norm() {
+        let points = [
+            ("a", [3.0, 4.0]),
+            ("b", [0.0, 1.0]),
+            ("c", [6.0, 8.0]),
+            ("d", [0.0, 2.0]),
+        ];
+        let index = NormIndex::build(points.iter().map(|(id, v)| (*id, v.as_slice())), 2);
+        let blocks: Vec<_> = index.blocks().collect();
+        assert_eq!(index.len(), 4);
+        assert_eq!(
+            blocks[0],
+            ((1.0, 2.0), &["b".to_string(), "d".to_string()][..])
+        );
+        assert_eq!(
+            blocks[1],
+            ((5.0, 10.0), &["a".to_string(), "c".to_string()][..])
+        );
+
+        // Segments get a section only when stored as f32
+        let vectors: Vec<Vector> = points
+            .iter()
+            .map(|(_, v)| Vector::new(v.to_vec()))
+            .collect();
+        let section = section_for(&vectors, VectorEncoding::F32).unwrap();
+        assert_eq!(NormBlocks::from_section(&section).unwrap().count(), 4);
+        assert!(section_for(&vectors, VectorEncoding::Int8).is_none());
+    }
+
+    #[test]
+    fn test_bounds_hold() {
+        let mut rng = Rng::stream(3, 0, 0);
+        for _ in 0..200 {
+            let q: Vec<f32> = (0..4).map(|_| rng.unit() as f32 * 4.0 - 2.0).collect();
+            let x: Vec<f32> = (0..4).map(|_| rng.unit() as f32 * 4.0 - 2.0).collect();
+            let qn = q.iter().map(|v| v * v).sum::<f32>().sqrt();
+            let xn = x.iter().map(|v| v * v).sum::<f32>().sqrt();
+            for metric in [
+                DistanceMetric::Dot,
+                DistanceMetric::Euclidean,
+                DistanceMetric::Cosine,
+            ] {
+                let score = metric.calculate(&q, &x);
+                let bound = best_score(metric, qn, xn * 0.9, xn * 1.1);
+                assert_ne!(
+                    search::compare_scores(metric, score, bound),
+                    Ordering::Less,
+                    "{:?}: {} beats bound {}",
+                    metric,
+                    score,
+                    bound
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_dot_scan_skips_small_norm_blocks() {
+        let scratch = Scratch::new("dot");
+        let path = scratch.0.join("skewed.vec");
+        // Norms 1, 2, 4, ... 128: the top 5 all come from the last blocks
+        let vectors = skewed(8, 32, |b| (1 << b) as f32);
+        write_with_norms(&path, &vectors, 32);
+
+        let query = vec![0.3, -0.2, 0.5, 0.1, 0.0, 0.4, -0.1, 0.2];
+        let scan = scan_segment(&path, &query, DistanceMetric::Dot, 5).unwrap();
+        let ids: Vec<String> = scan.hits.iter().map(|h| h.id.clone()).collect();
+        assert_eq!(ids, exact(&vectors, &query, DistanceMetric::Dot, 5));
+        assert_eq!(scan.blocks, 8);
+        assert!(scan.skipped >= 5, "skipped {}", scan.skipped);
+    }
+
+    #[test]
+    fn test_euclidean_scan_skips_far_norms() {
+        let scratch = Scratch::new("euclidean");
+        let path = scratch.0.join("shells.vec");
+        // Shells at norms 1, 10, 100, ...: a query on the first shell
+        // never needs the outer ones
+        let vectors = skewed(4, 16, |b| 10f32.powi(b as i32));
+        write_with_norms(&path, &vectors, 16);
+
+        let query = vectors[3].data.clone();
+        let scan = scan_segment(&path, &query, DistanceMetric::Euclidean, 3).unwrap();
+        let ids: Vec<String> = scan.hits.iter().map(|h| h.id.clone()).collect();
+        assert_eq!(ids, exact(&vectors, &query, DistanceMetric::Euclidean, 3));
+        assert_eq!(scan.hits[0].id, "3");
+        assert_eq!(scan.skipped, 3);
+
+        // Cosine can't use norms, so nothing is skipped, same answer
+        let scan = scan_segment(&path, &query, DistanceMetric::Cosine, 3).unwrap();
+        let ids: Vec<String> = scan.hits.iter().map(|h| h.id.clone()).collect();
+        assert_eq!(ids, exact(&vectors, &query, DistanceMetric::Cosine, 3));
+        assert_eq!(scan.skipped, 0);
+    }
+
+    #[test]
+    fn test_scan_without_norms_reads_everything() {
+        let scratch = Scratch::new("plain");
+        let path = scratch.0.join("plain.vec");
+        let vectors = skewed(3, 10, |b| (b + 1) as f32);
+        segment::write_segment(&path, &vectors).unwrap();
+
+        let query = vectors[0].data.clone();
+        let scan = scan_segment(&path, &query, DistanceMetric::Dot, 4).unwrap();
+        let ids: Vec<String> = scan.hits.iter().map(|h| h.id.clone()).collect();
+        assert_eq!(ids, exact(&vectors, &query, DistanceMetric::Dot, 4));
+        assert_eq!((scan.blocks, scan.skipped), (1, 0));
+
+        let err = scan_segment(&path, &[1.0], DistanceMetric::Dot, 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,474 @@
+// src/storage/npy.rs
+//
+// Converting between segments and NumPy .npy/.npz files.
+//
+// An .npy file is a small text header describing one array, then the raw
+// elements in order:
+//
+//   ┌────────────┬─────────┬─────────────┬─────────────────────┬──────────┐
+//   │ \x93NUMPY  │ major   │ header_len  │ header (dict, ASCII)│ elements │
+//   │ 6 bytes    │ minor   │ u16 (v1) or │ padded with spaces, │          │
+//   │            │ 2 bytes │ u32 (v2/v3) │ ends in '\n'        │          │
+//   └────────────┴─────────┴─────────────┴─────────────────────┴──────────┘
+//
+//   {'descr': '<f4', 'fortran_order': False, 'shape': (10000, 384), }
+//
+// Only what `np.save` writes for an embedding matrix is accepted: a 2-D,
+// row-major ('fortran_order': False) array of little-endian float32
+// ('<f4'). Anything else is rejected with the dtype or shape it had, rather
+// than converted behind the caller's back — `arr.astype(np.float32)` and
+// `np.ascontiguousarray(arr)` are one line on the NumPy side. Each row
+// becomes a vector, with ID "i" for row i.
+//
+// An .npz file (`np.savez`, `np.savez_compressed`) is a zip archive of .npy
+// files, one per array: `np.savez(path, emb=x)` stores emb.npy, positional
+// arrays are arr_0.npy, arr_1.npy, ... Importing one needs the array's name
+// unless the archive holds only one.
+//
+// Both directions stream rows a chunk at a time and go through
+// `<output>.tmp`, like the .fvecs conversions in storage::vecs. Exporting
+// writes a version 1.0 .npy file; IDs and metadata are left behind.
+
+use crate::storage::compaction::tmp_path;
+use crate::storage::norms::{self, NormBlocks};
+use crate::storage::segment::{self, Decoder, StreamingWriter};
+use crate::storage::vecs::{self, ConvertReport};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// First bytes of every .npy file
+pub const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The one element type accepted: little-endian float32
+pub const DTYPE: &str = "<f4";
+
+/// Headers are padded so the data starts on this boundary
+const ALIGNMENT: usize = 64;
+
+/// Longest header accepted, to bound what a corrupt length makes us read
+const MAX_HEADER_LEN: usize = 1 << 16;
+
+/// Vectors converted per read
+const CHUNK: u64 = 4096;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HEADER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Shape of a float32 matrix, from an .npy header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NpyShape {
+    /// Rows (vectors)
+    pub count: u64,
+    /// Columns (components per vector)
+    pub dimension: usize,
+}
+
+/// Read an .npy preamble and header, leaving `r` at the first element.
+/// Fails unless the array is a row-major 2-D float32 matrix.
+pub fn read_header(r: &mut impl Read) -> io::Result<NpyShape> {
+    let mut preamble = [0u8; 8];
+    r.read_exact(&mut preamble)?;
+    if &preamble[..6] != MAGIC {
+        return Err(invalid("not an .npy file (bad magic)".into()));
+    }
+    let len = match preamble[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            r.read_exact(&mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            r.read_exact(&mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        major => return Err(invalid(format!("unsupported .npy version {}", major))),
+    };
+    if len > MAX_HEADER_LEN {
+        return Err(invalid(format!("{}-byte .npy header", len)));
+    }
+    let mut header = vec![0u8; len];
+    r.read_exact(&mut header)?;
+    let header =
+        String::from_utf8(header).map_err(|_| invalid(".npy header isn't valid text".into()))?;
+    parse_header(&header)
+}
+
+/// Value following `'key':` in a header dict, up to the end of the dict
+fn field<'a>(header: &'a str, key: &str) -> io::Result<&'a str> {
+    let quoted = format!("'{}':", key);
+    header
+        .find(&quoted)
+        .map(|at| header[at + quoted.len()..].trim_start())
+        .ok_or_else(|| invalid(format!(".npy header has no '{}'", key)))
+}
+
+fn parse_header(header: &str) -> io::Result<NpyShape> {
+    let descr = field(header, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|d| d.split('\'').next())
+        .ok_or_else(|| invalid("malformed 'descr' in .npy header".into()))?;
+    if descr != DTYPE {
+        return Err(invalid(format!(
+            "array has dtype '{}', expected float32 ('{}'); convert it with .astype(np.float32)",
+            descr, DTYPE
+        )));
+    }
+
+    if field(header, "fortran_order")?.starts_with("True") {
+        return Err(invalid(
+            "array is column-major (fortran_order); save np.ascontiguousarray(arr) instead".into(),
+        ));
+    }
+
+    let shape = field(header, "shape")?;
+    let dims = shape
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| invalid("malformed 'shape' in .npy header".into()))?;
+    let dims = dims
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid(format!("malformed shape ({}) in .npy header", dims)))?;
+    match dims[..] {
+        [count, dimension] if dimension > 0 && dimension <= u32::MAX as u64 => Ok(NpyShape {
+            count,
+            dimension: dimension as usize,
+        }),
+        _ => Err(invalid(format!(
+            "array has shape ({}), expected a matrix of (vectors, dimension)",
+            shape
+                .split(')')
+                .next()
+                .unwrap_or("")
+                .trim_start_matches('(')
+        ))),
+    }
+}
+
+/// A version 1.0 header for a float32 matrix, padded so the data is aligned
+pub fn encode_header(shape: NpyShape) -> Vec<u8> {
+    let mut dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        DTYPE, shape.count, shape.dimension
+    );
+    // magic + version + u16 length + dict + '\n'
+    let unpadded = MAGIC.len() + 2 + 2 + dict.len() + 1;
+    let padding = (ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT;
+    dict.extend(std::iter::repeat(' ').take(padding));
+    dict.push('\n');
+
+    let mut out = Vec::with_capacity(unpadded + padding);
+    out.extend(MAGIC);
+    out.extend([1, 0]);
+    out.extend(&(dict.len() as u16).to_le_bytes());
+    out.extend(dict.as_bytes());
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Write the first `limit` rows (all of them if None) of an .npy file as a
+/// segment, row i with ID "i"
+pub fn npy_to_segment(
+    input: &Path,
+    output: &Path,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let mut r = BufReader::new(File::open(input)?);
+    array_to_segment(&mut r, output, limit)
+}
+
+/// Like `npy_to_segment`, for the array called `array` in an .npz archive
+/// (None: the archive's only array)
+pub fn npz_to_segment(
+    input: &Path,
+    array: Option<&str>,
+    output: &Path,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(input)?))
+        .map_err(|e| invalid(format!("{}: {}", input.display(), e)))?;
+    let arrays = npz_arrays(&mut archive);
+    let name = match array {
+        Some(name) => name.to_string(),
+        None if arrays.len() == 1 => arrays[0].clone(),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} holds {} arrays ({}); name one",
+                    input.display(),
+                    arrays.len(),
+                    arrays.join(", ")
+                ),
+            ))
+        }
+    };
+    let mut entry = archive.by_name(&format!("{}.npy", name)).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "{} has no array '{}' (it has: {})",
+                input.display(),
+                name,
+                arrays.join(", ")
+            ),
+        )
+    })?;
+    array_to_segment(&mut entry, output, limit)
+}
+
+/// Names of the arrays in an .npz archive, in archive order
+fn npz_arrays<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Vec<String> {
+    (0..archive.len())
+        .filter_map(|i| {
+            let entry = archive.by_index_raw(i).ok()?;
+            entry.name().strip_suffix(".npy").map(str::to_string)
+        })
+        .collect()
+}
+
+fn array_to_segment(
+    r: &mut impl Read,
+    output: &Path,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let shape = read_header(r)?;
+    let count = limit.map_or(shape.count, |l| l.min(shape.count));
+    let ids: Vec<String> = (0..count).map(|i| i.to_string()).collect();
+
+    let tmp = tmp_path(output);
+    let result = (|| {
+        let mut w = StreamingWriter::new(
+            BufWriter::new(File::create(&tmp)?),
+            count,
+            shape.dimension as u32,
+            0,
+            vec![
+                segment::id_table_section(&ids),
+                NormBlocks::placeholder(count, norms::DEFAULT_BLOCK_SIZE),
+            ],
+        )?;
+        let mut row = vec![0u8; shape.dimension * 4];
+        let mut vector = Vec::with_capacity(shape.dimension);
+        for _ in 0..count {
+            r.read_exact(&mut row)?;
+            vector.clear();
+            vector.extend(
+                row.chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+            w.write_vector(&vector)?;
+        }
+        let mut file = w.finish(&[])?;
+        file.flush()?;
+        file.get_ref().sync_all()
+    })();
+    vecs::finish(result, &tmp, output, count, shape.dimension)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Write the first `limit` vectors (all of them if None) of a segment as a
+/// float32 .npy matrix
+pub fn segment_to_npy(
+    input: &Path,
+    output: &Path,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let header = segment::verify_segment(input)?;
+    let count = limit.map_or(header.count, |l| l.min(header.count));
+    let dimension = header.dimension as usize;
+
+    let tmp = tmp_path(output);
+    let result = (|| {
+        let mut r = BufReader::new(File::open(input)?);
+        r.seek(SeekFrom::Start(header.data_offset() - header.params_size()))?;
+        let decoder = Decoder::read(&mut r, &header)?;
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        w.write_all(&encode_header(NpyShape { count, dimension }))?;
+        let mut index = 0;
+        while index < count {
+            let n = CHUNK.min(count - index);
+            for vector in decoder.read_vectors(&mut r, n)? {
+                for x in &vector.data {
+                    w.write_all(&x.to_le_bytes())?;
+                }
+            }
+            index += n;
+        }
+        w.flush()?;
+        w.get_ref().sync_all()
+    })();
+    vecs::finish(result, &tmp, output, count, dimension)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("vectordb_npy_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// An .npy file as `np.save` writes it, with an arbitrary header dict
+    fn npy(dict: &str, data: &[f32]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(MAGIC);
+        out.extend([1, 0]);
+        out.extend(&(dict.len() as u16 + 1).to_le_bytes());
+        out.extend(dict.as_bytes());
+        out.push(b'\n');
+        for x in data {
+            out.extend(&x.to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_npy_round_trip() {
+        let dir = Scratch::new("round_trip");
+        let input = dir.path().join("emb.npy");
+        let data = [0.5, -1.0, 2.25, 3.0, 1e-3, -0.0];
+        std::fs::write(
+            &input,
+            npy(
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }",
+                &data,
+            ),
+        )
+        .unwrap();
+
+        let seg = dir.path().join("emb.vec");
+        let report = npy_to_segment(&input, &seg, None).unwrap();
+        assert_eq!((report.vectors, report.dimension), (3, 2));
+        let vectors = segment::read_segment(&seg).unwrap();
+        assert_eq!(vectors[2].data, vec![1e-3, -0.0]);
+
+        let back = dir.path().join("back.npy");
+        let report = segment_to_npy(&seg, &back, Some(2)).unwrap();
+        assert_eq!(report.vectors, 2);
+        let bytes = std::fs::read(&back).unwrap();
+        let mut r = &bytes[..];
+        assert_eq!(
+            read_header(&mut r).unwrap(),
+            NpyShape {
+                count: 2,
+                dimension: 2
+            }
+        );
+        // Data starts on the alignment boundary
+        assert_eq!((bytes.len() - r.len()) % ALIGNMENT, 0);
+        assert_eq!(r.len(), 4 * 4);
+        assert_eq!(&r[..4], &0.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_rejects_wrong_dtype_order_and_shape() {
+        let check = |dict: &str, expected: &str| {
+            let err = read_header(&mut &npy(dict, &[])[..]).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        };
+        check(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (3, 2), }",
+            "dtype '<f8'",
+        );
+        check(
+            "{'descr': '<f4', 'fortran_order': True, 'shape': (3, 2), }",
+            "column-major",
+        );
+        check(
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (6,), }",
+            "shape (6,)",
+        );
+        check(
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3, 4), }",
+            "shape (2, 3, 4)",
+        );
+        assert!(read_header(&mut &b"PK\x03\x04...."[..]).is_err());
+    }
+
+    #[test]
+    fn test_truncated_data_leaves_no_output() {
+        let dir = Scratch::new("truncated");
+        let input = dir.path().join("short.npy");
+        std::fs::write(
+            &input,
+            npy(
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (4, 2), }",
+                &[1.0, 2.0, 3.0],
+            ),
+        )
+        .unwrap();
+        let seg = dir.path().join("short.vec");
+        assert!(npy_to_segment(&input, &seg, None).is_err());
+        assert!(!seg.exists() && !tmp_path(&seg).exists());
+    }
+
+    #[test]
+    fn test_npz_picks_named_array() {
+        let dir = Scratch::new("npz");
+        let input = dir.path().join("data.npz");
+        let mut zip = zip::ZipWriter::new(File::create(&input).unwrap());
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in [("ids", [7.0f32, 8.0]), ("emb", [0.25, 0.75])] {
+            zip.start_file(format!("{}.npy", name), options).unwrap();
+            zip.write_all(&npy(
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (1, 2), }",
+                &data,
+            ))
+            .unwrap();
+        }
+        zip.finish().unwrap();
+
+        let seg = dir.path().join("emb.vec");
+        let err = npz_to_segment(&input, None, &seg, None).unwrap_err();
+        assert!(err.to_string().contains("ids, emb"), "{}", err);
+        assert!(npz_to_segment(&input, Some("nope"), &seg, None).is_err());
+
+        npz_to_segment(&input, Some("emb"), &seg, None).unwrap();
+        assert_eq!(
+            segment::read_segment(&seg).unwrap()[0].data,
+            vec![0.25, 0.75]
+        );
+    }
+}
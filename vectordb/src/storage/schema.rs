@@ -0,0 +1,485 @@
+// src/storage/schema.rs
+//
+// A machine-readable description of the on-disk formats, for tools that
+// want to read our files without linking this crate:
+//
+//   vectordb-cli schema > formats.json
+//
+// Everything that has a constant in the reader/writer (magic bytes,
+// versions, header sizes, section tags, WAL record tags, file name
+// prefixes) is taken from that constant, so the description can't drift
+// from the code. Field offsets are computed from the field types and
+// checked against the header size constants in the tests below.
+//
+// Conventions used in the output:
+//
+//   types      u8, u32, u64, f32 (little-endian), bytes[N], and
+//              string (u32 byte length + UTF-8 bytes)
+//   offset     from the start of the enclosing structure; null once a
+//              variable-size field came before
+//   size       bytes, or an expression over header fields for regions
+//              ("count * encodings[encoding].vector_bytes")
+//
+// Bump SCHEMA_VERSION when the shape of this description changes (not
+// when a format gains a version: that shows up in the data).
+
+use crate::quantization::pq;
+use crate::storage::norms;
+use crate::storage::segment::{self, VectorEncoding};
+use crate::storage::{manifest, memtable, snapshot, sparse, wal};
+use serde_json::{json, Value};
+
+/// Version of the description's own shape
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Byte size of a fixed-size type, None for variable-size ones
+fn type_size(ty: &str) -> Option<u64> {
+    match ty {
+        "u8" => Some(1),
+        "u32" | "f32" => Some(4),
+        "u64" => Some(8),
+        _ => ty
+            .strip_prefix("bytes[")
+            .and_then(|n| n.strip_suffix(']'))
+            .and_then(|n| n.parse().ok()),
+    }
+}
+
+/// Lay out `(name, type, description)` fields back to back, returning the
+/// field list and the fixed size (None if any field is variable-size)
+fn layout(fields: &[(&str, &str, &str)]) -> (Value, Option<u64>) {
+    let mut offset = Some(0u64);
+    let mut out = Vec::with_capacity(fields.len());
+    for &(name, ty, description) in fields {
+        out.push(json!({
+            "name": name,
+            "offset": offset,
+            "type": ty,
+            "description": description,
+        }));
+        offset = offset.zip(type_size(ty)).map(|(at, size)| at + size);
+    }
+    (Value::Array(out), offset)
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SEGMENT FILES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Header fields for one segment version
+fn segment_header_fields(version: u32) -> Vec<(&'static str, &'static str, &'static str)> {
+    let magic = ("magic", "bytes[4]", "segment magic");
+    let ver = ("version", "u32", "format version");
+    let mut fields = match version {
+        1 => vec![magic, ver, ("count", "u32", "number of vectors")],
+        _ => vec![magic, ver, ("count", "u64", "number of vectors")],
+    };
+    fields.push(("dimension", "u32", "components per vector"));
+    if version >= 2 {
+        fields.push(match version {
+            2 | 3 => ("reserved", "u32", "always 0; vectors are f32"),
+            _ => ("encoding", "u32", "component encoding code"),
+        });
+    }
+    if version >= 3 {
+        fields.push(("metadata_size", "u64", "bytes in the metadata block"));
+    }
+    if version >= 4 {
+        fields.push(("sections_size", "u64", "bytes of sections"));
+    }
+    fields
+}
+
+/// Header size constant the reader uses for `version`
+fn segment_header_size(version: u32) -> u64 {
+    match version {
+        1 => segment::HEADER_SIZE_V1,
+        2 => segment::HEADER_SIZE_V2,
+        3 => segment::HEADER_SIZE_V3,
+        _ => segment::HEADER_SIZE,
+    }
+}
+
+fn encoding(encoding: VectorEncoding, name: &str, vector_bytes: &str, params: &str) -> Value {
+    json!({
+        "code": encoding.code(),
+        "name": name,
+        "vector_bytes": vector_bytes,
+        "parameters": params,
+    })
+}
+
+fn segment_format() -> Value {
+    let headers: serde_json::Map<String, Value> = (1..=segment::VERSION)
+        .map(|version| {
+            let (fields, size) = layout(&segment_header_fields(version));
+            debug_assert_eq!(size, Some(segment_header_size(version)));
+            let header = json!({ "size": segment_header_size(version), "fields": fields });
+            (version.to_string(), header)
+        })
+        .collect();
+
+    let (metadata_entry, _) = layout(&[
+        ("pairs", "u32", "number of key/value pairs"),
+        ("key", "string", "repeated per pair, keys sorted"),
+        ("value", "string", "repeated per pair"),
+    ]);
+    let (section_frame, frame_size) = layout(&[
+        ("tag", "bytes[4]", "section kind"),
+        ("length", "u64", "payload bytes"),
+        ("payload", "bytes[length]", "section data"),
+    ]);
+    debug_assert_eq!(frame_size, None);
+    let (id_table, _) = layout(&[
+        ("count", "u64", "number of IDs (the segment's vector count)"),
+        (
+            "offsets",
+            "u64[count + 1]",
+            "ID i is strings[offsets[i]..offsets[i + 1]]",
+        ),
+        ("strings", "bytes", "UTF-8 IDs back to back"),
+    ]);
+    let (pq_section, _) = layout(&[
+        ("dimension", "u32", "vector dimension"),
+        ("subspaces", "u32", "code bytes per vector"),
+        ("centroids", "u32", "centroids per subspace"),
+        ("reserved", "u32", "always 0"),
+        (
+            "codebooks",
+            "f32[centroids * dimension]",
+            "subspace s covers components start = s * dimension / subspaces up to (s + 1) * dimension / subspaces (width w); its centroid c is the w floats at centroids * start + c * w",
+        ),
+        ("count", "u64", "number of coded vectors"),
+        (
+            "codes",
+            "u8[count * subspaces]",
+            "centroid index per subspace",
+        ),
+    ]);
+    let (norm_ranges, norm_prefix) = layout(&[
+        ("block_size", "u32", "vectors per block"),
+        ("reserved", "u32", "always 0"),
+        ("count", "u64", "number of vectors covered"),
+        (
+            "ranges",
+            "(f32 min, f32 max)[ceil(count / block_size)]",
+            "smallest and largest L2 norm in each block of block_size vectors, in vector order",
+        ),
+    ]);
+    debug_assert_eq!(norm_prefix, None);
+    let (sparse_rows, _) = layout(&[
+        ("count", "u64", "number of vectors covered (the segment's count)"),
+        (
+            "offsets",
+            "u64[count + 1]",
+            "vector i's nonzeros are entries offsets[i]..offsets[i + 1]; an empty row means no sparse embedding",
+        ),
+        ("indices", "u32[offsets[count]]", "component indices, increasing within a row"),
+        ("values", "f32[offsets[count]]", "component values"),
+    ]);
+    let (footer, footer_size) = layout(&[
+        (
+            "crc32",
+            "u32",
+            "CRC-32 (IEEE) of every byte before the footer, header included",
+        ),
+        ("magic", "bytes[4]", "marks a complete write"),
+    ]);
+    debug_assert_eq!(footer_size, Some(segment::FOOTER_SIZE));
+
+    json!({
+        "extension": ".vec",
+        "magic": text(segment::MAGIC),
+        "current_version": segment::VERSION,
+        "max_file_bytes": segment::MAX_SEGMENT_BYTES,
+        "headers": headers,
+        "encodings": [
+            encoding(VectorEncoding::F32, "f32", "dimension * 4", "none"),
+            encoding(
+                VectorEncoding::Int8,
+                "int8",
+                "dimension",
+                "f32[dimension] mins then f32[dimension] scales; a component decodes as min[d] + code * scale[d]",
+            ),
+            encoding(
+                VectorEncoding::Binary,
+                "binary",
+                "ceil(dimension / 64) * 8",
+                "none; u64 words, component d in bit d % 64 of word d / 64",
+            ),
+        ],
+        "regions": [
+            { "name": "header", "size": "headers[version].size" },
+            { "name": "encoding_parameters", "size": "dimension * 8 for int8, else 0", "since_version": 4 },
+            { "name": "vectors", "size": "count * encodings[encoding].vector_bytes" },
+            { "name": "metadata", "size": "metadata_size", "since_version": 3,
+              "description": "one entry per vector in vector order; absent (size 0) if no vector has metadata" },
+            { "name": "sections", "size": "sections_size", "since_version": 4 },
+            { "name": "footer", "size": segment::FOOTER_SIZE, "since_version": 5 },
+        ],
+        "metadata_entry": metadata_entry,
+        "section_frame": {
+            "size": segment::SECTION_FRAMING,
+            "fields": section_frame,
+            "unknown_tags": "skip",
+        },
+        "sections": [
+            { "tag": text(&segment::ID_TABLE_TAG), "description": "point IDs in vector order", "fields": id_table },
+            { "tag": text(&pq::SECTION_TAG), "description": "product quantization codebook and codes", "fields": pq_section },
+            { "tag": text(&norms::SECTION_TAG), "description": "per-block vector norm ranges, for skipping blocks in exact scans (f32 segments only)", "fields": norm_ranges },
+            { "tag": text(&sparse::SECTION_TAG), "description": "sparse embeddings in vector order, CSR layout (only if some vector has one)", "fields": sparse_rows },
+            { "tag": text(&memtable::TOMBSTONE_TAG), "description": "IDs a memtable flush deletes (ID table layout; flushed segments only)", "fields": id_table },
+            { "tag": text(&memtable::COLLECTION_TAG), "description": "collection a memtable flush belongs to, as UTF-8 (flushed segments only)" },
+            { "tag": text(&memtable::SEQUENCE_TAG), "description": "WAL sequence a memtable flush was sealed at, u64 (flushed segments only; 0 if absent)" },
+        ],
+        "footer": {
+            "size": segment::FOOTER_SIZE,
+            "magic": text(segment::FOOTER_MAGIC),
+            "fields": footer,
+        },
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// WRITE-AHEAD LOG
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn wal_record(tag: u8, name: &str, fields: &[(&str, &str, &str)]) -> Value {
+    let (fields, _) = layout(fields);
+    json!({ "tag": tag, "name": name, "fields": fields })
+}
+
+fn wal_format() -> Value {
+    let (frame, frame_size) = layout(&[
+        ("crc32", "u32", "CRC-32 (IEEE) of length and payload"),
+        ("length", "u32", "payload bytes"),
+    ]);
+    debug_assert_eq!(frame_size, Some(wal::HEADER_LEN as u64));
+
+    let tag = ("tag", "u8", "record kind");
+    let collection = (
+        "collection",
+        "collection",
+        "u8 0 for the flat store, or 1 followed by the name as a string",
+    );
+    let id = ("id", "string", "point ID");
+    let info = (
+        "config",
+        "bytes",
+        "u32 length + collection configuration as JSON",
+    );
+    let name = ("name", "string", "collection name");
+    json!({
+        "file": wal::WAL_FILE,
+        "frame": {
+            "size": wal::HEADER_LEN,
+            "fields": frame,
+            "max_payload_bytes": wal::MAX_RECORD_LEN,
+            "torn_tail": "reading stops at the first incomplete or mismatching frame",
+        },
+        "group": {
+            "tag": wal::TAG_GROUP,
+            "fields": layout(&[tag, ("count", "u32", "records in the group, framed right after it")]).0,
+            "description": "precedes records appended together; the group is replayed only if all of them are intact, else the log is cut at this frame",
+        },
+        "skip": {
+            "tag": wal::TAG_SKIP,
+            "fields": layout(&[tag, ("count", "u64", "sequence numbers taken by records a memtable flush made redundant")]).0,
+            "description": "left where flushed records were dropped, so the records after it keep their sequence numbers",
+        },
+        "records": [
+            wal_record(wal::TAG_INSERT, "insert", &[
+                tag, collection, id,
+                ("dimension", "u32", "number of components"),
+                ("data", "f32[dimension]", "the vector"),
+                ("pairs", "u32", "metadata pairs"),
+                ("key", "string", "repeated per pair"),
+                ("value", "string", "repeated per pair"),
+                ("nnz", "u32", "sparse embedding nonzeros; this and the next two fields are present only if the point has one"),
+                ("sparse_indices", "u32[nnz]", "sparse component indices, increasing"),
+                ("sparse_values", "f32[nnz]", "sparse component values"),
+            ]),
+            wal_record(wal::TAG_DELETE, "delete", &[tag, collection, id]),
+            wal_record(wal::TAG_CREATE_COLLECTION, "create_collection", &[tag, info]),
+            wal_record(wal::TAG_UPDATE_COLLECTION, "update_collection", &[tag, info]),
+            wal_record(wal::TAG_RENAME_COLLECTION, "rename_collection", &[
+                tag,
+                ("from", "string", "old name"),
+                ("to", "string", "new name"),
+            ]),
+            wal_record(wal::TAG_TRASH_COLLECTION, "trash_collection", &[
+                tag, name,
+                ("deleted_at", "u64", "Unix seconds"),
+            ]),
+            wal_record(wal::TAG_RESTORE_COLLECTION, "restore_collection", &[tag, name]),
+            wal_record(wal::TAG_PURGE_COLLECTION, "purge_collection", &[tag, name]),
+            wal_record(wal::TAG_SET_ALIAS, "set_alias", &[
+                tag,
+                ("config", "bytes", "u32 length + alias configuration as JSON (name, collection, experiment)"),
+            ]),
+            wal_record(wal::TAG_REMOVE_ALIAS, "remove_alias", &[
+                tag,
+                ("name", "string", "alias name"),
+            ]),
+            wal_record(wal::TAG_SET_TEMPLATE, "set_template", &[
+                tag,
+                ("name", "string", "template name"),
+                ("config", "bytes", "u32 length + template configuration as JSON (collection, filter, top_k, metric, exact)"),
+            ]),
+            wal_record(wal::TAG_REMOVE_TEMPLATE, "remove_template", &[
+                tag,
+                ("name", "string", "template name"),
+            ]),
+            wal_record(wal::TAG_ID_COUNTER, "id_counter", &[
+                tag,
+                ("collection", "string", "collection name"),
+                ("next", "u64", "next auto-increment ID; every one below it was handed out"),
+            ]),
+        ],
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SNAPSHOTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn snapshot_format() -> Value {
+    let set = |prefix: &str, key: &str, description: &str| {
+        json!({
+            "segment": format!("{}<{}>.vec", prefix, key),
+            "sidecar": format!("{}<{}>.json", prefix, key),
+            "description": description,
+        })
+    };
+    json!({
+        "files": [
+            set(snapshot::VECTORS_PREFIX, "dimension", "flat /vectors store, one set per dimension"),
+            set(snapshot::COLLECTION_PREFIX, "name", "one set per collection"),
+            set(snapshot::TRASH_PREFIX, "name", "one set per soft-deleted collection"),
+        ],
+        "sidecar_fields": {
+            "collection": "collection configuration (absent for the flat store)",
+            "deleted_at": "Unix seconds (trashed sets only)",
+            "ids": "point IDs in segment order (old snapshots; newer ones use the ID table section)",
+            "metadata": "point metadata in segment order (snapshots from before v3 segments)",
+        },
+        "catalog": {
+            "file": snapshot::CATALOG_FILE,
+            "encoding": "JSON object, one section per kind; written atomically, absent when every section is empty",
+            "sections": {
+                "aliases": "alias configurations: name, collection, experiment (collection, percent)",
+                "templates": "query template configurations keyed by name: collection, filter, top_k, metric, exact",
+                "id_counters": "next auto-increment ID keyed by collection name",
+                "trash_id_counters": "next auto-increment ID keyed by trashed collection name",
+            },
+        },
+    })
+}
+
+fn flushed_format() -> Value {
+    json!({
+        "files": format!("{}/<generation>.vec", memtable::FLUSH_DIR),
+        "generation": "u64, zero-padded to 20 digits so name order is flush order",
+        "description": "one sealed memtable per file: upserted points with their IDs, plus TOMB, COLL and WSEQ sections; applied over the snapshot at their WSEQ point in WAL replay, and removed by the next snapshot",
+        "manifest": {
+            "file": format!("{}/{}", memtable::FLUSH_DIR, manifest::MANIFEST_FILE),
+            "encoding": "JSON, replaced atomically (tmp, fsync, rename)",
+            "fields": {
+                "next_generation": "u64, generation the next flush gets",
+                "segments": "live segments, oldest first: file, generation, collection, points, deleted, min_id, max_id",
+            },
+            "recovery": "segments the manifest doesn't list are deleted; a listed segment that's missing is an error",
+        },
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DESCRIPTION
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Describe every on-disk format
+pub fn describe() -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "byte_order": "little-endian",
+        "segment": segment_format(),
+        "wal": wal_format(),
+        "snapshot": snapshot_format(),
+        "flushed": flushed_format(),
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Vector;
+    use crate::storage::segment::SegmentHeader;
+
+    #[test]
+    fn test_header_sizes_match_reader() {
+        for version in 1..=segment::VERSION {
+            let (_, size) = layout(&segment_header_fields(version));
+            assert_eq!(size, Some(segment_header_size(version)), "v{}", version);
+        }
+    }
+
+    #[test]
+    fn test_described_offsets_read_a_real_segment() {
+        let vectors = vec![Vector::new(vec![1.0, 2.0, 3.0]); 2];
+        let mut bytes = Vec::new();
+        segment::write_segment_to(&mut bytes, &vectors).unwrap();
+        let header = SegmentHeader::read(&mut bytes.as_slice()).unwrap();
+
+        let schema = describe();
+        let current = &schema["segment"]["headers"][segment::VERSION.to_string()];
+        let field = |name: &str| -> &[u8] {
+            let f = current["fields"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|f| f["name"] == name)
+                .unwrap();
+            let at = f["offset"].as_u64().unwrap() as usize;
+            &bytes[at..at + type_size(f["type"].as_str().unwrap()).unwrap() as usize]
+        };
+        assert_eq!(
+            field("magic"),
+            schema["segment"]["magic"].as_str().unwrap().as_bytes()
+        );
+        assert_eq!(field("count"), header.count.to_le_bytes());
+        assert_eq!(field("dimension"), header.dimension.to_le_bytes());
+        assert_eq!(field("sections_size"), header.sections_size.to_le_bytes());
+
+        let footer = &bytes[bytes.len() - segment::FOOTER_SIZE as usize..];
+        assert_eq!(
+            &footer[4..],
+            schema["segment"]["footer"]["magic"]
+                .as_str()
+                .unwrap()
+                .as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_wal_tags_are_unique() {
+        let schema = describe();
+        let mut tags: Vec<u64> = schema["wal"]["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["tag"].as_u64().unwrap())
+            .collect();
+        let n = tags.len();
+        tags.dedup();
+        assert_eq!(tags.len(), n);
+        assert_eq!(schema["wal"]["frame"]["fields"][1]["offset"], 4);
+    }
+}
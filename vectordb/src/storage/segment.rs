@@ -0,0 +1,1604 @@
+// src/storage/segment.rs
+//
+// The .vec segment file format (Post #6).
+//
+// File Layout (version 5):
+// ┌──────────────────────────┐
+// │ Magic "VECT" (4 bytes)   │
+// │ Version (4 bytes)        │
+// │ Count (8 bytes)          │
+// │ Dimension (4 bytes)      │
+// │ Encoding (4 bytes)       │
+// │ Metadata size (8 bytes)  │
+// │ Sections size (8 bytes)  │
+// ├──────────────────────────┤
+// │ Encoding parameters      │
+// ├──────────────────────────┤
+// │ Vector 1 (V bytes)       │
+// │ Vector 2 (V bytes)       │
+// │ ...                      │
+// ├──────────────────────────┤
+// │ Metadata block           │
+// ├──────────────────────────┤
+// │ Sections                 │
+// ├──────────────────────────┤
+// │ CRC32 (4 bytes)          │
+// │ Magic "TCEV" (4 bytes)   │
+// └──────────────────────────┘
+//
+// The encoding says how components are stored (it was a reserved zero
+// before v4, so older files are all f32):
+//
+//   0  f32     V = 4D, no parameters
+//   1  int8    V = D, parameters are D f32 mins then D f32 scales, and a
+//              component decodes as min[d] + code · scale[d]
+//              (quantization::sq)
+//   2  binary  V = 8 · ⌈D / 64⌉, no parameters; each vector is u64 words
+//              with component d in bit d % 64 of word d / 64, decoding to
+//              0.0 or 1.0 (quantization::binary)
+//
+// Readers that load `Vector`s decode int8 and binary segments, so
+// everything above the file format works unchanged; Sq8Codes and
+// BinaryCodes read the stored form as is.
+//
+// The metadata block has one entry per vector, in vector order: a u32 pair
+// count, then each key and value as a u32 byte length and UTF-8 bytes.
+// Keys are sorted so the same points always encode to the same bytes. If
+// no vector has metadata the block is left out and its size is 0.
+//
+// Sections carry optional per-segment data that plain readers can skip:
+// each is a 4-byte tag, a u64 payload length, and the payload. Tags in use:
+//
+//   "IDS1"   point IDs, in vector order (see ID TABLE below)
+//   "PQ01"   product quantization codebook + codes (quantization::pq)
+//   "NRM1"   min/max vector norm per block, for pruned scans (norms.rs)
+//   "SPR1"   the vectors' sparse embeddings (sparse.rs); the writer adds it
+//            and the readers fill `Vector::sparse` from it, like metadata
+//
+// The ID table makes a segment self-describing: a u64 count, count + 1 u64
+// offsets into the string area, then the IDs' UTF-8 bytes back to back.
+// ID i is bytes offsets[i]..offsets[i+1], so one ID can be read with two
+// seeks and no scan.
+//
+// Readers ignore tags they don't know, so adding a section kind doesn't
+// need a version bump; changing the framing does.
+//
+// The footer's CRC32 covers every byte before it, header included, and
+// the reversed magic marks a complete write. `read_segment` and
+// `read_segment_from` check it as they go, and the segment catalog checks
+// it the first time it opens a file; random-access reads don't, since that
+// would mean reading the whole file. A mismatch, a missing footer, or a
+// file shorter than its header says is a `Corruption` error, which becomes
+// VectorDbError::Corrupted.
+//
+// Version 1 had a 16-byte header with a u32 count, which capped a segment
+// at ~4B vectors. Version 2 widened the count but had no metadata block,
+// so metadata was dropped on write. Version 3 had no sections. Version 4
+// had no footer, so corruption in it can't be detected. All are still
+// read (with empty metadata and no sections); new segments are always
+// written as v5.
+// All offsets are computed in u64 with overflow checks, and anything larger
+// than MAX_SEGMENT_BYTES is rejected with a clean error rather than an
+// attempt to allocate it.
+//
+// All integers and floats are little-endian. Byte-exact fixtures for every
+// version live in tests/fixtures/ and are checked by tests/golden.rs — if
+// you change the layout, bump VERSION and add a new fixture rather than
+// editing the old one, because existing files on users' disks still use it.
+
+use crate::limits;
+use crate::models::{Vector, VectorType};
+use crate::quantization::binary;
+use crate::quantization::sq::ScalarQuantizer;
+use crate::storage::norms::{self, NormBlocks};
+use crate::storage::sparse::{self, SparseRows};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONSTANTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Magic bytes identifying our file format
+pub const MAGIC: &[u8; 4] = b"VECT";
+
+/// Current format version (what the writer produces)
+pub const VERSION: u32 = 5;
+
+/// Header size in bytes for the current version
+pub const HEADER_SIZE: u64 = 40;
+
+/// Magic bytes ending a complete segment (the header's, reversed)
+pub const FOOTER_MAGIC: &[u8; 4] = b"TCEV";
+
+/// Footer size in bytes (CRC32 + magic), from version 5
+pub const FOOTER_SIZE: u64 = 8;
+
+/// Header size in bytes for version 3 (no sections size or encoding)
+pub const HEADER_SIZE_V3: u64 = 32;
+
+/// Header size in bytes for version 2 (no metadata size)
+pub const HEADER_SIZE_V2: u64 = 24;
+
+/// Header size in bytes for version 1 (magic + version + u32 count + dimension)
+pub const HEADER_SIZE_V1: u64 = 16;
+
+/// Largest segment we will write or read: 1 TiB
+pub const MAX_SEGMENT_BYTES: u64 = 1 << 40;
+
+/// Upper bound on vectors pre-allocated before any data has been read, so
+/// a corrupt count can't trigger a huge allocation on its own
+const MAX_PREALLOC: usize = 1 << 16;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// LOW-LEVEL I/O HELPERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+fn write_u32(w: &mut impl Write, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn write_u64(w: &mut impl Write, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn write_f32(w: &mut impl Write, value: f32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn read_f32(r: &mut impl Read) -> io::Result<f32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    let len = u32::try_from(s.len()).map_err(|_| {
+        invalid_data(format!(
+            "Metadata string of {} bytes does not fit in u32",
+            s.len()
+        ))
+    })?;
+    write_u32(w, len)?;
+    w.write_all(s.as_bytes())
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<String> {
+    let len = read_u32(r)? as u64;
+    let mut bytes = Vec::new();
+    r.by_ref().take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Metadata string runs past the end of the block",
+        ));
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("Metadata string is not UTF-8".into()))
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// INTEGRITY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A segment whose bytes aren't what was written: bad checksum, missing
+/// footer, or truncated. Travels inside an io::Error (kind InvalidData)
+/// so readers keep returning io::Result.
+#[derive(Debug)]
+pub struct Corruption(pub String);
+
+impl std::fmt::Display for Corruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Corruption {}
+
+pub(crate) fn corrupted(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, Corruption(msg))
+}
+
+/// Does `err` report a corrupt segment?
+pub fn is_corruption(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|e| e.is::<Corruption>())
+}
+
+/// Passes bytes through while feeding them to a CRC32
+struct Checksummed<T> {
+    inner: T,
+    crc: crc32fast::Hasher,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            crc: crc32fast::Hasher::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Read the footer and compare it with the CRC of everything before it
+fn check_footer(r: &mut impl Read, computed: u32) -> io::Result<()> {
+    let mut footer = [0u8; FOOTER_SIZE as usize];
+    r.read_exact(&mut footer).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => corrupted("Segment ends before its footer".into()),
+        _ => e,
+    })?;
+    if &footer[4..] != FOOTER_MAGIC {
+        return Err(corrupted(format!(
+            "Segment footer magic is {:?}, expected {:?}",
+            &footer[4..],
+            FOOTER_MAGIC
+        )));
+    }
+    let stored = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+    if stored != computed {
+        return Err(corrupted(format!(
+            "Segment checksum mismatch: footer says {:#010x}, contents hash to {:#010x}",
+            stored, computed
+        )));
+    }
+    Ok(())
+}
+
+/// Check a whole segment against its footer, reading from the start of
+/// the file. Segments before v5 have no footer and always pass.
+pub fn verify_checksum(r: &mut impl Read, header: &SegmentHeader) -> io::Result<()> {
+    if header.footer_size() == 0 {
+        return Ok(());
+    }
+    let body = header.file_size() - FOOTER_SIZE;
+    let mut hashed = Checksummed::new(r.by_ref().take(body));
+    let copied = io::copy(&mut hashed, &mut io::sink())?;
+    if copied < body {
+        return Err(corrupted(format!(
+            "Segment is truncated: header says {} bytes, file has {}",
+            header.file_size(),
+            copied
+        )));
+    }
+    let computed = hashed.crc.finalize();
+    check_footer(r, computed)
+}
+
+/// Open a segment file and check it against its footer
+pub fn verify_segment(path: &Path) -> io::Result<SegmentHeader> {
+    let (mut file, header) = open_segment(path)?;
+    file.seek(SeekFrom::Start(0))?;
+    verify_checksum(&mut BufReader::new(file), &header)?;
+    Ok(header)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SEGMENT HEADER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// How a segment stores vector components
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VectorEncoding {
+    /// Full precision, 4 bytes per component
+    #[default]
+    F32,
+    /// Scalar quantized, 1 byte per component
+    Int8,
+    /// One bit per component, packed into u64 words
+    Binary,
+}
+
+impl VectorEncoding {
+    pub(crate) fn code(self) -> u32 {
+        match self {
+            Self::F32 => 0,
+            Self::Int8 => 1,
+            Self::Binary => 2,
+        }
+    }
+
+    /// How points of a collection with `vector_type` are stored
+    pub fn for_vector_type(vector_type: VectorType) -> Self {
+        match vector_type {
+            VectorType::Float32 => Self::F32,
+            VectorType::Binary => Self::Binary,
+        }
+    }
+
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::F32),
+            1 => Some(Self::Int8),
+            2 => Some(Self::Binary),
+            _ => None,
+        }
+    }
+
+    /// Bytes per stored vector of `dimension` components
+    pub fn vector_size(self, dimension: u32) -> u64 {
+        let dimension = dimension as u64;
+        match self {
+            Self::F32 => dimension * 4,
+            Self::Int8 => dimension,
+            Self::Binary => binary::words(dimension as usize) as u64 * 8,
+        }
+    }
+}
+
+/// Header information for a segment file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentHeader {
+    pub version: u32,
+    pub count: u64,
+    pub dimension: u32,
+    /// Component encoding (always f32 before v4)
+    pub encoding: VectorEncoding,
+    /// Bytes in the metadata block after the vectors (0 before v3)
+    pub metadata_size: u64,
+    /// Bytes of sections after the metadata block (0 before v4)
+    pub sections_size: u64,
+}
+
+impl SegmentHeader {
+    /// Header for a new f32 segment in the current format, validating its
+    /// size
+    pub fn new(
+        count: u64,
+        dimension: u32,
+        metadata_size: u64,
+        sections_size: u64,
+    ) -> io::Result<Self> {
+        Self::new_encoded(
+            count,
+            dimension,
+            VectorEncoding::F32,
+            metadata_size,
+            sections_size,
+        )
+    }
+
+    /// Header for a new segment with the given encoding
+    pub fn new_encoded(
+        count: u64,
+        dimension: u32,
+        encoding: VectorEncoding,
+        metadata_size: u64,
+        sections_size: u64,
+    ) -> io::Result<Self> {
+        let header = Self {
+            version: VERSION,
+            count,
+            dimension,
+            encoding,
+            metadata_size,
+            sections_size,
+        };
+        header.validate_size()?;
+        Ok(header)
+    }
+
+    /// Bytes of footer after the sections (0 before v5)
+    pub fn footer_size(&self) -> u64 {
+        if self.version >= 5 {
+            FOOTER_SIZE
+        } else {
+            0
+        }
+    }
+
+    /// Bytes of encoding parameters between the header and the vectors
+    pub fn params_size(&self) -> u64 {
+        match self.encoding {
+            VectorEncoding::F32 | VectorEncoding::Binary => 0,
+            VectorEncoding::Int8 => self.dimension as u64 * 8,
+        }
+    }
+
+    /// Calculate the byte offset where vector data starts
+    pub fn data_offset(&self) -> u64 {
+        match self.version {
+            1 => HEADER_SIZE_V1,
+            2 => HEADER_SIZE_V2,
+            3 => HEADER_SIZE_V3,
+            _ => HEADER_SIZE + self.params_size(),
+        }
+    }
+
+    /// Bytes per stored vector
+    pub fn vector_size(&self) -> u64 {
+        self.encoding.vector_size(self.dimension)
+    }
+
+    /// Calculate the total file size, or None if it overflows u64
+    pub fn checked_file_size(&self) -> Option<u64> {
+        self.count
+            .checked_mul(self.vector_size())?
+            .checked_add(self.data_offset())?
+            .checked_add(self.metadata_size)?
+            .checked_add(self.sections_size)?
+            .checked_add(self.footer_size())
+    }
+
+    /// Calculate the total file size (headers are validated on read, so
+    /// this can't overflow for a header that came from `read` or `new`)
+    pub fn file_size(&self) -> u64 {
+        self.checked_file_size().unwrap_or(u64::MAX)
+    }
+
+    /// Calculate byte offset for a specific vector index
+    pub fn vector_offset(&self, index: u64) -> u64 {
+        self.data_offset() + index * self.vector_size()
+    }
+
+    /// Calculate the byte offset where the metadata block starts
+    pub fn metadata_offset(&self) -> u64 {
+        self.vector_offset(self.count)
+    }
+
+    /// Calculate the byte offset where the sections start
+    pub fn sections_offset(&self) -> u64 {
+        self.metadata_offset() + self.metadata_size
+    }
+
+    /// Reject headers describing more than MAX_SEGMENT_BYTES of data
+    pub fn validate_size(&self) -> io::Result<()> {
+        match self.checked_file_size() {
+            Some(size) if size <= MAX_SEGMENT_BYTES => Ok(()),
+            _ => Err(invalid_data(format!(
+                "Segment too large: {} vectors × {} dims + {} metadata bytes + {} section bytes exceeds the {} byte limit",
+                self.count, self.dimension, self.metadata_size, self.sections_size, MAX_SEGMENT_BYTES
+            ))),
+        }
+    }
+
+    /// Write header to a writer (always in the current format)
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        write_u32(w, VERSION)?;
+        write_u64(w, self.count)?;
+        write_u32(w, self.dimension)?;
+        write_u32(w, self.encoding.code())?;
+        write_u64(w, self.metadata_size)?;
+        write_u64(w, self.sections_size)?;
+        Ok(())
+    }
+
+    /// Read header from a reader (version 1 to 5)
+    pub fn read(r: &mut impl Read) -> io::Result<Self> {
+        // Validate magic bytes
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid_data(format!(
+                "Invalid magic bytes: expected {:?}, got {:?}",
+                MAGIC, magic
+            )));
+        }
+
+        let version = read_u32(r)?;
+        let (count, dimension, encoding, metadata_size, sections_size) = match version {
+            1 => {
+                let count = read_u32(r)? as u64;
+                (count, read_u32(r)?, VectorEncoding::F32, 0, 0)
+            }
+            2..=5 => {
+                let count = read_u64(r)?;
+                let dimension = read_u32(r)?;
+                let encoding = match read_u32(r)? {
+                    _ if version < 4 => VectorEncoding::F32, // reserved
+                    code => VectorEncoding::from_code(code).ok_or_else(|| {
+                        invalid_data(format!("Unsupported vector encoding {}", code))
+                    })?,
+                };
+                let metadata_size = if version >= 3 { read_u64(r)? } else { 0 };
+                let sections_size = if version >= 4 { read_u64(r)? } else { 0 };
+                (count, dimension, encoding, metadata_size, sections_size)
+            }
+            other => {
+                return Err(invalid_data(format!(
+                    "Unsupported version: expected 1..={}, got {}",
+                    VERSION, other
+                )))
+            }
+        };
+
+        // dimension 0 is only valid for an empty segment
+        if dimension as usize > limits::max_dimension() || (dimension == 0 && count > 0) {
+            return Err(invalid_data(format!(
+                "Unsupported dimension {}: supported dimensions are 1..={}",
+                dimension,
+                limits::max_dimension()
+            )));
+        }
+
+        let header = Self {
+            version,
+            count,
+            dimension,
+            encoding,
+            metadata_size,
+            sections_size,
+        };
+        header.validate_size()?;
+        Ok(header)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// METADATA BLOCK
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Encode every vector's metadata (empty if none of them has any)
+fn encode_metadata(vectors: &[Vector]) -> io::Result<Vec<u8>> {
+    let mut block = Vec::new();
+    if vectors.iter().all(|v| v.metadata.is_empty()) {
+        return Ok(block);
+    }
+    for vec in vectors {
+        encode_metadata_entry(&mut block, &vec.metadata)?;
+    }
+    Ok(block)
+}
+
+/// Append one vector's metadata entry to a metadata block
+pub(crate) fn encode_metadata_entry(
+    block: &mut Vec<u8>,
+    metadata: &HashMap<String, String>,
+) -> io::Result<()> {
+    let mut pairs: Vec<_> = metadata.iter().collect();
+    pairs.sort();
+    write_u32(block, pairs.len() as u32)?;
+    for (key, value) in pairs {
+        write_str(block, key)?;
+        write_str(block, value)?;
+    }
+    Ok(())
+}
+
+/// Read one vector's metadata entry
+pub(crate) fn read_metadata_entry(r: &mut impl Read) -> io::Result<HashMap<String, String>> {
+    let pairs = read_u32(r)?;
+    let mut metadata = HashMap::with_capacity((pairs as usize).min(MAX_PREALLOC));
+    for _ in 0..pairs {
+        let key = read_str(r)?;
+        metadata.insert(key, read_str(r)?);
+    }
+    Ok(metadata)
+}
+
+/// Fill in metadata for `vectors`, which are vectors `skip..` of the
+/// segment, from the metadata block at the reader's position
+pub(crate) fn read_metadata(
+    r: &mut impl Read,
+    header: &SegmentHeader,
+    skip: u64,
+    vectors: &mut [Vector],
+) -> io::Result<()> {
+    if header.metadata_size == 0 {
+        return Ok(());
+    }
+    let mut block = r.by_ref().take(header.metadata_size);
+    for _ in 0..skip {
+        read_metadata_entry(&mut block)?;
+    }
+    for vec in vectors.iter_mut() {
+        vec.metadata = read_metadata_entry(&mut block)?;
+    }
+    if skip + vectors.len() as u64 == header.count && block.limit() > 0 {
+        return Err(invalid_data(format!(
+            "Metadata block has {} bytes left over after {} entries",
+            block.limit(),
+            header.count
+        )));
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SECTIONS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Bytes of framing in front of each section's payload (tag + length)
+pub(crate) const SECTION_FRAMING: u64 = 12;
+
+/// One optional section of a segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub tag: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+impl Section {
+    pub fn new(tag: [u8; 4], data: Vec<u8>) -> Self {
+        Self { tag, data }
+    }
+
+    /// Tag as text, for messages
+    pub fn tag_str(&self) -> String {
+        String::from_utf8_lossy(&self.tag).into_owned()
+    }
+}
+
+/// Total bytes `sections` take on disk, framing included
+fn sections_size(sections: &[Section]) -> u64 {
+    sections
+        .iter()
+        .map(|s| SECTION_FRAMING + s.data.len() as u64)
+        .sum()
+}
+
+/// Read every section from the reader's position, which must be the start
+/// of the sections
+pub(crate) fn read_sections_at(
+    r: &mut impl Read,
+    header: &SegmentHeader,
+) -> io::Result<Vec<Section>> {
+    let mut block = r.by_ref().take(header.sections_size);
+    let mut sections = Vec::new();
+    while block.limit() > 0 {
+        let mut tag = [0u8; 4];
+        block.read_exact(&mut tag)?;
+        let len = read_u64(&mut block)?;
+        if len > block.limit() {
+            return Err(invalid_data(format!(
+                "Section {:?} claims {} bytes but only {} are left",
+                String::from_utf8_lossy(&tag),
+                len,
+                block.limit()
+            )));
+        }
+        let mut data = Vec::new();
+        block.by_ref().take(len).read_to_end(&mut data)?;
+        if (data.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Section runs past the end of the file",
+            ));
+        }
+        sections.push(Section { tag, data });
+    }
+    Ok(sections)
+}
+
+/// Read every section of a segment file (empty before v4)
+pub fn read_sections(path: &Path) -> io::Result<Vec<Section>> {
+    let (mut file, header) = open_segment(path)?;
+    if header.sections_size == 0 {
+        return Ok(Vec::new());
+    }
+    file.seek(SeekFrom::Start(header.sections_offset()))?;
+    read_sections_at(&mut BufReader::new(file), &header)
+}
+
+/// Read the first section tagged `tag`, if the segment has one
+pub fn read_section(path: &Path, tag: [u8; 4]) -> io::Result<Option<Section>> {
+    Ok(read_sections(path)?.into_iter().find(|s| s.tag == tag))
+}
+
+/// Find the first section tagged `tag` by walking the frames, returning
+/// its payload's offset and length without reading it
+pub(crate) fn find_section<R: Read + Seek>(
+    r: &mut R,
+    header: &SegmentHeader,
+    tag: [u8; 4],
+) -> io::Result<Option<(u64, u64)>> {
+    let end = header.sections_offset() + header.sections_size;
+    let mut at = header.sections_offset();
+    while at + SECTION_FRAMING <= end {
+        r.seek(SeekFrom::Start(at))?;
+        let mut found = [0u8; 4];
+        r.read_exact(&mut found)?;
+        let len = read_u64(r)?;
+        let payload = at + SECTION_FRAMING;
+        if len > end - payload {
+            return Err(invalid_data(format!(
+                "Section {:?} claims {} bytes but only {} are left",
+                String::from_utf8_lossy(&found),
+                len,
+                end - payload
+            )));
+        }
+        if found == tag {
+            return Ok(Some((payload, len)));
+        }
+        at = payload + len;
+    }
+    Ok(None)
+}
+
+/// The segment's sparse embeddings, if it has a SPR1 section
+pub(crate) fn read_sparse_rows<R: Read + Seek>(
+    r: &mut R,
+    header: &SegmentHeader,
+) -> io::Result<Option<SparseRows>> {
+    let Some((offset, len)) = find_section(r, header, sparse::SECTION_TAG)? else {
+        return Ok(None);
+    };
+    r.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; len as usize];
+    r.read_exact(&mut data)?;
+    let section = Section::new(sparse::SECTION_TAG, data);
+    SparseRows::from_section(&section, header.count).map(Some)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ID TABLE
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Section tag for the point ID table
+pub const ID_TABLE_TAG: [u8; 4] = *b"IDS1";
+
+/// Build the ID table section for `ids`, given in vector order
+pub fn id_table_section<S: AsRef<str>>(ids: &[S]) -> Section {
+    let strings: u64 = ids.iter().map(|id| id.as_ref().len() as u64).sum();
+    let mut data = Vec::with_capacity(8 * (ids.len() + 2) + strings as usize);
+    data.extend(&(ids.len() as u64).to_le_bytes());
+    let mut offset = 0u64;
+    data.extend(&offset.to_le_bytes());
+    for id in ids {
+        offset += id.as_ref().len() as u64;
+        data.extend(&offset.to_le_bytes());
+    }
+    for id in ids {
+        data.extend(id.as_ref().as_bytes());
+    }
+    Section::new(ID_TABLE_TAG, data)
+}
+
+/// Decode a whole ID table, checking it has one ID per vector
+pub fn parse_id_table(data: &[u8], count: u64) -> io::Result<Vec<String>> {
+    let mut r = data;
+    let stored = read_u64(&mut r)?;
+    if stored != count {
+        return Err(invalid_data(format!(
+            "ID table lists {} IDs for {} vectors",
+            stored, count
+        )));
+    }
+    let offsets_len = count
+        .checked_add(1)
+        .and_then(|n| n.checked_mul(8))
+        .filter(|&n| n <= r.len() as u64)
+        .ok_or_else(|| invalid_data("ID table offsets run past the section".into()))?;
+    let (offsets, strings) = r.split_at(offsets_len as usize);
+    let offset = |i: usize| u64::from_le_bytes(offsets[i * 8..i * 8 + 8].try_into().unwrap());
+    if offset(count as usize) != strings.len() as u64 {
+        return Err(invalid_data(format!(
+            "ID table strings take {} bytes, offsets say {}",
+            strings.len(),
+            offset(count as usize)
+        )));
+    }
+
+    let mut ids = Vec::with_capacity((count as usize).min(MAX_PREALLOC));
+    for i in 0..count as usize {
+        let (start, end) = (offset(i), offset(i + 1));
+        if start > end || end > strings.len() as u64 {
+            return Err(invalid_data(format!(
+                "ID table offsets for ID {} are out of order",
+                i
+            )));
+        }
+        let id = std::str::from_utf8(&strings[start as usize..end as usize])
+            .map_err(|e| invalid_data(format!("ID {} is not UTF-8: {}", i, e)))?;
+        ids.push(id.to_string());
+    }
+    Ok(ids)
+}
+
+/// The IDs stored in `sections`, if one of them is an ID table
+pub fn ids_from_sections(sections: &[Section], count: u64) -> io::Result<Option<Vec<String>>> {
+    sections
+        .iter()
+        .find(|s| s.tag == ID_TABLE_TAG)
+        .map(|s| parse_id_table(&s.data, count))
+        .transpose()
+}
+
+/// Read a segment's whole ID table (None if it has none)
+pub fn read_ids<R: Read + Seek>(
+    r: &mut R,
+    header: &SegmentHeader,
+) -> io::Result<Option<Vec<String>>> {
+    let Some((table, len)) = find_section(r, header, ID_TABLE_TAG)? else {
+        return Ok(None);
+    };
+    r.seek(SeekFrom::Start(table))?;
+    let mut data = Vec::with_capacity((len as usize).min(MAX_PREALLOC));
+    r.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "ID table runs past the end of the file",
+        ));
+    }
+    parse_id_table(&data, header.count).map(Some)
+}
+
+/// Read the ID of vector `index` by seeking within the ID table (None if
+/// the segment has no ID table)
+pub fn read_id_at<R: Read + Seek>(
+    r: &mut R,
+    header: &SegmentHeader,
+    index: u64,
+) -> io::Result<Option<String>> {
+    let Some((table, len)) = find_section(r, header, ID_TABLE_TAG)? else {
+        return Ok(None);
+    };
+    if index >= header.count {
+        return Err(invalid_data(format!(
+            "Index {} out of bounds (segment has {} vectors)",
+            index, header.count
+        )));
+    }
+    // count, then offsets[index] and offsets[index + 1]
+    let offsets_len = (header.count + 1) * 8;
+    if 8 + offsets_len > len {
+        return Err(invalid_data("ID table offsets run past the section".into()));
+    }
+    r.seek(SeekFrom::Start(table + 8 + index * 8))?;
+    let (start, end) = (read_u64(r)?, read_u64(r)?);
+    let strings = len - 8 - offsets_len;
+    if start > end || end > strings {
+        return Err(invalid_data(format!(
+            "ID table offsets for ID {} are out of order",
+            index
+        )));
+    }
+    r.seek(SeekFrom::Start(table + 8 + offsets_len + start))?;
+    let mut bytes = vec![0u8; (end - start) as usize];
+    r.read_exact(&mut bytes)?;
+    String::from_utf8(bytes)
+        .map(Some)
+        .map_err(|e| invalid_data(format!("ID {} is not UTF-8: {}", index, e)))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SEGMENT WRITER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Encode vectors and their metadata in segment format into any writer
+pub fn write_segment_to(w: &mut impl Write, vectors: &[Vector]) -> io::Result<()> {
+    write_segment_encoded_to(w, vectors, VectorEncoding::F32, &[])
+}
+
+/// Encode vectors, their metadata, and extra sections into any writer
+pub fn write_segment_with_sections_to(
+    w: &mut impl Write,
+    vectors: &[Vector],
+    sections: &[Section],
+) -> io::Result<()> {
+    write_segment_encoded_to(w, vectors, VectorEncoding::F32, sections)
+}
+
+/// Encode vectors with the given component encoding into any writer. For
+/// int8 the quantizer is fitted to `vectors` themselves.
+pub fn write_segment_encoded_to(
+    w: &mut impl Write,
+    vectors: &[Vector],
+    encoding: VectorEncoding,
+    sections: &[Section],
+) -> io::Result<()> {
+    // Determine dimension from first vector
+    let dimension = vectors.first().map(|v| v.dimension()).unwrap_or(0);
+    for (i, vec) in vectors.iter().enumerate() {
+        // Validate dimension consistency
+        if vec.dimension() != dimension {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Vector {} has dimension {}, expected {}",
+                    i,
+                    vec.dimension(),
+                    dimension
+                ),
+            ));
+        }
+    }
+    if let Some(ids) = sections.iter().find(|s| s.tag == ID_TABLE_TAG) {
+        parse_id_table(&ids.data, vectors.len() as u64)?;
+    }
+    let metadata = encode_metadata(vectors)?;
+    let sparse = sparse::section_for(vectors);
+    let mut w = Checksummed::new(w);
+    let w = &mut w;
+    let header = SegmentHeader::new_encoded(
+        vectors.len() as u64,
+        u32::try_from(dimension)
+            .map_err(|_| invalid_data(format!("Dimension {} does not fit in u32", dimension)))?,
+        encoding,
+        metadata.len() as u64,
+        sections_size(sections)
+            + sparse
+                .as_ref()
+                .map_or(0, |s| sections_size(std::slice::from_ref(s))),
+    )?;
+    header.write(w)?;
+
+    match encoding {
+        VectorEncoding::F32 => {
+            for vec in vectors {
+                for &val in &vec.data {
+                    write_f32(w, val)?;
+                }
+            }
+        }
+        VectorEncoding::Int8 if vectors.is_empty() => {}
+        VectorEncoding::Int8 => {
+            let data: Vec<&[f32]> = vectors.iter().map(|v| v.data.as_slice()).collect();
+            let sq = ScalarQuantizer::train(&data).map_err(|e| invalid_data(e.to_string()))?;
+            sq.write_params(w)?;
+            for v in &data {
+                w.write_all(&sq.encode(v).map_err(|e| invalid_data(e.to_string()))?)?;
+            }
+        }
+        VectorEncoding::Binary => {
+            for vec in vectors {
+                binary::check_bits(&vec.data).map_err(|e| invalid_data(e.to_string()))?;
+                for word in binary::pack(&vec.data) {
+                    write_u64(w, word)?;
+                }
+            }
+        }
+    }
+    w.write_all(&metadata)?;
+    for section in sections.iter().chain(&sparse) {
+        w.write_all(&section.tag)?;
+        write_u64(w, section.data.len() as u64)?;
+        w.write_all(&section.data)?;
+    }
+    let crc = w.crc.clone().finalize();
+    w.inner.write_all(&crc.to_le_bytes())?;
+    w.inner.write_all(FOOTER_MAGIC)
+}
+
+/// Writes an f32 segment one vector at a time, for segments too large to
+/// hold in memory. The header (count, dimension, metadata size) has to be
+/// known up front; `finish` checks that the promised amount was written.
+///
+/// A NRM1 section among `sections` is a placeholder
+/// (`NormBlocks::placeholder`): the writer records each vector's norm as
+/// it goes and writes the real ranges in its place.
+pub(crate) struct StreamingWriter<W: Write> {
+    w: Checksummed<W>,
+    header: SegmentHeader,
+    sections: Vec<Section>,
+    /// Index of the NRM1 placeholder in `sections`, and the ranges so far
+    norms: Option<(usize, NormBlocks)>,
+    written: u64,
+}
+
+impl<W: Write> StreamingWriter<W> {
+    /// Write the header for `count` vectors of `dimension` followed by a
+    /// `metadata_size`-byte metadata block and `sections`
+    pub(crate) fn new(
+        w: W,
+        count: u64,
+        dimension: u32,
+        metadata_size: u64,
+        sections: Vec<Section>,
+    ) -> io::Result<Self> {
+        Self::new_encoded(
+            w,
+            count,
+            dimension,
+            VectorEncoding::F32,
+            &[],
+            metadata_size,
+            sections,
+        )
+    }
+
+    /// Like `new`, for vectors stored with `encoding`; `params` are its
+    /// encoding parameters, written as is after the header. Vectors are
+    /// then appended already encoded, with `write_raw`.
+    pub(crate) fn new_encoded(
+        w: W,
+        count: u64,
+        dimension: u32,
+        encoding: VectorEncoding,
+        params: &[u8],
+        metadata_size: u64,
+        sections: Vec<Section>,
+    ) -> io::Result<Self> {
+        let header = SegmentHeader::new_encoded(
+            count,
+            dimension,
+            encoding,
+            metadata_size,
+            sections_size(&sections),
+        )?;
+        if params.len() as u64 != header.params_size() {
+            return Err(invalid_data(format!(
+                "{:?} segment needs {} bytes of encoding parameters, got {}",
+                encoding,
+                header.params_size(),
+                params.len()
+            )));
+        }
+        let norms = match sections.iter().position(|s| s.tag == norms::SECTION_TAG) {
+            Some(_) if encoding != VectorEncoding::F32 => {
+                return Err(invalid_data(format!(
+                    "{:?} segments can't carry norm ranges",
+                    encoding
+                )));
+            }
+            Some(i) => Some((
+                i,
+                NormBlocks::new(NormBlocks::section_block_size(&sections[i])?),
+            )),
+            None => None,
+        };
+        let mut w = Checksummed::new(w);
+        header.write(&mut w)?;
+        w.write_all(params)?;
+        Ok(Self {
+            w,
+            header,
+            sections,
+            norms,
+            written: 0,
+        })
+    }
+
+    /// Append the next vector's components
+    pub(crate) fn write_vector(&mut self, data: &[f32]) -> io::Result<()> {
+        if data.len() != self.header.dimension as usize {
+            return Err(invalid_data(format!(
+                "Vector {} has dimension {}, expected {}",
+                self.written,
+                data.len(),
+                self.header.dimension
+            )));
+        }
+        if self.written == self.header.count {
+            return Err(invalid_data(format!(
+                "Segment was declared with {} vectors",
+                self.header.count
+            )));
+        }
+        if self.header.encoding != VectorEncoding::F32 {
+            return Err(invalid_data(format!(
+                "Segment stores {:?} vectors; write them with write_raw",
+                self.header.encoding
+            )));
+        }
+        for &val in data {
+            write_f32(&mut self.w, val)?;
+        }
+        if let Some((_, norms)) = &mut self.norms {
+            norms.push(data);
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Append the next vector as stored bytes, in the segment's encoding
+    pub(crate) fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() as u64 != self.header.vector_size() {
+            return Err(invalid_data(format!(
+                "Vector {} is {} bytes, expected {}",
+                self.written,
+                bytes.len(),
+                self.header.vector_size()
+            )));
+        }
+        if self.written == self.header.count {
+            return Err(invalid_data(format!(
+                "Segment was declared with {} vectors",
+                self.header.count
+            )));
+        }
+        self.w.write_all(bytes)?;
+        if let Some((_, norms)) = &mut self.norms {
+            // Only f32 segments track norms, so these are f32 components
+            let data: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            norms.push(&data);
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Write the metadata block, sections, and footer, returning the inner
+    /// writer
+    pub(crate) fn finish(mut self, metadata: &[u8]) -> io::Result<W> {
+        if self.written != self.header.count || metadata.len() as u64 != self.header.metadata_size {
+            return Err(invalid_data(format!(
+                "Segment declared {} vectors and {} metadata bytes, got {} and {}",
+                self.header.count,
+                self.header.metadata_size,
+                self.written,
+                metadata.len()
+            )));
+        }
+        if let Some((i, norms)) = self.norms.take() {
+            let filled = norms.to_section();
+            if filled.data.len() != self.sections[i].data.len() {
+                return Err(invalid_data(format!(
+                    "NRM1 placeholder is {} bytes, the ranges for {} vectors take {}",
+                    self.sections[i].data.len(),
+                    self.written,
+                    filled.data.len()
+                )));
+            }
+            self.sections[i] = filled;
+        }
+        self.w.write_all(metadata)?;
+        for section in &self.sections {
+            self.w.write_all(&section.tag)?;
+            write_u64(&mut self.w, section.data.len() as u64)?;
+            self.w.write_all(&section.data)?;
+        }
+        let crc = self.w.crc.clone().finalize();
+        self.w.inner.write_all(&crc.to_le_bytes())?;
+        self.w.inner.write_all(FOOTER_MAGIC)?;
+        Ok(self.w.inner)
+    }
+}
+
+/// Write a collection of vectors to a segment file
+pub fn write_segment(path: &Path, vectors: &[Vector]) -> io::Result<()> {
+    write_segment_with_sections(path, vectors, &[])
+}
+
+/// Write vectors plus extra sections to a segment file
+pub fn write_segment_with_sections(
+    path: &Path,
+    vectors: &[Vector],
+    sections: &[Section],
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_segment_with_sections_to(&mut writer, vectors, sections)?;
+    writer.flush()
+}
+
+/// Write vectors as an int8 segment (a quarter of the size, lossy)
+pub fn write_segment_int8(path: &Path, vectors: &[Vector]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_segment_encoded_to(&mut writer, vectors, VectorEncoding::Int8, &[])?;
+    writer.flush()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SEGMENT READER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Turns stored components back into f32 vectors
+#[derive(Debug, Clone)]
+pub(crate) enum Decoder {
+    F32 { dimension: u32 },
+    Int8(ScalarQuantizer),
+    Binary { dimension: u32 },
+}
+
+impl Decoder {
+    /// Read the encoding parameters; `r` must be positioned right after
+    /// the fixed header (nothing is read for f32)
+    pub(crate) fn read(r: &mut impl Read, header: &SegmentHeader) -> io::Result<Self> {
+        Ok(match header.encoding {
+            VectorEncoding::F32 => Self::F32 {
+                dimension: header.dimension,
+            },
+            VectorEncoding::Int8 => {
+                Self::Int8(ScalarQuantizer::read_params(r, header.dimension as usize)?)
+            }
+            VectorEncoding::Binary => Self::Binary {
+                dimension: header.dimension,
+            },
+        })
+    }
+
+    /// Read `count` vectors from the current position
+    pub(crate) fn read_vectors(&self, r: &mut impl Read, count: u64) -> io::Result<Vec<Vector>> {
+        let mut vectors = Vec::with_capacity((count as usize).min(MAX_PREALLOC));
+        match self {
+            Self::F32 { dimension } => {
+                for _ in 0..count {
+                    let mut data = Vec::with_capacity(*dimension as usize);
+                    for _ in 0..*dimension {
+                        data.push(read_f32(r)?);
+                    }
+                    vectors.push(Vector::new(data));
+                }
+            }
+            Self::Int8(sq) => {
+                let mut code = vec![0u8; sq.dimension()];
+                for _ in 0..count {
+                    r.read_exact(&mut code)?;
+                    vectors.push(Vector::new(sq.decode(&code)));
+                }
+            }
+            Self::Binary { dimension } => {
+                let mut words = vec![0u64; binary::words(*dimension as usize)];
+                for _ in 0..count {
+                    for word in &mut words {
+                        *word = read_u64(r)?;
+                    }
+                    vectors.push(Vector::new(binary::unpack(&words, *dimension as usize)));
+                }
+            }
+        }
+        Ok(vectors)
+    }
+}
+
+/// Decode a whole segment from any reader, checking the footer. Sections
+/// are read and checked for framing, then dropped.
+pub fn read_segment_from(r: &mut impl Read) -> io::Result<Vec<Vector>> {
+    read_segment_with_sections_from(r).map(|(vectors, _)| vectors)
+}
+
+/// Decode a whole segment and its sections from any reader, checking the
+/// footer. A SPR1 section is moved onto the vectors rather than returned.
+pub fn read_segment_with_sections_from(
+    r: &mut impl Read,
+) -> io::Result<(Vec<Vector>, Vec<Section>)> {
+    let mut hashed = Checksummed::new(r);
+    let r = &mut hashed;
+    let header = SegmentHeader::read(r)?;
+    let mut vectors = Decoder::read(r, &header)?
+        .read_vectors(r, header.count)
+        .map_err(truncation)?;
+    read_metadata(r, &header, 0, &mut vectors).map_err(truncation)?;
+    let mut sections = read_sections_at(r, &header).map_err(truncation)?;
+    sparse::take_from_sections(&mut sections, &mut vectors)?;
+    if header.footer_size() > 0 {
+        let computed = hashed.crc.clone().finalize();
+        check_footer(&mut hashed.inner, computed)?;
+    }
+    Ok((vectors, sections))
+}
+
+/// Decode a whole segment into (ID, vector) pairs, checking the footer.
+/// IDs are None if the segment has no ID table.
+pub fn read_points_from(r: &mut impl Read) -> io::Result<Vec<(Option<String>, Vector)>> {
+    let (vectors, sections) = read_segment_with_sections_from(r)?;
+    let ids = match ids_from_sections(&sections, vectors.len() as u64)? {
+        Some(ids) => ids.into_iter().map(Some).collect(),
+        None => vec![None; vectors.len()],
+    };
+    Ok(ids.into_iter().zip(vectors).collect())
+}
+
+/// Running out of bytes mid-segment means the file was cut short
+fn truncation(e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => corrupted(format!("Segment is truncated: {}", e)),
+        _ => e,
+    }
+}
+
+/// Open a segment file and check its header against the file's length
+pub(crate) fn open_segment(path: &Path) -> io::Result<(File, SegmentHeader)> {
+    let mut file = File::open(path)?;
+    let header = SegmentHeader::read(&mut file)?;
+    let len = file.metadata()?.len();
+    if len < header.file_size() {
+        return Err(corrupted(format!(
+            "Segment {} is truncated: header says {} bytes, file has {}",
+            path.display(),
+            header.file_size(),
+            len
+        )));
+    }
+    Ok((file, header))
+}
+
+/// Read all vectors from a segment file, checking the footer
+pub fn read_segment(path: &Path) -> io::Result<Vec<Vector>> {
+    let (mut file, _) = open_segment(path)?;
+    file.seek(SeekFrom::Start(0))?;
+    read_segment_from(&mut BufReader::new(file))
+}
+
+/// Read only the header from a segment file
+pub fn read_segment_header(path: &Path) -> io::Result<SegmentHeader> {
+    open_segment(path).map(|(_, header)| header)
+}
+
+/// Read a single vector and its ID by index (random access). The ID is
+/// None if the segment has no ID table.
+pub fn read_vector_at(path: &Path, index: u64) -> io::Result<(Option<String>, Vector)> {
+    let mut vectors = read_vectors_range(path, index, 1)?;
+    let (mut file, header) = open_segment(path)?;
+    let id = read_id_at(&mut file, &header, index)?;
+    Ok((id, vectors.remove(0)))
+}
+
+/// Read a range of vectors (more efficient than multiple read_vector_at calls).
+///
+/// Metadata entries have variable length, so finding a range's metadata
+/// reads through the entries before it. The footer isn't checked.
+pub fn read_vectors_range(path: &Path, start: u64, count: u64) -> io::Result<Vec<Vector>> {
+    let (file, header) = open_segment(path)?;
+
+    // Validate range (use checked_add to prevent overflow)
+    let end = start.checked_add(count).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Range overflow: start={} + count={}", start, count),
+        )
+    })?;
+
+    if end > header.count {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Range {}..{} out of bounds (count: {})",
+                start, end, header.count
+            ),
+        ));
+    }
+
+    let mut reader = BufReader::new(file);
+    let decoder = Decoder::read(&mut reader, &header)?;
+    reader.seek(SeekFrom::Start(header.vector_offset(start)))?;
+    let mut vectors = decoder.read_vectors(&mut reader, count)?;
+    if header.metadata_size > 0 {
+        reader.seek(SeekFrom::Start(header.metadata_offset()))?;
+        read_metadata(&mut reader, &header, start, &mut vectors)?;
+    }
+    if let Some(rows) = read_sparse_rows(&mut reader, &header)? {
+        rows.fill(start as usize, &mut vectors);
+    }
+    Ok(vectors)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HEX DUMP UTILITY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Column header printed above hex dump rows
+pub const HEX_DUMP_HEADER: &str =
+    "Offset    00 01 02 03  04 05 06 07  08 09 0A 0B  0C 0D 0E 0F   ASCII";
+
+/// Format bytes as a hex dump (offset, 16 bytes per row, ASCII column)
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    out.push_str(HEX_DUMP_HEADER);
+    out.push('\n');
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        out.push_str(&hex_row(i as u64 * 16, chunk));
+        out.push('\n');
+    }
+    out
+}
+
+/// Format one hex dump row of up to 16 bytes starting at `offset`
+pub fn hex_row(offset: u64, chunk: &[u8]) -> String {
+    let mut out = format!("{:08X}  ", offset);
+
+    // Hex bytes in groups of 4, padding incomplete lines
+    for j in 0..16 {
+        match chunk.get(j) {
+            Some(byte) => out.push_str(&format!("{:02X} ", byte)),
+            None => out.push_str("   "),
+        }
+        if j % 4 == 3 {
+            out.push(' ');
+        }
+    }
+
+    out.push(' ');
+    for &byte in chunk {
+        out.push(if (0x20..0x7F).contains(&byte) {
+            byte as char
+        } else {
+            '.'
+        });
+    }
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_random_access() {
+        let path = std::env::temp_dir().join(format!("vectordb_seg_{}.vec", std::process::id()));
+        let mut vectors: Vec<Vector> = (0..5)
+            .map(|i| Vector::new(vec![i as f32, i as f32 + 0.5, -(i as f32)]))
+            .collect();
+        vectors[3].metadata.insert("title".into(), "three".into());
+
+        write_segment(&path, &vectors).unwrap();
+        let header = read_segment_header(&path).unwrap();
+        assert_eq!(header.count, 5);
+        assert_eq!(header.file_size(), std::fs::metadata(&path).unwrap().len());
+
+        let loaded = read_segment(&path).unwrap();
+        assert_eq!(loaded[4].data, vectors[4].data);
+        assert_eq!(loaded[3].metadata, vectors[3].metadata);
+        let (id, vector) = read_vector_at(&path, 2).unwrap();
+        assert_eq!((id, vector.data), (None, vectors[2].data.clone()));
+        let range = read_vectors_range(&path, 1, 3).unwrap();
+        assert_eq!(range.len(), 3);
+        assert_eq!(range[2].metadata["title"], "three");
+        assert!(range[0].metadata.is_empty());
+        assert!(read_vector_at(&path, 5).is_err());
+        assert!(read_vectors_range(&path, u64::MAX, 2).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_id_table_round_trip() {
+        let path = std::env::temp_dir().join(format!("vectordb_ids_{}.vec", std::process::id()));
+        let vectors: Vec<Vector> = (0..4).map(|i| Vector::new(vec![i as f32; 2])).collect();
+        let ids = ["doc_0", "", "ドキュメント", "doc_3"];
+        // Another section first, so lookups have to walk past it
+        let sections = [Section::new(*b"TST1", vec![9; 5]), id_table_section(&ids)];
+        write_segment_with_sections(&path, &vectors, &sections).unwrap();
+
+        for (i, id) in ids.iter().enumerate() {
+            let (got, vector) = read_vector_at(&path, i as u64).unwrap();
+            assert_eq!(got.as_deref(), Some(*id));
+            assert_eq!(vector.data, vectors[i].data);
+        }
+        let bytes = std::fs::read(&path).unwrap();
+        let points = read_points_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(points[2].0.as_deref(), Some("ドキュメント"));
+        assert_eq!(points.len(), 4);
+
+        // The table must have one ID per vector
+        let short = [id_table_section(&ids[..3])];
+        assert!(write_segment_with_sections(&path, &vectors, &short).is_err());
+        let mut bad = id_table_section(&ids);
+        bad.data[16..24].copy_from_slice(&99u64.to_le_bytes());
+        assert!(parse_id_table(&bad.data, 4).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sections_round_trip_and_are_skipped() {
+        let path = std::env::temp_dir().join(format!("vectordb_sect_{}.vec", std::process::id()));
+        let mut vectors: Vec<Vector> = (0..3).map(|i| Vector::new(vec![i as f32; 2])).collect();
+        vectors[1].metadata.insert("k".into(), "v".into());
+        let sections = [
+            Section::new(*b"TST1", vec![1, 2, 3]),
+            Section::new(*b"TST2", Vec::new()),
+        ];
+        write_segment_with_sections(&path, &vectors, &sections).unwrap();
+
+        let header = read_segment_header(&path).unwrap();
+        assert_eq!(header.sections_size, 12 * 2 + 3);
+        assert_eq!(header.file_size(), std::fs::metadata(&path).unwrap().len());
+        assert_eq!(read_sections(&path).unwrap(), sections);
+        assert_eq!(
+            read_section(&path, *b"TST2").unwrap(),
+            Some(sections[1].clone())
+        );
+        assert_eq!(read_section(&path, *b"NONE").unwrap(), None);
+
+        // Plain readers see the same vectors as without sections
+        let loaded = read_segment(&path).unwrap();
+        assert_eq!(loaded[1].metadata["k"], "v");
+        assert_eq!(
+            read_vectors_range(&path, 2, 1).unwrap()[0].data,
+            vectors[2].data
+        );
+
+        // A section length running past the end is caught
+        let mut bytes = std::fs::read(&path).unwrap();
+        let at = header.sections_offset() as usize + 4;
+        bytes[at..at + 8].copy_from_slice(&100u64.to_le_bytes());
+        assert!(read_segment_from(&mut bytes.as_slice()).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_footer_catches_bit_flips_and_truncation() {
+        let vectors: Vec<Vector> = (0..4).map(|i| Vector::new(vec![i as f32; 3])).collect();
+        let mut bytes = Vec::new();
+        write_segment_to(&mut bytes, &vectors).unwrap();
+        let header = SegmentHeader::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(header.file_size(), bytes.len() as u64);
+        assert_eq!(&bytes[bytes.len() - 4..], FOOTER_MAGIC);
+        verify_checksum(&mut bytes.as_slice(), &header).unwrap();
+
+        // One flipped bit in the vector data
+        let mut flipped = bytes.clone();
+        flipped[HEADER_SIZE as usize + 5] ^= 0x10;
+        let err = read_segment_from(&mut flipped.as_slice()).unwrap_err();
+        assert!(is_corruption(&err), "{}", err);
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
+        assert!(is_corruption(
+            &verify_checksum(&mut flipped.as_slice(), &header).unwrap_err()
+        ));
+
+        // Cut short, in the data or in the footer
+        for cut in [10, 3] {
+            let short = &bytes[..bytes.len() - cut];
+            let err = read_segment_from(&mut &short[..]).unwrap_err();
+            assert!(is_corruption(&err), "{}", err);
+        }
+
+        // Files on disk are checked by read_segment and verify_segment
+        let path = std::env::temp_dir().join(format!("vectordb_crc_{}.vec", std::process::id()));
+        std::fs::write(&path, &flipped).unwrap();
+        assert!(is_corruption(&read_segment(&path).unwrap_err()));
+        assert!(is_corruption(&verify_segment(&path).unwrap_err()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_mixed_dimensions_and_bad_magic() {
+        let mut buf = Vec::new();
+        let mixed = [Vector::new(vec![1.0, 2.0]), Vector::new(vec![1.0])];
+        assert!(write_segment_to(&mut buf, &mixed).is_err());
+
+        let err = read_segment_from(&mut &b"NOPE\x01\0\0\0"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_oversized_headers_are_rejected() {
+        // Counts beyond u32 are fine as long as the total stays under the limit
+        let big = SegmentHeader::new(u32::MAX as u64 + 1, 4, 0, 0).unwrap();
+        assert_eq!(big.vector_offset(big.count), big.file_size() - FOOTER_SIZE);
+        assert!(SegmentHeader::new(1 << 37, 4, 0, 0).is_err());
+        assert!(SegmentHeader::new(1, 4, u64::MAX, 0).is_err());
+        assert!(SegmentHeader::new(1, 4, 1, u64::MAX).is_err());
+
+        // count × dimension overflowing u64 must not wrap around
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        let err = SegmentHeader::read(&mut &bytes[..]).unwrap_err();
+        assert!(err.to_string().contains("Unsupported dimension"), "{}", err);
+
+        bytes[16..20].copy_from_slice(&4u32.to_le_bytes());
+        let err = SegmentHeader::read(&mut &bytes[..]).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+}
@@ -0,0 +1,480 @@
+// src/storage/segments.rs
+//
+// Lazily opened segments behind an open-file LRU.
+//
+// A collection made of thousands of segments shouldn't cost thousands of
+// file descriptors, or thousands of header reads before the server can
+// answer its first request. The catalog registers segments by path (from
+// a manifest, or `register_dir` for a directory of .vec files) without
+// touching them. A segment is opened, its header read and validated, and
+// its checksum verified against the footer on first access; at most
+// `max_open` files stay open at once, and opening one more closes the
+// least recently used. A closed segment reopens on its next access with
+// its header already cached, and isn't re-verified. `read_points` reads a
+// whole segment and checks the footer as it goes, so it skips the
+// separate verification pass.
+//
+// The server registers each collection's snapshot segment at startup and
+// reads it into memory the first time a request reaches the collection
+// (see `AppState::load_collection` in main.rs), then unregisters it.
+//
+// Open handles count against the process fd budget (see fds.rs). When the
+// budget refuses another segment, the catalog closes its own least
+// recently used handle and tries again, so `max_open` is an upper bound
+// and the budget can hold the catalog below it.
+//
+// Reads are positional (pread) on a shared handle, so readers of the same
+// segment never fight over a file cursor and the catalog lock is held only
+// to find or open a handle, not while reading. A handle evicted while a
+// read is using it stays open until that read finishes.
+
+use crate::fds::{self, FdKind, FdPermit};
+use crate::models::Vector;
+use crate::storage::segment::{self, Decoder, SegmentHeader, HEADER_SIZE};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Default cap on segment files open at once
+pub const DEFAULT_MAX_OPEN: usize = 256;
+
+/// Catalog-assigned segment handle
+pub type SegmentId = u64;
+
+/// Counters for the catalog (cumulative except `registered` and `open`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CatalogStats {
+    pub registered: usize,
+    pub open: usize,
+    pub max_open: usize,
+    /// Accesses that found the segment already open
+    pub hits: u64,
+    /// Files opened (first access, or reopened after eviction)
+    pub opens: u64,
+    /// Handles closed to stay under `max_open` or the fd budget
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+struct Registered {
+    path: PathBuf,
+    /// Known once the segment has been opened
+    header: Option<SegmentHeader>,
+}
+
+/// An open file and its place in the fd budget; both go when the last
+/// reader drops it
+#[derive(Debug)]
+struct Handle {
+    file: File,
+    _permit: FdPermit,
+}
+
+#[derive(Debug)]
+struct OpenSegment {
+    handle: Arc<Handle>,
+    /// Key of this handle in `Inner::lru`
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    segments: HashMap<SegmentId, Registered>,
+    open: HashMap<SegmentId, OpenSegment>,
+    /// Open handles by last use, oldest first
+    lru: BTreeMap<u64, SegmentId>,
+    clock: u64,
+    next_id: SegmentId,
+    stats: CatalogStats,
+}
+
+/// Registered segments, opened on demand
+#[derive(Debug)]
+pub struct SegmentCatalog {
+    inner: Mutex<Inner>,
+    max_open: usize,
+}
+
+impl Default for SegmentCatalog {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_OPEN)
+    }
+}
+
+impl SegmentCatalog {
+    /// A catalog that keeps at most `max_open` files open (at least 1)
+    pub fn new(max_open: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            max_open: max_open.max(1),
+        }
+    }
+
+    /// Add a segment without opening it
+    pub fn register(&self, path: impl Into<PathBuf>) -> SegmentId {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.segments.insert(
+            id,
+            Registered {
+                path: path.into(),
+                header: None,
+            },
+        );
+        id
+    }
+
+    /// Register every `.vec` file in `dir`, in name order
+    pub fn register_dir(&self, dir: &Path) -> io::Result<Vec<(SegmentId, PathBuf)>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "vec") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths
+            .into_iter()
+            .map(|path| (self.register(path.clone()), path))
+            .collect())
+    }
+
+    /// Forget a segment and close its file (before deleting it, say)
+    pub fn unregister(&self, id: SegmentId) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(open) = inner.open.remove(&id) {
+            inner.lru.remove(&open.last_used);
+        }
+        inner.segments.remove(&id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn path(&self, id: SegmentId) -> Option<PathBuf> {
+        let inner = self.inner.lock().unwrap();
+        inner.segments.get(&id).map(|s| s.path.clone())
+    }
+
+    pub fn stats(&self) -> CatalogStats {
+        let inner = self.inner.lock().unwrap();
+        CatalogStats {
+            registered: inner.segments.len(),
+            open: inner.open.len(),
+            max_open: self.max_open,
+            ..inner.stats
+        }
+    }
+
+    /// The segment's header (opens it on first access)
+    pub fn header(&self, id: SegmentId) -> io::Result<SegmentHeader> {
+        self.handle(id).map(|(_, header)| header)
+    }
+
+    /// Read all vectors of a segment
+    pub fn read_all(&self, id: SegmentId) -> io::Result<Vec<Vector>> {
+        let header = self.header(id)?;
+        self.read_range(id, 0, header.count)
+    }
+
+    /// Read a whole segment as (ID, vector) pairs, sparse embeddings
+    /// included, checking its footer. IDs are None if it has no ID table.
+    pub fn read_points(&self, id: SegmentId) -> io::Result<Vec<(Option<String>, Vector)>> {
+        let (handle, _) = self.open(id, false)?;
+        let path = self.path(id).unwrap_or_default();
+        segment::read_points_from(&mut BufReader::new(ReadAt::new(&handle.file, 0)))
+            .map_err(|e| located(&path, e))
+    }
+
+    /// Read vector `index` of a segment
+    pub fn read_vector(&self, id: SegmentId, index: u64) -> io::Result<Vector> {
+        Ok(self.read_range(id, index, 1)?.remove(0))
+    }
+
+    /// Read vectors `start..start + count` of a segment, with metadata
+    pub fn read_range(&self, id: SegmentId, start: u64, count: u64) -> io::Result<Vec<Vector>> {
+        let (handle, header) = self.handle(id)?;
+        let file = &handle.file;
+        match start.checked_add(count) {
+            Some(end) if end <= header.count => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Range {}+{} out of bounds (count: {})",
+                        start, count, header.count
+                    ),
+                ))
+            }
+        }
+
+        let decoder = Decoder::read(&mut ReadAt::new(file, HEADER_SIZE), &header)?;
+        let mut reader = BufReader::new(ReadAt::new(file, header.vector_offset(start)));
+        let mut vectors = decoder.read_vectors(&mut reader, count)?;
+        if header.metadata_size > 0 {
+            let mut reader = BufReader::new(ReadAt::new(file, header.metadata_offset()));
+            segment::read_metadata(&mut reader, &header, start, &mut vectors)?;
+        }
+        Ok(vectors)
+    }
+
+    /// Find or open the segment's file, verifying it the first time
+    fn handle(&self, id: SegmentId) -> io::Result<(Arc<Handle>, SegmentHeader)> {
+        self.open(id, true)
+    }
+
+    /// Find or open the segment's file, evicting the least recently used
+    /// handle if that would exceed `max_open`; `verify` checks the footer
+    /// on first open
+    fn open(&self, id: SegmentId, verify: bool) -> io::Result<(Arc<Handle>, SegmentHeader)> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let registered = inner.segments.get(&id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("segment {} is not registered", id),
+            )
+        })?;
+        let (path, cached) = (registered.path.clone(), registered.header.clone());
+
+        inner.clock += 1;
+        let now = inner.clock;
+        if let Some(open) = inner.open.get_mut(&id) {
+            inner.lru.remove(&open.last_used);
+            inner.lru.insert(now, id);
+            open.last_used = now;
+            inner.stats.hits += 1;
+            let header = cached.expect("open segments have a header");
+            return Ok((open.handle.clone(), header));
+        }
+
+        while inner.open.len() >= self.max_open {
+            if !inner.evict_oldest() {
+                break;
+            }
+        }
+        let permit = loop {
+            if let Some(permit) = fds::try_acquire(FdKind::Segment) {
+                break permit;
+            }
+            if !inner.evict_oldest() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "no file descriptors left to open {} (fd budget: {} segments)",
+                        path.display(),
+                        fds::BUDGET.segment_cap()
+                    ),
+                ));
+            }
+        };
+
+        let (file, header) = segment::open_segment(&path)?;
+        if verify && cached.is_none() {
+            let mut reader = BufReader::new(ReadAt::new(&file, 0));
+            segment::verify_checksum(&mut reader, &header).map_err(|e| located(&path, e))?;
+        }
+        if cached.is_some_and(|h| h != header) {
+            tracing::warn!("Segment {} changed on disk while closed", path.display());
+        }
+        if let Some(registered) = inner.segments.get_mut(&id) {
+            registered.header = Some(header.clone());
+        }
+
+        let handle = Arc::new(Handle {
+            file,
+            _permit: permit,
+        });
+        inner.open.insert(
+            id,
+            OpenSegment {
+                handle: handle.clone(),
+                last_used: now,
+            },
+        );
+        inner.lru.insert(now, id);
+        inner.stats.opens += 1;
+        Ok((handle, header))
+    }
+}
+
+impl Inner {
+    /// Close the least recently used handle; false if none is open
+    fn evict_oldest(&mut self) -> bool {
+        let Some((_, oldest)) = self.lru.pop_first() else {
+            return false;
+        };
+        self.open.remove(&oldest);
+        self.stats.evictions += 1;
+        true
+    }
+}
+
+/// Name the file in corruption errors, which don't say where they came from
+fn located(path: &Path, e: io::Error) -> io::Error {
+    if segment::is_corruption(&e) {
+        segment::corrupted(format!("{}: {}", path.display(), e))
+    } else {
+        e
+    }
+}
+
+/// `Read` from a fixed position of a shared file, without moving its cursor
+struct ReadAt<'a> {
+    file: &'a File,
+    pos: u64,
+}
+
+impl<'a> ReadAt<'a> {
+    fn new(file: &'a File, pos: u64) -> Self {
+        Self { file, pos }
+    }
+}
+
+impl Read for ReadAt<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(self.file, buf, self.pos)?;
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(self.file, buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_segments(name: &str, n: usize) -> (PathBuf, Vec<Vec<Vector>>) {
+        let dir =
+            std::env::temp_dir().join(format!("vectordb_segments_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sets: Vec<Vec<Vector>> = (0..n)
+            .map(|s| {
+                (0..4)
+                    .map(|i| {
+                        let mut v = Vector::new(vec![s as f32, i as f32]);
+                        v.metadata.insert("n".into(), format!("{}-{}", s, i));
+                        v
+                    })
+                    .collect()
+            })
+            .collect();
+        for (s, vectors) in sets.iter().enumerate() {
+            segment::write_segment(&dir.join(format!("seg{}.vec", s)), vectors).unwrap();
+        }
+        (dir, sets)
+    }
+
+    #[test]
+    fn test_segments_open_on_first_access() {
+        let (dir, sets) = write_segments("lazy", 2);
+        let catalog = SegmentCatalog::new(4);
+        let ids = catalog.register_dir(&dir).unwrap();
+        let missing = catalog.register(dir.join("missing.vec"));
+        assert_eq!(catalog.stats().open, 0);
+        assert_eq!(catalog.len(), 3);
+
+        let (id, _) = ids[1];
+        let all = catalog.read_all(id).unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[3].data, sets[1][3].data);
+        assert_eq!(all[3].metadata, sets[1][3].metadata);
+        let range = catalog.read_range(id, 2, 2).unwrap();
+        assert_eq!(range[1].metadata["n"], "1-3");
+        assert!(catalog.read_range(id, 3, 2).is_err());
+        assert_eq!(
+            catalog.read_vector(ids[0].0, 0).unwrap().data,
+            sets[0][0].data
+        );
+
+        // Unreadable segments only fail when they're used
+        assert!(catalog.header(missing).is_err());
+        let mut bytes = std::fs::read(dir.join("seg0.vec")).unwrap();
+        bytes[45] ^= 1;
+        std::fs::write(dir.join("flipped.vec"), bytes).unwrap();
+        let flipped = catalog.register(dir.join("flipped.vec"));
+        let err = catalog.header(flipped).unwrap_err();
+        assert!(segment::is_corruption(&err), "{}", err);
+        assert!(err.to_string().contains("flipped.vec"), "{}", err);
+        let stats = catalog.stats();
+        assert_eq!((stats.open, stats.opens), (2, 2));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_points_checks_the_whole_segment() {
+        let dir =
+            std::env::temp_dir().join(format!("vectordb_segments_points_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let vectors = vec![Vector::new(vec![1.0, 2.0]), Vector::new(vec![3.0, 4.0])];
+        let mut bytes = Vec::new();
+        segment::write_segment_encoded_to(
+            &mut bytes,
+            &vectors,
+            segment::VectorEncoding::F32,
+            &[segment::id_table_section(&["a", "b"])],
+        )
+        .unwrap();
+        std::fs::write(dir.join("ok.vec"), &bytes).unwrap();
+        let last = bytes.len() - 9;
+        bytes[last] ^= 1;
+        std::fs::write(dir.join("bad.vec"), &bytes).unwrap();
+
+        let catalog = SegmentCatalog::new(4);
+        let ok = catalog.register(dir.join("ok.vec"));
+        let points = catalog.read_points(ok).unwrap();
+        assert_eq!(points[1].0.as_deref(), Some("b"));
+        assert_eq!(points[1].1.data, vec![3.0, 4.0]);
+        let bad = catalog.register(dir.join("bad.vec"));
+        let err = catalog.read_points(bad).unwrap_err();
+        assert!(segment::is_corruption(&err), "{}", err);
+        assert!(err.to_string().contains("bad.vec"), "{}", err);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_least_recently_used_handle_is_closed() {
+        let (dir, sets) = write_segments("lru", 3);
+        let catalog = SegmentCatalog::new(2);
+        let ids: Vec<SegmentId> = catalog
+            .register_dir(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+
+        catalog.header(ids[0]).unwrap();
+        catalog.header(ids[1]).unwrap();
+        catalog.header(ids[0]).unwrap(); // 1 is now the oldest
+        catalog.header(ids[2]).unwrap(); // evicts 1
+        let stats = catalog.stats();
+        assert_eq!((stats.open, stats.hits, stats.evictions), (2, 1, 1));
+
+        catalog.header(ids[0]).unwrap();
+        assert_eq!(catalog.stats().hits, 2, "0 should still be open");
+        assert_eq!(
+            catalog.read_vector(ids[1], 3).unwrap().data,
+            sets[1][3].data
+        );
+        assert_eq!(catalog.stats().opens, 4, "1 had to be reopened");
+
+        assert!(catalog.unregister(ids[1]));
+        assert_eq!(catalog.stats().open, 1);
+        assert!(catalog.read_all(ids[1]).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
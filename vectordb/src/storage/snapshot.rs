@@ -0,0 +1,479 @@
+// src/storage/snapshot.rs
+//
+// Whole-state snapshots in segment files, written on shutdown and loaded
+// on startup.
+//
+// Each set of points becomes a pair of files in the data directory:
+//
+//   vectors.<dim>.vec       flat /vectors store, one segment per dimension
+//   vectors.<dim>.json
+//   collection.<name>.vec   one segment per collection
+//   collection.<name>.json
+//   trash.<name>.vec        one segment per soft-deleted collection
+//   trash.<name>.json
+//   catalog.json            named objects that aren't point sets (aliases,
+//                           query templates), and auto-increment counters
+//
+// The .vec file is a regular segment (see segment.rs) holding vector data,
+// metadata, and point IDs (in its ID table), with norm ranges (norms.rs)
+// for f32 ones; binary collections' vectors are stored bit-packed. The
+// .json sidecar carries the collection's configuration, plus the deletion
+// time for trashed ones.
+// The catalog has one section per kind of object and is left out when
+// there's nothing in it.
+// Older snapshots listed the IDs in the sidecar, and ones from before
+// segments stored metadata kept that there too; both are still read.
+//
+// `load_lazily` reads only the sidecars of live collections whose segment
+// holds everything, and hands back their segment paths for the caller to
+// open on first access (see segments.rs). Saving a snapshot that lists a
+// collection as still unloaded leaves its files as they are.
+//
+// Files are written to `.tmp` names, synced, and renamed into place; files
+// left over from collections that no longer exist are removed afterwards.
+// A snapshot is a point-in-time copy taken at shutdown; writes since then
+// are recovered from the write-ahead log (see wal.rs).
+
+use crate::models::{CollectionInfo, CreateAliasRequest, CreateTemplateRequest, Vector};
+use crate::storage::fs::Storage;
+use crate::storage::norms;
+use crate::storage::segment::{self, VectorEncoding};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File stem prefix for the flat /vectors store
+pub(crate) const VECTORS_PREFIX: &str = "vectors.";
+
+/// File stem prefix for collections
+pub(crate) const COLLECTION_PREFIX: &str = "collection.";
+
+/// File stem prefix for soft-deleted collections
+pub(crate) const TRASH_PREFIX: &str = "trash.";
+
+/// File holding the catalog sections
+pub(crate) const CATALOG_FILE: &str = "catalog.json";
+
+/// One set's points: (ID, vector) pairs
+pub type Points = Vec<(String, Vector)>;
+
+/// Everything that gets persisted
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Flat /vectors store (any mix of dimensions)
+    pub vectors: Vec<(String, Vector)>,
+    /// Each collection's configuration and points
+    pub collections: Vec<(CollectionInfo, Points)>,
+    /// Soft-deleted collections: deletion time (Unix seconds),
+    /// configuration and points
+    pub trash: Vec<(u64, CollectionInfo, Points)>,
+    /// Collection aliases, as configured
+    pub aliases: Vec<CreateAliasRequest>,
+    /// Query templates by name, as registered
+    pub templates: Vec<(String, CreateTemplateRequest)>,
+    /// Next auto-increment ID of the collections that draw them, by name
+    pub id_counters: Vec<(String, u64)>,
+    /// The same for trashed collections
+    pub trash_id_counters: Vec<(String, u64)>,
+    /// Collections whose segment hasn't been read: their configuration and
+    /// segment path from `load_lazily`, or for `save`, ones whose files
+    /// are kept as they are
+    pub unloaded: Vec<(CollectionInfo, PathBuf)>,
+}
+
+/// Everything else that's persisted, one section per kind
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Catalog {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<CreateAliasRequest>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    templates: BTreeMap<String, CreateTemplateRequest>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    id_counters: BTreeMap<String, u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    trash_id_counters: BTreeMap<String, u64>,
+}
+
+impl Catalog {
+    fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+            && self.templates.is_empty()
+            && self.id_counters.is_empty()
+            && self.trash_id_counters.is_empty()
+    }
+}
+
+/// What the segment format doesn't store
+#[derive(Debug, Serialize, Deserialize)]
+struct Sidecar {
+    /// Collection configuration (absent for the flat store)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    collection: Option<CollectionInfo>,
+    /// When the collection was moved to the trash (trashed sets only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted_at: Option<u64>,
+    /// Point IDs, in segment order (only in snapshots from before segments
+    /// had ID tables)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ids: Vec<String>,
+    /// Point metadata, in segment order (only in snapshots from before v3
+    /// segments; newer ones keep metadata in the segment)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    metadata: Vec<HashMap<String, String>>,
+}
+
+/// Write `snapshot` into `dir`, replacing the previous one
+pub fn save(storage: &dyn Storage, dir: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let _span = tracing::info_span!(
+        "snapshot.save",
+        dir = %dir.display(),
+        vectors = snapshot.vectors.len(),
+        collections = snapshot.collections.len()
+    )
+    .entered();
+    // The flat store may mix dimensions; a segment can't
+    let mut by_dimension: BTreeMap<usize, Vec<&(String, Vector)>> = BTreeMap::new();
+    for point in &snapshot.vectors {
+        by_dimension
+            .entry(point.1.dimension())
+            .or_default()
+            .push(point);
+    }
+
+    let mut written = HashSet::new();
+    for (dimension, points) in by_dimension {
+        let stem = format!("{}{}", VECTORS_PREFIX, dimension);
+        write_set(storage, dir, &stem, None, None, points)?;
+        written.insert(stem);
+    }
+    for (info, points) in &snapshot.collections {
+        let stem = format!("{}{}", COLLECTION_PREFIX, info.name);
+        write_set(
+            storage,
+            dir,
+            &stem,
+            Some(info),
+            None,
+            points.iter().collect(),
+        )?;
+        written.insert(stem);
+    }
+    for (deleted_at, info, points) in &snapshot.trash {
+        let stem = format!("{}{}", TRASH_PREFIX, info.name);
+        let points = points.iter().collect();
+        write_set(storage, dir, &stem, Some(info), Some(*deleted_at), points)?;
+        written.insert(stem);
+    }
+    for (info, _) in &snapshot.unloaded {
+        written.insert(format!("{}{}", COLLECTION_PREFIX, info.name));
+    }
+
+    let catalog = Catalog {
+        aliases: snapshot.aliases.clone(),
+        templates: snapshot.templates.iter().cloned().collect(),
+        id_counters: snapshot.id_counters.iter().cloned().collect(),
+        trash_id_counters: snapshot.trash_id_counters.iter().cloned().collect(),
+    };
+    let catalog_path = dir.join(CATALOG_FILE);
+    if !catalog.is_empty() {
+        write_atomic(storage, &catalog_path, &serde_json::to_vec(&catalog)?)?;
+    } else if storage.exists(&catalog_path) {
+        storage.remove(&catalog_path)?;
+    }
+
+    // Drop sets that no longer exist (renamed, restored or purged
+    // collections, emptied dimensions)
+    for path in storage.list(dir)? {
+        let Some(stem) = snapshot_stem(&path) else {
+            continue;
+        };
+        if !written.contains(stem) {
+            storage.remove(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write one segment and its sidecar
+fn write_set(
+    storage: &dyn Storage,
+    dir: &Path,
+    stem: &str,
+    collection: Option<&CollectionInfo>,
+    deleted_at: Option<u64>,
+    mut points: Vec<&(String, Vector)>,
+) -> io::Result<()> {
+    points.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let vectors: Vec<Vector> = points.iter().map(|(_, v)| v.clone()).collect();
+    let ids: Vec<&str> = points.iter().map(|(id, _)| id.as_str()).collect();
+    let encoding = collection.map_or(VectorEncoding::F32, |c| {
+        VectorEncoding::for_vector_type(c.vector_type)
+    });
+    let mut sections = vec![segment::id_table_section(&ids)];
+    sections.extend(norms::section_for(&vectors, encoding));
+    let mut bytes = Vec::new();
+    segment::write_segment_encoded_to(&mut bytes, &vectors, encoding, &sections)?;
+
+    let sidecar = Sidecar {
+        collection: collection.cloned(),
+        deleted_at,
+        ids: Vec::new(),
+        metadata: Vec::new(),
+    };
+    let json = serde_json::to_vec(&sidecar)?;
+
+    write_atomic(storage, &dir.join(format!("{}.vec", stem)), &bytes)?;
+    write_atomic(storage, &dir.join(format!("{}.json", stem)), &json)
+}
+
+/// Write to a temporary name, sync, then rename over `path`
+pub(crate) fn write_atomic(storage: &dyn Storage, path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    storage.write(&tmp, data)?;
+    storage.sync(&tmp)?;
+    storage.rename(&tmp, path)
+}
+
+/// Read the snapshot in `dir` (empty if there isn't one)
+pub fn load(storage: &dyn Storage, dir: &Path) -> io::Result<Snapshot> {
+    load_from(storage, dir, false)
+}
+
+/// Read the snapshot in `dir`, except for the segments of live
+/// collections, which are listed in `unloaded` instead
+pub fn load_lazily(storage: &dyn Storage, dir: &Path) -> io::Result<Snapshot> {
+    load_from(storage, dir, true)
+}
+
+fn load_from(storage: &dyn Storage, dir: &Path, lazy: bool) -> io::Result<Snapshot> {
+    let _span = tracing::info_span!("snapshot.load", dir = %dir.display(), lazy).entered();
+    let mut snapshot = Snapshot::default();
+    let paths = match storage.list(dir) {
+        Ok(paths) => paths,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(snapshot),
+        Err(e) => return Err(e),
+    };
+
+    for path in paths {
+        if path.extension().map_or(true, |e| e != "json") || snapshot_stem(&path).is_none() {
+            continue;
+        }
+        let sidecar: Sidecar = serde_json::from_slice(&storage.read(&path)?)
+            .map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+        let segment_path = path.with_extension("vec");
+        // Sidecars with IDs or metadata predate segments holding both
+        let whole = sidecar.ids.is_empty() && sidecar.metadata.is_empty();
+        if lazy && whole && sidecar.deleted_at.is_none() {
+            if let Some(info) = sidecar.collection {
+                snapshot.unloaded.push((info, segment_path));
+                continue;
+            }
+        }
+        let (mut vectors, sections) =
+            segment::read_segment_with_sections_from(&mut storage.read(&segment_path)?.as_slice())?;
+        let ids = match segment::ids_from_sections(&sections, vectors.len() as u64)? {
+            Some(ids) => ids,
+            None => sidecar.ids,
+        };
+
+        if vectors.len() != ids.len() {
+            return Err(invalid_data(format!(
+                "{} has {} vectors but {} lists {} IDs",
+                segment_path.display(),
+                vectors.len(),
+                path.display(),
+                ids.len()
+            )));
+        }
+        if !sidecar.metadata.is_empty() {
+            if sidecar.metadata.len() != vectors.len() {
+                return Err(invalid_data(format!(
+                    "{} has {} vectors but {} lists {} metadata entries",
+                    segment_path.display(),
+                    vectors.len(),
+                    path.display(),
+                    sidecar.metadata.len()
+                )));
+            }
+            for (v, metadata) in vectors.iter_mut().zip(sidecar.metadata) {
+                v.metadata = metadata;
+            }
+        }
+        let points: Vec<(String, Vector)> = ids.into_iter().zip(vectors).collect();
+
+        match (sidecar.collection, sidecar.deleted_at) {
+            (Some(info), Some(deleted_at)) => snapshot.trash.push((deleted_at, info, points)),
+            (Some(info), None) => snapshot.collections.push((info, points)),
+            (None, _) => snapshot.vectors.extend(points),
+        }
+    }
+
+    let catalog_path = dir.join(CATALOG_FILE);
+    if storage.exists(&catalog_path) {
+        let catalog: Catalog = serde_json::from_slice(&storage.read(&catalog_path)?)
+            .map_err(|e| invalid_data(format!("{}: {}", catalog_path.display(), e)))?;
+        snapshot.aliases = catalog.aliases;
+        snapshot.templates = catalog.templates.into_iter().collect();
+        snapshot.id_counters = catalog.id_counters.into_iter().collect();
+        snapshot.trash_id_counters = catalog.trash_id_counters.into_iter().collect();
+    }
+    Ok(snapshot)
+}
+
+/// The set name of a snapshot file ("collection.docs" for
+/// collection.docs.vec), or None for anything else
+fn snapshot_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name
+        .strip_suffix(".vec")
+        .or_else(|| name.strip_suffix(".json"))
+        .or_else(|| name.strip_suffix(".vec.tmp"))
+        .or_else(|| name.strip_suffix(".json.tmp"))?;
+    [VECTORS_PREFIX, COLLECTION_PREFIX, TRASH_PREFIX]
+        .iter()
+        .any(|prefix| stem.starts_with(prefix))
+        .then_some(stem)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::collection::Collection;
+    use crate::models::DistanceMetric;
+    use crate::storage::fs::{MemStorage, StorageOp};
+
+    fn point(id: &str, data: Vec<f32>, lang: &str) -> (String, Vector) {
+        let mut v = Vector::new(data);
+        v.metadata.insert("lang".into(), lang.into());
+        (id.to_string(), v)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let storage = MemStorage::new();
+        let dir = Path::new("data");
+        let docs = Collection::new("docs", 2, DistanceMetric::Euclidean).unwrap();
+        let snapshot = Snapshot {
+            vectors: vec![
+                point("a", vec![1.0], "en"),
+                point("b", vec![1.0, 2.0], "de"),
+            ],
+            collections: vec![(docs.info(), vec![point("x", vec![0.5, -0.5], "fr")])],
+            trash: vec![(1_700_000_000, docs.info(), Vec::new())],
+            aliases: vec![CreateAliasRequest {
+                name: "current".into(),
+                collection: "docs".into(),
+                experiment: None,
+            }],
+            templates: vec![(
+                "recent".into(),
+                CreateTemplateRequest {
+                    collection: "current".into(),
+                    filter: Some("year >= 2024".into()),
+                    top_k: 3,
+                    metric: None,
+                    exact: false,
+                },
+            )],
+            id_counters: vec![("docs".into(), 8)],
+            trash_id_counters: vec![("docs".into(), 3)],
+            unloaded: Vec::new(),
+        };
+        save(&storage, dir, &snapshot).unwrap();
+
+        let names: Vec<_> = storage.list(dir).unwrap();
+        assert_eq!(names.len(), 9); // 2 flat dimensions + 1 collection + 1 trashed, × 2 files, + catalog
+
+        storage.crash(); // everything was synced before the rename
+        let loaded = load(&storage, dir).unwrap();
+        let mut flat = loaded.vectors.clone();
+        flat.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(flat[1].1.data, vec![1.0, 2.0]);
+        assert_eq!(flat[1].1.metadata["lang"], "de");
+
+        let (info, points) = &loaded.collections[0];
+        assert_eq!(
+            (info.name.as_str(), info.distance),
+            ("docs", DistanceMetric::Euclidean)
+        );
+        assert_eq!(points[0].0, "x");
+        assert_eq!(points[0].1.metadata["lang"], "fr");
+        // IDs live in the segment now, not the sidecar
+        let bytes = storage.read(&dir.join("collection.docs.vec")).unwrap();
+        let (_, sections) =
+            segment::read_segment_with_sections_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            segment::ids_from_sections(&sections, 1).unwrap(),
+            Some(vec!["x".into()])
+        );
+        assert!(sections.iter().any(|s| s.tag == norms::SECTION_TAG));
+        let sidecar = storage.read(&dir.join("collection.docs.json")).unwrap();
+        assert!(!String::from_utf8(sidecar).unwrap().contains("\"ids\""));
+        assert_eq!(loaded.trash[0].0, 1_700_000_000);
+        assert_eq!(loaded.trash[0].1.name, "docs");
+        assert_eq!(loaded.aliases[0].name, "current");
+        assert_eq!(loaded.aliases[0].collection, "docs");
+        assert_eq!(loaded.templates[0].0, "recent");
+        assert_eq!(loaded.templates[0].1.top_k, 3);
+        assert_eq!(loaded.id_counters, [("docs".to_string(), 8)]);
+        assert_eq!(loaded.trash_id_counters, [("docs".to_string(), 3)]);
+
+        // A later snapshot without the collection removes its files
+        save(&storage, dir, &Snapshot::default()).unwrap();
+        assert!(storage.list(dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lazy_load_leaves_collection_segments() {
+        let storage = MemStorage::new();
+        let dir = Path::new("data");
+        let docs = Collection::new("docs", 2, DistanceMetric::Euclidean).unwrap();
+        let snapshot = Snapshot {
+            collections: vec![(docs.info(), vec![point("x", vec![0.5, -0.5], "fr")])],
+            trash: vec![(1_700_000_000, docs.info(), Vec::new())],
+            ..Default::default()
+        };
+        save(&storage, dir, &snapshot).unwrap();
+
+        let loaded = load_lazily(&storage, dir).unwrap();
+        assert!(loaded.collections.is_empty());
+        assert_eq!(loaded.trash.len(), 1, "trashed sets are still read");
+        let (info, path) = &loaded.unloaded[0];
+        assert_eq!(info.name, "docs");
+        assert_eq!(path, &dir.join("collection.docs.vec"));
+
+        // Saving it as still unloaded keeps its files
+        save(&storage, dir, &loaded).unwrap();
+        let again = load(&storage, dir).unwrap();
+        assert_eq!(again.collections[0].1[0].0, "x");
+    }
+
+    #[test]
+    fn test_failed_save_keeps_previous_snapshot() {
+        let storage = MemStorage::new();
+        let dir = Path::new("data");
+        let first = Snapshot {
+            vectors: vec![point("a", vec![1.0], "en")],
+            ..Default::default()
+        };
+        save(&storage, dir, &first).unwrap();
+
+        storage.fail_after(StorageOp::Sync, 0);
+        let second = Snapshot {
+            vectors: vec![point("a", vec![9.0], "en")],
+            ..Default::default()
+        };
+        assert!(save(&storage, dir, &second).is_err());
+
+        let loaded = load(&storage, dir).unwrap();
+        assert_eq!(loaded.vectors[0].1.data, vec![1.0]);
+    }
+}
@@ -0,0 +1,227 @@
+// src/storage/sparse.rs
+//
+// Sparse embeddings on disk: the "SPR1" segment section.
+//
+// A point may carry a sparse vector (BM25, SPLADE) next to its dense one.
+// Its few nonzeros out of a vocabulary-sized dimension don't fit the
+// fixed-size vector area, so they're kept in a section, in compressed
+// sparse row (CSR) layout, all little-endian:
+//
+//   u64 count                       vectors covered (the segment's count)
+//   u64 offsets[count + 1]          row i is entries offsets[i]..offsets[i+1]
+//   u32 indices[nnz]                component indices, increasing per row
+//   f32 values[nnz]
+//
+// where nnz = offsets[count]. A vector without a sparse embedding has an
+// empty row. Row i starts at one offset lookup, so a single vector's
+// embedding can be read without decoding the rest.
+//
+// Like the metadata block, the section belongs to the vectors rather than
+// to whoever writes the segment: `write_segment_encoded_to` adds it when
+// any vector has a sparse embedding (so other segments are unchanged, byte
+// for byte), the readers that build `Vector`s fill `Vector::sparse` back
+// in from it, and compaction keeps the rows of the vectors it keeps.
+
+use crate::models::{SparseVector, Vector};
+use crate::storage::segment::Section;
+use std::io;
+
+/// Segment section tag for sparse embeddings
+pub const SECTION_TAG: [u8; 4] = *b"SPR1";
+
+/// The sparse embeddings of a run of vectors, in vector order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseRows {
+    /// Start of each row in `indices`/`values`, plus the end of the last
+    offsets: Vec<u64>,
+    indices: Vec<u32>,
+    values: Vec<f32>,
+}
+
+impl Default for SparseRows {
+    fn default() -> Self {
+        Self {
+            offsets: vec![0],
+            indices: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl SparseRows {
+    /// Rows for `vectors`, in order
+    pub fn from_vectors(vectors: &[Vector]) -> Self {
+        let mut rows = Self::default();
+        for v in vectors {
+            rows.push(v.sparse.as_ref());
+        }
+        rows
+    }
+
+    /// Record the next vector's embedding (None: it has none)
+    pub fn push(&mut self, sparse: Option<&SparseVector>) {
+        if let Some(sparse) = sparse {
+            self.indices.extend_from_slice(&sparse.indices);
+            self.values.extend_from_slice(&sparse.values);
+        }
+        self.offsets.push(self.indices.len() as u64);
+    }
+
+    /// Vectors covered
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Does any vector have a sparse embedding?
+    pub fn has_entries(&self) -> bool {
+        !self.indices.is_empty()
+    }
+
+    /// Vector `i`'s embedding; None if its row is empty
+    pub fn row(&self, i: usize) -> Option<SparseVector> {
+        let (start, end) = (self.offsets[i] as usize, self.offsets[i + 1] as usize);
+        (start < end).then(|| {
+            SparseVector::new(
+                self.indices[start..end].to_vec(),
+                self.values[start..end].to_vec(),
+            )
+        })
+    }
+
+    /// Keep only the rows whose entry in `keep` is true
+    pub fn retain(&mut self, keep: &[bool]) {
+        let mut kept = Self::default();
+        for (i, _) in keep.iter().enumerate().filter(|(_, &k)| k) {
+            kept.push(self.row(i).as_ref());
+        }
+        *self = kept;
+    }
+
+    /// Set `Vector::sparse` on `vectors` from rows `start..`
+    pub fn fill(&self, start: usize, vectors: &mut [Vector]) {
+        for (i, v) in vectors.iter_mut().enumerate() {
+            v.sparse = self.row(start + i);
+        }
+    }
+
+    /// Serialize as a segment section
+    pub fn to_section(&self) -> Section {
+        let mut data = Vec::with_capacity(8 + self.offsets.len() * 8 + self.indices.len() * 8);
+        data.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        for offset in &self.offsets {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        for index in &self.indices {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        for value in &self.values {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        Section::new(SECTION_TAG, data)
+    }
+
+    /// Parse a section written by `to_section`, which must cover `count`
+    /// vectors
+    pub fn from_section(section: &Section, count: u64) -> io::Result<Self> {
+        let data = &section.data;
+        let u64_at = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        if data.len() < 8 || u64_at(0) != count {
+            return Err(invalid_data(format!(
+                "SPR1 section doesn't cover the segment's {} vectors",
+                count
+            )));
+        }
+        let rows_end = (count as usize)
+            .checked_add(2)
+            .and_then(|n| n.checked_mul(8))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| invalid_data("SPR1 section is truncated".into()))?;
+        let offsets: Vec<u64> = (8..rows_end).step_by(8).map(u64_at).collect();
+        let nnz = *offsets.last().unwrap();
+        if offsets[0] != 0 || offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid_data("SPR1 row offsets aren't increasing".into()));
+        }
+        if nnz.checked_mul(8) != Some((data.len() - rows_end) as u64) {
+            return Err(invalid_data(format!(
+                "SPR1 section has {} bytes of entries, not 8 for each of its {} nonzeros",
+                data.len() - rows_end,
+                nnz
+            )));
+        }
+        let (indices, values) = data[rows_end..].split_at(nnz as usize * 4);
+        Ok(Self {
+            offsets,
+            indices: indices
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+            values: values
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+        })
+    }
+}
+
+/// The SPR1 section for `vectors`, if any of them has a sparse embedding
+pub fn section_for(vectors: &[Vector]) -> Option<Section> {
+    vectors
+        .iter()
+        .any(|v| v.sparse.as_ref().is_some_and(|s| !s.is_empty()))
+        .then(|| SparseRows::from_vectors(vectors).to_section())
+}
+
+/// Move the embeddings in a SPR1 section among `sections` (if there is
+/// one) onto `vectors`, dropping the section
+pub fn take_from_sections(sections: &mut Vec<Section>, vectors: &mut [Vector]) -> io::Result<()> {
+    let Some(at) = sections.iter().position(|s| s.tag == SECTION_TAG) else {
+        return Ok(());
+    };
+    let section = sections.remove(at);
+    SparseRows::from_section(&section, vectors.len() as u64)?.fill(0, vectors);
+    Ok(())
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_round_trip_and_retain() {
+        let vectors = vec![
+            Vector::new(vec![1.0]).with_sparse(SparseVector::new(vec![2, 9], vec![0.5, 1.5])),
+            Vector::new(vec![2.0]),
+            Vector::new(vec![3.0]).with_sparse(SparseVector::new(vec![7], vec![3.0])),
+        ];
+        let section = section_for(&vectors).unwrap();
+        // count + 4 offsets + 3 nonzeros of index and value
+        assert_eq!(section.data.len(), 8 + 4 * 8 + 3 * 8);
+        assert!(section_for(&[Vector::new(vec![1.0])]).is_none());
+
+        let mut rows = SparseRows::from_section(&section, 3).unwrap();
+        assert_eq!(rows, SparseRows::from_vectors(&vectors));
+        assert_eq!(rows.row(0), vectors[0].sparse);
+        assert_eq!(rows.row(1), None);
+
+        rows.retain(&[false, true, true]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.row(1), vectors[2].sparse);
+
+        assert!(SparseRows::from_section(&section, 4).is_err());
+        let mut cut = section.clone();
+        cut.data.truncate(cut.data.len() - 4);
+        assert!(SparseRows::from_section(&cut, 3).is_err());
+    }
+}
@@ -0,0 +1,620 @@
+// src/storage/tiering.rs
+//
+// Hot/cold tiering for vectors that haven't been read in a long time.
+//
+// Most collections follow a power law: a small set of vectors gets almost
+// all of the reads, and the long tail is touched once a month (or never).
+// Keeping the tail at full f32 precision wastes 4 bytes per dimension, so
+// the tiering sweep re-encodes it into a heavily quantized "cold" tier:
+//
+//   Hot tier:  Vec<f32>  → 4 bytes/dim, exact scores
+//   Cold tier: Vec<u8>   → 1 byte/dim,  approximate scores
+//
+// Cold vectors stay searchable: the scan dequantizes them on the fly and
+// scores them like any other candidate. Reading one by ID promotes it back
+// into the hot tier.
+//
+// Demotion is driven by two signals:
+//   1. Age:       not accessed for `cold_after`
+//   2. Frequency: if the hot tier is over `max_hot_vectors`, the least-read
+//                 vectors (per the AccessTracker sketch) go first
+//
+// The cold tier is saved as a segment of its own, still quantized, so a
+// restart brings cold vectors back cold. It's written to an ObjectStore
+// (object.rs): the data directory by default, or an S3-compatible bucket.
+//
+// Cold segment layout (little-endian):
+// ┌────────────────────────────────┐
+// │ Magic "COLD" (4 bytes)         │
+// │ Version (4 bytes)              │
+// │ Count (8 bytes)                │
+// ├────────────────────────────────┤
+// │ Entry 1                        │
+// │ ...                            │
+// ├────────────────────────────────┤
+// │ CRC32 of everything above (4)  │
+// └────────────────────────────────┘
+//
+// Each entry is the ID (u32 length + UTF-8), the dimension (u32), min and
+// max (f32), one code byte per dimension, the metadata as in a .vec
+// metadata block, and the sparse embedding: a u32 that is 0 for none or
+// nnz + 1, then nnz u32 indices and nnz f32 values. Entries are sorted by
+// ID, so the same tier always encodes to the same bytes.
+//
+// Access times aren't saved: after a restart every hot vector gets a full
+// `cold_after` window again.
+
+use crate::models::{SparseVector, Vector};
+use crate::storage::access::AccessTracker;
+use crate::storage::object::ObjectStore;
+use crate::storage::segment;
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Object key of the cold tier's segment
+pub const COLD_SEGMENT: &str = "cold.seg";
+
+const COLD_MAGIC: &[u8; 4] = b"COLD";
+const COLD_VERSION: u32 = 1;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// POLICY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// When a vector counts as "cold" and how often we look for cold vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TieringPolicy {
+    /// Vectors not accessed for this long are demoted to the cold tier
+    pub cold_after: Duration,
+
+    /// How often the background sweep runs
+    pub sweep_interval: Duration,
+
+    /// Cap on the hot tier; excess vectors are demoted least-read first
+    pub max_hot_vectors: Option<usize>,
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            cold_after: Duration::from_secs(30 * 24 * 60 * 60), // 30 days
+            sweep_interval: Duration::from_secs(60 * 60),       // hourly
+            max_hot_vectors: None,
+        }
+    }
+}
+
+impl TieringPolicy {
+    /// Create a policy that demotes after `days` without access
+    pub fn after_days(days: u64) -> Self {
+        Self {
+            cold_after: Duration::from_secs(days.saturating_mul(24 * 60 * 60)),
+            ..Self::default()
+        }
+    }
+
+    /// Has a vector last accessed at `last_access` gone cold by `now`?
+    pub fn is_cold(&self, last_access: Instant, now: Instant) -> bool {
+        now.saturating_duration_since(last_access) >= self.cold_after
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COLD VECTOR ENCODING
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A vector re-encoded for the cold tier (u8 scalar quantization).
+///
+/// Each component is mapped onto 0..=255 between the vector's own min and
+/// max, so a 768-dim embedding shrinks from 3 KB to 776 bytes.
+#[derive(Debug, Clone)]
+pub struct ColdVector {
+    /// Quantized components (0-255)
+    pub values: Vec<u8>,
+
+    /// Smallest component of the original vector
+    pub min: f32,
+
+    /// Largest component of the original vector
+    pub max: f32,
+
+    /// Metadata is kept verbatim — it's small and needed for filtering
+    pub metadata: HashMap<String, String>,
+
+    /// So is the sparse embedding, already compact
+    pub sparse: Option<SparseVector>,
+}
+
+impl ColdVector {
+    /// Quantize a hot vector for the cold tier
+    ///
+    /// q = round((v - min) / (max - min) * 255)
+    pub fn encode(vector: &Vector) -> Self {
+        let min = vector.data.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = vector
+            .data
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let range = max - min;
+        let scale = if range > 0.0 { 255.0 / range } else { 0.0 };
+
+        let values = vector
+            .data
+            .iter()
+            .map(|&v| ((v - min) * scale).round().clamp(0.0, 255.0) as u8)
+            .collect();
+
+        Self {
+            values,
+            min,
+            max,
+            metadata: vector.metadata.clone(),
+            sparse: vector.sparse.clone(),
+        }
+    }
+
+    /// Dequantize back into a (lossy) hot vector
+    ///
+    /// v ≈ min + q / 255 * (max - min)
+    pub fn decode(&self) -> Vector {
+        let step = (self.max - self.min) / 255.0;
+        let data = self
+            .values
+            .iter()
+            .map(|&q| self.min + q as f32 * step)
+            .collect();
+        Vector {
+            sparse: self.sparse.clone(),
+            ..Vector::with_metadata(data, self.metadata.clone())
+        }
+    }
+
+    /// Dimensionality of the encoded vector
+    pub fn dimension(&self) -> usize {
+        self.values.len()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COLD TIER
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The cold tier plus the access bookkeeping that decides what goes into it.
+///
+/// The hot tier itself is owned by the caller (`AppState.vectors`); the cold
+/// tier only needs to borrow it while sweeping or promoting.
+#[derive(Debug, Default)]
+pub struct ColdTier {
+    /// Demotion policy
+    pub policy: TieringPolicy,
+
+    /// Quantized vectors: id → cold vector
+    vectors: HashMap<String, ColdVector>,
+
+    /// Last time each hot vector was inserted, read, or returned by a search
+    last_access: HashMap<String, Instant>,
+}
+
+impl ColdTier {
+    /// Create an empty cold tier with the given policy
+    pub fn new(policy: TieringPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Record an access to `id` at `now`
+    pub fn touch(&mut self, id: &str, now: Instant) {
+        self.last_access.insert(id.to_string(), now);
+    }
+
+    /// Forget everything about `id` (it was overwritten or deleted)
+    pub fn remove(&mut self, id: &str) -> Option<ColdVector> {
+        self.last_access.remove(id);
+        self.vectors.remove(id)
+    }
+
+    /// Add an already-encoded vector (one read back from a cold segment)
+    pub fn insert(&mut self, id: String, vector: ColdVector) {
+        self.last_access.remove(&id);
+        self.vectors.insert(id, vector);
+    }
+
+    /// Look up a cold vector without promoting it
+    pub fn get(&self, id: &str) -> Option<&ColdVector> {
+        self.vectors.get(id)
+    }
+
+    /// Move `id` from the cold tier back into `hot`.
+    ///
+    /// Returns `false` if the vector wasn't cold.
+    pub fn promote(&mut self, id: &str, hot: &mut HashMap<String, Vector>, now: Instant) -> bool {
+        match self.vectors.remove(id) {
+            Some(cold) => {
+                hot.insert(id.to_string(), cold.decode());
+                self.touch(id, now);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Demote every hot vector that has gone cold according to the policy.
+    ///
+    /// Vectors with no recorded access are treated as accessed at `now`, so
+    /// they get a full `cold_after` window before they can be demoted.
+    /// Returns the number of vectors demoted.
+    pub fn sweep(
+        &mut self,
+        hot: &mut HashMap<String, Vector>,
+        access: &AccessTracker,
+        now: Instant,
+    ) -> usize {
+        let _span = tracing::info_span!("tiering.sweep", hot = hot.len()).entered();
+        let policy = self.policy;
+
+        // 1. Age: anything not touched within the window
+        let mut cold_ids: Vec<String> = hot
+            .keys()
+            .filter(|id| {
+                let last = *self.last_access.entry((*id).clone()).or_insert(now);
+                policy.is_cold(last, now)
+            })
+            .cloned()
+            .collect();
+
+        // 2. Frequency: trim the least-read vectors until under the cap
+        if let Some(max_hot) = policy.max_hot_vectors {
+            let remaining = hot.len() - cold_ids.len();
+            if remaining > max_hot {
+                let mut by_frequency: Vec<(u32, &String)> = hot
+                    .keys()
+                    .filter(|id| !cold_ids.contains(id))
+                    .map(|id| (access.frequency(id), id))
+                    .collect();
+                by_frequency.sort();
+                let excess: Vec<String> = by_frequency
+                    .into_iter()
+                    .take(remaining - max_hot)
+                    .map(|(_, id)| id.clone())
+                    .collect();
+                cold_ids.extend(excess);
+            }
+        }
+
+        for id in &cold_ids {
+            if let Some(vector) = hot.remove(id) {
+                self.vectors.insert(id.clone(), ColdVector::encode(&vector));
+                self.last_access.remove(id);
+            }
+        }
+
+        cold_ids.len()
+    }
+
+    /// Iterate over all cold vectors
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ColdVector)> {
+        self.vectors.iter()
+    }
+
+    /// Number of vectors in the cold tier
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Is the cold tier empty?
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Write every cold vector to `store`, replacing the previous segment
+    pub async fn save(&self, store: &ObjectStore) -> io::Result<()> {
+        let bytes = encode_segment(self.vectors.iter())?;
+        store.put(COLD_SEGMENT, bytes).await
+    }
+
+    /// Add the vectors of the segment in `store` (if there is one);
+    /// returns how many were read
+    pub async fn load(&mut self, store: &ObjectStore) -> io::Result<usize> {
+        let Some(bytes) = store.get(COLD_SEGMENT).await? else {
+            return Ok(0);
+        };
+        let vectors = decode_segment(&bytes)?;
+        let count = vectors.len();
+        for (id, vector) in vectors {
+            self.insert(id, vector);
+        }
+        Ok(count)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COLD SEGMENT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Encode cold vectors as a segment (see the layout above)
+pub fn encode_segment<'a>(
+    vectors: impl Iterator<Item = (&'a String, &'a ColdVector)>,
+) -> io::Result<Vec<u8>> {
+    let mut vectors: Vec<_> = vectors.collect();
+    vectors.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut out = Vec::new();
+    out.extend(COLD_MAGIC);
+    out.extend(&COLD_VERSION.to_le_bytes());
+    out.extend(&(vectors.len() as u64).to_le_bytes());
+    for (id, vector) in vectors {
+        out.extend(&(id.len() as u32).to_le_bytes());
+        out.extend(id.as_bytes());
+        out.extend(&(vector.values.len() as u32).to_le_bytes());
+        out.extend(&vector.min.to_le_bytes());
+        out.extend(&vector.max.to_le_bytes());
+        out.extend(&vector.values);
+        segment::encode_metadata_entry(&mut out, &vector.metadata)?;
+        match &vector.sparse {
+            Some(sparse) => {
+                out.extend(&(sparse.indices.len() as u32 + 1).to_le_bytes());
+                for index in &sparse.indices {
+                    out.extend(&index.to_le_bytes());
+                }
+                for value in &sparse.values {
+                    out.extend(&value.to_le_bytes());
+                }
+            }
+            None => out.extend(&0u32.to_le_bytes()),
+        }
+    }
+    let crc = crc32fast::hash(&out);
+    out.extend(&crc.to_le_bytes());
+    Ok(out)
+}
+
+/// Decode a cold segment, checking its magic, version and checksum
+pub fn decode_segment(bytes: &[u8]) -> io::Result<Vec<(String, ColdVector)>> {
+    let Some((body, crc)) = bytes.len().checked_sub(4).map(|at| bytes.split_at(at)) else {
+        return Err(segment::corrupted("cold segment is truncated".into()));
+    };
+    if crc32fast::hash(body).to_le_bytes() != crc {
+        return Err(segment::corrupted("cold segment checksum mismatch".into()));
+    }
+    let mut r = body;
+    if take(&mut r, 4)? != COLD_MAGIC {
+        return Err(segment::corrupted("not a cold segment".into()));
+    }
+    let version = read_u32(&mut r)?;
+    if version != COLD_VERSION {
+        return Err(segment::corrupted(format!(
+            "unsupported cold segment version {}",
+            version
+        )));
+    }
+    let count = u64::from_le_bytes(take(&mut r, 8)?.try_into().unwrap());
+
+    let mut vectors = Vec::new();
+    for _ in 0..count {
+        let id_len = read_u32(&mut r)? as usize;
+        let id = String::from_utf8(take(&mut r, id_len)?.to_vec())
+            .map_err(|e| segment::corrupted(e.to_string()))?;
+        let dimension = read_u32(&mut r)? as usize;
+        let min = f32::from_bits(read_u32(&mut r)?);
+        let max = f32::from_bits(read_u32(&mut r)?);
+        let values = take(&mut r, dimension)?.to_vec();
+        let metadata = segment::read_metadata_entry(&mut r)?;
+        let sparse = match read_u32(&mut r)? {
+            0 => None,
+            n => {
+                let nnz = n as usize - 1;
+                if r.len() / 8 < nnz {
+                    return Err(segment::corrupted("cold segment is truncated".into()));
+                }
+                let indices = (0..nnz)
+                    .map(|_| read_u32(&mut r))
+                    .collect::<io::Result<_>>()?;
+                let values = (0..nnz)
+                    .map(|_| read_u32(&mut r).map(f32::from_bits))
+                    .collect::<io::Result<_>>()?;
+                Some(SparseVector::new(indices, values))
+            }
+        };
+        vectors.push((
+            id,
+            ColdVector {
+                values,
+                min,
+                max,
+                metadata,
+                sparse,
+            },
+        ));
+    }
+    if !r.is_empty() {
+        return Err(segment::corrupted(format!(
+            "{} bytes after the last cold vector",
+            r.len()
+        )));
+    }
+    Ok(vectors)
+}
+
+fn take<'a>(r: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if r.len() < n {
+        return Err(segment::corrupted("cold segment is truncated".into()));
+    }
+    let (head, rest) = r.split_at(n);
+    *r = rest;
+    Ok(head)
+}
+
+fn read_u32(r: &mut &[u8]) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(take(r, 4)?.try_into().unwrap()))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn test_cold_vector_round_trip() {
+        let v = Vector::new(vec![-1.0, 0.0, 0.5, 1.0]);
+        let cold = ColdVector::encode(&v);
+        assert_eq!(cold.dimension(), 4);
+        assert_eq!(cold.values[0], 0);
+        assert_eq!(cold.values[3], 255);
+
+        let back = cold.decode();
+        for (a, b) in v.data.iter().zip(&back.data) {
+            assert!((a - b).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_cold_vector_constant_input() {
+        let cold = ColdVector::encode(&Vector::new(vec![0.5, 0.5, 0.5]));
+        assert_eq!(cold.decode().data, vec![0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_policy_is_cold() {
+        let policy = TieringPolicy::after_days(7);
+        let start = Instant::now();
+        assert!(!policy.is_cold(start, start + 6 * DAY));
+        assert!(policy.is_cold(start, start + 7 * DAY));
+    }
+
+    #[test]
+    fn test_sweep_demotes_only_stale_vectors() {
+        let mut hot = HashMap::new();
+        hot.insert("old".to_string(), Vector::new(vec![1.0, 0.0]));
+        hot.insert("new".to_string(), Vector::new(vec![0.0, 1.0]));
+
+        let start = Instant::now();
+        let mut tier = ColdTier::new(TieringPolicy::after_days(7));
+        tier.touch("old", start);
+        tier.touch("new", start + 5 * DAY);
+
+        let demoted = tier.sweep(&mut hot, &AccessTracker::default(), start + 8 * DAY);
+        assert_eq!(demoted, 1);
+        assert!(hot.contains_key("new"));
+        assert!(tier.get("old").is_some());
+        assert_eq!(tier.len(), 1);
+    }
+
+    #[test]
+    fn test_promote_moves_vector_back_to_hot() {
+        let mut hot = HashMap::new();
+        hot.insert("a".to_string(), Vector::new(vec![1.0, 2.0]));
+
+        let start = Instant::now();
+        let mut tier = ColdTier::new(TieringPolicy::after_days(1));
+        tier.touch("a", start);
+        tier.sweep(&mut hot, &AccessTracker::default(), start + 2 * DAY);
+        assert!(hot.is_empty());
+
+        assert!(tier.promote("a", &mut hot, start + 3 * DAY));
+        assert!(tier.is_empty());
+        assert!(hot.contains_key("a"));
+        assert!(!tier.promote("a", &mut hot, start + 3 * DAY));
+    }
+
+    #[test]
+    fn test_sweep_caps_hot_tier_by_frequency() {
+        let mut hot = HashMap::new();
+        for id in ["a", "b", "c"] {
+            hot.insert(id.to_string(), Vector::new(vec![1.0, 0.0]));
+        }
+
+        let mut access = AccessTracker::default();
+        access.record("a", "hot");
+        access.record("a", "hot");
+        access.record("b", "hot");
+
+        let mut tier = ColdTier::new(TieringPolicy {
+            max_hot_vectors: Some(2),
+            ..TieringPolicy::default()
+        });
+        let demoted = tier.sweep(&mut hot, &access, Instant::now());
+
+        assert_eq!(demoted, 1);
+        assert!(tier.get("c").is_some());
+        assert!(hot.contains_key("a") && hot.contains_key("b"));
+    }
+
+    #[test]
+    fn test_cold_segment_round_trip() {
+        let mut tier = ColdTier::default();
+        let mut v = Vector::with_metadata(
+            vec![0.25, -1.0, 3.0],
+            HashMap::from([("lang".to_string(), "en".to_string())]),
+        );
+        v.sparse = Some(SparseVector::new(vec![2, 9], vec![0.5, 1.5]));
+        tier.insert("b".into(), ColdVector::encode(&v));
+        tier.insert("a".into(), ColdVector::encode(&Vector::new(vec![1.0])));
+
+        let bytes = encode_segment(tier.iter()).unwrap();
+        assert_eq!(&bytes[..4], COLD_MAGIC);
+        let decoded = decode_segment(&bytes).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].0, "a");
+        let (id, cold) = &decoded[1];
+        assert_eq!(id, "b");
+        assert_eq!(cold.values, tier.get("b").unwrap().values);
+        assert_eq!(cold.decode().data, tier.get("b").unwrap().decode().data);
+        assert_eq!(cold.metadata["lang"], "en");
+        assert_eq!(cold.sparse, v.sparse);
+
+        // Encoding doesn't depend on the map's order
+        let mut again = ColdTier::default();
+        for (id, cold) in decoded {
+            again.insert(id, cold);
+        }
+        assert_eq!(encode_segment(again.iter()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_damaged_cold_segment_is_refused() {
+        let mut tier = ColdTier::default();
+        tier.insert("a".into(), ColdVector::encode(&Vector::new(vec![1.0, 2.0])));
+        let mut bytes = encode_segment(tier.iter()).unwrap();
+
+        let last = bytes.len() - 5;
+        bytes[last] ^= 0xff;
+        let err = decode_segment(&bytes).unwrap_err();
+        assert!(segment::is_corruption(&err), "{}", err);
+        assert!(decode_segment(&bytes[..3]).is_err());
+        assert!(decode_segment(&[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_keep_vectors_cold() {
+        use crate::storage::fs::MemStorage;
+        use std::sync::Arc;
+
+        let store = ObjectStore::local(Arc::new(MemStorage::new()), "/data");
+        let mut tier = ColdTier::default();
+        assert_eq!(tier.load(&store).await.unwrap(), 0);
+
+        let mut hot = HashMap::new();
+        hot.insert("a".to_string(), Vector::new(vec![1.0, 2.0]));
+        let start = Instant::now();
+        tier.touch("a", start);
+        tier.sweep(&mut hot, &AccessTracker::default(), start + 31 * DAY);
+        tier.save(&store).await.unwrap();
+
+        let mut restored = ColdTier::default();
+        assert_eq!(restored.load(&store).await.unwrap(), 1);
+        assert_eq!(
+            restored.get("a").unwrap().values,
+            tier.get("a").unwrap().values
+        );
+    }
+}
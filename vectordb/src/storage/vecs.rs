@@ -0,0 +1,435 @@
+// src/storage/vecs.rs
+//
+// Converting between segments and the TEXMEX vector formats.
+//
+// The standard ANN benchmark datasets (SIFT1M, GIST1M, SIFT1B/BIGANN, ...)
+// ship as .fvecs/.bvecs/.ivecs files: a bare sequence of records, each the
+// vector's dimension followed by its components, all little-endian:
+//
+//   ┌──────────────┬──────────────────────────────────┐
+//   │ dimension i32│ d components                     │  × count
+//   └──────────────┴──────────────────────────────────┘
+//     .fvecs  f32 components   (base and query vectors)
+//     .bvecs  u8 components    (SIFT1B)
+//     .ivecs  i32 components   (ground truth: neighbour indices)
+//
+// There's no header, so the count is the file size divided by the record
+// size, which has to come out exact. Every record must repeat the first
+// record's dimension.
+//
+// Converting to a segment gives vector i the ID "i", so the indices in a
+// ground-truth .ivecs file are the IDs search results come back with.
+// Converting from a segment writes components as they decode (int8
+// segments come out dequantized); .bvecs and .ivecs only take integral
+// values in range, and anything else is an error rather than a silent
+// rounding. Both directions stream a chunk at a time and write to
+// `<output>.tmp` before renaming it into place, like compaction.
+
+use crate::storage::compaction::tmp_path;
+use crate::storage::norms::{self, NormBlocks};
+use crate::storage::segment::{self, Decoder, StreamingWriter};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Vectors converted per read
+const CHUNK: u64 = 4096;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// FORMAT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Component type of a TEXMEX vector file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VecsFormat {
+    Fvecs,
+    Bvecs,
+    Ivecs,
+}
+
+impl VecsFormat {
+    /// Format named by a path's extension (.fvecs, .bvecs, .ivecs)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "fvecs" => Some(Self::Fvecs),
+            "bvecs" => Some(Self::Bvecs),
+            "ivecs" => Some(Self::Ivecs),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fvecs => "fvecs",
+            Self::Bvecs => "bvecs",
+            Self::Ivecs => "ivecs",
+        }
+    }
+
+    /// Bytes per component
+    pub fn component_size(self) -> usize {
+        match self {
+            Self::Bvecs => 1,
+            Self::Fvecs | Self::Ivecs => 4,
+        }
+    }
+
+    /// Bytes per record of `dimension` components, prefix included
+    pub fn record_size(self, dimension: usize) -> u64 {
+        4 + (dimension * self.component_size()) as u64
+    }
+
+    fn decode(self, bytes: &[u8], out: &mut Vec<f32>) {
+        out.clear();
+        match self {
+            Self::Fvecs => out.extend(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            ),
+            Self::Bvecs => out.extend(bytes.iter().map(|&b| b as f32)),
+            Self::Ivecs => out.extend(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32),
+            ),
+        }
+    }
+
+    /// Append `data` as one record; `index` is only for the error message
+    fn encode(self, index: u64, data: &[f32], out: &mut Vec<u8>) -> io::Result<()> {
+        out.extend(&(data.len() as i32).to_le_bytes());
+        for (j, &x) in data.iter().enumerate() {
+            let unrepresentable = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "vector {} component {} is {}, which {} can't hold",
+                        index,
+                        j,
+                        x,
+                        self.name()
+                    ),
+                )
+            };
+            match self {
+                Self::Fvecs => out.extend(&x.to_le_bytes()),
+                Self::Bvecs => {
+                    if x.fract() != 0.0 || !(0.0..=255.0).contains(&x) {
+                        return Err(unrepresentable());
+                    }
+                    out.push(x as u8);
+                }
+                Self::Ivecs => {
+                    if x.fract() != 0.0 || !(i32::MIN as f32..=i32::MAX as f32).contains(&x) {
+                        return Err(unrepresentable());
+                    }
+                    out.extend(&(x as i32).to_le_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Vector count and dimension of a TEXMEX file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VecsShape {
+    pub count: u64,
+    pub dimension: usize,
+}
+
+/// Read the first record's dimension and derive the count from the file
+/// size. An empty file is zero vectors of dimension 0.
+pub fn vecs_shape(path: &Path, format: VecsFormat) -> io::Result<VecsShape> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(VecsShape {
+            count: 0,
+            dimension: 0,
+        });
+    }
+    let mut prefix = [0u8; 4];
+    file.read_exact(&mut prefix)?;
+    let dimension = i32::from_le_bytes(prefix);
+    if dimension <= 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} starts with dimension {}", path.display(), dimension),
+        ));
+    }
+    let dimension = dimension as usize;
+    let record = format.record_size(dimension);
+    if len % record != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is {} bytes, not a whole number of {}-byte {} records (dimension {})",
+                path.display(),
+                len,
+                record,
+                format.name(),
+                dimension
+            ),
+        ));
+    }
+    Ok(VecsShape {
+        count: len / record,
+        dimension,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CONVERSION
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a conversion wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertReport {
+    pub vectors: u64,
+    pub dimension: usize,
+    /// Size of the output file
+    pub bytes: u64,
+}
+
+/// Write the first `limit` vectors (all of them if None) of a TEXMEX file
+/// as a segment, vector i with ID "i"
+pub fn vecs_to_segment(
+    input: &Path,
+    format: VecsFormat,
+    output: &Path,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let shape = vecs_shape(input, format)?;
+    let count = limit.map_or(shape.count, |l| l.min(shape.count));
+    let ids: Vec<String> = (0..count).map(|i| i.to_string()).collect();
+
+    let tmp = tmp_path(output);
+    let result = (|| {
+        let mut r = BufReader::new(File::open(input)?);
+        let mut w = StreamingWriter::new(
+            BufWriter::new(File::create(&tmp)?),
+            count,
+            shape.dimension as u32,
+            0,
+            vec![
+                segment::id_table_section(&ids),
+                NormBlocks::placeholder(count, norms::DEFAULT_BLOCK_SIZE),
+            ],
+        )?;
+        let mut record = vec![0u8; shape.dimension * format.component_size()];
+        let mut vector = Vec::with_capacity(shape.dimension);
+        for index in 0..count {
+            let mut prefix = [0u8; 4];
+            r.read_exact(&mut prefix)?;
+            let dimension = i32::from_le_bytes(prefix);
+            if dimension as usize != shape.dimension {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "vector {} has dimension {}, the first has {}",
+                        index, dimension, shape.dimension
+                    ),
+                ));
+            }
+            r.read_exact(&mut record)?;
+            format.decode(&record, &mut vector);
+            w.write_vector(&vector)?;
+        }
+        let mut file = w.finish(&[])?;
+        file.flush()?;
+        file.get_ref().sync_all()
+    })();
+    finish(result, &tmp, output, count, shape.dimension)
+}
+
+/// Write the first `limit` vectors (all of them if None) of a segment as a
+/// TEXMEX file. IDs and metadata are left behind.
+pub fn segment_to_vecs(
+    input: &Path,
+    output: &Path,
+    format: VecsFormat,
+    limit: Option<u64>,
+) -> io::Result<ConvertReport> {
+    let header = segment::verify_segment(input)?;
+    let count = limit.map_or(header.count, |l| l.min(header.count));
+
+    let tmp = tmp_path(output);
+    let result = (|| {
+        let mut r = BufReader::new(File::open(input)?);
+        r.seek(SeekFrom::Start(header.data_offset() - header.params_size()))?;
+        let decoder = Decoder::read(&mut r, &header)?;
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        let mut buf = Vec::new();
+        let mut index = 0;
+        while index < count {
+            let n = CHUNK.min(count - index);
+            buf.clear();
+            for vector in decoder.read_vectors(&mut r, n)? {
+                format.encode(index, &vector.data, &mut buf)?;
+                index += 1;
+            }
+            w.write_all(&buf)?;
+        }
+        w.flush()?;
+        w.get_ref().sync_all()
+    })();
+    finish(result, &tmp, output, count, header.dimension as usize)
+}
+
+/// Rename a successful conversion into place, or clean up a failed one
+pub(crate) fn finish(
+    result: io::Result<()>,
+    tmp: &Path,
+    output: &Path,
+    vectors: u64,
+    dimension: usize,
+) -> io::Result<ConvertReport> {
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(tmp);
+        return Err(e);
+    }
+    std::fs::rename(tmp, output)?;
+    Ok(ConvertReport {
+        vectors,
+        dimension,
+        bytes: std::fs::metadata(output)?.len(),
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("vectordb_vecs_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write_vecs(path: &Path, format: VecsFormat, vectors: &[Vec<f32>]) {
+        let mut bytes = Vec::new();
+        for (i, v) in vectors.iter().enumerate() {
+            format.encode(i as u64, v, &mut bytes).unwrap();
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_fvecs_round_trip() {
+        let dir = Scratch::new("round_trip");
+        let fvecs = dir.path().join("base.fvecs");
+        let vectors = vec![vec![0.5, -1.25, 3.0], vec![1e-3, 2.0, -0.0]];
+        write_vecs(&fvecs, VecsFormat::Fvecs, &vectors);
+
+        let seg = dir.path().join("base.vec");
+        let report = vecs_to_segment(&fvecs, VecsFormat::Fvecs, &seg, None).unwrap();
+        assert_eq!((report.vectors, report.dimension), (2, 3));
+        let read = segment::read_segment(&seg).unwrap();
+        assert_eq!(read[1].data, vectors[1]);
+        let mut file = File::open(&seg).unwrap();
+        let header = segment::read_segment_header(&seg).unwrap();
+        let ids = segment::read_ids(&mut file, &header).unwrap().unwrap();
+        assert_eq!(ids, ["0", "1"]);
+
+        let back = dir.path().join("back.fvecs");
+        segment_to_vecs(&seg, &back, VecsFormat::Fvecs, None).unwrap();
+        assert_eq!(
+            std::fs::read(&back).unwrap(),
+            std::fs::read(&fvecs).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bvecs_limit_and_range_check() {
+        let dir = Scratch::new("bvecs");
+        let bvecs = dir.path().join("base.bvecs");
+        write_vecs(
+            &bvecs,
+            VecsFormat::Bvecs,
+            &[vec![0.0, 255.0], vec![7.0, 8.0], vec![9.0, 10.0]],
+        );
+        assert_eq!(
+            vecs_shape(&bvecs, VecsFormat::Bvecs).unwrap(),
+            VecsShape {
+                count: 3,
+                dimension: 2
+            }
+        );
+
+        let seg = dir.path().join("base.vec");
+        let report = vecs_to_segment(&bvecs, VecsFormat::Bvecs, &seg, Some(2)).unwrap();
+        assert_eq!(report.vectors, 2);
+        let read = segment::read_segment(&seg).unwrap();
+        assert_eq!(read[0].data, vec![0.0, 255.0]);
+
+        // Fractional values don't fit bvecs, and nothing is left behind
+        let fractional = dir.path().join("frac.vec");
+        segment::write_segment(&fractional, &[crate::models::Vector::new(vec![0.5, 1.0])]).unwrap();
+        let out = dir.path().join("frac.bvecs");
+        let err = segment_to_vecs(&fractional, &out, VecsFormat::Bvecs, None).unwrap_err();
+        assert!(err.to_string().contains("component 0 is 0.5"), "{}", err);
+        assert!(!out.exists() && !tmp_path(&out).exists());
+    }
+
+    #[test]
+    fn test_rejects_truncated_and_mixed_dimensions() {
+        let dir = Scratch::new("truncated");
+        let path = dir.path().join("bad.ivecs");
+        let mut bytes = Vec::new();
+        VecsFormat::Ivecs
+            .encode(0, &[1.0, 2.0], &mut bytes)
+            .unwrap();
+        bytes.extend(&[0u8; 3]);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(vecs_shape(&path, VecsFormat::Ivecs).is_err());
+
+        // Two 12-byte records, but the second claims dimension 1
+        let mut bytes = Vec::new();
+        VecsFormat::Ivecs
+            .encode(0, &[1.0, 2.0], &mut bytes)
+            .unwrap();
+        VecsFormat::Ivecs.encode(1, &[3.0], &mut bytes).unwrap();
+        bytes.extend(&[0u8; 4]);
+        std::fs::write(&path, &bytes).unwrap();
+        let seg = dir.path().join("bad.vec");
+        let err = vecs_to_segment(&path, VecsFormat::Ivecs, &seg, None).unwrap_err();
+        assert!(err.to_string().contains("dimension 1"), "{}", err);
+        assert!(!seg.exists());
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            VecsFormat::from_path(Path::new("sift/sift_base.fvecs")),
+            Some(VecsFormat::Fvecs)
+        );
+        assert_eq!(
+            VecsFormat::from_path(Path::new("gt.ivecs")),
+            Some(VecsFormat::Ivecs)
+        );
+        assert_eq!(VecsFormat::from_path(Path::new("base.vec")), None);
+    }
+}
@@ -0,0 +1,1199 @@
+// src/storage/wal.rs
+//
+// Write-ahead log: every write is appended here before it's acknowledged,
+// so a crash between snapshots loses nothing that a client was told had
+// been stored.
+//
+// The log is a single append-only file of framed records (Post #8):
+//
+//   ┌──────────────┬──────────────┬──────────────────────┐
+//   │ CRC32 (4B)   │ Length (4B)  │ Payload (N bytes)    │
+//   └──────────────┴──────────────┴──────────────────────┘
+//
+// The CRC covers length + payload. On startup the log is read front to
+// back and replayed on top of the last snapshot; reading stops at the
+// first record that is incomplete or fails its checksum (a write torn by
+// the crash), and that tail is cut off so new records follow valid ones.
+//
+// Records appended together (a transaction, an atomic batch, a rename
+// with its alias updates) land or are lost together: they're preceded by
+// a group frame giving their count, and a group is only replayed when
+// every one of its records is intact. A group torn anywhere is cut off
+// from its group frame on, so replay never applies half of one.
+//
+// Records hold the full resulting state of a point (not a diff), so
+// replaying a record that the snapshot already contains is harmless. Once
+// a snapshot has been written the log is reset to empty.
+//
+// A memtable flush (memtable.rs) also makes part of the log redundant: the
+// point records of the collections it flushed, up to the sequence it was
+// sealed at. `drop_flushed` rewrites the log without them, putting a skip
+// frame where they were so the records that remain keep their numbers.
+//
+// How often the file is fsynced is a trade between latency and how much
+// can be lost in a power cut (see SyncPolicy).
+//
+// Every record gets a sequence number, one more than the record before
+// it. Writes return the sequence of their last record, and searches may
+// ask to wait until some sequence has been applied (read-after-write for
+// a client that wrote through another connection or replica). Numbers
+// keep growing across resets and restarts: `reset` saves the last one in
+// wal.seq next to the log, and `open` continues from it plus the records
+// replayed. A crash between the two only skips numbers, never reuses
+// them — except for records lost unsynced in a power cut, which were
+// never durable under any number.
+
+use crate::models::{
+    CollectionInfo, CreateAliasRequest, CreateTemplateRequest, SparseVector, Vector,
+};
+use crate::storage::fs::Storage;
+use crate::storage::snapshot;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// File name of the log inside the data directory
+pub const WAL_FILE: &str = "wal.log";
+
+/// Extension of the file holding the sequence the log was last reset at
+pub const SEQ_EXTENSION: &str = "seq";
+
+/// Bytes of framing before each payload (CRC + length)
+pub(crate) const HEADER_LEN: usize = 8;
+
+/// Largest payload accepted on replay; anything bigger is corruption
+pub(crate) const MAX_RECORD_LEN: usize = 256 * 1024 * 1024;
+
+pub(crate) const TAG_INSERT: u8 = 1;
+pub(crate) const TAG_DELETE: u8 = 2;
+pub(crate) const TAG_CREATE_COLLECTION: u8 = 3;
+pub(crate) const TAG_UPDATE_COLLECTION: u8 = 4;
+pub(crate) const TAG_RENAME_COLLECTION: u8 = 5;
+pub(crate) const TAG_TRASH_COLLECTION: u8 = 6;
+pub(crate) const TAG_RESTORE_COLLECTION: u8 = 7;
+pub(crate) const TAG_PURGE_COLLECTION: u8 = 8;
+/// Not a record: the frame before a group of records appended together
+pub(crate) const TAG_GROUP: u8 = 9;
+/// Not a record: stands in for this many records dropped by a flush
+pub(crate) const TAG_SKIP: u8 = 10;
+pub(crate) const TAG_SET_ALIAS: u8 = 11;
+pub(crate) const TAG_REMOVE_ALIAS: u8 = 12;
+pub(crate) const TAG_SET_TEMPLATE: u8 = 13;
+pub(crate) const TAG_REMOVE_TEMPLATE: u8 = 14;
+pub(crate) const TAG_ID_COUNTER: u8 = 15;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// RECORDS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// One logged write. `collection: None` is the flat /vectors store.
+#[derive(Debug, Clone)]
+pub enum WalRecord {
+    /// A point now has this vector and metadata
+    Insert {
+        collection: Option<String>,
+        id: String,
+        vector: Vector,
+    },
+    /// A point was removed
+    Delete {
+        collection: Option<String>,
+        id: String,
+    },
+    /// A collection was created with this configuration
+    CreateCollection(CollectionInfo),
+    /// A collection's runtime settings changed (see Collection::reconfigure)
+    UpdateCollection(CollectionInfo),
+    /// A collection now goes by `to`
+    RenameCollection { from: String, to: String },
+    /// A collection was moved to the trash at `deleted_at` (Unix seconds)
+    TrashCollection { name: String, deleted_at: u64 },
+    /// A trashed collection was put back
+    RestoreCollection(String),
+    /// A trashed collection was purged ahead of its retention window
+    PurgeCollection(String),
+    /// An alias was created or re-pointed with this configuration
+    SetAlias(CreateAliasRequest),
+    /// An alias was deleted
+    RemoveAlias(String),
+    /// A query template was registered or replaced with this configuration
+    SetTemplate {
+        name: String,
+        config: CreateTemplateRequest,
+    },
+    /// A query template was deleted
+    RemoveTemplate(String),
+    /// An auto-increment collection has handed out every ID below `next`
+    /// (kept apart from the inserts, which a delete or flush may outlive)
+    IdCounter { collection: String, next: u64 },
+}
+
+impl WalRecord {
+    /// `id` in `collection` now holds `vector`
+    pub fn insert(collection: Option<&str>, id: &str, vector: &Vector) -> Self {
+        WalRecord::Insert {
+            collection: collection.map(String::from),
+            id: id.to_string(),
+            vector: vector.clone(),
+        }
+    }
+
+    /// `id` was removed from `collection`
+    pub fn delete(collection: Option<&str>, id: &str) -> Self {
+        WalRecord::Delete {
+            collection: collection.map(String::from),
+            id: id.to_string(),
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            WalRecord::Insert {
+                collection,
+                id,
+                vector,
+            } => {
+                buf.push(TAG_INSERT);
+                put_collection(buf, collection);
+                put_str(buf, id);
+                buf.extend(&(vector.data.len() as u32).to_le_bytes());
+                for &x in &vector.data {
+                    buf.extend(&x.to_le_bytes());
+                }
+                buf.extend(&(vector.metadata.len() as u32).to_le_bytes());
+                for (key, value) in &vector.metadata {
+                    put_str(buf, key);
+                    put_str(buf, value);
+                }
+                // Optional tail, so records without one read as before
+                if let Some(sparse) = &vector.sparse {
+                    buf.extend(&(sparse.nnz() as u32).to_le_bytes());
+                    for &i in &sparse.indices {
+                        buf.extend(&i.to_le_bytes());
+                    }
+                    for &x in &sparse.values {
+                        buf.extend(&x.to_le_bytes());
+                    }
+                }
+            }
+            WalRecord::Delete { collection, id } => {
+                buf.push(TAG_DELETE);
+                put_collection(buf, collection);
+                put_str(buf, id);
+            }
+            WalRecord::CreateCollection(info) | WalRecord::UpdateCollection(info) => {
+                buf.push(match self {
+                    WalRecord::CreateCollection(_) => TAG_CREATE_COLLECTION,
+                    _ => TAG_UPDATE_COLLECTION,
+                });
+                // CollectionInfo grows with features; JSON keeps old logs readable
+                let json = serde_json::to_vec(info).expect("CollectionInfo serializes");
+                put_bytes(buf, &json);
+            }
+            WalRecord::RenameCollection { from, to } => {
+                buf.push(TAG_RENAME_COLLECTION);
+                put_str(buf, from);
+                put_str(buf, to);
+            }
+            WalRecord::TrashCollection { name, deleted_at } => {
+                buf.push(TAG_TRASH_COLLECTION);
+                put_str(buf, name);
+                buf.extend(&deleted_at.to_le_bytes());
+            }
+            WalRecord::RestoreCollection(name) => {
+                buf.push(TAG_RESTORE_COLLECTION);
+                put_str(buf, name);
+            }
+            WalRecord::PurgeCollection(name) => {
+                buf.push(TAG_PURGE_COLLECTION);
+                put_str(buf, name);
+            }
+            WalRecord::SetAlias(alias) => {
+                buf.push(TAG_SET_ALIAS);
+                let json = serde_json::to_vec(alias).expect("alias config serializes");
+                put_bytes(buf, &json);
+            }
+            WalRecord::RemoveAlias(name) => {
+                buf.push(TAG_REMOVE_ALIAS);
+                put_str(buf, name);
+            }
+            WalRecord::SetTemplate { name, config } => {
+                buf.push(TAG_SET_TEMPLATE);
+                put_str(buf, name);
+                let json = serde_json::to_vec(config).expect("template config serializes");
+                put_bytes(buf, &json);
+            }
+            WalRecord::RemoveTemplate(name) => {
+                buf.push(TAG_REMOVE_TEMPLATE);
+                put_str(buf, name);
+            }
+            WalRecord::IdCounter { collection, next } => {
+                buf.push(TAG_ID_COUNTER);
+                put_str(buf, collection);
+                buf.extend(&next.to_le_bytes());
+            }
+        }
+    }
+
+    fn decode(payload: &[u8]) -> io::Result<Self> {
+        let mut r = Reader(payload);
+        let record = match r.u8()? {
+            TAG_INSERT => {
+                let collection = r.collection()?;
+                let id = r.string()?;
+                let dimension = r.u32()? as usize;
+                let mut data = Vec::with_capacity(dimension.min(r.0.len() / 4));
+                for _ in 0..dimension {
+                    data.push(f32::from_le_bytes(r.array()?));
+                }
+                let mut metadata = HashMap::new();
+                for _ in 0..r.u32()? {
+                    let key = r.string()?;
+                    metadata.insert(key, r.string()?);
+                }
+                let mut vector = Vector::with_metadata(data, metadata);
+                if !r.0.is_empty() {
+                    let nnz = r.u32()? as usize;
+                    let mut sparse = SparseVector::default();
+                    for _ in 0..nnz {
+                        sparse.indices.push(r.u32()?);
+                    }
+                    for _ in 0..nnz {
+                        sparse.values.push(f32::from_le_bytes(r.array()?));
+                    }
+                    vector.sparse = Some(sparse);
+                }
+                WalRecord::Insert {
+                    collection,
+                    id,
+                    vector,
+                }
+            }
+            TAG_DELETE => WalRecord::Delete {
+                collection: r.collection()?,
+                id: r.string()?,
+            },
+            tag @ (TAG_CREATE_COLLECTION | TAG_UPDATE_COLLECTION) => {
+                let info = serde_json::from_slice(r.bytes()?)
+                    .map_err(|e| invalid_data(format!("bad collection config: {}", e)))?;
+                if tag == TAG_CREATE_COLLECTION {
+                    WalRecord::CreateCollection(info)
+                } else {
+                    WalRecord::UpdateCollection(info)
+                }
+            }
+            TAG_RENAME_COLLECTION => WalRecord::RenameCollection {
+                from: r.string()?,
+                to: r.string()?,
+            },
+            TAG_TRASH_COLLECTION => WalRecord::TrashCollection {
+                name: r.string()?,
+                deleted_at: u64::from_le_bytes(r.array()?),
+            },
+            TAG_RESTORE_COLLECTION => WalRecord::RestoreCollection(r.string()?),
+            TAG_PURGE_COLLECTION => WalRecord::PurgeCollection(r.string()?),
+            TAG_SET_ALIAS => WalRecord::SetAlias(
+                serde_json::from_slice(r.bytes()?)
+                    .map_err(|e| invalid_data(format!("bad alias config: {}", e)))?,
+            ),
+            TAG_REMOVE_ALIAS => WalRecord::RemoveAlias(r.string()?),
+            TAG_SET_TEMPLATE => WalRecord::SetTemplate {
+                name: r.string()?,
+                config: serde_json::from_slice(r.bytes()?)
+                    .map_err(|e| invalid_data(format!("bad template config: {}", e)))?,
+            },
+            TAG_REMOVE_TEMPLATE => WalRecord::RemoveTemplate(r.string()?),
+            TAG_ID_COUNTER => WalRecord::IdCounter {
+                collection: r.string()?,
+                next: u64::from_le_bytes(r.array()?),
+            },
+            tag => return Err(invalid_data(format!("unknown record tag {}", tag))),
+        };
+        if !r.0.is_empty() {
+            return Err(invalid_data(format!("{} trailing bytes", r.0.len())));
+        }
+        Ok(record)
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend(&(bytes.len() as u32).to_le_bytes());
+    buf.extend(bytes);
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_bytes(buf, s.as_bytes());
+}
+
+fn put_collection(buf: &mut Vec<u8>, collection: &Option<String>) {
+    match collection {
+        Some(name) => {
+            buf.push(1);
+            put_str(buf, name);
+        }
+        None => buf.push(0),
+    }
+}
+
+/// Cursor over a record payload
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid_data("record payload is truncated".into()));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|e| invalid_data(e.to_string()))
+    }
+
+    fn collection(&mut self) -> io::Result<Option<String>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.string().map(Some),
+        }
+    }
+}
+
+/// Frame `records` as CRC + length + payload, back to back; more than one
+/// record goes behind a group frame, so they replay all or not at all
+fn encode_frames(records: &[WalRecord]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut payload = Vec::new();
+    if records.len() > 1 {
+        payload.push(TAG_GROUP);
+        payload.extend(&(records.len() as u32).to_le_bytes());
+        put_frame(&mut out, &payload);
+    }
+    for record in records {
+        payload.clear();
+        record.encode(&mut payload);
+        put_frame(&mut out, &payload);
+    }
+    out
+}
+
+fn put_frame(out: &mut Vec<u8>, payload: &[u8]) {
+    let len = (payload.len() as u32).to_le_bytes();
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(&len);
+    crc.update(payload);
+    out.extend(&crc.finalize().to_le_bytes());
+    out.extend(&len);
+    out.extend(payload);
+}
+
+/// The payload of the frame at `pos` and the position after it, if the
+/// frame is complete and its checksum matches
+fn read_frame(bytes: &[u8], pos: usize) -> Option<(&[u8], usize)> {
+    if bytes.len() - pos < HEADER_LEN {
+        return None;
+    }
+    let stored = u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+    let len_bytes = &bytes[pos + 4..pos + HEADER_LEN];
+    let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    if len > MAX_RECORD_LEN || bytes.len() - pos - HEADER_LEN < len {
+        return None;
+    }
+    let payload = &bytes[pos + HEADER_LEN..pos + HEADER_LEN + len];
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(len_bytes);
+    crc.update(payload);
+    (crc.finalize() == stored).then_some((payload, pos + HEADER_LEN + len))
+}
+
+/// The size a group frame announces, if `payload` is one
+fn group_size(payload: &[u8]) -> Option<usize> {
+    match payload {
+        [TAG_GROUP, count @ ..] => Some(u32::from_le_bytes(count.try_into().ok()?) as usize),
+        _ => None,
+    }
+}
+
+/// The records a skip frame stands in for, if `payload` is one
+fn skip_count(payload: &[u8]) -> Option<u64> {
+    match payload {
+        [TAG_SKIP, count @ ..] => Some(u64::from_le_bytes(count.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn skip_frame(out: &mut Vec<u8>, count: u64) {
+    let mut payload = vec![TAG_SKIP];
+    payload.extend(&count.to_le_bytes());
+    put_frame(out, &payload);
+}
+
+/// What one append left in the log: its records (one, or a whole group),
+/// or a skip frame's count
+enum Unit {
+    Records(Vec<WalRecord>),
+    Skip(u64),
+}
+
+impl Unit {
+    /// Sequence numbers it takes up
+    fn len(&self) -> u64 {
+        match self {
+            Unit::Records(records) => records.len() as u64,
+            Unit::Skip(count) => *count,
+        }
+    }
+}
+
+/// The whole units at the front of `bytes`, each with the offset it ends
+/// at, stopping at the first bad frame (or group that isn't whole)
+fn units(bytes: &[u8]) -> Vec<(Unit, usize)> {
+    let mut units = Vec::new();
+    let mut pos = 0;
+    'frames: while let Some((payload, next)) = read_frame(bytes, pos) {
+        if let Some(count) = skip_count(payload) {
+            units.push((Unit::Skip(count), next));
+            pos = next;
+            continue;
+        }
+        let Some(count) = group_size(payload) else {
+            match WalRecord::decode(payload) {
+                Ok(record) => units.push((Unit::Records(vec![record]), next)),
+                Err(_) => break,
+            }
+            pos = next;
+            continue;
+        };
+        let mut group = Vec::with_capacity(count.min(bytes.len() / HEADER_LEN));
+        let mut end = next;
+        for _ in 0..count {
+            let Some((payload, next)) = read_frame(bytes, end) else {
+                break 'frames;
+            };
+            match WalRecord::decode(payload) {
+                Ok(record) => group.push(record),
+                Err(_) => break 'frames,
+            }
+            end = next;
+        }
+        units.push((Unit::Records(group), end));
+        pos = end;
+    }
+    units
+}
+
+/// Result of reading a log: the valid records, and how much of the file
+/// they cover
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// Each record with its sequence number, counted from the start of
+    /// the file (1 for the first)
+    pub records: Vec<(u64, WalRecord)>,
+    /// Sequence numbers the file takes up, records and skips together
+    pub sequences: u64,
+    /// Length of the valid prefix
+    pub valid_len: u64,
+    /// Bytes after the valid prefix (a torn or corrupt tail)
+    pub discarded: u64,
+}
+
+/// Read every valid record in `bytes`, stopping at the first bad one (or
+/// at the start of a group that isn't whole)
+pub fn decode(bytes: &[u8]) -> Replay {
+    let mut replay = Replay::default();
+    let mut pos = 0;
+    for (unit, end) in units(bytes) {
+        match unit {
+            Unit::Records(records) => {
+                for record in records {
+                    replay.sequences += 1;
+                    replay.records.push((replay.sequences, record));
+                }
+            }
+            Unit::Skip(count) => replay.sequences += count,
+        }
+        pos = end;
+    }
+    replay.valid_len = pos as u64;
+    replay.discarded = (bytes.len() - pos) as u64;
+    replay
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SYNC POLICY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// When appended records are fsynced (the `wal_sync` setting, see
+/// config.rs)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SyncPolicy {
+    /// Before every write is acknowledged (nothing acknowledged is lost)
+    #[default]
+    Always,
+    /// After every N records (up to N-1 acknowledged records can be lost)
+    EveryN(u64),
+    /// At most this long after a record is written (group commit)
+    Periodic(Duration),
+    /// Leave it to the OS (survives a process crash, not a power cut)
+    Never,
+}
+
+impl SyncPolicy {
+    /// Parse `always`, `never`, `every:<records>` or `interval:<ms>`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let number = |n: &str| {
+            n.parse::<u64>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("'{}' is not a positive number", n))
+        };
+        match s.split_once(':') {
+            None if s == "always" => Ok(SyncPolicy::Always),
+            None if s == "never" => Ok(SyncPolicy::Never),
+            Some(("every", n)) => Ok(SyncPolicy::EveryN(number(n)?)),
+            Some(("interval", ms)) => Ok(SyncPolicy::Periodic(Duration::from_millis(number(ms)?))),
+            _ => Err(format!(
+                "unknown WAL sync policy '{}' (expected always, never, every:<records> or interval:<ms>)",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for SyncPolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        Self::parse(&s)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// WRITE-AHEAD LOG
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug)]
+struct SyncState {
+    /// Records appended since the last fsync
+    unsynced: u64,
+    last_sync: Instant,
+    /// Records in the file (since the last reset)
+    records: u64,
+    bytes: u64,
+    /// Sequence the log was last reset at
+    base: u64,
+}
+
+/// Where the sequence a log was last reset at is saved
+fn seq_path(path: &Path) -> PathBuf {
+    path.with_extension(SEQ_EXTENSION)
+}
+
+/// The sequence saved by the last reset (0 if the log never was)
+fn read_base_seq(storage: &dyn Storage, path: &Path) -> io::Result<u64> {
+    let path = seq_path(path);
+    match storage.read(&path) {
+        Ok(bytes) => std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or_else(|| invalid_data(format!("{} is not a sequence number", path.display()))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// The open log. Appends take `&self`, so the log can sit in shared state.
+#[derive(Debug)]
+pub struct Wal {
+    storage: Arc<dyn Storage>,
+    path: PathBuf,
+    policy: SyncPolicy,
+    state: Mutex<SyncState>,
+    /// Sequence of the last appended record
+    seq: watch::Sender<u64>,
+}
+
+impl Wal {
+    /// Open the log at `path` (creating it if needed) and return the
+    /// records to replay, each with its sequence number. A torn or corrupt
+    /// tail is cut off first.
+    pub fn open(
+        storage: Arc<dyn Storage>,
+        path: impl Into<PathBuf>,
+        policy: SyncPolicy,
+    ) -> io::Result<(Self, Vec<(u64, WalRecord)>)> {
+        let path = path.into();
+        let _span = tracing::info_span!("wal.replay", path = %path.display()).entered();
+        let bytes = match storage.read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let replay = decode(&bytes);
+        if replay.discarded > 0 || !storage.exists(&path) {
+            if replay.discarded > 0 {
+                tracing::warn!(
+                    "Discarding {} bytes of torn or corrupt records at the end of {}",
+                    replay.discarded,
+                    path.display()
+                );
+            }
+            storage.write(&path, &bytes[..replay.valid_len as usize])?;
+            storage.sync(&path)?;
+        }
+
+        let base = read_base_seq(storage.as_ref(), &path)?;
+        let seq = base + replay.sequences;
+        let records = replay
+            .records
+            .into_iter()
+            .map(|(seq, record)| (base + seq, record))
+            .collect::<Vec<_>>();
+        let wal = Self {
+            seq: watch::Sender::new(seq),
+            storage,
+            path,
+            policy,
+            state: Mutex::new(SyncState {
+                unsynced: 0,
+                last_sync: Instant::now(),
+                records: records.len() as u64,
+                bytes: replay.valid_len,
+                base,
+            }),
+        };
+        Ok((wal, records))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn policy(&self) -> SyncPolicy {
+        self.policy
+    }
+
+    /// Sequence of the last appended record (0 before the first)
+    pub fn sequence(&self) -> u64 {
+        *self.seq.borrow()
+    }
+
+    /// Sequence the log was last reset at: every write up to it is in the
+    /// snapshot
+    pub fn base_sequence(&self) -> u64 {
+        self.state.lock().unwrap().base
+    }
+
+    /// Watch the sequence as records are appended
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.seq.subscribe()
+    }
+
+    /// Append `records` as one write, then fsync if the policy says so,
+    /// and return the sequence of the last one. When this returns Ok
+    /// under SyncPolicy::Always, they're durable.
+    pub fn append(&self, records: &[WalRecord]) -> io::Result<u64> {
+        if records.is_empty() {
+            return Ok(self.sequence());
+        }
+        let _span = tracing::info_span!("wal.append", records = records.len()).entered();
+        let frames = encode_frames(records);
+        let mut state = self.state.lock().unwrap();
+        self.storage.append(&self.path, &frames)?;
+        state.unsynced += records.len() as u64;
+        state.records += records.len() as u64;
+        state.bytes += frames.len() as u64;
+
+        let due = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::EveryN(n) => state.unsynced >= n,
+            SyncPolicy::Periodic(interval) => state.last_sync.elapsed() >= interval,
+            SyncPolicy::Never => false,
+        };
+        // Numbered as soon as they're in the file: if the sync fails
+        // they may still be replayed, so their numbers can't be reused
+        let seq = self.sequence() + records.len() as u64;
+        self.seq.send_replace(seq);
+        if due {
+            self.sync_locked(&mut state)?;
+        }
+        Ok(seq)
+    }
+
+    /// Fsync anything appended since the last sync
+    pub fn sync(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.unsynced > 0 {
+            self.sync_locked(&mut state)?;
+        }
+        Ok(())
+    }
+
+    fn sync_locked(&self, state: &mut SyncState) -> io::Result<()> {
+        let _span = tracing::info_span!("wal.sync", records = state.unsynced).entered();
+        self.storage.sync(&self.path)?;
+        state.unsynced = 0;
+        state.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Empty the log. Only call this once a snapshot holding every logged
+    /// write is durable.
+    pub fn reset(&self) -> io::Result<()> {
+        let _span = tracing::info_span!("wal.reset").entered();
+        let mut state = self.state.lock().unwrap();
+        snapshot::write_atomic(
+            self.storage.as_ref(),
+            &seq_path(&self.path),
+            self.sequence().to_string().as_bytes(),
+        )?;
+        self.storage.write(&self.path, &[])?;
+        self.sync_locked(&mut state)?;
+        state.records = 0;
+        state.bytes = 0;
+        state.base = self.sequence();
+        Ok(())
+    }
+
+    /// Rewrite the log without the point records that flushes sealed at
+    /// sequence `upto` now hold: those up to `upto` in the collections
+    /// named `flushed` (as they were named at `upto`). Dropped records
+    /// become skip frames, so every other record keeps its number. Appends
+    /// wait while this runs. Returns how many records were dropped.
+    pub fn drop_flushed(&self, upto: u64, flushed: &[&str]) -> io::Result<u64> {
+        let _span = tracing::info_span!("wal.drop_flushed", upto).entered();
+        let mut state = self.state.lock().unwrap();
+        let bytes = self.storage.read(&self.path)?;
+        let units = units(&bytes);
+
+        // The units up to `upto` are rewritten, the rest copied as they are
+        let mut seq = state.base;
+        let mut split = 0;
+        let mut head = Vec::new();
+        for (unit, end) in &units {
+            if seq + unit.len() > upto {
+                break;
+            }
+            seq += unit.len();
+            split = *end;
+            head.push(unit);
+        }
+        let mut numbered = Vec::new();
+        let mut seq = state.base;
+        for unit in &head {
+            match unit {
+                Unit::Records(records) => {
+                    for record in records {
+                        seq += 1;
+                        numbered.push((seq, record));
+                    }
+                }
+                Unit::Skip(count) => seq += count,
+            }
+        }
+        let covered = covered(&numbered, flushed);
+        let dropped = covered.iter().filter(|&&c| c).count() as u64;
+        if dropped == 0 {
+            return Ok(0);
+        }
+
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut skipped = 0;
+        let mut kept = 0;
+        let mut payload = Vec::new();
+        let mut covered = covered.into_iter();
+        for unit in head {
+            match unit {
+                Unit::Skip(count) => skipped += count,
+                Unit::Records(records) => {
+                    for record in records {
+                        if covered.next() == Some(true) {
+                            skipped += 1;
+                            continue;
+                        }
+                        if skipped > 0 {
+                            skip_frame(&mut out, skipped);
+                            skipped = 0;
+                        }
+                        payload.clear();
+                        record.encode(&mut payload);
+                        put_frame(&mut out, &payload);
+                        kept += 1;
+                    }
+                }
+            }
+        }
+        if skipped > 0 {
+            skip_frame(&mut out, skipped);
+        }
+        let tail: u64 = units
+            .iter()
+            .skip_while(|(_, end)| *end <= split)
+            .map(|(unit, _)| match unit {
+                Unit::Records(records) => records.len() as u64,
+                Unit::Skip(_) => 0,
+            })
+            .sum();
+        out.extend_from_slice(&bytes[split..]);
+
+        snapshot::write_atomic(self.storage.as_ref(), &self.path, &out)?;
+        state.records = kept + tail;
+        state.bytes = out.len() as u64;
+        state.unsynced = 0;
+        state.last_sync = Instant::now();
+        Ok(dropped)
+    }
+
+    /// Records and bytes in the log, for stats
+    pub fn size(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.records, state.bytes)
+    }
+}
+
+/// Which of `records` (all at or before the flushes) belong to a
+/// collection in `flushed`. Names are followed through renames, trash and
+/// restores, so a record counts for the collection it was written to even
+/// if that name has since gone to another one.
+fn covered(records: &[(u64, &WalRecord)], flushed: &[&str]) -> Vec<bool> {
+    // Each collection seen gets a number; `live` and `trash` map names to
+    // them as the records go by
+    let mut live: HashMap<&str, usize> = HashMap::new();
+    let mut trash: HashMap<&str, usize> = HashMap::new();
+    let mut next = 0;
+    let mut fresh = || {
+        next += 1;
+        next
+    };
+    let mut owners = Vec::with_capacity(records.len());
+    for (_, record) in records {
+        let mut owner = None;
+        match record {
+            WalRecord::Insert {
+                collection: Some(name),
+                ..
+            }
+            | WalRecord::Delete {
+                collection: Some(name),
+                ..
+            } => owner = Some(*live.entry(name).or_insert_with(&mut fresh)),
+            WalRecord::CreateCollection(info) => {
+                live.insert(&info.name, fresh());
+            }
+            WalRecord::RenameCollection { from, to } => {
+                let id = live.remove(from.as_str()).unwrap_or_else(&mut fresh);
+                live.insert(to, id);
+            }
+            WalRecord::TrashCollection { name, .. } => {
+                let id = live.remove(name.as_str()).unwrap_or_else(&mut fresh);
+                trash.insert(name, id);
+            }
+            WalRecord::RestoreCollection(name) => {
+                let id = trash.remove(name.as_str()).unwrap_or_else(&mut fresh);
+                live.insert(name, id);
+            }
+            WalRecord::PurgeCollection(name) => {
+                trash.remove(name.as_str());
+            }
+            WalRecord::UpdateCollection(_)
+            | WalRecord::SetAlias(_)
+            | WalRecord::RemoveAlias(_)
+            | WalRecord::SetTemplate { .. }
+            | WalRecord::RemoveTemplate(_)
+            | WalRecord::IdCounter { .. }
+            | WalRecord::Insert {
+                collection: None, ..
+            }
+            | WalRecord::Delete {
+                collection: None, ..
+            } => {}
+        }
+        owners.push(owner);
+    }
+    let flushed: Vec<usize> = flushed
+        .iter()
+        .filter_map(|n| live.get(n).copied())
+        .collect();
+    owners
+        .into_iter()
+        .map(|owner| owner.is_some_and(|id| flushed.contains(&id)))
+        .collect()
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::collection::Collection;
+    use crate::models::DistanceMetric;
+    use crate::storage::fs::{MemStorage, StorageOp};
+
+    fn insert(collection: Option<&str>, id: &str, data: Vec<f32>) -> WalRecord {
+        let mut vector = Vector::new(data);
+        vector.metadata.insert("lang".into(), "en".into());
+        WalRecord::Insert {
+            collection: collection.map(String::from),
+            id: id.into(),
+            vector,
+        }
+    }
+
+    #[test]
+    fn test_records_round_trip_and_survive_crash() {
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("data/wal.log");
+        let info = Collection::new("docs", 2, DistanceMetric::Cosine)
+            .unwrap()
+            .info();
+        let records = vec![
+            WalRecord::CreateCollection(info),
+            insert(Some("docs"), "a", vec![0.5, -1.0]),
+            insert(None, "flat", vec![1.0]),
+            WalRecord::insert(
+                Some("docs"),
+                "b",
+                &Vector::new(vec![1.0, 0.0])
+                    .with_sparse(SparseVector::new(vec![4, 9], vec![0.5, 2.0])),
+            ),
+            WalRecord::Delete {
+                collection: Some("docs".into()),
+                id: "a".into(),
+            },
+            WalRecord::RenameCollection {
+                from: "docs".into(),
+                to: "docs_v1".into(),
+            },
+            WalRecord::TrashCollection {
+                name: "docs_v1".into(),
+                deleted_at: 1_700_000_000,
+            },
+            WalRecord::RestoreCollection("docs_v1".into()),
+            WalRecord::SetAlias(CreateAliasRequest {
+                name: "docs".into(),
+                collection: "docs_v1".into(),
+                experiment: None,
+            }),
+            WalRecord::RemoveAlias("docs".into()),
+            WalRecord::SetTemplate {
+                name: "news".into(),
+                config: CreateTemplateRequest {
+                    collection: "docs_v1".into(),
+                    filter: Some(r#"category == "news""#.into()),
+                    top_k: 5,
+                    metric: Some(DistanceMetric::Dot),
+                    exact: true,
+                },
+            },
+            WalRecord::RemoveTemplate("news".into()),
+            WalRecord::IdCounter {
+                collection: "docs_v1".into(),
+                next: 42,
+            },
+        ];
+
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert!(replayed.is_empty());
+        wal.append(&records[..2]).unwrap();
+        wal.append(&records[2..]).unwrap();
+        assert_eq!(wal.size().0, 13);
+
+        storage.crash(); // every append was synced before returning
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        let numbered: Vec<(u64, &WalRecord)> = (1..).zip(&records).collect();
+        assert_eq!(format!("{:?}", replayed), format!("{:?}", numbered));
+
+        wal.reset().unwrap();
+        let (_, replayed) = Wal::open(storage, path, SyncPolicy::Always).unwrap();
+        assert!(replayed.is_empty());
+    }
+
+    #[test]
+    fn test_torn_tail_is_cut_off() {
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("wal.log");
+        let (wal, _) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        wal.append(&[insert(None, "a", vec![1.0])]).unwrap();
+        let good_len = storage.read(path).unwrap().len();
+
+        // Half a record (a crash mid-write), then a flipped bit
+        let second = encode_frames(&[insert(None, "b", vec![2.0])]);
+        storage.append(path, &second[..second.len() / 2]).unwrap();
+        let replay = decode(&storage.read(path).unwrap());
+        assert_eq!(
+            (replay.records.len(), replay.valid_len),
+            (1, good_len as u64)
+        );
+
+        let mut corrupt = second.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(decode(&corrupt).records.is_empty());
+
+        // Reopening truncates the tail so new records follow valid ones
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 1);
+        wal.append(&[insert(None, "c", vec![3.0])]).unwrap();
+        let (_, replayed) = Wal::open(storage, path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 2);
+    }
+
+    #[test]
+    fn test_groups_torn_anywhere_are_dropped_whole() {
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("wal.log");
+        let (wal, _) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        wal.append(&[insert(None, "a", vec![1.0])]).unwrap();
+        let good_len = storage.read(path).unwrap().len();
+        let batch = [
+            insert(None, "b", vec![2.0]),
+            insert(None, "c", vec![3.0]),
+            insert(None, "d", vec![4.0]),
+        ];
+        let group = encode_frames(&batch);
+
+        // A crash at any byte inside the group, even between two whole
+        // records, loses all of it
+        for cut in 1..group.len() {
+            let mut bytes = storage.read(path).unwrap();
+            bytes.extend_from_slice(&group[..cut]);
+            let replay = decode(&bytes);
+            assert_eq!(replay.records.len(), 1, "cut at {}", cut);
+            assert_eq!(replay.valid_len, good_len as u64, "cut at {}", cut);
+        }
+
+        // Written whole, it replays whole and numbers every record
+        let seq = wal.append(&batch).unwrap();
+        assert_eq!(seq, 4);
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 4);
+        assert_eq!(wal.sequence(), 4);
+
+        // Reopening cuts a torn group off so later appends aren't behind it
+        storage.append(path, &group[..group.len() - 3]).unwrap();
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 4);
+        wal.append(&[insert(None, "e", vec![5.0])]).unwrap();
+        let (_, replayed) = Wal::open(storage, path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 5);
+    }
+
+    #[test]
+    fn test_dropping_flushed_records_keeps_the_numbering() {
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("wal.log");
+        let (wal, _) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        let rename = |from: &str, to: &str| WalRecord::RenameCollection {
+            from: from.into(),
+            to: to.into(),
+        };
+        wal.append(&[insert(Some("docs"), "a", vec![1.0])]).unwrap();
+        wal.append(&[insert(None, "flat", vec![1.0])]).unwrap();
+        wal.append(&[
+            insert(Some("docs"), "b", vec![2.0]),
+            insert(Some("other"), "x", vec![3.0]),
+        ])
+        .unwrap();
+        wal.append(&[rename("docs", "old")]).unwrap();
+        wal.append(&[rename("other", "docs")]).unwrap();
+        wal.append(&[insert(Some("old"), "c", vec![4.0])]).unwrap();
+        let upto = wal.sequence();
+        // After the flushes were sealed: stays whatever they cover
+        wal.append(&[insert(Some("old"), "d", vec![5.0])]).unwrap();
+
+        // "old" at the flush is what "docs" was when a and b were written;
+        // x went to the collection that's called "docs" only later
+        assert_eq!(wal.drop_flushed(upto, &["old"]).unwrap(), 3);
+        assert_eq!(wal.size().0, 5);
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        let kept: Vec<(u64, &str)> = replayed
+            .iter()
+            .map(|(seq, record)| match record {
+                WalRecord::Insert { id, .. } => (*seq, id.as_str()),
+                WalRecord::RenameCollection { to, .. } => (*seq, to.as_str()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            kept,
+            [(2, "flat"), (4, "x"), (5, "old"), (6, "docs"), (8, "d")]
+        );
+        assert_eq!(wal.sequence(), 8);
+
+        // Nothing left to drop the second time
+        assert_eq!(wal.drop_flushed(upto, &["old"]).unwrap(), 0);
+        wal.append(&[insert(None, "e", vec![6.0])]).unwrap();
+        assert_eq!(wal.sequence(), 9);
+    }
+
+    #[test]
+    fn test_sync_policies() {
+        assert_eq!(SyncPolicy::parse("every:100"), Ok(SyncPolicy::EveryN(100)));
+        assert_eq!(
+            SyncPolicy::parse("interval:50"),
+            Ok(SyncPolicy::Periodic(Duration::from_millis(50)))
+        );
+        assert!(SyncPolicy::parse("every:0").is_err());
+        assert!(SyncPolicy::parse("sometimes").is_err());
+
+        // Unsynced records are lost in a crash; a failed fsync is an error
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("wal.log");
+        let (wal, _) = Wal::open(storage.clone(), path, SyncPolicy::EveryN(2)).unwrap();
+        wal.append(&[insert(None, "a", vec![1.0])]).unwrap();
+        storage.crash();
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert!(replayed.is_empty());
+
+        storage.fail_after(StorageOp::Sync, 0);
+        assert!(wal.append(&[insert(None, "a", vec![1.0])]).is_err());
+    }
+
+    #[test]
+    fn test_sequence_numbers_keep_growing_across_resets() {
+        let storage = Arc::new(MemStorage::new());
+        let path = Path::new("data/wal.log");
+        let (wal, _) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert_eq!(wal.sequence(), 0);
+        let mut applied = wal.subscribe();
+        assert_eq!(wal.append(&[insert(None, "a", vec![1.0])]).unwrap(), 1);
+        let two = [insert(None, "b", vec![2.0]), insert(None, "c", vec![3.0])];
+        assert_eq!(wal.append(&two).unwrap(), 3);
+        assert_eq!(wal.append(&[]).unwrap(), 3);
+        assert!(applied.has_changed().unwrap());
+        assert_eq!(*applied.borrow_and_update(), 3);
+
+        // Replayed records count from where the last reset left off
+        wal.reset().unwrap();
+        wal.append(&[insert(None, "d", vec![4.0])]).unwrap();
+        storage.crash();
+        let (wal, replayed) = Wal::open(storage.clone(), path, SyncPolicy::Always).unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(wal.sequence(), 4);
+        assert_eq!(wal.append(&[insert(None, "e", vec![5.0])]).unwrap(), 5);
+    }
+}
@@ -0,0 +1,20 @@
+// src/sync.rs
+//
+// Synchronization primitives used by shared engine state.
+//
+// Modules import Mutex and atomics from here instead of std::sync, so that
+// a `--cfg vectordb_loom` build swaps in loom's instrumented versions and
+// the loom tests (tests/loom.rs) can check every thread interleaving.
+// Normal builds get the std types with zero overhead.
+//
+// (Not plain `--cfg loom`: tokio reacts to that flag too.)
+
+#[cfg(vectordb_loom)]
+pub use loom::sync::atomic::{AtomicU64, Ordering};
+#[cfg(vectordb_loom)]
+pub use loom::sync::{Arc, Mutex};
+
+#[cfg(not(vectordb_loom))]
+pub use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(vectordb_loom))]
+pub use std::sync::{Arc, Mutex};
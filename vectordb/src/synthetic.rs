@@ -0,0 +1,273 @@
+// src/synthetic.rs
+//
+// Synthetic vector datasets, for benchmarking before real data exists.
+//
+//   gaussian    every coordinate drawn from N(0, 1): no structure at all,
+//               the worst case for any index (all points look alike)
+//   clustered   `clusters` centroids drawn from N(0, 1), each point a
+//               centroid plus N(0, spread²) noise per coordinate: closer to
+//               real embeddings, where topics form dense regions
+//
+// A dataset is a pure function of its spec. `vector(i)` draws point i from
+// its own seeded stream, so the same point can be regenerated later (to
+// compute exact neighbours after loading, say) without keeping millions of
+// vectors in memory, and two runs with the same seed see the same data.
+// Queries come from the same distribution on a separate stream, so they
+// land where the data is.
+
+use crate::models::{Result, VectorDbError};
+use serde::{Deserialize, Serialize};
+
+/// Shape of a synthetic dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Independent standard normal coordinates
+    Gaussian,
+    /// Noisy copies of random centroids
+    #[default]
+    Clustered,
+}
+
+impl std::str::FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "gaussian" => Ok(Self::Gaussian),
+            "clustered" => Ok(Self::Clustered),
+            other => Err(format!(
+                "unknown distribution '{}' (expected gaussian or clustered)",
+                other
+            )),
+        }
+    }
+}
+
+/// Everything that determines a dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetSpec {
+    pub dimension: usize,
+    pub count: u64,
+    pub distribution: Distribution,
+    /// Number of centroids (clustered only)
+    pub clusters: usize,
+    /// Standard deviation of the noise around a centroid, per coordinate,
+    /// relative to the centroids' own (clustered only). Small values give
+    /// tight, well-separated clusters; around 1.0 they start to merge.
+    pub spread: f32,
+    pub seed: u64,
+}
+
+impl Default for DatasetSpec {
+    fn default() -> Self {
+        Self {
+            dimension: 768,
+            count: 100_000,
+            distribution: Distribution::Clustered,
+            clusters: 100,
+            spread: 0.25,
+            seed: 42,
+        }
+    }
+}
+
+/// Stream tags, so data, queries, and centroids never share draws
+const DATA_STREAM: u64 = 0;
+const QUERY_STREAM: u64 = 1;
+const CENTROID_STREAM: u64 = 2;
+
+/// Deterministic generator for one dataset
+#[derive(Debug, Clone)]
+pub struct Generator {
+    spec: DatasetSpec,
+    centroids: Vec<Vec<f32>>,
+}
+
+impl Generator {
+    pub fn new(spec: DatasetSpec) -> Result<Self> {
+        crate::limits::check_dimension(spec.dimension)?;
+        if spec.distribution == Distribution::Clustered {
+            if spec.clusters == 0 {
+                return Err(VectorDbError::InvalidParameter(
+                    "clustered data needs at least 1 cluster".into(),
+                ));
+            }
+            if !(spec.spread.is_finite() && spec.spread >= 0.0) {
+                return Err(VectorDbError::InvalidParameter(format!(
+                    "spread must be a finite number >= 0, got {}",
+                    spec.spread
+                )));
+            }
+        }
+
+        let centroids = match spec.distribution {
+            Distribution::Gaussian => Vec::new(),
+            Distribution::Clustered => (0..spec.clusters as u64)
+                .map(|c| {
+                    let mut rng = Rng::stream(spec.seed, CENTROID_STREAM, c);
+                    (0..spec.dimension).map(|_| rng.normal()).collect()
+                })
+                .collect(),
+        };
+        Ok(Self { spec, centroids })
+    }
+
+    pub fn spec(&self) -> &DatasetSpec {
+        &self.spec
+    }
+
+    /// Data point `i` (any `i`, not just below `count`)
+    pub fn vector(&self, i: u64) -> Vec<f32> {
+        self.draw(Rng::stream(self.spec.seed, DATA_STREAM, i))
+    }
+
+    /// Cluster that data point `i` was drawn around (None for gaussian data)
+    pub fn cluster_of(&self, i: u64) -> Option<usize> {
+        if self.centroids.is_empty() {
+            return None;
+        }
+        let mut rng = Rng::stream(self.spec.seed, DATA_STREAM, i);
+        Some(rng.below(self.centroids.len() as u64) as usize)
+    }
+
+    /// Query `i`, from the data's distribution but a separate stream
+    pub fn query(&self, i: u64) -> Vec<f32> {
+        self.draw(Rng::stream(self.spec.seed, QUERY_STREAM, i))
+    }
+
+    fn draw(&self, mut rng: Rng) -> Vec<f32> {
+        if self.centroids.is_empty() {
+            return (0..self.spec.dimension).map(|_| rng.normal()).collect();
+        }
+        let centroid = &self.centroids[rng.below(self.centroids.len() as u64) as usize];
+        centroid
+            .iter()
+            .map(|c| c + self.spec.spread * rng.normal())
+            .collect()
+    }
+}
+
+/// SplitMix64: tiny, fast, and good enough for test data (and for k-means
+/// seeding in quantization). Not for secrets.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+    /// Second normal from the last Box-Muller draw
+    spare: Option<f32>,
+}
+
+impl Rng {
+    /// Independent stream `index` of kind `stream` under `seed`
+    pub(crate) fn stream(seed: u64, stream: u64, index: u64) -> Self {
+        let mut mix = Self {
+            state: seed ^ stream.wrapping_mul(0xD6E8_FEB8_6659_FD93),
+            spare: None,
+        };
+        let state = mix.next_u64() ^ index;
+        let mut rng = Self { state, spare: None };
+        rng.next_u64(); // decorrelate neighbouring indices
+        rng
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in (0, 1]
+    pub(crate) fn unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in 0..n (n > 0)
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+
+    /// Standard normal (Box-Muller)
+    pub(crate) fn normal(&mut self) -> f32 {
+        if let Some(z) = self.spare.take() {
+            return z;
+        }
+        let r = (-2.0 * self.unit().ln()).sqrt();
+        let theta = std::f64::consts::TAU * self.unit();
+        self.spare = Some((r * theta.sin()) as f32);
+        (r * theta.cos()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DistanceMetric;
+
+    fn spec(distribution: Distribution) -> DatasetSpec {
+        DatasetSpec {
+            dimension: 32,
+            count: 1000,
+            distribution,
+            clusters: 4,
+            spread: 0.1,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        let a = Generator::new(spec(Distribution::Clustered)).unwrap();
+        let b = Generator::new(spec(Distribution::Clustered)).unwrap();
+        assert_eq!(a.vector(17), b.vector(17));
+        assert_eq!(a.query(3), b.query(3));
+        assert_ne!(a.vector(17), a.vector(18));
+        assert_ne!(a.vector(3), a.query(3));
+
+        let other = Generator::new(DatasetSpec {
+            seed: 8,
+            ..spec(Distribution::Clustered)
+        })
+        .unwrap();
+        assert_ne!(a.vector(17), other.vector(17));
+    }
+
+    #[test]
+    fn test_gaussian_moments() {
+        let g = Generator::new(spec(Distribution::Gaussian)).unwrap();
+        let values: Vec<f32> = (0..500).flat_map(|i| g.vector(i)).collect();
+        let n = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+        assert!(mean.abs() < 0.05, "mean {}", mean);
+        assert!((variance - 1.0).abs() < 0.05, "variance {}", variance);
+        assert_eq!(g.cluster_of(0), None);
+    }
+
+    #[test]
+    fn test_clustered_points_sit_near_their_centroid() {
+        let g = Generator::new(spec(Distribution::Clustered)).unwrap();
+        let cosine = DistanceMetric::Cosine;
+        let (mut same, mut other) = (Vec::new(), Vec::new());
+        for i in 0..50 {
+            for j in (i + 1)..50 {
+                let score = cosine.calculate(&g.vector(i), &g.vector(j));
+                if g.cluster_of(i) == g.cluster_of(j) {
+                    same.push(score);
+                } else {
+                    other.push(score);
+                }
+            }
+        }
+        let min_same = same.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max_other = other.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        assert!(min_same > max_other, "{} <= {}", min_same, max_other);
+
+        assert!(Generator::new(DatasetSpec {
+            clusters: 0,
+            ..spec(Distribution::Clustered)
+        })
+        .is_err());
+    }
+}
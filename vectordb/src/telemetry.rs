@@ -0,0 +1,122 @@
+// src/telemetry.rs
+//
+// Logging and trace export.
+//
+// Storage work runs inside `tracing` spans, the same way HTTP requests do
+// (TraceLayer):
+//
+//   snapshot.save / snapshot.load   whole-state snapshots
+//   wal.append / wal.sync / wal.replay / wal.reset
+//   tiering.sweep                   demoting idle vectors to the cold tier
+//   compaction.merge                merging segments, dropping deleted vectors
+//   usage.flush                     writing daily usage rollups
+//   lock.wait                       a task queued for the state lock, with
+//                                   the operations holding it (blocked_by)
+//
+// Logs always go to stdout. A build with `--features otel` also exports
+// the spans over OTLP/HTTP when OTEL_EXPORTER_OTLP_ENDPOINT (or
+// OTEL_EXPORTER_OTLP_TRACES_ENDPOINT) is set. A search stuck behind a slow
+// snapshot then shows a `lock.wait` span naming the snapshot, next to the
+// snapshot's own trace.
+//
+// The exporter reads the standard OTEL_* variables (headers, timeout,
+// resource attributes). The service name defaults to "vectordb".
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Environment variables that turn on trace export
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+
+/// Keeps the trace exporter alive; call `shutdown` before exiting so
+/// buffered spans are sent
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Telemetry {
+    /// Flush buffered spans and stop the exporter
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!("Failed to flush trace exporter: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber: stdout logging, plus OTLP export when
+/// it's configured and compiled in
+pub fn init() -> Telemetry {
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .with_level(true)
+        .with_filter(LevelFilter::INFO);
+    let endpoint = ENDPOINT_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .filter(|e| !e.is_empty());
+
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = endpoint {
+        match otlp::provider() {
+            Ok(provider) => {
+                use opentelemetry::trace::TracerProvider as _;
+                let tracer = provider.tracer("vectordb");
+                tracing_subscriber::registry()
+                    .with(fmt)
+                    .with(
+                        tracing_opentelemetry::layer()
+                            .with_tracer(tracer)
+                            .with_filter(LevelFilter::INFO),
+                    )
+                    .init();
+                tracing::info!("Exporting traces over OTLP to {}", endpoint);
+                return Telemetry {
+                    provider: Some(provider),
+                };
+            }
+            Err(e) => {
+                tracing_subscriber::registry().with(fmt).init();
+                tracing::error!("Trace export disabled: {}", e);
+                return Telemetry::default();
+            }
+        }
+    }
+
+    tracing_subscriber::registry().with(fmt).init();
+    #[cfg(not(feature = "otel"))]
+    if endpoint.is_some() {
+        tracing::warn!("OTLP endpoint is set, but this build has no trace export (feature 'otel')");
+    }
+    Telemetry::default()
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::{runtime, Resource};
+
+    /// Batch exporter over OTLP/HTTP, configured from OTEL_* variables
+    pub fn provider() -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()?;
+        let service = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "vectordb".to_string());
+        let resource =
+            Resource::default().merge(&Resource::new([KeyValue::new("service.name", service)]));
+        Ok(TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(resource)
+            .build())
+    }
+}
@@ -0,0 +1,139 @@
+// src/tls.rs
+//
+// Mutual TLS for the admin listener.
+//
+// With an admin port (see src/config.rs), the admin endpoints and /metrics
+// get a listener of their own. An `[admin_tls]` table (or the matching
+// --admin-tls-* flags) puts that listener behind TLS and makes it ask for a
+// client certificate:
+//
+//   admin_port = 9090
+//
+//   [admin_tls]
+//   cert = "/etc/vectordb/admin.pem"      # server chain, leaf first
+//   key = "/etc/vectordb/admin.key"       # PKCS#8, PKCS#1 or SEC1
+//   client_ca = "/etc/vectordb/ops-ca.pem"
+//
+// A connection is served only once its certificate chains to one of the
+// CAs in `client_ca`. Without one, or with one from any other issuer, the
+// handshake fails before a request is read, so the admin key (if set) is a
+// second factor rather than the only one. The public listener stays plain
+// HTTP: it's expected to sit behind a load balancer that terminates TLS.
+//
+// Files are read once at startup; a missing or unparsable file stops the
+// server with its path in the message.
+
+use crate::models::{Result, VectorDbError};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_rustls::TlsAcceptor;
+
+/// How long a client gets to finish the handshake before it's dropped
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The admin listener's certificate and the CAs its clients must chain to
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MtlsConfig {
+    /// PEM certificate chain the listener presents
+    pub cert: PathBuf,
+    /// PEM private key for `cert`
+    pub key: PathBuf,
+    /// PEM bundle of the CAs client certificates must be issued by
+    pub client_ca: PathBuf,
+}
+
+impl MtlsConfig {
+    /// Read the files and build the rustls server config: client
+    /// certificates are required and checked against `client_ca`
+    pub fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let mut roots = RootCertStore::empty();
+        for ca in read_certs(&self.client_ca)? {
+            roots
+                .add(ca)
+                .map_err(|e| invalid(&self.client_ca, e.to_string()))?;
+        }
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .map_err(|e| invalid(&self.client_ca, e.to_string()))?;
+
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .map_err(|e| invalid(&self.key, e.to_string()))?;
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| VectorDbError::InvalidParameter(e.to_string()))?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(read_certs(&self.cert)?, key)
+            .map_err(|e| invalid(&self.cert, e.to_string()))?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+
+    /// A handshake acceptor for the admin listener
+    pub fn acceptor(&self) -> Result<TlsAcceptor> {
+        self.server_config().map(TlsAcceptor::from)
+    }
+}
+
+/// Every certificate in a PEM file; at least one
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| invalid(path, e.to_string()))?;
+    if certs.is_empty() {
+        return Err(invalid(path, "no certificates found".into()));
+    }
+    Ok(certs)
+}
+
+fn invalid(path: &Path, message: String) -> VectorDbError {
+    VectorDbError::InvalidParameter(format!("TLS file {}: {}", path.display(), message))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/tls")
+            .join(name)
+    }
+
+    #[test]
+    fn test_server_config_requires_client_certificates() {
+        let config = MtlsConfig {
+            cert: fixture("server.pem"),
+            key: fixture("server.key"),
+            client_ca: fixture("ca.pem"),
+        };
+        assert!(config.server_config().is_ok());
+
+        // A key where the CA bundle should be names the file
+        let wrong = MtlsConfig {
+            client_ca: fixture("server.key"),
+            ..config.clone()
+        };
+        let e = wrong.server_config().unwrap_err().to_string();
+        assert!(e.contains("server.key"), "{}", e);
+
+        let missing = MtlsConfig {
+            key: fixture("absent.key"),
+            ..config
+        };
+        assert!(missing.server_config().is_err());
+    }
+}
@@ -0,0 +1,394 @@
+// src/uploads.rs
+//
+// Resumable upload sessions for bulk import.
+//
+// A 40 GB JSONL file shouldn't have to start over because a connection
+// dropped at 39 GB. Instead of one huge POST, the client opens a session
+// and sends the file in pieces, each tagged with the byte offset it
+// starts at:
+//
+//   POST  /api/collections/docs/uploads        → { "upload_id": "9f…", "offset": 0 }
+//   PATCH /api/uploads/9f…  (Upload-Offset: 0) → { "offset": 8388608 }
+//   ...connection drops...
+//   GET   /api/uploads/9f…                     → { "offset": 8388608 }   (resume here)
+//   PATCH /api/uploads/9f…  (Upload-Offset: 8388608) ...
+//   POST  /api/uploads/9f…/complete            → import report
+//
+// Bytes are appended to `<dir>/<id>.part` as they arrive, so even a PATCH
+// that dies halfway keeps everything received before the drop; the
+// session's offset is always the length of what's safely on disk. A
+// small `<id>.json` beside it records the target collection, so sessions
+// survive a server restart too.
+
+use crate::models::{Result, VectorDbError};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Sessions untouched for this long are removed with their data
+pub const UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Bytes read at a time when the assembled file is imported
+const READ_CHUNK: usize = 1024 * 1024;
+
+/// Persistent description of a session (the `<id>.json` file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionMeta {
+    collection: String,
+    created_at: u64,
+}
+
+/// One in-progress upload
+#[derive(Debug)]
+pub struct UploadSession {
+    pub id: String,
+    pub collection: String,
+    pub created_at: u64,
+    /// Bytes received so far (= length of the .part file)
+    pub offset: u64,
+    /// Last time bytes were appended (unix seconds)
+    pub updated_at: u64,
+    part_path: PathBuf,
+}
+
+impl UploadSession {
+    /// Path of the assembled data
+    pub fn part_path(&self) -> &Path {
+        &self.part_path
+    }
+
+    /// Append a stream of chunks that the client says starts at `offset`.
+    ///
+    /// A mismatched offset is a Conflict carrying the real one, so the
+    /// client can resume from there. If the stream fails partway, the
+    /// bytes written before the failure are kept and counted.
+    pub async fn append<S, B, E>(&mut self, offset: u64, mut chunks: S) -> Result<u64>
+    where
+        S: Stream<Item = std::result::Result<B, E>> + Unpin,
+        B: AsRef<[u8]>,
+        E: std::fmt::Display,
+    {
+        if offset != self.offset {
+            return Err(VectorDbError::Conflict(format!(
+                "upload '{}' is at offset {}, not {}",
+                self.id, self.offset, offset
+            )));
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.part_path)
+            .await?;
+        let mut failure = None;
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    file.write_all(bytes.as_ref()).await?;
+                    self.offset += bytes.as_ref().len() as u64;
+                }
+                Err(e) => {
+                    failure = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        file.sync_data().await?;
+        self.updated_at = unix_now();
+
+        match failure {
+            Some(e) => Err(VectorDbError::InvalidParameter(format!(
+                "upload interrupted at offset {}: {}",
+                self.offset, e
+            ))),
+            None => Ok(self.offset),
+        }
+    }
+
+    /// Read the assembled data back in chunks, for the import that
+    /// completes the session
+    pub async fn read_chunks(
+        &self,
+    ) -> Result<impl Stream<Item = std::io::Result<Vec<u8>>> + Unpin> {
+        let file = tokio::fs::File::open(&self.part_path).await?;
+        Ok(Box::pin(futures_util::stream::unfold(
+            file,
+            |mut file| async move {
+                let mut buf = vec![0u8; READ_CHUNK];
+                match file.read(&mut buf).await {
+                    Ok(0) => None,
+                    Ok(n) => {
+                        buf.truncate(n);
+                        Some((Ok(buf), file))
+                    }
+                    Err(e) => Some((Err(e), file)),
+                }
+            },
+        )))
+    }
+
+    /// JSON shape returned by the API
+    pub fn info(&self) -> serde_json::Value {
+        serde_json::json!({
+            "upload_id": self.id,
+            "collection": self.collection,
+            "offset": self.offset,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+        })
+    }
+}
+
+/// All sessions, backed by a directory
+#[derive(Debug)]
+pub struct UploadStore {
+    dir: PathBuf,
+    sessions: Mutex<HashMap<String, Arc<tokio::sync::Mutex<UploadSession>>>>,
+}
+
+impl Default for UploadStore {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("uploads"),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl UploadStore {
+    /// Open (creating if needed) `dir` and pick up sessions left by a
+    /// previous run
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut sessions = HashMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let meta: SessionMeta = match std::fs::read(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            {
+                Some(meta) => meta,
+                None => {
+                    tracing::warn!("Skipping unreadable upload session {}", path.display());
+                    continue;
+                }
+            };
+            let part_path = dir.join(format!("{}.part", id));
+            let (offset, updated_at) = match std::fs::metadata(&part_path) {
+                Ok(m) => (m.len(), modified_secs(&m).unwrap_or(meta.created_at)),
+                Err(_) => continue,
+            };
+            let session = UploadSession {
+                id: id.to_string(),
+                collection: meta.collection,
+                created_at: meta.created_at,
+                offset,
+                updated_at,
+                part_path,
+            };
+            sessions.insert(id.to_string(), Arc::new(tokio::sync::Mutex::new(session)));
+        }
+
+        Ok(Self {
+            dir,
+            sessions: Mutex::new(sessions),
+        })
+    }
+
+    /// Start a new, empty session for `collection`, returning its ID
+    pub fn create(&self, collection: &str) -> Result<String> {
+        self.expire(unix_now());
+        std::fs::create_dir_all(&self.dir)?;
+
+        let id = new_id();
+        let now = unix_now();
+        let part_path = self.dir.join(format!("{}.part", id));
+        std::fs::File::create(&part_path)?;
+        let meta = SessionMeta {
+            collection: collection.to_string(),
+            created_at: now,
+        };
+        let meta_json = serde_json::to_vec(&meta)
+            .map_err(|e| VectorDbError::SerializationError(e.to_string()))?;
+        std::fs::write(self.dir.join(format!("{}.json", id)), meta_json)?;
+
+        let session = UploadSession {
+            id: id.clone(),
+            collection: meta.collection,
+            created_at: now,
+            offset: 0,
+            updated_at: now,
+            part_path,
+        };
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Arc::new(tokio::sync::Mutex::new(session)));
+        Ok(id)
+    }
+
+    /// Look up a session (lock it to append or complete)
+    pub fn get(&self, id: &str) -> Result<Arc<tokio::sync::Mutex<UploadSession>>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| VectorDbError::NotFound(format!("upload '{}'", id)))
+    }
+
+    /// Forget a session and delete its files
+    pub fn remove(&self, id: &str) -> Result<()> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| VectorDbError::NotFound(format!("upload '{}'", id)))?;
+        self.remove_files(id);
+        Ok(())
+    }
+
+    /// Drop sessions idle for longer than UPLOAD_TTL (skipping any that
+    /// are mid-append)
+    pub fn expire(&self, now: u64) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let stale: Vec<String> = sessions
+            .iter()
+            .filter(|(_, s)| {
+                s.try_lock()
+                    .map(|s| now.saturating_sub(s.updated_at) > UPLOAD_TTL.as_secs())
+                    .unwrap_or(false)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            sessions.remove(id);
+            self.remove_files(id);
+            tracing::info!("Expired idle upload session {}", id);
+        }
+        stale.len()
+    }
+
+    fn remove_files(&self, id: &str) {
+        std::fs::remove_file(self.dir.join(format!("{}.part", id))).ok();
+        std::fs::remove_file(self.dir.join(format!("{}.json", id))).ok();
+    }
+}
+
+/// Hard-to-guess session ID: 128 bits of hasher randomness as hex
+fn new_id() -> String {
+    let half = || {
+        let mut h = RandomState::new().build_hasher();
+        h.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        h.finish()
+    };
+    format!("{:016x}{:016x}", half(), half())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn modified_secs(meta: &std::fs::Metadata) -> Option<u64> {
+    meta.modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("vectordb_uploads_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn chunks(
+        parts: &[&'static str],
+    ) -> impl Stream<Item = std::result::Result<&'static [u8], String>> + Unpin {
+        futures_util::stream::iter(parts.iter().map(|p| Ok(p.as_bytes())).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn test_append_checks_offsets_and_survives_reopen() {
+        let dir = temp_dir("resume");
+        let store = UploadStore::open(&dir).unwrap();
+        let id = store.create("docs").unwrap();
+
+        let session = store.get(&id).unwrap();
+        let mut s = session.lock().await;
+        assert_eq!(
+            s.append(0, chunks(&["{\"id\":", "\"a\"}\n"]))
+                .await
+                .unwrap(),
+            11
+        );
+
+        // Replaying an old offset is rejected with the real one
+        let err = s.append(0, chunks(&["x"])).await.unwrap_err();
+        assert!(err.to_string().contains("at offset 11"), "{}", err);
+        drop(s);
+
+        // A new process sees the same session at the same offset
+        let reopened = UploadStore::open(&dir).unwrap();
+        let session = reopened.get(&id).unwrap();
+        let mut s = session.lock().await;
+        assert_eq!((s.collection.as_str(), s.offset), ("docs", 11));
+        assert_eq!(s.append(11, chunks(&["more"])).await.unwrap(), 15);
+        assert_eq!(
+            std::fs::read_to_string(s.part_path()).unwrap(),
+            "{\"id\":\"a\"}\nmore"
+        );
+        drop(s);
+
+        reopened.remove(&id).unwrap();
+        assert!(reopened.get(&id).is_err());
+        assert!(UploadStore::open(&dir).unwrap().get(&id).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_append_keeps_received_bytes() {
+        let dir = temp_dir("interrupted");
+        let store = UploadStore::open(&dir).unwrap();
+        let id = store.create("docs").unwrap();
+        let session = store.get(&id).unwrap();
+        let mut s = session.lock().await;
+
+        let flaky = futures_util::stream::iter(vec![Ok(&b"abc"[..]), Err("connection reset")]);
+        assert!(s.append(0, flaky).await.is_err());
+        assert_eq!(s.offset, 3);
+
+        // Idle sessions expire with their files
+        drop(s);
+        assert_eq!(store.expire(unix_now() + UPLOAD_TTL.as_secs() + 1), 1);
+        assert!(!dir.join(format!("{}.part", id)).exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,369 @@
+// src/usage.rs
+//
+// Per-API-key usage accounting, for chargeback on shared deployments.
+//
+// Every request is attributed to the API key it presents (`X-API-Key`, or
+// `Authorization: Bearer <key>`), or to "anonymous" when it has none, and
+// added to that key's counters for the current UTC day:
+//
+//   requests, searches, inserts, bytes_in, bytes_out
+//
+// Rollups live in memory and are flushed to `<data dir>/usage/<date>.json`
+// (one file per day) every minute and at shutdown, so a crash loses at
+// most the last interval of counts. Files from earlier days are loaded on
+// startup and served by GET /api/admin/usage.
+//
+// Raw keys are never stored. Each key is recorded under a fingerprint
+// (FNV-1a hash, hex), so the usage files aren't a list of credentials;
+// the admin endpoint accepts a raw key and fingerprints it the same way.
+
+use crate::models::{Result, VectorDbError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Subdirectory of the data directory holding the daily files
+pub const USAGE_DIR: &str = "usage";
+
+/// How often rollups are written to disk
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Attribution for requests without an API key
+pub const ANONYMOUS: &str = "anonymous";
+
+const SECS_PER_DAY: u64 = 86_400;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// COUNTERS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// What a request did, for the per-kind counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    Search,
+    Insert,
+    Other,
+}
+
+/// Usage counters for one key (over one day, or summed over several)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounters {
+    pub requests: u64,
+    pub searches: u64,
+    pub inserts: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+impl UsageCounters {
+    /// Counters for one request of `kind`
+    pub fn request(kind: RequestKind, bytes_in: u64, bytes_out: u64) -> Self {
+        Self {
+            requests: 1,
+            searches: (kind == RequestKind::Search) as u64,
+            inserts: (kind == RequestKind::Insert) as u64,
+            bytes_in,
+            bytes_out,
+        }
+    }
+
+    /// Counters for body bytes counted after the request was recorded
+    /// (streamed bodies whose length isn't known up front)
+    pub fn bytes(bytes_in: u64, bytes_out: u64) -> Self {
+        Self {
+            bytes_in,
+            bytes_out,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, other: &UsageCounters) {
+        self.requests += other.requests;
+        self.searches += other.searches;
+        self.inserts += other.inserts;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+    }
+}
+
+/// One day's counters, by key fingerprint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    /// UTC date, YYYY-MM-DD
+    pub date: String,
+    pub keys: BTreeMap<String, UsageCounters>,
+}
+
+/// The fingerprint a key is recorded under
+pub fn fingerprint(key: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("key_{:016x}", hash)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// DATES
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Days since the Unix epoch for a timestamp
+pub fn day_of(unix_secs: u64) -> u64 {
+    unix_secs / SECS_PER_DAY
+}
+
+/// Format a day number as YYYY-MM-DD (proleptic Gregorian, UTC)
+pub fn format_date(day: u64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// Parse YYYY-MM-DD back into a day number
+pub fn parse_date(s: &str) -> Result<u64> {
+    let invalid = || VectorDbError::InvalidParameter(format!("'{}' is not a YYYY-MM-DD date", s));
+    let mut parts = s.splitn(3, '-');
+    let mut next = |len: usize| -> Result<i64> {
+        let part = parts
+            .next()
+            .filter(|p| p.len() == len)
+            .ok_or_else(invalid)?;
+        part.parse().map_err(|_| invalid())
+    };
+    let (y, m, d) = (next(4)?, next(2)?, next(2)?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || y < 1970 {
+        return Err(invalid());
+    }
+
+    // Hinnant's days_from_civil
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let day = (era * 146_097 + doe - 719_468) as u64;
+
+    // Reject dates like 2026-02-31 that roll over into the next month
+    if format_date(day) != s {
+        return Err(invalid());
+    }
+    Ok(day)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// USAGE LOG
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Default)]
+struct Rollups {
+    /// day → fingerprint → counters
+    days: BTreeMap<u64, BTreeMap<String, UsageCounters>>,
+    /// Days changed since the last flush
+    dirty: BTreeSet<u64>,
+}
+
+/// Daily usage rollups, optionally backed by a directory
+#[derive(Debug, Default)]
+pub struct UsageLog {
+    dir: Option<PathBuf>,
+    rollups: Mutex<Rollups>,
+}
+
+impl UsageLog {
+    /// Open the rollups in `dir`, loading every day already on disk
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut rollups = Rollups::default();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(day) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".json"))
+                .and_then(|date| parse_date(date).ok())
+            else {
+                continue;
+            };
+            let usage: DailyUsage =
+                serde_json::from_slice(&std::fs::read(&path)?).map_err(|e| {
+                    VectorDbError::SerializationError(format!("{}: {}", path.display(), e))
+                })?;
+            rollups.days.insert(day, usage.keys);
+        }
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            rollups: Mutex::new(rollups),
+        })
+    }
+
+    /// Add `counters` to `fingerprint`'s total for the day of `unix_secs`
+    pub fn record(&self, fingerprint: &str, counters: UsageCounters, unix_secs: u64) {
+        let day = day_of(unix_secs);
+        let mut rollups = self.rollups.lock().unwrap();
+        rollups
+            .days
+            .entry(day)
+            .or_default()
+            .entry(fingerprint.to_string())
+            .or_default()
+            .add(&counters);
+        rollups.dirty.insert(day);
+    }
+
+    /// Write every day changed since the last flush
+    pub fn flush(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let _span = tracing::info_span!("usage.flush").entered();
+        let changed: Vec<DailyUsage> = {
+            let mut rollups = self.rollups.lock().unwrap();
+            let dirty = std::mem::take(&mut rollups.dirty);
+            dirty
+                .into_iter()
+                .map(|day| DailyUsage {
+                    date: format_date(day),
+                    keys: rollups.days.get(&day).cloned().unwrap_or_default(),
+                })
+                .collect()
+        };
+
+        for (i, usage) in changed.iter().enumerate() {
+            if let Err(e) = write_day(dir, usage) {
+                // Retry the rest on the next flush
+                let mut rollups = self.rollups.lock().unwrap();
+                for usage in &changed[i..] {
+                    rollups.dirty.insert(parse_date(&usage.date)?);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rollups for days in `from..=to` (either end open), optionally for
+    /// a single fingerprint, oldest first
+    pub fn query(&self, from: Option<u64>, to: Option<u64>, key: Option<&str>) -> Vec<DailyUsage> {
+        let rollups = self.rollups.lock().unwrap();
+        let range = from.unwrap_or(0)..=to.unwrap_or(u64::MAX);
+        rollups
+            .days
+            .range(range)
+            .map(|(&day, keys)| DailyUsage {
+                date: format_date(day),
+                keys: keys
+                    .iter()
+                    .filter(|(fp, _)| key.map_or(true, |k| k == fp.as_str()))
+                    .map(|(fp, counters)| (fp.clone(), *counters))
+                    .collect(),
+            })
+            .filter(|usage| !usage.keys.is_empty())
+            .collect()
+    }
+}
+
+/// Sum each key's counters over `days`
+pub fn totals(days: &[DailyUsage]) -> BTreeMap<String, UsageCounters> {
+    let mut totals: BTreeMap<String, UsageCounters> = BTreeMap::new();
+    for usage in days {
+        for (fp, counters) in &usage.keys {
+            totals.entry(fp.clone()).or_default().add(counters);
+        }
+    }
+    totals
+}
+
+/// Replace one day's file: write a temporary file, then rename over it
+fn write_day(dir: &Path, usage: &DailyUsage) -> Result<()> {
+    let path = dir.join(format!("{}.json", usage.date));
+    let tmp = dir.join(format!("{}.json.tmp", usage.date));
+    std::fs::write(&tmp, serde_json::to_vec_pretty(usage)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_round_trip() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(day_of(1_709_208_000)), "2024-02-29");
+        for day in [0, 59, 365, 11_016, 19_782, 20_742, 50_000] {
+            assert_eq!(parse_date(&format_date(day)).unwrap(), day);
+        }
+        for bad in ["2026-02-30", "2026-13-01", "26-01-01", "2026-1-01", "today"] {
+            assert!(parse_date(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_rollups_persist_per_day() {
+        let dir = std::env::temp_dir().join(format!("vectordb_usage_{}", std::process::id()));
+        let alice = fingerprint("alice-secret");
+        let day1 = 20_000 * SECS_PER_DAY;
+        let day2 = day1 + SECS_PER_DAY + 5;
+
+        let log = UsageLog::open(&dir).unwrap();
+        log.record(
+            &alice,
+            UsageCounters::request(RequestKind::Search, 100, 40),
+            day1,
+        );
+        log.record(&alice, UsageCounters::bytes(0, 60), day1 + 10);
+        log.record(
+            &alice,
+            UsageCounters::request(RequestKind::Insert, 7, 2),
+            day2,
+        );
+        log.record(
+            ANONYMOUS,
+            UsageCounters::request(RequestKind::Other, 0, 5),
+            day2,
+        );
+        log.flush().unwrap();
+
+        // Keys are stored by fingerprint only
+        let file = std::fs::read_to_string(dir.join(format!("{}.json", format_date(20_000))));
+        assert!(!file.unwrap().contains("alice-secret"));
+
+        let log = UsageLog::open(&dir).unwrap();
+        let days = log.query(None, None, Some(&alice));
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0].keys[&alice],
+            UsageCounters {
+                requests: 1,
+                searches: 1,
+                inserts: 0,
+                bytes_in: 100,
+                bytes_out: 100,
+            }
+        );
+        assert_eq!(totals(&days)[&alice].inserts, 1);
+
+        let second = log.query(Some(20_001), None, None);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].keys.len(), 2);
+        assert!(log.query(Some(20_002), None, None).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
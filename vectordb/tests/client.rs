@@ -0,0 +1,105 @@
+// tests/client.rs
+//
+// End-to-end tests for the Rust client (crates/vectordb-client) against a
+// spawned server process: the shared types must round-trip through the
+// real handlers, and error statuses must come back as the right variants.
+
+mod common;
+
+use common::{TempDir, TestServer};
+use std::collections::HashMap;
+use vectordb_client::{
+    Client, CreateCollectionRequest, DistanceMetric, Error, OnConflict, PointInput, PointStatus,
+    SearchRequest, UpsertRequest,
+};
+
+#[tokio::test]
+async fn test_client_round_trip() {
+    let dir = TempDir::new("client");
+    let server = TestServer::start(dir.path());
+    let client = Client::new(server.base_url.clone()).unwrap();
+
+    let info = client
+        .create_collection(&CreateCollectionRequest {
+            distance: Some(DistanceMetric::Euclidean),
+            ..CreateCollectionRequest::new("docs", 2)
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        (info.dimension, info.distance),
+        (2, DistanceMetric::Euclidean)
+    );
+
+    let metadata = HashMap::from([("title".to_string(), "first".to_string())]);
+    let response = client
+        .insert(
+            "docs",
+            vec![
+                PointInput::with_metadata("a", vec![1.0, 0.0], metadata),
+                PointInput::new("b", vec![0.0, 1.0]),
+                PointInput::new("bad", vec![1.0]),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!((response.inserted, response.failed), (2, 1));
+    assert_eq!(response.results[2].status, PointStatus::Failed);
+
+    let hits = client
+        .search("docs", &SearchRequest::new(vec![0.9, 0.1], 1))
+        .await
+        .unwrap();
+    assert_eq!(hits[0].id, "a");
+
+    let listed = client.list_collections().await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(client.get_collection("docs").await.unwrap().count, 2);
+}
+
+#[tokio::test]
+async fn test_client_typed_errors() {
+    let dir = TempDir::new("client_errors");
+    let server = TestServer::start(dir.path());
+    let client = Client::new(server.base_url.clone()).unwrap();
+
+    let err = client.get_collection("missing").await.unwrap_err();
+    assert!(matches!(err, Error::NotFound(_)), "{:?}", err);
+
+    client
+        .create_collection(&CreateCollectionRequest::new("docs", 2))
+        .await
+        .unwrap();
+    let err = client
+        .create_collection(&CreateCollectionRequest::new("docs", 2))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Conflict(_)), "{:?}", err);
+
+    let err = client
+        .search("docs", &SearchRequest::new(vec![1.0, 0.0, 0.0], 1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::BadRequest { .. }), "{:?}", err);
+
+    // An atomic batch with a bad point is rejected as a whole
+    let err = client
+        .upsert(
+            "docs",
+            &UpsertRequest {
+                points: vec![
+                    PointInput::new("a", vec![1.0, 0.0]),
+                    PointInput::new("b", vec![]),
+                ],
+                on_conflict: OnConflict::Overwrite,
+                atomic: true,
+            },
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(400));
+    assert_eq!(client.get_collection("docs").await.unwrap().count, 0);
+
+    client.delete_collection("docs").await.unwrap();
+    assert!(client.list_collections().await.unwrap().is_empty());
+}
@@ -0,0 +1,436 @@
+// tests/common/mod.rs
+//
+// Harness for end-to-end tests: runs the real `vectordb` binary on a free
+// port inside a temporary data directory and talks to it over HTTP.
+//
+//   let server = TestServer::start(&data_dir);
+//   let client = server.client();
+//   client.create_collection("docs", 3).await;
+//   let server = server.restart();   // same data dir, new process
+
+#![allow(dead_code)] // Each test binary uses a different subset
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for the server to start listening
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// TEMP DIRECTORY
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// A directory under the system temp dir, removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "vectordb_e2e_{}_{}_{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// SERVER PROCESS
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Which address a startup log line announces
+enum Listener {
+    Http,
+    Grpc,
+    Admin,
+}
+
+/// A running `vectordb` process
+pub struct TestServer {
+    child: Child,
+    data_dir: PathBuf,
+    env: Vec<(String, String)>,
+    pub base_url: String,
+    /// gRPC endpoint, in `grpc` builds
+    pub grpc_url: Option<String>,
+    /// Admin listener, if VECTORDB_ADMIN_PORT is set
+    pub admin_url: Option<String>,
+}
+
+impl TestServer {
+    /// Start the server with `data_dir` as its working directory and wait
+    /// until it is listening.
+    pub fn start(data_dir: &Path) -> Self {
+        Self::start_with_env(data_dir, &[])
+    }
+
+    /// Start the server with extra environment variables (kept across
+    /// restarts)
+    pub fn start_with_env(data_dir: &Path, env: &[(&str, &str)]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_vectordb"))
+            .current_dir(data_dir)
+            .env("VECTORDB_ADDR", "127.0.0.1:0")
+            .env("VECTORDB_GRPC_ADDR", "127.0.0.1:0")
+            .env("NO_COLOR", "1")
+            .envs(env.iter().copied())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .expect("failed to spawn vectordb");
+
+        // Keep draining stdout for the life of the process so the server
+        // never blocks on a full pipe; report the addresses as we see them
+        // (gRPC and the admin listener, if enabled, are up before HTTP).
+        let stdout = child.stdout.take().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Some(i) = line.find("gRPC listening on ") {
+                    let addr = line[i + "gRPC listening on ".len()..].trim();
+                    tx.send((Listener::Grpc, format!("http://{}", addr))).ok();
+                } else if let Some(i) = line.find("Admin API listening on ") {
+                    let rest = &line[i + "Admin API listening on ".len()..];
+                    let addr = rest.split_whitespace().next().unwrap_or_default();
+                    tx.send((Listener::Admin, addr.to_string())).ok();
+                } else if let Some(i) = line.find("Listening on http://") {
+                    let addr = line[i + "Listening on ".len()..].trim().to_string();
+                    tx.send((Listener::Http, addr)).ok();
+                }
+            }
+        });
+
+        let mut grpc_url = None;
+        let mut admin_url = None;
+        let base_url = loop {
+            match rx.recv_timeout(STARTUP_TIMEOUT) {
+                Ok((Listener::Http, url)) => break url,
+                Ok((Listener::Grpc, url)) => grpc_url = Some(url),
+                Ok((Listener::Admin, url)) => admin_url = Some(url),
+                Err(_) => {
+                    child.kill().ok();
+                    panic!(
+                        "vectordb did not start listening within {:?}",
+                        STARTUP_TIMEOUT
+                    );
+                }
+            }
+        };
+
+        Self {
+            child,
+            data_dir: data_dir.to_path_buf(),
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            base_url,
+            grpc_url,
+            admin_url,
+        }
+    }
+
+    /// A client for the admin listener (the server must have one)
+    pub fn admin_client(&self) -> Client {
+        Client::new(self.admin_url.as_deref().expect("no admin listener"))
+    }
+
+    pub fn client(&self) -> Client {
+        Client::new(&self.base_url)
+    }
+
+    /// A client that sends `key` as X-API-Key on every request
+    pub fn client_with_key(&self, key: &str) -> Client {
+        Client::with_api_key(&self.base_url, key)
+    }
+
+    fn env(&self) -> Vec<(&str, &str)> {
+        self.env
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    /// Ask the server to shut down gracefully (SIGINT) and wait for it
+    pub fn stop(mut self) {
+        self.interrupt();
+        self.child.wait().unwrap();
+    }
+
+    /// Stop gracefully, then start a new process on the same data dir
+    pub fn restart(self) -> Self {
+        let data_dir = self.data_dir.clone();
+        let env = self.env.clone();
+        self.stop();
+        let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        Self::start_with_env(&data_dir, &env)
+    }
+
+    /// Kill the process without letting it shut down (no snapshot is
+    /// written), then start a new one on the same data dir
+    pub fn crash(mut self) -> Self {
+        self.kill();
+        Self::start_with_env(&self.data_dir, &self.env())
+    }
+
+    /// Kill the process without letting it shut down, and leave it down
+    pub fn kill(&mut self) {
+        self.child.kill().unwrap();
+        self.child.wait().unwrap();
+    }
+
+    /// Send SIGTERM (as Kubernetes and systemd do) and wait for the
+    /// process to exit
+    pub fn terminate(mut self) -> std::process::ExitStatus {
+        self.signal("-TERM");
+        self.child.wait().unwrap()
+    }
+
+    fn interrupt(&mut self) {
+        self.signal("-INT");
+    }
+
+    #[cfg(unix)]
+    fn signal(&mut self, signal: &str) {
+        Command::new("kill")
+            .args([signal, &self.child.id().to_string()])
+            .status()
+            .unwrap();
+    }
+
+    #[cfg(not(unix))]
+    fn signal(&mut self, _signal: &str) {
+        self.child.kill().ok();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // No-op if stop() already reaped the process
+        if let Ok(None) = self.child.try_wait() {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// CLIENT
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Minimal typed wrapper over the collections API
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// A client that sends `key` as X-API-Key on every request
+    pub fn with_api_key(base_url: &str, key: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-api-key", key.parse().expect("valid header value"));
+        Self {
+            http: reqwest::Client::builder()
+                .default_headers(headers)
+                .build()
+                .expect("client builds"),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// POST a JSON body, returning the status code and JSON response
+    pub async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let resp = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(&body)
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// PUT a JSON body, returning the status code and JSON response
+    /// POST a JSON body, also returning the response headers
+    pub async fn post_with_headers(
+        &self,
+        path: &str,
+        body: Value,
+    ) -> (u16, reqwest::header::HeaderMap, Value) {
+        let resp = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .json(&body)
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        let headers = resp.headers().clone();
+        (status, headers, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> (u16, Value) {
+        let resp = self
+            .http
+            .put(format!("{}{}", self.base_url, path))
+            .json(&body)
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// PUT a raw body, returning the status code and JSON response
+    pub async fn put_bytes(&self, path: &str, body: impl Into<reqwest::Body>) -> (u16, Value) {
+        let resp = self
+            .http
+            .put(format!("{}{}", self.base_url, path))
+            .body(body)
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// POST a raw body, returning the status code and JSON response
+    pub async fn post_bytes(&self, path: &str, body: impl Into<reqwest::Body>) -> (u16, Value) {
+        let resp = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .body(body)
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// DELETE a path, returning the status code and JSON response
+    pub async fn delete(&self, path: &str) -> (u16, Value) {
+        let resp = self
+            .http
+            .delete(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// GET a path, returning the status code and JSON response
+    pub async fn get(&self, path: &str) -> (u16, Value) {
+        let resp = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.json().await.unwrap_or(Value::Null))
+    }
+
+    /// GET a path, returning the status code and the raw response body
+    pub async fn get_text(&self, path: &str) -> (u16, String) {
+        let resp = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (status, resp.text().await.unwrap_or_default())
+    }
+
+    /// GET a path, returning the status code and the raw response bytes
+    pub async fn get_bytes(&self, path: &str) -> (u16, Vec<u8>) {
+        let resp = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed");
+        let status = resp.status().as_u16();
+        (
+            status,
+            resp.bytes().await.map(|b| b.to_vec()).unwrap_or_default(),
+        )
+    }
+
+    /// HEAD a path, returning the status code and the response headers
+    pub async fn head(&self, path: &str) -> (u16, reqwest::header::HeaderMap) {
+        let resp = self
+            .http
+            .head(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .expect("request failed");
+        (resp.status().as_u16(), resp.headers().clone())
+    }
+
+    pub async fn create_collection(&self, name: &str, dimension: usize) {
+        let (status, body) = self
+            .post(
+                "/api/collections",
+                json!({ "name": name, "dimension": dimension }),
+            )
+            .await;
+        assert_eq!(status, 201, "create_collection failed: {}", body);
+    }
+
+    pub async fn upsert(&self, collection: &str, points: &[(&str, Vec<f32>)]) {
+        let points: Vec<Value> = points
+            .iter()
+            .map(|(id, vector)| json!({ "id": id, "vector": vector }))
+            .collect();
+        let (status, body) = self
+            .post(
+                &format!("/api/collections/{}/points", collection),
+                json!({ "points": points }),
+            )
+            .await;
+        assert_eq!(status, 200, "upsert failed: {}", body);
+    }
+
+    /// IDs of the top-k hits, best first
+    pub async fn search(&self, collection: &str, vector: &[f32], top_k: usize) -> Vec<String> {
+        let (status, body) = self
+            .post(
+                &format!("/api/collections/{}/search", collection),
+                json!({ "vector": vector, "top_k": top_k }),
+            )
+            .await;
+        assert_eq!(status, 200, "search failed: {}", body);
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
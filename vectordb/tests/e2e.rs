@@ -0,0 +1,2597 @@
+// tests/e2e.rs
+//
+// End-to-end tests against a spawned server process.
+//
+// Run with: cargo test --test e2e
+
+mod common;
+
+use common::{Client, TempDir, TestServer};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_insert_then_search() {
+    let dir = TempDir::new("insert_search");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 3).await;
+    client
+        .upsert(
+            "docs",
+            &[
+                ("x", vec![1.0, 0.0, 0.0]),
+                ("y", vec![0.0, 1.0, 0.0]),
+                ("xy", vec![0.7, 0.7, 0.0]),
+            ],
+        )
+        .await;
+
+    let hits = client.search("docs", &[1.0, 0.1, 0.0], 2).await;
+    assert_eq!(hits, vec!["x", "xy"]);
+
+    // Wrong dimension is a client error, not a crash
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search",
+            serde_json::json!({ "vector": [1.0], "top_k": 1 }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    let (status, info) = client.get("/api/collections/docs").await;
+    assert_eq!(status, 200);
+    assert_eq!(info["count"], 3);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_data_survives_restart() {
+    let dir = TempDir::new("restart");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert("docs", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+    assert_eq!(client.search("docs", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    let server = server.restart();
+    let client = server.client();
+
+    let (status, info) = client.get("/api/collections/docs").await;
+    assert_eq!(status, 200, "collection lost across restart: {}", info);
+    assert_eq!(info["count"], 2);
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_collections_load_on_first_access_after_restart() {
+    let dir = TempDir::new("lazy_load");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+    client.create_collection("logs", 2).await;
+    client
+        .upsert("docs", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+    client.upsert("logs", &[("x", vec![1.0, 1.0])]).await;
+
+    // Nothing is read at startup: both segments are only registered
+    let server = server.restart();
+    let client = server.client();
+    let (_, stats) = client.get("/stats").await;
+    let segments = &stats["segments"];
+    assert_eq!(segments["unloaded_collections"], 2, "{}", stats);
+    assert_eq!(segments["catalog"]["registered"], 2, "{}", stats);
+    assert_eq!(segments["catalog"]["opens"], 0, "{}", stats);
+    let (_, list) = client.get("/api/collections").await;
+    assert_eq!(list[0]["name"], "docs");
+    assert_eq!(list[0]["count"], 2, "{}", list);
+
+    // A search reads the one collection it names
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+    let (_, stats) = client.get("/stats").await;
+    let segments = &stats["segments"];
+    assert_eq!(segments["unloaded_collections"], 1, "{}", stats);
+    assert_eq!(segments["catalog"]["opens"], 1, "{}", stats);
+    assert_eq!(segments["catalog"]["open"], 0, "{}", stats);
+
+    // The unread one keeps its files through the next snapshot
+    let server = server.restart();
+    let client = server.client();
+    assert_eq!(client.search("logs", &[1.0, 1.0], 1).await, vec!["x"]);
+    assert_eq!(client.search("docs", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_sigterm_flushes_before_exiting() {
+    let dir = TempDir::new("sigterm");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_WAL_SYNC", "never")]);
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert("docs", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+
+    let status = server.terminate();
+    assert!(status.success(), "unclean exit on SIGTERM: {}", status);
+    // Everything went into the snapshot, so the log is empty
+    let wal = std::fs::metadata(dir.path().join("data/wal.log")).unwrap();
+    assert_eq!(wal.len(), 0);
+
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    let (status, info) = client.get("/api/collections/docs").await;
+    assert_eq!(status, 200, "collection lost on SIGTERM: {}", info);
+    assert_eq!(info["count"], 2);
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_memtables_flush_to_segments() {
+    let dir = TempDir::new("memtable");
+    let env = [("VECTORDB_MEMTABLE_MAX_BYTES", "1")];
+    let server = TestServer::start_with_env(dir.path(), &env);
+    server.client().create_collection("docs", 2).await;
+    // The collection itself is in the snapshot from here on
+    let mut server = server.restart();
+    let client = server.client();
+
+    client
+        .upsert(
+            "docs",
+            &[
+                ("a", vec![1.0, 0.0]),
+                ("b", vec![0.0, 1.0]),
+                ("c", vec![1.0, 1.0]),
+            ],
+        )
+        .await;
+    let flushed = |n: usize| {
+        let dir = dir.path().join("data/flushed");
+        let segments = std::fs::read_dir(dir).map_or(0, |d| {
+            d.filter(|f| {
+                f.as_ref()
+                    .is_ok_and(|f| f.path().extension() == Some("vec".as_ref()))
+            })
+            .count()
+        });
+        segments >= n
+    };
+    for _ in 0..100 {
+        if flushed(1) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(flushed(1), "memtable never flushed");
+    let (status, _) = client.delete("/api/collections/docs/points/c").await;
+    assert_eq!(status, 200);
+    for _ in 0..100 {
+        if flushed(2) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(flushed(2), "delete never flushed");
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["memtable"]["points"], 0, "{}", index);
+
+    // Lose the log: the points come back from the flushed segments
+    server.kill();
+    std::fs::remove_file(dir.path().join("data/wal.log")).unwrap();
+    let server = TestServer::start_with_env(dir.path(), &env);
+    let client = server.client();
+    let (status, info) = client.get("/api/collections/docs").await;
+    assert_eq!(status, 200, "{}", info);
+    assert_eq!(info["count"], 2);
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_flushes_replay_in_log_order_and_shorten_it() {
+    let dir = TempDir::new("flush_order");
+    let env = [("VECTORDB_MEMTABLE_MAX_BYTES", "1")];
+    let server = TestServer::start_with_env(dir.path(), &env);
+    server.client().create_collection("docs", 2).await;
+    // "docs" is in the snapshot from here on
+    let server = server.restart();
+    let client = server.client();
+    let wal_records = || async {
+        let (_, stats) = client.get("/stats").await;
+        stats["wal"]["records"].as_u64().unwrap()
+    };
+    let settle = |records: u64| async move {
+        for _ in 0..100 {
+            if wal_records().await == records {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        panic!("the WAL never came down to {} records", records);
+    };
+
+    // Flushed: the log no longer needs the insert
+    client.upsert("docs", &[("a", vec![1.0, 0.0])]).await;
+    settle(0).await;
+
+    // The name moves on to a new collection, which gets its own flush;
+    // only the collection records stay in the log
+    let (status, _) = client
+        .post("/api/collections/docs/rename", json!({ "to": "old" }))
+        .await;
+    assert_eq!(status, 200);
+    client.create_collection("docs", 2).await;
+    client.upsert("docs", &[("b", vec![0.0, 1.0])]).await;
+    settle(2).await;
+    let (_, stats) = client.get("/stats").await;
+    let seq = stats["wal"]["seq"].clone();
+
+    // Each flush lands on the collection that had the name when it was
+    // sealed, and numbering carries on past the dropped records
+    let server = server.crash();
+    let client = server.client();
+    let (_, old) = client.get("/api/collections/old").await;
+    assert_eq!(old["count"], 1, "{}", old);
+    let (status, _) = client.get("/api/collections/old/points/a").await;
+    assert_eq!(status, 200);
+    let (_, docs) = client.get("/api/collections/docs").await;
+    assert_eq!(docs["count"], 1, "{}", docs);
+    let (status, _) = client.get("/api/collections/docs/points/a").await;
+    assert_eq!(status, 404);
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+    let (_, stats) = client.get("/stats").await;
+    assert_eq!(stats["wal"]["seq"], seq);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_acknowledged_writes_survive_crash() {
+    let dir = TempDir::new("crash");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert("docs", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+
+    // Killed before any snapshot: everything comes back from the WAL
+    let server = server.crash();
+    let client = server.client();
+
+    let (status, info) = client.get("/api/collections/docs").await;
+    assert_eq!(status, 200, "collection lost in crash: {}", info);
+    assert_eq!(info["count"], 2);
+    assert_eq!(client.search("docs", &[0.0, 1.0], 1).await, vec!["b"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_flat_store_deletes_and_updates_survive_crash() {
+    let dir = TempDir::new("flat_delete");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    for (id, data) in [("a", [1.0, 0.0]), ("b", [0.0, 1.0])] {
+        let (status, _) = client
+            .post("/vectors", json!({ "id": id, "vector": { "data": data } }))
+            .await;
+        assert_eq!(status, 200);
+    }
+
+    // Both vectors are in the snapshot; the changes below only in the WAL
+    let server = server.restart();
+    let client = server.client();
+    assert_eq!(client.delete("/api/vectors/a").await.0, 200);
+    assert_eq!(client.delete("/api/vectors/a").await.0, 404);
+    let (status, _) = client
+        .put("/api/vectors/b", json!({ "data": [0.6, 0.8] }))
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        client
+            .put("/api/vectors/b", json!({ "data": [1.0] }))
+            .await
+            .0,
+        400
+    );
+    assert_eq!(
+        client
+            .put("/api/vectors/c", json!({ "data": [1.0, 0.0] }))
+            .await
+            .0,
+        404
+    );
+
+    let server = server.crash();
+    let client = server.client();
+    assert_eq!(client.get("/vectors/a").await.0, 404);
+    let (_, b) = client.get("/vectors/b").await;
+    assert_eq!(b["data"], json!([0.6, 0.8]));
+    let (_, hits) = client
+        .post("/search", json!({ "vector": [1.0, 0.0], "top_k": 5 }))
+        .await;
+    assert_eq!(hits.as_array().unwrap().len(), 1, "{}", hits);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_batch_insert_reports_each_item() {
+    let dir = TempDir::new("batch_insert");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/vectors/batch",
+            json!([
+                { "id": "a", "vector": { "data": [1.0, 0.0] } },
+                { "id": "b", "vector": { "data": [0.0, 1.0, 0.0] } },
+                { "id": "", "vector": { "data": [0.5, 0.5] } },
+                { "id": "c", "vector": { "data": [0.0, 1.0] } },
+                { "id": "a", "vector": { "data": [0.0, 0.0] } },
+            ]),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["status"], "partial");
+    assert_eq!(body["count"], 2);
+    assert_eq!(body["failed"], 3);
+    let statuses: Vec<&str> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        statuses,
+        vec!["inserted", "failed", "failed", "inserted", "failed"]
+    );
+    assert!(body["results"][1]["error"]
+        .as_str()
+        .unwrap()
+        .contains("Dimension mismatch"));
+
+    // The accepted items were logged before they were acknowledged
+    let server = server.crash();
+    let client = server.client();
+    let (status, a) = client.get("/vectors/a").await;
+    assert_eq!(status, 200);
+    assert_eq!(a["data"], json!([1.0, 0.0]));
+    assert_eq!(client.get("/vectors/b").await.0, 404);
+
+    let (_, body) = client
+        .post(
+            "/api/vectors/batch",
+            json!([{ "id": "c", "vector": { "data": [0.6, 0.8] } }]),
+        )
+        .await;
+    assert_eq!(body["status"], "inserted");
+    assert_eq!(body["results"][0]["status"], "overwritten");
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_batch_get_partitions_found_and_missing() {
+    let dir = TempDir::new("batch_get");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, _) = client
+        .post(
+            "/api/vectors/batch",
+            json!([
+                { "id": "a", "vector": { "data": [1.0, 0.0], "metadata": { "n": "1" } } },
+                { "id": "b", "vector": { "data": [0.0, 1.0] } },
+            ]),
+        )
+        .await;
+    assert_eq!(status, 200);
+
+    let (status, body) = client
+        .post(
+            "/api/vectors/get",
+            json!({ "ids": ["b", "nope", "a", "b"] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let found = body["found"].as_array().unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0]["id"], "b");
+    assert_eq!(found[1]["data"], json!([1.0, 0.0]));
+    assert_eq!(found[1]["metadata"]["n"], "1");
+    assert_eq!(body["missing"], json!(["nope"]));
+
+    let ids: Vec<String> = (0..1001).map(|i| i.to_string()).collect();
+    let (status, _) = client.post("/api/vectors/get", json!({ "ids": ids })).await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_repeated_ids_in_one_upsert_follow_the_policy() {
+    let dir = TempDir::new("repeated_ids");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    let repeated = |policy: &str| {
+        json!({ "on_conflict": policy, "points": [
+            { "id": "a", "vector": [1.0, 0.0], "metadata": { "lang": "en", "v": "1" } },
+            { "id": "a", "vector": [0.0, 1.0], "metadata": { "v": "2" } },
+        ] })
+    };
+    let statuses = |body: &Value| -> Vec<String> {
+        body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["status"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Each case gets a fresh collection, so the first item is an insert
+    // and only the second conflicts
+    let cases = [
+        (
+            "error",
+            "failed",
+            json!([1.0, 0.0]),
+            json!({ "lang": "en", "v": "1" }),
+        ),
+        (
+            "skip",
+            "skipped",
+            json!([1.0, 0.0]),
+            json!({ "lang": "en", "v": "1" }),
+        ),
+        (
+            "overwrite",
+            "overwritten",
+            json!([0.0, 1.0]),
+            json!({ "v": "2" }),
+        ),
+        (
+            "merge_metadata",
+            "merged",
+            json!([0.0, 1.0]),
+            json!({ "lang": "en", "v": "2" }),
+        ),
+    ];
+    for (policy, second, data, metadata) in cases {
+        client.create_collection(policy, 2).await;
+        let (status, body) = client
+            .post(
+                &format!("/api/collections/{}/points", policy),
+                repeated(policy),
+            )
+            .await;
+        assert_eq!(status, 200, "{}: {}", policy, body);
+        assert_eq!(
+            statuses(&body),
+            vec!["inserted", second],
+            "{}: {}",
+            policy,
+            body
+        );
+        assert_eq!(body["count"], 1, "{}: {}", policy, body);
+        assert_eq!(body["inserted"], 1, "{}: {}", policy, body);
+
+        let (_, info) = client.get(&format!("/api/collections/{}", policy)).await;
+        assert_eq!(info["count"], 1, "{}: {}", policy, info);
+        let (_, point) = client
+            .get(&format!("/api/collections/{}/points/a", policy))
+            .await;
+        assert_eq!(point["vector"]["data"], data, "{}: {}", policy, point);
+        assert_eq!(
+            point["vector"]["metadata"], metadata,
+            "{}: {}",
+            policy, point
+        );
+    }
+
+    // An atomic batch with a repeated ID under `error` writes nothing
+    client.create_collection("atomic", 2).await;
+    let mut body = repeated("error");
+    body["atomic"] = json!(true);
+    let (status, body) = client.post("/api/collections/atomic/points", body).await;
+    assert_eq!(status, 400, "{}", body);
+    assert!(body["results"][1]["error"]
+        .as_str()
+        .unwrap()
+        .contains("already exists"));
+    let (_, info) = client.get("/api/collections/atomic").await;
+    assert_eq!(info["count"], 0, "{}", info);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_exists_checks_survive_restart() {
+    let dir = TempDir::new("exists");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, _) = client
+        .post(
+            "/vectors",
+            json!({ "id": "a", "vector": { "data": [1.0, 0.0] } }),
+        )
+        .await;
+    assert_eq!(status, 200);
+
+    let (status, headers) = client.head("/api/vectors/a").await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-vector-tier"], "hot");
+    let (status, _) = client.head("/api/vectors/b").await;
+    assert_eq!(status, 404);
+
+    // The filter is rebuilt from the log on restart
+    let server = server.restart();
+    let client = server.client();
+    let (status, body) = client
+        .post("/api/vectors/exists", json!({ "ids": ["b", "a", "a"] }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["existing"], json!(["a"]));
+    assert_eq!(body["missing"], json!(["b"]));
+
+    let (status, _) = client.delete("/api/vectors/a").await;
+    assert_eq!(status, 200);
+    let (status, _) = client.head("/api/vectors/a").await;
+    assert_eq!(status, 404);
+
+    server.stop();
+}
+
+/// Poll /stats until `count` flat-store vectors are cold
+async fn wait_for_cold(client: &Client, count: u64) {
+    let mut stats = Value::Null;
+    for _ in 0..100 {
+        stats = client.get("/stats").await.1;
+        if stats["cold_vector_count"] == count {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("never reached {} cold vectors: {}", count, stats);
+}
+
+/// Insert `ids` into the flat store, then read the last one so it's the
+/// one a one-vector hot tier keeps
+async fn insert_flat(client: &Client, ids: &[&str]) {
+    for (i, id) in ids.iter().enumerate() {
+        let (status, _) = client
+            .post(
+                "/vectors",
+                json!({ "id": id, "vector": { "data": [i as f32, 1.0] } }),
+            )
+            .await;
+        assert_eq!(status, 200);
+    }
+    let (status, _) = client
+        .get(&format!("/vectors/{}", ids[ids.len() - 1]))
+        .await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn test_cold_tier_survives_restart() {
+    let dir = TempDir::new("cold_restart");
+    let server = TestServer::start_with_env(
+        dir.path(),
+        &[
+            ("VECTORDB_MAX_HOT_VECTORS", "1"),
+            ("VECTORDB_TIERING_SWEEP_SECS", "1"),
+        ],
+    );
+    let client = server.client();
+    insert_flat(&client, &["a", "b", "c"]).await;
+    wait_for_cold(&client, 2).await;
+    server.stop();
+    assert!(dir.path().join("data/cold.seg").exists());
+
+    // Without the cap nothing would demote them again for 30 days, so
+    // they're cold only if the tier itself was saved
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    let (_, stats) = client.get("/stats").await;
+    assert_eq!(stats["vector_count"], 3, "{}", stats);
+    assert_eq!(stats["cold_vector_count"], 2, "{}", stats);
+    let (status, headers) = client.head("/api/vectors/a").await;
+    assert_eq!(status, 200);
+    assert_eq!(headers["x-vector-tier"], "cold");
+    let (_, headers) = client.head("/api/vectors/c").await;
+    assert_eq!(headers["x-vector-tier"], "hot");
+
+    // Reading promotes it, and that sticks too
+    let (status, body) = client.get("/vectors/a").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"], json!([0.0, 1.0]));
+    let server = server.restart();
+    let client = server.client();
+    let (_, headers) = client.head("/api/vectors/a").await;
+    assert_eq!(headers["x-vector-tier"], "hot");
+    let (_, stats) = client.get("/stats").await;
+    assert_eq!(stats["cold_vector_count"], 1, "{}", stats);
+
+    server.stop();
+}
+
+/// A stand-in S3 bucket: objects PUT under /<bucket>/..., kept in memory,
+/// and the Authorization header of every request
+#[derive(Clone, Default)]
+struct FakeBucket {
+    objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    authorizations: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeBucket {
+    /// Serve the bucket on a free port; returns its endpoint URL
+    async fn serve(&self) -> String {
+        use axum::body::Bytes;
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, Method, StatusCode};
+
+        async fn object(
+            State(bucket): State<FakeBucket>,
+            method: Method,
+            Path(key): Path<String>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> (StatusCode, Vec<u8>) {
+            let authorization = headers
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            bucket
+                .authorizations
+                .lock()
+                .unwrap()
+                .push(authorization.into());
+            let mut objects = bucket.objects.lock().unwrap();
+            if method == Method::PUT {
+                objects.insert(key, body.to_vec());
+                return (StatusCode::OK, Vec::new());
+            }
+            match objects.get(&key) {
+                Some(data) => (StatusCode::OK, data.clone()),
+                None => (StatusCode::NOT_FOUND, b"NoSuchKey".to_vec()),
+            }
+        }
+
+        let app = axum::Router::new()
+            .route("/*key", axum::routing::get(object).put(object))
+            .with_state(self.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{}", addr)
+    }
+}
+
+// Multi-threaded, so the bucket keeps serving while TestServer blocks
+// waiting for the server to start
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cold_tier_in_an_object_store() {
+    let bucket = FakeBucket::default();
+    let endpoint = bucket.serve().await;
+    let store = [
+        ("VECTORDB_COLD_STORE_ENDPOINT", endpoint.as_str()),
+        ("VECTORDB_COLD_STORE_BUCKET", "cold"),
+        ("VECTORDB_COLD_STORE_PREFIX", "node-1/"),
+        ("AWS_ACCESS_KEY_ID", "test-key"),
+        ("AWS_SECRET_ACCESS_KEY", "test-secret"),
+    ];
+    let dir = TempDir::new("cold_object_store");
+    let mut env = store.to_vec();
+    env.extend([
+        ("VECTORDB_MAX_HOT_VECTORS", "1"),
+        ("VECTORDB_TIERING_SWEEP_SECS", "1"),
+    ]);
+    let server = TestServer::start_with_env(dir.path(), &env);
+    let client = server.client();
+    insert_flat(&client, &["a", "b", "c"]).await;
+    wait_for_cold(&client, 2).await;
+    server.stop();
+
+    // The segment went to the bucket, signed, and not to the data dir
+    assert!(!dir.path().join("data/cold.seg").exists());
+    let objects = bucket.objects.lock().unwrap().clone();
+    assert_eq!(objects.keys().collect::<Vec<_>>(), ["cold/node-1/cold.seg"]);
+    assert!(objects["cold/node-1/cold.seg"].starts_with(b"COLD"));
+    for authorization in bucket.authorizations.lock().unwrap().iter() {
+        assert!(
+            authorization.starts_with("AWS4-HMAC-SHA256 Credential=test-key/"),
+            "{}",
+            authorization
+        );
+    }
+
+    let server = TestServer::start_with_env(dir.path(), &store);
+    let client = server.client();
+    let (_, stats) = client.get("/stats").await;
+    assert_eq!(stats["vector_count"], 3, "{}", stats);
+    assert_eq!(stats["cold_vector_count"], 2, "{}", stats);
+    let (_, body) = client.get("/vectors/b").await;
+    assert_eq!(body["data"], json!([1.0, 1.0]));
+    server.stop();
+
+    // Missing credentials are a config error, not a failed upload later
+    let dir = TempDir::new("cold_object_store_no_keys");
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_vectordb"))
+        .current_dir(dir.path())
+        .env("VECTORDB_COLD_STORE_ENDPOINT", &endpoint)
+        .env("VECTORDB_COLD_STORE_BUCKET", "cold")
+        .env_remove("AWS_ACCESS_KEY_ID")
+        .env_remove("AWS_SECRET_ACCESS_KEY")
+        .stderr(std::process::Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(2));
+}
+
+/// Write `count` points without IDs to `collection`, returning the IDs
+/// the server gave them
+async fn generate_ids(client: &Client, collection: &str, count: usize) -> Vec<String> {
+    let points: Vec<Value> = (0..count).map(|_| json!({ "vector": [1.0] })).collect();
+    let (status, body) = client
+        .post(
+            &format!("/api/collections/{}/points", collection),
+            json!({ "points": points }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_auto_increment_ids_are_never_reused_after_restart() {
+    let dir = TempDir::new("auto_increment");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    let (status, body) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "tickets", "dimension": 1, "id_strategy": "auto_increment" }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(generate_ids(&client, "tickets", 3).await, ["1", "2", "3"]);
+    let (status, _) = client.delete("/api/collections/tickets/points/3").await;
+    assert_eq!(status, 200);
+
+    // Recovered from the log...
+    let server = server.crash();
+    let client = server.client();
+    assert_eq!(generate_ids(&client, "tickets", 1).await, ["4"]);
+    let (status, _) = client.delete("/api/collections/tickets/points/4").await;
+    assert_eq!(status, 200);
+
+    // ...and from a snapshot, which no longer holds point 4
+    let server = server.restart();
+    let client = server.client();
+    assert_eq!(generate_ids(&client, "tickets", 1).await, ["5"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_rename_and_trash_survive_restart() {
+    let dir = TempDir::new("trash");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("prod", 2).await;
+    client
+        .upsert("prod", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+    let (status, _) = client
+        .post(
+            "/api/aliases",
+            json!({ "name": "live", "collection": "prod" }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    // The alias follows the rename
+    let (status, body) = client
+        .post("/api/collections/prod/rename", json!({ "to": "prod_v1" }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["name"], "prod_v1");
+    assert_eq!(client.search("live", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    // Still routed to, so it can't be deleted yet
+    let (status, _) = client.delete("/api/collections/prod_v1").await;
+    assert_eq!(status, 409);
+    client.delete("/api/aliases/live").await;
+    let (status, body) = client.delete("/api/collections/prod_v1").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["status"], "trashed");
+    let (status, _) = client.get("/api/collections/prod_v1").await;
+    assert_eq!(status, 404);
+
+    // Crash before any snapshot: rename and delete come back from the log
+    let server = server.crash();
+    let client = server.client();
+    let (_, trash) = client.get("/api/trash").await;
+    assert_eq!(trash[0]["name"], "prod_v1");
+    assert_eq!(trash[0]["count"], 2);
+
+    let (status, body) = client.post("/api/trash/prod_v1/restore", json!({})).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["count"], 2);
+
+    // Snapshotted on a clean restart, then purged for good
+    let server = server.restart();
+    let client = server.client();
+    assert_eq!(client.search("prod_v1", &[0.0, 1.0], 1).await, vec!["b"]);
+    let (status, _) = client.delete("/api/collections/prod_v1").await;
+    assert_eq!(status, 200);
+    let (status, _) = client.delete("/api/trash/prod_v1").await;
+    assert_eq!(status, 204);
+    let (_, trash) = client.get("/api/trash").await;
+    assert_eq!(trash, json!([]));
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_aliases_survive_crash_and_restart() {
+    let dir = TempDir::new("aliases");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("v1", 2).await;
+    client.create_collection("v2", 2).await;
+    client.upsert("v1", &[("a", vec![1.0, 0.0])]).await;
+    client.upsert("v2", &[("b", vec![1.0, 0.0])]).await;
+    for (name, body) in [
+        ("live", json!({ "name": "live", "collection": "v1" })),
+        ("gone", json!({ "name": "gone", "collection": "v1" })),
+        (
+            "split",
+            json!({ "name": "split", "collection": "v1",
+                    "experiment": { "collection": "v2", "percent": 100 } }),
+        ),
+    ] {
+        let (status, body) = client.post("/api/aliases", body).await;
+        assert_eq!(status, 201, "{}: {}", name, body);
+    }
+    let (status, _) = client.delete("/api/aliases/gone").await;
+    assert_eq!(status, 204);
+
+    // Crash before any snapshot: the aliases come back from the log
+    let server = server.crash();
+    let client = server.client();
+    assert_eq!(client.search("live", &[1.0, 0.0], 1).await, vec!["a"]);
+    assert_eq!(client.search("split", &[1.0, 0.0], 1).await, vec!["b"]);
+    let (status, _) = client.get("/api/aliases/gone").await;
+    assert_eq!(status, 404);
+
+    // Re-pointed, then snapshotted on a clean restart
+    let (status, _) = client
+        .post(
+            "/api/aliases",
+            json!({ "name": "live", "collection": "v2" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let server = server.restart();
+    let client = server.client();
+    let (_, aliases) = client.get("/api/aliases").await;
+    let names: Vec<_> = aliases
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| {
+            (
+                a["name"].as_str().unwrap(),
+                a["collection"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(names, [("live", "v2"), ("split", "v1")]);
+    assert_eq!(aliases[1]["experiment"]["percent"], 100.0);
+    assert_eq!(client.search("live", &[1.0, 0.0], 1).await, vec!["b"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_shadow_changes_survive_crash() {
+    let dir = TempDir::new("shadow");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client.create_collection("docs_v2", 2).await;
+    let (status, body) = client
+        .put(
+            "/api/collections/docs/shadow",
+            json!({ "collection": "docs_v2" }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["shadow"], "docs_v2");
+
+    // Attached before the crash: still mirroring after it
+    let server = server.crash();
+    let client = server.client();
+    let (_, body) = client.get("/api/collections/docs").await;
+    assert_eq!(body["shadow"], "docs_v2", "{}", body);
+    let mirrored = |id: &str, vector: [f32; 2]| json!({ "points": [{ "id": id, "vector": vector, "shadow_vector": vector }] });
+    let (status, body) = client
+        .post("/api/collections/docs/points", mirrored("a", [1.0, 0.0]))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(client.search("docs_v2", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    // Cleared before the crash: no longer mirroring after it
+    let (status, _) = client.delete("/api/collections/docs/shadow").await;
+    assert_eq!(status, 200);
+    let server = server.crash();
+    let client = server.client();
+    let (_, body) = client.get("/api/collections/docs").await;
+    assert!(body["shadow"].is_null(), "{}", body);
+    let (status, body) = client
+        .post("/api/collections/docs/points", mirrored("b", [0.0, 1.0]))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(client.search("docs_v2", &[0.0, 1.0], 2).await, vec!["a"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_deletes_are_mirrored_into_the_shadow() {
+    let dir = TempDir::new("shadow_deletes");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client.create_collection("docs_v2", 2).await;
+    let (status, body) = client
+        .put(
+            "/api/collections/docs/shadow",
+            json!({ "collection": "docs_v2" }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let points: Vec<Value> = [("a", [1.0, 0.0]), ("b", [0.8, 0.6]), ("c", [0.0, 1.0])]
+        .iter()
+        .map(|(id, v)| json!({ "id": id, "vector": v, "shadow_vector": v }))
+        .collect();
+    let (status, body) = client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["mirrored"], 3, "{}", body);
+
+    let (status, body) = client.delete("/api/collections/docs/points/a").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        client.search("docs_v2", &[1.0, 0.0], 3).await,
+        vec!["b", "c"]
+    );
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/transactions",
+            json!({ "operations": [{ "op": "delete", "id": "b" }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(client.search("docs_v2", &[1.0, 0.0], 3).await, vec!["c"]);
+
+    // The mirrored deletes were logged with the collection's own
+    let server = server.crash();
+    let client = server.client();
+    assert_eq!(client.search("docs_v2", &[1.0, 0.0], 3).await, vec!["c"]);
+    assert_eq!(client.search("docs", &[1.0, 0.0], 3).await, vec!["c"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_protected_collection_needs_force_and_admin_key() {
+    let dir = TempDir::new("protected");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_ADMIN_KEY", "sekrit")]);
+    let client = server.client();
+    let admin = server.client_with_key("sekrit");
+
+    client.create_collection("main", 2).await;
+    client.upsert("main", &[("a", vec![1.0, 0.0])]).await;
+    let (status, _) = client
+        .put(
+            "/api/admin/collections/main/protection",
+            json!({ "protected": true }),
+        )
+        .await;
+    assert_eq!(status, 401);
+    let (status, body) = admin
+        .put(
+            "/api/admin/collections/main/protection",
+            json!({ "protected": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["protected"], true);
+
+    // Protection is part of the config, so it survives a crash
+    let server = server.crash();
+    let client = server.client();
+    let admin = server.client_with_key("sekrit");
+
+    let (status, _) = client.delete("/api/collections/main").await;
+    assert_eq!(status, 409);
+    let (status, _) = client.delete("/api/collections/main?force=true").await;
+    assert_eq!(status, 403);
+    let (status, _) = client
+        .post("/api/collections/main/rename", json!({ "to": "moved" }))
+        .await;
+    assert_eq!(status, 409);
+    let (status, _) = client
+        .post(
+            "/api/collections/main/rename?force=true",
+            json!({ "to": "moved" }),
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = client
+        .post("/api/collections/main/normalize?force=true", json!({}))
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = client
+        .post("/api/collections/main/normalize?dry_run=true", json!({}))
+        .await;
+    assert_eq!(status, 202);
+
+    let (status, body) = admin
+        .post(
+            "/api/collections/main/rename?force=true",
+            json!({ "to": "kept" }),
+        )
+        .await;
+    assert_eq!(
+        (status, &body["protected"]),
+        (200, &json!(true)),
+        "{}",
+        body
+    );
+    let (status, body) = admin.delete("/api/collections/kept?force=true").await;
+    assert_eq!(status, 200, "{}", body);
+    // Still protected in the trash
+    let (status, _) = client.delete("/api/trash/kept?force=true").await;
+    assert_eq!(status, 403);
+    let (status, _) = admin.delete("/api/trash/kept?force=true").await;
+    assert_eq!(status, 204);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_read_only_collection_rejects_writes_but_serves_searches() {
+    let dir = TempDir::new("read_only");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("held", 2).await;
+    client
+        .upsert("held", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+    let (status, body) = client
+        .put(
+            "/api/admin/collections/held/read_only",
+            json!({ "read_only": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["read_only"], true);
+
+    // The freeze is part of the config, so it survives a crash
+    let server = server.crash();
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/collections/held/points",
+            json!({ "points": [{ "id": "c", "vector": [1.0, 1.0] }] }),
+        )
+        .await;
+    assert_eq!(status, 423, "{}", body);
+    let (status, _) = client.delete("/api/collections/held/points/a").await;
+    assert_eq!(status, 423);
+    let (status, _) = client.delete("/api/collections/held").await;
+    assert_eq!(status, 423);
+    let (status, _) = client
+        .post("/api/collections/held/rename", json!({ "to": "moved" }))
+        .await;
+    assert_eq!(status, 423);
+    assert_eq!(client.search("held", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    let (status, _) = client
+        .put(
+            "/api/admin/collections/held/read_only",
+            json!({ "read_only": false }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    client.upsert("held", &[("c", vec![1.0, 1.0])]).await;
+    let (_, body) = client.get("/api/collections/held").await;
+    assert_eq!(
+        (body["count"].as_u64(), &body["read_only"]),
+        (Some(3), &json!(false))
+    );
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_search_stream_sends_one_event_per_hit() {
+    let dir = TempDir::new("search_stream");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert(
+            "docs",
+            &[
+                ("a", vec![1.0, 0.0]),
+                ("b", vec![0.7, 0.7]),
+                ("c", vec![0.0, 1.0]),
+            ],
+        )
+        .await;
+
+    let (status, body) = client
+        .get_text("/api/search/stream?collection=docs&vector=1,0.1&top_k=2")
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let events: Vec<(&str, Value)> = body
+        .split("\n\n")
+        .filter_map(|event| {
+            let name = event.lines().find_map(|l| l.strip_prefix("event: "))?;
+            let data = event.lines().find_map(|l| l.strip_prefix("data: "))?;
+            Some((name, serde_json::from_str(data).unwrap()))
+        })
+        .collect();
+    assert_eq!(events.len(), 3, "{}", body);
+    assert_eq!((events[0].0, &events[0].1["id"]), ("result", &json!("a")));
+    assert_eq!(
+        (events[1].1["rank"].as_u64(), &events[1].1["id"]),
+        (Some(2), &json!("b"))
+    );
+    assert_eq!(
+        (events[2].0, events[2].1["count"].as_u64()),
+        ("done", Some(2))
+    );
+
+    // Problems found before streaming starts are plain JSON errors
+    let (status, _) = client
+        .get("/api/search/stream?collection=docs&vector=1,x")
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = client
+        .get("/api/search/stream?collection=nope&vector=1,0")
+        .await;
+    assert_eq!(status, 404);
+
+    server.stop();
+}
+
+#[cfg(feature = "wasm")]
+#[tokio::test]
+async fn test_udfs_filter_search_results_and_survive_restart() {
+    // Keeps IDs whose first character is "a" (the byte after `{"id":"`)
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "filter") (param $ptr i32) (param i32) (result i32)
+            (i32.eq (i32.load8_u offset=7 (local.get $ptr)) (i32.const 97))))
+    "#;
+    let dir = TempDir::new("udfs");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert(
+            "docs",
+            &[
+                ("b1", vec![1.0, 0.0]),
+                ("a1", vec![0.9, 0.1]),
+                ("a2", vec![0.0, 1.0]),
+            ],
+        )
+        .await;
+    let (status, body) = client.put_bytes("/api/admin/udfs/only_a", MODULE).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        (body["filter"].as_bool(), body["score"].as_bool()),
+        (Some(true), Some(false))
+    );
+
+    let server = server.restart();
+    let client = server.client();
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "top_k": 2, "filter_udf": "only_a" }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(
+        (&body[0]["id"], &body[1]["id"]),
+        (&json!("a1"), &json!("a2"))
+    );
+
+    // Asking a filter-only module to score is a client error
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "score_udf": "only_a" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = client.delete("/api/admin/udfs/only_a").await;
+    assert_eq!(status, 204);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_reports_routes_searches_and_sizes() {
+    let dir = TempDir::new("metrics");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert("docs", &[("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])])
+        .await;
+    client.search("docs", &[1.0, 0.0], 1).await;
+
+    let (status, text) = client.get_text("/metrics").await;
+    assert_eq!(status, 200);
+    for expected in [
+        "vectordb_http_requests_total{method=\"POST\",route=\"/api/collections/:name/search\",status=\"200\"} 1",
+        "vectordb_search_duration_seconds_count{collection=\"docs\"} 1",
+        "vectordb_collection_vectors{collection=\"docs\"} 2",
+        "# TYPE vectordb_http_request_duration_seconds histogram",
+    ] {
+        assert!(text.contains(expected), "missing {}\n{}", expected, text);
+    }
+    let wal_bytes = text
+        .lines()
+        .find_map(|l| l.strip_prefix("vectordb_wal_bytes "))
+        .and_then(|v| v.parse::<u64>().ok());
+    assert!(wal_bytes.is_some_and(|b| b > 0), "{}", text);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_result_cache_serves_repeats_until_a_write() {
+    let dir = TempDir::new("result_cache");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_RESULT_CACHE_SIZE", "16")]);
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client.upsert("docs", &[("a", vec![0.5, 0.5])]).await;
+    assert_eq!(client.search("docs", &[1.0, 0.0], 1).await, vec!["a"]);
+    assert_eq!(client.search("docs", &[1.0, 0.0], 1).await, vec!["a"]);
+
+    // The write moves the collection to a new epoch; the next search misses
+    client.upsert("docs", &[("b", vec![1.0, 0.0])]).await;
+    assert_eq!(client.search("docs", &[1.0, 0.0], 1).await, vec!["b"]);
+
+    let (_, text) = client.get_text("/metrics").await;
+    for expected in [
+        "vectordb_result_cache_lookups_total{result=\"hit\"} 1",
+        "vectordb_result_cache_lookups_total{result=\"miss\"} 2",
+    ] {
+        assert!(text.contains(expected), "missing {}\n{}", expected, text);
+    }
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_calibration_changes_are_not_served_from_the_cache() {
+    let dir = TempDir::new("cache_calibration");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_RESULT_CACHE_SIZE", "16")]);
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+    client.upsert("docs", &[("a", vec![0.6, 0.8])]).await;
+    let search = || async {
+        let (status, body) = client
+            .post(
+                "/api/collections/docs/search",
+                json!({ "vector": [1.0, 0.0], "top_k": 1 }),
+            )
+            .await;
+        assert_eq!(status, 200, "{}", body);
+        body[0]["probability"].clone()
+    };
+    assert!(search().await.is_null());
+
+    let pairs: Vec<_> = [(0.9, true), (0.8, true), (0.7, true)]
+        .into_iter()
+        .chain([(0.1, false), (0.2, false), (0.3, false)])
+        .map(|(score, relevant)| json!({ "score": score, "relevant": relevant }))
+        .collect();
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/calibration",
+            json!({ "pairs": pairs }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(
+        search().await.is_number(),
+        "cached hit without a probability"
+    );
+
+    let (status, _) = client.delete("/api/collections/docs/calibration").await;
+    assert_eq!(status, 204);
+    assert!(
+        search().await.is_null(),
+        "cached hit with a stale probability"
+    );
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_collection_stats_estimate_distinct_values() {
+    let dir = TempDir::new("field_stats");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/collections",
+            json!({
+                "name": "docs",
+                "dimension": 2,
+                "schema": { "fields": [
+                    { "name": "category", "type": "string" },
+                    { "name": "price", "type": "integer" },
+                ] },
+            }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    let points: Vec<Value> = (0..40)
+        .map(|i| {
+            json!({
+                "id": format!("p{}", i),
+                "vector": [1.0, i as f32],
+                "metadata": {
+                    "category": format!("c{}", i % 8),
+                    "price": (i * 10).to_string(),
+                },
+            })
+        })
+        .collect();
+    let (status, body) = client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    // Sketches are rebuilt from the stored points on restart
+    let server = server.restart();
+    let (status, body) = server.client().get("/api/collections/docs/stats").await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["count"], 40);
+    assert_eq!(body["fields"]["category"]["distinct"], 8);
+    assert_eq!(body["fields"]["category"]["eq_selectivity"], 0.125);
+    assert!(body["fields"]["category"].get("histogram").is_none());
+
+    let (status, body) = server
+        .client()
+        .get("/api/collections/docs/stats?percentiles=0.5,1&filter=price%20%3C%20100")
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let price = &body["fields"]["price"];
+    assert_eq!(price["histogram"]["bounds"][0], 0.0);
+    assert_eq!(price["percentiles"]["1"], 390.0);
+    assert_eq!(price["percentiles"]["0.5"], 195.0);
+    assert_eq!(body["filter"]["estimated_matches"], 10);
+
+    let (status, _) = server
+        .client()
+        .get("/api/collections/docs/stats?percentiles=50")
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_query_template_runs_registered_filter() {
+    let dir = TempDir::new("templates");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    let points: Vec<Value> = [("n1", "news", 1.0), ("b1", "blog", 0.9), ("n2", "news", 0.1)]
+        .iter()
+        .map(|(id, category, x)| {
+            json!({ "id": id, "vector": [*x, 1.0 - *x], "metadata": { "category": category } })
+        })
+        .collect();
+    let (status, body) = client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = client
+        .put(
+            "/api/search/template/news",
+            json!({ "collection": "docs", "filter": "category == \"news\"", "top_k": 5 }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    let (status, _) = client
+        .put(
+            "/api/search/template/broken",
+            json!({ "collection": "docs", "filter": "category ==" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = client
+        .put("/api/search/template/lost", json!({ "collection": "nope" }))
+        .await;
+    assert_eq!(status, 404);
+
+    let (status, body) = client
+        .post("/api/search/template/news", json!({ "vector": [1.0, 0.0] }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let ids: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["n1", "n2"]);
+
+    let (status, _) = client
+        .post("/api/search/template/news", json!({ "vector": [1.0] }))
+        .await;
+    assert_eq!(status, 400);
+
+    let (_, body) = client.get("/api/search/templates").await;
+    assert_eq!(body[0]["name"], "news");
+    assert_eq!(body[0]["invocations"], 2);
+
+    // Templates come back from the log after a crash, and from the
+    // snapshot after a clean restart
+    let (status, _) = client
+        .put("/api/search/template/old", json!({ "collection": "docs" }))
+        .await;
+    assert_eq!(status, 201);
+    let (status, _) = client.delete("/api/search/template/old").await;
+    assert_eq!(status, 204);
+    let server = server.crash().restart();
+    let client = server.client();
+    let (_, body) = client.get("/api/search/templates").await;
+    assert_eq!(body.as_array().unwrap().len(), 1, "{}", body);
+    assert_eq!(body[0]["filter"], "category == \"news\"");
+    assert_eq!(body[0]["top_k"], 5);
+    let (status, body) = client
+        .post("/api/search/template/news", json!({ "vector": [1.0, 0.0] }))
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    let (status, _) = client.delete("/api/search/template/news").await;
+    assert_eq!(status, 204);
+    let (status, _) = client
+        .post("/api/search/template/news", json!({ "vector": [1.0, 0.0] }))
+        .await;
+    assert_eq!(status, 404);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_batch_search_returns_one_list_per_query() {
+    let dir = TempDir::new("batch_search");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("docs", 2).await;
+    client
+        .upsert(
+            "docs",
+            &[
+                ("east", vec![1.0, 0.0]),
+                ("north", vec![0.0, 1.0]),
+                ("diagonal", vec![0.7, 0.7]),
+            ],
+        )
+        .await;
+
+    let (status, body) = client
+        .post(
+            "/api/search/batch",
+            json!({ "collection": "docs", "vectors": [[0.0, 1.0], [1.0, 0.1]], "top_k": 2 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let lists = body.as_array().unwrap();
+    assert_eq!(lists.len(), 2);
+    assert_eq!(lists[0][0]["id"], "north");
+    assert_eq!(lists[1][0]["id"], "east");
+    assert_eq!(lists[1].as_array().unwrap().len(), 2);
+    assert_eq!(
+        client.search("docs", &[1.0, 0.1], 2).await,
+        ["east", "diagonal"]
+    );
+
+    let (status, _) = client
+        .post(
+            "/api/search/batch",
+            json!({ "collection": "docs", "vectors": [[0.0, 1.0], [1.0]] }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_radius_search_pages_with_a_cursor() {
+    let dir = TempDir::new("radius");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_MAX_RADIUS_RESULTS", "3")]);
+    let client = server.client();
+
+    let (status, _) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "docs", "dimension": 1, "distance": "euclidean" }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let points: Vec<_> = (0..20)
+        .map(|i| (format!("p{:02}", i), vec![i as f32]))
+        .collect();
+    let points: Vec<_> = points
+        .iter()
+        .map(|(id, v)| (id.as_str(), v.clone()))
+        .collect();
+    client.upsert("docs", &points).await;
+
+    // top_k asks for 10 per page, the server caps it at 3
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut req = json!({ "vector": [0.0], "top_k": 10, "radius": 7.5 });
+        if let Some(cursor) = &cursor {
+            req["cursor"] = json!(cursor);
+        }
+        let (status, headers, body) = client
+            .post_with_headers("/api/collections/docs/search", req)
+            .await;
+        assert_eq!(status, 200, "{}", body);
+        let page = body.as_array().unwrap();
+        assert!(page.len() <= 3);
+        seen.extend(page.iter().map(|r| r["id"].as_str().unwrap().to_string()));
+        match headers.get("x-next-cursor") {
+            Some(next) => cursor = Some(next.to_str().unwrap().to_string()),
+            None => break,
+        }
+    }
+    let expected: Vec<_> = (0..8).map(|i| format!("p{:02}", i)).collect();
+    assert_eq!(seen, expected);
+
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [0.0], "top_k": 10, "radius": 1.0, "cursor": "garbage" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_index_builds_in_the_background() {
+    let dir = TempDir::new("index_build");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, _) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "docs", "dimension": 2, "distance": "euclidean" }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let points: Vec<Value> = (0..300)
+        .map(|i| json!({ "id": format!("p{}", i), "vector": [i as f32, 0.0] }))
+        .collect();
+    let (status, _) = client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+    assert_eq!(status, 200);
+
+    let (status, body) = client
+        .post("/api/collections/docs/index", json!({ "hnsw": { "m": 1 } }))
+        .await;
+    assert_eq!(status, 400, "{}", body);
+    let (status, body) = client
+        .post("/api/collections/docs/index/relayout", json!({}))
+        .await;
+    assert_eq!(status, 400, "no graph to lay out: {}", body);
+    let (status, body) = client
+        .post("/api/collections/docs/index", json!({ "hnsw": { "m": 8 } }))
+        .await;
+    assert_eq!(status, 202, "{}", body);
+    let job_id = body["job_id"].as_u64().unwrap();
+
+    let mut job = Value::Null;
+    for _ in 0..200 {
+        job = client.get(&format!("/api/jobs/{}", job_id)).await.1;
+        if job["status"] != "running" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(job["status"], "completed", "{}", job);
+    assert_eq!(job["kind"], "index_build");
+    assert_eq!(job["progress"], 1.0);
+    assert_eq!(job["result"]["nodes"], 300);
+
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["type"], "hnsw");
+    assert_eq!(client.search("docs", &[42.2, 0.0], 2).await, ["p42", "p43"]);
+
+    // The index is part of the collection's config, so a crash keeps it
+    let server = server.crash();
+    let client = server.client();
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["type"], "hnsw");
+    assert_eq!(index["parameters"]["m"], 8);
+
+    // Renumbering the graph leaves results alone
+    let (status, layout) = client
+        .post("/api/collections/docs/index/relayout", json!({}))
+        .await;
+    assert_eq!(status, 200, "{}", layout);
+    assert_eq!(layout["nodes"], 300);
+    assert_eq!(client.search("docs", &[42.2, 0.0], 2).await, ["p42", "p43"]);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_auto_index_builds_past_the_threshold() {
+    let dir = TempDir::new("auto_index");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/collections",
+            json!({
+                "name": "docs",
+                "dimension": 2,
+                "distance": "euclidean",
+                "auto_index": { "threshold": 100, "hnsw": { "m": 8 } }
+            }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(body["auto_index"]["threshold"], 100);
+    let points: Vec<Value> = (0..99)
+        .map(|i| json!({ "id": format!("p{}", i), "vector": [i as f32, 0.0] }))
+        .collect();
+    client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+
+    // Small collections stay on exact scans
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let (_, stats) = client.get("/api/collections/docs/stats").await;
+    assert_eq!(stats["index"]["state"], "below_threshold", "{}", stats);
+    assert_eq!(stats["index"]["auto_index_threshold"], 100);
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["type"], "flat");
+
+    client.upsert("docs", &[("p99", vec![99.0, 0.0])]).await;
+    let mut stats = Value::Null;
+    for _ in 0..100 {
+        stats = client.get("/api/collections/docs/stats").await.1;
+        if stats["index"]["state"] == "indexed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(stats["index"]["state"], "indexed", "{}", stats);
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["type"], "hnsw");
+    assert_eq!(index["parameters"]["m"], 8);
+    assert_eq!(index["nodes"], 100);
+
+    // Later writes wait outside the graph until the memtable is sealed,
+    // and searches score them exactly meanwhile
+    client.upsert("docs", &[("p100", vec![100.0, 0.0])]).await;
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["nodes"], 100);
+    assert_eq!(index["memtable"]["unindexed"], 1);
+    assert_eq!(
+        client.search("docs", &[100.2, 0.0], 2).await,
+        ["p100", "p99"]
+    );
+
+    let server = server.crash();
+    let client = server.client();
+    let (_, stats) = client.get("/api/collections/docs/stats").await;
+    assert_eq!(stats["index"]["state"], "indexed", "{}", stats);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_hnsw_collection_searches_through_the_graph() {
+    let dir = TempDir::new("hnsw");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "docs", "dimension": 2, "distance": "euclidean", "hnsw": { "m": 8 } }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(body["hnsw"]["m"], 8);
+    assert_eq!(body["hnsw"]["ef_search"], 64);
+
+    let points: Vec<Value> = (0..50)
+        .map(|i| json!({ "id": format!("p{}", i), "vector": [i as f32, 0.0] }))
+        .collect();
+    let (status, _) = client
+        .post("/api/collections/docs/points", json!({ "points": points }))
+        .await;
+    assert_eq!(status, 200);
+
+    assert_eq!(client.search("docs", &[10.2, 0.0], 2).await, ["p10", "p11"]);
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [10.2, 0.0], "top_k": 1, "exact": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "p10");
+
+    let (_, index) = client.get("/api/collections/docs/index").await;
+    assert_eq!(index["type"], "hnsw");
+    assert_eq!(index["exact"], false);
+    let graphed = index["nodes"].as_u64().unwrap();
+    assert_eq!(
+        graphed + index["memtable"]["unindexed"].as_u64().unwrap(),
+        50
+    );
+
+    // The graph only ranks by the metric it was built for
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "metric": "cosine" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    let (status, _) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "bad", "dimension": 2, "hnsw": { "m": 1 } }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_binary_collection_stores_packed_bits() {
+    let dir = TempDir::new("binary");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    let (status, body) = client
+        .post(
+            "/api/collections",
+            json!({ "name": "codes", "dimension": 256, "vector_type": "binary" }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(body["vector_type"], "binary");
+    assert_eq!(body["distance"], "hamming");
+
+    // Point i has its first i bits set
+    let bits = |i: usize| -> Vec<f32> { (0..256).map(|d| (d < i) as u8 as f32).collect() };
+    let points: Vec<(String, Vec<f32>)> = (0..64).map(|i| (format!("p{}", i), bits(i))).collect();
+    let refs: Vec<(&str, Vec<f32>)> = points
+        .iter()
+        .map(|(id, v)| (id.as_str(), v.clone()))
+        .collect();
+    client.upsert("codes", &refs).await;
+    let (status, body) = client
+        .post(
+            "/api/collections/codes/points",
+            json!({ "points": [{ "id": "half", "vector": vec![0.5; 256] }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["results"][0]["status"], "failed");
+    assert!(body["results"][0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("only 0s and 1s"));
+
+    assert_eq!(client.search("codes", &bits(20), 3).await[0], "p20");
+
+    // Snapshotted on a clean restart: 64 × 256 bits is 2 KiB of vectors,
+    // where f32 would take 64 KiB
+    let server = server.restart();
+    let client = server.client();
+    let snapshot = std::fs::metadata(dir.path().join("data/collection.codes.vec")).unwrap();
+    assert!(snapshot.len() < 8 * 1024, "{} bytes", snapshot.len());
+    let (status, body) = client
+        .post(
+            "/api/collections/codes/search",
+            json!({ "vector": bits(41), "top_k": 2 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "p41");
+    assert_eq!(body[0]["score"], 0.0);
+    assert_eq!(body[1]["score"], 1.0);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_writes_return_sequence_numbers_searches_wait_on() {
+    let dir = TempDir::new("min_seq");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/points",
+            json!({ "points": [{ "id": "a", "vector": [1.0, 0.0] }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let upserted = body["seq"].as_u64().unwrap();
+    let (status, body) = client
+        .put(
+            "/api/collections/docs/points/b",
+            json!({ "vector": [0.0, 1.0] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let put = body["seq"].as_u64().unwrap();
+    assert!(put > upserted);
+
+    // A write that's already applied doesn't hold the search up
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [0.0, 1.0], "top_k": 1, "min_seq": put }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "b");
+
+    // One that never comes times out
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [0.0, 1.0], "top_k": 1, "min_seq": put + 100 }),
+        )
+        .await;
+    assert_eq!(status, 503, "{}", body);
+
+    // Numbers aren't reused after a restart
+    let server = server.restart();
+    let client = server.client();
+    let (status, body) = client.delete("/api/collections/docs/points/a").await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["seq"].as_u64().unwrap() > put);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_sparse_vectors_are_searched_by_dot_product() {
+    let dir = TempDir::new("sparse");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/points",
+            json!({ "points": [
+                { "id": "a", "vector": [1.0, 0.0],
+                  "sparse": { "indices": [40, 7], "values": [2.0, 1.0] } },
+                { "id": "b", "vector": [0.0, 1.0],
+                  "sparse": { "indices": [7], "values": [0.5] } },
+                { "id": "c", "vector": [1.0, 1.0] },
+            ] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let query = json!({ "vector": { "indices": [7, 40], "values": [1.0, 1.0] }, "top_k": 5 });
+    let (status, body) = client
+        .post("/api/collections/docs/search/sparse", query.clone())
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let ids: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].clone())
+        .collect();
+    assert_eq!(ids, vec![json!("a"), json!("b")], "{}", body);
+    assert_eq!(body[0]["score"], 3.0);
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search/sparse",
+            json!({ "vector": { "indices": [1, 1], "values": [1.0, 1.0] } }),
+        )
+        .await;
+    assert_eq!(status, 400, "{}", body);
+
+    // Replacing just the metadata keeps the stored embedding
+    let (status, body) = client
+        .put(
+            "/api/collections/docs/points/a",
+            json!({ "metadata": { "lang": "en" } }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (_, point) = client.get("/api/collections/docs/points/a").await;
+    assert_eq!(point["vector"]["metadata"]["lang"], "en", "{}", point);
+    let (_, body) = client
+        .post("/api/collections/docs/search/sparse", query.clone())
+        .await;
+    assert_eq!(body[0]["id"], "a", "{}", body);
+    assert_eq!(body[0]["score"], 3.0);
+
+    // ...and a PUT can replace the embedding too
+    let (status, body) = client
+        .put(
+            "/api/collections/docs/points/c",
+            json!({ "sparse": { "indices": [7], "values": [0.25] } }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (_, body) = client
+        .post("/api/collections/docs/search/sparse", query.clone())
+        .await;
+    assert_eq!(body.as_array().unwrap().len(), 3, "{}", body);
+    assert_eq!(body[2]["id"], "c");
+
+    // The embeddings outlive a restart
+    let server = server.restart();
+    let client = server.client();
+    let (status, body) = client
+        .post("/api/collections/docs/search/sparse", query)
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "a");
+    assert_eq!(body.as_array().unwrap().len(), 3);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_scoped_keys_limit_collections_and_verbs() {
+    let dir = TempDir::new("scoped_keys");
+    let keys_dir = TempDir::new("scoped_keys_file");
+    let keys = keys_dir.path().join("keys.json");
+    std::fs::write(
+        &keys,
+        json!({ "keys": [
+            { "key": "searcher", "collections": ["docs-*"], "verbs": ["search"] },
+            { "key": "ops" },
+        ] })
+        .to_string(),
+    )
+    .unwrap();
+    let server =
+        TestServer::start_with_env(dir.path(), &[("VECTORDB_API_KEYS", keys.to_str().unwrap())]);
+    let anonymous = server.client();
+    let ops = server.client_with_key("ops");
+    let searcher = server.client_with_key("searcher");
+
+    let (status, _) = anonymous.get("/health").await;
+    assert_eq!(status, 200);
+    let (status, body) = anonymous.get("/api/collections").await;
+    assert_eq!(status, 401, "{}", body);
+    let (status, _) = server
+        .client_with_key("nobody")
+        .get("/api/collections")
+        .await;
+    assert_eq!(status, 401);
+
+    for name in ["docs-en", "private"] {
+        ops.create_collection(name, 2).await;
+        ops.upsert(name, &[("a", vec![1.0, 0.0])]).await;
+    }
+
+    let query = json!({ "vector": [1.0, 0.0], "top_k": 1 });
+    let (status, body) = searcher
+        .post("/api/collections/docs-en/search", query.clone())
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "a");
+
+    // Other collections, other verbs, and requests that could reach any
+    // collection are all off limits
+    let (status, body) = searcher
+        .post("/api/collections/private/search", query.clone())
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    assert!(
+        body["message"].as_str().unwrap().contains("private"),
+        "{}",
+        body
+    );
+    let (status, _) = searcher
+        .post(
+            "/api/collections/docs-en/points",
+            json!({ "points": [{ "id": "b", "vector": [0.0, 1.0] }] }),
+        )
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = searcher.delete("/api/collections/docs-en").await;
+    assert_eq!(status, 403);
+    let (status, _) = searcher
+        .post(
+            "/api/search/batch",
+            json!({ "collection": "private", "queries": [query] }),
+        )
+        .await;
+    assert_eq!(status, 403);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_scoped_keys_cover_every_collection_a_request_reaches() {
+    let dir = TempDir::new("scoped_reach");
+    let keys_dir = TempDir::new("scoped_reach_file");
+    let keys = keys_dir.path().join("keys.json");
+    std::fs::write(
+        &keys,
+        json!({ "keys": [
+            { "key": "tenant", "collections": ["docs-*"] },
+            { "key": "ops" },
+        ] })
+        .to_string(),
+    )
+    .unwrap();
+    let server =
+        TestServer::start_with_env(dir.path(), &[("VECTORDB_API_KEYS", keys.to_str().unwrap())]);
+    let ops = server.client_with_key("ops");
+    let tenant = server.client_with_key("tenant");
+    for name in ["docs-a", "hr-private"] {
+        ops.create_collection(name, 2).await;
+        ops.upsert(name, &[("a", vec![1.0, 0.0])]).await;
+    }
+
+    // Mirroring into, or renaming onto, a collection outside the scope
+    let (status, body) = tenant
+        .put(
+            "/api/collections/docs-a/shadow",
+            json!({ "collection": "hr-private" }),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+    let (status, body) = tenant
+        .post("/api/collections/docs-a/rename", json!({ "to": "hr-x" }))
+        .await;
+    assert_eq!(status, 403, "{}", body);
+
+    // A shadow someone else attached can't be searched through compare
+    let (status, _) = ops
+        .put(
+            "/api/collections/docs-a/shadow",
+            json!({ "collection": "hr-private" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    let (status, body) = tenant
+        .post(
+            "/api/collections/docs-a/shadow/compare",
+            json!({ "sample_size": 1, "top_k": 1 }),
+        )
+        .await;
+    assert_eq!(status, 403, "{}", body);
+
+    // Nor can an in-scope alias that routes outside the scope
+    let (status, _) = ops
+        .post(
+            "/api/aliases",
+            json!({ "name": "docs-live", "collection": "hr-private" }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let query = json!({ "vector": [1.0, 0.0], "top_k": 1 });
+    let (status, body) = tenant
+        .post("/api/collections/docs-live/search", query.clone())
+        .await;
+    assert_eq!(status, 403, "{}", body);
+
+    // Names are checked as the handler decodes them
+    let (status, body) = tenant
+        .post("/api/collections/docs%2Da/search", query.clone())
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, _) = tenant
+        .post("/api/collections/hr%2Dprivate/search", query)
+        .await;
+    assert_eq!(status, 403);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_hybrid_search_fuses_dense_and_sparse_rankings() {
+    let dir = TempDir::new("hybrid");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+
+    // "both" is second by either ranking's top two, first once they're fused
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/points",
+            json!({ "points": [
+                { "id": "dense", "vector": [1.0, 0.0],
+                  "sparse": { "indices": [1], "values": [0.1] } },
+                { "id": "both", "vector": [0.9, 0.1],
+                  "sparse": { "indices": [7], "values": [1.0] } },
+                { "id": "sparse", "vector": [0.0, 1.0],
+                  "sparse": { "indices": [7], "values": [2.0] } },
+            ] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search/hybrid",
+            json!({
+                "vector": [1.0, 0.0],
+                "sparse": { "indices": [7], "values": [1.0] },
+                "top_k": 2,
+                "candidates": 2,
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(body[0]["id"], "both", "{}", body);
+
+    // All the weight on the sparse ranking: its order wins
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search/hybrid",
+            json!({
+                "vector": [1.0, 0.0],
+                "sparse": { "indices": [7], "values": [1.0] },
+                "fusion": { "method": "weighted", "dense_weight": 0.0, "sparse_weight": 1.0 },
+            }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "sparse", "{}", body);
+    assert_eq!(body[0]["score"], 1.0);
+
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search/hybrid",
+            json!({
+                "vector": [1.0, 0.0],
+                "sparse": { "indices": [7], "values": [1.0] },
+                "fusion": { "method": "rrf", "k": -5 },
+            }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_oidc_tokens_map_tenant_and_scopes() {
+    let dir = TempDir::new("oidc");
+    let config_dir = TempDir::new("oidc_config");
+    let config = config_dir.path().join("vectordb.toml");
+    let jwks = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/oidc_jwks.json");
+    std::fs::write(
+        &config,
+        format!(
+            "[oidc]\nissuer = \"https://id.example.com\"\njwks = \"{}\"\n\
+             audience = \"vectordb\"\ntenant_claim = \"org\"\n",
+            jwks
+        ),
+    )
+    .unwrap();
+    let server = TestServer::start_with_env(
+        dir.path(),
+        &[
+            ("VECTORDB_CONFIG", config.to_str().unwrap()),
+            ("VECTORDB_ADMIN_KEY", "root"),
+        ],
+    );
+    let tokens: Value = serde_json::from_str(include_str!("fixtures/oidc_tokens.json")).unwrap();
+    let as_token = |name: &str| server.client_with_key(tokens[name].as_str().unwrap());
+
+    let admin = server.client_with_key("root");
+    for name in ["acme-docs", "globex-docs"] {
+        admin.create_collection(name, 2).await;
+        admin.upsert(name, &[("a", vec![1.0, 0.0])]).await;
+    }
+
+    let (status, _) = server.client().get("/api/collections/acme-docs").await;
+    assert_eq!(status, 401);
+    let (status, _) = server
+        .client_with_key("some-key")
+        .get("/api/collections/acme-docs")
+        .await;
+    assert_eq!(status, 401);
+
+    // Alice (tenant acme) may read and search acme's collections only
+    let alice = as_token("acme_search");
+    let query = json!({ "vector": [1.0, 0.0], "top_k": 1 });
+    let (status, body) = alice
+        .post("/api/collections/acme-docs/search", query.clone())
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, _) = alice
+        .post("/api/collections/globex-docs/search", query.clone())
+        .await;
+    assert_eq!(status, 403);
+    let (status, _) = alice
+        .post(
+            "/api/collections/acme-docs/points",
+            json!({ "points": [{ "id": "b", "vector": [0.0, 1.0] }] }),
+        )
+        .await;
+    assert_eq!(status, 403);
+
+    // Bob (tenant globex) has every scope, for globex
+    let (status, body) = as_token("globex_all")
+        .post(
+            "/api/collections/globex-docs/points",
+            json!({ "points": [{ "id": "b", "vector": [0.0, 1.0] }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = as_token("expired")
+        .post("/api/collections/acme-docs/search", query)
+        .await;
+    assert_eq!(status, 401);
+    assert!(
+        body["message"].as_str().unwrap().contains("expired"),
+        "{}",
+        body
+    );
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_search_pages_follow_the_next_page_token() {
+    let dir = TempDir::new("search_pages");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+    let points: Vec<(String, Vec<f32>)> = (0..5)
+        .map(|i| (format!("p{}", i), vec![1.0, i as f32]))
+        .collect();
+    let points: Vec<(&str, Vec<f32>)> = points
+        .iter()
+        .map(|(id, v)| (id.as_str(), v.clone()))
+        .collect();
+    client.upsert("docs", &points).await;
+
+    let mut seen = Vec::new();
+    let mut query = json!({ "vector": [1.0, 0.0], "top_k": 2, "offset": 0 });
+    loop {
+        let (status, headers, body) = client
+            .post_with_headers("/api/collections/docs/search", query.clone())
+            .await;
+        assert_eq!(status, 200, "{}", body);
+        seen.extend(body.as_array().unwrap().iter().map(|h| h["id"].clone()));
+        let Some(token) = headers.get("x-next-page-token") else {
+            break;
+        };
+        query = json!({ "vector": [1.0, 0.0], "top_k": 2, "page_token": token.to_str().unwrap() });
+    }
+    assert_eq!(seen, ["p0", "p1", "p2", "p3", "p4"]);
+
+    // An offset lands on the same page
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "top_k": 2, "offset": 2 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["id"], "p2");
+
+    let (status, _) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "page_token": "bogus" }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_search_returns_vectors_and_metadata_on_request() {
+    let dir = TempDir::new("search_payloads");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+    client.create_collection("docs", 2).await;
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/points",
+            json!({ "points": [{ "id": "a", "vector": [1.0, 0.0], "metadata": { "lang": "en" } }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "top_k": 1 }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body[0].get("vector").is_none() && body[0].get("metadata").is_none());
+
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/search",
+            json!({ "vector": [1.0, 0.0], "top_k": 1, "include_vector": true, "include_metadata": true }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["vector"], json!([1.0, 0.0]));
+    assert_eq!(body[0]["metadata"]["lang"], "en");
+
+    // Every other search takes the same flags
+    let (status, body) = client
+        .post(
+            "/api/collections/docs/points",
+            json!({ "points": [{ "id": "b", "vector": [0.0, 1.0], "metadata": { "lang": "de" },
+                                 "sparse": { "indices": [7], "values": [1.0] } }] }),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, body) = client
+        .put(
+            "/api/search/template/near",
+            json!({ "collection": "docs", "top_k": 1 }),
+        )
+        .await;
+    assert_eq!(status, 201, "{}", body);
+    let both = |mut body: Value| {
+        body["include_vector"] = true.into();
+        body["include_metadata"] = true.into();
+        body
+    };
+    let searches = [
+        (
+            "/api/search/batch",
+            json!({ "collection": "docs", "vectors": [[0.0, 1.0]], "top_k": 1 }),
+            "/0/0",
+        ),
+        (
+            "/api/search/multi",
+            json!({ "collections": ["docs"], "vector": [0.0, 1.0], "top_k": 1 }),
+            "/0",
+        ),
+        (
+            "/api/collections/docs/search/sparse",
+            json!({ "vector": { "indices": [7], "values": [1.0] }, "top_k": 1 }),
+            "/0",
+        ),
+        (
+            "/api/collections/docs/search/hybrid",
+            json!({ "vector": [0.0, 1.0], "sparse": { "indices": [7], "values": [1.0] }, "top_k": 1 }),
+            "/0",
+        ),
+        (
+            "/api/search/template/near",
+            json!({ "vector": [0.0, 1.0] }),
+            "/0",
+        ),
+    ];
+    for (path, request, hit) in searches {
+        let (status, body) = client.post(path, request.clone()).await;
+        assert_eq!(status, 200, "{}: {}", path, body);
+        let hit_of = |body: &Value| body.pointer(hit).cloned().unwrap();
+        assert!(hit_of(&body).get("vector").is_none(), "{}: {}", path, body);
+        let (status, body) = client.post(path, both(request)).await;
+        assert_eq!(status, 200, "{}: {}", path, body);
+        let found = hit_of(&body);
+        assert_eq!(
+            (&found["id"], &found["vector"], &found["metadata"]["lang"]),
+            (&json!("b"), &json!([0.0, 1.0]), &json!("de")),
+            "{}",
+            path
+        );
+    }
+    let (status, body) = client
+        .get_text("/api/search/stream?collection=docs&vector=0,1&top_k=1&include_vector=true&include_metadata=true")
+        .await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(
+        body.contains(r#""vector":[0.0,1.0]"#) && body.contains(r#""lang":"de""#),
+        "{}",
+        body
+    );
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_admin_port_moves_admin_endpoints_and_metrics_off_the_public_listener() {
+    let dir = TempDir::new("admin_listener");
+    let server = TestServer::start_with_env(dir.path(), &[("VECTORDB_ADMIN_PORT", "0")]);
+    let client = server.client();
+    let admin = server.admin_client();
+
+    let (status, _) = client.get("/health").await;
+    assert_eq!(status, 200);
+    let (status, _) = client.get_text("/metrics").await;
+    assert_eq!(status, 404);
+    let (status, _) = client.get("/api/admin/fds").await;
+    assert_eq!(status, 404);
+
+    let (status, text) = admin.get_text("/metrics").await;
+    assert_eq!(status, 200);
+    assert!(text.contains("http_requests_total"), "{}", text);
+    let (status, body) = admin.get("/api/admin/fds").await;
+    assert_eq!(status, 200, "{}", body);
+    let (status, _) = admin.get("/health").await;
+    assert_eq!(status, 404);
+
+    server.stop();
+}
+
+/// GET `path` from an https admin listener at `url`, trusting the test CA
+/// and presenting the client certificate `identity` (a fixture name), if
+/// any. Handshake and I/O failures come back as errors.
+async fn admin_tls_get(url: &str, path: &str, identity: Option<&str>) -> std::io::Result<String> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let fixture =
+        |name: &str| format!("{}/tests/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let mut roots = rustls::RootCertStore::empty();
+    roots
+        .add(CertificateDer::from_pem_file(fixture("ca.pem")).unwrap())
+        .unwrap();
+    let provider = std::sync::Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    let config = match identity {
+        Some(name) => builder
+            .with_client_auth_cert(
+                vec![CertificateDer::from_pem_file(fixture(&format!("{}.pem", name))).unwrap()],
+                PrivateKeyDer::from_pem_file(fixture(&format!("{}.key", name))).unwrap(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+
+    let addr = url.trim_start_matches("https://");
+    let tcp = tokio::net::TcpStream::connect(addr).await?;
+    let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut stream = connector.connect(server_name, tcp).await?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test]
+async fn test_admin_tls_requires_a_client_certificate_from_the_ca() {
+    let dir = TempDir::new("admin_tls");
+    let fixture =
+        |name: &str| format!("{}/tests/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let (cert, key, ca) = (
+        fixture("server.pem"),
+        fixture("server.key"),
+        fixture("ca.pem"),
+    );
+    let server = TestServer::start_with_env(
+        dir.path(),
+        &[
+            ("VECTORDB_ADMIN_PORT", "0"),
+            ("VECTORDB_ADMIN_TLS_CERT", &cert),
+            ("VECTORDB_ADMIN_TLS_KEY", &key),
+            ("VECTORDB_ADMIN_CLIENT_CA", &ca),
+        ],
+    );
+    let url = server.admin_url.clone().unwrap();
+    assert!(url.starts_with("https://"), "{}", url);
+
+    let response = admin_tls_get(&url, "/metrics", Some("client"))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    // No certificate, or one from another issuer: refused in the handshake
+    for identity in [None, Some("rogue")] {
+        let refused = admin_tls_get(&url, "/metrics", identity).await;
+        assert!(refused.is_err(), "{:?}: {:?}", identity, refused);
+    }
+
+    // Plain HTTP doesn't get an answer either
+    let (status, _) = server.client().get("/health").await;
+    assert_eq!(status, 200);
+    let plain = reqwest::get(url.replace("https://", "http://") + "/metrics").await;
+    assert!(plain.is_err(), "{:?}", plain);
+
+    server.stop();
+}
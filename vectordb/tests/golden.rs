@@ -0,0 +1,633 @@
+// tests/golden.rs
+//
+// Golden-file tests for on-disk formats.
+//
+// Each fixture in tests/fixtures/ is a small file in a released format
+// version. The tests check both directions:
+//
+//   write(canonical data) == fixture bytes   (we didn't change the layout)
+//   read(fixture)         == canonical data  (we can still read old files)
+//
+// Only the current version is checked in the write direction; older
+// versions stay in the read direction forever.
+//
+// If one of these fails, existing user data is at risk. Don't regenerate
+// the fixture — bump the format version and add a new one instead.
+//
+// The write-ahead log and the segment manifest have fixtures too. Some WAL
+// records carry JSON (collection, alias and template configs) that gains
+// fields as features land, so the WAL fixture puts those last and checks
+// them in the read direction only.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vectordb::engine::collection::Collection;
+use vectordb::models::{
+    CreateAliasRequest, CreateTemplateRequest, DistanceMetric, ExperimentConfig, SparseVector,
+    Vector,
+};
+use vectordb::storage::fs::{DiskStorage, Storage};
+use vectordb::storage::manifest::{self, Manifest, SegmentEntry};
+use vectordb::storage::segment::{self, SegmentHeader, VectorEncoding};
+use vectordb::storage::wal::{self, SyncPolicy, Wal, WalRecord};
+
+const SEGMENT_V1: &[u8] = include_bytes!("fixtures/segment_v1.vec");
+const SEGMENT_V2: &[u8] = include_bytes!("fixtures/segment_v2.vec");
+const SEGMENT_V3: &[u8] = include_bytes!("fixtures/segment_v3.vec");
+const SEGMENT_V4: &[u8] = include_bytes!("fixtures/segment_v4.vec");
+const SEGMENT_V4_INT8: &[u8] = include_bytes!("fixtures/segment_v4_int8.vec");
+const SEGMENT_V5: &[u8] = include_bytes!("fixtures/segment_v5.vec");
+const SEGMENT_V5_INT8: &[u8] = include_bytes!("fixtures/segment_v5_int8.vec");
+const SEGMENT_V5_BINARY: &[u8] = include_bytes!("fixtures/segment_v5_binary.vec");
+const WAL_V1: &[u8] = include_bytes!("fixtures/wal_v1.log");
+const MANIFEST_V1: &[u8] = include_bytes!("fixtures/manifest_v1.json");
+
+/// The vectors stored in every segment fixture. Includes signed zero, the largest
+/// and smallest normal floats, and a subnormal, so any change to how floats
+/// are encoded shows up.
+fn canonical_vectors() -> Vec<Vector> {
+    vec![
+        Vector::new(vec![0.0, -0.0, 1.5, -2.25]),
+        Vector::new(vec![f32::MAX, f32::MIN_POSITIVE, 1e-45, -1.0]),
+        Vector::new(vec![0.1, 100.0, -7.75, 42.0]),
+    ]
+}
+
+/// The canonical section stored in the v4/v5 fixtures: an unknown tag, which
+/// every reader must carry through or skip
+fn canonical_section() -> segment::Section {
+    segment::Section::new(*b"TEST", vec![0xDE, 0xAD, 0xBE, 0xEF])
+}
+
+/// The canonical vectors with metadata, as stored from v3 on. Covers an
+/// empty map, an empty value, several keys (written sorted), and non-ASCII
+/// text.
+fn canonical_with_metadata() -> Vec<Vector> {
+    let metadata: [&[(&str, &str)]; 3] =
+        [&[("title", "Zürich"), ("lang", "de")], &[], &[("tag", "")]];
+    canonical_vectors()
+        .into_iter()
+        .zip(metadata)
+        .map(|(v, pairs)| {
+            let metadata: HashMap<String, String> = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            Vector::with_metadata(v.data, metadata)
+        })
+        .collect()
+}
+
+/// Compare bit patterns so -0.0 vs 0.0 counts as a difference
+fn assert_bits_eq(vectors: &[Vector]) {
+    let expected = canonical_vectors();
+    assert_eq!(vectors.len(), expected.len());
+    for (got, want) in vectors.iter().zip(&expected) {
+        let got: Vec<u32> = got.data.iter().map(|f| f.to_bits()).collect();
+        let want: Vec<u32> = want.data.iter().map(|f| f.to_bits()).collect();
+        assert_eq!(got, want);
+    }
+}
+
+#[test]
+fn test_segment_v5_write_is_byte_exact() {
+    let mut written = Vec::new();
+    segment::write_segment_with_sections_to(
+        &mut written,
+        &canonical_with_metadata(),
+        &[canonical_section()],
+    )
+    .unwrap();
+
+    assert_eq!(
+        written,
+        SEGMENT_V5,
+        "segment writer output changed:\n{}\nexpected:\n{}",
+        segment::hex_dump(&written),
+        segment::hex_dump(SEGMENT_V5)
+    );
+}
+
+#[test]
+fn test_segment_v5_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V5[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 5,
+            count: 3,
+            dimension: 4,
+            encoding: VectorEncoding::F32,
+            metadata_size: 57,
+            sections_size: 16
+        }
+    );
+    assert_eq!(header.file_size(), SEGMENT_V5.len() as u64);
+    segment::verify_checksum(&mut &SEGMENT_V5[..], &header).unwrap();
+
+    let vectors = segment::read_segment_from(&mut &SEGMENT_V5[..]).unwrap();
+    assert_bits_eq(&vectors);
+    for (got, want) in vectors.iter().zip(canonical_with_metadata()) {
+        assert_eq!(got.metadata, want.metadata);
+    }
+}
+
+#[test]
+fn test_segment_v5_detects_corruption() {
+    // Every single-byte change before the footer is caught, except the
+    // version flip 5 -> 4: that names a format without a footer, so the
+    // checksum is never consulted
+    for at in 0..SEGMENT_V5.len() - 8 {
+        let mut bytes = SEGMENT_V5.to_vec();
+        bytes[at] ^= 0x01;
+        if at == 4 {
+            assert_eq!(SegmentHeader::read(&mut &bytes[..]).unwrap().version, 4);
+            continue;
+        }
+        assert!(
+            segment::read_segment_from(&mut bytes.as_slice()).is_err(),
+            "flip at byte {} went unnoticed",
+            at
+        );
+    }
+}
+
+#[test]
+fn test_segment_v4_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V4[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 4,
+            count: 3,
+            dimension: 4,
+            encoding: VectorEncoding::F32,
+            metadata_size: 57,
+            sections_size: 16
+        }
+    );
+    assert_eq!(header.file_size(), SEGMENT_V4.len() as u64);
+
+    let vectors = segment::read_segment_from(&mut &SEGMENT_V4[..]).unwrap();
+    assert_bits_eq(&vectors);
+    for (got, want) in vectors.iter().zip(canonical_with_metadata()) {
+        assert_eq!(got.metadata, want.metadata);
+    }
+}
+
+#[test]
+fn test_segment_v5_int8_write_is_byte_exact() {
+    let mut written = Vec::new();
+    segment::write_segment_encoded_to(
+        &mut written,
+        &canonical_with_metadata(),
+        VectorEncoding::Int8,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        written,
+        SEGMENT_V5_INT8,
+        "int8 segment writer output changed:\n{}\nexpected:\n{}",
+        segment::hex_dump(&written),
+        segment::hex_dump(SEGMENT_V5_INT8)
+    );
+}
+
+#[test]
+fn test_segment_int8_read() {
+    for fixture in [SEGMENT_V4_INT8, SEGMENT_V5_INT8] {
+        let header = SegmentHeader::read(&mut &fixture[..]).unwrap();
+        assert_eq!(header.encoding, VectorEncoding::Int8);
+        assert_eq!(header.data_offset(), 40 + 4 * 8);
+        assert_eq!(header.file_size(), fixture.len() as u64);
+        assert_int8_close(&segment::read_segment_from(&mut &fixture[..]).unwrap());
+    }
+}
+
+/// The vectors stored in the binary fixture: 70 bits, so each takes two
+/// words and the second is mostly padding, with the canonical metadata
+fn canonical_bits() -> Vec<Vector> {
+    let patterns: [fn(usize) -> bool; 3] = [|d| d % 2 == 0, |d| d >= 64, |_| false];
+    canonical_with_metadata()
+        .into_iter()
+        .zip(patterns)
+        .map(|(v, bit)| {
+            let data = (0..70).map(|d| if bit(d) { 1.0 } else { 0.0 }).collect();
+            Vector::with_metadata(data, v.metadata)
+        })
+        .collect()
+}
+
+#[test]
+fn test_segment_v5_binary_write_is_byte_exact() {
+    let mut written = Vec::new();
+    segment::write_segment_encoded_to(&mut written, &canonical_bits(), VectorEncoding::Binary, &[])
+        .unwrap();
+
+    assert_eq!(
+        written,
+        SEGMENT_V5_BINARY,
+        "binary segment writer output changed:\n{}\nexpected:\n{}",
+        segment::hex_dump(&written),
+        segment::hex_dump(SEGMENT_V5_BINARY)
+    );
+}
+
+#[test]
+fn test_segment_v5_binary_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V5_BINARY[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 5,
+            count: 3,
+            dimension: 70,
+            encoding: VectorEncoding::Binary,
+            metadata_size: 57,
+            sections_size: 0
+        }
+    );
+    // Encoding code 2, right after the dimension
+    assert_eq!(SEGMENT_V5_BINARY[20], 2);
+    assert_eq!(header.data_offset(), 40);
+    assert_eq!(header.file_size(), SEGMENT_V5_BINARY.len() as u64);
+    segment::verify_checksum(&mut &SEGMENT_V5_BINARY[..], &header).unwrap();
+
+    // Bit d of a vector is bit d % 64 of its word d / 64, little-endian
+    let words: Vec<u64> = SEGMENT_V5_BINARY[40..88]
+        .chunks(8)
+        .map(|w| u64::from_le_bytes(w.try_into().unwrap()))
+        .collect();
+    assert_eq!(words, [0x5555_5555_5555_5555, 0x15, 0, 0x3F, 0, 0]);
+
+    let vectors = segment::read_segment_from(&mut &SEGMENT_V5_BINARY[..]).unwrap();
+    assert_eq!(vectors.len(), 3);
+    for (got, want) in vectors.iter().zip(canonical_bits()) {
+        assert_eq!(got.data, want.data);
+        assert_eq!(got.metadata, want.metadata);
+    }
+}
+
+/// Lossy, but every component lands within half a step of the original
+fn assert_int8_close(vectors: &[Vector]) {
+    let canonical = canonical_with_metadata();
+    for d in 0..4 {
+        let column: Vec<f32> = canonical.iter().map(|v| v.data[d]).collect();
+        let min = column.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = column.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let half_step = (max - min) / 255.0 / 2.0;
+        for (got, want) in vectors.iter().zip(&canonical) {
+            assert!((got.data[d] - want.data[d]).abs() <= half_step * 1.001);
+        }
+    }
+    for (got, want) in vectors.iter().zip(&canonical) {
+        assert_eq!(got.metadata, want.metadata);
+    }
+}
+
+#[test]
+fn test_segment_v3_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V3[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 3,
+            count: 3,
+            dimension: 4,
+            encoding: VectorEncoding::F32,
+            metadata_size: 57,
+            sections_size: 0
+        }
+    );
+    assert_eq!(header.file_size(), SEGMENT_V3.len() as u64);
+
+    let vectors = segment::read_segment_from(&mut &SEGMENT_V3[..]).unwrap();
+    assert_bits_eq(&vectors);
+    for (got, want) in vectors.iter().zip(canonical_with_metadata()) {
+        assert_eq!(got.metadata, want.metadata);
+    }
+}
+
+#[test]
+fn test_segment_v2_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V2[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 2,
+            count: 3,
+            dimension: 4,
+            encoding: VectorEncoding::F32,
+            metadata_size: 0,
+            sections_size: 0
+        }
+    );
+    assert_eq!(header.file_size(), SEGMENT_V2.len() as u64);
+
+    let vectors = segment::read_segment_from(&mut &SEGMENT_V2[..]).unwrap();
+    assert_bits_eq(&vectors);
+    assert!(vectors.iter().all(|v| v.metadata.is_empty()));
+}
+
+#[test]
+fn test_segment_v1_read() {
+    let header = SegmentHeader::read(&mut &SEGMENT_V1[..]).unwrap();
+    assert_eq!(
+        header,
+        SegmentHeader {
+            version: 1,
+            count: 3,
+            dimension: 4,
+            encoding: VectorEncoding::F32,
+            metadata_size: 0,
+            sections_size: 0
+        }
+    );
+    assert_eq!(header.file_size(), SEGMENT_V1.len() as u64);
+
+    assert_bits_eq(&segment::read_segment_from(&mut &SEGMENT_V1[..]).unwrap());
+}
+
+#[test]
+fn test_truncated_fixtures_are_an_error() {
+    for fixture in [
+        SEGMENT_V1,
+        SEGMENT_V2,
+        SEGMENT_V3,
+        SEGMENT_V4,
+        SEGMENT_V4_INT8,
+        SEGMENT_V5,
+        SEGMENT_V5_INT8,
+        SEGMENT_V5_BINARY,
+    ] {
+        let truncated = &fixture[..fixture.len() - 1];
+        assert!(segment::read_segment_from(&mut &truncated[..]).is_err());
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// WRITE-AHEAD LOG
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// An empty directory of its own for one test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vectordb_golden_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The canonical log's appends whose payloads are binary: single records,
+/// and a group (a rename with its alias update)
+fn canonical_binary_appends() -> Vec<Vec<WalRecord>> {
+    let metadata = HashMap::from([("lang".to_string(), "de".to_string())]);
+    let sparse = SparseVector::new(vec![3, 9], vec![0.5, -1.0]);
+    vec![
+        vec![WalRecord::insert(
+            Some("docs"),
+            "a",
+            &Vector::with_metadata(vec![1.5, -0.0], metadata),
+        )],
+        vec![WalRecord::delete(Some("docs"), "b")],
+        vec![WalRecord::insert(
+            None,
+            "c",
+            &Vector::new(vec![0.25, 2.0]).with_sparse(sparse),
+        )],
+        vec![
+            WalRecord::RenameCollection {
+                from: "docs".into(),
+                to: "papers".into(),
+            },
+            WalRecord::RemoveAlias("old".into()),
+        ],
+        vec![WalRecord::TrashCollection {
+            name: "papers".into(),
+            deleted_at: 1_700_000_000,
+        }],
+        vec![WalRecord::RestoreCollection("papers".into())],
+        vec![WalRecord::PurgeCollection("gone".into())],
+        vec![WalRecord::RemoveTemplate("daily".into())],
+    ]
+}
+
+/// The canonical log's appends whose payloads hold JSON, the last a group
+fn canonical_json_appends() -> Vec<Vec<WalRecord>> {
+    let template: CreateTemplateRequest =
+        serde_json::from_str(r#"{"collection": "papers", "top_k": 5}"#).unwrap();
+    let info = Collection::new("papers", 2, DistanceMetric::Cosine)
+        .unwrap()
+        .info();
+    vec![
+        vec![WalRecord::SetAlias(CreateAliasRequest {
+            name: "live".into(),
+            collection: "papers".into(),
+            experiment: Some(ExperimentConfig {
+                collection: "papers-v2".into(),
+                percent: 10.0,
+            }),
+        })],
+        vec![WalRecord::SetTemplate {
+            name: "daily".into(),
+            config: template,
+        }],
+        vec![
+            WalRecord::CreateCollection(info.clone()),
+            WalRecord::UpdateCollection(info),
+        ],
+    ]
+}
+
+/// Write the canonical log in `dir`: the binary appends, then a flush of
+/// "docs" that turns its two point records into a skip frame, then the
+/// JSON appends. Returns the log and where the JSON appends start.
+fn write_canonical_wal(dir: &Path) -> (Vec<u8>, usize) {
+    let storage: Arc<dyn Storage> = Arc::new(DiskStorage);
+    let path = dir.join(wal::WAL_FILE);
+    let (log, replayed) = Wal::open(storage, &path, SyncPolicy::Never).unwrap();
+    assert!(replayed.is_empty());
+    for records in canonical_binary_appends() {
+        log.append(&records).unwrap();
+    }
+    assert_eq!(log.drop_flushed(2, &["docs"]).unwrap(), 2);
+    let json_at = std::fs::metadata(&path).unwrap().len() as usize;
+    for records in canonical_json_appends() {
+        log.append(&records).unwrap();
+    }
+    (std::fs::read(&path).unwrap(), json_at)
+}
+
+#[test]
+fn test_wal_v1_write_is_byte_exact() {
+    let dir = scratch_dir("wal");
+    let (written, json_at) = write_canonical_wal(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(written.len(), WAL_V1.len());
+    assert_eq!(
+        written[..json_at],
+        WAL_V1[..json_at],
+        "WAL writer output changed:\n{}\nexpected:\n{}",
+        segment::hex_dump(&written[..json_at]),
+        segment::hex_dump(&WAL_V1[..json_at])
+    );
+}
+
+#[test]
+fn test_wal_v1_read() {
+    // CRC, length, then the payload: a skip frame for the two dropped records
+    assert_eq!(&WAL_V1[4..8], &9u32.to_le_bytes());
+    assert_eq!(WAL_V1[8], 10);
+    assert_eq!(&WAL_V1[9..17], &2u64.to_le_bytes());
+
+    let replay = wal::decode(WAL_V1);
+    assert_eq!(replay.valid_len, WAL_V1.len() as u64);
+    assert_eq!(replay.discarded, 0);
+    assert_eq!(replay.sequences, 13);
+    let seqs: Vec<u64> = replay.records.iter().map(|(seq, _)| *seq).collect();
+    assert_eq!(seqs, (3..=13).collect::<Vec<_>>());
+
+    let records: Vec<&WalRecord> = replay.records.iter().map(|(_, r)| r).collect();
+    match records[0] {
+        WalRecord::Insert {
+            collection: None,
+            id,
+            vector,
+        } => {
+            assert_eq!(id, "c");
+            assert_eq!(vector.data, [0.25, 2.0]);
+            assert!(vector.metadata.is_empty());
+            let sparse = vector.sparse.as_ref().unwrap();
+            assert_eq!(
+                (&sparse.indices[..], &sparse.values[..]),
+                (&[3, 9][..], &[0.5, -1.0][..])
+            );
+        }
+        other => panic!("expected the flat store's insert, got {:?}", other),
+    }
+    assert!(
+        matches!(records[1], WalRecord::RenameCollection { from, to } if from == "docs" && to == "papers")
+    );
+    assert!(matches!(records[2], WalRecord::RemoveAlias(name) if name == "old"));
+    assert!(matches!(
+        records[3],
+        WalRecord::TrashCollection { name, deleted_at: 1_700_000_000 } if name == "papers"
+    ));
+    assert!(matches!(records[4], WalRecord::RestoreCollection(name) if name == "papers"));
+    assert!(matches!(records[5], WalRecord::PurgeCollection(name) if name == "gone"));
+    assert!(matches!(records[6], WalRecord::RemoveTemplate(name) if name == "daily"));
+    match records[7] {
+        WalRecord::SetAlias(alias) => {
+            assert_eq!(
+                (alias.name.as_str(), alias.collection.as_str()),
+                ("live", "papers")
+            );
+            let experiment = alias.experiment.as_ref().unwrap();
+            assert_eq!(
+                (experiment.collection.as_str(), experiment.percent),
+                ("papers-v2", 10.0)
+            );
+        }
+        other => panic!("expected an alias, got {:?}", other),
+    }
+    match records[8] {
+        WalRecord::SetTemplate { name, config } => {
+            assert_eq!(name, "daily");
+            assert_eq!((config.collection.as_str(), config.top_k), ("papers", 5));
+            assert!(config.filter.is_none());
+        }
+        other => panic!("expected a template, got {:?}", other),
+    }
+    for record in &records[9..] {
+        let (WalRecord::CreateCollection(info) | WalRecord::UpdateCollection(info)) = record else {
+            panic!("expected a collection config, got {:?}", record);
+        };
+        assert_eq!((info.name.as_str(), info.dimension), ("papers", 2));
+        assert_eq!(info.distance, DistanceMetric::Cosine);
+    }
+    assert!(matches!(records[9], WalRecord::CreateCollection(_)));
+    assert!(matches!(records[10], WalRecord::UpdateCollection(_)));
+}
+
+#[test]
+fn test_wal_v1_torn_group_is_dropped_whole() {
+    // The last two records are a group; tearing its last byte loses both
+    let torn = &WAL_V1[..WAL_V1.len() - 1];
+    let replay = wal::decode(torn);
+    assert_eq!(replay.sequences, 11);
+    assert_eq!(replay.records.last().unwrap().0, 11);
+    assert!(matches!(
+        replay.records.last().unwrap().1,
+        WalRecord::SetTemplate { .. }
+    ));
+    assert_eq!(replay.valid_len + replay.discarded, torn.len() as u64);
+
+    // A bad checksum anywhere in the log stops replay there
+    let mut corrupt = WAL_V1.to_vec();
+    corrupt[20] ^= 0x01;
+    assert_eq!(wal::decode(&corrupt).valid_len, 17);
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// MANIFEST
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// The manifest in the fixture: a flushed segment with its ID range, and
+/// an empty one (every write in it was a delete of nothing) without
+fn canonical_manifest() -> Manifest {
+    let mut manifest = Manifest::default();
+    manifest.add(
+        SegmentEntry {
+            file: "docs-000003.vec".into(),
+            generation: 3,
+            collection: "docs".into(),
+            points: 2,
+            deleted: 1,
+            min_id: None,
+            max_id: None,
+        }
+        .with_ids(["b", "a", "zürich"]),
+    );
+    manifest.add(SegmentEntry {
+        file: "news-000007.vec".into(),
+        generation: 7,
+        collection: "news".into(),
+        points: 0,
+        deleted: 0,
+        min_id: None,
+        max_id: None,
+    });
+    manifest
+}
+
+#[test]
+fn test_manifest_v1_write_is_byte_exact() {
+    let dir = scratch_dir("manifest");
+    canonical_manifest().save(&DiskStorage, &dir).unwrap();
+    let written = std::fs::read(dir.join(manifest::MANIFEST_FILE)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        std::str::from_utf8(MANIFEST_V1).unwrap()
+    );
+}
+
+#[test]
+fn test_manifest_v1_read() {
+    let dir = scratch_dir("manifest_read");
+    std::fs::write(dir.join(manifest::MANIFEST_FILE), MANIFEST_V1).unwrap();
+    let loaded = Manifest::load(&DiskStorage, &dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let manifest = loaded.unwrap();
+    assert_eq!(manifest, canonical_manifest());
+    assert_eq!(manifest.next_generation, 8);
+    assert_eq!(manifest.segments[0].min_id.as_deref(), Some("a"));
+    assert_eq!(manifest.segments[0].max_id.as_deref(), Some("zürich"));
+    let candidates: Vec<u64> = manifest.candidates("m").map(|s| s.generation).collect();
+    assert_eq!(candidates, [3]);
+}
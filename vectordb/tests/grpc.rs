@@ -0,0 +1,185 @@
+// tests/grpc.rs
+//
+// End-to-end tests for the gRPC API, against a spawned server process.
+//
+// Run with: cargo test --features grpc --test grpc
+
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use common::{TempDir, TestServer};
+use std::collections::HashMap;
+use vectordb::grpc::proto::{self, vector_db_client::VectorDbClient};
+
+fn point(id: &str, vector: Vec<f32>) -> proto::Point {
+    proto::Point {
+        id: id.into(),
+        vector,
+        metadata: HashMap::from([("src".to_string(), "grpc".to_string())]),
+    }
+}
+
+#[tokio::test]
+async fn test_grpc_shares_state_with_http() {
+    let dir = TempDir::new("grpc");
+    let server = TestServer::start(dir.path());
+    let http = server.client();
+    let mut grpc = VectorDbClient::connect(server.grpc_url.clone().unwrap())
+        .await
+        .unwrap();
+
+    http.create_collection("docs", 2).await;
+    let inserted = grpc
+        .insert(proto::InsertRequest {
+            collection: "docs".into(),
+            points: vec![point("a", vec![1.0, 0.0]), point("b", vec![0.0, 1.0])],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(inserted.count, 2);
+    assert_eq!(inserted.results[0].status(), proto::PointStatus::Inserted);
+
+    // Written over gRPC, visible over HTTP, and the other way round
+    assert_eq!(http.search("docs", &[1.0, 0.1], 1).await, vec!["a"]);
+    http.upsert("docs", &[("c", vec![0.6, 0.8])]).await;
+    let hits = grpc
+        .search(proto::SearchRequest {
+            collection: "docs".into(),
+            vector: vec![0.0, 1.0],
+            top_k: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .hits;
+    let ids: Vec<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, ["b", "c"]);
+    assert!(hits[0].vector.is_empty() && hits[0].metadata.is_empty());
+    let hits = grpc
+        .search(proto::SearchRequest {
+            collection: "docs".into(),
+            vector: vec![0.0, 1.0],
+            top_k: 1,
+            include_vector: true,
+            include_metadata: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .hits;
+    assert_eq!(
+        (&hits[0].vector[..], &hits[0].metadata["src"][..]),
+        (&[0.0, 1.0][..], "grpc")
+    );
+
+    let got = grpc
+        .get(proto::GetRequest {
+            collection: "docs".into(),
+            id: "a".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let stored = got.point.unwrap();
+    assert_eq!((stored.vector, got.version), (vec![1.0, 0.0], 1));
+    assert_eq!(stored.metadata["src"], "grpc");
+
+    grpc.delete(proto::DeleteRequest {
+        collection: "docs".into(),
+        id: "a".into(),
+    })
+    .await
+    .unwrap();
+    let missing = grpc
+        .get(proto::GetRequest {
+            collection: "docs".into(),
+            id: "a".into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::NotFound);
+
+    let err = grpc
+        .insert(proto::InsertRequest {
+            collection: "nope".into(),
+            points: vec![point("x", vec![1.0, 0.0])],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::NotFound);
+
+    // gRPC writes go through the WAL like HTTP ones
+    let server = server.crash();
+    let http = server.client();
+    let (_, body) = http.get("/api/collections/docs").await;
+    assert_eq!(body["count"], 2);
+
+    server.stop();
+}
+
+#[tokio::test]
+async fn test_grpc_calls_need_a_key_allowing_their_collection() {
+    let dir = TempDir::new("grpc_keys");
+    let keys_dir = TempDir::new("grpc_keys_file");
+    let keys = keys_dir.path().join("keys.json");
+    std::fs::write(
+        &keys,
+        r#"{ "keys": [{ "key": "tenant", "collections": ["docs-*"] }, { "key": "ops" }] }"#,
+    )
+    .unwrap();
+    let server = TestServer::start_with_env(
+        dir.path(),
+        &[
+            ("VECTORDB_API_KEYS", keys.to_str().unwrap()),
+            // The open channel below holds up shutdown until the drain ends
+            ("VECTORDB_DRAIN_TIMEOUT_SECS", "1"),
+        ],
+    );
+    let ops = server.client_with_key("ops");
+    ops.create_collection("docs-a", 2).await;
+    ops.create_collection("hr", 2).await;
+    let mut grpc = VectorDbClient::connect(server.grpc_url.clone().unwrap())
+        .await
+        .unwrap();
+
+    let get = |collection: &str, key: Option<&str>| {
+        let mut request = tonic::Request::new(proto::GetRequest {
+            collection: collection.into(),
+            id: "a".into(),
+        });
+        if let Some(key) = key {
+            request
+                .metadata_mut()
+                .insert("x-api-key", key.parse().unwrap());
+        }
+        request
+    };
+    let code = |result: Result<tonic::Response<proto::GetResponse>, tonic::Status>| {
+        result.map(|_| tonic::Code::Ok).unwrap_or_else(|e| e.code())
+    };
+    assert_eq!(
+        code(grpc.get(get("docs-a", None)).await),
+        tonic::Code::Unauthenticated
+    );
+    assert_eq!(
+        code(grpc.get(get("docs-a", Some("nobody"))).await),
+        tonic::Code::Unauthenticated
+    );
+    assert_eq!(
+        code(grpc.get(get("hr", Some("tenant"))).await),
+        tonic::Code::PermissionDenied
+    );
+    // Allowed through to the lookup, which finds no point "a"
+    assert_eq!(
+        code(grpc.get(get("docs-a", Some("tenant"))).await),
+        tonic::Code::NotFound
+    );
+
+    server.stop();
+}
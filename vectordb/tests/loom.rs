@@ -0,0 +1,156 @@
+// tests/loom.rs
+//
+// Exhaustive concurrency tests with loom.
+//
+// loom replaces the primitives re-exported by src/sync.rs with instrumented
+// versions and runs each model under every possible thread interleaving, so
+// a race that would show up once in a million runs fails deterministically.
+//
+// Run with:
+//   RUSTFLAGS="--cfg vectordb_loom" cargo test --test loom --release
+//
+// Models must take their locks and atomics from vectordb::sync; a std
+// primitive inside the code under test hides its interleavings from loom.
+//
+// loom checks orderings, not memory safety. The crate's unsafe code is
+// elsewhere and covered by Miri where Miri can run it:
+//
+//   hugepages.rs  mmap/munmap, slices over the mapping, Send/Sync for the
+//                 Buffer; under Miri the mapping comes from the allocator
+//                 (cargo +nightly miri test --lib hugepages::)
+//   hnsw.rs       prefetch intrinsics, hints that never dereference
+//   fds.rs        getrlimit/setrlimit on a stack struct
+//   profiling.rs  jemalloc mallctl reads and writes (feature "pprof")
+
+#![cfg(vectordb_loom)]
+
+use loom::sync::Arc;
+use loom::thread;
+use std::time::{Duration, Instant};
+use vectordb::engine::cache::{CacheConfig, Fingerprint, ResultCache};
+use vectordb::jobs::{JobRegistry, JobStatus};
+use vectordb::models::SearchResult;
+use vectordb::storage::memtable::Memtable;
+
+#[test]
+fn loom_job_progress_with_concurrent_workers() {
+    loom::model(|| {
+        let mut registry = JobRegistry::default();
+        let job = registry.start("loom", 4);
+
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let job = job.clone();
+                thread::spawn(move || {
+                    job.advance(1);
+                    let p = job.progress();
+                    assert!((0.0..=1.0).contains(&p), "progress out of range: {}", p);
+                    job.advance(1);
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(job.progress(), 1.0);
+
+        job.complete(serde_json::json!({ "done": true }));
+        assert_eq!(job.status(), JobStatus::Completed);
+        assert_eq!(job.snapshot()["processed"], 4);
+    });
+}
+
+#[test]
+fn loom_completion_races_with_status_reads() {
+    loom::model(|| {
+        let mut registry = JobRegistry::default();
+        let job = registry.start("loom", 1);
+
+        let finisher = {
+            let job = job.clone();
+            thread::spawn(move || {
+                job.advance(1);
+                job.complete(serde_json::Value::Null);
+            })
+        };
+
+        // A reader never sees a completed job with an ETA or < 100% progress
+        if job.status() == JobStatus::Completed {
+            assert_eq!(job.progress(), 1.0);
+            assert!(job.eta().is_none());
+        }
+
+        finisher.join().unwrap();
+        assert_eq!(job.status(), JobStatus::Completed);
+    });
+}
+
+#[test]
+fn loom_memtable_swap_keeps_every_write_once() {
+    loom::model(|| {
+        let memtable = Arc::new(Memtable::default());
+
+        let writers: Vec<_> = ["a", "b"]
+            .into_iter()
+            .map(|id| {
+                let memtable = memtable.clone();
+                thread::spawn(move || memtable.put(id, 10))
+            })
+            .collect();
+
+        // The flusher seals whatever has arrived so far
+        let sealed = memtable.take();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Each write is either in the sealed batch or still live, never both
+        let live = memtable.take();
+        for id in ["a", "b"] {
+            let in_sealed = sealed.upserts().any(|u| u == id);
+            let in_live = live.upserts().any(|u| u == id);
+            assert!(
+                in_sealed != in_live,
+                "{} sealed={} live={}",
+                id,
+                in_sealed,
+                in_live
+            );
+        }
+        assert_eq!(sealed.len() + live.len(), 2);
+        assert_eq!(sealed.bytes(), 11 * sealed.len());
+        assert_eq!(live.bytes(), 11 * live.len());
+        assert!(memtable.is_empty());
+    });
+}
+
+#[test]
+fn loom_result_cache_stale_put_never_outlives_invalidation() {
+    loom::model(|| {
+        let cache = Arc::new(ResultCache::new(CacheConfig {
+            capacity: 4,
+            ttl: Duration::from_secs(60),
+        }));
+        let now = Instant::now();
+        let key = |epoch| Fingerprint::new("docs", epoch, &[1.0], "k=1".into());
+        let hits = |id: &str| vec![SearchResult::new(id, 1.0)];
+
+        // A search that read epoch 1 before a write, finishing late...
+        let stale = {
+            let cache = cache.clone();
+            thread::spawn(move || cache.put(key(1), hits("old"), now))
+        };
+        // ...racing the first search after the write, at epoch 2
+        let fresh = {
+            let cache = cache.clone();
+            thread::spawn(move || cache.put(key(2), hits("new"), now))
+        };
+        stale.join().unwrap();
+        fresh.join().unwrap();
+
+        assert!(cache.get(&key(1), now).is_none());
+        assert_eq!(cache.get(&key(2), now).unwrap()[0].id, "new");
+        assert_eq!(cache.len(), 1);
+    });
+}
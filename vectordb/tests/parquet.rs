@@ -0,0 +1,69 @@
+// tests/parquet.rs
+//
+// End-to-end tests for Parquet export and import, against a spawned server
+// process.
+//
+// Run with: cargo test --features parquet --test parquet
+
+#![cfg(feature = "parquet")]
+
+mod common;
+
+use common::{TempDir, TestServer};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_parquet_export_imports_into_another_collection() {
+    let dir = TempDir::new("parquet");
+    let server = TestServer::start(dir.path());
+    let client = server.client();
+
+    client.create_collection("src", 2).await;
+    let (status, body) = client
+        .post(
+            "/api/collections/src/points",
+            json!({ "points": [
+                { "id": "a", "vector": [1.0, 0.0], "metadata": { "title": "first" } },
+                { "id": "b", "vector": [0.0, 1.0] },
+            ]}),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, file) = client
+        .get_bytes("/api/collections/src/export?format=parquet")
+        .await;
+    assert_eq!(status, 200);
+    assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+
+    client.create_collection("dst", 2).await;
+    let (status, report) = client
+        .post_bytes(
+            "/api/collections/dst/import?format=parquet&validate_only=true",
+            file.clone(),
+        )
+        .await;
+    assert_eq!(status, 200, "{}", report);
+    assert_eq!(
+        (report["rows"].as_u64(), report["written"].as_u64()),
+        (Some(2), Some(0))
+    );
+
+    let (status, report) = client
+        .post_bytes("/api/collections/dst/import?format=parquet", file)
+        .await;
+    assert_eq!(status, 200, "{}", report);
+    assert_eq!(report["written"], 2);
+    let (_, point) = client.get("/api/collections/dst/points/a").await;
+    assert_eq!(point["vector"]["metadata"]["title"], "first");
+    assert_eq!(client.search("dst", &[0.1, 1.0], 1).await, vec!["b"]);
+
+    // Not Parquet at all: rejected before anything is checked
+    let (status, _) = client
+        .post_bytes(
+            "/api/collections/dst/import?format=parquet",
+            "{\"id\":\"c\",\"vector\":[1,1]}\n",
+        )
+        .await;
+    assert_eq!(status, 400);
+}